@@ -0,0 +1,215 @@
+// Offline benchmarks for the scanner, the dependency map plumbing, license normalization and the writer.
+// Run with `cargo bench --features bench`; DEPSFETCH_BENCH_BREADTH / DEPSFETCH_BENCH_DEPTH size the synthetic tree.
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use depsfetch::{
+  deps::{detect::DetectedManifests, go_deps, js_deps::JsParser, manifests::ManifestFilter, patterns::PatternSet},
+  diagnostics::Diagnostics,
+  license_data::LicenseData,
+  report::{ReportWriter, ResolvedRow, Section, WriterMessage},
+  types::{DepScope, DepsEntry, Ecosystem, ReportRow},
+};
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap},
+  env, fs,
+  hint::black_box,
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+use tokio::runtime::Runtime;
+
+const MERGE_ENTRIES: usize = 100_000;
+const WRITER_ROWS: usize = 50_000;
+
+fn env_or(name: &str, default: usize) -> usize {
+  env::var(name)
+    .ok()
+    .and_then(|value| value.parse().ok())
+    .unwrap_or(default)
+}
+
+fn scratch_dir(name: &str) -> PathBuf {
+  let dir = env::temp_dir().join(format!("depsfetch-bench-{}-{}", name, std::process::id()));
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).expect("Failed to create bench directory");
+  dir
+}
+
+// `breadth` directories per level, `depth` levels, a package.json in every directory plus a few files to walk past
+fn synthetic_tree(root: &Path, breadth: usize, depth: usize) {
+  fn fill(dir: &Path, breadth: usize, depth: usize, counter: &mut usize) {
+    *counter += 1;
+    let package_json = format!(
+      r#"{{"name":"pkg-{0}","dependencies":{{"dep-{0}":"^1.0.0","shared":"^2.0.0"}},"peerDependencies":{{"peer-{0}":"3.0.0"}}}}"#,
+      counter
+    );
+    fs::write(dir.join("package.json"), package_json).expect("Failed to write package.json");
+    fs::write(dir.join("index.js"), "module.exports = {}\n").expect("Failed to write index.js");
+    if depth == 0 {
+      return;
+    }
+    for idx in 0..breadth {
+      let child = dir.join(format!("d{}", idx));
+      fs::create_dir_all(&child).expect("Failed to create directory");
+      fill(&child, breadth, depth - 1, counter);
+    }
+  }
+
+  fill(root, breadth, depth, &mut 0);
+}
+
+fn bench_scan(c: &mut Criterion) {
+  let breadth = env_or("DEPSFETCH_BENCH_BREADTH", 4);
+  let depth = env_or("DEPSFETCH_BENCH_DEPTH", 4);
+  let root = scratch_dir("scan");
+  synthetic_tree(&root, breadth, depth);
+
+  let runtime = Runtime::new().expect("Failed to start runtime");
+  let directory = root.display().to_string();
+  let mut group = c.benchmark_group("scan");
+  group.sample_size(10);
+  group.bench_function(format!("package.json breadth {} depth {}", breadth, depth), |b| {
+    b.iter(|| {
+      let exclude = Arc::new(PatternSet::compile("--exclude", &None).expect("Failed to compile patterns"));
+      let skip = Arc::new(PatternSet::compile("--skip", &None).expect("Failed to compile patterns"));
+      let manifests = Arc::new(ManifestFilter::new(&BTreeMap::new(), &None).expect("Failed to build filter"));
+      let parser =
+        JsParser::new(&directory, exclude, skip, manifests, Arc::default()).expect("Failed to create parser");
+      let mut detected = DetectedManifests::default();
+      black_box(runtime.block_on(parser.parse(&mut detected)).expect("Scan failed"))
+    })
+  });
+  group.finish();
+
+  let _ = fs::remove_dir_all(&root);
+}
+
+// Go requires where every tenth module path also shows up with different case, the worst case for the merge
+fn bench_merge(c: &mut Criterion) {
+  let entries = (0..MERGE_ENTRIES)
+    .map(|idx| {
+      let name = match idx % 10 {
+        0 => format!("github.com/Org{}/Module", idx + 1),
+        _ => format!("github.com/org{}/module", idx),
+      };
+      let entry = DepsEntry {
+        name: name.clone(),
+        version: format!("v1.{}.0", idx % 50),
+        scope: DepScope::Runtime,
+        sources: BTreeSet::from([format!("mod{}/go.mod", idx % 100)]),
+      };
+      (name, entry)
+    })
+    .collect::<HashMap<_, _>>();
+
+  let diagnostics = Diagnostics::default();
+  c.bench_function(&format!("merge {} entries", MERGE_ENTRIES), |b| {
+    b.iter_batched(
+      || entries.clone(),
+      |mut dependencies| {
+        go_deps::merge_case_duplicates(&mut dependencies, &diagnostics);
+        black_box(dependencies)
+      },
+      BatchSize::LargeInput,
+    )
+  });
+}
+
+// Every id and alias of the embedded data as written, lower-cased and inside an expression
+fn bench_normalize(c: &mut Criterion) {
+  let data = LicenseData::load(None).expect("Failed to load license data");
+  let raw: serde_json::Value = serde_json::from_str(include_str!("../data/license_data.json")).expect("Bad data file");
+  let mut corpus = raw["licenses"]
+    .as_array()
+    .into_iter()
+    .flatten()
+    .filter_map(|id| id.as_str().map(str::to_owned))
+    .collect::<Vec<_>>();
+  corpus.extend(
+    raw["aliases"]
+      .as_object()
+      .into_iter()
+      .flat_map(|aliases| aliases.keys().cloned()),
+  );
+  let lowered = corpus
+    .iter()
+    .map(|id| format!(" {} ", id.to_lowercase()))
+    .collect::<Vec<_>>();
+  let expressions = corpus
+    .windows(2)
+    .map(|pair| format!("({} OR {}) AND MIT", pair[0], pair[1]))
+    .collect::<Vec<_>>();
+
+  c.bench_function(&format!("normalize {} licenses", corpus.len() * 3), |b| {
+    b.iter(|| {
+      for license in corpus.iter().chain(&lowered) {
+        black_box(data.normalize(license));
+      }
+      for expression in &expressions {
+        black_box(data.category(Some(expression)));
+      }
+    })
+  });
+}
+
+fn bench_writer(c: &mut Criterion) {
+  let dir = scratch_dir("writer");
+  let path = dir.join("bench_report.xlsx").display().to_string();
+  let rows = (0..WRITER_ROWS)
+    .map(|idx| ReportRow {
+      ecosystem: Ecosystem::Npm,
+      name: format!("package-{}", idx),
+      version: format!("1.{}.{}", idx % 100, idx % 7),
+      scope: DepScope::Runtime,
+      homepage: format!("https://www.npmjs.com/package/package-{}", idx),
+      license: Some(["MIT", "Apache-2.0", "GPL-3.0-only", "BSD-3-Clause"][idx % 4].to_owned()),
+      license_confidence: None,
+      license_url: Some(format!("https://github.com/example/package-{}/blob/HEAD/LICENSE", idx)),
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    })
+    .collect::<Vec<_>>();
+
+  let runtime = Runtime::new().expect("Failed to start runtime");
+  let mut group = c.benchmark_group("writer");
+  group.sample_size(10);
+  group.bench_function(format!("write {} rows", WRITER_ROWS), |b| {
+    b.iter_batched(
+      || rows.clone(),
+      |rows| {
+        runtime.block_on(async {
+          let (sender, handle) = ReportWriter::new(&path).expect("Failed to create writer").spawn();
+          sender
+            .send(WriterMessage::Section(Section::Dependencies("Web")))
+            .expect("Writer hung up");
+          for row in rows {
+            sender
+              .send(WriterMessage::Row(Box::new(ResolvedRow { sheet: "Web", row })))
+              .expect("Writer hung up");
+          }
+          sender
+            .send(WriterMessage::Section(Section::Summary("Summary")))
+            .expect("Writer hung up");
+          sender.send(WriterMessage::Finish).expect("Writer hung up");
+          black_box(handle.await.expect("Writer panicked").expect("Writer failed"))
+        })
+      },
+      BatchSize::LargeInput,
+    )
+  });
+  group.finish();
+
+  let _ = fs::remove_dir_all(&dir);
+}
+
+criterion_group!(benches, bench_scan, bench_merge, bench_normalize, bench_writer);
+criterion_main!(benches);
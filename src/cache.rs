@@ -0,0 +1,165 @@
+use crate::{hermetic, license_data::LicenseData};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::BTreeMap,
+  path::PathBuf,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+  },
+  time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const CACHE_FILE: &str = "cache.json";
+// Default branches hardly ever change, and a stale one is caught by the probe that 404s on it
+const BRANCH_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+#[derive(Default, Serialize, Deserialize)]
+struct CacheFile {
+  // host/owner/repo -> default branch
+  #[serde(default)]
+  branches: BTreeMap<String, BranchEntry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct BranchEntry {
+  branch: String,
+  // Unix seconds of the lookup
+  checked: u64,
+}
+
+// Repository default branches shared by every package of a run and kept between runs in the user data directory.
+// Without a data directory the cache only lives for the run.
+pub struct BranchCache {
+  path: Option<PathBuf>,
+  content: Mutex<CacheFile>,
+  changed: AtomicBool,
+}
+
+impl BranchCache {
+  // An unreadable cache file counts as empty, it's rewritten on save
+  pub fn load() -> Self {
+    let path = LicenseData::default_dir().map(|dir| dir.join(CACHE_FILE));
+    let content = path
+      .as_ref()
+      .and_then(|path| hermetic::read(path).ok())
+      .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+      .unwrap_or_default();
+
+    Self {
+      path,
+      content: Mutex::new(content),
+      changed: AtomicBool::new(false),
+    }
+  }
+
+  // `host/owner/repo` of a repository URL, None for anything else
+  pub fn key(repo_url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(repo_url).ok()?;
+    let mut segments = url.path_segments()?.filter(|segment| !segment.is_empty());
+    let owner = segments.next()?;
+    let repo = segments.next()?.trim_end_matches(".git");
+    Some(format!("{}/{}/{}", url.host_str()?, owner, repo).to_lowercase())
+  }
+
+  // The cached branch while it is younger than the TTL
+  pub fn get(&self, repo_url: &str) -> Option<String> {
+    let key = Self::key(repo_url)?;
+    let content = self.content.lock().expect("Branch cache lock poisoned");
+    let entry = content.branches.get(&key)?;
+    (now().saturating_sub(entry.checked) < BRANCH_TTL.as_secs()).then(|| entry.branch.clone())
+  }
+
+  pub fn insert(&self, repo_url: &str, branch: &str) {
+    let Some(key) = Self::key(repo_url) else {
+      return;
+    };
+    let entry = BranchEntry {
+      branch: branch.to_owned(),
+      checked: now(),
+    };
+    self
+      .content
+      .lock()
+      .expect("Branch cache lock poisoned")
+      .branches
+      .insert(key, entry);
+    self.changed.store(true, Ordering::Relaxed);
+  }
+
+  pub fn invalidate(&self, repo_url: &str) {
+    let Some(key) = Self::key(repo_url) else {
+      return;
+    };
+    let removed = self
+      .content
+      .lock()
+      .expect("Branch cache lock poisoned")
+      .branches
+      .remove(&key);
+    if removed.is_some() {
+      self.changed.store(true, Ordering::Relaxed);
+    }
+  }
+
+  // Writes the file back when the run changed anything, dropping expired entries on the way
+  pub fn save(&self) -> Result<()> {
+    let Some(path) = self.path.as_ref().filter(|_| self.changed.load(Ordering::Relaxed)) else {
+      return Ok(());
+    };
+    let mut content = self.content.lock().expect("Branch cache lock poisoned");
+    let now = now();
+    content
+      .branches
+      .retain(|_, entry| now.saturating_sub(entry.checked) < BRANCH_TTL.as_secs());
+
+    if let Some(dir) = path.parent() {
+      hermetic::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let json = serde_json::to_vec_pretty(&*content).context("Failed to serialize the cache")?;
+    hermetic::write(path, json).with_context(|| format!("Failed to write {}", path.display()))
+  }
+}
+
+// Nothing is read and nothing saved, the cache lives for the run only
+impl Default for BranchCache {
+  fn default() -> Self {
+    Self {
+      path: None,
+      content: Mutex::default(),
+      changed: AtomicBool::new(false),
+    }
+  }
+}
+
+// `cache show-branches`: every cached default branch with its age
+pub fn show_branches() -> Result<()> {
+  let cache = BranchCache::load();
+  let content = cache.content.lock().expect("Branch cache lock poisoned");
+  if content.branches.is_empty() {
+    match &cache.path {
+      Some(path) => println!("No default branches cached in {}", path.display()),
+      None => println!("No user data directory, nothing is cached between runs"),
+    }
+  }
+  let now = now();
+  for (key, entry) in &content.branches {
+    let age = now.saturating_sub(entry.checked);
+    println!(
+      "{:<60} {:<20} {}d old{}",
+      key,
+      entry.branch,
+      age / (24 * 60 * 60),
+      if age >= BRANCH_TTL.as_secs() { " (expired)" } else { "" }
+    );
+  }
+  Ok(())
+}
+
+fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|elapsed| elapsed.as_secs())
+    .unwrap_or_default()
+}
@@ -0,0 +1,349 @@
+use crate::deps::patterns::ExclusionRule;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs};
+
+const COLUMNS: &[&str] = &["funding", "provenance", "confidence"];
+// Loaded from the scanned directory when no --config is given, `depsfetch init` writes one
+pub const CONFIG_FILE: &str = ".collect_lic_info.toml";
+
+// Settings a config file (or one of its profiles) may set; None means "not set at this level"
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct ConfigLayer {
+  pub exclude: Option<Vec<PatternEntry>>,
+  pub skip: Option<Vec<PatternEntry>>,
+  pub skip_scope: Option<Vec<PatternEntry>>,
+  pub skip_prefix: Option<Vec<PatternEntry>>,
+  pub debug_http: Option<String>,
+  pub canonical_map: Option<String>,
+  pub by_license: Option<bool>,
+  pub by_license_collapse: Option<usize>,
+  pub split_by_project: Option<String>,
+  pub no_combined: Option<bool>,
+  pub constant_memory: Option<bool>,
+  pub no_hyperlink_format: Option<bool>,
+  pub link_limit: Option<usize>,
+  pub split_on_link_limit: Option<bool>,
+  pub verbose: Option<bool>,
+  pub filter: Option<Vec<String>>,
+  pub sheet: Option<Vec<String>>,
+  pub js_sheet_name: Option<String>,
+  pub go_sheet_name: Option<String>,
+  pub rust_sheet_name: Option<String>,
+  pub python_sheet_name: Option<String>,
+  pub java_sheet_name: Option<String>,
+  pub ruby_sheet_name: Option<String>,
+  pub php_sheet_name: Option<String>,
+  pub dotnet_sheet_name: Option<String>,
+  pub dart_sheet_name: Option<String>,
+  pub swift_sheet_name: Option<String>,
+  pub cpp_sheet_name: Option<String>,
+  pub deno_sheet_name: Option<String>,
+  pub helm_sheet_name: Option<String>,
+  pub terraform_sheet_name: Option<String>,
+  pub github_actions: Option<bool>,
+  pub graph: Option<String>,
+  pub use_go_tool: Option<bool>,
+  pub pipenv_dev: Option<bool>,
+  pub transitive: Option<bool>,
+  pub maven_test: Option<bool>,
+  pub funding: Option<bool>,
+  pub provenance: Option<bool>,
+  pub repo_mismatch: Option<bool>,
+  pub verify_installed: Option<bool>,
+  pub nested_notices: Option<bool>,
+  pub nested_notices_min_size: Option<u64>,
+  pub nested_notices_package: Option<Vec<String>>,
+  pub trust_baseline: Option<String>,
+  pub max_baseline_age: Option<u64>,
+  pub revalidate_sample: Option<f64>,
+  pub revalidate_seed: Option<u64>,
+  pub fail_on_repo_mismatch: Option<bool>,
+  pub verify_links: Option<bool>,
+  pub replace_dead_links: Option<bool>,
+  pub fail_on_dead_links: Option<bool>,
+  pub confidence: Option<bool>,
+  pub license_flags: Option<bool>,
+  pub review_columns: Option<bool>,
+  pub review_file: Option<String>,
+  pub require_osi_approved: Option<bool>,
+  pub require_fsf_libre: Option<bool>,
+  pub min_confidence: Option<String>,
+  pub dedup: Option<String>,
+  pub collapse_by_repo: Option<bool>,
+  pub detect_twins: Option<bool>,
+  pub count_twins: Option<bool>,
+  // `primary=variant` entries, a profile's list replaces the base one
+  pub twin_pairs: Option<Vec<String>>,
+  pub npm_registry: Option<String>,
+  pub npm_auth: Option<String>,
+  pub scope_registry: Option<Vec<String>>,
+  pub ca_bundle: Option<String>,
+  pub pin: Option<Vec<String>>,
+  pub target_platform: Option<String>,
+  pub strict_platform: Option<bool>,
+  pub strict_versions: Option<bool>,
+  pub prerelease: Option<String>,
+  pub provenance_check: Option<bool>,
+  pub sizes: Option<bool>,
+  pub sign_key: Option<String>,
+  pub bundle: Option<String>,
+  pub notice: Option<String>,
+  pub exclude_private: Option<bool>,
+  // The key stays on the command line or in the environment, a config is usually committed
+  pub redact_internal: Option<bool>,
+  pub internal_namespace: Option<Vec<String>>,
+  pub redaction_map: Option<String>,
+  pub format: Option<String>,
+  pub license_data: Option<String>,
+  pub exclude_licenses: Option<Vec<String>>,
+  pub import_sbom: Option<String>,
+  pub post_process: Option<String>,
+  pub re_resolve_imported: Option<bool>,
+  pub csv: Option<String>,
+  pub jsonl: Option<String>,
+  pub no_sort: Option<bool>,
+  pub self_audit: Option<bool>,
+  pub fail_on_self_audit: Option<bool>,
+  pub check_version: Option<bool>,
+  pub require_version: Option<String>,
+  // `[[manual_component]]` sections, a profile's list replaces the base one
+  pub manual_component: Option<Vec<ManualComponent>>,
+  // `[manifests."package.json"]` tables, keyed by manifest file name
+  pub manifests: Option<BTreeMap<String, ManifestConfig>>,
+  // Shorthand for the column flags above, explicit flags in the same layer win
+  pub columns: Option<Vec<String>>,
+}
+
+// Switch and extra excludes for one manifest kind, on top of the global excludes
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct ManifestConfig {
+  pub enabled: Option<bool>,
+  pub exclude: Option<Vec<String>>,
+}
+
+// A component no parser covers, reported as written; name and license are required
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManualComponent {
+  pub name: Option<String>,
+  pub version: Option<String>,
+  pub license: Option<String>,
+  pub homepage: Option<String>,
+  pub license_url: Option<String>,
+  // Free text, e.g. "binary SDK" or "font"
+  pub ecosystem: Option<String>,
+}
+
+// `"pattern"`, or `{ pattern = "...", reason = "...", added_by = "...", date = "..." }` so auditors can see why
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PatternEntry {
+  Plain(String),
+  Documented {
+    pattern: String,
+    reason: Option<String>,
+    added_by: Option<String>,
+    date: Option<String>,
+  },
+}
+
+impl PatternEntry {
+  pub fn into_rule(self, option: &'static str) -> ExclusionRule {
+    match self {
+      PatternEntry::Plain(pattern) => ExclusionRule {
+        option,
+        pattern,
+        reason: None,
+        added_by: None,
+        date: None,
+      },
+      PatternEntry::Documented {
+        pattern,
+        reason,
+        added_by,
+        date,
+      } => ExclusionRule {
+        option,
+        pattern,
+        reason,
+        added_by,
+        date,
+      },
+    }
+  }
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigFile {
+  #[serde(flatten)]
+  base: ConfigLayer,
+  #[serde(default)]
+  profile: BTreeMap<String, ConfigLayer>,
+}
+
+// Base settings with the selected profile applied on top
+pub fn load(path: &str, profile: Option<&str>) -> Result<ConfigLayer> {
+  let content = fs::read_to_string(path).with_context(|| format!("Failed to read config: {}", path))?;
+  let mut file: ConfigFile = toml::from_str(&content).with_context(|| format!("Failed to parse config: {}", path))?;
+
+  let base = file
+    .base
+    .expand_columns()
+    .with_context(|| format!("Invalid base settings in {}", path))?;
+  let Some(name) = profile else {
+    return Ok(base);
+  };
+
+  let selected = file.profile.remove(name).ok_or_else(|| {
+    let available = file.profile.keys().cloned().collect::<Vec<_>>();
+    if available.is_empty() {
+      anyhow!("Unknown profile `{}`: {} defines no profiles", name, path)
+    } else {
+      anyhow!("Unknown profile `{}`, available: {}", name, available.join(", "))
+    }
+  })?;
+  let selected = selected
+    .expand_columns()
+    .with_context(|| format!("Invalid settings in profile `{}` of {}", name, path))?;
+
+  Ok(base.overridden_by(selected))
+}
+
+impl ConfigLayer {
+  fn overridden_by(self, other: ConfigLayer) -> ConfigLayer {
+    ConfigLayer {
+      exclude: other.exclude.or(self.exclude),
+      skip: other.skip.or(self.skip),
+      skip_scope: other.skip_scope.or(self.skip_scope),
+      skip_prefix: other.skip_prefix.or(self.skip_prefix),
+      debug_http: other.debug_http.or(self.debug_http),
+      canonical_map: other.canonical_map.or(self.canonical_map),
+      by_license: other.by_license.or(self.by_license),
+      by_license_collapse: other.by_license_collapse.or(self.by_license_collapse),
+      split_by_project: other.split_by_project.or(self.split_by_project),
+      no_combined: other.no_combined.or(self.no_combined),
+      constant_memory: other.constant_memory.or(self.constant_memory),
+      no_hyperlink_format: other.no_hyperlink_format.or(self.no_hyperlink_format),
+      link_limit: other.link_limit.or(self.link_limit),
+      split_on_link_limit: other.split_on_link_limit.or(self.split_on_link_limit),
+      verbose: other.verbose.or(self.verbose),
+      filter: other.filter.or(self.filter),
+      sheet: other.sheet.or(self.sheet),
+      js_sheet_name: other.js_sheet_name.or(self.js_sheet_name),
+      go_sheet_name: other.go_sheet_name.or(self.go_sheet_name),
+      rust_sheet_name: other.rust_sheet_name.or(self.rust_sheet_name),
+      python_sheet_name: other.python_sheet_name.or(self.python_sheet_name),
+      java_sheet_name: other.java_sheet_name.or(self.java_sheet_name),
+      ruby_sheet_name: other.ruby_sheet_name.or(self.ruby_sheet_name),
+      php_sheet_name: other.php_sheet_name.or(self.php_sheet_name),
+      dotnet_sheet_name: other.dotnet_sheet_name.or(self.dotnet_sheet_name),
+      dart_sheet_name: other.dart_sheet_name.or(self.dart_sheet_name),
+      swift_sheet_name: other.swift_sheet_name.or(self.swift_sheet_name),
+      cpp_sheet_name: other.cpp_sheet_name.or(self.cpp_sheet_name),
+      deno_sheet_name: other.deno_sheet_name.or(self.deno_sheet_name),
+      helm_sheet_name: other.helm_sheet_name.or(self.helm_sheet_name),
+      terraform_sheet_name: other.terraform_sheet_name.or(self.terraform_sheet_name),
+      github_actions: other.github_actions.or(self.github_actions),
+      graph: other.graph.or(self.graph),
+      use_go_tool: other.use_go_tool.or(self.use_go_tool),
+      pipenv_dev: other.pipenv_dev.or(self.pipenv_dev),
+      transitive: other.transitive.or(self.transitive),
+      maven_test: other.maven_test.or(self.maven_test),
+      funding: other.funding.or(self.funding),
+      provenance: other.provenance.or(self.provenance),
+      repo_mismatch: other.repo_mismatch.or(self.repo_mismatch),
+      verify_installed: other.verify_installed.or(self.verify_installed),
+      nested_notices: other.nested_notices.or(self.nested_notices),
+      nested_notices_min_size: other.nested_notices_min_size.or(self.nested_notices_min_size),
+      nested_notices_package: other.nested_notices_package.or(self.nested_notices_package),
+      trust_baseline: other.trust_baseline.or(self.trust_baseline),
+      max_baseline_age: other.max_baseline_age.or(self.max_baseline_age),
+      revalidate_sample: other.revalidate_sample.or(self.revalidate_sample),
+      revalidate_seed: other.revalidate_seed.or(self.revalidate_seed),
+      fail_on_repo_mismatch: other.fail_on_repo_mismatch.or(self.fail_on_repo_mismatch),
+      verify_links: other.verify_links.or(self.verify_links),
+      replace_dead_links: other.replace_dead_links.or(self.replace_dead_links),
+      fail_on_dead_links: other.fail_on_dead_links.or(self.fail_on_dead_links),
+      confidence: other.confidence.or(self.confidence),
+      license_flags: other.license_flags.or(self.license_flags),
+      review_columns: other.review_columns.or(self.review_columns),
+      review_file: other.review_file.or(self.review_file),
+      require_osi_approved: other.require_osi_approved.or(self.require_osi_approved),
+      require_fsf_libre: other.require_fsf_libre.or(self.require_fsf_libre),
+      min_confidence: other.min_confidence.or(self.min_confidence),
+      dedup: other.dedup.or(self.dedup),
+      collapse_by_repo: other.collapse_by_repo.or(self.collapse_by_repo),
+      detect_twins: other.detect_twins.or(self.detect_twins),
+      count_twins: other.count_twins.or(self.count_twins),
+      twin_pairs: other.twin_pairs.or(self.twin_pairs),
+      npm_registry: other.npm_registry.or(self.npm_registry),
+      npm_auth: other.npm_auth.or(self.npm_auth),
+      scope_registry: other.scope_registry.or(self.scope_registry),
+      ca_bundle: other.ca_bundle.or(self.ca_bundle),
+      pin: other.pin.or(self.pin),
+      target_platform: other.target_platform.or(self.target_platform),
+      strict_platform: other.strict_platform.or(self.strict_platform),
+      strict_versions: other.strict_versions.or(self.strict_versions),
+      prerelease: other.prerelease.or(self.prerelease),
+      provenance_check: other.provenance_check.or(self.provenance_check),
+      sizes: other.sizes.or(self.sizes),
+      sign_key: other.sign_key.or(self.sign_key),
+      bundle: other.bundle.or(self.bundle),
+      notice: other.notice.or(self.notice),
+      exclude_private: other.exclude_private.or(self.exclude_private),
+      redact_internal: other.redact_internal.or(self.redact_internal),
+      internal_namespace: other.internal_namespace.or(self.internal_namespace),
+      redaction_map: other.redaction_map.or(self.redaction_map),
+      format: other.format.or(self.format),
+      license_data: other.license_data.or(self.license_data),
+      exclude_licenses: other.exclude_licenses.or(self.exclude_licenses),
+      import_sbom: other.import_sbom.or(self.import_sbom),
+      post_process: other.post_process.or(self.post_process),
+      re_resolve_imported: other.re_resolve_imported.or(self.re_resolve_imported),
+      csv: other.csv.or(self.csv),
+      jsonl: other.jsonl.or(self.jsonl),
+      no_sort: other.no_sort.or(self.no_sort),
+      self_audit: other.self_audit.or(self.self_audit),
+      fail_on_self_audit: other.fail_on_self_audit.or(self.fail_on_self_audit),
+      check_version: other.check_version.or(self.check_version),
+      require_version: other.require_version.or(self.require_version),
+      manual_component: other.manual_component.or(self.manual_component),
+      manifests: merge_manifests(self.manifests, other.manifests),
+      columns: None,
+    }
+  }
+
+  fn expand_columns(mut self) -> Result<Self> {
+    let Some(columns) = self.columns.take() else {
+      return Ok(self);
+    };
+
+    if let Some(unknown) = columns.iter().find(|column| !COLUMNS.contains(&column.as_str())) {
+      bail!("Unknown column `{}`, expected one of: {}", unknown, COLUMNS.join(", "));
+    }
+    let has = |name: &str| Some(columns.iter().any(|column| column == name));
+    self.funding = self.funding.or(has("funding"));
+    self.provenance = self.provenance.or(has("provenance"));
+    self.confidence = self.confidence.or(has("confidence"));
+
+    Ok(self)
+  }
+}
+
+// A profile overrides manifest settings field by field, kinds it doesn't mention keep the base settings
+fn merge_manifests(
+  base: Option<BTreeMap<String, ManifestConfig>>,
+  other: Option<BTreeMap<String, ManifestConfig>>,
+) -> Option<BTreeMap<String, ManifestConfig>> {
+  let Some(other) = other else {
+    return base;
+  };
+  let mut merged = base.unwrap_or_default();
+  for (kind, manifest) in other {
+    let entry = merged.entry(kind).or_default();
+    entry.enabled = manifest.enabled.or(entry.enabled);
+    entry.exclude = manifest.exclude.or(entry.exclude.take());
+  }
+
+  Some(merged)
+}
@@ -0,0 +1,370 @@
+use crate::{hermetic, license_data::LicenseData};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use ring::{
+  aead::{self, Aad, LessSafeKey, Nonce, UnboundKey},
+  pbkdf2,
+  rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::BTreeMap,
+  env, fs,
+  io::{self, BufRead, IsTerminal, Write},
+  num::NonZeroU32,
+  path::PathBuf,
+};
+
+#[cfg(feature = "keyring")]
+const SERVICE: &str = "collect_lic_info";
+// Keyrings can't enumerate entries, so the stored host names are kept under this user
+#[cfg(feature = "keyring")]
+const INDEX_USER: &str = "@hosts";
+// Unlocks the fallback file on machines without a keyring backend
+const PASSPHRASE_ENV: &str = "DEPSFETCH_CREDENTIALS_PASSPHRASE";
+const CREDENTIALS_FILE: &str = "credentials.json";
+const PBKDF2_ITERATIONS: u32 = 100_000;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+// Where `auth set` put the tokens. Nothing here ever prints or returns a token in an error.
+pub enum CredentialStore {
+  #[cfg(feature = "keyring")]
+  Keyring,
+  File(Box<FileStore>),
+}
+
+pub struct FileStore {
+  path: PathBuf,
+  key: LessSafeKey,
+  content: CredentialsFile,
+}
+
+// Host names stay readable so `auth list` works without the passphrase; each token is sealed with the host as AAD
+#[derive(Serialize, Deserialize)]
+struct CredentialsFile {
+  salt: String,
+  #[serde(default)]
+  hosts: BTreeMap<String, String>,
+}
+
+impl CredentialStore {
+  // The system keyring when it answers (and the build has it), the passphrase-encrypted file otherwise. Every use
+  // of either starts here.
+  pub fn open() -> Result<Self> {
+    if hermetic::is_enabled() {
+      bail!("Stored credentials are off in hermetic runs, pass tokens through the environment");
+    }
+    #[cfg(feature = "keyring")]
+    match keyring_index() {
+      Ok(_) => return Ok(CredentialStore::Keyring),
+      Err(keyring::Error::NoStorageAccess(_) | keyring::Error::PlatformFailure(_)) => {},
+      Err(err) => bail!("Failed to read the system keyring: {}", err),
+    }
+    Ok(CredentialStore::File(Box::new(FileStore::open()?)))
+  }
+
+  pub fn describe(&self) -> String {
+    match self {
+      #[cfg(feature = "keyring")]
+      CredentialStore::Keyring => "system keyring".to_owned(),
+      CredentialStore::File(file) => format!("encrypted file {}", file.path.display()),
+    }
+  }
+
+  pub fn hosts(&self) -> Result<Vec<String>> {
+    match self {
+      #[cfg(feature = "keyring")]
+      CredentialStore::Keyring => Ok(keyring_hosts()?),
+      CredentialStore::File(file) => Ok(file.content.hosts.keys().cloned().collect()),
+    }
+  }
+
+  pub fn get(&self, host: &str) -> Result<Option<String>> {
+    match self {
+      #[cfg(feature = "keyring")]
+      CredentialStore::Keyring => match keyring_entry(host)?.get_password() {
+        Ok(token) => Ok(Some(token)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(anyhow!(
+          "Failed to read the token for {} from the keyring: {}",
+          host,
+          err
+        )),
+      },
+      CredentialStore::File(file) => file.get(host),
+    }
+  }
+
+  pub fn set(&mut self, host: &str, token: &str) -> Result<()> {
+    match self {
+      #[cfg(feature = "keyring")]
+      CredentialStore::Keyring => {
+        keyring_entry(host)?
+          .set_password(token)
+          .map_err(|err| anyhow!("Failed to store the token for {} in the keyring: {}", host, err))?;
+        let mut hosts = keyring_hosts()?;
+        if !hosts.iter().any(|known| known == host) {
+          hosts.push(host.to_owned());
+          write_keyring_index(&hosts)?;
+        }
+        Ok(())
+      },
+      CredentialStore::File(file) => file.set(host, token),
+    }
+  }
+
+  // Whether there was anything to remove
+  pub fn remove(&mut self, host: &str) -> Result<bool> {
+    match self {
+      #[cfg(feature = "keyring")]
+      CredentialStore::Keyring => {
+        let removed = match keyring_entry(host)?.delete_credential() {
+          Ok(()) => true,
+          Err(keyring::Error::NoEntry) => false,
+          Err(err) => bail!("Failed to remove the token for {} from the keyring: {}", host, err),
+        };
+        let hosts = keyring_hosts()?;
+        if hosts.iter().any(|known| known == host) {
+          write_keyring_index(&hosts.into_iter().filter(|known| known != host).collect::<Vec<_>>())?;
+        }
+        Ok(removed)
+      },
+      CredentialStore::File(file) => file.remove(host),
+    }
+  }
+}
+
+impl FileStore {
+  fn open() -> Result<Self> {
+    let path = LicenseData::default_dir()
+      .context("No keyring backend and no user data directory for the credentials file")?
+      .join(CREDENTIALS_FILE);
+    let passphrase = env::var(PASSPHRASE_ENV).with_context(|| {
+      format!(
+        "No keyring backend available; set {} to use the encrypted file",
+        PASSPHRASE_ENV
+      )
+    })?;
+
+    let content = match hermetic::read_to_string(&path) {
+      Ok(content) => serde_json::from_str::<CredentialsFile>(&content)
+        .with_context(|| format!("Failed to parse credentials file {}", path.display()))?,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => {
+        let mut salt = [0u8; SALT_LEN];
+        SystemRandom::new()
+          .fill(&mut salt)
+          .map_err(|_| anyhow!("Failed to generate a salt"))?;
+        CredentialsFile {
+          salt: STANDARD.encode(salt),
+          hosts: BTreeMap::new(),
+        }
+      },
+      Err(err) => return Err(err).with_context(|| format!("Failed to read credentials file {}", path.display())),
+    };
+
+    let salt = STANDARD
+      .decode(&content.salt)
+      .with_context(|| format!("Credentials file {} has an invalid salt", path.display()))?;
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+      pbkdf2::PBKDF2_HMAC_SHA256,
+      NonZeroU32::new(PBKDF2_ITERATIONS).expect("iterations are not zero"),
+      &salt,
+      passphrase.as_bytes(),
+      &mut key,
+    );
+    let key = UnboundKey::new(&aead::CHACHA20_POLY1305, &key).map_err(|_| anyhow!("Failed to derive the file key"))?;
+
+    Ok(Self {
+      path,
+      key: LessSafeKey::new(key),
+      content,
+    })
+  }
+
+  fn get(&self, host: &str) -> Result<Option<String>> {
+    let Some(sealed) = self.content.hosts.get(host) else {
+      return Ok(None);
+    };
+    let sealed = STANDARD
+      .decode(sealed)
+      .ok()
+      .filter(|sealed| sealed.len() > NONCE_LEN)
+      .ok_or_else(|| anyhow!("Stored token for {} in {} is corrupt", host, self.path.display()))?;
+
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("Stored token for {} is corrupt", host))?;
+    let mut in_out = ciphertext.to_vec();
+    let token = self
+      .key
+      .open_in_place(nonce, Aad::from(host.as_bytes()), &mut in_out)
+      .map_err(|_| anyhow!("Can't decrypt the token for {}: wrong {}?", host, PASSPHRASE_ENV))?;
+
+    Ok(Some(
+      String::from_utf8(token.to_vec()).map_err(|_| anyhow!("Stored token for {} is not UTF-8", host))?,
+    ))
+  }
+
+  fn set(&mut self, host: &str, token: &str) -> Result<()> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+      .fill(&mut nonce)
+      .map_err(|_| anyhow!("Failed to generate a nonce"))?;
+    let mut in_out = token.as_bytes().to_vec();
+    self
+      .key
+      .seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(host.as_bytes()),
+        &mut in_out,
+      )
+      .map_err(|_| anyhow!("Failed to encrypt the token for {}", host))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend(in_out);
+    self.content.hosts.insert(host.to_owned(), STANDARD.encode(sealed));
+    self.save()
+  }
+
+  fn remove(&mut self, host: &str) -> Result<bool> {
+    let removed = self.content.hosts.remove(host).is_some();
+    if removed {
+      self.save()?;
+    }
+    Ok(removed)
+  }
+
+  fn save(&self) -> Result<()> {
+    if let Some(dir) = self.path.parent() {
+      hermetic::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    // Sealed or not, the file is nobody else's business: created owner-only, so it is never readable by others even
+    // for a moment, and renamed over the old one once complete
+    let content = serde_json::to_string_pretty(&self.content)?;
+    let partial = self.path.with_extension("json.partial");
+    hermetic::check(&partial).with_context(|| format!("Failed to write credentials file {}", partial.display()))?;
+    let _ = fs::remove_file(&partial);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::OpenOptionsExt;
+      options.mode(0o600);
+    }
+    options
+      .open(&partial)
+      .and_then(|mut file| file.write_all(content.as_bytes()))
+      .with_context(|| format!("Failed to write credentials file {}", partial.display()))?;
+    fs::rename(&partial, &self.path)
+      .with_context(|| format!("Failed to move the credentials file to {}", self.path.display()))
+  }
+}
+
+// `auth set`: a hidden prompt on a terminal, the first line of stdin otherwise (for scripted setup)
+pub fn set(host: &str) -> Result<()> {
+  let mut store = CredentialStore::open()?;
+  let token = if io::stdin().is_terminal() {
+    rpassword::prompt_password(format!("Token for {}: ", host)).context("Failed to read the token")?
+  } else {
+    let mut line = String::new();
+    io::stdin()
+      .lock()
+      .read_line(&mut line)
+      .context("Failed to read the token from stdin")?;
+    line.trim_end_matches(['\n', '\r']).to_owned()
+  };
+  if token.is_empty() {
+    bail!("No token given for {}", host);
+  }
+
+  store.set(host, &token)?;
+  println!("Stored the token for {} in the {}", host, store.describe());
+  Ok(())
+}
+
+pub fn list() -> Result<()> {
+  let store = CredentialStore::open()?;
+  let hosts = store.hosts()?;
+  if hosts.is_empty() {
+    println!("No tokens stored in the {}", store.describe());
+  }
+  for host in hosts {
+    println!("{}", host);
+  }
+  Ok(())
+}
+
+pub fn remove(host: &str) -> Result<()> {
+  let mut store = CredentialStore::open()?;
+  if store.remove(host)? {
+    println!("Removed the token for {} from the {}", host, store.describe());
+  } else {
+    println!("No token stored for {} in the {}", host, store.describe());
+  }
+  Ok(())
+}
+
+// Tokens for every stored host. A store that can't be opened costs a note, never the run.
+pub fn load_stored() -> (BTreeMap<String, String>, Option<String>) {
+  if hermetic::is_enabled() {
+    return (BTreeMap::new(), None);
+  }
+  let store = match CredentialStore::open() {
+    Ok(store) => store,
+    Err(err) => {
+      if env::var_os(PASSPHRASE_ENV).is_some() {
+        println!("Stored credentials unavailable: {:#}", err);
+      }
+      return (BTreeMap::new(), None);
+    },
+  };
+
+  let mut tokens = BTreeMap::new();
+  match store.hosts() {
+    Ok(hosts) => {
+      for host in hosts {
+        match store.get(&host) {
+          Ok(Some(token)) => {
+            tokens.insert(host, token);
+          },
+          Ok(None) => {},
+          Err(err) => println!("Skipping stored token: {:#}", err),
+        }
+      }
+    },
+    Err(err) => println!("Stored credentials unavailable: {:#}", err),
+  }
+
+  (tokens, Some(store.describe()))
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_entry(user: &str) -> Result<keyring::Entry> {
+  keyring::Entry::new(SERVICE, user).map_err(|err| anyhow!("Failed to open keyring entry for {}: {}", user, err))
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_index() -> keyring::Result<Vec<String>> {
+  match keyring::Entry::new(SERVICE, INDEX_USER)?.get_password() {
+    Ok(index) => Ok(index.lines().map(str::to_owned).collect()),
+    Err(keyring::Error::NoEntry) => Ok(Vec::new()),
+    Err(err) => Err(err),
+  }
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_hosts() -> Result<Vec<String>> {
+  keyring_index().map_err(|err| anyhow!("Failed to read the host index from the keyring: {}", err))
+}
+
+#[cfg(feature = "keyring")]
+fn write_keyring_index(hosts: &[String]) -> Result<()> {
+  let entry = keyring_entry(INDEX_USER)?;
+  let result = if hosts.is_empty() {
+    entry.delete_credential()
+  } else {
+    entry.set_password(&hosts.join("\n"))
+  };
+  result.map_err(|err| anyhow!("Failed to update the host index in the keyring: {}", err))
+}
@@ -0,0 +1,133 @@
+use super::{manifests::ManifestFilter, patterns::PatternSet};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry},
+};
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::{
+  collections::{BTreeSet, HashMap},
+  fs,
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+use walkdir::WalkDir;
+
+lazy_static! {
+  // `uses:` of a step or of a job calling a reusable workflow, quoted or not, up to a comment
+  static ref USES_REGEX: Regex =
+    Regex::new(r#"^\s*(?:-\s+)?uses:\s*["']?([^"'\s#]+)"#).expect("Failed to compile uses regex");
+}
+
+pub const WORKFLOWS_KIND: &str = ".github/workflows";
+const WORKFLOWS_DIR: &str = "workflows";
+const GITHUB_DIR: &str = ".github";
+// Runs an image instead of an action from a repository
+const DOCKER_PREFIX: &str = "docker://";
+
+// Third-party GitHub Actions, only scanned with --github-actions: the `uses: owner/repo[/path]@ref` entries of
+// .github/workflows/*.yml. Each (action, ref) pair is one entry, keyed `action@ref`, since workflows commonly pin
+// the same action at different refs. Actions only run in CI, so they're listed as dev dependencies.
+pub struct ActionsParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+}
+
+impl ActionsParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+    })
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies: HashMap<String, DepsEntry> = HashMap::new();
+
+    let workflows = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| !entry.file_type().is_dir() && is_workflow(entry.path()));
+    for entry in workflows {
+      let path = entry.path();
+      if path.to_str().is_some_and(|path| self.exclude_patterns.is_match(path))
+        || !self.manifests.allows(WORKFLOWS_KIND, path)
+      {
+        continue;
+      }
+
+      println!("Processing file: {}", path.display());
+      let content = fs::read_to_string(path).with_context(|| format!("Failed to read workflow: {}", path.display()))?;
+      let source = self.source(path);
+      for uses in content.lines().filter_map(|line| USES_REGEX.captures(line)) {
+        let uses = &uses[1];
+        // Local actions are part of the repository, expressions can't be resolved without running the workflow
+        if uses.starts_with("./") || uses.starts_with(DOCKER_PREFIX) || uses.contains("${{") {
+          continue;
+        }
+        let Some((action, reference)) = uses.split_once('@').filter(|(action, _)| repository(action).is_some()) else {
+          self.diagnostics.warn(
+            Category::ParseFailure,
+            format!("{}: `uses: {}` is not owner/repo@ref, skipped", source, uses),
+          );
+          continue;
+        };
+
+        dependencies
+          .entry(format!("{}@{}", action, reference))
+          .or_insert_with(|| DepsEntry {
+            name: action.to_owned(),
+            version: reference.to_owned(),
+            scope: DepScope::Dev,
+            sources: BTreeSet::new(),
+          })
+          .sources
+          .insert(source.clone());
+      }
+    }
+
+    Ok(dependencies)
+  }
+
+  fn source(&self, path: &Path) -> String {
+    match path.strip_prefix(&self.root_path) {
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => path.display().to_string(),
+    }
+  }
+}
+
+// `owner/repo` of an action, which may live in a subdirectory (`github/codeql-action/init`) or be a reusable
+// workflow (`org/shared/.github/workflows/build.yml`)
+pub fn repository(action: &str) -> Option<String> {
+  let mut segments = action.split('/');
+  let (owner, repo) = (segments.next()?, segments.next()?);
+  (!owner.is_empty() && !repo.is_empty()).then(|| format!("{}/{}", owner, repo))
+}
+
+fn is_workflow(path: &Path) -> bool {
+  let is_yaml = path
+    .extension()
+    .is_some_and(|extension| extension == "yml" || extension == "yaml");
+  let dir = path.parent();
+  is_yaml
+    && dir.and_then(Path::file_name).is_some_and(|name| name == WORKFLOWS_DIR)
+    && dir
+      .and_then(Path::parent)
+      .and_then(Path::file_name)
+      .is_some_and(|name| name == GITHUB_DIR)
+}
@@ -0,0 +1,78 @@
+use crate::types::{PackageJson, SelfAuditFinding};
+use std::{fs, path::Path};
+
+// File name prefixes accepted as a license file next to a manifest, compared case-insensitively
+const LICENSE_FILE_PREFIXES: &[&str] = &["LICENSE", "LICENCE", "COPYING"];
+const NOTICE_FILE_PREFIXES: &[&str] = &[
+  "THIRDPARTYNOTICES",
+  "THIRD_PARTY_NOTICES",
+  "THIRD-PARTY-NOTICES",
+  "NOTICE",
+];
+
+pub fn has_license_file(dir: &Path) -> bool {
+  let Ok(entries) = fs::read_dir(dir) else {
+    return false;
+  };
+
+  entries
+    .filter_map(Result::ok)
+    .any(|entry| entry.path().is_file() && is_license_file_name(&entry.file_name().to_string_lossy()))
+}
+
+pub fn is_license_file_name(name: &str) -> bool {
+  let name = name.to_uppercase();
+  LICENSE_FILE_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+// `ThirdPartyNotices.txt`, `NOTICE.md` and the like, crediting code the package bundles
+pub fn is_notice_file_name(name: &str) -> bool {
+  let name = name.to_uppercase();
+  NOTICE_FILE_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+pub fn audit_package_json(manifest: &str, package_json: &PackageJson, dir: &Path) -> Vec<SelfAuditFinding> {
+  let package = package_json.name.clone().unwrap_or_else(|| manifest.to_owned());
+  let finding = |issue: &str| SelfAuditFinding {
+    manifest: manifest.to_owned(),
+    package: package.clone(),
+    issue: issue.to_owned(),
+  };
+
+  let mut findings = Vec::new();
+  match package_json.license.as_ref().and_then(license_id) {
+    None => findings.push(finding("missing license field")),
+    Some(license) if license.eq_ignore_ascii_case("UNLICENSED") => findings.push(finding("license is UNLICENSED")),
+    Some(_) => {},
+  }
+  if package_json.repository.is_none() {
+    findings.push(finding("missing repository field"));
+  }
+  if !has_license_file(dir) {
+    findings.push(finding("no LICENSE file in package directory"));
+  }
+
+  findings
+}
+
+pub fn audit_go_mod(manifest: &str, module: &str, dir: &Path) -> Vec<SelfAuditFinding> {
+  if has_license_file(dir) {
+    return Vec::new();
+  }
+
+  vec![SelfAuditFinding {
+    manifest: manifest.to_owned(),
+    package: module.to_owned(),
+    issue: "no LICENSE file in module directory".to_owned(),
+  }]
+}
+
+// `"MIT"` or the legacy `{ "type": "MIT" }`
+fn license_id(value: &serde_json::Value) -> Option<&str> {
+  match value {
+    serde_json::Value::String(license) => Some(license.as_str()),
+    serde_json::Value::Object(license) => license.get("type").and_then(|kind| kind.as_str()),
+    _ => None,
+  }
+  .filter(|license| !license.trim().is_empty())
+}
@@ -0,0 +1,91 @@
+use super::deno_deps::strip_jsonc;
+use crate::version::Version;
+use anyhow::{anyhow, bail, Context, Result};
+use std::{collections::HashMap, fs, path::Path, process::Command};
+
+pub const BUN_LOCK_FILE: &str = "bun.lock";
+pub const BUN_LOCKB_FILE: &str = "bun.lockb";
+
+// Exact versions a Bun project installed, what its package.json ranges resolved to.
+// bun.lock is JSON with trailing commas and is read as it is. The binary bun.lockb is only readable by bun itself,
+// so it goes through `bun pm ls` when bun is on PATH; without bun the package.json ranges stay what gets looked up.
+pub struct BunLock {
+  // Lockfile key -> version: a bare name for the hoisted copy, `<parent>/<name>` for a workspace's or package's own
+  versions: HashMap<String, String>,
+}
+
+impl BunLock {
+  pub fn read(path: &Path) -> Result<Self> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let lock = serde_json::from_str::<serde_json::Value>(&strip_jsonc(&content))
+      .with_context(|| format!("Failed to parse JSON from: {}", path.display()))?;
+    let Some(packages) = lock.get("packages").and_then(serde_json::Value::as_object) else {
+      bail!("{} has no packages", path.display());
+    };
+
+    // Each entry is an array that starts with the `name@version` installed under that key
+    let versions = packages
+      .iter()
+      .filter_map(|(key, entry)| {
+        let ident = entry.get(0)?.as_str()?;
+        let version = pinned_version(key_name(key), ident)?;
+        Some((key.clone(), version.to_owned()))
+      })
+      .collect();
+
+    Ok(Self { versions })
+  }
+
+  // `bun pm ls` lists the project's direct dependencies as a tree: `├── name@version`
+  pub fn list(path: &Path) -> Result<Self> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let output = Command::new("bun")
+      .args(["pm", "ls"])
+      .current_dir(dir)
+      .output()
+      .context("Failed to start bun binary")?;
+
+    if !output.status.success() {
+      return Err(anyhow!(String::from_utf8_lossy(&output.stderr).trim().to_owned()));
+    }
+
+    let versions = String::from_utf8_lossy(&output.stdout)
+      .lines()
+      .filter_map(|line| line.split_once("── "))
+      .filter_map(|(_, ident)| {
+        let (name, _) = ident.trim().rsplit_once('@')?;
+        let version = pinned_version(name, ident.trim())?;
+        Some((name.to_owned(), version.to_owned()))
+      })
+      .collect();
+
+    Ok(Self { versions })
+  }
+
+  // The version `name` resolved to for the package.json named `workspace`: its own copy first, then the hoisted one
+  pub fn version(&self, workspace: Option<&str>, name: &str) -> Option<&str> {
+    workspace
+      .and_then(|workspace| self.versions.get(&format!("{}/{}", workspace, name)))
+      .or_else(|| self.versions.get(name))
+      .map(String::as_str)
+  }
+}
+
+// The package a lockfile key installs: the last name in `parent/@scope/name`
+fn key_name(key: &str) -> &str {
+  let Some((parent, name)) = key.rsplit_once('/') else {
+    return key;
+  };
+  let scope = parent.rsplit('/').next().unwrap_or(parent);
+  match scope.starts_with('@') {
+    true => &key[key.len() - name.len() - scope.len() - 1..],
+    false => name,
+  }
+}
+
+// The version of a `name@version` ident when it installs `name` itself from the registry. Aliases
+// (`npm:other@1.0.0`), workspaces, git and file dependencies have nothing the registry would find under `name`.
+fn pinned_version<'a>(name: &str, ident: &'a str) -> Option<&'a str> {
+  let (installed, version) = ident.rsplit_once('@')?;
+  (installed == name && Version::parse(version).is_ok()).then_some(version)
+}
@@ -0,0 +1,339 @@
+use super::{manifests::ManifestFilter, patterns::PatternSet};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry},
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+  fs,
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+use walkdir::{DirEntry, WalkDir};
+
+static CARGO_TOML_FILE: &str = "Cargo.toml";
+static CARGO_LOCK_FILE: &str = "Cargo.lock";
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct CargoManifest {
+  package: Option<CargoPackage>,
+  workspace: Option<CargoWorkspace>,
+  #[serde(default)]
+  dependencies: BTreeMap<String, CargoDependency>,
+  // `[target.'cfg(unix)'.dependencies]`
+  #[serde(default)]
+  target: BTreeMap<String, CargoTarget>,
+}
+
+#[derive(Deserialize)]
+struct CargoPackage {
+  name: String,
+}
+
+#[derive(Default, Deserialize)]
+struct CargoWorkspace {
+  #[serde(default)]
+  dependencies: BTreeMap<String, CargoDependency>,
+}
+
+#[derive(Default, Deserialize)]
+struct CargoTarget {
+  #[serde(default)]
+  dependencies: BTreeMap<String, CargoDependency>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+enum CargoDependency {
+  Version(String),
+  Detailed(DetailedDependency),
+}
+
+#[derive(Clone, Default, Deserialize)]
+struct DetailedDependency {
+  version: Option<String>,
+  path: Option<String>,
+  git: Option<String>,
+  // Inherited from the workspace's `[workspace.dependencies]`
+  workspace: Option<bool>,
+  // The crate's name when the key renames it
+  package: Option<String>,
+  optional: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct CargoLock {
+  #[serde(default)]
+  package: Vec<LockedPackage>,
+}
+
+#[derive(Deserialize)]
+struct LockedPackage {
+  name: String,
+  version: String,
+  // Workspace members and path dependencies have none
+  source: Option<String>,
+}
+
+struct Manifest {
+  path: PathBuf,
+  source: String,
+  content: CargoManifest,
+}
+
+// Only `[dependencies]` and their per-target variants ship with the crate; dev- and build-dependencies are left out
+// like npm devDependencies are.
+pub struct CargoParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+}
+
+impl CargoParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+    })
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies = HashMap::new();
+
+    let mut manifests = Vec::new();
+    let cargo_toml_files = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| self.is_valid_cargo_toml(entry));
+    for entry in cargo_toml_files {
+      let path = entry.path();
+      println!("Processing file: {}", path.display());
+
+      let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read Cargo.toml file: {}", path.display()))?;
+      let content = match toml::from_str::<CargoManifest>(&content) {
+        Ok(content) => content,
+        Err(err) => {
+          self.diagnostics.warn(
+            Category::ParseFailure,
+            format!("Can't parse {}: {}", path.display(), err),
+          );
+          continue;
+        },
+      };
+      let source = match path.strip_prefix(&self.root_path) {
+        Ok(relative) => relative.display().to_string(),
+        Err(_) => path.display().to_string(),
+      };
+      manifests.push(Manifest {
+        path: path.to_path_buf(),
+        source,
+        content,
+      });
+    }
+
+    // Crates of the scanned tree itself, whatever way they are depended on
+    let local = manifests
+      .iter()
+      .filter_map(|manifest| manifest.content.package.as_ref())
+      .map(|package| package.name.clone())
+      .collect::<HashSet<_>>();
+    let mut locks = HashMap::new();
+
+    for manifest in &manifests {
+      let dir = manifest.path.parent().unwrap_or(&self.root_path);
+      let lock = match self.find_up(dir, CARGO_LOCK_FILE) {
+        Some(lock_path) => locks
+          .entry(lock_path.clone())
+          .or_insert_with(|| self.read_lock(&lock_path))
+          .as_ref(),
+        None => None,
+      };
+      let workspace = self.workspace_dependencies(dir, &manifests);
+
+      let declared = manifest
+        .content
+        .dependencies
+        .iter()
+        .chain(manifest.content.target.values().flat_map(|target| &target.dependencies));
+      for (key, dependency) in declared {
+        let mut dependency = match dependency {
+          CargoDependency::Version(version) => DetailedDependency {
+            version: Some(version.clone()),
+            ..Default::default()
+          },
+          CargoDependency::Detailed(detailed) => detailed.clone(),
+        };
+        if dependency.workspace == Some(true) {
+          match workspace.and_then(|workspace| workspace.get(key)) {
+            Some(CargoDependency::Version(version)) => dependency.version = Some(version.clone()),
+            Some(CargoDependency::Detailed(inherited)) => {
+              dependency.version = inherited.version.clone();
+              dependency.path = inherited.path.clone();
+              dependency.git = inherited.git.clone();
+              dependency.package = dependency.package.or_else(|| inherited.package.clone());
+            },
+            None => {},
+          }
+        }
+
+        let name = dependency.package.clone().unwrap_or_else(|| key.clone());
+        if dependency.path.is_some() || local.contains(&name) {
+          continue;
+        }
+        if let Some(git) = &dependency.git {
+          self.diagnostics.warn(
+            Category::ScanIssue,
+            format!(
+              "{} in {} comes from {}, not crates.io; list it as a manual component",
+              name, manifest.source, git
+            ),
+          );
+          continue;
+        }
+
+        let requirement = dependency.version.as_deref().unwrap_or("*");
+        let version = lock
+          .and_then(|lock| locked_version(lock, &name, requirement))
+          .unwrap_or_else(|| minimum_version(requirement));
+        DepsEntry {
+          name,
+          version,
+          scope: match dependency.optional {
+            Some(true) => DepScope::Optional,
+            _ => DepScope::Runtime,
+          },
+          sources: BTreeSet::from([manifest.source.clone()]),
+        }
+        .insert_into(&mut dependencies);
+      }
+    }
+
+    Ok(dependencies)
+  }
+
+  // `[workspace.dependencies]` of the nearest workspace root at or above `dir`
+  fn workspace_dependencies<'a>(
+    &self,
+    dir: &Path,
+    manifests: &'a [Manifest],
+  ) -> Option<&'a BTreeMap<String, CargoDependency>> {
+    manifests
+      .iter()
+      .filter(|manifest| manifest.content.workspace.is_some())
+      .filter(|manifest| manifest.path.parent().is_some_and(|root| dir.starts_with(root)))
+      .max_by_key(|manifest| manifest.path.as_os_str().len())
+      .and_then(|manifest| manifest.content.workspace.as_ref())
+      .map(|workspace| &workspace.dependencies)
+  }
+
+  // The closest `file` from `dir` up to the scanned root, the way cargo finds a workspace's lock file
+  fn find_up(&self, dir: &Path, file: &str) -> Option<PathBuf> {
+    dir
+      .ancestors()
+      .take_while(|ancestor| ancestor.starts_with(&self.root_path))
+      .map(|ancestor| ancestor.join(file))
+      .find(|path| path.is_file())
+  }
+
+  // Registry packages by name, an unreadable lock file is warned about and ignored
+  fn read_lock(&self, path: &Path) -> Option<HashMap<String, Vec<String>>> {
+    let lock = fs::read_to_string(path)
+      .map_err(anyhow::Error::from)
+      .and_then(|content| Ok(toml::from_str::<CargoLock>(&content)?));
+    let lock = match lock {
+      Ok(lock) => lock,
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::ParseFailure,
+          format!(
+            "Can't read {}, using the Cargo.toml requirements: {}",
+            path.display(),
+            err
+          ),
+        );
+        return None;
+      },
+    };
+
+    let mut packages: HashMap<String, Vec<String>> = HashMap::new();
+    for package in lock.package.into_iter().filter(|package| package.source.is_some()) {
+      packages.entry(package.name).or_default().push(package.version);
+    }
+    Some(packages)
+  }
+
+  fn is_valid_cargo_toml(&self, entry: &DirEntry) -> bool {
+    // Skip directories and hidden files
+    if entry.file_type().is_dir() || entry.file_name().to_str().is_some_and(|s| s.starts_with('.')) {
+      return false;
+    }
+
+    // Skip excluded paths
+    if let Some(path_str) = entry.path().to_str() {
+      if self.exclude_patterns.is_match(path_str) {
+        return false;
+      }
+    }
+
+    // Check if it's a Cargo.toml file the Cargo.toml rules let through
+    entry.file_name().to_str() == Some(CARGO_TOML_FILE) && self.manifests.allows(CARGO_TOML_FILE, entry.path())
+  }
+}
+
+// A lock file may hold several semver-incompatible versions of one crate, the one the requirement allows is taken
+fn locked_version(lock: &HashMap<String, Vec<String>>, name: &str, requirement: &str) -> Option<String> {
+  let versions = lock.get(name)?;
+  let minimum = minimum_version(requirement);
+  versions
+    .iter()
+    .find(|version| compatible(version, &minimum))
+    .or_else(|| versions.last())
+    .cloned()
+}
+
+// `^1.2`, `=1.2.3`, `~1.2`, `>=1.0, <2` -> the lowest version the requirement accepts, padded to three parts
+fn minimum_version(requirement: &str) -> String {
+  let first = requirement.split(',').next().unwrap_or(requirement).trim();
+  let version = first.trim_start_matches(['^', '=', '~', '>', '<', ' ']).trim();
+  if version.is_empty() || version == "*" {
+    return "*".to_owned();
+  }
+  let mut parts = version.split('.').take_while(|part| *part != "*").collect::<Vec<_>>();
+  while parts.len() < 3 {
+    parts.push("0");
+  }
+  parts.join(".")
+}
+
+// Cargo's caret rule: versions agree up to and including their first non-zero part
+fn compatible(version: &str, minimum: &str) -> bool {
+  if minimum == "*" {
+    return true;
+  }
+  let mut version = version.split('.');
+  for part in minimum.split('.') {
+    if version.next() != Some(part) {
+      return false;
+    }
+    if part != "0" {
+      return true;
+    }
+  }
+  true
+}
@@ -0,0 +1,302 @@
+use super::{
+  license_text, local_path,
+  manifests::ManifestFilter,
+  patterns::PatternSet,
+  yaml::{self, Node},
+};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::{Context, Result};
+use std::{
+  collections::{btree_map, BTreeMap, BTreeSet, HashMap},
+  fs,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+};
+use walkdir::WalkDir;
+
+static MANIFEST: &str = "Podfile";
+static LOCKFILE: &str = "Podfile.lock";
+// The installed pods, with their own podspecs and license files
+const PODS_DIR: &str = "Pods";
+// How Podfile.lock names the public Specs repo, the CDN and the old git checkout of it
+const TRUNK_REPOS: &[&str] = &["trunk", "https://github.com/CocoaPods/Specs.git"];
+
+// CocoaPods pods from Podfile.lock's PODS section, subspecs like `Firebase/Analytics` folded into their pod.
+// Pods from the public Specs repo are resolved against trunk; pods from a path, a git repository or a private
+// Specs repo are listed with where they come from.
+pub struct CocoaPodsParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+  // Each with the manifest it was found in
+  direct: Mutex<BTreeMap<String, (String, ReportRow)>>,
+}
+
+impl CocoaPodsParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+      direct: Mutex::new(BTreeMap::new()),
+    })
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies = HashMap::new();
+
+    let entries = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_entry(|entry| entry.depth() == 0 || entry.file_name() != PODS_DIR)
+      .filter_map(Result::ok)
+      .filter(|entry| !entry.file_type().is_dir());
+    for entry in entries {
+      let path = entry.path();
+      if path.to_str().is_some_and(|path| self.exclude_patterns.is_match(path)) {
+        continue;
+      }
+      let name = entry.file_name().to_string_lossy();
+      if name == MANIFEST && !path.with_file_name(LOCKFILE).is_file() {
+        self.diagnostics.warn(
+          Category::ScanIssue,
+          format!(
+            "{} has no {}, its pods aren't listed; write one with `pod install`",
+            self.source(path),
+            LOCKFILE
+          ),
+        );
+      } else if name == LOCKFILE && self.manifests.allows(LOCKFILE, path) {
+        println!("Processing file: {}", path.display());
+        self.parse_lockfile(path, &mut dependencies)?;
+      }
+    }
+
+    Ok(dependencies)
+  }
+
+  // Path, git and private Specs repo pods found by `parse`, reported with their origin instead of a trunk lookup
+  pub fn direct_references(&self) -> Vec<(String, ReportRow)> {
+    self
+      .direct
+      .lock()
+      .map(|mut rows| std::mem::take(&mut *rows).into_values().collect())
+      .unwrap_or_default()
+  }
+
+  fn parse_lockfile(&self, path: &Path, dependencies: &mut HashMap<String, DepsEntry>) -> Result<()> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read lock file: {}", path.display()))?;
+    let source = self.source(path);
+    // The YAML reader skips the sequences, PODS and SPEC REPOS are read line by line
+    let lock = match Node::parse(&content) {
+      Ok(lock) => lock,
+      Err(err) => {
+        self
+          .diagnostics
+          .warn(Category::ParseFailure, format!("Can't parse {}: {:#}", source, err));
+        return Ok(());
+      },
+    };
+    let (pods, spec_repos) = sequences(&content);
+    let external = lock.get("EXTERNAL SOURCES");
+    let checkout = lock.get("CHECKOUT OPTIONS");
+    let dir = path.parent().unwrap_or(&self.root_path);
+
+    for (name, version) in pods {
+      let external = external.and_then(|external| external.get(&name));
+      let spec_repo = spec_repos
+        .get(&name)
+        .filter(|repo| !TRUNK_REPOS.contains(&repo.as_str()));
+      if external.is_none() && spec_repo.is_none() {
+        DepsEntry {
+          name,
+          version,
+          scope: DepScope::Runtime,
+          sources: BTreeSet::from([source.clone()]),
+        }
+        .insert_into(dependencies);
+        continue;
+      }
+
+      let mut direct = self.direct.lock().expect("Direct references lock poisoned");
+      let btree_map::Entry::Vacant(entry) = direct.entry(name) else {
+        continue;
+      };
+      let mut row = empty_row(entry.key(), &version);
+      let name = entry.key();
+      if let Some(local) = external.and_then(|external| external.str(":path")) {
+        self.local_row(&mut row, local, &source, dir);
+      } else if let Some(external) = external {
+        let origin = external
+          .str(":git")
+          .or_else(|| external.str(":podspec"))
+          .unwrap_or_default();
+        // CHECKOUT OPTIONS has the commit `pod install` checked out, EXTERNAL SOURCES what the Podfile asked for
+        let pinned = checkout.and_then(|checkout| checkout.get(name));
+        let reference = [":commit", ":tag", ":branch"]
+          .iter()
+          .find_map(|key| pinned.and_then(|pinned| pinned.str(key)).or_else(|| external.str(key)));
+        let location = match reference {
+          Some(reference) => format!("{} at {}", origin, reference),
+          None => origin.to_owned(),
+        };
+        if external.str(":git").is_some() {
+          row.homepage = origin.trim_end_matches(".git").to_owned();
+        }
+        row.provenance.push(format!("installed from {} ({})", location, source));
+        self.diagnostics.warn(
+          Category::UnknownLicense,
+          format!(
+            "{} in {} is installed from {}, its license isn't looked up",
+            name, source, location
+          ),
+        );
+      } else {
+        let repo = spec_repo.map_or("", String::as_str);
+        row
+          .provenance
+          .push(format!("installed from Specs repo {} ({})", repo, source));
+        self.diagnostics.warn(
+          Category::UnknownLicense,
+          format!(
+            "{} in {} comes from the private Specs repo {}, its license isn't looked up",
+            name, source, repo
+          ),
+        );
+      }
+      entry.insert((source.clone(), row));
+    }
+
+    Ok(())
+  }
+
+  fn local_row(&self, row: &mut ReportRow, path: &str, source: &str, dir: &Path) {
+    let relative = |path: &Path| path.strip_prefix(&self.root_path).unwrap_or(path).display().to_string();
+    let target = match local_path::safe_join(&self.root_path, dir, path) {
+      Ok(target) => target,
+      Err(refusal) => {
+        self.diagnostics.warn(
+          refusal.category(),
+          format!(
+            "{} in {} is installed from {}, which {}",
+            row.name, source, path, refusal
+          ),
+        );
+        row.provenance.push(format!("unresolved local path: {}", path));
+        return;
+      },
+    };
+
+    row.homepage = relative(&target);
+    row.provenance.push(format!("local path: {}", relative(&target)));
+    match license_text::read_license_file(&self.root_path, &target) {
+      Some((file, text)) => match license_text::classify(&text) {
+        Some(license) => {
+          row.license = Some(license.to_owned());
+          row.license_confidence = Some(LicenseConfidence::Detected);
+          row.provenance.push(format!("license file: {}", relative(&file)));
+        },
+        None => self.diagnostics.warn(
+          Category::UnknownLicense,
+          format!(
+            "License file {} of {} is not a license we recognize",
+            relative(&file),
+            row.name
+          ),
+        ),
+      },
+      None => self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("{} at {} has no license file", row.name, relative(&target)),
+      ),
+    }
+  }
+
+  fn source(&self, path: &Path) -> String {
+    match path.strip_prefix(&self.root_path) {
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => path.display().to_string(),
+    }
+  }
+}
+
+// PODS's `- Name (1.2.3)` entries with subspecs folded into their pod, and SPEC REPOS's pods by repo. The
+// dependencies nested under a PODS entry are listed at the top as well.
+fn sequences(content: &str) -> (Vec<(String, String)>, HashMap<String, String>) {
+  let mut pods = Vec::new();
+  let mut spec_repos = HashMap::new();
+  let mut section = "";
+  let mut repo = String::new();
+
+  for line in content.lines() {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+      continue;
+    }
+    let indent = line.len() - line.trim_start().len();
+    if indent == 0 {
+      section = trimmed.trim_end_matches(':');
+      continue;
+    }
+    let item = trimmed
+      .strip_prefix("- ")
+      .map(|item| yaml::unquote(item.trim_end_matches(':')));
+    match (section, indent, item) {
+      ("PODS", 2, Some(item)) => {
+        let Some((name, version)) = item.rsplit_once(" (") else {
+          continue;
+        };
+        let pod = name.split('/').next().unwrap_or(name);
+        let version = version.trim_end_matches(')');
+        if !pods.iter().any(|(known, _): &(String, String)| known == pod) {
+          pods.push((pod.to_owned(), version.to_owned()));
+        }
+      },
+      ("SPEC REPOS", _, None) => repo = yaml::unquote(trimmed.trim_end_matches(':')),
+      ("SPEC REPOS", _, Some(item)) => {
+        spec_repos.insert(item, repo.clone());
+      },
+      _ => {},
+    }
+  }
+
+  (pods, spec_repos)
+}
+
+fn empty_row(name: &str, version: &str) -> ReportRow {
+  ReportRow {
+    ecosystem: Ecosystem::Cocoapods,
+    name: name.to_owned(),
+    version: version.to_owned(),
+    scope: DepScope::Runtime,
+    homepage: format!("https://cocoapods.org/pods/{}", name),
+    license: None,
+    license_confidence: None,
+    license_url: None,
+    funding: None,
+    platform: None,
+    repo_mismatch: None,
+    installed_mismatch: None,
+    third_party_notices: None,
+    link_status: None,
+    license_text_key: None,
+    attestation: None,
+    osi_approved: None,
+    fsf_libre: None,
+    size: None,
+    provenance: Vec::new(),
+  }
+}
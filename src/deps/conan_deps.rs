@@ -0,0 +1,229 @@
+use super::{manifests::ManifestFilter, patterns::PatternSet};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry},
+};
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::{
+  collections::{BTreeSet, HashMap},
+  fs,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+};
+use walkdir::WalkDir;
+
+static CONANFILE_TXT: &str = "conanfile.txt";
+static CONANFILE_PY: &str = "conanfile.py";
+// The one conanfile.txt section listing what the project links; tool_requires and test_requires are build tools
+const REQUIRES_SECTION: &str = "[requires]";
+
+lazy_static! {
+  // `requires = "zlib/1.3.1"`, or a list or tuple of references, as a class attribute. Quoted strings are
+  // skipped whole, version ranges have brackets of their own.
+  static ref REQUIRES_ATTRIBUTE: Regex = Regex::new(
+    r#"(?m)^\s*requires\s*=\s*(\[(?:[^\]"']|"[^"]*"|'[^']*')*\]|\((?:[^)"']|"[^"]*"|'[^']*')*\)|"[^"]*"|'[^']*')"#
+  )
+  .expect("Failed to compile requires attribute regex");
+  // `self.requires("zlib/1.3.1")` in a requirements() method; `self.tool_requires(...)` doesn't match
+  static ref REQUIRES_CALL: Regex =
+    Regex::new(r#"\bself\.requires\(\s*(?:"([^"]+)"|'([^']+)')"#).expect("Failed to compile requires call regex");
+  static ref QUOTED: Regex = Regex::new(r#""([^"]+)"|'([^']+)'"#).expect("Failed to compile quoted string regex");
+  // `license = "MIT"` or a tuple of licenses in a recipe, and its `homepage = "..."`
+  static ref RECIPE_LICENSE: Regex = Regex::new(r#"(?m)^\s*license\s*=\s*(\([^)]*\)|"[^"]*"|'[^']*')"#)
+    .expect("Failed to compile recipe license regex");
+  static ref RECIPE_HOMEPAGE: Regex =
+    Regex::new(r#"(?m)^\s*homepage\s*=\s*["']([^"']+)["']"#).expect("Failed to compile recipe homepage regex");
+}
+
+// A `name/version[@user/channel][#revision]` reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConanReference {
+  pub name: String,
+  pub version: String,
+  // `user/channel`, packages a team publishes to its own remote
+  pub channel: Option<String>,
+}
+
+impl ConanReference {
+  pub fn parse(reference: &str) -> Option<Self> {
+    let reference = reference.split('#').next().unwrap_or(reference).trim();
+    let (reference, channel) = match reference.split_once('@') {
+      Some((reference, channel)) => (reference, Some(channel.trim()).filter(|channel| !channel.is_empty())),
+      None => (reference, None),
+    };
+    let (name, version) = reference.split_once('/')?;
+    let (name, version) = (name.trim(), version.trim());
+    if name.is_empty() || version.is_empty() {
+      return None;
+    }
+
+    Some(Self {
+      name: name.to_owned(),
+      version: version.to_owned(),
+      channel: channel.map(str::to_owned),
+    })
+  }
+}
+
+// Conan projects: conanfile.txt's [requires] section and, best effort, the `requires` attribute and
+// `self.requires(...)` calls of a conanfile.py. Version ranges (`[>=1.2 <2]`) are kept as written, the recipe
+// lookup falls back to the recipe's only or latest folder for them.
+pub struct ConanParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+  // user/channel of references that had one, by package name
+  channels: Mutex<HashMap<String, String>>,
+}
+
+impl ConanParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+      channels: Mutex::new(HashMap::new()),
+    })
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies = HashMap::new();
+
+    let entries = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| !entry.file_type().is_dir());
+    for entry in entries {
+      let path = entry.path();
+      if path.to_str().is_some_and(|path| self.exclude_patterns.is_match(path)) {
+        continue;
+      }
+      let name = entry.file_name().to_string_lossy();
+      let references = if name == CONANFILE_TXT && self.manifests.allows(CONANFILE_TXT, path) {
+        // A conanfile.py next to it is the recipe of the same project, the .txt is what `conan install` reads
+        println!("Processing file: {}", path.display());
+        txt_requires(&self.read(path)?)
+      } else if name == CONANFILE_PY
+        && !path.with_file_name(CONANFILE_TXT).is_file()
+        && self.manifests.allows(CONANFILE_PY, path)
+      {
+        println!("Processing file: {}", path.display());
+        py_requires(&self.read(path)?)
+      } else {
+        continue;
+      };
+
+      let source = self.source(path);
+      for reference in references {
+        let Some(parsed) = ConanReference::parse(&reference) else {
+          self.diagnostics.warn(
+            Category::ParseFailure,
+            format!("{}: `{}` is not a name/version reference, skipped", source, reference),
+          );
+          continue;
+        };
+        if let Some(channel) = &parsed.channel {
+          self
+            .channels
+            .lock()
+            .expect("Conan channels lock poisoned")
+            .insert(parsed.name.clone(), channel.clone());
+        }
+        DepsEntry {
+          name: parsed.name,
+          version: parsed.version,
+          scope: DepScope::Runtime,
+          sources: BTreeSet::from([source.clone()]),
+        }
+        .insert_into(&mut dependencies);
+      }
+    }
+
+    Ok(dependencies)
+  }
+
+  // user/channel found by `parse` for each package that had one, noted on its row
+  pub fn channels(&self) -> HashMap<String, String> {
+    self
+      .channels
+      .lock()
+      .map(|mut channels| std::mem::take(&mut *channels))
+      .unwrap_or_default()
+  }
+
+  fn read(&self, path: &Path) -> Result<String> {
+    fs::read_to_string(path).with_context(|| format!("Failed to read conanfile: {}", path.display()))
+  }
+
+  fn source(&self, path: &Path) -> String {
+    match path.strip_prefix(&self.root_path) {
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => path.display().to_string(),
+    }
+  }
+}
+
+fn txt_requires(content: &str) -> Vec<String> {
+  let mut references = Vec::new();
+  let mut in_requires = false;
+  for line in content.lines() {
+    let line = line.split('#').next().unwrap_or_default().trim();
+    if line.starts_with('[') {
+      in_requires = line == REQUIRES_SECTION;
+      continue;
+    }
+    if in_requires && !line.is_empty() {
+      references.push(line.to_owned());
+    }
+  }
+  references
+}
+
+fn py_requires(content: &str) -> Vec<String> {
+  let mut references = Vec::new();
+  for captures in REQUIRES_ATTRIBUTE.captures_iter(content) {
+    references.extend(quoted_strings(&captures[1]));
+  }
+  for captures in REQUIRES_CALL.captures_iter(content) {
+    if let Some(reference) = captures.get(1).or_else(|| captures.get(2)) {
+      references.push(reference.as_str().to_owned());
+    }
+  }
+  references
+}
+
+fn quoted_strings(text: &str) -> Vec<String> {
+  QUOTED
+    .captures_iter(text)
+    .filter_map(|captures| captures.get(1).or_else(|| captures.get(2)))
+    .map(|value| value.as_str().to_owned())
+    .collect()
+}
+
+// The `license` a ConanCenter recipe declares; a tuple lists licenses that all apply to the package
+pub fn recipe_license(recipe: &str) -> Option<String> {
+  let captures = RECIPE_LICENSE.captures(recipe)?;
+  let licenses = quoted_strings(&captures[1]);
+  match licenses.len() {
+    0 => None,
+    1 => licenses.into_iter().next(),
+    _ => Some(licenses.join(" AND ")),
+  }
+}
+
+pub fn recipe_homepage(recipe: &str) -> Option<String> {
+  Some(RECIPE_HOMEPAGE.captures(recipe)?[1].to_owned())
+}
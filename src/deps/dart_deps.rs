@@ -0,0 +1,350 @@
+use super::{license_text, local_path, manifests::ManifestFilter, patterns::PatternSet, yaml::Node};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::{Context, Result};
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap},
+  fs,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+};
+use walkdir::WalkDir;
+
+static MANIFEST: &str = "pubspec.yaml";
+static LOCKFILE: &str = "pubspec.lock";
+// Hosted packages from these are resolved from pub.dev's API
+const PUB_DEV_HOSTS: &[&str] = &["https://pub.dev", "https://pub.dartlang.org"];
+
+// Where a dependency comes from, as pubspec.lock's `source` or the pubspec.yaml entry says
+enum PubSource {
+  // The package repository at this URL
+  Hosted(String),
+  Git { url: String, reference: Option<String> },
+  Path(String),
+  // `flutter`, `flutter_test` and the like come with the SDK
+  Sdk,
+}
+
+// Dart and Flutter packages: pubspec.lock's exact versions, or the `dependencies` constraints of a pubspec.yaml
+// without a lock file, resolved against pub.dev. SDK packages aren't listed, dev dependencies are left out like
+// npm's devDependencies (the lock doesn't say which transitive packages only they pull in). Workspace members
+// are covered by the workspace root's lock. Git and path packages are listed with where they come from, like gems.
+pub struct DartParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+  // Each with the manifest it was found in
+  direct: Mutex<BTreeMap<String, (String, ReportRow)>>,
+  // Package -> the repository serving it, for those not on pub.dev
+  hosts: Mutex<HashMap<String, String>>,
+}
+
+impl DartParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+      direct: Mutex::new(BTreeMap::new()),
+      hosts: Mutex::new(HashMap::new()),
+    })
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies = HashMap::new();
+
+    let entries = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| !entry.file_type().is_dir());
+    for entry in entries {
+      let path = entry.path();
+      if path.to_str().is_some_and(|path| self.exclude_patterns.is_match(path)) {
+        continue;
+      }
+      let name = entry.file_name().to_string_lossy();
+      if name == LOCKFILE && self.manifests.allows(LOCKFILE, path) {
+        println!("Processing file: {}", path.display());
+        self.parse_lockfile(path, &mut dependencies)?;
+      } else if name == MANIFEST && !path.with_file_name(LOCKFILE).is_file() && self.manifests.allows(MANIFEST, path) {
+        println!("Processing file: {}", path.display());
+        self.parse_manifest(path, &mut dependencies)?;
+      }
+    }
+
+    Ok(dependencies)
+  }
+
+  // Git and path packages found by `parse`, reported with their origin instead of a pub.dev lookup
+  pub fn direct_references(&self) -> Vec<(String, ReportRow)> {
+    self
+      .direct
+      .lock()
+      .map(|mut rows| std::mem::take(&mut *rows).into_values().collect())
+      .unwrap_or_default()
+  }
+
+  // Hosted packages served by a repository other than pub.dev, by name
+  pub fn hosted_elsewhere(&self) -> HashMap<String, String> {
+    self
+      .hosts
+      .lock()
+      .map(|mut hosts| std::mem::take(&mut *hosts))
+      .unwrap_or_default()
+  }
+
+  fn parse_lockfile(&self, path: &Path, dependencies: &mut HashMap<String, DepsEntry>) -> Result<()> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read lock file: {}", path.display()))?;
+    let source = self.source(path);
+    let lock = match Node::parse(&content) {
+      Ok(lock) => lock,
+      Err(err) => {
+        self
+          .diagnostics
+          .warn(Category::ParseFailure, format!("Can't parse {}: {:#}", source, err));
+        return Ok(());
+      },
+    };
+    let dir = path.parent().unwrap_or(&self.root_path);
+
+    for (name, package) in lock
+      .get("packages")
+      .map(|packages| packages.entries.as_slice())
+      .unwrap_or_default()
+    {
+      if package.str("dependency") == Some("direct dev") {
+        continue;
+      }
+      let Some(version) = package.str("version") else {
+        continue;
+      };
+      let description = package.get("description");
+      let field = |key: &str| {
+        description
+          .and_then(|description| description.str(key))
+          .map(str::to_owned)
+      };
+      let pub_source = match package.str("source") {
+        Some("hosted") => PubSource::Hosted(field("url").unwrap_or_else(|| PUB_DEV_HOSTS[0].to_owned())),
+        Some("git") => PubSource::Git {
+          url: field("url").unwrap_or_default(),
+          reference: field("resolved-ref").or_else(|| field("ref")),
+        },
+        Some("path") => PubSource::Path(field("path").unwrap_or_default()),
+        _ => PubSource::Sdk,
+      };
+      self.add(name, version, pub_source, &source, dir, dependencies);
+    }
+
+    Ok(())
+  }
+
+  fn parse_manifest(&self, path: &Path, dependencies: &mut HashMap<String, DepsEntry>) -> Result<()> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read manifest: {}", path.display()))?;
+    let source = self.source(path);
+    let manifest = match Node::parse(&content) {
+      Ok(manifest) => manifest,
+      Err(err) => {
+        self
+          .diagnostics
+          .warn(Category::ParseFailure, format!("Can't parse {}: {:#}", source, err));
+        return Ok(());
+      },
+    };
+    if manifest.str("resolution") == Some("workspace") {
+      return Ok(());
+    }
+    let dir = path.parent().unwrap_or(&self.root_path);
+
+    for (name, entry) in manifest
+      .get("dependencies")
+      .map(|deps| deps.entries.as_slice())
+      .unwrap_or_default()
+    {
+      // `http: ^1.1.0`, or a mapping saying where the package comes from
+      let constraint = entry.value.as_deref().or_else(|| entry.str("version")).unwrap_or("any");
+      let constraint = match constraint {
+        "any" => "*",
+        constraint => constraint,
+      };
+      let pub_source = if entry.get("sdk").is_some() {
+        PubSource::Sdk
+      } else if let Some(git) = entry.get("git") {
+        PubSource::Git {
+          url: git
+            .value
+            .clone()
+            .or_else(|| git.str("url").map(str::to_owned))
+            .unwrap_or_default(),
+          reference: git.str("ref").map(str::to_owned),
+        }
+      } else if let Some(path) = entry.str("path") {
+        PubSource::Path(path.to_owned())
+      } else {
+        let hosted = entry.get("hosted");
+        let url = hosted.and_then(|hosted| hosted.value.clone().or_else(|| hosted.str("url").map(str::to_owned)));
+        PubSource::Hosted(url.unwrap_or_else(|| PUB_DEV_HOSTS[0].to_owned()))
+      };
+      self.add(name, constraint, pub_source, &source, dir, dependencies);
+    }
+
+    Ok(())
+  }
+
+  fn add(
+    &self,
+    name: &str,
+    version: &str,
+    pub_source: PubSource,
+    source: &str,
+    dir: &Path,
+    dependencies: &mut HashMap<String, DepsEntry>,
+  ) {
+    match pub_source {
+      PubSource::Hosted(url) => {
+        let url = url.trim_end_matches('/');
+        if !PUB_DEV_HOSTS.contains(&url) {
+          self
+            .hosts
+            .lock()
+            .expect("Hosted packages lock poisoned")
+            .insert(name.to_owned(), url.to_owned());
+        }
+        DepsEntry {
+          name: name.to_owned(),
+          version: version.to_owned(),
+          scope: DepScope::Runtime,
+          sources: BTreeSet::from([source.to_owned()]),
+        }
+        .insert_into(dependencies)
+      },
+      PubSource::Sdk => {},
+      pub_source => {
+        let mut direct = self.direct.lock().expect("Direct references lock poisoned");
+        if !direct.contains_key(name) {
+          if let Some(row) = self.direct_row(name, version, &pub_source, source, dir) {
+            direct.insert(name.to_owned(), (source.to_owned(), row));
+          }
+        }
+      },
+    }
+  }
+
+  // None for a path dependency on the package itself
+  fn direct_row(
+    &self,
+    name: &str,
+    version: &str,
+    pub_source: &PubSource,
+    source: &str,
+    dir: &Path,
+  ) -> Option<ReportRow> {
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Pub,
+      name: name.to_owned(),
+      version: version.to_owned(),
+      scope: DepScope::Runtime,
+      homepage: String::new(),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+
+    let path = match pub_source {
+      PubSource::Git { url, reference } => {
+        let location = match reference {
+          Some(reference) => format!("{} at {}", url, reference),
+          None => url.clone(),
+        };
+        row.homepage = url.trim_end_matches(".git").to_owned();
+        row
+          .provenance
+          .push(format!("installed from git {} ({})", location, source));
+        self.diagnostics.warn(
+          Category::UnknownLicense,
+          format!(
+            "{} in {} is installed from {}, its license isn't looked up",
+            name, source, location
+          ),
+        );
+        return Some(row);
+      },
+      PubSource::Path(path) => path,
+      PubSource::Hosted(_) | PubSource::Sdk => return None,
+    };
+
+    let relative = |path: &Path| path.strip_prefix(&self.root_path).unwrap_or(path).display().to_string();
+    let target = match local_path::safe_join(&self.root_path, dir, path) {
+      Ok(target) => target,
+      Err(refusal) => {
+        self.diagnostics.warn(
+          refusal.category(),
+          format!("{} in {} is installed from {}, which {}", name, source, path, refusal),
+        );
+        row.provenance.push(format!("unresolved local path: {}", path));
+        return Some(row);
+      },
+    };
+    if fs::canonicalize(dir).is_ok_and(|dir| dir == target) {
+      return None;
+    }
+
+    row.homepage = relative(&target);
+    row.provenance.push(format!("local path: {}", relative(&target)));
+    match license_text::read_license_file(&self.root_path, &target) {
+      Some((file, text)) => match license_text::classify(&text) {
+        Some(license) => {
+          row.license = Some(license.to_owned());
+          row.license_confidence = Some(LicenseConfidence::Detected);
+          row.provenance.push(format!("license file: {}", relative(&file)));
+        },
+        None => self.diagnostics.warn(
+          Category::UnknownLicense,
+          format!(
+            "License file {} of {} is not a license we recognize",
+            relative(&file),
+            name
+          ),
+        ),
+      },
+      None => self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("{} at {} has no license file", name, relative(&target)),
+      ),
+    }
+
+    Some(row)
+  }
+
+  fn source(&self, path: &Path) -> String {
+    match path.strip_prefix(&self.root_path) {
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => path.display().to_string(),
+    }
+  }
+}
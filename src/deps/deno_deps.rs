@@ -0,0 +1,346 @@
+use super::{local_path, manifests::ManifestFilter, patterns::PatternSet};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry},
+};
+use anyhow::{Context, Result};
+use std::{
+  collections::{BTreeSet, HashMap},
+  fs,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+};
+use walkdir::WalkDir;
+
+static DENO_JSON: &str = "deno.json";
+static DENO_JSONC: &str = "deno.jsonc";
+static IMPORT_MAP: &str = "import_map.json";
+// `npm:` specifiers without a version take whatever the registry tags latest
+const NPM_LATEST: &str = "latest";
+// URL imports with no version anywhere in the path
+pub const ANY_VERSION: &str = "*";
+
+// What an import map value points at. Relative paths and `node:` builtins aren't dependencies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DenoSpecifier {
+  Npm { name: String, version: String },
+  Jsr { name: String, version: String },
+  // The module URL without the file inside it: `https://deno.land/x/oak`, `https://esm.sh/preact`
+  Url { module: String, version: String },
+}
+
+impl DenoSpecifier {
+  pub fn parse(specifier: &str) -> Option<Self> {
+    let specifier = specifier.trim();
+    if let Some(rest) = specifier.strip_prefix("npm:") {
+      let (name, version) = package_specifier(rest)?;
+      // The same as package.json ranges, see the JS parser
+      let version = version.map_or(NPM_LATEST, |version| version.strip_prefix('^').unwrap_or(version));
+      return Some(Self::Npm {
+        name,
+        version: version.to_owned(),
+      });
+    }
+    if let Some(rest) = specifier.strip_prefix("jsr:") {
+      let (name, version) = package_specifier(rest)?;
+      // JSR only hosts scoped packages
+      if !name.starts_with('@') {
+        return None;
+      }
+      return Some(Self::Jsr {
+        name,
+        version: version.unwrap_or(ANY_VERSION).to_owned(),
+      });
+    }
+    if specifier.starts_with("https://") || specifier.starts_with("http://") {
+      let (module, version) = url_module(specifier)?;
+      return Some(Self::Url { module, version });
+    }
+    None
+  }
+}
+
+// Deno projects: the `imports` (and `scopes`) of deno.json, deno.jsonc and import maps, the one a deno.json's
+// `importMap` points to included. `npm:` imports are npm packages and go with the JS ones; JSR packages and URL
+// modules are what `parse` returns.
+pub struct DenoParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+  npm: Mutex<HashMap<String, DepsEntry>>,
+}
+
+impl DenoParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+      npm: Mutex::new(HashMap::new()),
+    })
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies = HashMap::new();
+    // An import map is read once, whether the walk or a deno.json got to it first
+    let mut read = BTreeSet::new();
+
+    let entries = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| !entry.file_type().is_dir());
+    for entry in entries {
+      let path = entry.path();
+      if path.to_str().is_some_and(|path| self.exclude_patterns.is_match(path)) {
+        continue;
+      }
+      let name = entry.file_name().to_string_lossy();
+      let kind = match name.as_ref() {
+        "deno.json" => DENO_JSON,
+        "deno.jsonc" => DENO_JSONC,
+        "import_map.json" => IMPORT_MAP,
+        _ => continue,
+      };
+      if !self.manifests.allows(kind, path) || !read.insert(path.to_path_buf()) {
+        continue;
+      }
+
+      println!("Processing file: {}", path.display());
+      let Some(config) = self.read(path)? else {
+        continue;
+      };
+      self.collect(path, &config, &mut dependencies);
+
+      let Some(import_map) = config.get("importMap").and_then(serde_json::Value::as_str) else {
+        continue;
+      };
+      if import_map.contains("://") {
+        self.diagnostics.warn(
+          Category::ParseFailure,
+          format!("{}: remote import map {} isn't read", self.source(path), import_map),
+        );
+        continue;
+      }
+      let import_map =
+        match local_path::safe_join(&self.root_path, path.parent().unwrap_or(&self.root_path), import_map) {
+          Ok(import_map) => import_map,
+          Err(refusal) => {
+            let category = match refusal.is_escape() {
+              true => Category::RefusedPath,
+              false => Category::ParseFailure,
+            };
+            self.diagnostics.warn(
+              category,
+              format!("{}: import map {} {}", self.source(path), import_map, refusal),
+            );
+            continue;
+          },
+        };
+      if !self.manifests.allows(IMPORT_MAP, &import_map) || !read.insert(import_map.clone()) {
+        continue;
+      }
+      println!("Processing file: {}", import_map.display());
+      if let Some(map) = self.read(&import_map)? {
+        self.collect(&import_map, &map, &mut dependencies);
+      }
+    }
+
+    Ok(dependencies)
+  }
+
+  // `npm:` imports found by `parse`, to be resolved with the package.json dependencies
+  pub fn npm_imports(&self) -> HashMap<String, DepsEntry> {
+    self
+      .npm
+      .lock()
+      .map(|mut npm| std::mem::take(&mut *npm))
+      .unwrap_or_default()
+  }
+
+  fn read(&self, path: &Path) -> Result<Option<serde_json::Value>> {
+    let content =
+      fs::read_to_string(path).with_context(|| format!("Failed to read Deno config: {}", path.display()))?;
+    match serde_json::from_str(&strip_jsonc(&content)) {
+      Ok(config) => Ok(Some(config)),
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::ParseFailure,
+          format!("Can't parse {}: {}", self.source(path), err),
+        );
+        Ok(None)
+      },
+    }
+  }
+
+  fn collect(&self, path: &Path, config: &serde_json::Value, dependencies: &mut HashMap<String, DepsEntry>) {
+    let source = self.source(path);
+    let imports = config.get("imports").into_iter();
+    let scopes = config
+      .get("scopes")
+      .and_then(serde_json::Value::as_object)
+      .into_iter()
+      .flat_map(|scopes| scopes.values());
+    let specifiers = imports
+      .chain(scopes)
+      .filter_map(serde_json::Value::as_object)
+      .flat_map(|imports| imports.values())
+      .filter_map(serde_json::Value::as_str);
+
+    for specifier in specifiers {
+      let (name, version, npm) = match DenoSpecifier::parse(specifier) {
+        Some(DenoSpecifier::Npm { name, version }) => (name, version, true),
+        Some(DenoSpecifier::Jsr { name, version }) => (name, version, false),
+        Some(DenoSpecifier::Url { module, version }) => (module, version, false),
+        None => continue,
+      };
+      let entry = DepsEntry {
+        name,
+        version,
+        scope: DepScope::Runtime,
+        sources: BTreeSet::from([source.clone()]),
+      };
+      match npm {
+        true => entry.insert_into(&mut self.npm.lock().expect("Deno npm imports lock poisoned")),
+        false => entry.insert_into(dependencies),
+      }
+    }
+  }
+
+  fn source(&self, path: &Path) -> String {
+    match path.strip_prefix(&self.root_path) {
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => path.display().to_string(),
+    }
+  }
+}
+
+// `@scope/name@version/sub/path` or `name@version/sub/path`, a leading slash allowed (`jsr:/@std/path`)
+fn package_specifier(specifier: &str) -> Option<(String, Option<&str>)> {
+  let specifier = specifier.trim_start_matches('/');
+  let (scope, rest) = match specifier.strip_prefix('@') {
+    Some(rest) => {
+      let (scope, rest) = rest.split_once('/')?;
+      (Some(scope), rest)
+    },
+    None => (None, specifier),
+  };
+  let package = rest.split('/').next().unwrap_or(rest);
+  let (package, version) = match package.split_once('@') {
+    Some((package, version)) => (package, Some(version).filter(|version| !version.is_empty())),
+    None => (package, None),
+  };
+  if package.is_empty() || scope.is_some_and(str::is_empty) {
+    return None;
+  }
+
+  let name = match scope {
+    Some(scope) => format!("@{}/{}", scope, package),
+    None => package.to_owned(),
+  };
+  Some((name, version))
+}
+
+// The module a URL import belongs to and its version: the path up to the first `name@version` segment
+// (`deno.land/x/oak@v12.6.1/mod.ts`, `esm.sh/@preact/signals@1.2.0`), a raw GitHub file's repository and ref
+fn url_module(url: &str) -> Option<(String, String)> {
+  let (scheme, rest) = url.split_once("://")?;
+  let rest = rest.split(['?', '#']).next().unwrap_or(rest);
+  let mut segments = rest.split('/').filter(|segment| !segment.is_empty());
+  let host = segments.next()?.to_lowercase();
+  let segments = segments.collect::<Vec<_>>();
+
+  if host == "raw.githubusercontent.com" {
+    if let [owner, repo, reference, ..] = segments[..] {
+      return Some((format!("https://github.com/{}/{}", owner, repo), reference.to_owned()));
+    }
+  }
+
+  let mut module = vec![host.as_str()];
+  for segment in &segments {
+    match segment.split_once('@') {
+      Some((name, version)) if !name.is_empty() => {
+        module.push(name);
+        return Some((format!("{}://{}", scheme, module.join("/")), version.to_owned()));
+      },
+      _ => module.push(segment),
+    }
+  }
+  // deno.land without a version serves the latest one; the module is still only `x/<name>` or `std`
+  if host == "deno.land" {
+    let depth = match segments.first() {
+      Some(&"x") => 3,
+      _ => 2,
+    };
+    module.truncate(depth);
+  }
+  Some((format!("{}://{}", scheme, module.join("/")), ANY_VERSION.to_owned()))
+}
+
+// deno.jsonc allows comments and trailing commas, serde_json takes neither
+pub fn strip_jsonc(content: &str) -> String {
+  let without_comments = skip_strings(content, |current, rest, stripped| match (current, rest.peek()) {
+    ('/', Some('/')) => while rest.next_if(|next| *next != '\n').is_some() {},
+    ('/', Some('*')) => {
+      rest.next();
+      let mut previous = ' ';
+      for next in rest.by_ref() {
+        if previous == '*' && next == '/' {
+          break;
+        }
+        previous = next;
+      }
+    },
+    _ => stripped.push(current),
+  });
+  // A comma is trailing when the next thing that isn't whitespace closes the object or array
+  skip_strings(&without_comments, |current, rest, stripped| {
+    let closes = || matches!(rest.clone().find(|next| !next.is_whitespace()), Some('}' | ']'));
+    if current != ',' || !closes() {
+      stripped.push(current);
+    }
+  })
+}
+
+// Copies string literals as they are and hands everything else to `outside`
+fn skip_strings(
+  content: &str,
+  mut outside: impl FnMut(char, &mut std::iter::Peekable<std::str::Chars>, &mut String),
+) -> String {
+  let mut stripped = String::with_capacity(content.len());
+  let mut rest = content.chars().peekable();
+  let mut in_string = false;
+  while let Some(current) = rest.next() {
+    match (in_string, current) {
+      (true, '\\') => {
+        stripped.push(current);
+        stripped.extend(rest.next());
+      },
+      (true, '"') | (false, '"') => {
+        in_string = !in_string;
+        stripped.push(current);
+      },
+      (true, _) => stripped.push(current),
+      (false, _) => outside(current, &mut rest, &mut stripped),
+    }
+  }
+  stripped
+}
+
+// Where a JSR package or URL module is described: jsr.io, or the module URL itself
+pub fn homepage(name: &str) -> String {
+  match name.starts_with("https://") || name.starts_with("http://") {
+    true => name.to_owned(),
+    false => format!("https://jsr.io/{}", name),
+  }
+}
@@ -0,0 +1,112 @@
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry, Ecosystem},
+};
+use anyhow::{bail, Context, Result};
+use std::{
+  collections::{BTreeSet, HashMap},
+  fs,
+  io::{self, Read},
+};
+
+// --from-list reads the list from standard input
+const STDIN: &str = "-";
+
+// Packages of one ecosystem listed by hand or exported from another system, one per line as `name@version` or
+// `name,version` (further CSV columns are ignored). Blank lines and `#` comments are skipped, a line that is
+// neither form is reported with its number and skipped.
+pub struct DependencyList {
+  source: String,
+  ecosystem: Ecosystem,
+  entries: Vec<(String, String)>,
+}
+
+impl DependencyList {
+  pub fn load(path: &str, ecosystem: &str, diagnostics: &Diagnostics) -> Result<Self> {
+    let ecosystem = match ecosystem {
+      "npm" => Ecosystem::Npm,
+      "go" => Ecosystem::Go,
+      other => bail!("Unsupported --list-ecosystem {}, expected npm or go", other),
+    };
+    let (source, content) = match path {
+      STDIN => {
+        let mut content = String::new();
+        io::stdin()
+          .read_to_string(&mut content)
+          .context("Failed to read the dependency list from stdin")?;
+        ("stdin".to_owned(), content)
+      },
+      path => (
+        path.to_owned(),
+        fs::read_to_string(path).with_context(|| format!("Failed to read dependency list: {}", path))?,
+      ),
+    };
+
+    let mut entries = Vec::new();
+    for (idx, line) in content.lines().enumerate() {
+      let line = line.trim();
+      // Blank lines, comments and a CSV export's header row
+      if line.is_empty() || line.starts_with('#') || (idx == 0 && line.to_lowercase().starts_with("name,")) {
+        continue;
+      }
+      match parse_line(line, ecosystem) {
+        Some(entry) => entries.push(entry),
+        None => diagnostics.warn(
+          Category::ParseFailure,
+          format!(
+            "{}:{}: `{}` is not name@version or name,version, skipped",
+            source,
+            idx + 1,
+            line
+          ),
+        ),
+      }
+    }
+    println!("{} lists {} {} packages", source, entries.len(), ecosystem);
+
+    Ok(Self {
+      source,
+      ecosystem,
+      entries,
+    })
+  }
+
+  // Adds the listed packages to `deps` when they're of its ecosystem, a later line deciding a repeated package's
+  // version
+  pub fn merge_into(&self, ecosystem: Ecosystem, deps: &mut HashMap<String, DepsEntry>) {
+    if ecosystem != self.ecosystem {
+      return;
+    }
+    for (name, version) in &self.entries {
+      DepsEntry {
+        name: name.clone(),
+        version: version.clone(),
+        scope: DepScope::Runtime,
+        sources: BTreeSet::from([self.source.clone()]),
+      }
+      .insert_into(deps);
+    }
+  }
+}
+
+// The last `@` splits so scoped npm packages (`@types/node@20.1.0`) keep theirs
+fn parse_line(line: &str, ecosystem: Ecosystem) -> Option<(String, String)> {
+  let (name, version) = match line.split_once(',') {
+    Some((name, rest)) => (name, rest.split(',').next().unwrap_or_default()),
+    None => line.rsplit_once('@').filter(|(name, _)| !name.is_empty())?,
+  };
+  let unquote = |field: &str| field.trim().trim_matches('"').trim().to_owned();
+  let (name, mut version) = (unquote(name), unquote(version));
+  if name.is_empty()
+    || version.is_empty()
+    || name.contains(char::is_whitespace)
+    || version.contains(char::is_whitespace)
+  {
+    return None;
+  }
+  // Go module versions are `v`-prefixed, other systems tend to drop it
+  if ecosystem == Ecosystem::Go && version.starts_with(|ch: char| ch.is_ascii_digit()) {
+    version.insert(0, 'v');
+  }
+  Some((name, version))
+}
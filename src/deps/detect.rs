@@ -0,0 +1,48 @@
+use std::collections::BTreeMap;
+
+// Manifests of ecosystems without a parser in this build; a leading `*` matches by suffix
+const UNPARSED_MANIFESTS: &[(&str, &str)] = &[("mix.exs", "Elixir")];
+
+// Counts manifests the scan walks past but can't read, so an empty report comes with a reason
+#[derive(Debug, Default)]
+pub struct DetectedManifests {
+  // (file pattern, ecosystem) -> files seen
+  counts: BTreeMap<(&'static str, &'static str), usize>,
+}
+
+impl DetectedManifests {
+  pub fn observe(&mut self, file_name: &str) {
+    let manifest = UNPARSED_MANIFESTS
+      .iter()
+      .find(|(pattern, _)| match pattern.strip_prefix('*') {
+        Some(suffix) => file_name.ends_with(suffix),
+        None => file_name == *pattern,
+      });
+    if let Some(manifest) = manifest {
+      *self.counts.entry(*manifest).or_default() += 1;
+    }
+  }
+
+  pub fn suggestions(&self) -> Vec<String> {
+    self
+      .counts
+      .iter()
+      .map(|((pattern, ecosystem), count)| {
+        format!(
+          "Found {} {} file(s); {} is not supported yet",
+          count, pattern, ecosystem
+        )
+      })
+      .collect()
+  }
+
+  // Compact form for the run info sheet
+  pub fn describe(&self) -> String {
+    self
+      .counts
+      .iter()
+      .map(|((pattern, ecosystem), count)| format!("{} {} ({})", count, pattern, ecosystem))
+      .collect::<Vec<_>>()
+      .join(", ")
+  }
+}
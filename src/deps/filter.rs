@@ -0,0 +1,103 @@
+use crate::types::{DepsEntry, Ecosystem, ReportRow};
+use anyhow::{anyhow, Result};
+use glob::Pattern;
+use regex::Regex;
+use std::collections::HashMap;
+
+enum NameMatcher {
+  Glob(Pattern),
+  // Written as `/regex/`
+  Regex(Regex),
+}
+
+// `--filter` patterns scoping the report to matching package names, e.g. `@aws-sdk/*`, `go:golang.org/x/*` or
+// `npm:/^@types\//`. The scan itself still runs in full, --skip is applied before and wins.
+pub struct PackageFilter {
+  rules: Vec<(Option<Ecosystem>, NameMatcher)>,
+  filters: Vec<String>,
+}
+
+impl PackageFilter {
+  // None without any filter
+  pub fn new(filters: &Option<Vec<String>>) -> Result<Option<Self>> {
+    let Some(filters) = filters.as_ref().filter(|filters| !filters.is_empty()) else {
+      return Ok(None);
+    };
+
+    let mut rules = Vec::new();
+    for filter in filters {
+      let (ecosystem, pattern) = match filter.split_once(':') {
+        Some(("npm", pattern)) => (Some(Ecosystem::Npm), pattern),
+        Some(("go", pattern)) => (Some(Ecosystem::Go), pattern),
+        Some(("cargo", pattern)) => (Some(Ecosystem::Cargo), pattern),
+        Some(("pypi", pattern)) => (Some(Ecosystem::Pypi), pattern),
+        Some(("maven", pattern)) => (Some(Ecosystem::Maven), pattern),
+        Some(("gem", pattern)) => (Some(Ecosystem::Gem), pattern),
+        Some(("composer", pattern)) => (Some(Ecosystem::Composer), pattern),
+        Some(("nuget", pattern)) => (Some(Ecosystem::Nuget), pattern),
+        Some(("pub", pattern)) => (Some(Ecosystem::Pub), pattern),
+        Some(("swift", pattern)) => (Some(Ecosystem::Swift), pattern),
+        Some(("cocoapods", pattern)) => (Some(Ecosystem::Cocoapods), pattern),
+        Some(("conan", pattern)) => (Some(Ecosystem::Conan), pattern),
+        Some(("deno", pattern)) => (Some(Ecosystem::Deno), pattern),
+        Some(("helm", pattern)) => (Some(Ecosystem::Helm), pattern),
+        Some(("terraform", pattern)) => (Some(Ecosystem::Terraform), pattern),
+        Some(("github-actions", pattern)) => (Some(Ecosystem::GithubActions), pattern),
+        _ => (None, filter.as_str()),
+      };
+      let matcher = match pattern.strip_prefix('/').and_then(|pattern| pattern.strip_suffix('/')) {
+        Some(regex) => {
+          NameMatcher::Regex(Regex::new(regex).map_err(|err| anyhow!("--filter `{}`: invalid regex: {}", filter, err))?)
+        },
+        None => NameMatcher::Glob(Pattern::new(pattern).map_err(|err| {
+          anyhow!(
+            "--filter `{}`: invalid glob: {} at position {}",
+            filter,
+            err.msg,
+            err.pos
+          )
+        })?),
+      };
+      rules.push((ecosystem, matcher));
+    }
+
+    Ok(Some(Self {
+      rules,
+      filters: filters.clone(),
+    }))
+  }
+
+  pub fn filters(&self) -> &[String] {
+    &self.filters
+  }
+
+  pub fn matches(&self, ecosystem: Ecosystem, name: &str) -> bool {
+    self.rules.iter().any(|(only, matcher)| {
+      only.is_none_or(|only| only == ecosystem)
+        && match matcher {
+          NameMatcher::Glob(pattern) => pattern.matches(name),
+          NameMatcher::Regex(regex) => regex.is_match(name),
+        }
+    })
+  }
+
+  // Drops the collected dependencies that don't match, printing how many
+  pub fn retain(&self, ecosystem: Ecosystem, deps: &mut HashMap<String, DepsEntry>) {
+    let collected = deps.len();
+    deps.retain(|_, dep| self.matches(ecosystem, &dep.name));
+    if collected > 0 {
+      println!(
+        "--filter kept {} of {} {} dependencies, filtered out {}",
+        deps.len(),
+        collected,
+        ecosystem,
+        collected - deps.len()
+      );
+    }
+  }
+
+  // The same for rows that skip resolution (imported or manual components)
+  pub fn retain_rows(&self, rows: &mut Vec<ReportRow>) {
+    rows.retain(|row| self.matches(row.ecosystem, &row.name));
+  }
+}
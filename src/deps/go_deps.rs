@@ -1,37 +1,97 @@
-use crate::types::DepsEntry;
+use super::{
+  audit,
+  go_work::{self, WorkReplace},
+  license_text, local_path,
+  manifests::ManifestFilter,
+  patterns::{NamespaceSet, PatternSet},
+};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  graph::DependencyGraph,
+  types::{DepScope, DepsEntry, Ecosystem, LicenseConfidence, ReportRow, SelfAuditFinding},
+};
 use anyhow::anyhow;
 use anyhow::{Context as AnyhowContext, Result};
-use gomod_rs::{parse_gomod, Context, Directive};
-use regex::Regex;
-use std::{collections::HashMap, fs, path::PathBuf};
+use gomod_rs::{parse_gomod, Context, Directive, Replacement};
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+  fs,
+  path::{Path, PathBuf},
+  process::Command,
+  sync::Arc,
+};
 use walkdir::{DirEntry, WalkDir};
 
 static GO_MOD_FILE: &str = "go.mod";
+static GO_WORK_FILE: &str = "go.work";
+
+// A `replace` pointing at a directory instead of another module
+struct LocalReplace {
+  module: String,
+  version: String,
+  // As written in the go.mod, relative to `from`
+  target: String,
+  // Directory of the go.mod
+  from: PathBuf,
+  // The go.mod or go.work, relative to the scanned root
+  manifest: String,
+}
+
+// The modules a go.work puts in its workspace are built from their directories; its replaces apply to all of them
+struct Workspace {
+  // Module path by the member's canonical directory
+  members: HashMap<PathBuf, String>,
+  replaces: Vec<LocalReplace>,
+}
+
+impl Workspace {
+  fn contains(&self, dir: &Path) -> bool {
+    fs::canonicalize(dir).is_ok_and(|dir| self.members.contains_key(&dir))
+  }
+}
 
 pub struct GoParser {
   root_path: PathBuf,
-  exclude_patterns: Vec<Regex>,
+  exclude_patterns: Arc<PatternSet>,
+  skip_namespaces: Arc<NamespaceSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
 }
 
 impl GoParser {
-  pub fn new(directory: &str, exclude: &Option<Vec<String>>) -> Result<Self> {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
     let root_path =
       std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
 
     Ok(Self {
       root_path,
-      exclude_patterns: Self::compile_patterns(exclude)?,
+      exclude_patterns: exclude,
+      skip_namespaces: Arc::default(),
+      manifests,
+      diagnostics,
     })
   }
 
+  // Modules under a --skip-prefix (or --skip-scope, though Go paths never start with `@`) are left out
+  pub fn skip_namespaces(mut self, namespaces: Arc<NamespaceSet>) -> Self {
+    self.skip_namespaces = namespaces;
+    self
+  }
+
   pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
     let mut dependencies = HashMap::new();
+    let workspaces = self.workspaces()?;
 
     let go_mod_files = WalkDir::new(&self.root_path)
       .follow_links(true)
       .into_iter()
       .filter_map(Result::ok)
-      .filter(|entry| self.is_valid_go_mod(entry));
+      .filter(|entry| self.is_valid_manifest(entry, GO_MOD_FILE));
 
     for entry in go_mod_files {
       let path = entry.path();
@@ -40,30 +100,361 @@ impl GoParser {
       let go_mod_content =
         fs::read_to_string(path).with_context(|| format!("Failed to read go.mod file: {}", path.display()))?;
       let parsed_mod = parse_gomod(&go_mod_content).context("Failed to parse go.mod file")?;
-      self.extract_dependencies(parsed_mod, &mut dependencies);
+      let source = match path.strip_prefix(&self.root_path) {
+        Ok(relative) => relative.display().to_string(),
+        Err(_) => path.display().to_string(),
+      };
+      // Reported by `local_replacements` from the directory instead of the module proxy, and so are the modules
+      // replaced by the module's workspace. Its other members are first-party.
+      let dir = path.parent().unwrap_or(&self.root_path);
+      let mut local = local_replaces(&parsed_mod, dir, &source)
+        .into_iter()
+        .map(|replace| replace.module)
+        .collect::<HashSet<_>>();
+      for workspace in workspaces.iter().filter(|workspace| workspace.contains(dir)) {
+        local.extend(workspace.members.values().cloned());
+        local.extend(workspace.replaces.iter().map(|replace| replace.module.clone()));
+      }
+      self.extract_dependencies(parsed_mod, &source, &local, &mut dependencies);
     }
 
+    merge_case_duplicates(&mut dependencies, &self.diagnostics);
     Ok(dependencies)
   }
 
-  fn is_valid_go_mod(&self, entry: &DirEntry) -> bool {
+  pub fn collect_graph(&self, graph: &mut DependencyGraph, use_go_tool: bool) -> Result<()> {
+    let go_mod_files = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| self.is_valid_manifest(entry, GO_MOD_FILE));
+
+    for entry in go_mod_files {
+      let path = entry.path();
+
+      if use_go_tool {
+        match self.run_go_mod_graph(path) {
+          Ok(output) => {
+            Self::parse_go_mod_graph(&output, graph);
+            continue;
+          },
+          Err(err) => self.diagnostics.warn(
+            Category::ScanIssue,
+            format!("Can't run `go mod graph` for {}: {:#}", path.display(), err),
+          ),
+        }
+      }
+
+      let go_mod_content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read go.mod file: {}", path.display()))?;
+      let parsed_mod = parse_gomod(&go_mod_content).context("Failed to parse go.mod file")?;
+
+      let module = parsed_mod.iter().find_map(|context| match context.value {
+        Directive::Module { module_path } => Some(module_path),
+        _ => None,
+      });
+      let Some(module) = module else {
+        continue;
+      };
+
+      for context in &parsed_mod {
+        if let Directive::Require { specs } = &context.value {
+          for spec in specs.iter().filter(|spec| !self.skip_namespaces.is_match(spec.value.0)) {
+            graph.add_edge(module, spec.value.0);
+          }
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  // Modules replaced by a local directory. Inside the scanned root they are forks, whose license is classified from
+  // their license file as the upstream license still applies; outside it they can't be resolved at all. Each comes
+  // with the go.mod or go.work replacing it.
+  pub fn local_replacements(&self) -> Result<Vec<(String, ReportRow)>> {
+    let mut rows = BTreeMap::new();
+    // A go.work's replaces override the ones in its members' go.mod files, so they come first
+    let mut replaces = self
+      .workspaces()?
+      .into_iter()
+      .flat_map(|workspace| workspace.replaces)
+      .collect::<Vec<_>>();
+
+    let go_mod_files = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| self.is_valid_manifest(entry, GO_MOD_FILE));
+
+    for entry in go_mod_files {
+      let path = entry.path();
+      let go_mod_content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read go.mod file: {}", path.display()))?;
+      let parsed_mod = parse_gomod(&go_mod_content).context("Failed to parse go.mod file")?;
+
+      let source = path.strip_prefix(&self.root_path).unwrap_or(path).display().to_string();
+      replaces.extend(local_replaces(
+        &parsed_mod,
+        path.parent().unwrap_or(&self.root_path),
+        &source,
+      ));
+    }
+
+    for replace in replaces {
+      if self.skip_namespaces.is_match(&replace.module) {
+        continue;
+      }
+      if !rows.contains_key(&replace.module) {
+        let manifest = replace.manifest.clone();
+        rows.insert(replace.module.clone(), (manifest, self.local_replacement_row(replace)));
+      }
+    }
+
+    Ok(rows.into_values().collect())
+  }
+
+  // Workspaces of the go.work files the go.work rules let through. Without one each module stands alone, and
+  // requires on its sibling modules are fetched like any other.
+  fn workspaces(&self) -> Result<Vec<Workspace>> {
+    let mut workspaces = Vec::new();
+
+    let go_work_files = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| self.is_valid_manifest(entry, GO_WORK_FILE));
+
+    for entry in go_work_files {
+      let path = entry.path();
+      let dir = path.parent().unwrap_or(&self.root_path);
+      let source = path.strip_prefix(&self.root_path).unwrap_or(path).display().to_string();
+      let go_work_content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read go.work file: {}", path.display()))?;
+      let work = go_work::parse_go_work(&go_work_content)
+        .with_context(|| format!("Failed to parse go.work file: {}", path.display()))?;
+
+      let mut members = HashMap::new();
+      let mut required = HashMap::new();
+      for used in &work.uses {
+        let member = match local_path::safe_join(&self.root_path, dir, used) {
+          Ok(member) => member,
+          Err(refusal) => {
+            self.diagnostics.warn(
+              refusal.category(),
+              format!(
+                "{} uses {}, which {}; requires on that module are resolved like any other",
+                source, used, refusal
+              ),
+            );
+            continue;
+          },
+        };
+        let Ok(go_mod_content) = fs::read_to_string(member.join(GO_MOD_FILE)) else {
+          self.diagnostics.warn(
+            Category::ScanIssue,
+            format!("{} uses {}, which has no go.mod", source, used),
+          );
+          continue;
+        };
+        let parsed_mod = parse_gomod(&go_mod_content)
+          .with_context(|| format!("Failed to parse go.mod file of {} used by {}", used, source))?;
+
+        for context in &parsed_mod {
+          match &context.value {
+            Directive::Module { module_path } => {
+              members.insert(member.clone(), module_path.to_string());
+            },
+            Directive::Require { specs } => {
+              for spec in specs {
+                required
+                  .entry(spec.value.0.to_owned())
+                  .or_insert_with(|| spec.value.1.to_string());
+              }
+            },
+            _ => {},
+          }
+        }
+      }
+
+      // Replaces by another module are left to the module proxy, as in a go.mod
+      let replaces = work
+        .replaces
+        .into_iter()
+        .filter(WorkReplace::is_local)
+        .map(|replace| LocalReplace {
+          version: replace
+            .version
+            .or_else(|| required.get(&replace.module).cloned())
+            .unwrap_or_else(|| "local".to_owned()),
+          module: replace.module,
+          target: replace.target,
+          from: dir.to_path_buf(),
+          manifest: source.clone(),
+        })
+        .collect();
+      workspaces.push(Workspace { members, replaces });
+    }
+
+    Ok(workspaces)
+  }
+
+  fn local_replacement_row(&self, replace: LocalReplace) -> ReportRow {
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Go,
+      homepage: format!("https://pkg.go.dev/{}", replace.module),
+      name: replace.module,
+      version: replace.version,
+      scope: DepScope::Runtime,
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+
+    let fork = match local_path::safe_join(&self.root_path, &replace.from, &replace.target) {
+      Ok(dir) => dir,
+      Err(refusal) => {
+        self.diagnostics.warn(
+          refusal.category(),
+          format!(
+            "{} is replaced by {}, which {}; reported as an unresolved local replacement",
+            row.name, replace.target, refusal
+          ),
+        );
+        row
+          .provenance
+          .push(format!("unresolved local replacement: {} {}", replace.target, refusal));
+        return row;
+      },
+    };
+
+    let relative = |path: &Path| path.strip_prefix(&self.root_path).unwrap_or(path).display().to_string();
+    row
+      .provenance
+      .push(format!("forked locally: replaced by {}", relative(&fork)));
+    match license_text::read_license_file(&self.root_path, &fork) {
+      Some((file, text)) => match license_text::classify(&text) {
+        Some(license) => {
+          row.license = Some(license.to_owned());
+          row.license_confidence = Some(LicenseConfidence::Detected);
+          row.provenance.push(format!("license file: {}", relative(&file)));
+        },
+        None => {
+          self.diagnostics.warn(
+            Category::UnknownLicense,
+            format!(
+              "License file {} of the local fork of {} is not a license we recognize",
+              relative(&file),
+              row.name
+            ),
+          );
+          row
+            .provenance
+            .push(format!("license file {} not recognized", relative(&file)));
+        },
+      },
+      None => row.provenance.push("no license file in the fork".to_owned()),
+    }
+    row
+  }
+
+  // Checks that each of the scanned tree's own modules ships a license file
+  pub fn self_audit(&self) -> Result<Vec<SelfAuditFinding>> {
+    let mut findings = Vec::new();
+
+    let go_mod_files = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| self.is_valid_manifest(entry, GO_MOD_FILE));
+
+    for entry in go_mod_files {
+      let path = entry.path();
+      let go_mod_content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read go.mod file: {}", path.display()))?;
+      let parsed_mod = parse_gomod(&go_mod_content).context("Failed to parse go.mod file")?;
+
+      let dir = path.parent().unwrap_or(&self.root_path);
+      let manifest = match path.strip_prefix(&self.root_path) {
+        Ok(relative) => relative.display().to_string(),
+        Err(_) => path.display().to_string(),
+      };
+      let module = parsed_mod
+        .iter()
+        .find_map(|context| match context.value {
+          Directive::Module { module_path } => Some(module_path),
+          _ => None,
+        })
+        .unwrap_or(&manifest);
+
+      findings.extend(audit::audit_go_mod(&manifest, module, dir));
+    }
+
+    Ok(findings)
+  }
+
+  fn run_go_mod_graph(&self, go_mod_path: &Path) -> Result<String> {
+    let dir = go_mod_path.parent().unwrap_or(&self.root_path);
+    let output = Command::new("go")
+      .args(["mod", "graph"])
+      .current_dir(dir)
+      .output()
+      .context("Failed to start go binary")?;
+
+    if !output.status.success() {
+      return Err(anyhow!(String::from_utf8_lossy(&output.stderr).trim().to_owned()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+  }
+
+  // Each line is "parent[@version] child@version"
+  fn parse_go_mod_graph(output: &str, graph: &mut DependencyGraph) {
+    let strip_version = |node: &str| node.split_once('@').map_or(node, |(name, _)| name).to_owned();
+
+    for line in output.lines() {
+      if let Some((parent, child)) = line.split_once(' ') {
+        graph.add_edge(&strip_version(parent), &strip_version(child.trim()));
+      }
+    }
+  }
+
+  fn is_valid_manifest(&self, entry: &DirEntry, kind: &'static str) -> bool {
     // Skip directories and hidden files
-    if entry.file_type().is_dir() || entry.file_name().to_str().map_or(false, |s| s.starts_with('.')) {
+    if entry.file_type().is_dir() || entry.file_name().to_str().is_some_and(|s| s.starts_with('.')) {
       return false;
     }
 
     // Skip excluded paths
     if let Some(path_str) = entry.path().to_str() {
-      if self.exclude_patterns.iter().any(|pattern| pattern.is_match(path_str)) {
+      if self.exclude_patterns.is_match(path_str) {
         return false;
       }
     }
 
-    // Check if it's a go.mod file
-    entry.file_name().to_str().map_or(false, |s| s == GO_MOD_FILE)
+    // Check if it's a file of that kind its rules let through
+    entry.file_name().to_str() == Some(kind) && self.manifests.allows(kind, entry.path())
   }
 
-  fn extract_dependencies(&self, go_mod: Vec<Context<Directive>>, dependencies: &mut HashMap<String, DepsEntry>) {
+  fn extract_dependencies(
+    &self,
+    go_mod: Vec<Context<Directive>>,
+    source: &str,
+    replaced: &HashSet<String>,
+    dependencies: &mut HashMap<String, DepsEntry>,
+  ) {
     for context in go_mod {
       if let Context {
         value: Directive::Require { specs },
@@ -73,26 +464,108 @@ impl GoParser {
         for spec in specs {
           let version: &str = &spec.value.1;
           let name = spec.value.0;
+          if replaced.contains(name) {
+            continue;
+          }
+          if self.skip_namespaces.is_match(name) {
+            println!("Skipping dependency: {}", name);
+            continue;
+          }
 
-          dependencies.insert(
-            name.to_string(),
-            DepsEntry {
-              name: name.to_string(),
-              version: version.to_string(),
-            },
-          );
+          DepsEntry {
+            name: name.to_string(),
+            version: version.to_string(),
+            scope: DepScope::Runtime,
+            sources: BTreeSet::from([source.to_owned()]),
+          }
+          .insert_into(dependencies);
         }
       }
     }
   }
+}
 
-  fn compile_patterns(patterns: &Option<Vec<String>>) -> Result<Vec<Regex>> {
-    match patterns {
-      Some(patterns) => patterns
-        .iter()
-        .map(|p| Regex::new(p).map_err(|_| anyhow!("Failed to compile regex pattern")))
-        .collect(),
-      None => Ok(vec![]),
-    }
+// Replaces with a `./fork` or `/abs/fork` target, resolved against the go.mod's directory. The version is the
+// replaced or required one, a fork that neither pins is just "local".
+fn local_replaces(go_mod: &[Context<Directive>], dir: &Path, source: &str) -> Vec<LocalReplace> {
+  let required = go_mod
+    .iter()
+    .filter_map(|context| match &context.value {
+      Directive::Require { specs } => Some(specs),
+      _ => None,
+    })
+    .flatten()
+    .map(|spec| (spec.value.0, spec.value.1.to_string()))
+    .collect::<HashMap<_, _>>();
+
+  go_mod
+    .iter()
+    .filter_map(|context| match &context.value {
+      Directive::Replace { specs } => Some(specs),
+      _ => None,
+    })
+    .flatten()
+    .filter_map(|spec| {
+      let Replacement::FilePath(target) = &spec.value.replacement else {
+        return None;
+      };
+      let version = spec
+        .value
+        .version
+        .as_ref()
+        .map(|version| version.to_string())
+        .or_else(|| required.get(spec.value.module_path).cloned())
+        .unwrap_or_else(|| "local".to_owned());
+      Some(LocalReplace {
+        module: spec.value.module_path.to_owned(),
+        version,
+        target: target.to_string(),
+        from: dir.to_path_buf(),
+        manifest: source.to_owned(),
+      })
+    })
+    .collect()
+}
+
+// Hosts like GitHub ignore case, so `github.com/Sirupsen/logrus` and `github.com/sirupsen/logrus` in different
+// go.mod files are one module. Spellings that only differ by case collapse into the lowercase path.
+pub fn merge_case_duplicates(dependencies: &mut HashMap<String, DepsEntry>, diagnostics: &Diagnostics) {
+  let mut spellings: HashMap<String, Vec<String>> = HashMap::new();
+  for name in dependencies.keys() {
+    spellings.entry(name.to_lowercase()).or_default().push(name.clone());
+  }
+
+  for (lowercase, mut names) in spellings.into_iter().filter(|(_, names)| names.len() > 1) {
+    names.sort();
+    diagnostics.warn(
+      Category::ScanIssue,
+      format!(
+        "Module path differs by case across go.mod files: {}; reporting it once as {}, fix the stale require",
+        names.join(", "),
+        lowercase
+      ),
+    );
+
+    // Prefer the version required under the lowercase spelling, it's the one newer go.mod files use
+    let entries = names
+      .iter()
+      .filter_map(|name| dependencies.remove(name))
+      .collect::<Vec<_>>();
+    let version = entries
+      .iter()
+      .find(|entry| entry.name == lowercase)
+      .or(entries.last())
+      .map(|entry| entry.version.clone())
+      .unwrap_or_default();
+    let sources = entries.into_iter().flat_map(|entry| entry.sources).collect();
+    dependencies.insert(
+      lowercase.clone(),
+      DepsEntry {
+        name: lowercase,
+        version,
+        scope: DepScope::Runtime,
+        sources,
+      },
+    );
   }
 }
@@ -0,0 +1,115 @@
+use anyhow::{bail, Result};
+
+// A `replace` line of a go.work, the same as in a go.mod
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkReplace {
+  pub module: String,
+  pub version: Option<String>,
+  // A directory as written, or a module path
+  pub target: String,
+}
+
+impl WorkReplace {
+  // Go takes a target starting with ./, ../ or / as a directory, anything else as another module
+  pub fn is_local(&self) -> bool {
+    self.target.starts_with("./") || self.target.starts_with("../") || self.target.starts_with('/')
+  }
+}
+
+// What depsfetch needs from a go.work: the module directories it puts in the workspace and its replaces. `go`,
+// `toolchain` and `godebug` lines don't change which modules get fetched and are skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GoWork {
+  pub uses: Vec<String>,
+  pub replaces: Vec<WorkReplace>,
+}
+
+// Both `use ./svc` and a `use ( ... )` block, one directive or entry per line, `//` comments anywhere
+pub fn parse_go_work(content: &str) -> Result<GoWork> {
+  let mut work = GoWork::default();
+  let mut block: Option<String> = None;
+
+  for (index, line) in content.lines().enumerate() {
+    let line = line.split_once("//").map_or(line, |(line, _)| line).trim();
+    if line.is_empty() {
+      continue;
+    }
+
+    let (directive, args) = match &block {
+      Some(_) if line == ")" => {
+        block = None;
+        continue;
+      },
+      Some(directive) => (directive.clone(), line),
+      None => {
+        let (directive, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let args = args.trim();
+        if args == "(" {
+          block = Some(directive.to_owned());
+          continue;
+        }
+        (directive.to_owned(), args)
+      },
+    };
+
+    match directive.as_str() {
+      "use" => match fields(args)[..] {
+        [dir] => work.uses.push(dir.to_owned()),
+        _ => bail!("line {}: expected `use DIR`, got `{}`", index + 1, line),
+      },
+      "replace" => {
+        let Some((from, to)) = args.split_once("=>") else {
+          bail!(
+            "line {}: expected `replace MODULE => TARGET`, got `{}`",
+            index + 1,
+            line
+          );
+        };
+        let (from, to) = (fields(from), fields(to));
+        match (&from[..], &to[..]) {
+          ([module, version @ ..], [target, ..]) if version.len() <= 1 && to.len() <= 2 => {
+            work.replaces.push(WorkReplace {
+              module: module.to_string(),
+              version: version.first().map(|version| version.to_string()),
+              target: target.to_string(),
+            })
+          },
+          _ => bail!(
+            "line {}: expected `replace MODULE => TARGET`, got `{}`",
+            index + 1,
+            line
+          ),
+        }
+      },
+      _ => {},
+    }
+  }
+
+  if let Some(directive) = block {
+    bail!("unclosed `{} (` block", directive);
+  }
+  Ok(work)
+}
+
+// Whitespace separated, a double-quoted field may hold spaces
+fn fields(args: &str) -> Vec<&str> {
+  let mut fields = Vec::new();
+  let mut rest = args.trim_start();
+  while !rest.is_empty() {
+    let end = match rest.strip_prefix('"') {
+      Some(quoted) => quoted.find('"').map_or(rest.len(), |end| end + 2),
+      None => rest.find(char::is_whitespace).unwrap_or(rest.len()),
+    };
+    let (field, tail) = rest.split_at(end);
+    fields.push(unquote(field));
+    rest = tail.trim_start();
+  }
+  fields
+}
+
+fn unquote(value: &str) -> &str {
+  value
+    .strip_prefix('"')
+    .and_then(|value| value.strip_suffix('"'))
+    .unwrap_or(value)
+}
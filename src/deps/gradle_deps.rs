@@ -0,0 +1,366 @@
+use super::{manifests::ManifestFilter, maven_deps, patterns::PatternSet};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry},
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap},
+  fs,
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+use walkdir::WalkDir;
+
+static LOCKFILE: &str = "gradle.lockfile";
+static CATALOG_FILE: &str = "libs.versions.toml";
+// Per-configuration lock files of Gradle before 6.4: `gradle/dependency-locks/<configuration>.lockfile`
+static LEGACY_LOCKS_DIR: &str = "dependency-locks";
+static BUILD_SCRIPTS: &[&str] = &["build.gradle", "build.gradle.kts"];
+
+#[derive(Default, Deserialize)]
+struct VersionCatalog {
+  #[serde(default)]
+  versions: BTreeMap<String, CatalogVersion>,
+  #[serde(default)]
+  libraries: BTreeMap<String, CatalogLibrary>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CatalogVersion {
+  Plain(String),
+  Rich(RichVersion),
+}
+
+#[derive(Deserialize)]
+struct RichVersion {
+  strictly: Option<String>,
+  require: Option<String>,
+  prefer: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CatalogLibrary {
+  // `"group:artifact:version"`
+  Notation(String),
+  Table(LibraryTable),
+}
+
+#[derive(Deserialize)]
+struct LibraryTable {
+  module: Option<String>,
+  group: Option<String>,
+  name: Option<String>,
+  version: Option<LibraryVersion>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LibraryVersion {
+  Plain(String),
+  Ref {
+    #[serde(rename = "ref")]
+    reference: String,
+  },
+  Rich(RichVersion),
+}
+
+impl RichVersion {
+  fn version(&self) -> Option<&str> {
+    self
+      .strictly
+      .as_deref()
+      .or(self.require.as_deref())
+      .or(self.prefer.as_deref())
+  }
+}
+
+// What a walk of the tree found, the lock files deciding which catalogs are read
+#[derive(Default)]
+struct GradleFiles {
+  // Path and, for a legacy lock file, the configuration its name gives
+  lockfiles: Vec<(PathBuf, Option<String>)>,
+  catalogs: Vec<PathBuf>,
+  build_scripts: Vec<PathBuf>,
+}
+
+// Gradle builds, keyed by `group:artifact` like Maven artifacts. Lock files give the exact versions; a version
+// catalog is the fallback for builds without any, and build scripts themselves are never read. Dependencies only
+// test configurations resolve are listed on request.
+pub struct GradleParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+  include_test: bool,
+}
+
+impl GradleParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+      include_test: false,
+    })
+  }
+
+  // List dependencies locked for test configurations only too, with the dev scope
+  pub fn include_test_configurations(mut self, include: bool) -> Self {
+    self.include_test = include;
+    self
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies = HashMap::new();
+    let files = self.find_files();
+
+    for (path, configuration) in &files.lockfiles {
+      println!("Processing file: {}", path.display());
+      self.parse_lockfile(path, configuration.as_deref(), &mut dependencies)?;
+    }
+
+    for path in &files.catalogs {
+      // `<build>/gradle/libs.versions.toml`
+      let build_root = path.parent().and_then(Path::parent).unwrap_or(&self.root_path);
+      if files
+        .lockfiles
+        .iter()
+        .any(|(lockfile, _)| lockfile.starts_with(build_root))
+      {
+        continue;
+      }
+      println!("Processing file: {}", path.display());
+      self.parse_catalog(path, &mut dependencies)?;
+    }
+
+    for script in &files.build_scripts {
+      let dir = script.parent().unwrap_or(&self.root_path);
+      let locked = files.lockfiles.iter().any(|(lockfile, _)| {
+        lockfile.parent() == Some(dir) || lockfile.starts_with(dir.join("gradle").join(LEGACY_LOCKS_DIR))
+      });
+      let cataloged = files.catalogs.iter().any(|catalog| {
+        catalog
+          .parent()
+          .and_then(Path::parent)
+          .is_some_and(|build_root| dir.starts_with(build_root))
+      });
+      if !locked && !cataloged {
+        self.diagnostics.warn(
+          Category::ScanIssue,
+          format!(
+            "{} has neither a {} nor a version catalog, its dependencies aren't listed; write one with `gradle \
+             dependencies --write-locks`",
+            self.source(script),
+            LOCKFILE
+          ),
+        );
+      }
+    }
+
+    Ok(dependencies)
+  }
+
+  fn find_files(&self) -> GradleFiles {
+    let mut files = GradleFiles::default();
+    let entries = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| !entry.file_type().is_dir());
+    for entry in entries {
+      let path = entry.path();
+      if path.to_str().is_some_and(|path| self.exclude_patterns.is_match(path)) {
+        continue;
+      }
+      let Some(name) = entry.file_name().to_str() else {
+        continue;
+      };
+      let dir_name = path.parent().and_then(Path::file_name).and_then(|name| name.to_str());
+
+      if name == LOCKFILE {
+        if self.manifests.allows(LOCKFILE, path) {
+          files.lockfiles.push((path.to_path_buf(), None));
+        }
+      } else if let Some(configuration) = name
+        .strip_suffix(".lockfile")
+        .filter(|_| dir_name == Some(LEGACY_LOCKS_DIR))
+      {
+        if self.manifests.allows(LOCKFILE, path) {
+          files
+            .lockfiles
+            .push((path.to_path_buf(), Some(configuration.to_owned())));
+        }
+      } else if name.ends_with(".versions.toml") && dir_name == Some("gradle") {
+        if self.manifests.allows(CATALOG_FILE, path) {
+          files.catalogs.push(path.to_path_buf());
+        }
+      } else if BUILD_SCRIPTS.contains(&name) {
+        files.build_scripts.push(path.to_path_buf());
+      }
+    }
+    files
+  }
+
+  // `group:artifact:version=configuration,..` lines, or bare coordinates in a legacy per-configuration file
+  fn parse_lockfile(
+    &self,
+    path: &Path,
+    configuration: Option<&str>,
+    dependencies: &mut HashMap<String, DepsEntry>,
+  ) -> Result<()> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read lock file: {}", path.display()))?;
+    let source = self.source(path);
+
+    for line in content.lines().map(str::trim) {
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      let (coordinates, configurations) = match line.split_once('=') {
+        Some((coordinates, configurations)) => (coordinates, configurations.split(',').collect::<Vec<_>>()),
+        None => (line, configuration.into_iter().collect()),
+      };
+      // `empty=annotationProcessor` lists configurations that resolve nothing
+      let mut parts = coordinates.split(':');
+      let (Some(group), Some(artifact), Some(version)) = (parts.next(), parts.next(), parts.next()) else {
+        if coordinates != "empty" {
+          self
+            .diagnostics
+            .warn(Category::ParseFailure, format!("Can't parse `{}` in {}", line, source));
+        }
+        continue;
+      };
+
+      let test_only = !configurations.is_empty() && configurations.iter().all(|configuration| is_test(configuration));
+      let scope = match test_only {
+        true if !self.include_test => continue,
+        true => DepScope::Dev,
+        false => DepScope::Runtime,
+      };
+      DepsEntry {
+        name: format!("{}:{}", group, artifact),
+        version: version.to_owned(),
+        scope,
+        sources: BTreeSet::from([source.clone()]),
+      }
+      .insert_into(dependencies);
+    }
+
+    Ok(())
+  }
+
+  // Every [libraries] entry, whichever build script uses it
+  fn parse_catalog(&self, path: &Path, dependencies: &mut HashMap<String, DepsEntry>) -> Result<()> {
+    let content =
+      fs::read_to_string(path).with_context(|| format!("Failed to read version catalog: {}", path.display()))?;
+    let source = self.source(path);
+    let catalog = match toml::from_str::<VersionCatalog>(&content) {
+      Ok(catalog) => catalog,
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::ParseFailure,
+          format!("Can't parse {}: {}", path.display(), err),
+        );
+        return Ok(());
+      },
+    };
+
+    for (alias, library) in &catalog.libraries {
+      let (name, version) = match library {
+        CatalogLibrary::Notation(notation) => match notation.rsplit_once(':') {
+          Some((name, version)) if name.contains(':') => (name.to_owned(), Some(version.to_owned())),
+          _ => (notation.clone(), None),
+        },
+        CatalogLibrary::Table(table) => {
+          let name = match (&table.module, &table.group, &table.name) {
+            (Some(module), _, _) => module.clone(),
+            (None, Some(group), Some(name)) => format!("{}:{}", group, name),
+            _ => {
+              self.diagnostics.warn(
+                Category::ParseFailure,
+                format!("Library `{}` in {} has no module or group and name", alias, source),
+              );
+              continue;
+            },
+          };
+          let version = match &table.version {
+            None => None,
+            Some(LibraryVersion::Plain(version)) => Some(version.clone()),
+            Some(LibraryVersion::Rich(rich)) => rich.version().map(str::to_owned),
+            Some(LibraryVersion::Ref { reference }) => match catalog.versions.get(reference) {
+              Some(CatalogVersion::Plain(version)) => Some(version.clone()),
+              Some(CatalogVersion::Rich(rich)) => rich.version().map(str::to_owned),
+              None => {
+                self.diagnostics.warn(
+                  Category::MalformedSpecifier,
+                  format!(
+                    "Library `{}` in {} refers to the undefined version `{}`",
+                    alias, source, reference
+                  ),
+                );
+                None
+              },
+            },
+          };
+          (name, version)
+        },
+      };
+
+      let version = match version {
+        Some(version) => catalog_version(&version),
+        None => {
+          self.diagnostics.warn(
+            Category::ScanIssue,
+            format!(
+              "{} in {} has no version, a platform or BOM likely manages it; using the latest release",
+              name, source
+            ),
+          );
+          "*".to_owned()
+        },
+      };
+      DepsEntry {
+        name,
+        version,
+        scope: DepScope::Runtime,
+        sources: BTreeSet::from([source.clone()]),
+      }
+      .insert_into(dependencies);
+    }
+
+    Ok(())
+  }
+
+  fn source(&self, path: &Path) -> String {
+    match path.strip_prefix(&self.root_path) {
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => path.display().to_string(),
+    }
+  }
+}
+
+// `testRuntimeClasspath`, `androidTestImplementation`, `debugUnitTestCompileClasspath`, ..
+fn is_test(configuration: &str) -> bool {
+  configuration.starts_with("test") || configuration.contains("Test")
+}
+
+// Dynamic versions (`1.+`, `latest.release`) resolve the latest release, ranges their lower bound
+fn catalog_version(version: &str) -> String {
+  if version.ends_with('+') || version.starts_with("latest.") || version.starts_with(']') {
+    return "*".to_owned();
+  }
+  // Gradle also writes an exclusive bound as an outward bracket: `[1.0,2.0[`
+  maven_deps::range_version(version.trim_end_matches('['))
+}
@@ -0,0 +1,169 @@
+use super::{manifests::ManifestFilter, patterns::PatternSet, yaml::Node};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry},
+};
+use anyhow::{Context, Result};
+use std::{
+  collections::{BTreeSet, HashMap},
+  fs,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+};
+use walkdir::WalkDir;
+
+static CHART_YAML: &str = "Chart.yaml";
+static CHART_LOCK: &str = "Chart.lock";
+// Subcharts in the tree itself, scanned as charts of their own
+const LOCAL_REPOSITORY: &str = "file://";
+
+// Helm charts: the `dependencies` of Chart.yaml, at the versions Chart.lock next to it resolved them to. A dependency
+// with a `condition` or `tags` is only installed when the values enable it and is listed as optional.
+pub struct HelmParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+  // The repository each chart is pulled from, by chart name
+  repositories: Mutex<HashMap<String, String>>,
+}
+
+impl HelmParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+      repositories: Mutex::new(HashMap::new()),
+    })
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies = HashMap::new();
+
+    let charts = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| !entry.file_type().is_dir() && entry.file_name().to_str() == Some(CHART_YAML));
+    for entry in charts {
+      let path = entry.path();
+      if path.to_str().is_some_and(|path| self.exclude_patterns.is_match(path))
+        || !self.manifests.allows(CHART_YAML, path)
+      {
+        continue;
+      }
+
+      println!("Processing file: {}", path.display());
+      let Some(chart) = self.read(path)? else {
+        continue;
+      };
+      let locked = self.locked_versions(&path.with_file_name(CHART_LOCK))?;
+
+      let source = self.source(path);
+      for dependency in chart.items("dependencies") {
+        let Some(name) = dependency.str("name") else {
+          self.diagnostics.warn(
+            Category::ParseFailure,
+            format!("{}: a dependency without a name, skipped", source),
+          );
+          continue;
+        };
+        let repository = dependency.str("repository").unwrap_or_default();
+        // Charts vendored into charts/ have no repository either
+        if repository.is_empty() || repository.starts_with(LOCAL_REPOSITORY) {
+          println!("Skipping local chart: {}", name);
+          continue;
+        }
+        let Some(version) = locked.get(name).map(String::as_str).or(dependency.str("version")) else {
+          self.diagnostics.warn(
+            Category::ParseFailure,
+            format!("{}: chart {} has no version, skipped", source, name),
+          );
+          continue;
+        };
+
+        self
+          .repositories
+          .lock()
+          .expect("Helm repositories lock poisoned")
+          .insert(name.to_owned(), repository.trim_end_matches('/').to_owned());
+        let scope = match dependency.get("condition").is_some() || dependency.get("tags").is_some() {
+          true => DepScope::Optional,
+          false => DepScope::Runtime,
+        };
+        DepsEntry {
+          name: name.to_owned(),
+          version: version.to_owned(),
+          scope,
+          sources: BTreeSet::from([source.clone()]),
+        }
+        .insert_into(&mut dependencies);
+      }
+    }
+
+    Ok(dependencies)
+  }
+
+  // The chart repository found by `parse` for each chart, where its index.yaml is fetched from
+  pub fn repositories(&self) -> HashMap<String, String> {
+    self
+      .repositories
+      .lock()
+      .map(|mut repositories| std::mem::take(&mut *repositories))
+      .unwrap_or_default()
+  }
+
+  // Chart.lock pins the versions `helm dependency update` picked for the ranges of Chart.yaml
+  fn locked_versions(&self, path: &Path) -> Result<HashMap<String, String>> {
+    if !path.is_file() || !self.manifests.allows(CHART_LOCK, path) {
+      return Ok(HashMap::new());
+    }
+    println!("Processing file: {}", path.display());
+    let Some(lock) = self.read(path)? else {
+      return Ok(HashMap::new());
+    };
+    Ok(
+      lock
+        .items("dependencies")
+        .iter()
+        .filter_map(|dependency| {
+          Some((
+            dependency.str("name")?.to_owned(),
+            dependency.str("version")?.to_owned(),
+          ))
+        })
+        .collect(),
+    )
+  }
+
+  fn read(&self, path: &Path) -> Result<Option<Node>> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read Helm chart: {}", path.display()))?;
+    match Node::parse(&content) {
+      Ok(node) => Ok(Some(node)),
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::ParseFailure,
+          format!("Can't parse {}: {:#}", self.source(path), err),
+        );
+        Ok(None)
+      },
+    }
+  }
+
+  fn source(&self, path: &Path) -> String {
+    match path.strip_prefix(&self.root_path) {
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => path.display().to_string(),
+    }
+  }
+}
@@ -0,0 +1,116 @@
+use crate::types::Ecosystem;
+use anyhow::{Context, Result};
+use std::{
+  collections::{BTreeSet, HashMap},
+  fs,
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+// Module -> versions a go.sum has hashes for
+type GoSum = HashMap<String, BTreeSet<String>>;
+
+// What is actually installed next to the scanned manifests: node_modules packages and go.sum entries. Packages of
+// other ecosystems, and manifests with nothing installed beside them, are never compared.
+pub struct InstalledVersions {
+  root_path: PathBuf,
+  // None for a go.mod without a go.sum
+  go_sums: Mutex<HashMap<PathBuf, Option<GoSum>>>,
+}
+
+impl InstalledVersions {
+  pub fn new(directory: &str) -> Result<Self> {
+    let root_path =
+      fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      go_sums: Mutex::new(HashMap::new()),
+    })
+  }
+
+  // How the installed copy differs from `version` for the manifests (relative to the root) that declare it, None
+  // when it matches or there is nothing installed to compare with
+  pub fn mismatch(
+    &self,
+    ecosystem: Ecosystem,
+    name: &str,
+    version: &str,
+    sources: &BTreeSet<String>,
+  ) -> Option<String> {
+    let mismatches = sources
+      .iter()
+      .filter_map(|source| {
+        let dir = self.root_path.join(source);
+        let dir = dir.parent()?;
+        match ecosystem {
+          Ecosystem::Npm => self.node_modules_mismatch(dir, name, version),
+          Ecosystem::Go => self.go_sum_mismatch(dir, name, version),
+          _ => None,
+        }
+      })
+      .collect::<BTreeSet<_>>();
+
+    Some(mismatches.into_iter().collect::<Vec<_>>().join("; ")).filter(|mismatches| !mismatches.is_empty())
+  }
+
+  // The copy Node would load: the closest node_modules/<name> from the manifest's directory up to the root
+  fn node_modules_mismatch(&self, dir: &Path, name: &str, version: &str) -> Option<String> {
+    let mut has_node_modules = false;
+    for ancestor in dir
+      .ancestors()
+      .take_while(|ancestor| ancestor.starts_with(&self.root_path))
+    {
+      let node_modules = ancestor.join("node_modules");
+      has_node_modules |= node_modules.is_dir();
+      let package_json = node_modules.join(name).join("package.json");
+      let Ok(content) = fs::read(&package_json) else {
+        continue;
+      };
+      let installed = serde_json::from_slice::<serde_json::Value>(&content)
+        .ok()
+        .and_then(|package| package.get("version")?.as_str().map(str::to_owned))?;
+
+      return (installed != version).then(|| format!("installed {} in {}", installed, self.relative(&node_modules)));
+    }
+
+    has_node_modules.then(|| "not installed in node_modules".to_owned())
+  }
+
+  fn go_sum_mismatch(&self, dir: &Path, name: &str, version: &str) -> Option<String> {
+    let path = dir.join("go.sum");
+    let mut go_sums = self.go_sums.lock().ok()?;
+    let modules = go_sums
+      .entry(path.clone())
+      .or_insert_with(|| read_go_sum(&path))
+      .as_ref()?;
+
+    match modules.get(name) {
+      None => Some(format!("missing from {}", self.relative(&path))),
+      Some(versions) if !versions.contains(version) => Some(format!(
+        "{} has {}",
+        self.relative(&path),
+        versions.iter().cloned().collect::<Vec<_>>().join(", ")
+      )),
+      Some(_) => None,
+    }
+  }
+
+  fn relative(&self, path: &Path) -> String {
+    path.strip_prefix(&self.root_path).unwrap_or(path).display().to_string()
+  }
+}
+
+// Each line is "module version[/go.mod] hash"
+fn read_go_sum(path: &Path) -> Option<GoSum> {
+  let content = fs::read_to_string(path).ok()?;
+  let mut modules: GoSum = HashMap::new();
+  for line in content.lines() {
+    let mut fields = line.split_whitespace();
+    if let (Some(module), Some(version)) = (fields.next(), fields.next()) {
+      let version = version.strip_suffix("/go.mod").unwrap_or(version);
+      modules.entry(module.to_owned()).or_default().insert(version.to_owned());
+    }
+  }
+  Some(modules)
+}
@@ -1,39 +1,116 @@
-use crate::types::{DepsEntry, PackageJson};
+use super::{
+  audit,
+  bun_lock::{BunLock, BUN_LOCKB_FILE, BUN_LOCK_FILE},
+  detect::DetectedManifests,
+  manifests::ManifestFilter,
+  npmrc::{Npmrc, NPMRC_FILE},
+  patterns::{NamespaceSet, PatternSet},
+  pnpm_lock::{PnpmLock, PNPM_LOCK_FILE},
+  yarn_berry::{YarnCache, PNP_FILE},
+  yarn_lock::{Locked, YarnLock, YARN_LOCK_FILE},
+};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  graph::DependencyGraph,
+  types::{DepScope, DepsEntry, PackageJson, PackageLock, PackageLockEntry, PackageLockV1Entry, SelfAuditFinding},
+};
 use anyhow::{Context, Result};
-use regex::Regex;
 use std::{
-  collections::HashMap,
+  collections::{BTreeSet, HashMap, HashSet},
   fs,
   path::{Path, PathBuf},
+  sync::Arc,
 };
 use walkdir::{DirEntry, WalkDir};
 
 static PACKAGE_JSON_FILE: &str = "package.json";
+static PACKAGE_LOCK_FILE: &str = "package-lock.json";
+static NODE_MODULES: &str = "node_modules/";
 
 pub struct JsParser {
   root_path: PathBuf,
-  exclude_patterns: Vec<Regex>,
-  skip_patterns: Vec<Regex>,
+  exclude_patterns: Arc<PatternSet>,
+  skip_patterns: Arc<PatternSet>,
+  skip_namespaces: Arc<NamespaceSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+  exclude_first_party: bool,
+  transitive: bool,
+}
+
+// A package-lock.json (lockfileVersion 2 or 3) in use, its install paths are relative to `dir`
+struct NpmLock {
+  dir: PathBuf,
+  source: String,
+  lock: PackageLock,
+}
+
+// The lockfiles a package.json is installed from, whichever the project has
+struct Locks<'a> {
+  bun: Option<&'a BunLock>,
+  npm: Option<&'a NpmLock>,
+  pnpm: Option<&'a PnpmLock>,
+  yarn: Option<&'a YarnLock>,
 }
 
 impl JsParser {
-  pub fn new(directory: &str, exclude: &Option<Vec<String>>, skip: &Option<Vec<String>>) -> Result<Self> {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    skip: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
     let root_path = std::fs::canonicalize(directory).context("Failed to canonicalize directory path")?;
 
     Ok(Self {
       root_path,
-      exclude_patterns: Self::compile_patterns(exclude)?,
-      skip_patterns: Self::compile_patterns(skip)?,
+      exclude_patterns: exclude,
+      skip_patterns: skip,
+      skip_namespaces: Arc::default(),
+      manifests,
+      diagnostics,
+      exclude_first_party: false,
+      transitive: false,
     })
   }
 
-  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+  // --skip-scope and --skip-prefix, applied next to the --skip patterns
+  pub fn skip_namespaces(mut self, namespaces: Arc<NamespaceSet>) -> Self {
+    self.skip_namespaces = namespaces;
+    self
+  }
+
+  // Drop dependencies that name one of the package.json files found under the root
+  pub fn exclude_first_party(mut self, exclude: bool) -> Self {
+    self.exclude_first_party = exclude;
+    self
+  }
+
+  // Add every package package-lock.json and pnpm-lock.yaml files install for the runtime, transitive ones included
+  pub fn transitive(mut self, transitive: bool) -> Self {
+    self.transitive = transitive;
+    self
+  }
+
+  // Manifests of other ecosystems seen on the way are tallied into `detected`
+  pub async fn parse(&self, detected: &mut DetectedManifests) -> Result<HashMap<String, DepsEntry>> {
     let mut dependencies = HashMap::new();
+    let mut first_party = HashSet::new();
+    let mut bun_locks = HashMap::new();
+    let mut npm_locks = HashMap::new();
+    let mut pnpm_locks = HashMap::new();
+    let mut yarn_locks = HashMap::new();
 
     let package_json_files = WalkDir::new(&self.root_path)
       .follow_links(true)
       .into_iter()
       .filter_map(|entry| entry.ok())
+      .inspect(|entry| {
+        if entry.file_type().is_file() && !self.is_excluded(entry.path()) {
+          detected.observe(&entry.file_name().to_string_lossy());
+        }
+      })
       .filter(|entry| self.is_valid_package_json(entry));
 
     for entry in package_json_files {
@@ -44,27 +121,406 @@ impl JsParser {
         .parse_package_json(path)
         .with_context(|| format!("Failed to parse {}", path.display()))?;
 
-      self.process_dependencies(&package_json, &mut dependencies)?;
+      let bun_lock = self.bun_lock(path, &mut bun_locks);
+      let npm_lock = self.npm_lock(path, &mut npm_locks);
+      let pnpm_lock = self.pnpm_lock(path, &mut pnpm_locks);
+      let yarn_lock = self.yarn_lock(path, &mut yarn_locks);
+      let locks = Locks {
+        bun: bun_lock,
+        npm: npm_lock,
+        pnpm: pnpm_lock,
+        yarn: yarn_lock,
+      };
+      self.process_dependencies(&package_json, path, locks, &mut dependencies)?;
+      first_party.extend(package_json.name);
+    }
+
+    if self.transitive {
+      let mut npm_locks = npm_locks.into_values().flatten().collect::<Vec<_>>();
+      npm_locks.sort_by(|lock, other| lock.source.cmp(&other.source));
+      for lock in &npm_locks {
+        self.add_installed(lock, &mut dependencies);
+      }
+      let mut pnpm_locks = pnpm_locks
+        .into_iter()
+        .filter_map(|(path, lock)| Some((self.relative_path(&path), lock?)))
+        .collect::<Vec<_>>();
+      pnpm_locks.sort_by(|(source, _), (other, _)| source.cmp(other));
+      for (source, lock) in &pnpm_locks {
+        for (name, version, optional) in lock.installed() {
+          if self.should_skip_dependency(name) {
+            continue;
+          }
+          let scope = if *optional {
+            DepScope::Optional
+          } else {
+            DepScope::Runtime
+          };
+          add_transitive(name, version, scope, source, &mut dependencies);
+        }
+      }
+    }
+
+    if self.exclude_first_party {
+      dependencies.retain(|name, _| {
+        let keep = !first_party.contains(name);
+        if !keep {
+          println!("Skipping first-party package: {}", name);
+        }
+        keep
+      });
     }
 
     Ok(dependencies)
   }
 
+  pub fn collect_graph(&self, graph: &mut DependencyGraph) -> Result<()> {
+    let package_json_files = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(|entry| entry.ok())
+      .filter(|entry| self.is_valid_package_json(entry));
+
+    for entry in package_json_files {
+      let path = entry.path();
+      let package_json = self
+        .parse_package_json(path)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+      let root = match &package_json.name {
+        Some(name) => name.clone(),
+        None => self.relative_dir(path),
+      };
+
+      let lock_path = path.with_file_name(PACKAGE_LOCK_FILE);
+      if lock_path.is_file() && self.manifests.allows(PACKAGE_LOCK_FILE, &lock_path) {
+        let lock = self
+          .parse_package_lock(&lock_path)
+          .with_context(|| format!("Failed to parse {}", lock_path.display()))?;
+        self.collect_lock_edges(&root, &package_json, &lock, graph);
+      } else {
+        let direct = package_json
+          .dependencies
+          .iter()
+          .chain(package_json.peer_dependencies.iter());
+        for name in direct.flat_map(|deps| deps.keys()) {
+          self.add_edge(graph, &root, name);
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  // Yarn Berry projects (a .pnp.cjs next to yarn.lock) whose cache can be read without the registry
+  pub fn yarn_caches(&self) -> Vec<YarnCache> {
+    let pnp_files = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(|entry| entry.ok())
+      .filter(|entry| {
+        entry.file_name().to_str() == Some(PNP_FILE)
+          && !self.is_excluded(entry.path())
+          && self.manifests.allows(PNP_FILE, entry.path())
+      });
+
+    let mut caches = Vec::new();
+    for entry in pnp_files {
+      let project_dir = entry.path().parent().unwrap_or(&self.root_path);
+      match YarnCache::load(project_dir, &self.diagnostics) {
+        Ok(cache) => {
+          println!("Using yarn cache in {}", cache.project_dir().display());
+          caches.push(cache);
+        },
+        Err(err) => self.diagnostics.warn(
+          Category::ScanIssue,
+          format!("Can't use yarn cache in {}: {:#}", project_dir.display(), err),
+        ),
+      }
+    }
+
+    caches
+  }
+
+  // The .npmrc files of the project and of each package, the project's first: where scoped packages come from
+  pub fn npmrc_files(&self) -> Vec<Npmrc> {
+    let mut paths = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(|entry| entry.ok())
+      .filter(|entry| {
+        entry.file_type().is_file()
+          && entry.file_name().to_str() == Some(NPMRC_FILE)
+          && !self.is_excluded(entry.path())
+          && self.manifests.allows(NPMRC_FILE, entry.path())
+      })
+      .map(|entry| entry.into_path())
+      .collect::<Vec<_>>();
+    paths.sort_by_key(|path| (path.components().count(), path.clone()));
+
+    let mut files = Vec::new();
+    for path in paths {
+      match Npmrc::read(&path, self.relative_path(&path), &self.diagnostics) {
+        Ok(npmrc) if npmrc.is_empty() => {},
+        Ok(npmrc) => {
+          println!("Using registry settings of {}", path.display());
+          files.push(npmrc);
+        },
+        Err(err) => self.diagnostics.warn(Category::ScanIssue, format!("{:#}", err)),
+      }
+    }
+
+    files
+  }
+
+  // Checks the scanned tree's own package.json files rather than their dependencies
+  pub fn self_audit(&self) -> Result<Vec<SelfAuditFinding>> {
+    let mut findings = Vec::new();
+
+    let package_json_files = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(|entry| entry.ok())
+      .filter(|entry| self.is_valid_package_json(entry));
+
+    for entry in package_json_files {
+      let path = entry.path();
+      let package_json = self
+        .parse_package_json(path)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+      let manifest = format!("{}/{}", self.relative_dir(path), PACKAGE_JSON_FILE);
+
+      findings.extend(audit::audit_package_json(
+        &manifest,
+        &package_json,
+        path.parent().unwrap_or(&self.root_path),
+      ));
+    }
+
+    Ok(findings)
+  }
+
   fn is_valid_package_json(&self, entry: &DirEntry) -> bool {
     // Skip directories and hidden files
-    if entry.file_type().is_dir() || entry.file_name().to_str().map_or(false, |s| s.starts_with('.')) {
+    if entry.file_type().is_dir() || entry.file_name().to_str().is_some_and(|s| s.starts_with('.')) {
       return false;
     }
 
     // Skip excluded paths
-    if let Some(path_str) = entry.path().to_str() {
-      if self.exclude_patterns.iter().any(|pattern| pattern.is_match(path_str)) {
-        return false;
+    if self.is_excluded(entry.path()) {
+      return false;
+    }
+
+    // Check if it's a package.json file the package.json rules let through
+    entry.file_name().to_str() == Some(PACKAGE_JSON_FILE) && self.manifests.allows(PACKAGE_JSON_FILE, entry.path())
+  }
+
+  // The Bun lockfile next to a package.json or, for a workspace member, in a directory above it. Each is loaded once.
+  fn bun_lock<'a>(&self, package_json: &Path, locks: &'a mut HashMap<PathBuf, Option<BunLock>>) -> Option<&'a BunLock> {
+    let lock_path = package_json
+      .ancestors()
+      .skip(1)
+      .take_while(|dir| dir.starts_with(&self.root_path))
+      .flat_map(|dir| [dir.join(BUN_LOCK_FILE), dir.join(BUN_LOCKB_FILE)])
+      .find(|path| path.is_file())?;
+    locks
+      .entry(lock_path.clone())
+      .or_insert_with(|| self.load_bun_lock(&lock_path))
+      .as_ref()
+  }
+
+  fn load_bun_lock(&self, path: &Path) -> Option<BunLock> {
+    let text = path.file_name().and_then(|name| name.to_str()) == Some(BUN_LOCK_FILE);
+    let kind = if text { BUN_LOCK_FILE } else { BUN_LOCKB_FILE };
+    if self.is_excluded(path) || !self.manifests.allows(kind, path) {
+      return None;
+    }
+
+    let lock = match text {
+      true => BunLock::read(path),
+      false => BunLock::list(path),
+    };
+    match lock {
+      Ok(lock) => {
+        println!("Using bun lockfile {}", path.display());
+        Some(lock)
+      },
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::ScanIssue,
+          format!(
+            "Can't use {}, versions are the package.json ranges: {:#}",
+            self.relative_path(path),
+            err
+          ),
+        );
+        None
+      },
+    }
+  }
+
+  // The yarn.lock next to a package.json or, for a workspace member, in a directory above it. Each is loaded once.
+  fn yarn_lock<'a>(
+    &self,
+    package_json: &Path,
+    locks: &'a mut HashMap<PathBuf, Option<YarnLock>>,
+  ) -> Option<&'a YarnLock> {
+    let lock_path = package_json
+      .ancestors()
+      .skip(1)
+      .take_while(|dir| dir.starts_with(&self.root_path))
+      .map(|dir| dir.join(YARN_LOCK_FILE))
+      .find(|path| path.is_file())?;
+    locks
+      .entry(lock_path.clone())
+      .or_insert_with(|| self.load_yarn_lock(&lock_path))
+      .as_ref()
+  }
+
+  fn load_yarn_lock(&self, path: &Path) -> Option<YarnLock> {
+    if self.is_excluded(path) || !self.manifests.allows(YARN_LOCK_FILE, path) {
+      return None;
+    }
+
+    match YarnLock::read(path) {
+      Ok(lock) => {
+        println!("Using yarn lockfile {}", path.display());
+        Some(lock)
+      },
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::ScanIssue,
+          format!(
+            "Can't use {}, versions are the package.json ranges: {:#}",
+            self.relative_path(path),
+            err
+          ),
+        );
+        None
+      },
+    }
+  }
+
+  // The pnpm-lock.yaml next to a package.json or, for a workspace member, in a directory above it. Each is loaded
+  // once.
+  fn pnpm_lock<'a>(
+    &self,
+    package_json: &Path,
+    locks: &'a mut HashMap<PathBuf, Option<PnpmLock>>,
+  ) -> Option<&'a PnpmLock> {
+    let lock_path = package_json
+      .ancestors()
+      .skip(1)
+      .take_while(|dir| dir.starts_with(&self.root_path))
+      .map(|dir| dir.join(PNPM_LOCK_FILE))
+      .find(|path| path.is_file())?;
+    locks
+      .entry(lock_path.clone())
+      .or_insert_with(|| self.load_pnpm_lock(&lock_path))
+      .as_ref()
+  }
+
+  fn load_pnpm_lock(&self, path: &Path) -> Option<PnpmLock> {
+    if self.is_excluded(path) || !self.manifests.allows(PNPM_LOCK_FILE, path) {
+      return None;
+    }
+
+    match PnpmLock::read(path) {
+      Ok(lock) => {
+        println!("Using pnpm lockfile {}", path.display());
+        Some(lock)
+      },
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::ScanIssue,
+          format!(
+            "Can't use {}, versions are the package.json ranges: {:#}",
+            self.relative_path(path),
+            err
+          ),
+        );
+        None
+      },
+    }
+  }
+
+  // The package-lock.json next to a package.json or, for a workspace member, in a directory above it. Each is loaded
+  // once; lockfileVersion 1 has no install paths to look versions up by and is left to the graph.
+  fn npm_lock<'a>(&self, package_json: &Path, locks: &'a mut HashMap<PathBuf, Option<NpmLock>>) -> Option<&'a NpmLock> {
+    let lock_path = package_json
+      .ancestors()
+      .skip(1)
+      .take_while(|dir| dir.starts_with(&self.root_path))
+      .map(|dir| dir.join(PACKAGE_LOCK_FILE))
+      .find(|path| path.is_file())?;
+    locks
+      .entry(lock_path.clone())
+      .or_insert_with(|| self.load_npm_lock(&lock_path))
+      .as_ref()
+  }
+
+  fn load_npm_lock(&self, path: &Path) -> Option<NpmLock> {
+    if self.is_excluded(path) || !self.manifests.allows(PACKAGE_LOCK_FILE, path) {
+      return None;
+    }
+
+    match self.parse_package_lock(path) {
+      Ok(lock) if lock.packages.is_empty() => None,
+      Ok(lock) => {
+        println!("Using npm lockfile {}", path.display());
+        Some(NpmLock {
+          dir: path.parent().unwrap_or(&self.root_path).to_path_buf(),
+          source: self.relative_path(path),
+          lock,
+        })
+      },
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::ScanIssue,
+          format!(
+            "Can't use {}, versions are the package.json ranges: {:#}",
+            self.relative_path(path),
+            err
+          ),
+        );
+        None
+      },
+    }
+  }
+
+  // --transitive: what the lockfile installs except for devDependencies, keyed by name unless the name is already
+  // listed at another version. Shallow install paths first, so the hoisted copy is the one keyed by name.
+  fn add_installed(&self, npm_lock: &NpmLock, dependencies: &mut HashMap<String, DepsEntry>) {
+    let mut installed = npm_lock
+      .lock
+      .packages
+      .iter()
+      .filter_map(|(install_path, entry)| Some((install_path, install_path.rsplit_once(NODE_MODULES)?.1, entry)))
+      .collect::<Vec<_>>();
+    installed.sort_by_key(|(install_path, _, _)| (install_path.matches(NODE_MODULES).count(), *install_path));
+
+    for (_, name, entry) in installed {
+      // Aliased installs (`"alias": "npm:real@1.0.0"`) carry the real name
+      let name = entry.name.as_deref().unwrap_or(name);
+      let Some(version) = entry.version.as_ref() else {
+        continue;
+      };
+      if entry.dev || entry.dev_optional || is_local(entry) || self.should_skip_dependency(name) {
+        continue;
       }
+
+      let scope = match (entry.peer, entry.optional) {
+        (true, _) => DepScope::Peer,
+        (_, true) => DepScope::Optional,
+        _ => DepScope::Runtime,
+      };
+      add_transitive(name, version, scope, &npm_lock.source, dependencies);
     }
+  }
 
-    // Check if it's a package.json file
-    entry.file_name().to_str().map_or(false, |s| s == PACKAGE_JSON_FILE)
+  fn is_excluded(&self, path: &Path) -> bool {
+    path
+      .to_str()
+      .is_some_and(|path_str| self.exclude_patterns.is_match(path_str))
   }
 
   fn parse_package_json(&self, path: &Path) -> Result<PackageJson> {
@@ -73,68 +529,236 @@ impl JsParser {
     serde_json::from_reader(file).with_context(|| format!("Failed to parse JSON from: {}", path.display()))
   }
 
+  fn parse_package_lock(&self, path: &Path) -> Result<PackageLock> {
+    let file = fs::File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+
+    serde_json::from_reader(file).with_context(|| format!("Failed to parse JSON from: {}", path.display()))
+  }
+
+  fn collect_lock_edges(
+    &self,
+    root: &str,
+    package_json: &PackageJson,
+    lock: &PackageLock,
+    graph: &mut DependencyGraph,
+  ) {
+    if !lock.packages.is_empty() {
+      for (install_path, entry) in &lock.packages {
+        let parent = match install_path.rsplit_once("node_modules/") {
+          Some((_, name)) => name,
+          None if install_path.is_empty() => root,
+          // Workspace members are keyed by their directory
+          None => entry.name.as_deref().unwrap_or(install_path),
+        };
+
+        for child in entry.dependencies.keys().chain(entry.peer_dependencies.keys()) {
+          self.add_edge(graph, parent, child);
+        }
+      }
+      return;
+    }
+
+    // lockfileVersion 1 doesn't record the root's requirements, take them from package.json
+    for name in package_json.dependencies.iter().flat_map(|deps| deps.keys()) {
+      self.add_edge(graph, root, name);
+    }
+    self.collect_v1_edges(&lock.dependencies, graph);
+  }
+
+  fn collect_v1_edges(&self, entries: &HashMap<String, PackageLockV1Entry>, graph: &mut DependencyGraph) {
+    for (name, entry) in entries {
+      for child in entry.requires.keys() {
+        self.add_edge(graph, name, child);
+      }
+      self.collect_v1_edges(&entry.dependencies, graph);
+    }
+  }
+
+  fn add_edge(&self, graph: &mut DependencyGraph, parent: &str, child: &str) {
+    if !self.should_skip_dependency(child) {
+      graph.add_edge(parent, child);
+    }
+  }
+
+  fn relative_path(&self, path: &Path) -> String {
+    match path.strip_prefix(&self.root_path) {
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => path.display().to_string(),
+    }
+  }
+
+  fn relative_dir(&self, path: &Path) -> String {
+    let dir = path.parent().unwrap_or(path);
+    match dir.strip_prefix(&self.root_path) {
+      Ok(relative) if relative.as_os_str().is_empty() => ".".to_owned(),
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => dir.display().to_string(),
+    }
+  }
+
   fn process_dependencies(
     &self,
     package_json: &PackageJson,
+    path: &Path,
+    locks: Locks,
     dependencies: &mut HashMap<String, DepsEntry>,
   ) -> Result<()> {
-    let Some(deps) = &package_json.dependencies else {
-      return Ok(());
-    };
+    let source = self.relative_path(path);
+    let sources = || BTreeSet::from([source.clone()]);
+    let package_dir = path.parent().unwrap_or(&self.root_path);
+    // What npm, yarn, pnpm or bun installed beats the range, the registry has no version called `^1.2.0`; `fallback`
+    // is looked up otherwise. None for a package npm linked or installed from the disk, or a Yarn or pnpm workspace
+    // package, which the registry doesn't have either.
+    let locked =
+      |name: &str, range: &str, fallback: &str| match locks.npm.and_then(|lock| lock.installed(package_dir, name)) {
+        Some(entry) if is_local(entry) => None,
+        Some(PackageLockEntry {
+          version: Some(version), ..
+        }) => Some(version.clone()),
+        _ => match locks
+          .yarn
+          .and_then(|lock| lock.lookup(name, range))
+          .or_else(|| locks.pnpm.and_then(|lock| lock.lookup(package_dir, name)))
+        {
+          Some(Locked::Local) => None,
+          Some(Locked::Version(version)) => Some(version.clone()),
+          None => Some(
+            locks
+              .bun
+              .and_then(|lock| lock.version(package_json.name.as_deref(), name))
+              .unwrap_or(fallback)
+              .to_owned(),
+          ),
+        },
+      };
 
-    for (name, version) in deps {
+    for (name, version) in package_json.dependencies.iter().flatten() {
       if self.should_skip_dependency(name) {
         println!("Skipping dependency: {}", name);
         continue;
       }
 
-      let version = match version.strip_prefix("^") {
-        Some(version) => version,
-        None => version,
+      let Some(version) = locked(name, version, version.strip_prefix("^").unwrap_or(version)) else {
+        println!("Skipping local package: {}", name);
+        continue;
       };
 
-      dependencies.insert(
-        name.clone(),
-        DepsEntry {
-          name: name.clone(),
-          version: version.to_string(),
-        },
-      );
+      DepsEntry {
+        name: name.clone(),
+        version,
+        scope: DepScope::Runtime,
+        sources: sources(),
+      }
+      .insert_into(dependencies);
     }
 
-    let Some(deps) = &package_json.peer_dependencies else {
-      return Ok(());
-    };
+    for (name, version) in package_json.peer_dependencies.iter().flatten() {
+      if self.should_skip_dependency(name) {
+        println!("Skipping dependency: {}", name);
+        continue;
+      }
 
-    for (name, version) in deps {
+      let Some(version) = locked(name, version, version) else {
+        println!("Skipping local package: {}", name);
+        continue;
+      };
+
+      DepsEntry {
+        name: name.clone(),
+        version,
+        scope: DepScope::Peer,
+        sources: sources(),
+      }
+      .insert_into(dependencies);
+    }
+
+    for (name, version) in package_json.optional_dependencies.iter().flatten() {
       if self.should_skip_dependency(name) {
         println!("Skipping dependency: {}", name);
         continue;
       }
 
-      dependencies.insert(
-        name.clone(),
-        DepsEntry {
-          name: name.clone(),
-          version: version.clone(),
-        },
-      );
+      let Some(version) = locked(name, version, version.strip_prefix("^").unwrap_or(version)) else {
+        println!("Skipping local package: {}", name);
+        continue;
+      };
+
+      DepsEntry {
+        name: name.clone(),
+        version,
+        scope: DepScope::Optional,
+        sources: sources(),
+      }
+      .insert_into(dependencies);
     }
 
     Ok(())
   }
 
   fn should_skip_dependency(&self, name: &str) -> bool {
-    self.skip_patterns.iter().any(|pattern| pattern.is_match(name))
+    self.skip_patterns.is_match(name) || self.skip_namespaces.is_match(name)
   }
+}
 
-  fn compile_patterns(patterns: &Option<Vec<String>>) -> Result<Vec<Regex>> {
-    match patterns {
-      Some(patterns) => patterns
-        .iter()
-        .map(|p| Regex::new(p).context("Failed to compile regex pattern"))
-        .collect(),
-      None => Ok(vec![]),
+impl NpmLock {
+  // The entry of what a package.json in `package_dir` gets for `name`: npm's resolution, the closest node_modules
+  // from its directory up to the lockfile's. None when the lockfile is a directory above that doesn't list the
+  // package.json as a workspace member.
+  fn installed(&self, package_dir: &Path, name: &str) -> Option<&PackageLockEntry> {
+    let member = package_dir.strip_prefix(&self.dir).ok()?;
+    let member_key = install_key(member);
+    if !member_key.is_empty() && !self.lock.packages.contains_key(&member_key) {
+      return None;
     }
+
+    member.ancestors().find_map(|dir| {
+      let key = match install_key(dir) {
+        dir if dir.is_empty() => format!("{}{}", NODE_MODULES, name),
+        dir => format!("{}/{}{}", dir, NODE_MODULES, name),
+      };
+      self.lock.packages.get(&key)
+    })
   }
 }
+
+// A package a lockfile installs, keyed by name unless the name is already listed at another version
+fn add_transitive(
+  name: &str,
+  version: &str,
+  scope: DepScope,
+  source: &str,
+  dependencies: &mut HashMap<String, DepsEntry>,
+) {
+  let key = match dependencies.get(name) {
+    Some(known) if known.version != version => format!("{}@{}", name, version),
+    _ => name.to_owned(),
+  };
+  dependencies
+    .entry(key)
+    .or_insert_with(|| DepsEntry {
+      name: name.to_owned(),
+      version: version.to_owned(),
+      scope,
+      sources: BTreeSet::new(),
+    })
+    .sources
+    .insert(source.to_owned());
+}
+
+// Install paths use `/` whatever the platform
+fn install_key(dir: &Path) -> String {
+  dir
+    .components()
+    .map(|component| component.as_os_str().to_string_lossy())
+    .collect::<Vec<_>>()
+    .join("/")
+}
+
+// A symlink, or a package installed from a directory or tarball on the disk rather than from a registry
+fn is_local(entry: &PackageLockEntry) -> bool {
+  entry.link
+    || entry
+      .resolved
+      .as_deref()
+      .is_some_and(|resolved| resolved.starts_with("file:") || !resolved.contains("://"))
+}
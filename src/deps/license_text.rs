@@ -0,0 +1,61 @@
+use super::{audit::is_license_file_name, local_path};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+lazy_static! {
+  // Phrases particular to each license text, checked in order: the LGPL quotes the GPL, BSD-3-Clause contains
+  // BSD-2-Clause. The text alone can't tell "-only" from "-or-later", the narrower id is reported.
+  static ref MARKERS: Vec<(&'static str, Regex)> = [
+    ("AGPL-3.0-only", r"gnu affero general public license version 3"),
+    ("LGPL-3.0-only", r"gnu lesser general public license version 3"),
+    ("LGPL-2.1-only", r"gnu lesser general public license version 2\.1"),
+    ("GPL-3.0-only", r"gnu general public license version 3"),
+    ("GPL-2.0-only", r"gnu general public license version 2"),
+    ("MPL-2.0", r"mozilla public license,? (version|v\.) ?2\.0"),
+    ("Apache-2.0", r"apache license,? version 2\.0"),
+    ("BSD-3-Clause", r"neither the name of .{1,200}? nor the names of (its|the) contributors"),
+    ("BSD-2-Clause", r"redistributions in binary form must reproduce the above copyright"),
+    ("ISC", r"permission to use, copy, modify, and(/or)? distribute this software for any purpose"),
+    ("MIT", r"permission is hereby granted, free of charge, to any person obtaining a copy"),
+    ("Unlicense", r"this is free and unencumbered software released into the public domain"),
+  ]
+  .into_iter()
+  .map(|(id, pattern)| (id, Regex::new(pattern).expect("Failed to compile license marker regex")))
+  .collect();
+}
+
+// SPDX id of a license text, None when it matches none of the common licenses
+pub fn classify(text: &str) -> Option<&'static str> {
+  // Line breaks and indentation differ between copies of the same text
+  let text = text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+  MARKERS
+    .iter()
+    .find(|(_, marker)| marker.is_match(&text))
+    .map(|(id, _)| *id)
+}
+
+// The directory's license file (the shortest name wins, LICENSE over LICENSE-THIRD-PARTY) with its content. A
+// license file symlinked to somewhere outside `root` is passed over like any other unreadable one.
+pub fn read_license_file(root: &Path, dir: &Path) -> Option<(PathBuf, String)> {
+  let mut candidates = fs::read_dir(dir)
+    .ok()?
+    .filter_map(Result::ok)
+    .map(|entry| entry.path())
+    .filter(|path| {
+      path.is_file()
+        && local_path::is_contained(root, path)
+        && path
+          .file_name()
+          .is_some_and(|name| is_license_file_name(&name.to_string_lossy()))
+    })
+    .collect::<Vec<_>>();
+  candidates.sort_by_key(|path| (path.as_os_str().len(), path.clone()));
+
+  candidates
+    .into_iter()
+    .find_map(|path| fs::read_to_string(&path).ok().map(|text| (path, text)))
+}
@@ -0,0 +1,99 @@
+use crate::diagnostics::Category;
+use std::{
+  fmt, fs, io,
+  path::{Component, Path, PathBuf},
+};
+
+// Why a path taken from manifest content wasn't followed
+#[derive(Debug)]
+pub enum PathRefusal {
+  // Manifests travel between machines, an absolute path is about the author's
+  Absolute,
+  // `..` climbs out of the scanned root before anything on disk is looked at
+  Traversal,
+  // Inside the root as written, but a symlink on the way leads out of it, to the path given
+  SymlinkEscape(PathBuf),
+  // Nothing there, a broken path rather than an escape
+  Missing(io::Error),
+}
+
+impl PathRefusal {
+  // Whether the path tried to leave the root, as opposed to not existing
+  pub fn is_escape(&self) -> bool {
+    !matches!(self, PathRefusal::Missing(_))
+  }
+
+  // Escapes are counted on their own in the recap, a missing path is an ordinary scan issue
+  pub fn category(&self) -> Category {
+    match self.is_escape() {
+      true => Category::RefusedPath,
+      false => Category::ScanIssue,
+    }
+  }
+}
+
+impl fmt::Display for PathRefusal {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      PathRefusal::Absolute => write!(f, "is absolute"),
+      PathRefusal::Traversal => write!(f, "leads out of the scanned root"),
+      PathRefusal::SymlinkEscape(target) => {
+        write!(
+          f,
+          "goes through a symlink out of the scanned root (to {})",
+          target.display()
+        )
+      },
+      PathRefusal::Missing(_) => write!(f, "does not exist"),
+    }
+  }
+}
+
+/// Resolves `relative`, a path a manifest in `from` names (a `path:` dependency, a `replace` directory, an
+/// included requirements file), to the canonical path it reaches, only when that stays inside `root`. Every
+/// local-path lookup goes through here so a manifest can't make the scan read outside the tree.
+///
+/// ```
+/// use depsfetch::deps::local_path::{safe_join, PathRefusal};
+/// use std::path::Path;
+///
+/// let (root, app) = (Path::new("/repo"), Path::new("/repo/app"));
+/// assert!(matches!(safe_join(root, app, "../../etc/passwd"), Err(PathRefusal::Traversal)));
+/// assert!(matches!(safe_join(root, app, "/etc/passwd"), Err(PathRefusal::Absolute)));
+/// ```
+pub fn safe_join(root: &Path, from: &Path, relative: &str) -> Result<PathBuf, PathRefusal> {
+  let relative = Path::new(relative);
+  if relative.is_absolute() || relative.has_root() {
+    return Err(PathRefusal::Absolute);
+  }
+
+  // `..` is settled on the path as written first, nothing outside the root gets so much as a stat
+  let mut joined = PathBuf::new();
+  for component in from.join(relative).components() {
+    match component {
+      Component::CurDir => {},
+      Component::ParentDir => {
+        if !joined.pop() {
+          return Err(PathRefusal::Traversal);
+        }
+      },
+      component => joined.push(component),
+    }
+  }
+  if !joined.starts_with(root) {
+    return Err(PathRefusal::Traversal);
+  }
+
+  let target = fs::canonicalize(&joined).map_err(PathRefusal::Missing)?;
+  let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+  match target.starts_with(&root) {
+    true => Ok(target),
+    false => Err(PathRefusal::SymlinkEscape(target)),
+  }
+}
+
+// Whether an existing path, e.g. a file found by listing a directory, resolves inside `root`
+pub fn is_contained(root: &Path, path: &Path) -> bool {
+  let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+  fs::canonicalize(path).is_ok_and(|path| path.starts_with(root))
+}
@@ -0,0 +1,206 @@
+use super::patterns::PatternSet;
+use crate::config::ManifestConfig;
+use anyhow::{anyhow, bail, Result};
+use std::{
+  collections::{BTreeMap, BTreeSet, HashSet},
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+// Manifest files the parsers read, each with its own switch and extra excludes
+pub const MANIFEST_KINDS: &[&str] = &[
+  "package.json",
+  "package-lock.json",
+  "bun.lock",
+  "bun.lockb",
+  "yarn.lock",
+  "pnpm-lock.yaml",
+  ".pnp.cjs",
+  ".npmrc",
+  "go.mod",
+  "go.work",
+  "Cargo.toml",
+  "requirements.txt",
+  "pyproject.toml",
+  "Pipfile",
+  "Pipfile.lock",
+  "pom.xml",
+  "Gemfile.lock",
+  "composer.json",
+  "composer.lock",
+  "*.csproj",
+  "packages.lock.json",
+  "Directory.Packages.props",
+  "pubspec.yaml",
+  "pubspec.lock",
+  "Package.resolved",
+  "Podfile.lock",
+  "conanfile.txt",
+  "conanfile.py",
+  "deno.json",
+  "deno.jsonc",
+  "import_map.json",
+  "Chart.yaml",
+  "Chart.lock",
+  ".terraform.lock.hcl",
+  ".github/workflows",
+  "gradle.lockfile",
+  "libs.versions.toml",
+];
+
+struct ManifestRule {
+  enabled: bool,
+  exclude: PatternSet,
+}
+
+// Per-kind rules layered over the global --exclude: a file has to pass both. Like PatternSet it remembers
+// distinct paths, the parsers walk the tree more than once.
+pub struct ManifestFilter {
+  rules: BTreeMap<&'static str, ManifestRule>,
+  found: Mutex<BTreeMap<&'static str, HashSet<String>>>,
+  // Set while the parsers of a --watch cycle after the first run
+  reparse: Mutex<Option<ReparseScope>>,
+}
+
+// The manifests a --watch cycle after the first reads again: the ones next to and below the changed files, and the
+// ones above them where workspace roots keep their lockfiles. The others are taken from the cycle before.
+#[derive(Debug, Clone)]
+pub struct ReparseScope {
+  dirs: Vec<PathBuf>,
+}
+
+impl ReparseScope {
+  pub fn new(changed: &BTreeSet<PathBuf>) -> Self {
+    Self {
+      dirs: changed
+        .iter()
+        .filter_map(|path| path.parent())
+        .map(Path::to_path_buf)
+        .collect(),
+    }
+  }
+
+  pub fn covers(&self, manifest: &Path) -> bool {
+    let Some(dir) = manifest.parent() else {
+      return true;
+    };
+    self
+      .dirs
+      .iter()
+      .any(|changed| dir.starts_with(changed) || changed.starts_with(dir))
+  }
+}
+
+// One line of the scan summary: files that got past the global excludes and how many the kind's rules dropped
+#[derive(Debug, Clone)]
+pub struct ManifestTally {
+  pub kind: &'static str,
+  pub enabled: bool,
+  pub found: usize,
+  pub excluded: usize,
+}
+
+impl ManifestFilter {
+  // `overrides` are `--manifest-exclude KIND=PATTERN` values; they replace the config's patterns for their kind
+  pub fn new(config: &BTreeMap<String, ManifestConfig>, overrides: &Option<Vec<String>>) -> Result<Self> {
+    let mut patterns = BTreeMap::new();
+    for (kind, manifest) in config {
+      patterns.insert(known_kind(kind)?, manifest.exclude.clone().unwrap_or_default());
+    }
+    let mut overridden = HashSet::new();
+    for value in overrides.iter().flatten() {
+      let (kind, pattern) = value
+        .split_once('=')
+        .ok_or_else(|| anyhow!("--manifest-exclude `{}` is not KIND=PATTERN", value))?;
+      let kind = known_kind(kind)?;
+      let kind_patterns = patterns.entry(kind).or_insert_with(Vec::new);
+      if overridden.insert(kind) {
+        kind_patterns.clear();
+      }
+      kind_patterns.push(pattern.to_owned());
+    }
+
+    let mut rules = BTreeMap::new();
+    for kind in MANIFEST_KINDS {
+      let enabled = config.get(*kind).and_then(|manifest| manifest.enabled).unwrap_or(true);
+      let exclude = PatternSet::compile(
+        &format!("exclude for {}", kind),
+        &patterns.remove(kind).filter(|patterns| !patterns.is_empty()),
+      )?;
+      rules.insert(*kind, ManifestRule { enabled, exclude });
+    }
+
+    Ok(Self {
+      rules,
+      found: Mutex::new(BTreeMap::new()),
+      reparse: Mutex::new(None),
+    })
+  }
+
+  // Only the scope's manifests get through until it's lifted with None; the graph, the self audit and the settings
+  // files are read whole either side of it
+  pub fn set_reparse_scope(&self, scope: Option<ReparseScope>) {
+    if let Ok(mut reparse) = self.reparse.lock() {
+      *reparse = scope;
+    }
+  }
+
+  // Only asked about files that already are of `kind` and passed the global excludes
+  pub fn allows(&self, kind: &'static str, path: &Path) -> bool {
+    let Some(rule) = self.rules.get(kind) else {
+      return true;
+    };
+    let reparsed = match self.reparse.lock() {
+      Ok(reparse) => reparse.as_ref().is_none_or(|scope| scope.covers(path)),
+      Err(_) => true,
+    };
+    let path = path.to_string_lossy();
+    if let Ok(mut found) = self.found.lock() {
+      found.entry(kind).or_default().insert(path.to_string());
+    }
+
+    rule.enabled && !rule.exclude.is_match(&path) && reparsed
+  }
+
+  // Whether anything differs from reading every manifest kind
+  pub fn is_configured(&self) -> bool {
+    self
+      .rules
+      .values()
+      .any(|rule| !rule.enabled || !rule.exclude.is_empty())
+  }
+
+  pub fn tallies(&self) -> Vec<ManifestTally> {
+    let found = self.found.lock().map(|found| found.clone()).unwrap_or_default();
+    MANIFEST_KINDS
+      .iter()
+      .map(|kind| {
+        let rule = &self.rules[kind];
+        let found = found.get(kind).map_or(0, HashSet::len);
+        // First-match credit means every path is counted by one pattern at most
+        let excluded = if rule.enabled {
+          rule.exclude.filtered_counts().iter().sum()
+        } else {
+          found
+        };
+        ManifestTally {
+          kind,
+          enabled: rule.enabled,
+          found,
+          excluded,
+        }
+      })
+      .collect()
+  }
+}
+
+fn known_kind(kind: &str) -> Result<&'static str> {
+  match MANIFEST_KINDS.iter().find(|known| **known == kind) {
+    Some(known) => Ok(known),
+    None => bail!(
+      "Unknown manifest kind `{}`, expected one of: {}",
+      kind,
+      MANIFEST_KINDS.join(", ")
+    ),
+  }
+}
@@ -0,0 +1,72 @@
+use crate::{
+  config::ManualComponent,
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+
+// `[[manual_component]]` entries of the config, for what no parser finds (a purchased SDK, a font, a dataset).
+// They become rows as written, nothing is resolved.
+pub fn rows(components: &[ManualComponent]) -> Result<Vec<ReportRow>> {
+  let mut rows: Vec<ReportRow> = Vec::new();
+  for (idx, component) in components.iter().enumerate() {
+    let given = |value: &Option<String>| {
+      value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+    };
+    let (Some(name), Some(license)) = (given(&component.name), given(&component.license)) else {
+      bail!(
+        "manual_component #{} ({}) needs at least a name and a license",
+        idx + 1,
+        component.name.as_deref().unwrap_or("unnamed")
+      );
+    };
+    let version = given(&component.version).unwrap_or_default();
+    if rows.iter().any(|row| row.name == name && row.version == version) {
+      bail!("manual_component {}@{} is listed twice", name, version);
+    }
+
+    let mut provenance = vec!["metadata: manual component from the config".to_owned()];
+    if let Some(label) = given(&component.ecosystem) {
+      provenance.push(format!("ecosystem: {}", label));
+    }
+    rows.push(ReportRow {
+      ecosystem: Ecosystem::Manual,
+      name,
+      version,
+      scope: DepScope::Runtime,
+      homepage: given(&component.homepage).unwrap_or_default(),
+      license: Some(license),
+      license_confidence: Some(LicenseConfidence::Declared),
+      license_url: given(&component.license_url),
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance,
+    });
+  }
+
+  Ok(rows)
+}
+
+// A component listed by hand that a parser finds as well is likely a leftover, both rows are kept
+pub fn warn_duplicates(rows: &[ReportRow], scanned: &HashSet<String>, diagnostics: &Diagnostics) {
+  for row in rows.iter().filter(|row| scanned.contains(&row.name)) {
+    diagnostics.warn(
+      Category::ScanIssue,
+      format!("Manual component {} is also a scanned dependency", row.name),
+    );
+  }
+}
@@ -0,0 +1,465 @@
+use super::{manifests::ManifestFilter, patterns::PatternSet, xml::Element};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry},
+};
+use anyhow::{bail, Context, Result};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::{
+  collections::{BTreeSet, HashMap, HashSet},
+  fs,
+  path::PathBuf,
+  sync::Arc,
+};
+use walkdir::{DirEntry, WalkDir};
+
+static POM_FILE: &str = "pom.xml";
+// Parent chains longer than this are taken for a cycle
+const MAX_PARENT_DEPTH: usize = 10;
+
+lazy_static! {
+  static ref PROPERTY_REGEX: Regex = Regex::new(r"\$\{([^}]+)\}").expect("Failed to compile property regex");
+}
+
+pub struct ParentRef {
+  pub group_id: String,
+  pub artifact_id: String,
+  pub version: Option<String>,
+  // None for the default `../pom.xml`, empty when the parent only comes from a repository
+  relative_path: Option<String>,
+}
+
+pub struct License {
+  pub name: Option<String>,
+  pub url: Option<String>,
+}
+
+struct Dependency {
+  group_id: Option<String>,
+  artifact_id: Option<String>,
+  version: Option<String>,
+  scope: Option<String>,
+  optional: bool,
+}
+
+// The parts of a POM the scan and the Maven Central lookup read
+pub struct Pom {
+  group_id: Option<String>,
+  artifact_id: Option<String>,
+  version: Option<String>,
+  pub parent: Option<ParentRef>,
+  properties: HashMap<String, String>,
+  dependencies: Vec<Dependency>,
+  managed: Vec<Dependency>,
+  pub licenses: Vec<License>,
+  url: Option<String>,
+  scm_url: Option<String>,
+}
+
+impl Pom {
+  pub fn parse(content: &str) -> Result<Pom> {
+    let project = Element::parse(content)?;
+    if project.name != "project" {
+      bail!("expected <project>, found <{}>", project.name);
+    }
+
+    let dependencies = |parent: Option<&Element>| {
+      parent
+        .and_then(|parent| parent.child("dependencies"))
+        .into_iter()
+        .flat_map(|dependencies| dependencies.children("dependency"))
+        .map(|dependency| Dependency {
+          group_id: dependency.text_of("groupId"),
+          artifact_id: dependency.text_of("artifactId"),
+          version: dependency.text_of("version"),
+          scope: dependency.text_of("scope"),
+          optional: dependency.text_of("optional").as_deref() == Some("true"),
+        })
+        .collect()
+    };
+
+    Ok(Pom {
+      group_id: project.text_of("groupId"),
+      artifact_id: project.text_of("artifactId"),
+      version: project.text_of("version"),
+      parent: project.child("parent").and_then(|parent| {
+        Some(ParentRef {
+          group_id: parent.text_of("groupId")?,
+          artifact_id: parent.text_of("artifactId")?,
+          version: parent.text_of("version"),
+          relative_path: parent.child("relativePath").map(|path| path.text.trim().to_owned()),
+        })
+      }),
+      properties: project
+        .child("properties")
+        .into_iter()
+        .flat_map(|properties| &properties.children)
+        .map(|property| (property.name.clone(), property.text.trim().to_owned()))
+        .collect(),
+      dependencies: dependencies(Some(&project)),
+      managed: dependencies(project.child("dependencyManagement")),
+      licenses: project
+        .child("licenses")
+        .into_iter()
+        .flat_map(|licenses| licenses.children("license"))
+        .map(|license| License {
+          name: license.text_of("name"),
+          url: license.text_of("url"),
+        })
+        .collect(),
+      url: project.text_of("url"),
+      scm_url: project.child("scm").and_then(|scm| scm.text_of("url")),
+    })
+  }
+
+  // The groupId and version a POM without its own inherits from the parent
+  fn group_id(&self) -> Option<&str> {
+    self
+      .group_id
+      .as_deref()
+      .or_else(|| Some(self.parent.as_ref()?.group_id.as_str()))
+  }
+
+  fn version(&self) -> Option<&str> {
+    self
+      .version
+      .as_deref()
+      .or_else(|| self.parent.as_ref()?.version.as_deref())
+  }
+
+  fn coordinates(&self) -> Option<String> {
+    Some(format!("{}:{}", self.group_id()?, self.artifact_id.as_deref()?))
+  }
+
+  // `${project.*}` built-ins, which aren't declared in <properties>
+  fn builtins(&self) -> Vec<(String, String)> {
+    let parent = self.parent.as_ref();
+    [
+      ("project.groupId", self.group_id()),
+      ("project.artifactId", self.artifact_id.as_deref()),
+      ("project.version", self.version()),
+      ("project.parent.groupId", parent.map(|parent| parent.group_id.as_str())),
+      (
+        "project.parent.artifactId",
+        parent.map(|parent| parent.artifact_id.as_str()),
+      ),
+      (
+        "project.parent.version",
+        parent.and_then(|parent| parent.version.as_deref()),
+      ),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some((name.to_owned(), value?.to_owned())))
+    .collect()
+  }
+
+  // The project's site, then its source repository, placeholders resolved from the POM itself
+  pub fn homepage(&self) -> Option<String> {
+    self
+      .url
+      .iter()
+      .chain(&self.scm_url)
+      .find_map(|url| self.interpolated(url))
+  }
+
+  pub fn repository(&self) -> Option<String> {
+    self
+      .scm_url
+      .as_deref()
+      .and_then(|url| self.interpolated(url))
+      .map(|url| url.trim_end_matches('/').trim_end_matches(".git").to_owned())
+  }
+
+  fn interpolated(&self, value: &str) -> Option<String> {
+    let mut properties = self.properties.clone();
+    properties.extend(self.builtins());
+    Some(interpolate(value, &properties)).filter(|value| !value.contains("${"))
+  }
+}
+
+// The version maven-metadata.xml lists as the newest release
+pub fn latest_release(metadata: &str) -> Result<Option<String>> {
+  let metadata = Element::parse(metadata)?;
+  let Some(versioning) = metadata.child("versioning") else {
+    return Ok(None);
+  };
+  Ok(
+    versioning
+      .text_of("release")
+      .or_else(|| versioning.text_of("latest"))
+      .or_else(|| {
+        versioning
+          .child("versions")?
+          .children("version")
+          .last()
+          .map(|version| version.text.trim().to_owned())
+      }),
+  )
+}
+
+struct Manifest {
+  path: PathBuf,
+  source: String,
+  pom: Pom,
+}
+
+// `<dependencies>` of every pom.xml, keyed by `groupId:artifactId`. Versions and `${property}` placeholders are
+// resolved through parent POMs found in the scanned tree; a parent only published to a repository isn't fetched.
+// Test-scoped dependencies are only listed on request.
+pub struct MavenParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+  include_test: bool,
+}
+
+impl MavenParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+      include_test: false,
+    })
+  }
+
+  // List `<scope>test</scope>` dependencies too, with the dev scope
+  pub fn include_test_scope(mut self, include: bool) -> Self {
+    self.include_test = include;
+    self
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies = HashMap::new();
+
+    let mut manifests = Vec::new();
+    let pom_files = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| self.is_valid_pom(entry));
+    for entry in pom_files {
+      let path = entry.path();
+      println!("Processing file: {}", path.display());
+
+      let content = fs::read_to_string(path).with_context(|| format!("Failed to read POM file: {}", path.display()))?;
+      let pom = match Pom::parse(&content) {
+        Ok(pom) => pom,
+        Err(err) => {
+          self.diagnostics.warn(
+            Category::ParseFailure,
+            format!("Can't parse {}: {}", path.display(), err),
+          );
+          continue;
+        },
+      };
+      let source = match path.strip_prefix(&self.root_path) {
+        Ok(relative) => relative.display().to_string(),
+        Err(_) => path.display().to_string(),
+      };
+      manifests.push(Manifest {
+        path: path.to_path_buf(),
+        source,
+        pom,
+      });
+    }
+
+    // Modules of the scanned tree itself, whatever way they are depended on
+    let local = manifests
+      .iter()
+      .filter_map(|manifest| manifest.pom.coordinates())
+      .collect::<HashSet<_>>();
+
+    for manifest in &manifests {
+      let chain = self.parent_chain(manifest, &manifests);
+      // Closer POMs win, both for properties and for <dependencyManagement> versions
+      let mut properties = HashMap::new();
+      for ancestor in chain.iter().rev() {
+        properties.extend(ancestor.pom.properties.clone());
+      }
+      properties.extend(manifest.pom.builtins());
+      let mut managed = HashMap::new();
+      for ancestor in chain.iter().rev() {
+        for dependency in &ancestor.pom.managed {
+          if let (Some(group_id), Some(artifact_id), Some(version)) =
+            (&dependency.group_id, &dependency.artifact_id, &dependency.version)
+          {
+            let name = format!(
+              "{}:{}",
+              interpolate(group_id, &properties),
+              interpolate(artifact_id, &properties)
+            );
+            managed.insert(name, version.clone());
+          }
+        }
+      }
+
+      for dependency in &manifest.pom.dependencies {
+        let (Some(group_id), Some(artifact_id)) = (&dependency.group_id, &dependency.artifact_id) else {
+          continue;
+        };
+        let name = format!(
+          "{}:{}",
+          interpolate(group_id, &properties),
+          interpolate(artifact_id, &properties)
+        );
+        if local.contains(&name) {
+          continue;
+        }
+        let scope = match dependency.scope.as_deref() {
+          Some("test") if !self.include_test => continue,
+          Some("test") => DepScope::Dev,
+          Some("system") => {
+            self.diagnostics.warn(
+              Category::ScanIssue,
+              format!(
+                "{} in {} is a system-scoped jar, not from a repository; list it as a manual component",
+                name, manifest.source
+              ),
+            );
+            continue;
+          },
+          _ if dependency.optional => DepScope::Optional,
+          _ => DepScope::Runtime,
+        };
+
+        let version = match dependency.version.as_ref().or_else(|| managed.get(&name)) {
+          Some(version) => interpolate(version, &properties),
+          None => {
+            self.diagnostics.warn(
+              Category::ScanIssue,
+              format!(
+                "{} in {} has no version in the POM or a parent POM inside the scanned tree, using the latest release",
+                name, manifest.source
+              ),
+            );
+            "*".to_owned()
+          },
+        };
+        let version = match version.contains("${") {
+          true => {
+            self.diagnostics.warn(
+              Category::ScanIssue,
+              format!(
+                "Can't resolve the version {} of {} in {}, no POM inside the scanned tree defines it; using the latest \
+                 release",
+                version, name, manifest.source
+              ),
+            );
+            "*".to_owned()
+          },
+          false => range_version(&version),
+        };
+
+        DepsEntry {
+          name,
+          version,
+          scope,
+          sources: BTreeSet::from([manifest.source.clone()]),
+        }
+        .insert_into(&mut dependencies);
+      }
+    }
+
+    Ok(dependencies)
+  }
+
+  // Parent POMs inside the scanned tree, closest first: the one at <relativePath> (`../pom.xml` by default) when
+  // it's the declared parent, otherwise any scanned POM with the parent's coordinates
+  fn parent_chain<'a>(&self, manifest: &'a Manifest, manifests: &'a [Manifest]) -> Vec<&'a Manifest> {
+    let mut chain = Vec::new();
+    let mut current = manifest;
+    while let Some(parent) = &current.pom.parent {
+      let coordinates = format!("{}:{}", parent.group_id, parent.artifact_id);
+      let dir = current.path.parent().unwrap_or(&self.root_path);
+      let relative = match parent.relative_path.as_deref() {
+        None => Some(dir.join("../pom.xml")),
+        Some("") => None,
+        Some(path) if path.ends_with(".xml") => Some(dir.join(path)),
+        Some(path) => Some(dir.join(path).join(POM_FILE)),
+      };
+      let relative = relative.and_then(|path| fs::canonicalize(path).ok());
+      let matches = |candidate: &&Manifest| candidate.pom.coordinates().as_ref() == Some(&coordinates);
+      let found = manifests
+        .iter()
+        .filter(matches)
+        .find(|candidate| relative.as_deref() == fs::canonicalize(&candidate.path).ok().as_deref())
+        .or_else(|| manifests.iter().find(matches));
+
+      match found {
+        Some(found) if chain.len() < MAX_PARENT_DEPTH && !std::ptr::eq(found, manifest) => {
+          chain.push(found);
+          current = found;
+        },
+        _ => break,
+      }
+    }
+    chain
+  }
+
+  fn is_valid_pom(&self, entry: &DirEntry) -> bool {
+    // Skip directories and hidden files
+    if entry.file_type().is_dir() || entry.file_name().to_str().is_some_and(|s| s.starts_with('.')) {
+      return false;
+    }
+
+    // Skip excluded paths
+    if let Some(path_str) = entry.path().to_str() {
+      if self.exclude_patterns.is_match(path_str) {
+        return false;
+      }
+    }
+
+    // Check if it's a pom.xml file the pom.xml rules let through
+    entry.file_name().to_str() == Some(POM_FILE) && self.manifests.allows(POM_FILE, entry.path())
+  }
+}
+
+// Properties may refer to other properties; the pass limit stops cycles
+fn interpolate(value: &str, properties: &HashMap<String, String>) -> String {
+  let mut value = value.to_owned();
+  for _ in 0..MAX_PARENT_DEPTH {
+    let next = PROPERTY_REGEX
+      .replace_all(&value, |captures: &Captures| {
+        properties
+          .get(&captures[1])
+          .cloned()
+          .unwrap_or_else(|| captures[0].to_owned())
+      })
+      .into_owned();
+    if next == value {
+      break;
+    }
+    value = next;
+  }
+  value
+}
+
+// `[1.2,2.0)` -> `1.2`, the lowest version an inclusive range allows; other ranges resolve the latest release
+pub fn range_version(version: &str) -> String {
+  if !version.starts_with(['[', '(']) {
+    return version.to_owned();
+  }
+  let lower = version[1..].split(',').next().unwrap_or_default().trim();
+  match version.starts_with('[') && !lower.is_empty() {
+    true => lower.trim_end_matches([']', ')']).to_owned(),
+    false => "*".to_owned(),
+  }
+}
+
+// `org.slf4j:slf4j-api` -> `org/slf4j/slf4j-api`, the artifact's directory in a Maven repository
+pub fn repository_path(name: &str) -> Option<String> {
+  let (group_id, artifact_id) = name.split_once(':')?;
+  Some(format!("{}/{}", group_id.replace('.', "/"), artifact_id))
+}
@@ -1,2 +1,37 @@
+pub mod actions_deps;
+pub mod audit;
+pub mod bun_lock;
+pub mod cargo_deps;
+pub mod cocoapods_deps;
+pub mod conan_deps;
+pub mod dart_deps;
+pub mod deno_deps;
+pub mod dependency_list;
+pub mod detect;
+pub mod filter;
 pub mod go_deps;
+pub mod go_work;
+pub mod gradle_deps;
+pub mod helm_deps;
+pub mod installed;
 pub mod js_deps;
+pub mod license_text;
+pub mod local_path;
+pub mod manifests;
+pub mod manual;
+pub mod maven_deps;
+pub mod npmrc;
+pub mod nuget_deps;
+pub mod patterns;
+pub mod php_deps;
+pub mod pnpm_lock;
+pub mod py_deps;
+pub mod ruby_deps;
+pub mod sbom;
+pub mod sheets;
+pub mod swift_deps;
+pub mod terraform_deps;
+pub mod xml;
+pub mod yaml;
+pub mod yarn_berry;
+pub mod yarn_lock;
@@ -0,0 +1,107 @@
+use crate::diagnostics::{Category, Diagnostics};
+use anyhow::{Context, Result};
+use std::{collections::BTreeMap, env, fs, path::Path};
+
+pub const NPMRC_FILE: &str = ".npmrc";
+const REGISTRY_SUFFIX: &str = ":registry";
+const TOKEN_SUFFIX: &str = ":_authToken";
+
+// The registry settings of one .npmrc: `@scope:registry=URL` lines and the `//host/path/:_authToken=TOKEN` lines
+// that authenticate to them. The rest of the file is npm's business.
+#[derive(Debug, Clone, Default)]
+pub struct Npmrc {
+  pub source: String,
+  // `@scope` -> registry URL
+  pub scopes: BTreeMap<String, String>,
+  // `//host/path/` -> token, `${VAR}` references expanded
+  tokens: BTreeMap<String, String>,
+}
+
+impl Npmrc {
+  pub fn read(path: &Path, source: String, diagnostics: &Diagnostics) -> Result<Self> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(Self::parse(source, &content, diagnostics))
+  }
+
+  pub fn parse(source: String, content: &str, diagnostics: &Diagnostics) -> Self {
+    let mut npmrc = Self {
+      source,
+      ..Self::default()
+    };
+    for (idx, line) in content.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+        continue;
+      }
+      let Some((key, value)) = line.split_once('=') else {
+        continue;
+      };
+      let (key, value) = (key.trim(), value.trim().trim_matches(|ch| ch == '"' || ch == '\''));
+      if let Some(scope) = key.strip_suffix(REGISTRY_SUFFIX).filter(|scope| scope.starts_with('@')) {
+        npmrc
+          .scopes
+          .insert(scope.to_lowercase(), value.trim_end_matches('/').to_owned());
+      } else if let Some(prefix) = key.strip_suffix(TOKEN_SUFFIX).filter(|prefix| prefix.starts_with("//")) {
+        match expand_env(value) {
+          Ok(token) if token.is_empty() => {},
+          Ok(token) => {
+            npmrc.tokens.insert(with_slash(prefix), token);
+          },
+          Err(var) => diagnostics.warn(
+            Category::ScanIssue,
+            format!(
+              "{}:{}: {} is not set, no token for {} from this file",
+              npmrc.source,
+              idx + 1,
+              var,
+              prefix
+            ),
+          ),
+        }
+      }
+    }
+    npmrc
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.scopes.is_empty() && self.tokens.is_empty()
+  }
+
+  // The token of the longest `//host/path/` prefix of `registry`, the way npm matches them
+  pub fn token_for(&self, registry: &str) -> Option<&str> {
+    let registry = with_slash(registry.split_once("://").map_or(registry, |(_, rest)| rest));
+    self
+      .tokens
+      .iter()
+      .filter(|(prefix, _)| registry.starts_with(&prefix[2..]))
+      .max_by_key(|(prefix, _)| prefix.len())
+      .map(|(_, token)| token.as_str())
+  }
+}
+
+fn with_slash(url: &str) -> String {
+  format!("{}/", url.trim_end_matches('/'))
+}
+
+// `${VAR}` references as npm expands them; the name of the first unset one otherwise
+fn expand_env(value: &str) -> Result<String, String> {
+  let mut expanded = String::new();
+  let mut rest = value;
+  while let Some(start) = rest.find("${") {
+    let Some(end) = rest[start..].find('}') else {
+      break;
+    };
+    // `${VAR?}` may be unset
+    let var = &rest[start + 2..start + end];
+    let value = match (env::var(var.trim_end_matches('?')), var.ends_with('?')) {
+      (Ok(value), _) => value,
+      (Err(_), true) => String::new(),
+      (Err(_), false) => return Err(var.to_owned()),
+    };
+    expanded.push_str(&rest[..start]);
+    expanded.push_str(&value);
+    rest = &rest[start + end + 1..];
+  }
+  expanded.push_str(rest);
+  Ok(expanded)
+}
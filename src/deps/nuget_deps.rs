@@ -0,0 +1,331 @@
+use super::{manifests::ManifestFilter, patterns::PatternSet, xml::Element};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry},
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap},
+  fs,
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+use walkdir::WalkDir;
+
+static PROJECT_KIND: &str = "*.csproj";
+static PROJECT_SUFFIX: &str = ".csproj";
+static LOCKFILE: &str = "packages.lock.json";
+// Central Package Management: <PackageVersion> items shared by every project below it
+static CENTRAL_VERSIONS: &str = "Directory.Packages.props";
+
+#[derive(Deserialize)]
+struct PackagesLock {
+  // Target framework -> package id -> entry
+  #[serde(default)]
+  dependencies: BTreeMap<String, BTreeMap<String, LockedPackage>>,
+}
+
+#[derive(Deserialize)]
+struct LockedPackage {
+  #[serde(rename = "type")]
+  kind: Option<String>,
+  resolved: Option<String>,
+}
+
+// What a walk of the tree found
+#[derive(Default)]
+struct NugetFiles {
+  projects: Vec<PathBuf>,
+  lockfiles: Vec<PathBuf>,
+  // Directory -> the versions its Directory.Packages.props pins
+  central: HashMap<PathBuf, HashMap<String, String>>,
+}
+
+// SDK-style .NET projects. packages.lock.json, when restore wrote one, gives every resolved version including the
+// transitive ones; otherwise the project's <PackageReference> items are read, their versions coming from the
+// closest Directory.Packages.props when the project leaves them to Central Package Management. Ranges resolve to
+// their lower bound and floating versions to the latest release, as NuGet itself picks them.
+pub struct NugetParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+}
+
+impl NugetParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+    })
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies = HashMap::new();
+    let files = self.find_files()?;
+
+    for path in &files.lockfiles {
+      println!("Processing file: {}", path.display());
+      self.parse_lockfile(path, &mut dependencies)?;
+    }
+    for path in &files.projects {
+      if files
+        .lockfiles
+        .iter()
+        .any(|lockfile| lockfile.parent() == path.parent())
+      {
+        continue;
+      }
+      println!("Processing file: {}", path.display());
+      self.parse_project(path, &files.central, &mut dependencies)?;
+    }
+
+    Ok(dependencies)
+  }
+
+  fn find_files(&self) -> Result<NugetFiles> {
+    let mut files = NugetFiles::default();
+    let entries = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| !entry.file_type().is_dir());
+    for entry in entries {
+      let path = entry.path();
+      if path.to_str().is_some_and(|path| self.exclude_patterns.is_match(path)) {
+        continue;
+      }
+      let name = entry.file_name().to_string_lossy();
+      if name.ends_with(PROJECT_SUFFIX) {
+        if self.manifests.allows(PROJECT_KIND, path) {
+          files.projects.push(path.to_path_buf());
+        }
+      } else if name == LOCKFILE {
+        if self.manifests.allows(LOCKFILE, path) {
+          files.lockfiles.push(path.to_path_buf());
+        }
+      } else if name == CENTRAL_VERSIONS && self.manifests.allows(CENTRAL_VERSIONS, path) {
+        println!("Processing file: {}", path.display());
+        if let Some(versions) = self.central_versions(path)? {
+          files
+            .central
+            .insert(path.parent().unwrap_or(&self.root_path).to_path_buf(), versions);
+        }
+      }
+    }
+    Ok(files)
+  }
+
+  // Every target framework's packages, direct and transitive; project references aren't packages
+  fn parse_lockfile(&self, path: &Path, dependencies: &mut HashMap<String, DepsEntry>) -> Result<()> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read lock file: {}", path.display()))?;
+    let source = self.source(path);
+    let lock = match serde_json::from_str::<PackagesLock>(&content) {
+      Ok(lock) => lock,
+      Err(err) => {
+        self
+          .diagnostics
+          .warn(Category::ParseFailure, format!("Can't parse {}: {}", source, err));
+        return Ok(());
+      },
+    };
+
+    for (id, package) in lock.dependencies.into_values().flatten() {
+      if package.kind.as_deref() == Some("Project") {
+        continue;
+      }
+      let Some(version) = package.resolved else {
+        continue;
+      };
+      DepsEntry {
+        name: id,
+        version,
+        scope: DepScope::Runtime,
+        sources: BTreeSet::from([source.clone()]),
+      }
+      .insert_into(dependencies);
+    }
+
+    Ok(())
+  }
+
+  fn parse_project(
+    &self,
+    path: &Path,
+    central: &HashMap<PathBuf, HashMap<String, String>>,
+    dependencies: &mut HashMap<String, DepsEntry>,
+  ) -> Result<()> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read project: {}", path.display()))?;
+    let source = self.source(path);
+    let project = match Element::parse(&content) {
+      Ok(project) => project,
+      Err(err) => {
+        self
+          .diagnostics
+          .warn(Category::ParseFailure, format!("Can't parse {}: {:#}", source, err));
+        return Ok(());
+      },
+    };
+    // MSBuild only imports the Directory.Packages.props closest to the project
+    let central = path
+      .ancestors()
+      .skip(1)
+      .take_while(|dir| dir.starts_with(&self.root_path))
+      .find_map(|dir| central.get(dir));
+
+    let references = project
+      .children("ItemGroup")
+      .flat_map(|group| group.children("PackageReference"));
+    for reference in references {
+      // `Update` items only change metadata of a reference made elsewhere
+      let Some(id) = reference.attribute("Include") else {
+        continue;
+      };
+      let declared = reference
+        .attribute_or_text("VersionOverride")
+        .or_else(|| reference.attribute_or_text("Version"))
+        .or_else(|| {
+          central.and_then(|versions| {
+            versions
+              .iter()
+              .find(|(name, _)| name.eq_ignore_ascii_case(&id))
+              .map(|(_, version)| version.clone())
+          })
+        });
+      let version = match declared {
+        Some(version) if version.contains("$(") => {
+          self.diagnostics.warn(
+            Category::ScanIssue,
+            format!(
+              "{} in {} takes its version from the MSBuild property {}, using the latest release",
+              id, source, version
+            ),
+          );
+          "*".to_owned()
+        },
+        Some(version) => requested_version(&version),
+        None => {
+          self.diagnostics.warn(
+            Category::ScanIssue,
+            format!(
+              "{} in {} has no version, neither its own nor from a {}; using the latest release",
+              id, source, CENTRAL_VERSIONS
+            ),
+          );
+          "*".to_owned()
+        },
+      };
+      DepsEntry {
+        name: id,
+        version,
+        scope: DepScope::Runtime,
+        sources: BTreeSet::from([source.clone()]),
+      }
+      .insert_into(dependencies);
+    }
+
+    Ok(())
+  }
+
+  // <PackageVersion Include="..." Version="..."/> items, None when the file can't be read as XML
+  fn central_versions(&self, path: &Path) -> Result<Option<HashMap<String, String>>> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let props = match Element::parse(&content) {
+      Ok(props) => props,
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::ParseFailure,
+          format!("Can't parse {}: {:#}", self.source(path), err),
+        );
+        return Ok(None);
+      },
+    };
+
+    Ok(Some(
+      props
+        .children("ItemGroup")
+        .flat_map(|group| group.children("PackageVersion"))
+        .filter_map(|item| Some((item.attribute("Include")?, item.attribute_or_text("Version")?)))
+        .collect(),
+    ))
+  }
+
+  fn source(&self, path: &Path) -> String {
+    match path.strip_prefix(&self.root_path) {
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => path.display().to_string(),
+    }
+  }
+}
+
+// NuGet restores the lowest version a range allows: `[1.2.0, 2.0)` and `1.2.0` (a minimum itself) give 1.2.0,
+// `[1.2.0]` pins it. A floating version (`1.*`) is kept for the report to resolve against the version list.
+fn requested_version(version: &str) -> String {
+  let version = version.trim();
+  if version.contains('*') {
+    return version.to_owned();
+  }
+  let lower = version
+    .trim_start_matches(['[', '('])
+    .split(',')
+    .next()
+    .unwrap_or_default()
+    .trim_end_matches([']', ')'])
+    .trim();
+  match lower {
+    // `(, 2.0]` has no lower bound
+    "" => "*".to_owned(),
+    lower => lower.to_owned(),
+  }
+}
+
+// The form the NuGet API keys versions by: lowercase, at least three parts, a zero fourth part and build metadata
+// dropped (1.0 -> 1.0.0, 1.0.0.0 -> 1.0.0, 2.1.0-RC.1 -> 2.1.0-rc.1)
+pub fn normalize_version(version: &str) -> String {
+  let version = version.split('+').next().unwrap_or(version).to_lowercase();
+  let (release, prerelease) = match version.split_once('-') {
+    Some((release, prerelease)) => (release, Some(prerelease)),
+    None => (version.as_str(), None),
+  };
+
+  let mut parts = release
+    .split('.')
+    .map(|part| {
+      part
+        .parse::<u64>()
+        .map_or_else(|_| part.to_owned(), |part| part.to_string())
+    })
+    .collect::<Vec<_>>();
+  while parts.len() < 3 {
+    parts.push("0".to_owned());
+  }
+  if parts.len() == 4 && parts[3] == "0" {
+    parts.pop();
+  }
+  match prerelease {
+    Some(prerelease) => format!("{}-{}", parts.join("."), prerelease),
+    None => parts.join("."),
+  }
+}
+
+// The newest release in a flat container version list (`{"versions": [...]}`, oldest first) that a floating
+// version allows: `*` any, `1.2.*` starting with 1.2. Prereleases only match a floating prerelease.
+pub fn floating_match<'a>(floating: &str, versions: impl DoubleEndedIterator<Item = &'a str>) -> Option<String> {
+  let prefix = floating.split('*').next().unwrap_or_default().to_lowercase();
+  let prerelease = floating.contains('-');
+  versions
+    .rev()
+    .find(|version| version.to_lowercase().starts_with(&prefix) && (prerelease || !version.contains('-')))
+    .map(str::to_owned)
+}
@@ -0,0 +1,197 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::{collections::HashSet, sync::Mutex};
+
+pub const COMMAND_LINE_REASON: &str = "(command line)";
+
+// One --exclude/--skip/--skip-scope/--skip-prefix value with what the config says about it
+#[derive(Debug, Clone)]
+pub struct ExclusionRule {
+  pub option: &'static str,
+  pub pattern: String,
+  pub reason: Option<String>,
+  pub added_by: Option<String>,
+  pub date: Option<String>,
+}
+
+// Patterns of one option, remembering the distinct paths or names each one filtered.
+// Parsers walk the tree more than once, so counting calls would overstate it.
+pub struct PatternSet {
+  patterns: Vec<Regex>,
+  filtered: Mutex<Vec<HashSet<String>>>,
+}
+
+impl PatternSet {
+  pub fn compile(source: &str, patterns: &Option<Vec<String>>) -> Result<Self> {
+    let patterns = compile_patterns(source, patterns)?;
+    Ok(Self {
+      filtered: Mutex::new(vec![HashSet::new(); patterns.len()]),
+      patterns,
+    })
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.patterns.is_empty()
+  }
+
+  // The first matching pattern gets the credit
+  pub fn is_match(&self, value: &str) -> bool {
+    let Some(idx) = self.patterns.iter().position(|pattern| pattern.is_match(value)) else {
+      return false;
+    };
+    if let Ok(mut filtered) = self.filtered.lock() {
+      filtered[idx].insert(value.to_owned());
+    }
+    true
+  }
+
+  // Distinct values filtered per pattern, in the order the patterns were given
+  pub fn filtered_counts(&self) -> Vec<usize> {
+    match self.filtered.lock() {
+      Ok(filtered) => filtered.iter().map(HashSet::len).collect(),
+      Err(_) => vec![0; self.patterns.len()],
+    }
+  }
+}
+
+// --skip-scope and --skip-prefix values, matched on whole name segments instead of as regexes: `@ourorg` skips
+// `@ourorg/ui` but not `@ourorg-community/ui`, `github.com/ourorg` skips `github.com/ourorg/api` but not
+// `github.com/ourorg-tools/api`. A trailing `/*` says the same thing; case is ignored, as GitHub and the Go case
+// merge do.
+#[derive(Default)]
+pub struct NamespaceSet {
+  // The option each namespace came from, for the counts
+  namespaces: Vec<(&'static str, String)>,
+  filtered: Mutex<Vec<HashSet<String>>>,
+}
+
+impl NamespaceSet {
+  // Checks every value of both options, reporting all malformed ones together
+  pub fn new(scopes: &Option<Vec<String>>, prefixes: &Option<Vec<String>>) -> Result<Self> {
+    let mut namespaces = Vec::new();
+    let mut errors = Vec::new();
+
+    for value in scopes.iter().flatten() {
+      let scope = namespace(value);
+      match scope.strip_prefix('@') {
+        Some(name) if !name.is_empty() && !name.contains(['/', '*']) => {
+          namespaces.push(("--skip-scope", scope.to_owned()))
+        },
+        _ => errors.push(format!(
+          "--skip-scope `{}`: expected an npm scope like `@ourorg`",
+          value
+        )),
+      }
+    }
+    for value in prefixes.iter().flatten() {
+      let prefix = namespace(value);
+      match prefix.is_empty() || prefix.starts_with('/') || prefix.contains("//") || prefix.contains('*') {
+        true => errors.push(format!(
+          "--skip-prefix `{}`: expected a module path prefix like `github.com/ourorg`",
+          value
+        )),
+        false => namespaces.push(("--skip-prefix", prefix.to_owned())),
+      }
+    }
+
+    if !errors.is_empty() {
+      return Err(anyhow!(
+        "Invalid {} namespace(s):\n  {}",
+        errors.len(),
+        errors.join("\n  ")
+      ));
+    }
+    Ok(Self {
+      filtered: Mutex::new(vec![HashSet::new(); namespaces.len()]),
+      namespaces,
+    })
+  }
+
+  // The name itself or anything below it; the first matching namespace gets the credit
+  pub fn is_match(&self, name: &str) -> bool {
+    let Some(idx) = self.namespaces.iter().position(|(_, namespace)| {
+      name
+        .get(..namespace.len())
+        .is_some_and(|head| head.eq_ignore_ascii_case(namespace))
+        && name[namespace.len()..].chars().next().is_none_or(|next| next == '/')
+    }) else {
+      return false;
+    };
+    if let Ok(mut filtered) = self.filtered.lock() {
+      filtered[idx].insert(name.to_owned());
+    }
+    true
+  }
+
+  // Distinct names each namespace of `option` skipped, in the order they were given
+  pub fn filtered_counts(&self, option: &str) -> Vec<usize> {
+    let Ok(filtered) = self.filtered.lock() else {
+      return Vec::new();
+    };
+    self
+      .namespaces
+      .iter()
+      .zip(filtered.iter())
+      .filter(|((source, _), _)| *source == option)
+      .map(|(_, names)| names.len())
+      .collect()
+  }
+}
+
+// `@ourorg/*` and `github.com/ourorg/` are `@ourorg` and `github.com/ourorg`
+fn namespace(value: &str) -> &str {
+  let value = value.trim();
+  value.strip_suffix("/*").unwrap_or(value).trim_end_matches('/')
+}
+
+// Compiles every pattern of one option, reporting all malformed ones together
+fn compile_patterns(source: &str, patterns: &Option<Vec<String>>) -> Result<Vec<Regex>> {
+  let mut compiled = Vec::new();
+  let mut errors = Vec::new();
+
+  for pattern in patterns.iter().flatten() {
+    match Regex::new(pattern) {
+      Ok(regex) => compiled.push(regex),
+      Err(err) => errors.push(describe(source, pattern, &err)),
+    }
+  }
+
+  if errors.is_empty() {
+    Ok(compiled)
+  } else {
+    Err(aggregate(errors))
+  }
+}
+
+// Validates the patterns of several options at once so every mistake shows up before scanning
+pub fn validate_patterns(sources: &[(&str, &Option<Vec<String>>)]) -> Result<()> {
+  let errors: Vec<String> = sources
+    .iter()
+    .flat_map(|(source, patterns)| {
+      patterns
+        .iter()
+        .flatten()
+        .filter_map(move |pattern| Regex::new(pattern).err().map(|err| describe(source, pattern, &err)))
+    })
+    .collect();
+
+  if errors.is_empty() {
+    Ok(())
+  } else {
+    Err(aggregate(errors))
+  }
+}
+
+fn describe(source: &str, pattern: &str, err: &regex::Error) -> String {
+  // Syntax errors span several lines with a caret under the offending position
+  let details = err.to_string().lines().collect::<Vec<_>>().join("\n    ");
+  format!("{} `{}`:\n    {}", source, pattern, details)
+}
+
+fn aggregate(errors: Vec<String>) -> anyhow::Error {
+  anyhow!(
+    "Failed to compile {} regex pattern(s):\n  {}",
+    errors.len(),
+    errors.join("\n  ")
+  )
+}
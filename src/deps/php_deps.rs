@@ -0,0 +1,283 @@
+use super::{manifests::ManifestFilter, patterns::PatternSet};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry},
+};
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap},
+  fs,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+};
+use walkdir::WalkDir;
+
+static LOCKFILE: &str = "composer.lock";
+static MANIFEST: &str = "composer.json";
+
+lazy_static! {
+  // Composer's tilde is PEP 440's: ~1.2 allows 1.x, ~1.2.3 allows 1.2.x
+  static ref TILDE_REGEX: Regex = Regex::new(r"~\s*([0-9][0-9A-Za-z.*+!-]*)").expect("Failed to compile tilde regex");
+  // `v1.2.3`, and stability flags like `@dev`
+  static ref PREFIX_REGEX: Regex = Regex::new(r"(^|[\s,|^~=<>])v([0-9])").expect("Failed to compile prefix regex");
+  static ref STABILITY_REGEX: Regex = Regex::new(r"@[A-Za-z]+").expect("Failed to compile stability regex");
+}
+
+#[derive(Deserialize)]
+struct ComposerLock {
+  #[serde(default)]
+  packages: Vec<LockEntry>,
+}
+
+#[derive(Deserialize)]
+struct LockEntry {
+  name: String,
+  version: String,
+  #[serde(flatten)]
+  package: ComposerPackage,
+}
+
+#[derive(Deserialize)]
+struct ComposerManifest {
+  #[serde(default)]
+  require: BTreeMap<String, String>,
+}
+
+// The metadata a composer.lock entry and a Packagist version share, all a row needs
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ComposerPackage {
+  #[serde(default)]
+  license: ComposerLicenses,
+  pub homepage: Option<String>,
+  pub source: Option<ComposerSource>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ComposerSource {
+  pub url: Option<String>,
+}
+
+// An array everywhere but in some old composer.json files
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum ComposerLicenses {
+  One(String),
+  Many(Vec<String>),
+}
+
+impl Default for ComposerLicenses {
+  fn default() -> Self {
+    ComposerLicenses::Many(Vec::new())
+  }
+}
+
+#[derive(Deserialize)]
+struct PackagistVersion {
+  version: String,
+  version_normalized: String,
+  #[serde(flatten)]
+  package: ComposerPackage,
+}
+
+impl ComposerPackage {
+  // Several licenses are alternatives, as Composer documents them
+  pub fn license(&self) -> Option<String> {
+    let licenses = match &self.license {
+      ComposerLicenses::One(license) => vec![license.as_str()],
+      ComposerLicenses::Many(licenses) => licenses.iter().map(String::as_str).collect(),
+    };
+    let licenses = licenses
+      .into_iter()
+      .map(str::trim)
+      .filter(|license| !license.is_empty())
+      .collect::<Vec<_>>();
+    Some(licenses.join(" OR ")).filter(|license| !license.is_empty())
+  }
+
+  pub fn source_url(&self) -> Option<String> {
+    let url = self.source.as_ref()?.url.as_deref()?;
+    Some(url.trim_end_matches('/').trim_end_matches(".git").to_owned())
+  }
+}
+
+// Composer projects: composer.lock's exact versions, whose entries already carry the license and source, or the
+// `require` constraints of a composer.json without a lock file, resolved against Packagist. Platform requirements
+// (`php`, `ext-*` and the like) aren't packages, and dev packages are left out like npm's devDependencies.
+pub struct PhpParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+  locked: Mutex<HashMap<(String, String), ComposerPackage>>,
+}
+
+impl PhpParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+      locked: Mutex::new(HashMap::new()),
+    })
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies = HashMap::new();
+
+    let entries = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| !entry.file_type().is_dir());
+    for entry in entries {
+      let path = entry.path();
+      if path.to_str().is_some_and(|path| self.exclude_patterns.is_match(path)) {
+        continue;
+      }
+      let name = entry.file_name().to_string_lossy();
+      if name == LOCKFILE && self.manifests.allows(LOCKFILE, path) {
+        println!("Processing file: {}", path.display());
+        self.parse_lockfile(path, &mut dependencies)?;
+      } else if name == MANIFEST && !path.with_file_name(LOCKFILE).is_file() && self.manifests.allows(MANIFEST, path) {
+        println!("Processing file: {}", path.display());
+        self.parse_manifest(path, &mut dependencies)?;
+      }
+    }
+
+    Ok(dependencies)
+  }
+
+  // composer.lock entries by name and version, resolved from the lock instead of Packagist
+  pub fn locked_packages(&self) -> HashMap<(String, String), ComposerPackage> {
+    self
+      .locked
+      .lock()
+      .map(|mut locked| std::mem::take(&mut *locked))
+      .unwrap_or_default()
+  }
+
+  fn parse_lockfile(&self, path: &Path, dependencies: &mut HashMap<String, DepsEntry>) -> Result<()> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read lock file: {}", path.display()))?;
+    let source = self.source(path);
+    let lock = match serde_json::from_str::<ComposerLock>(&content) {
+      Ok(lock) => lock,
+      Err(err) => {
+        self
+          .diagnostics
+          .warn(Category::ParseFailure, format!("Can't parse {}: {}", source, err));
+        return Ok(());
+      },
+    };
+
+    let mut locked = self.locked.lock().expect("Locked packages lock poisoned");
+    for entry in lock.packages {
+      locked.insert((entry.name.clone(), entry.version.clone()), entry.package);
+      DepsEntry {
+        name: entry.name,
+        version: entry.version,
+        scope: DepScope::Runtime,
+        sources: BTreeSet::from([source.clone()]),
+      }
+      .insert_into(dependencies);
+    }
+
+    Ok(())
+  }
+
+  fn parse_manifest(&self, path: &Path, dependencies: &mut HashMap<String, DepsEntry>) -> Result<()> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read manifest: {}", path.display()))?;
+    let source = self.source(path);
+    let manifest = match serde_json::from_str::<ComposerManifest>(&content) {
+      Ok(manifest) => manifest,
+      Err(err) => {
+        self
+          .diagnostics
+          .warn(Category::ParseFailure, format!("Can't parse {}: {}", source, err));
+        return Ok(());
+      },
+    };
+
+    // Packages are always `vendor/name`, platform requirements never are
+    for (name, constraint) in manifest.require.into_iter().filter(|(name, _)| name.contains('/')) {
+      DepsEntry {
+        name,
+        version: constraint,
+        scope: DepScope::Runtime,
+        sources: BTreeSet::from([source.clone()]),
+      }
+      .insert_into(dependencies);
+    }
+
+    Ok(())
+  }
+
+  fn source(&self, path: &Path) -> String {
+    match path.strip_prefix(&self.root_path) {
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => path.display().to_string(),
+    }
+  }
+}
+
+// The newest stable version in a Packagist `p2/<name>.json` response that a composer.json constraint allows (None
+// when none does or the constraint isn't understood), with its metadata
+pub fn packagist_version(body: &Value, name: &str, constraint: &str) -> Option<(String, ComposerPackage)> {
+  let versions = expand_minified(body.get("packages")?.get(name)?.as_array()?)
+    .into_iter()
+    .filter_map(|version| serde_json::from_value::<PackagistVersion>(Value::Object(version)).ok())
+    .collect::<Vec<_>>();
+  let constraint = pep440_constraint(constraint);
+  let normalized = super::py_deps::highest_matching(
+    &constraint,
+    versions.iter().map(|version| version.version_normalized.as_str()),
+  )?;
+
+  versions
+    .into_iter()
+    .find(|version| version.version_normalized == normalized)
+    .map(|version| (version.version, version.package))
+}
+
+// Packagist sends the first version in full and each following one as the fields that changed from the one
+// before, `__unset` removing a field
+fn expand_minified(versions: &[Value]) -> Vec<Map<String, Value>> {
+  let mut current = Map::new();
+  versions
+    .iter()
+    .filter_map(Value::as_object)
+    .map(|changes| {
+      for (key, value) in changes {
+        match value.as_str() == Some("__unset") {
+          true => current.remove(key),
+          false => current.insert(key.clone(), value.clone()),
+        };
+      }
+      current.clone()
+    })
+    .collect()
+}
+
+// `^1.2 || ~2.1@dev`, `>=1.0 <2.0`, `v1.2.3` in the syntax `highest_matching` reads
+fn pep440_constraint(constraint: &str) -> String {
+  let constraint = STABILITY_REGEX.replace_all(constraint, "");
+  let constraint = PREFIX_REGEX.replace_all(&constraint, "$1$2");
+  TILDE_REGEX
+    .replace_all(&constraint, |captures: &Captures| match captures[1].contains('.') {
+      true => format!("~={}", &captures[1]),
+      false => format!("^{}", &captures[1]),
+    })
+    .into_owned()
+}
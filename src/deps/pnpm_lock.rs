@@ -0,0 +1,184 @@
+use super::{yaml::Node, yarn_lock::Locked};
+use crate::version::Version;
+use anyhow::{anyhow, bail, Context, Result};
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+  fs,
+  path::{Path, PathBuf},
+};
+
+pub const PNPM_LOCK_FILE: &str = "pnpm-lock.yaml";
+// Sections of an importer, or of an installed package, that the runtime gets
+const RUNTIME_SECTIONS: &[&str] = &["dependencies", "optionalDependencies"];
+const IMPORTER_SECTIONS: &[&str] = &["dependencies", "optionalDependencies", "devDependencies"];
+
+// Exact versions a pnpm workspace installed. Each importer (a package.json's directory, `.` for the lockfile's
+// own) lists its dependencies with the range asked for and what it resolved to:
+//   importers:
+//     packages/app:
+//       dependencies:
+//         react-dom:
+//           specifier: ^18.2.0
+//           version: 18.2.0(react@18.2.0)
+// The `(react@18.2.0)` suffix names the peers the copy was installed for. lockfileVersion 6 keys the installed
+// packages `/name@version` under `packages:`; 9 drops the slash and moves their dependencies to `snapshots:`.
+pub struct PnpmLock {
+  dir: PathBuf,
+  // Importer directory -> dependency name -> what it's locked to
+  importers: HashMap<String, HashMap<String, Locked>>,
+  // What the importers' dependencies and optionalDependencies install, theirs included: (name, version, only
+  // installed as an optional dependency)
+  installed: Vec<(String, String, bool)>,
+}
+
+impl PnpmLock {
+  pub fn read(path: &Path) -> Result<Self> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let root = Node::parse(&content)?;
+    let version = root
+      .str("lockfileVersion")
+      .ok_or_else(|| anyhow!("{} has no lockfileVersion", path.display()))?;
+    // v6 keys installed packages `/name@version`, v9 `name@version` with their dependencies in another section
+    let (section, prefix) = match version.split('.').next() {
+      Some("6") => ("packages", "/"),
+      Some("9") => ("snapshots", ""),
+      _ => bail!("lockfileVersion {} is not supported, only 6 and 9 are", version),
+    };
+
+    // A lockfileVersion 6 project without workspaces has its one importer's sections at the top
+    let importers: Vec<(&str, &Node)> = match root.get("importers") {
+      Some(importers) => importers
+        .entries
+        .iter()
+        .map(|(dir, node)| (dir.as_str(), node))
+        .collect(),
+      None => vec![(".", &root)],
+    };
+    let snapshots: BTreeMap<&str, &Node> = root
+      .get(section)
+      .map(|snapshots| {
+        snapshots
+          .entries
+          .iter()
+          .map(|(key, node)| (key.as_str(), node))
+          .collect()
+      })
+      .unwrap_or_default();
+
+    Ok(Self {
+      dir: path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+      importers: importers
+        .iter()
+        .map(|(dir, importer)| (dir.to_string(), direct_dependencies(importer)))
+        .collect(),
+      installed: installed(&importers, &snapshots, prefix),
+    })
+  }
+
+  // What `name` is locked to for the package.json in `package_dir`. None when the lockfile, a directory above,
+  // doesn't have the package.json as an importer.
+  pub fn lookup(&self, package_dir: &Path, name: &str) -> Option<&Locked> {
+    let member = package_dir.strip_prefix(&self.dir).ok()?;
+    let importer = match member.components().count() {
+      0 => ".".to_owned(),
+      _ => member
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/"),
+    };
+    self.importers.get(&importer)?.get(name)
+  }
+
+  pub fn installed(&self) -> &[(String, String, bool)] {
+    &self.installed
+  }
+}
+
+fn direct_dependencies(importer: &Node) -> HashMap<String, Locked> {
+  IMPORTER_SECTIONS
+    .iter()
+    .filter_map(|section| importer.get(section))
+    .flat_map(|section| &section.entries)
+    .filter_map(|(name, dependency)| Some((name.clone(), locked(dependency_version(dependency)?)?)))
+    .collect()
+}
+
+// v6 and v9 give `specifier` and `version` per dependency, earlier ones the version alone
+fn dependency_version(dependency: &Node) -> Option<&str> {
+  dependency.value.as_deref().or_else(|| dependency.str("version"))
+}
+
+// `link:` points at a workspace package, `file:` at a directory or tarball on the disk; the registry has neither.
+// Aliases (`/other@1.0.0`, `other@1.0.0`), git and tarball URLs resolve to nothing the registry has under the name.
+fn locked(version: &str) -> Option<Locked> {
+  if version.starts_with("link:") || version.starts_with("file:") {
+    return Some(Locked::Local);
+  }
+  let version = strip_peers(version);
+  Version::parse(version)
+    .is_ok()
+    .then(|| Locked::Version(version.to_owned()))
+}
+
+// `18.2.0(react@18.2.0)(@types/react@18.2.0)` -> `18.2.0`
+fn strip_peers(version: &str) -> &str {
+  version.split('(').next().unwrap_or(version)
+}
+
+// The runtime packages the importers install, walked from their dependencies through each installed package's own
+fn installed(
+  importers: &[(&str, &Node)],
+  snapshots: &BTreeMap<&str, &Node>,
+  prefix: &str,
+) -> Vec<(String, String, bool)> {
+  let mut queue = importers
+    .iter()
+    .flat_map(|(_, importer)| runtime_dependencies(importer, prefix))
+    .collect::<VecDeque<_>>();
+  let mut seen = BTreeSet::new();
+  while let Some(key) = queue.pop_front() {
+    if !seen.insert(key.clone()) {
+      continue;
+    }
+    if let Some(snapshot) = snapshots.get(key.as_str()) {
+      queue.extend(runtime_dependencies(snapshot, prefix));
+    }
+  }
+
+  seen
+    .iter()
+    .filter_map(|key| {
+      let (name, version) = strip_peers(&key[prefix.len()..]).rsplit_once('@')?;
+      Version::parse(version).ok()?;
+      let optional = snapshots
+        .get(key.as_str())
+        .and_then(|snapshot| snapshot.str("optional"))
+        == Some("true");
+      Some((name.to_owned(), version.to_owned(), optional))
+    })
+    .collect()
+}
+
+// The snapshot keys of what an importer or an installed package depends on at runtime. A dependency's version is
+// the key's own part, or for an alias the whole key without its prefix.
+fn runtime_dependencies(node: &Node, prefix: &str) -> Vec<String> {
+  RUNTIME_SECTIONS
+    .iter()
+    .filter_map(|section| node.get(section))
+    .flat_map(|section| &section.entries)
+    .filter_map(|(name, dependency)| {
+      let version = dependency_version(dependency)?;
+      if version.starts_with("link:") || version.starts_with("file:") {
+        return None;
+      }
+      let version = version.strip_prefix('/').unwrap_or(version);
+      Some(
+        match version.get(1..).is_some_and(|rest| strip_peers(rest).contains('@')) {
+          true => format!("{}{}", prefix, version),
+          false => format!("{}{}@{}", prefix, name, version),
+        },
+      )
+    })
+    .collect()
+}
@@ -0,0 +1,901 @@
+use super::{license_text, local_path, manifests::ManifestFilter, patterns::PatternSet};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use std::{
+  cmp::Ordering,
+  collections::{btree_map, BTreeMap, BTreeSet, HashMap, HashSet},
+  fs,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+};
+use walkdir::{DirEntry, WalkDir};
+
+// The manifest kind covering every `requirements*.txt`
+static REQUIREMENTS_FILE: &str = "requirements.txt";
+static PYPROJECT_FILE: &str = "pyproject.toml";
+static POETRY_LOCK_FILE: &str = "poetry.lock";
+static PIPFILE: &str = "Pipfile";
+static PIPFILE_LOCK: &str = "Pipfile.lock";
+
+lazy_static! {
+  // One comparator of a PEP 440 or Poetry constraint, e.g. `^1.2`, `>= 2.0`, `==1.4.*`
+  static ref COMPARATOR_REGEX: Regex = Regex::new(r"(\^|~=|~|===|==|!=|>=|<=|>|<)?\s*([0-9][0-9A-Za-z.*+!-]*|\*)")
+    .expect("Failed to compile version comparator regex");
+}
+
+// One requirement line, with its continuations joined and comments, hashes and markers gone
+enum Line {
+  // `specifiers` is what follows the name and extras, e.g. `>=1.2,<2`
+  Requirement { name: String, specifiers: String },
+  // `-r other.txt` / `--requirement other.txt`
+  Include(String),
+  // `name @ https://...`, `-e ./local`, `git+https://...`: not on PyPI
+  Direct(String),
+  Skip,
+}
+
+#[derive(Default, Deserialize)]
+struct Pyproject {
+  project: Option<ProjectTable>,
+  tool: Option<ToolTable>,
+}
+
+// PEP 621 `[project]`
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ProjectTable {
+  #[serde(default)]
+  dependencies: Vec<String>,
+  #[serde(default)]
+  optional_dependencies: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Default, Deserialize)]
+struct ToolTable {
+  poetry: Option<PoetryTable>,
+}
+
+// Only the main `[tool.poetry.dependencies]`; dev groups don't ship, like npm devDependencies
+#[derive(Default, Deserialize)]
+struct PoetryTable {
+  #[serde(default)]
+  dependencies: BTreeMap<String, PoetryDependency>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PoetryDependency {
+  Version(String),
+  Detailed(PoetryDetailed),
+  // One entry per marker, e.g. different versions per Python version
+  Multiple(Vec<PoetryDetailed>),
+}
+
+#[derive(Default, Deserialize)]
+struct PoetryDetailed {
+  version: Option<String>,
+  optional: Option<bool>,
+  git: Option<String>,
+  url: Option<String>,
+  path: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PoetryLock {
+  #[serde(default)]
+  package: Vec<LockedPackage>,
+}
+
+#[derive(Deserialize)]
+struct LockedPackage {
+  name: String,
+  version: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Pipfile {
+  #[serde(default)]
+  packages: BTreeMap<String, PipfileEntry>,
+  #[serde(default)]
+  dev_packages: BTreeMap<String, PipfileEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PipfileEntry {
+  Version(String),
+  Detailed(PipenvPackage),
+}
+
+// A Pipfile entry or a Pipfile.lock one, which pins `version` to `==x.y.z` and `ref` to a commit
+#[derive(Default, Deserialize)]
+struct PipenvPackage {
+  version: Option<String>,
+  git: Option<String>,
+  #[serde(rename = "ref")]
+  reference: Option<String>,
+  path: Option<String>,
+  // An archive or wheel URL
+  file: Option<String>,
+}
+
+impl PipenvPackage {
+  fn is_registry(&self) -> bool {
+    self.git.is_none() && self.path.is_none() && self.file.is_none()
+  }
+}
+
+#[derive(Deserialize)]
+struct PipfileLock {
+  #[serde(default)]
+  default: BTreeMap<String, PipenvPackage>,
+  #[serde(default)]
+  develop: BTreeMap<String, PipenvPackage>,
+}
+
+// The packages of one pipenv project and the file they were read from
+struct PipenvProject {
+  dir: PathBuf,
+  source: String,
+  packages: Vec<(String, PipenvPackage, DepScope)>,
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+  Ge,
+  Gt,
+  Le,
+  Lt,
+  Eq,
+  Ne,
+  // `==1.4.*`: the release starts with the bound
+  Prefix,
+  NotPrefix,
+}
+
+pub struct PyParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+  include_pipenv_dev: bool,
+  // Each with the manifest it was found in
+  direct: Mutex<BTreeMap<String, (String, ReportRow)>>,
+}
+
+impl PyParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+      include_pipenv_dev: false,
+      direct: Mutex::new(BTreeMap::new()),
+    })
+  }
+
+  // Pipfile.lock `develop` and Pipfile `[dev-packages]` entries, listed with the dev scope
+  pub fn include_pipenv_dev(mut self, include: bool) -> Self {
+    self.include_pipenv_dev = include;
+    self
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies = HashMap::new();
+    let mut visited = HashSet::new();
+
+    let manifest_files = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter_map(|entry| Some((self.manifest_kind(&entry)?, entry)));
+
+    for (kind, entry) in manifest_files {
+      if kind == PYPROJECT_FILE {
+        self.parse_pyproject(entry.path(), &mut dependencies)?;
+      } else if kind == REQUIREMENTS_FILE {
+        self.parse_file(entry.path(), &mut visited, &mut dependencies)?;
+      } else if let Some(project) = self.pipenv_project(kind, entry.path())? {
+        for (name, package, scope) in &project.packages {
+          let name = normalize_name(name);
+          // Git, path and file entries aren't on PyPI
+          if !package.is_registry() {
+            let mut direct = self.direct.lock().expect("Direct references lock poisoned");
+            if let btree_map::Entry::Vacant(slot) = direct.entry(name.clone()) {
+              if let Some(row) = self.direct_row(&project, &name, package, *scope) {
+                slot.insert((project.source.clone(), row));
+              }
+            }
+            continue;
+          }
+          DepsEntry {
+            name,
+            version: package.version.as_deref().map_or_else(|| "*".to_owned(), range_version),
+            scope: *scope,
+            sources: BTreeSet::from([project.source.clone()]),
+          }
+          .insert_into(&mut dependencies);
+        }
+      }
+    }
+
+    Ok(dependencies)
+  }
+
+  // Pipenv packages installed from git, a local path or an archive URL, found by `parse` and reported with what the
+  // lock file and the path's license file tell instead of a PyPI lookup that would 404
+  pub fn direct_references(&self) -> Vec<(String, ReportRow)> {
+    self
+      .direct
+      .lock()
+      .map(|mut rows| std::mem::take(&mut *rows).into_values().collect())
+      .unwrap_or_default()
+  }
+
+  // A Pipfile with its sibling Pipfile.lock's pins when there is one, or a Pipfile.lock on its own. A lock next to a
+  // Pipfile is read through the Pipfile, so each project is read once.
+  fn pipenv_project(&self, kind: &str, path: &Path) -> Result<Option<PipenvProject>> {
+    let dir = path.parent().unwrap_or(&self.root_path).to_path_buf();
+    let lock_path = dir.join(PIPFILE_LOCK);
+    if kind == PIPFILE_LOCK && dir.join(PIPFILE).is_file() {
+      return Ok(None);
+    }
+
+    if lock_path.is_file() && (kind == PIPFILE_LOCK || self.manifests.allows(PIPFILE_LOCK, &lock_path)) {
+      println!("Processing file: {}", lock_path.display());
+      let lock = fs::read(&lock_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| Ok(serde_json::from_slice::<PipfileLock>(&content)?));
+      match lock {
+        Ok(lock) => {
+          let mut packages = lock
+            .default
+            .into_iter()
+            .map(|(name, package)| (name, package, DepScope::Runtime))
+            .collect::<Vec<_>>();
+          if self.include_pipenv_dev {
+            packages.extend(
+              lock
+                .develop
+                .into_iter()
+                .map(|(name, package)| (name, package, DepScope::Dev)),
+            );
+          }
+          return Ok(Some(PipenvProject {
+            source: self.source(&lock_path),
+            dir,
+            packages,
+          }));
+        },
+        Err(err) => self.diagnostics.warn(
+          Category::ParseFailure,
+          format!("Can't read {}, using the Pipfile: {}", lock_path.display(), err),
+        ),
+      }
+    }
+    if kind == PIPFILE_LOCK {
+      return Ok(None);
+    }
+
+    println!("Processing file: {}", path.display());
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read Pipfile: {}", path.display()))?;
+    let pipfile = match toml::from_str::<Pipfile>(&content) {
+      Ok(pipfile) => pipfile,
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::ParseFailure,
+          format!("Can't parse {}: {}", path.display(), err),
+        );
+        return Ok(None);
+      },
+    };
+    let entry = |entry: PipfileEntry| match entry {
+      PipfileEntry::Version(version) => PipenvPackage {
+        version: Some(version),
+        ..Default::default()
+      },
+      PipfileEntry::Detailed(package) => package,
+    };
+    let mut packages = pipfile
+      .packages
+      .into_iter()
+      .map(|(name, package)| (name, entry(package), DepScope::Runtime))
+      .collect::<Vec<_>>();
+    if self.include_pipenv_dev {
+      packages.extend(
+        pipfile
+          .dev_packages
+          .into_iter()
+          .map(|(name, package)| (name, entry(package), DepScope::Dev)),
+      );
+    }
+
+    Ok(Some(PipenvProject {
+      source: self.source(path),
+      dir,
+      packages,
+    }))
+  }
+
+  // None for the project itself (`-e .`)
+  fn direct_row(
+    &self,
+    project: &PipenvProject,
+    name: &str,
+    package: &PipenvPackage,
+    scope: DepScope,
+  ) -> Option<ReportRow> {
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Pypi,
+      name: name.to_owned(),
+      version: package.reference.clone().unwrap_or_else(|| "*".to_owned()),
+      scope,
+      homepage: String::new(),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+    let relative = |path: &Path| path.strip_prefix(&self.root_path).unwrap_or(path).display().to_string();
+
+    if let Some(location) = package.git.as_ref().or(package.file.as_ref()) {
+      let location = location.strip_prefix("git+").unwrap_or(location);
+      row.homepage = location.trim_end_matches(".git").to_owned();
+      row
+        .provenance
+        .push(format!("installed from {} ({})", location, project.source));
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!(
+          "{} in {} is installed from {}, its license isn't looked up",
+          name, project.source, location
+        ),
+      );
+      return Some(row);
+    }
+
+    let path = package.path.as_deref().unwrap_or(".");
+    let target = match local_path::safe_join(&self.root_path, &project.dir, path) {
+      Ok(target) => target,
+      Err(refusal) => {
+        self.diagnostics.warn(
+          refusal.category(),
+          format!(
+            "{} in {} is installed from {}, which {}",
+            name, project.source, path, refusal
+          ),
+        );
+        row.provenance.push(format!("unresolved local path: {}", path));
+        return Some(row);
+      },
+    };
+    if fs::canonicalize(&project.dir).is_ok_and(|dir| dir == target) {
+      return None;
+    }
+
+    row.homepage = relative(&target);
+    row.provenance.push(format!("local path: {}", relative(&target)));
+    match license_text::read_license_file(&self.root_path, &target) {
+      Some((file, text)) => match license_text::classify(&text) {
+        Some(license) => {
+          row.license = Some(license.to_owned());
+          row.license_confidence = Some(LicenseConfidence::Detected);
+          row.provenance.push(format!("license file: {}", relative(&file)));
+        },
+        None => {
+          self.diagnostics.warn(
+            Category::UnknownLicense,
+            format!(
+              "License file {} of {} is not a license we recognize",
+              relative(&file),
+              name
+            ),
+          );
+          row
+            .provenance
+            .push(format!("license file {} not recognized", relative(&file)));
+        },
+      },
+      None => row.provenance.push("no license file in the local path".to_owned()),
+    }
+    Some(row)
+  }
+
+  // PEP 621 and Poetry dependencies, at the versions a sibling poetry.lock pins when there is one. Without a lock
+  // ranges are kept as they are and resolved against PyPI's release list.
+  fn parse_pyproject(&self, path: &Path, dependencies: &mut HashMap<String, DepsEntry>) -> Result<()> {
+    println!("Processing file: {}", path.display());
+
+    let content =
+      fs::read_to_string(path).with_context(|| format!("Failed to read pyproject.toml file: {}", path.display()))?;
+    let pyproject = match toml::from_str::<Pyproject>(&content) {
+      Ok(pyproject) => pyproject,
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::ParseFailure,
+          format!("Can't parse {}: {}", path.display(), err),
+        );
+        return Ok(());
+      },
+    };
+    let source = self.source(path);
+    let lock = self.read_lock(&path.with_file_name(POETRY_LOCK_FILE));
+    let mut insert = |name: &str, range: String, scope: DepScope| {
+      let name = normalize_name(name);
+      let version = lock.as_ref().and_then(|lock| lock.get(&name).cloned()).unwrap_or(range);
+      DepsEntry {
+        name,
+        version,
+        scope,
+        sources: BTreeSet::from([source.clone()]),
+      }
+      .insert_into(dependencies);
+    };
+
+    let project = pyproject.project.unwrap_or_default();
+    let optional = project.optional_dependencies.values().flatten();
+    let requirements = project
+      .dependencies
+      .iter()
+      .map(|requirement| (requirement, DepScope::Runtime))
+      .chain(optional.map(|requirement| (requirement, DepScope::Optional)));
+    for (requirement, scope) in requirements {
+      match parse_line(requirement) {
+        Line::Requirement { name, specifiers } => insert(&name, range_version(&specifiers), scope),
+        Line::Direct(requirement) => self.warn_direct(&requirement, &source),
+        Line::Include(_) | Line::Skip => {},
+      }
+    }
+
+    let poetry = pyproject.tool.and_then(|tool| tool.poetry).unwrap_or_default();
+    for (name, dependency) in poetry.dependencies {
+      // The interpreter constraint, not a package
+      if name.eq_ignore_ascii_case("python") {
+        continue;
+      }
+      let dependency = match dependency {
+        PoetryDependency::Version(version) => PoetryDetailed {
+          version: Some(version),
+          ..Default::default()
+        },
+        PoetryDependency::Detailed(detailed) => detailed,
+        PoetryDependency::Multiple(alternatives) => alternatives.into_iter().next().unwrap_or_default(),
+      };
+      if dependency.path.is_some() {
+        continue;
+      }
+      if let Some(location) = dependency.git.as_ref().or(dependency.url.as_ref()) {
+        self.warn_direct(&format!("{} @ {}", name, location), &source);
+        continue;
+      }
+      let scope = match dependency.optional {
+        Some(true) => DepScope::Optional,
+        _ => DepScope::Runtime,
+      };
+      insert(
+        &name,
+        poetry_version(dependency.version.as_deref().unwrap_or("*")),
+        scope,
+      );
+    }
+
+    Ok(())
+  }
+
+  // Locked versions by normalized name, None without a lock file; an unreadable one is warned about and ignored
+  fn read_lock(&self, path: &Path) -> Option<HashMap<String, String>> {
+    if !path.is_file() {
+      return None;
+    }
+    let lock = fs::read_to_string(path)
+      .map_err(anyhow::Error::from)
+      .and_then(|content| Ok(toml::from_str::<PoetryLock>(&content)?));
+    match lock {
+      Ok(lock) => Some(
+        lock
+          .package
+          .into_iter()
+          .map(|package| (normalize_name(&package.name), package.version))
+          .collect(),
+      ),
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::ParseFailure,
+          format!(
+            "Can't read {}, using the pyproject.toml ranges: {}",
+            path.display(),
+            err
+          ),
+        );
+        None
+      },
+    }
+  }
+
+  // Includes are followed from the including file's directory, each file is read once however often it's included
+  fn parse_file(
+    &self,
+    path: &Path,
+    visited: &mut HashSet<PathBuf>,
+    dependencies: &mut HashMap<String, DepsEntry>,
+  ) -> Result<()> {
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if !visited.insert(canonical) {
+      return Ok(());
+    }
+    println!("Processing file: {}", path.display());
+
+    let content =
+      fs::read_to_string(path).with_context(|| format!("Failed to read requirements file: {}", path.display()))?;
+    let source = self.source(path);
+
+    for line in logical_lines(&content) {
+      match parse_line(&line) {
+        Line::Requirement { name, specifiers } => DepsEntry {
+          name,
+          version: pinned_version(&specifiers),
+          scope: DepScope::Runtime,
+          sources: BTreeSet::from([source.clone()]),
+        }
+        .insert_into(dependencies),
+        Line::Include(include) => {
+          let include_path =
+            match local_path::safe_join(&self.root_path, path.parent().unwrap_or(&self.root_path), &include) {
+              Ok(include_path) if include_path.is_file() => include_path,
+              Ok(_) => {
+                self.diagnostics.warn(
+                  Category::ScanIssue,
+                  format!("{} includes {}, which is not a file", source, include),
+                );
+                continue;
+              },
+              Err(refusal) => {
+                self.diagnostics.warn(
+                  refusal.category(),
+                  format!("{} includes {}, which {}", source, include, refusal),
+                );
+                continue;
+              },
+            };
+          self.parse_file(&include_path, visited, dependencies)?;
+        },
+        Line::Direct(requirement) => self.warn_direct(&requirement, &source),
+        Line::Skip => {},
+      }
+    }
+
+    Ok(())
+  }
+
+  fn warn_direct(&self, requirement: &str, source: &str) {
+    self.diagnostics.warn(
+      Category::ScanIssue,
+      format!(
+        "`{}` in {} is not installed from PyPI; list it as a manual component",
+        requirement, source
+      ),
+    );
+  }
+
+  fn source(&self, path: &Path) -> String {
+    match path.strip_prefix(&self.root_path) {
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => path.display().to_string(),
+    }
+  }
+
+  fn manifest_kind(&self, entry: &DirEntry) -> Option<&'static str> {
+    // Skip directories and hidden files
+    if entry.file_type().is_dir() || entry.file_name().to_str().is_some_and(|s| s.starts_with('.')) {
+      return None;
+    }
+
+    // Skip excluded paths
+    if let Some(path_str) = entry.path().to_str() {
+      if self.exclude_patterns.is_match(path_str) {
+        return None;
+      }
+    }
+
+    let name = entry.file_name().to_str()?;
+    let kind = if let Some(kind) = [PYPROJECT_FILE, PIPFILE, PIPFILE_LOCK]
+      .into_iter()
+      .find(|kind| *kind == name)
+    {
+      kind
+    } else if name.starts_with("requirements") && name.ends_with(".txt") {
+      // requirements.txt, requirements-dev.txt, requirements_test.txt and the like
+      REQUIREMENTS_FILE
+    } else {
+      return None;
+    };
+    self.manifests.allows(kind, entry.path()).then_some(kind)
+  }
+}
+
+// Lines with their `\` continuations joined and comments dropped
+fn logical_lines(content: &str) -> Vec<String> {
+  let mut lines = Vec::new();
+  let mut current = String::new();
+  for line in content.lines() {
+    // A `#` only starts a comment at the start of the line or after whitespace, URLs may carry fragments
+    let line = match line.find(" #").or_else(|| line.starts_with('#').then_some(0)) {
+      Some(idx) => &line[..idx],
+      None => line,
+    };
+    match line.trim_end().strip_suffix('\\') {
+      Some(continued) => {
+        current.push_str(continued);
+        current.push(' ');
+      },
+      None => {
+        current.push_str(line);
+        lines.push(std::mem::take(&mut current));
+      },
+    }
+  }
+  lines.push(current);
+  lines.retain(|line| !line.trim().is_empty());
+  lines
+}
+
+fn parse_line(line: &str) -> Line {
+  let line = line.trim();
+  if let Some(option) = line.strip_prefix('-') {
+    let (flag, value) = match option.split_once(['=', ' ']) {
+      Some((flag, value)) => (flag, value.trim()),
+      None => (option, ""),
+    };
+    return match flag {
+      "r" | "-requirement" => Line::Include(value.to_owned()),
+      "e" | "-editable" => Line::Direct(value.to_owned()),
+      // Constraints, index URLs and the like don't add packages
+      _ => Line::Skip,
+    };
+  }
+
+  // Per-requirement options such as `--hash=sha256:...` follow the requirement, markers follow a `;`
+  let requirement = line.split(" --").next().unwrap_or(line);
+  let requirement = requirement.split(';').next().unwrap_or(requirement).trim();
+  if requirement.contains("://") || requirement.contains(" @ ") || requirement.starts_with('.') {
+    return Line::Direct(requirement.to_owned());
+  }
+
+  let name_end = requirement
+    .find(|ch: char| !(ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.')))
+    .unwrap_or(requirement.len());
+  let (name, rest) = requirement.split_at(name_end);
+  if name.is_empty() {
+    return Line::Skip;
+  }
+  // Extras don't change which distribution is installed
+  let rest = match rest.trim_start().strip_prefix('[') {
+    Some(extras) => extras.split_once(']').map_or("", |(_, rest)| rest),
+    None => rest,
+  };
+
+  Line::Requirement {
+    name: normalize_name(name),
+    specifiers: rest.trim().to_owned(),
+  }
+}
+
+// `==1.2`, `===1.2` pin exactly; `>=1.2` and `~=1.2` give their lower bound; anything else resolves the latest release
+fn pinned_version(specifiers: &str) -> String {
+  let specifiers = specifiers.replace(' ', "");
+  let mut lower = None;
+  for specifier in specifiers.split(',') {
+    if let Some(version) = specifier.strip_prefix("===").or_else(|| specifier.strip_prefix("==")) {
+      if !version.contains('*') {
+        return version.to_owned();
+      }
+    } else if let Some(version) = specifier.strip_prefix(">=").or_else(|| specifier.strip_prefix("~=")) {
+      lower = Some(version.to_owned());
+    }
+  }
+  lower.unwrap_or_else(|| "*".to_owned())
+}
+
+// An exact pin as is, anything else kept as a range for `highest_matching`
+fn range_version(specifiers: &str) -> String {
+  let specifiers = specifiers.replace(' ', "");
+  let specifiers = specifiers.trim_start_matches('(').trim_end_matches(')');
+  if specifiers.is_empty() {
+    return "*".to_owned();
+  }
+  match specifiers.strip_prefix("===").or_else(|| specifiers.strip_prefix("==")) {
+    Some(version) if !is_range(version) => version.to_owned(),
+    _ => specifiers.to_owned(),
+  }
+}
+
+// Poetry reads a bare `1.2.3` as `==1.2.3`
+fn poetry_version(constraint: &str) -> String {
+  let constraint = constraint.trim();
+  let exact = constraint.strip_prefix("==").or_else(|| constraint.strip_prefix('='));
+  match exact.unwrap_or(constraint).trim() {
+    "" => "*".to_owned(),
+    version if !is_range(version) => version.to_owned(),
+    _ => constraint.to_owned(),
+  }
+}
+
+// Whether a version from the parser is a constraint PyPI has to be asked about rather than a release
+pub fn is_range(version: &str) -> bool {
+  version != "*"
+    && !version
+      .chars()
+      .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '.' | '+' | '!' | '-'))
+}
+
+// The newest final release that satisfies a PEP 440 or Poetry constraint (`^1.2`, `~1.2`, `~=1.2`, `>=1,<2`,
+// `1.4.*`, `|`-separated alternatives). Pre-, post- and dev releases are never picked.
+pub fn highest_matching<'a>(constraint: &str, versions: impl Iterator<Item = &'a str>) -> Option<String> {
+  let alternatives = constraint
+    .split('|')
+    .filter(|alternative| !alternative.trim().is_empty())
+    .map(comparators)
+    .collect::<Option<Vec<_>>>()?;
+
+  versions
+    .filter_map(|version| Some((release(version)?, version)))
+    .filter(|(release, _)| {
+      alternatives
+        .iter()
+        .any(|comparators| comparators.iter().all(|(op, bound)| satisfies(release, *op, bound)))
+    })
+    .max_by(|(a, _), (b, _)| compare_releases(a, b))
+    .map(|(_, version)| version.to_owned())
+}
+
+// Caret and tilde are expanded into the bounds they stand for; None when part of the constraint isn't understood
+fn comparators(constraint: &str) -> Option<Vec<(Op, Vec<u64>)>> {
+  let mut comparators = Vec::new();
+  let mut rest = constraint;
+  for captures in COMPARATOR_REGEX.captures_iter(constraint) {
+    let matched = captures.get(0)?;
+    let gap = &constraint[constraint.len() - rest.len()..matched.start()];
+    if !gap.chars().all(|ch| ch == ',' || ch.is_whitespace()) {
+      return None;
+    }
+    rest = &constraint[matched.end()..];
+
+    let version = &captures[2];
+    if version == "*" {
+      continue;
+    }
+    let bound = bound(version)?;
+    let wildcard = version.ends_with(".*");
+    match captures.get(1).map_or("", |op| op.as_str()) {
+      "^" => {
+        // Up to the first non-zero part: ^1.2.3 < 2.0.0, ^0.2.3 < 0.3.0, ^0.0.3 < 0.0.4
+        let significant = bound.iter().position(|part| *part != 0).unwrap_or(bound.len() - 1);
+        comparators.push((Op::Lt, bump(&bound[..=significant])));
+        comparators.push((Op::Ge, bound));
+      },
+      "~" => {
+        comparators.push((Op::Lt, bump(&bound[..bound.len().min(2)])));
+        comparators.push((Op::Ge, bound));
+      },
+      "~=" if bound.len() > 1 => {
+        comparators.push((Op::Lt, bump(&bound[..bound.len() - 1])));
+        comparators.push((Op::Ge, bound));
+      },
+      "~=" => return None,
+      ">=" => comparators.push((Op::Ge, bound)),
+      ">" => comparators.push((Op::Gt, bound)),
+      "<=" => comparators.push((Op::Le, bound)),
+      "<" => comparators.push((Op::Lt, bound)),
+      "!=" if wildcard => comparators.push((Op::NotPrefix, bound)),
+      "!=" => comparators.push((Op::Ne, bound)),
+      _ if wildcard => comparators.push((Op::Prefix, bound)),
+      _ => comparators.push((Op::Eq, bound)),
+    }
+  }
+
+  rest
+    .chars()
+    .all(|ch| ch == ',' || ch.is_whitespace())
+    .then_some(comparators)
+}
+
+fn satisfies(release: &[u64], op: Op, bound: &[u64]) -> bool {
+  let order = compare_releases(release, bound);
+  let prefix = bound
+    .iter()
+    .enumerate()
+    .all(|(idx, part)| release.get(idx).unwrap_or(&0) == part);
+  match op {
+    Op::Ge => order != Ordering::Less,
+    Op::Gt => order == Ordering::Greater,
+    Op::Le => order != Ordering::Greater,
+    Op::Lt => order == Ordering::Less,
+    Op::Eq => order == Ordering::Equal,
+    Op::Ne => order != Ordering::Equal,
+    Op::Prefix => prefix,
+    Op::NotPrefix => !prefix,
+  }
+}
+
+// The release segment of a final release: `2.31.0` -> [2, 31, 0]; `2.0rc1`, `1.0.post1` and epochs give None
+fn release(version: &str) -> Option<Vec<u64>> {
+  version
+    .split('.')
+    .map(|part| match part.bytes().all(|byte| byte.is_ascii_digit()) {
+      true => part.parse().ok(),
+      false => None,
+    })
+    .collect()
+}
+
+// The numeric parts of a constraint's version, up to a `*` or a pre-release suffix
+fn bound(version: &str) -> Option<Vec<u64>> {
+  let parts = version
+    .split('.')
+    .map_while(|part| {
+      let digits = part.bytes().take_while(u8::is_ascii_digit).count();
+      part[..digits].parse().ok()
+    })
+    .collect::<Vec<u64>>();
+  (!parts.is_empty()).then_some(parts)
+}
+
+// [1, 2] -> [1, 3]
+fn bump(parts: &[u64]) -> Vec<u64> {
+  let mut bumped = parts.to_vec();
+  if let Some(last) = bumped.last_mut() {
+    *last += 1;
+  }
+  bumped
+}
+
+// Missing parts count as zero, 1.2 == 1.2.0
+fn compare_releases(a: &[u64], b: &[u64]) -> Ordering {
+  (0..a.len().max(b.len()))
+    .map(|idx| a.get(idx).unwrap_or(&0).cmp(b.get(idx).unwrap_or(&0)))
+    .find(|order| *order != Ordering::Equal)
+    .unwrap_or(Ordering::Equal)
+}
+
+// PEP 503: `Foo.Bar_baz` and `foo-bar-baz` are one project
+fn normalize_name(name: &str) -> String {
+  let mut normalized = String::with_capacity(name.len());
+  for ch in name.chars() {
+    match ch {
+      '-' | '_' | '.' => {
+        if !normalized.ends_with('-') {
+          normalized.push('-');
+        }
+      },
+      ch => normalized.push(ch.to_ascii_lowercase()),
+    }
+  }
+  normalized
+}
@@ -0,0 +1,288 @@
+use super::{license_text, local_path, manifests::ManifestFilter, patterns::PatternSet};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::{Context, Result};
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap},
+  fs,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+};
+use walkdir::WalkDir;
+
+static LOCKFILE: &str = "Gemfile.lock";
+static GEMFILE: &str = "Gemfile";
+
+// Where a lock file section says its gems come from
+#[derive(Clone)]
+enum GemSource {
+  Rubygems,
+  Git { remote: String, revision: Option<String> },
+  Path { remote: String },
+  // PLATFORMS, DEPENDENCIES, BUNDLED WITH and the like
+  Other,
+}
+
+struct LockedGem {
+  name: String,
+  version: String,
+  source: GemSource,
+}
+
+// Bundler projects, read from Gemfile.lock's specs: the Gemfile itself only has ranges. Gems from rubygems.org are
+// resolved by the report; GIT and PATH gems are listed with where they come from, like Pipenv's direct references.
+// The lock file doesn't record Gemfile groups, so every gem is a runtime dependency.
+pub struct RubyParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+  // Each with the manifest it was found in
+  direct: Mutex<BTreeMap<String, (String, ReportRow)>>,
+}
+
+impl RubyParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+      direct: Mutex::new(BTreeMap::new()),
+    })
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies = HashMap::new();
+
+    let entries = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| !entry.file_type().is_dir());
+    for entry in entries {
+      let path = entry.path();
+      if path.to_str().is_some_and(|path| self.exclude_patterns.is_match(path)) {
+        continue;
+      }
+      let name = entry.file_name().to_string_lossy();
+      if name == GEMFILE && !path.with_file_name(LOCKFILE).is_file() {
+        self.diagnostics.warn(
+          Category::ScanIssue,
+          format!(
+            "{} has no {}, its gems aren't listed; write one with `bundle lock`",
+            self.source(path),
+            LOCKFILE
+          ),
+        );
+      } else if name == LOCKFILE && self.manifests.allows(LOCKFILE, path) {
+        println!("Processing file: {}", path.display());
+        self.parse_lockfile(path, &mut dependencies)?;
+      }
+    }
+
+    Ok(dependencies)
+  }
+
+  // GIT and PATH gems found by `parse`, reported with their origin instead of a rubygems.org lookup that would
+  // 404 or find another gem
+  pub fn direct_references(&self) -> Vec<(String, ReportRow)> {
+    self
+      .direct
+      .lock()
+      .map(|mut rows| std::mem::take(&mut *rows).into_values().collect())
+      .unwrap_or_default()
+  }
+
+  fn parse_lockfile(&self, path: &Path, dependencies: &mut HashMap<String, DepsEntry>) -> Result<()> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read lock file: {}", path.display()))?;
+    let source = self.source(path);
+    let dir = path.parent().unwrap_or(&self.root_path);
+
+    for gem in locked_gems(&content) {
+      match &gem.source {
+        GemSource::Rubygems => DepsEntry {
+          name: gem.name,
+          version: gem.version,
+          scope: DepScope::Runtime,
+          sources: BTreeSet::from([source.clone()]),
+        }
+        .insert_into(dependencies),
+        GemSource::Git { .. } | GemSource::Path { .. } => {
+          let mut direct = self.direct.lock().expect("Direct references lock poisoned");
+          if !direct.contains_key(&gem.name) {
+            if let Some(row) = self.direct_row(&source, dir, &gem) {
+              direct.insert(gem.name.clone(), (source.clone(), row));
+            }
+          }
+        },
+        GemSource::Other => {},
+      }
+    }
+
+    Ok(())
+  }
+
+  // None for the project's own gemspec (`remote: .`)
+  fn direct_row(&self, source: &str, dir: &Path, gem: &LockedGem) -> Option<ReportRow> {
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Gem,
+      name: gem.name.clone(),
+      version: gem.version.clone(),
+      scope: DepScope::Runtime,
+      homepage: String::new(),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+
+    let remote = match &gem.source {
+      GemSource::Git { remote, revision } => {
+        let location = match revision {
+          Some(revision) => format!("{} at {}", remote, revision),
+          None => remote.clone(),
+        };
+        row.homepage = remote.trim_end_matches(".git").to_owned();
+        row
+          .provenance
+          .push(format!("installed from git {} ({})", location, source));
+        self.diagnostics.warn(
+          Category::UnknownLicense,
+          format!(
+            "{} in {} is installed from {}, its license isn't looked up",
+            gem.name, source, location
+          ),
+        );
+        return Some(row);
+      },
+      GemSource::Path { remote } => remote,
+      GemSource::Rubygems | GemSource::Other => return None,
+    };
+
+    let relative = |path: &Path| path.strip_prefix(&self.root_path).unwrap_or(path).display().to_string();
+    let target = match local_path::safe_join(&self.root_path, dir, remote) {
+      Ok(target) => target,
+      Err(refusal) => {
+        self.diagnostics.warn(
+          refusal.category(),
+          format!(
+            "{} in {} is installed from {}, which {}",
+            gem.name, source, remote, refusal
+          ),
+        );
+        row.provenance.push(format!("unresolved local path: {}", remote));
+        return Some(row);
+      },
+    };
+    if fs::canonicalize(dir).is_ok_and(|dir| dir == target) {
+      return None;
+    }
+
+    row.homepage = relative(&target);
+    row.provenance.push(format!("local path: {}", relative(&target)));
+    match license_text::read_license_file(&self.root_path, &target) {
+      Some((file, text)) => match license_text::classify(&text) {
+        Some(license) => {
+          row.license = Some(license.to_owned());
+          row.license_confidence = Some(LicenseConfidence::Detected);
+          row.provenance.push(format!("license file: {}", relative(&file)));
+        },
+        None => self.diagnostics.warn(
+          Category::UnknownLicense,
+          format!(
+            "License file {} of {} is not a license we recognize",
+            relative(&file),
+            gem.name
+          ),
+        ),
+      },
+      None => self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("{} at {} has no license file", gem.name, relative(&target)),
+      ),
+    }
+
+    Some(row)
+  }
+
+  fn source(&self, path: &Path) -> String {
+    match path.strip_prefix(&self.root_path) {
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => path.display().to_string(),
+    }
+  }
+}
+
+// The `specs:` entries of every GEM, GIT and PATH section. Gems are indented by four spaces, their own
+// requirements by six.
+fn locked_gems(content: &str) -> Vec<LockedGem> {
+  let mut gems = Vec::new();
+  let mut source = GemSource::Other;
+  for line in content.lines() {
+    let indent = line.len() - line.trim_start().len();
+    let line = line.trim();
+    if line.is_empty() {
+      continue;
+    }
+
+    match indent {
+      0 => {
+        source = match line {
+          "GEM" => GemSource::Rubygems,
+          "GIT" => GemSource::Git {
+            remote: String::new(),
+            revision: None,
+          },
+          "PATH" => GemSource::Path { remote: String::new() },
+          _ => GemSource::Other,
+        }
+      },
+      2 => {
+        let Some((key, value)) = line.split_once(": ") else {
+          continue;
+        };
+        match (&mut source, key) {
+          (GemSource::Git { remote, .. } | GemSource::Path { remote }, "remote") => *remote = value.to_owned(),
+          (GemSource::Git { revision, .. }, "revision") => *revision = Some(value.to_owned()),
+          _ => {},
+        }
+      },
+      4 if !matches!(source, GemSource::Other) => {
+        // `nokogiri (1.15.4-x86_64-linux)`: versions can't hold a dash, the platform follows one
+        let Some((name, version)) = line.strip_suffix(')').and_then(|line| line.split_once(" (")) else {
+          continue;
+        };
+        let version = version.split_once('-').map_or(version, |(version, _)| version);
+        gems.push(LockedGem {
+          name: name.to_owned(),
+          version: version.to_owned(),
+          source: source.clone(),
+        });
+      },
+      _ => {},
+    }
+  }
+  gems
+}
@@ -0,0 +1,377 @@
+use super::{deno_deps, terraform_deps};
+use crate::types::{DepScope, DepsEntry, Ecosystem, LicenseConfidence, ReportRow};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::{
+  collections::{BTreeSet, HashMap, HashSet},
+  fs,
+  path::PathBuf,
+};
+
+// Components of a CycloneDX or SPDX JSON document produced elsewhere, e.g. by syft
+pub struct SbomImport {
+  path: String,
+  components: Vec<ImportedComponent>,
+}
+
+struct ImportedComponent {
+  ecosystem: Ecosystem,
+  name: String,
+  version: String,
+  purl: Option<String>,
+  license: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDx {
+  bom_format: Option<String>,
+  #[serde(default)]
+  components: Vec<CycloneDxComponent>,
+}
+
+#[derive(Deserialize)]
+struct CycloneDxComponent {
+  group: Option<String>,
+  name: String,
+  version: Option<String>,
+  purl: Option<String>,
+  #[serde(default)]
+  licenses: Vec<CycloneDxLicenseChoice>,
+  // Sub-components, e.g. the libraries bundled in a jar or the packages of an image layer
+  #[serde(default)]
+  components: Vec<CycloneDxComponent>,
+}
+
+// Either `{"license": {"id": ..}}` / `{"license": {"name": ..}}` or `{"expression": ..}`
+#[derive(Deserialize)]
+struct CycloneDxLicenseChoice {
+  license: Option<CycloneDxLicense>,
+  expression: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CycloneDxLicense {
+  id: Option<String>,
+  name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxDocument {
+  spdx_version: Option<String>,
+  #[serde(default)]
+  packages: Vec<SpdxPackage>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxPackage {
+  name: String,
+  version_info: Option<String>,
+  license_declared: Option<String>,
+  license_concluded: Option<String>,
+  #[serde(default)]
+  external_refs: Vec<SpdxExternalRef>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxExternalRef {
+  reference_type: String,
+  reference_locator: String,
+}
+
+// What a run without a directory scans: an empty directory, so every parser finds nothing and the SBOM's components
+// or the --from-list packages are the whole report. Removed when dropped.
+pub struct EmptyRoot(PathBuf);
+
+impl EmptyRoot {
+  pub fn create() -> Result<Self> {
+    let path = std::env::temp_dir().join(format!("depsfetch-no-scan-{}", std::process::id()));
+    fs::create_dir_all(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+    Ok(Self(path))
+  }
+
+  pub fn path(&self) -> &str {
+    self.0.to_str().unwrap_or_default()
+  }
+}
+
+impl Drop for EmptyRoot {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir(&self.0);
+  }
+}
+
+impl SbomImport {
+  pub fn load(path: &str) -> Result<Self> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read SBOM: {}", path))?;
+    let document: serde_json::Value =
+      serde_json::from_str(&content).with_context(|| format!("Failed to parse SBOM: {}", path))?;
+
+    let components = if document.get("bomFormat").is_some() {
+      let bom: CycloneDx =
+        serde_json::from_value(document).with_context(|| format!("Failed to parse CycloneDX SBOM: {}", path))?;
+      if bom.bom_format.as_deref() != Some("CycloneDX") {
+        bail!("SBOM {} has an unsupported bomFormat", path);
+      }
+      let mut components = Vec::new();
+      flatten(bom.components, &mut HashSet::new(), &mut components);
+      components
+    } else if document.get("spdxVersion").is_some() {
+      let doc: SpdxDocument =
+        serde_json::from_value(document).with_context(|| format!("Failed to parse SPDX SBOM: {}", path))?;
+      if !doc
+        .spdx_version
+        .as_deref()
+        .is_some_and(|version| version.starts_with("SPDX-"))
+      {
+        bail!("SBOM {} has an unsupported spdxVersion", path);
+      }
+      doc.packages.into_iter().map(ImportedComponent::from_spdx).collect()
+    } else {
+      bail!("SBOM {} is neither CycloneDX JSON nor SPDX JSON", path);
+    };
+
+    Ok(Self {
+      path: path.to_owned(),
+      components,
+    })
+  }
+
+  // Moves the ecosystem's components into `deps` unless already scanned. Components that already
+  // assert a license come back as finished rows instead, unless they should be resolved again.
+  pub fn merge_into(
+    &self,
+    ecosystem: Ecosystem,
+    deps: &mut HashMap<String, DepsEntry>,
+    re_resolve: bool,
+  ) -> Vec<ReportRow> {
+    let mut rows = Vec::new();
+    for component in self
+      .components
+      .iter()
+      .filter(|component| component.ecosystem == ecosystem)
+    {
+      if deps.contains_key(&component.name) {
+        println!(
+          "Skipping imported {}@{}: already scanned",
+          component.name, component.version
+        );
+        continue;
+      }
+
+      if component.license.is_some() && !re_resolve {
+        rows.push(self.row(component));
+      } else {
+        deps.insert(
+          component.name.clone(),
+          DepsEntry {
+            name: component.name.clone(),
+            version: component.version.clone(),
+            scope: DepScope::Runtime,
+            sources: BTreeSet::new(),
+          },
+        );
+      }
+    }
+
+    rows
+  }
+
+  // Components whose purl type has no parser here, listed as they are
+  pub fn unknown_rows(&self) -> Vec<ReportRow> {
+    self
+      .components
+      .iter()
+      .filter(|component| component.ecosystem == Ecosystem::Unknown)
+      .map(|component| self.row(component))
+      .collect()
+  }
+
+  fn row(&self, component: &ImportedComponent) -> ReportRow {
+    let homepage = match component.ecosystem {
+      Ecosystem::Npm => format!("https://www.npmjs.com/package/{}", component.name),
+      Ecosystem::Go => format!("https://pkg.go.dev/{}", component.name),
+      Ecosystem::Cargo => format!("https://crates.io/crates/{}", component.name),
+      Ecosystem::Pypi => format!("https://pypi.org/project/{}/", component.name),
+      Ecosystem::Maven => format!(
+        "https://central.sonatype.com/artifact/{}",
+        component.name.replace(':', "/")
+      ),
+      Ecosystem::Gem => format!("https://rubygems.org/gems/{}", component.name),
+      Ecosystem::Composer => format!("https://packagist.org/packages/{}", component.name),
+      Ecosystem::Nuget => format!("https://www.nuget.org/packages/{}", component.name),
+      Ecosystem::Pub => format!("https://pub.dev/packages/{}", component.name),
+      Ecosystem::Cocoapods => format!("https://cocoapods.org/pods/{}", component.name),
+      Ecosystem::Conan => format!("https://conan.io/center/recipes/{}", component.name),
+      Ecosystem::Deno => deno_deps::homepage(&component.name),
+      Ecosystem::Helm => format!("https://artifacthub.io/packages/search?ts_query_web={}", component.name),
+      Ecosystem::Terraform => terraform_deps::homepage(&component.name, &component.version),
+      Ecosystem::GithubActions => format!("https://github.com/{}", component.name),
+      Ecosystem::Swift | Ecosystem::Unknown | Ecosystem::Manual => component.purl.clone().unwrap_or_default(),
+    };
+
+    ReportRow {
+      ecosystem: component.ecosystem,
+      name: component.name.clone(),
+      version: component.version.clone(),
+      scope: DepScope::Runtime,
+      homepage,
+      license: component.license.clone(),
+      license_confidence: component.license.as_ref().map(|_| LicenseConfidence::Declared),
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: vec![format!("metadata: imported from {}", self.path)],
+    }
+  }
+}
+
+// A component nested at several levels is imported once
+fn flatten(
+  components: Vec<CycloneDxComponent>,
+  listed: &mut HashSet<(Ecosystem, String, String)>,
+  flat: &mut Vec<ImportedComponent>,
+) {
+  for mut component in components {
+    flatten(std::mem::take(&mut component.components), listed, flat);
+    let imported = ImportedComponent::from_cyclonedx(component);
+    if listed.insert((imported.ecosystem, imported.name.clone(), imported.version.clone())) {
+      flat.push(imported);
+    }
+  }
+}
+
+impl ImportedComponent {
+  fn from_cyclonedx(component: CycloneDxComponent) -> Self {
+    let licenses = component
+      .licenses
+      .into_iter()
+      .filter_map(|choice| {
+        choice
+          .expression
+          .or_else(|| choice.license.and_then(|license| license.id.or(license.name)))
+      })
+      .collect::<Vec<_>>();
+    let name = match component.group.filter(|group| !group.is_empty()) {
+      Some(group) => format!("{}/{}", group, component.name),
+      None => component.name,
+    };
+
+    Self::new(
+      name,
+      component.version.unwrap_or_default(),
+      component.purl,
+      Some(licenses.join(" AND ")).filter(|license| !license.is_empty()),
+    )
+  }
+
+  fn from_spdx(package: SpdxPackage) -> Self {
+    let purl = package
+      .external_refs
+      .into_iter()
+      .find(|reference| reference.reference_type == "purl")
+      .map(|reference| reference.reference_locator);
+    // NOASSERTION and NONE are how SPDX spells "no license given"
+    let license = package
+      .license_declared
+      .into_iter()
+      .chain(package.license_concluded)
+      .find(|license| license != "NOASSERTION" && license != "NONE");
+
+    Self::new(package.name, package.version_info.unwrap_or_default(), purl, license)
+  }
+
+  // The purl, when there is one, decides the ecosystem and the name as it appears in the scanned manifests
+  fn new(name: String, version: String, purl: Option<String>, license: Option<String>) -> Self {
+    let (ecosystem, name, version) = match purl.as_deref().and_then(parse_purl) {
+      Some((ecosystem, purl_name, purl_version)) => (
+        ecosystem,
+        purl_name,
+        purl_version.filter(|version| !version.is_empty()).unwrap_or(version),
+      ),
+      None => (Ecosystem::Unknown, name, version),
+    };
+
+    Self {
+      ecosystem,
+      name,
+      version,
+      purl,
+      license,
+    }
+  }
+}
+
+// `pkg:npm/%40scope/name@1.0.0?qualifiers#subpath` -> (npm, "@scope/name", Some("1.0.0"))
+fn parse_purl(purl: &str) -> Option<(Ecosystem, String, Option<String>)> {
+  let rest = purl.strip_prefix("pkg:")?;
+  let rest = rest.split(['?', '#']).next().unwrap_or(rest);
+  let (kind, path) = rest.split_once('/')?;
+  let (path, version) = match path.rsplit_once('@') {
+    Some((path, version)) => (path, Some(percent_decode(version))),
+    None => (path, None),
+  };
+
+  let ecosystem = match kind.to_ascii_lowercase().as_str() {
+    "npm" => Ecosystem::Npm,
+    "golang" => Ecosystem::Go,
+    "cargo" => Ecosystem::Cargo,
+    "pypi" => Ecosystem::Pypi,
+    "maven" => Ecosystem::Maven,
+    "gem" => Ecosystem::Gem,
+    "composer" => Ecosystem::Composer,
+    "nuget" => Ecosystem::Nuget,
+    "pub" => Ecosystem::Pub,
+    "swift" => Ecosystem::Swift,
+    "cocoapods" => Ecosystem::Cocoapods,
+    "conan" => Ecosystem::Conan,
+    "jsr" | "deno" => Ecosystem::Deno,
+    "helm" => Ecosystem::Helm,
+    "terraform" => Ecosystem::Terraform,
+    _ => Ecosystem::Unknown,
+  };
+  // The parsers key Maven artifacts by `groupId:artifactId`, the purl puts a slash between them
+  let name = match ecosystem {
+    Ecosystem::Maven => percent_decode(path).replacen('/', ":", 1),
+    // `github.com/apple/swift-nio`, while SwiftPM knows the package by its identity
+    Ecosystem::Swift => percent_decode(path.rsplit('/').next().unwrap_or(path)).to_lowercase(),
+    _ => percent_decode(path),
+  };
+  Some((ecosystem, name, version))
+}
+
+fn percent_decode(value: &str) -> String {
+  let bytes = value.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut idx = 0;
+  while idx < bytes.len() {
+    let escaped = match (bytes[idx], value.get(idx + 1..idx + 3)) {
+      (b'%', Some(hex)) => u8::from_str_radix(hex, 16).ok(),
+      _ => None,
+    };
+    match escaped {
+      Some(byte) => {
+        decoded.push(byte);
+        idx += 3;
+      },
+      None => {
+        decoded.push(bytes[idx]);
+        idx += 1;
+      },
+    }
+  }
+
+  String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,389 @@
+use crate::types::{DepsEntry, Ecosystem};
+use anyhow::{anyhow, bail, Result};
+use glob::Pattern;
+use ring::digest::{digest, SHA256};
+use std::collections::{HashMap, HashSet};
+
+pub const DEFAULT_JS_SHEET: &str = "Web";
+pub const DEFAULT_GO_SHEET: &str = "Backend";
+pub const DEFAULT_RUST_SHEET: &str = "Rust";
+pub const DEFAULT_PYTHON_SHEET: &str = "Python";
+pub const DEFAULT_JAVA_SHEET: &str = "Java";
+pub const DEFAULT_RUBY_SHEET: &str = "Ruby";
+pub const DEFAULT_PHP_SHEET: &str = "PHP";
+pub const DEFAULT_DOTNET_SHEET: &str = ".NET";
+pub const DEFAULT_DART_SHEET: &str = "Dart";
+pub const DEFAULT_SWIFT_SHEET: &str = "iOS";
+pub const DEFAULT_CPP_SHEET: &str = "C++";
+pub const DEFAULT_DENO_SHEET: &str = "Deno";
+pub const DEFAULT_HELM_SHEET: &str = "Helm";
+pub const DEFAULT_TERRAFORM_SHEET: &str = "Terraform";
+// Sheets the report always or optionally writes under these names
+const RESERVED_SHEETS: &[&str] = &[
+  "Imported",
+  "Manual",
+  "CI Actions",
+  "Projects",
+  "By license",
+  "Links",
+  "Self audit",
+  "Exclusions",
+  "Manifests",
+  "Summary",
+  "Run info",
+];
+// Excel's limits on worksheet names
+const MAX_SHEET_NAME: usize = 31;
+// A trailing label in parentheses longer than this is cut like the rest of the name
+const MAX_LABEL: usize = 16;
+const INVALID_SHEET_CHARS: &[char] = &['[', ']', ':', '*', '?', '/', '\\'];
+
+// The --*-sheet-name options, None keeping an ecosystem's default sheet
+#[derive(Default)]
+pub struct SheetNames<'a> {
+  pub js: Option<&'a str>,
+  pub go: Option<&'a str>,
+  pub rust: Option<&'a str>,
+  pub python: Option<&'a str>,
+  pub java: Option<&'a str>,
+  pub ruby: Option<&'a str>,
+  pub php: Option<&'a str>,
+  pub dotnet: Option<&'a str>,
+  pub dart: Option<&'a str>,
+  pub swift: Option<&'a str>,
+  pub cpp: Option<&'a str>,
+  pub deno: Option<&'a str>,
+  pub helm: Option<&'a str>,
+  pub terraform: Option<&'a str>,
+}
+
+// `--sheet NAME=GLOB` rules matched against manifest paths relative to the scanned root, first match wins
+pub struct SheetRouter {
+  rules: Vec<(&'static str, Pattern)>,
+  js_default: &'static str,
+  go_default: &'static str,
+  rust_default: &'static str,
+  python_default: &'static str,
+  java_default: &'static str,
+  ruby_default: &'static str,
+  php_default: &'static str,
+  dotnet_default: &'static str,
+  dart_default: &'static str,
+  swift_default: &'static str,
+  cpp_default: &'static str,
+  deno_default: &'static str,
+  helm_default: &'static str,
+  terraform_default: &'static str,
+  // Shortened sheet names with the names they stand for
+  legend: Vec<(&'static str, String)>,
+}
+
+impl SheetRouter {
+  pub fn new(mappings: &Option<Vec<String>>, names: &SheetNames) -> Result<Self> {
+    let js_default = clean_sheet_name(names.js.unwrap_or(DEFAULT_JS_SHEET))?;
+    let go_default = clean_sheet_name(names.go.unwrap_or(DEFAULT_GO_SHEET))?;
+    let rust_default = clean_sheet_name(names.rust.unwrap_or(DEFAULT_RUST_SHEET))?;
+    let python_default = clean_sheet_name(names.python.unwrap_or(DEFAULT_PYTHON_SHEET))?;
+    let java_default = clean_sheet_name(names.java.unwrap_or(DEFAULT_JAVA_SHEET))?;
+    let ruby_default = clean_sheet_name(names.ruby.unwrap_or(DEFAULT_RUBY_SHEET))?;
+    let php_default = clean_sheet_name(names.php.unwrap_or(DEFAULT_PHP_SHEET))?;
+    let dotnet_default = clean_sheet_name(names.dotnet.unwrap_or(DEFAULT_DOTNET_SHEET))?;
+    let dart_default = clean_sheet_name(names.dart.unwrap_or(DEFAULT_DART_SHEET))?;
+    let swift_default = clean_sheet_name(names.swift.unwrap_or(DEFAULT_SWIFT_SHEET))?;
+    let cpp_default = clean_sheet_name(names.cpp.unwrap_or(DEFAULT_CPP_SHEET))?;
+    let deno_default = clean_sheet_name(names.deno.unwrap_or(DEFAULT_DENO_SHEET))?;
+    let helm_default = clean_sheet_name(names.helm.unwrap_or(DEFAULT_HELM_SHEET))?;
+    let terraform_default = clean_sheet_name(names.terraform.unwrap_or(DEFAULT_TERRAFORM_SHEET))?;
+
+    let mut mapped = Vec::new();
+    for mapping in mappings.iter().flatten() {
+      let (name, glob) = mapping
+        .split_once('=')
+        .ok_or_else(|| anyhow!("--sheet `{}` is not NAME=GLOB", mapping))?;
+      let pattern = Pattern::new(glob).map_err(|err| {
+        anyhow!(
+          "--sheet `{}`: invalid glob: {} at position {}",
+          mapping,
+          err.msg,
+          err.pos
+        )
+      })?;
+      // Several globs may feed one sheet
+      mapped.push((clean_sheet_name(name)?, pattern));
+    }
+
+    // Shortened together, a name only gets a hash when its cut collides with another sheet
+    let mut full_names = vec![
+      &js_default,
+      &go_default,
+      &rust_default,
+      &python_default,
+      &java_default,
+      &ruby_default,
+      &php_default,
+      &dotnet_default,
+      &dart_default,
+      &swift_default,
+      &cpp_default,
+      &deno_default,
+      &helm_default,
+      &terraform_default,
+    ];
+    full_names.extend(mapped.iter().map(|(name, _)| name));
+    let mut distinct = Vec::new();
+    for name in full_names {
+      if !distinct.contains(&name.as_str()) {
+        distinct.push(name.as_str());
+      }
+    }
+    let shortened = shorten_sheet_names(&distinct)?;
+    let mut sheet_names = HashMap::new();
+    let mut legend = Vec::new();
+    for (full, short) in distinct.into_iter().zip(shortened) {
+      let short = leak(short);
+      if short != full {
+        println!("Sheet name `{}` written as `{}`", full, short);
+        legend.push((short, full.to_owned()));
+      }
+      sheet_names.insert(full.to_owned(), short);
+    }
+    let sheet = |full: &String| sheet_names[full];
+
+    let router = Self {
+      rules: mapped
+        .iter()
+        .map(|(name, pattern)| (sheet(name), pattern.clone()))
+        .collect(),
+      js_default: sheet(&js_default),
+      go_default: sheet(&go_default),
+      rust_default: sheet(&rust_default),
+      python_default: sheet(&python_default),
+      java_default: sheet(&java_default),
+      ruby_default: sheet(&ruby_default),
+      php_default: sheet(&php_default),
+      dotnet_default: sheet(&dotnet_default),
+      dart_default: sheet(&dart_default),
+      swift_default: sheet(&swift_default),
+      cpp_default: sheet(&cpp_default),
+      deno_default: sheet(&deno_default),
+      helm_default: sheet(&helm_default),
+      terraform_default: sheet(&terraform_default),
+      legend,
+    };
+    let sheets = router.sheets();
+    for (idx, sheet) in sheets.iter().enumerate() {
+      if let Some(other) = sheets[..idx].iter().find(|other| other.eq_ignore_ascii_case(sheet)) {
+        if other != sheet {
+          bail!("Sheet names `{}` and `{}` only differ by case", other, sheet);
+        }
+      }
+    }
+
+    Ok(router)
+  }
+
+  // For the Run info sheet, so a cut name can be told apart from its neighbours
+  pub fn legend(&self) -> &[(&'static str, String)] {
+    &self.legend
+  }
+
+  pub fn default_sheet(&self, ecosystem: Ecosystem) -> &'static str {
+    match ecosystem {
+      Ecosystem::Go => self.go_default,
+      Ecosystem::Cargo => self.rust_default,
+      Ecosystem::Pypi => self.python_default,
+      Ecosystem::Maven => self.java_default,
+      Ecosystem::Gem => self.ruby_default,
+      Ecosystem::Composer => self.php_default,
+      Ecosystem::Nuget => self.dotnet_default,
+      Ecosystem::Pub => self.dart_default,
+      Ecosystem::Swift | Ecosystem::Cocoapods => self.swift_default,
+      Ecosystem::Conan => self.cpp_default,
+      Ecosystem::Deno => self.deno_default,
+      Ecosystem::Helm => self.helm_default,
+      Ecosystem::Terraform => self.terraform_default,
+      _ => self.js_default,
+    }
+  }
+
+  // Mapped sheets in the order they were given, then the per-ecosystem defaults
+  pub fn sheets(&self) -> Vec<&'static str> {
+    let mut sheets = Vec::new();
+    let names = self.rules.iter().map(|(name, _)| *name).chain([
+      self.js_default,
+      self.go_default,
+      self.rust_default,
+      self.python_default,
+      self.java_default,
+      self.ruby_default,
+      self.php_default,
+      self.dotnet_default,
+      self.dart_default,
+      self.swift_default,
+      self.cpp_default,
+      self.deno_default,
+      self.helm_default,
+      self.terraform_default,
+    ]);
+    for name in names {
+      if !sheets.contains(&name) {
+        sheets.push(name);
+      }
+    }
+    sheets
+  }
+
+  // Groups dependencies by the sheets they go to, so one declared by manifests routed to different sheets is
+  // resolved once and listed on each of them. Entries without a manifest (merged from an SBOM) stay on the default
+  // sheet.
+  pub fn route(
+    &self,
+    ecosystem: Ecosystem,
+    deps: HashMap<String, DepsEntry>,
+  ) -> Vec<(Vec<&'static str>, HashMap<String, DepsEntry>)> {
+    let sheets = self.sheets();
+    let mut routed: Vec<(Vec<&'static str>, HashMap<String, DepsEntry>)> = Vec::new();
+    for (name, dep) in deps {
+      let mut matched = dep
+        .sources
+        .iter()
+        .map(|source| self.sheet_for(ecosystem, source))
+        .collect::<Vec<_>>();
+      if matched.is_empty() {
+        matched.push(self.default_sheet(ecosystem));
+      }
+      let targets = sheets
+        .iter()
+        .copied()
+        .filter(|sheet| matched.contains(sheet))
+        .collect::<Vec<_>>();
+
+      match routed.iter_mut().find(|(known, _)| *known == targets) {
+        Some((_, group)) => {
+          group.insert(name, dep);
+        },
+        None => routed.push((targets, HashMap::from([(name, dep)]))),
+      }
+    }
+
+    routed
+  }
+
+  fn sheet_for(&self, ecosystem: Ecosystem, source: &str) -> &'static str {
+    self
+      .rules
+      .iter()
+      .find(|(_, pattern)| pattern.matches(source))
+      .map_or_else(|| self.default_sheet(ecosystem), |(name, _)| *name)
+  }
+}
+
+// A name a worksheet can carry, but for its length: see `shorten_sheet_names`
+fn clean_sheet_name(name: &str) -> Result<String> {
+  let cleaned = name
+    .trim()
+    .chars()
+    .map(|ch| if INVALID_SHEET_CHARS.contains(&ch) { '_' } else { ch })
+    .collect::<String>();
+  let cleaned = cleaned.trim().trim_matches('\'');
+  if cleaned.is_empty() {
+    bail!("Sheet name `{}` is empty once cleaned up", name);
+  }
+  if let Some(reserved) = RESERVED_SHEETS
+    .iter()
+    .find(|reserved| reserved.eq_ignore_ascii_case(cleaned))
+  {
+    bail!("Sheet name `{}` is taken by the {} sheet", name, reserved);
+  }
+  if cleaned != name {
+    println!("Sheet name `{}` written as `{}`", name, cleaned);
+  }
+
+  Ok(cleaned.to_owned())
+}
+
+/// Fits sheet names into Excel's 31 characters, keeping them distinct. A longer name keeps its label in
+/// parentheses at the end (`payments-service-backend-api (Go)`) and is cut before it; only when that cut collides
+/// with another sheet, a reserved one included, does it also get `~` and 4 hex digits of a hash of the whole name.
+/// The result only depends on the names given, so unchanged settings give the same sheets on every run.
+///
+/// ```
+/// use depsfetch::deps::sheets::shorten_sheet_names;
+///
+/// let names = ["Backend", "payments-service-backend-api (Go)", "payments-service-backend-worker (Go)"];
+/// let shortened = shorten_sheet_names(&names).unwrap();
+/// assert_eq!(shortened[0], "Backend");
+/// assert!(shortened[1].starts_with("payments-service-bac") && shortened[1].ends_with(" (Go)"));
+/// assert_ne!(shortened[1], shortened[2]);
+/// let shortened = shorten_sheet_names(&["checkout-frontend-web-app-v2 (npm)"]).unwrap();
+/// assert_eq!(shortened, ["checkout-frontend-web-app (npm)"]);
+/// ```
+pub fn shorten_sheet_names(names: &[&str]) -> Result<Vec<String>> {
+  let fits = |name: &str| name.chars().count() <= MAX_SHEET_NAME;
+  // Case doesn't tell worksheets apart
+  let mut taken = names
+    .iter()
+    .filter(|name| fits(name))
+    .map(|name| name.to_lowercase())
+    .chain(RESERVED_SHEETS.iter().map(|name| name.to_lowercase()))
+    .collect::<HashSet<_>>();
+
+  let cuts = names
+    .iter()
+    .map(|name| match fits(name) {
+      true => None,
+      false => Some(cut(name, "")),
+    })
+    .collect::<Vec<_>>();
+  let mut shortened = Vec::with_capacity(names.len());
+  for (name, own_cut) in names.iter().zip(&cuts) {
+    let Some(own_cut) = own_cut else {
+      shortened.push((*name).to_owned());
+      continue;
+    };
+    let lowercase = own_cut.to_lowercase();
+    let shared = cuts
+      .iter()
+      .flatten()
+      .filter(|other| other.to_lowercase() == lowercase)
+      .count()
+      > 1;
+    match shared || taken.contains(&lowercase) {
+      true => shortened.push(cut(name, &format!("~{}", short_hash(name)))),
+      false => shortened.push(own_cut.clone()),
+    }
+  }
+
+  for (name, short) in names.iter().zip(&shortened) {
+    if short != name && !taken.insert(short.to_lowercase()) {
+      bail!(
+        "Sheet name `{}` can't be shortened to a name of its own, `{}` is taken; shorten it by hand",
+        name,
+        short
+      );
+    }
+  }
+  Ok(shortened)
+}
+
+// The part of the name before its label cut so that the name, `marker` and the label fit
+fn cut(name: &str, marker: &str) -> String {
+  let (head, label) = match name.rfind(" (").filter(|_| name.ends_with(')')) {
+    Some(idx) if name[idx..].chars().count() <= MAX_LABEL => (&name[..idx], &name[idx..]),
+    _ => (name, ""),
+  };
+  let room = MAX_SHEET_NAME - marker.chars().count() - label.chars().count();
+  let head = head.chars().take(room).collect::<String>();
+  // A cut right after a separator would leave it dangling
+  format!("{}{}{}", head.trim_end_matches([' ', '-', '_', '.']), marker, label)
+}
+
+fn short_hash(name: &str) -> String {
+  let hash = digest(&SHA256, name.as_bytes());
+  format!("{:02x}{:02x}", hash.as_ref()[0], hash.as_ref()[1])
+}
+
+// Sheet names live for the whole run and rows carry them as `&'static str`
+fn leak(name: String) -> &'static str {
+  Box::leak(name.into_boxed_str())
+}
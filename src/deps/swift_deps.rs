@@ -0,0 +1,320 @@
+use super::{license_text, local_path, manifests::ManifestFilter, patterns::PatternSet};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+  collections::{btree_map, BTreeMap, BTreeSet, HashMap},
+  fs,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+};
+use walkdir::WalkDir;
+
+static MANIFEST: &str = "Package.swift";
+static LOCKFILE: &str = "Package.resolved";
+// SwiftPM's and Xcode's checkouts of the dependencies, with their own Package.resolved files
+const CHECKOUT_DIRS: &[&str] = &[".build", "SourcePackages"];
+
+// Version 1 nests the pins under `object`, versions 2 and 3 list them at the top
+#[derive(Deserialize)]
+struct PackageResolved {
+  object: Option<ResolvedObject>,
+  #[serde(default)]
+  pins: Vec<Pin>,
+}
+
+#[derive(Deserialize)]
+struct ResolvedObject {
+  #[serde(default)]
+  pins: Vec<Pin>,
+}
+
+#[derive(Deserialize)]
+struct Pin {
+  // Versions 2 and 3
+  identity: Option<String>,
+  kind: Option<String>,
+  location: Option<String>,
+  // Version 1
+  #[serde(rename = "repositoryURL")]
+  repository_url: Option<String>,
+  #[serde(default)]
+  state: PinState,
+}
+
+#[derive(Default, Deserialize)]
+struct PinState {
+  version: Option<String>,
+  branch: Option<String>,
+  revision: Option<String>,
+}
+
+// Where a resolved package is checked out from, for its license lookup
+#[derive(Debug, Clone)]
+pub struct SwiftPin {
+  pub location: String,
+  pub branch: Option<String>,
+  pub revision: Option<String>,
+}
+
+// SwiftPM packages pinned in Package.resolved, either layout, including the ones Xcode keeps inside
+// .xcodeproj and .xcworkspace bundles. Every package is a git repository, keyed by SwiftPM's identity; local
+// checkouts are listed with their license file, registry packages with where they come from.
+pub struct SwiftParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+  // Each with the manifest it was found in
+  direct: Mutex<BTreeMap<String, (String, ReportRow)>>,
+  pins: Mutex<HashMap<String, SwiftPin>>,
+}
+
+impl SwiftParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+      direct: Mutex::new(BTreeMap::new()),
+      pins: Mutex::new(HashMap::new()),
+    })
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies = HashMap::new();
+
+    let entries = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_entry(|entry| {
+        entry.depth() == 0 || !CHECKOUT_DIRS.contains(&entry.file_name().to_string_lossy().as_ref())
+      })
+      .filter_map(Result::ok)
+      .filter(|entry| !entry.file_type().is_dir());
+    for entry in entries {
+      let path = entry.path();
+      if path.to_str().is_some_and(|path| self.exclude_patterns.is_match(path)) {
+        continue;
+      }
+      let name = entry.file_name().to_string_lossy();
+      if name == MANIFEST && !path.with_file_name(LOCKFILE).is_file() {
+        self.diagnostics.warn(
+          Category::ScanIssue,
+          format!(
+            "{} has no {}, its packages aren't listed; write one with `swift package resolve`",
+            self.source(path),
+            LOCKFILE
+          ),
+        );
+      } else if name == LOCKFILE && self.manifests.allows(LOCKFILE, path) {
+        println!("Processing file: {}", path.display());
+        self.parse_lockfile(path, &mut dependencies)?;
+      }
+    }
+
+    Ok(dependencies)
+  }
+
+  // Local and registry packages found by `parse`, reported with their origin instead of a repository lookup
+  pub fn direct_references(&self) -> Vec<(String, ReportRow)> {
+    self
+      .direct
+      .lock()
+      .map(|mut rows| std::mem::take(&mut *rows).into_values().collect())
+      .unwrap_or_default()
+  }
+
+  // Repositories of the packages `parse` returned, by identity
+  pub fn pins(&self) -> HashMap<String, SwiftPin> {
+    self
+      .pins
+      .lock()
+      .map(|mut pins| std::mem::take(&mut *pins))
+      .unwrap_or_default()
+  }
+
+  fn parse_lockfile(&self, path: &Path, dependencies: &mut HashMap<String, DepsEntry>) -> Result<()> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read lock file: {}", path.display()))?;
+    let source = self.source(path);
+    let resolved = match serde_json::from_str::<PackageResolved>(&content) {
+      Ok(resolved) => resolved,
+      Err(err) => {
+        self
+          .diagnostics
+          .warn(Category::ParseFailure, format!("Can't parse {}: {}", source, err));
+        return Ok(());
+      },
+    };
+    let dir = path.parent().unwrap_or(&self.root_path);
+    let pins = match resolved.object {
+      Some(object) => object.pins,
+      None => resolved.pins,
+    };
+
+    for pin in pins {
+      let Some(location) = pin.location.or(pin.repository_url) else {
+        continue;
+      };
+      // Version 1 has no identity, SwiftPM derives it from the URL the same way
+      let identity = match pin.identity {
+        Some(identity) => identity,
+        None => identity_of(&location),
+      };
+      let state = pin.state;
+      let version = match state.version.as_deref().or(state.revision.as_deref()) {
+        Some(version) => version.to_owned(),
+        None => continue,
+      };
+
+      match pin.kind.as_deref() {
+        Some("localSourceControl" | "fileSystem") => {
+          let mut direct = self.direct.lock().expect("Direct references lock poisoned");
+          if let btree_map::Entry::Vacant(entry) = direct.entry(identity) {
+            let row = self.local_row(entry.key(), &version, &location, &source, dir);
+            entry.insert((source.clone(), row));
+          }
+        },
+        Some("registry") => {
+          let mut row = empty_row(&identity, &version);
+          row
+            .provenance
+            .push(format!("installed from a package registry ({})", source));
+          self.diagnostics.warn(
+            Category::UnknownLicense,
+            format!(
+              "{} in {} comes from a package registry, its license isn't looked up",
+              identity, source
+            ),
+          );
+          self
+            .direct
+            .lock()
+            .expect("Direct references lock poisoned")
+            .entry(identity)
+            .or_insert((source.clone(), row));
+        },
+        _ => {
+          self
+            .pins
+            .lock()
+            .expect("Pins lock poisoned")
+            .entry(identity.clone())
+            .or_insert(SwiftPin {
+              location,
+              branch: state.branch,
+              revision: state.revision,
+            });
+          DepsEntry {
+            name: identity,
+            version,
+            scope: DepScope::Runtime,
+            sources: BTreeSet::from([source.clone()]),
+          }
+          .insert_into(dependencies)
+        },
+      }
+    }
+
+    Ok(())
+  }
+
+  fn local_row(&self, identity: &str, version: &str, location: &str, source: &str, dir: &Path) -> ReportRow {
+    let mut row = empty_row(identity, version);
+    let relative = |path: &Path| path.strip_prefix(&self.root_path).unwrap_or(path).display().to_string();
+    let target = match local_path::safe_join(&self.root_path, dir, location) {
+      Ok(target) => target,
+      Err(refusal) => {
+        self.diagnostics.warn(
+          refusal.category(),
+          format!(
+            "{} in {} is checked out from {}, which {}",
+            identity, source, location, refusal
+          ),
+        );
+        row.provenance.push(format!("unresolved local path: {}", location));
+        return row;
+      },
+    };
+
+    row.homepage = relative(&target);
+    row.provenance.push(format!("local path: {}", relative(&target)));
+    match license_text::read_license_file(&self.root_path, &target) {
+      Some((file, text)) => match license_text::classify(&text) {
+        Some(license) => {
+          row.license = Some(license.to_owned());
+          row.license_confidence = Some(LicenseConfidence::Detected);
+          row.provenance.push(format!("license file: {}", relative(&file)));
+        },
+        None => self.diagnostics.warn(
+          Category::UnknownLicense,
+          format!(
+            "License file {} of {} is not a license we recognize",
+            relative(&file),
+            identity
+          ),
+        ),
+      },
+      None => self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("{} at {} has no license file", identity, relative(&target)),
+      ),
+    }
+
+    row
+  }
+
+  fn source(&self, path: &Path) -> String {
+    match path.strip_prefix(&self.root_path) {
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => path.display().to_string(),
+    }
+  }
+}
+
+// The last path segment of the repository URL, lowercased, without `.git`
+fn identity_of(location: &str) -> String {
+  let name = location
+    .trim_end_matches('/')
+    .rsplit(['/', ':'])
+    .next()
+    .unwrap_or(location);
+  name.strip_suffix(".git").unwrap_or(name).to_lowercase()
+}
+
+fn empty_row(identity: &str, version: &str) -> ReportRow {
+  ReportRow {
+    ecosystem: Ecosystem::Swift,
+    name: identity.to_owned(),
+    version: version.to_owned(),
+    scope: DepScope::Runtime,
+    homepage: String::new(),
+    license: None,
+    license_confidence: None,
+    license_url: None,
+    funding: None,
+    platform: None,
+    repo_mismatch: None,
+    installed_mismatch: None,
+    third_party_notices: None,
+    link_status: None,
+    license_text_key: None,
+    attestation: None,
+    osi_approved: None,
+    fsf_libre: None,
+    size: None,
+    provenance: Vec::new(),
+  }
+}
@@ -0,0 +1,158 @@
+use super::{manifests::ManifestFilter, patterns::PatternSet};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{DepScope, DepsEntry},
+};
+use anyhow::{Context, Result};
+use std::{
+  collections::{BTreeSet, HashMap},
+  fs,
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+use walkdir::WalkDir;
+
+static LOCK_FILE: &str = ".terraform.lock.hcl";
+// `terraform init` downloads providers and modules here, with lock files of their own
+const WORK_DIR: &str = ".terraform";
+// Where a provider address without a hostname comes from
+pub const PUBLIC_REGISTRY: &str = "registry.terraform.io";
+
+// Terraform providers: the `provider` blocks of .terraform.lock.hcl. Public registry providers are named
+// `namespace/type` (`hashicorp/aws`) as the registry lists them, others keep their hostname
+// (`tf.example.com/acme/internal`) so they never collide with a public one
+pub struct TerraformParser {
+  root_path: PathBuf,
+  exclude_patterns: Arc<PatternSet>,
+  manifests: Arc<ManifestFilter>,
+  diagnostics: Arc<Diagnostics>,
+}
+
+impl TerraformParser {
+  pub fn new(
+    directory: &str,
+    exclude: Arc<PatternSet>,
+    manifests: Arc<ManifestFilter>,
+    diagnostics: Arc<Diagnostics>,
+  ) -> Result<Self> {
+    let root_path =
+      std::fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      exclude_patterns: exclude,
+      manifests,
+      diagnostics,
+    })
+  }
+
+  pub async fn parse(&self) -> Result<HashMap<String, DepsEntry>> {
+    let mut dependencies = HashMap::new();
+
+    let locks = WalkDir::new(&self.root_path)
+      .follow_links(true)
+      .into_iter()
+      .filter_entry(|entry| entry.file_name() != WORK_DIR)
+      .filter_map(Result::ok)
+      .filter(|entry| !entry.file_type().is_dir() && entry.file_name().to_str() == Some(LOCK_FILE));
+    for entry in locks {
+      let path = entry.path();
+      if path.to_str().is_some_and(|path| self.exclude_patterns.is_match(path))
+        || !self.manifests.allows(LOCK_FILE, path)
+      {
+        continue;
+      }
+
+      println!("Processing file: {}", path.display());
+      let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read lock file: {}", path.display()))?;
+      let source = self.source(path);
+      for (address, version) in providers(&content) {
+        let Some(version) = version else {
+          self.diagnostics.warn(
+            Category::ParseFailure,
+            format!("{}: provider {} has no version, skipped", source, address),
+          );
+          continue;
+        };
+        DepsEntry {
+          name: address,
+          version,
+          scope: DepScope::Runtime,
+          sources: BTreeSet::from([source.clone()]),
+        }
+        .insert_into(&mut dependencies);
+      }
+    }
+
+    Ok(dependencies)
+  }
+
+  fn source(&self, path: &Path) -> String {
+    match path.strip_prefix(&self.root_path) {
+      Ok(relative) => relative.display().to_string(),
+      Err(_) => path.display().to_string(),
+    }
+  }
+}
+
+// (hostname, namespace, type) of a provider address; the hostname is optional in source addresses
+pub fn address_parts(address: &str) -> Option<(&str, &str, &str)> {
+  let parts = address.split('/').collect::<Vec<_>>();
+  match parts[..] {
+    [host, namespace, name] => Some((host, namespace, name)),
+    [namespace, name] => Some((PUBLIC_REGISTRY, namespace, name)),
+    _ => None,
+  }
+}
+
+// The provider's page: the public registry's, the hostname itself for other registries
+pub fn homepage(name: &str, version: &str) -> String {
+  match address_parts(name) {
+    Some((PUBLIC_REGISTRY, namespace, kind)) => {
+      format!(
+        "https://{}/providers/{}/{}/{}",
+        PUBLIC_REGISTRY, namespace, kind, version
+      )
+    },
+    Some((host, _, _)) => format!("https://{}", host),
+    None => String::new(),
+  }
+}
+
+// The lock file is HCL `terraform providers lock` writes: one `provider "<address>" { ... }` block per provider,
+// `version = "..."` inside, and nothing nested but the `hashes` list
+fn providers(content: &str) -> Vec<(String, Option<String>)> {
+  let mut providers = Vec::new();
+  let mut current: Option<(String, Option<String>)> = None;
+  for line in content.lines().map(str::trim) {
+    if let Some(rest) = line.strip_prefix("provider ") {
+      let address = rest.trim().trim_end_matches('{').trim().trim_matches('"');
+      providers.extend(current.take());
+      let address = address.to_lowercase();
+      let address = match address
+        .strip_prefix(PUBLIC_REGISTRY)
+        .and_then(|rest| rest.strip_prefix('/'))
+      {
+        Some(short) => short.to_owned(),
+        None => address,
+      };
+      current = Some((address, None));
+      continue;
+    }
+    let Some((_, version)) = current.as_mut() else {
+      continue;
+    };
+    if line == "}" {
+      providers.extend(current.take());
+      continue;
+    }
+    if let Some((key, value)) = line.split_once('=') {
+      if key.trim() == "version" {
+        *version = Some(value.trim().trim_matches('"').to_owned());
+      }
+    }
+  }
+  providers.extend(current);
+  providers
+}
@@ -0,0 +1,178 @@
+use anyhow::{anyhow, bail, Result};
+
+// Just enough XML for POMs, maven-metadata.xml and MSBuild project files: elements with their text and
+// attributes, namespace prefixes dropped
+#[derive(Default)]
+pub struct Element {
+  pub name: String,
+  pub text: String,
+  pub attributes: Vec<(String, String)>,
+  pub children: Vec<Element>,
+}
+
+impl Element {
+  pub fn parse(content: &str) -> Result<Element> {
+    // The bottom entry collects the root element
+    let mut stack = vec![Element::default()];
+    let mut rest = content;
+    while let Some(idx) = rest.find('<') {
+      let text = unescape(&rest[..idx]);
+      top(&mut stack).text.push_str(&text);
+      rest = &rest[idx..];
+
+      if let Some(after) = rest.strip_prefix("<!--") {
+        let end = after.find("-->").ok_or_else(|| anyhow!("unterminated comment"))?;
+        rest = &after[end + 3..];
+      } else if let Some(after) = rest.strip_prefix("<![CDATA[") {
+        let end = after.find("]]>").ok_or_else(|| anyhow!("unterminated CDATA section"))?;
+        top(&mut stack).text.push_str(&after[..end]);
+        rest = &after[end + 3..];
+      } else if rest.starts_with("<?") || rest.starts_with("<!") {
+        let end = rest.find('>').ok_or_else(|| anyhow!("unterminated declaration"))?;
+        rest = &rest[end + 1..];
+      } else if let Some(after) = rest.strip_prefix("</") {
+        let end = after.find('>').ok_or_else(|| anyhow!("unterminated closing tag"))?;
+        let name = local_name(after[..end].trim());
+        if stack.len() < 2 || top(&mut stack).name != name {
+          bail!("unexpected </{}>", name);
+        }
+        let element = stack.pop().unwrap_or_default();
+        top(&mut stack).children.push(element);
+        rest = &after[end + 1..];
+      } else {
+        let end = tag_end(rest).ok_or_else(|| anyhow!("unterminated tag"))?;
+        let (tag, closed) = match rest[1..end].strip_suffix('/') {
+          Some(tag) => (tag, true),
+          None => (&rest[1..end], false),
+        };
+        let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let element = Element {
+          name: local_name(name),
+          attributes: parse_attributes(attributes),
+          ..Default::default()
+        };
+        match closed {
+          true => top(&mut stack).children.push(element),
+          false => stack.push(element),
+        }
+        rest = &rest[end + 1..];
+      }
+    }
+
+    if stack.len() > 1 {
+      bail!("unclosed <{}>", top(&mut stack).name);
+    }
+    stack
+      .pop()
+      .and_then(|document| document.children.into_iter().next())
+      .ok_or_else(|| anyhow!("no root element"))
+  }
+
+  pub fn child(&self, name: &str) -> Option<&Element> {
+    self.children.iter().find(|child| child.name == name)
+  }
+
+  pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+    self.children.iter().filter(move |child| child.name == name)
+  }
+
+  // Trimmed text of a child, None when it's missing or empty
+  pub fn text_of(&self, name: &str) -> Option<String> {
+    self
+      .child(name)
+      .map(|child| child.text.trim())
+      .filter(|text| !text.is_empty())
+      .map(str::to_owned)
+  }
+
+  // Trimmed value of an attribute, None when it's missing or empty
+  pub fn attribute(&self, name: &str) -> Option<String> {
+    self
+      .attributes
+      .iter()
+      .find(|(attribute, _)| attribute == name)
+      .map(|(_, value)| value.trim())
+      .filter(|value| !value.is_empty())
+      .map(str::to_owned)
+  }
+
+  // An MSBuild-style setting: the attribute, otherwise the child element of that name
+  pub fn attribute_or_text(&self, name: &str) -> Option<String> {
+    self.attribute(name).or_else(|| self.text_of(name))
+  }
+}
+
+fn top(stack: &mut [Element]) -> &mut Element {
+  stack.last_mut().expect("XML element stack is never empty")
+}
+
+fn local_name(tag: &str) -> String {
+  tag.rsplit(':').next().unwrap_or(tag).to_owned()
+}
+
+// The `>` closing a tag, skipping any inside quoted attribute values like MSBuild conditions
+fn tag_end(rest: &str) -> Option<usize> {
+  let mut quote = None;
+  for (idx, ch) in rest.char_indices() {
+    match (quote, ch) {
+      (None, '"' | '\'') => quote = Some(ch),
+      (Some(open), _) if ch == open => quote = None,
+      (None, '>') => return Some(idx),
+      _ => {},
+    }
+  }
+  None
+}
+
+// `Include="Foo" Version='1.0'`, names without their namespace prefix
+fn parse_attributes(attributes: &str) -> Vec<(String, String)> {
+  let mut parsed = Vec::new();
+  let mut rest = attributes.trim_start();
+  while let Some(eq) = rest.find('=') {
+    let name = local_name(rest[..eq].trim());
+    let value = rest[eq + 1..].trim_start();
+    let Some(quote) = value.chars().next().filter(|ch| *ch == '"' || *ch == '\'') else {
+      break;
+    };
+    let Some(end) = value[1..].find(quote) else {
+      break;
+    };
+    parsed.push((name, unescape(&value[1..end + 1])));
+    rest = value[end + 2..].trim_start();
+  }
+  parsed
+}
+
+fn unescape(text: &str) -> String {
+  let mut unescaped = String::with_capacity(text.len());
+  let mut rest = text;
+  while let Some(idx) = rest.find('&') {
+    unescaped.push_str(&rest[..idx]);
+    rest = &rest[idx..];
+    let entity = rest.find(';').map(|end| (&rest[1..end], end));
+    let decoded = entity.and_then(|(entity, _)| match entity {
+      "lt" => Some('<'),
+      "gt" => Some('>'),
+      "amp" => Some('&'),
+      "quot" => Some('"'),
+      "apos" => Some('\''),
+      entity => entity
+        .strip_prefix("#x")
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .or_else(|| entity.strip_prefix('#')?.parse().ok())
+        .and_then(char::from_u32),
+    });
+    match (decoded, entity) {
+      (Some(decoded), Some((_, end))) => {
+        unescaped.push(decoded);
+        rest = &rest[end + 1..];
+      },
+      _ => {
+        unescaped.push('&');
+        rest = &rest[1..];
+      },
+    }
+  }
+  unescaped.push_str(rest);
+  unescaped
+}
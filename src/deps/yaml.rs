@@ -0,0 +1,161 @@
+use anyhow::{bail, Result};
+
+// Just enough YAML for pubspec.yaml, pubspec.lock and Helm charts: block mappings and sequences of plain or quoted
+// scalars. Block scalars and flow collections are skipped together with everything indented under them, and so are
+// the continuation lines of a plain scalar wrapped over several.
+#[derive(Debug, Default)]
+pub struct Node {
+  // None for a mapping or sequence, and for a key without a value
+  pub value: Option<String>,
+  pub entries: Vec<(String, Node)>,
+  pub items: Vec<Node>,
+}
+
+impl Node {
+  pub fn parse(content: &str) -> Result<Node> {
+    let mut root = Node::default();
+    // Mappings and sequence items still taking entries, with the indent of their key or dash. Items have no key.
+    let mut open: Vec<(usize, Option<String>, Node)> = Vec::new();
+    // Lines indented deeper than this belong to something skipped
+    let mut skip_deeper = None;
+
+    for (idx, line) in content.lines().enumerate() {
+      let line = strip_comment(line);
+      let mut trimmed = line.trim();
+      if trimmed.is_empty() || trimmed == "---" {
+        continue;
+      }
+      let mut indent = line.len() - line.trim_start().len();
+      match skip_deeper {
+        Some(limit) if indent > limit => continue,
+        _ => skip_deeper = None,
+      }
+
+      // A sequence may sit at the indent of its key, a dash there only ends the item before it
+      let dash = trimmed == "-" || trimmed.starts_with("- ");
+      while open
+        .last()
+        .is_some_and(|(depth, key, _)| *depth > indent || (*depth == indent && (!dash || key.is_none())))
+      {
+        close(&mut open, &mut root);
+      }
+      if dash {
+        let item = trimmed[1..].trim_start();
+        match item.chars().next() {
+          None => {
+            open.push((indent, None, Node::default()));
+            continue;
+          },
+          Some('|' | '>' | '[' | '{' | '&' | '*' | '!' | '-') => {
+            skip_deeper = Some(indent);
+            parent(&mut open, &mut root).items.push(Node::default());
+            continue;
+          },
+          Some(_) if key_value(item).is_none() => {
+            skip_deeper = Some(indent);
+            parent(&mut open, &mut root).items.push(Node::scalar(item));
+            continue;
+          },
+          // `- key: value` opens a mapping whose keys line up with this one
+          Some(_) => {
+            open.push((indent, None, Node::default()));
+            indent += trimmed.len() - item.len();
+            trimmed = item;
+          },
+        }
+      }
+
+      let Some((key, value)) = key_value(trimmed) else {
+        bail!("line {}: expected `key: value`", idx + 1);
+      };
+      let key = unquote(key.trim());
+      match value.chars().next() {
+        None => open.push((indent, Some(key), Node::default())),
+        Some('|' | '>' | '[' | '{' | '&' | '*' | '!') => {
+          skip_deeper = Some(indent);
+          parent(&mut open, &mut root).entries.push((key, Node::default()));
+        },
+        Some(_) => {
+          skip_deeper = Some(indent);
+          parent(&mut open, &mut root).entries.push((key, Node::scalar(value)));
+        },
+      }
+    }
+
+    while !open.is_empty() {
+      close(&mut open, &mut root);
+    }
+    Ok(root)
+  }
+
+  fn scalar(value: &str) -> Node {
+    Node {
+      value: Some(unquote(value)),
+      ..Default::default()
+    }
+  }
+
+  pub fn get(&self, key: &str) -> Option<&Node> {
+    self.entries.iter().find(|(name, _)| name == key).map(|(_, node)| node)
+  }
+
+  // The scalar under `key`, None when it's missing, empty or a mapping
+  pub fn str(&self, key: &str) -> Option<&str> {
+    self.get(key)?.value.as_deref().filter(|value| !value.is_empty())
+  }
+
+  // The items of the sequence under `key`, none when it's missing or not a sequence
+  pub fn items(&self, key: &str) -> &[Node] {
+    self.get(key).map_or(&[], |node| &node.items)
+  }
+}
+
+fn parent<'a>(open: &'a mut [(usize, Option<String>, Node)], root: &'a mut Node) -> &'a mut Node {
+  match open.last_mut() {
+    Some((_, _, node)) => node,
+    None => root,
+  }
+}
+
+fn close(open: &mut Vec<(usize, Option<String>, Node)>, root: &mut Node) {
+  let (_, key, node) = open.pop().unwrap_or_default();
+  let parent = parent(open, root);
+  match key {
+    Some(key) => parent.entries.push((key, node)),
+    None => parent.items.push(node),
+  }
+}
+
+// `key: value`, or `key:` with what it holds on the lines below
+fn key_value(line: &str) -> Option<(&str, &str)> {
+  match line.strip_suffix(':') {
+    Some(key) if !key.contains(": ") => Some((key, "")),
+    _ => line.split_once(": ").map(|(key, value)| (key, value.trim())),
+  }
+}
+
+// A `#` starting the line or following whitespace, outside quotes
+fn strip_comment(line: &str) -> &str {
+  let mut quote = None;
+  let mut previous = ' ';
+  for (idx, ch) in line.char_indices() {
+    match (quote, ch) {
+      (None, '"' | '\'') => quote = Some(ch),
+      (Some(open), _) if ch == open => quote = None,
+      (None, '#') if previous.is_whitespace() => return &line[..idx],
+      _ => {},
+    }
+    previous = ch;
+  }
+  line
+}
+
+pub fn unquote(value: &str) -> String {
+  if let Some(inner) = value.strip_prefix('"').and_then(|value| value.strip_suffix('"')) {
+    return inner.replace("\\\"", "\"").replace("\\\\", "\\");
+  }
+  if let Some(inner) = value.strip_prefix('\'').and_then(|value| value.strip_suffix('\'')) {
+    return inner.replace("''", "'");
+  }
+  value.to_owned()
+}
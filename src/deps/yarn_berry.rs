@@ -0,0 +1,245 @@
+use super::audit::{is_license_file_name, is_notice_file_name};
+use crate::{
+  diagnostics::{Category, Diagnostics},
+  types::{PackageDist, PackageFunding, PackageInfo, PackageRepo, PakageBugs},
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+  collections::HashMap,
+  fs::{self, File},
+  io::Read,
+  path::{Path, PathBuf},
+};
+use zip::ZipArchive;
+
+pub const PNP_FILE: &str = ".pnp.cjs";
+const YARN_LOCK_FILE: &str = "yarn.lock";
+const CACHE_DIR: &str = ".yarn/cache";
+
+// Package manifests and license entries read straight from a Yarn Berry (PnP) project's cache zips
+pub struct YarnCache {
+  project_dir: PathBuf,
+  // name -> (requested range, resolved version) from yarn.lock
+  locked: HashMap<String, Vec<(String, String)>>,
+  packages: HashMap<(String, String), CachedPackage>,
+}
+
+pub struct CachedPackage {
+  pub zip: String,
+  pub license_entry: Option<String>,
+  // Relative to the package, as it's published
+  pub notices_file: Option<String>,
+  unpacked_size: u64,
+  manifest: CachedManifest,
+}
+
+// package.json as published, far less regular than the registry's view of it
+#[derive(Deserialize)]
+struct CachedManifest {
+  name: String,
+  version: String,
+  license: Option<serde_json::Value>,
+  homepage: Option<String>,
+  repository: Option<serde_json::Value>,
+  funding: Option<PackageFunding>,
+  #[serde(default)]
+  os: Vec<String>,
+  #[serde(default)]
+  cpu: Vec<String>,
+  engines: Option<serde_json::Value>,
+}
+
+impl YarnCache {
+  // `project_dir` is the directory holding `.pnp.cjs`; unreadable zips are reported and left to the registry
+  pub fn load(project_dir: &Path, diagnostics: &Diagnostics) -> Result<Self> {
+    let lock_path = project_dir.join(YARN_LOCK_FILE);
+    let lock = fs::read_to_string(&lock_path).with_context(|| format!("Failed to read {}", lock_path.display()))?;
+
+    let mut cache = Self {
+      project_dir: project_dir.to_owned(),
+      locked: parse_yarn_lock(&lock),
+      packages: HashMap::new(),
+    };
+
+    let cache_dir = project_dir.join(CACHE_DIR);
+    let entries = fs::read_dir(&cache_dir).with_context(|| format!("Failed to read {}", cache_dir.display()))?;
+    for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+      if path.extension().is_some_and(|ext| ext == "zip") {
+        if let Err(err) = cache.add_zip(&path) {
+          diagnostics.warn(
+            Category::ScanIssue,
+            format!("Can't read yarn cache entry {}: {:#}", path.display(), err),
+          );
+        }
+      }
+    }
+
+    Ok(cache)
+  }
+
+  pub fn project_dir(&self) -> &Path {
+    &self.project_dir
+  }
+
+  // `version` is what package.json asked for (with a leading ^ already stripped)
+  pub fn lookup(&self, name: &str, version: &str) -> Option<&CachedPackage> {
+    let candidates = self.locked.get(name)?;
+    let only = match candidates.as_slice() {
+      [only] => Some(only),
+      _ => None,
+    };
+    let resolved = candidates
+      .iter()
+      .find(|(_, resolved)| resolved == version)
+      .or_else(|| {
+        candidates
+          .iter()
+          .find(|(range, _)| range.trim_start_matches(['^', '~']) == version)
+      })
+      .or(only)?;
+
+    self.packages.get(&(name.to_owned(), resolved.1.clone()))
+  }
+
+  fn add_zip(&mut self, path: &Path) -> Result<()> {
+    let file = File::open(path).context("Failed to open zip")?;
+    let mut archive = ZipArchive::new(file).context("Failed to open zip")?;
+    let names: Vec<String> = archive.file_names().map(str::to_owned).collect();
+
+    // Each cache zip holds exactly one package under node_modules/<name>/
+    let Some(manifest_entry) = names.iter().find(|name| is_package_manifest(name)) else {
+      return Ok(());
+    };
+    let mut content = String::new();
+    archive
+      .by_name(manifest_entry)
+      .context("Failed to read package.json")?
+      .read_to_string(&mut content)
+      .context("Failed to read package.json")?;
+    let manifest: CachedManifest = serde_json::from_str(&content).context("Failed to parse package.json")?;
+
+    let package_dir = manifest_entry.trim_end_matches("package.json");
+    let top_level = || {
+      names
+        .iter()
+        .filter_map(|name| name.strip_prefix(package_dir))
+        .filter(|name| !name.contains('/'))
+    };
+    let license_entry = top_level()
+      .find(|name| is_license_file_name(name))
+      .map(|name| format!("{}{}", package_dir, name));
+    let notices_file = top_level().find(|name| is_notice_file_name(name)).map(str::to_owned);
+    let unpacked_size = (0..archive.len())
+      .filter_map(|idx| archive.by_index(idx).ok().map(|entry| entry.size()))
+      .sum();
+
+    let zip = path.file_name().map_or_else(
+      || path.display().to_string(),
+      |name| name.to_string_lossy().into_owned(),
+    );
+    self.packages.insert(
+      (manifest.name.clone(), manifest.version.clone()),
+      CachedPackage {
+        zip,
+        license_entry,
+        notices_file,
+        unpacked_size,
+        manifest,
+      },
+    );
+
+    Ok(())
+  }
+}
+
+impl CachedPackage {
+  // Shaped like a registry response so the rest of the resolution doesn't care where it came from
+  pub fn package_info(&self) -> PackageInfo {
+    let manifest = &self.manifest;
+    let repository = match &manifest.repository {
+      Some(serde_json::Value::String(url)) => url.clone(),
+      Some(serde_json::Value::Object(repo)) => repo.get("url").and_then(|url| url.as_str()).unwrap_or("").to_owned(),
+      _ => String::new(),
+    };
+    let license = match &manifest.license {
+      Some(serde_json::Value::String(license)) => license.clone(),
+      Some(serde_json::Value::Object(license)) => license
+        .get("type")
+        .and_then(|kind| kind.as_str())
+        .unwrap_or("")
+        .to_owned(),
+      _ => String::new(),
+    };
+
+    PackageInfo {
+      name: manifest.name.clone(),
+      version: manifest.version.clone(),
+      license,
+      homepage: manifest.homepage.clone().unwrap_or_else(|| repository.clone()),
+      bugs: PakageBugs { url: String::new() },
+      repository: PackageRepo {
+        url: repository,
+        directory: None,
+      },
+      funding: manifest.funding.clone(),
+      os: manifest.os.clone(),
+      cpu: manifest.cpu.clone(),
+      engines: manifest.engines.clone(),
+      dist: PackageDist {
+        unpacked_size: Some(self.unpacked_size),
+      },
+    }
+  }
+}
+
+// node_modules/<name>/package.json or node_modules/@scope/<name>/package.json, nothing nested deeper
+fn is_package_manifest(entry: &str) -> bool {
+  let Some(name) = entry
+    .strip_prefix("node_modules/")
+    .and_then(|rest| rest.strip_suffix("/package.json"))
+  else {
+    return false;
+  };
+
+  match name.split_once('/') {
+    Some((scope, package)) => scope.starts_with('@') && !package.contains('/'),
+    None => true,
+  }
+}
+
+// Berry lockfile blocks look like:
+//   "lodash@npm:^4.17.20, lodash@npm:^4.17.21":
+//     version: 4.17.21
+fn parse_yarn_lock(content: &str) -> HashMap<String, Vec<(String, String)>> {
+  let mut locked: HashMap<String, Vec<(String, String)>> = HashMap::new();
+  let mut descriptors: Vec<(String, String)> = Vec::new();
+
+  for line in content.lines() {
+    if !line.starts_with(' ') && line.ends_with(':') {
+      descriptors = line
+        .trim_end_matches(':')
+        .trim_matches('"')
+        .split(", ")
+        .filter_map(parse_descriptor)
+        .collect();
+    } else if let Some(version) = line.trim().strip_prefix("version: ") {
+      let version = version.trim_matches('"');
+      for (name, range) in descriptors.drain(..) {
+        locked.entry(name).or_default().push((range, version.to_owned()));
+      }
+    }
+  }
+
+  locked
+}
+
+// `@scope/name@npm:^1.0.0` -> ("@scope/name", "^1.0.0")
+fn parse_descriptor(descriptor: &str) -> Option<(String, String)> {
+  let descriptor = descriptor.trim_matches('"');
+  let at = descriptor.get(1..)?.find('@')? + 1;
+  let (name, range) = descriptor.split_at(at);
+  let range = &range[1..];
+
+  Some((name.to_owned(), range.strip_prefix("npm:").unwrap_or(range).to_owned()))
+}
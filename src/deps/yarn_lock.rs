@@ -0,0 +1,145 @@
+use super::yaml::{self, Node};
+use anyhow::{bail, Context, Result};
+use std::{collections::HashMap, fs, path::Path};
+
+pub const YARN_LOCK_FILE: &str = "yarn.lock";
+// Yarn Berry's lockfile is YAML and starts with this block, its `version` being the lockfile format's
+const BERRY_METADATA: &str = "__metadata";
+
+// What a `name@range` of a package.json was locked to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Locked {
+  Version(String),
+  // A `workspace:` package of the project, the registry doesn't have it
+  Local,
+}
+
+// Exact versions a Yarn project installed: the lockfile lists every `name@range` selector of the workspace's
+// package.json files above the version it resolved to. Classic (v1) blocks look like:
+//   "@babel/core@^7.0.0", "@babel/core@^7.12.3":
+//     version "7.12.3"
+// Berry (Yarn 2+) ones are YAML, their descriptors carry the protocol:
+//   "@babel/core@npm:^7.0.0, @babel/core@npm:^7.12.3":
+//     version: 7.12.3
+//     resolution: "@babel/core@npm:7.12.3"
+pub struct YarnLock {
+  // `name@range` -> what it's locked to; for Berry the range keeps its protocol, `npm:` included
+  locked: HashMap<String, Locked>,
+  berry: bool,
+}
+
+impl YarnLock {
+  pub fn read(path: &Path) -> Result<Self> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if !content.lines().any(|line| line.starts_with(BERRY_METADATA)) {
+      return Ok(Self {
+        locked: parse_classic(&content),
+        berry: false,
+      });
+    }
+
+    let root = Node::parse(&content)?;
+    if root
+      .get(BERRY_METADATA)
+      .and_then(|metadata| metadata.str("version"))
+      .is_none()
+    {
+      bail!("{} has no version", BERRY_METADATA);
+    }
+    Ok(Self {
+      locked: parse_berry(&root),
+      berry: true,
+    })
+  }
+
+  // What `name@range` of a package.json was locked to. None for aliases (`npm:other@^1.0.0`), git, file and
+  // tarball dependencies: their version is not one the registry has under `name`.
+  pub fn lookup(&self, name: &str, range: &str) -> Option<&Locked> {
+    let selector = match self.berry {
+      // A bare range is an npm one, Berry spells that out
+      true if !has_protocol(range) => format!("{}@npm:{}", name, range),
+      true => format!("{}@{}", name, range),
+      false if range.contains(':') || range.contains('/') => return None,
+      false => format!("{}@{}", name, range),
+    };
+    self.locked.get(&selector)
+  }
+}
+
+fn parse_classic(content: &str) -> HashMap<String, Locked> {
+  let mut locked = HashMap::new();
+  let mut selectors = Vec::new();
+
+  for line in content.lines() {
+    if line.starts_with('#') || line.trim().is_empty() {
+      continue;
+    }
+    if !line.starts_with(' ') {
+      selectors = line
+        .trim_end_matches(':')
+        .split(", ")
+        .map(|selector| selector.trim().trim_matches('"').to_owned())
+        .collect();
+    } else if let Some(version) = line.strip_prefix("  version ") {
+      // The block's own field only, a dependency called `version` is indented deeper
+      let version = version.trim().trim_matches('"');
+      for selector in selectors.drain(..) {
+        locked.insert(selector, Locked::Version(version.to_owned()));
+      }
+    }
+  }
+
+  locked
+}
+
+fn parse_berry(root: &Node) -> HashMap<String, Locked> {
+  let mut locked = HashMap::new();
+
+  for (descriptors, entry) in root.entries.iter().filter(|(key, _)| key != BERRY_METADATA) {
+    let Some(resolution) = entry.str("resolution").and_then(split_descriptor) else {
+      continue;
+    };
+    for descriptor in descriptors.split(", ") {
+      let descriptor = yaml::unquote(descriptor.trim());
+      let Some((name, _)) = split_descriptor(&descriptor) else {
+        continue;
+      };
+      if let Some(target) = resolve_berry(name, resolution, entry.str("version")) {
+        locked.insert(descriptor, target);
+      }
+    }
+  }
+
+  locked
+}
+
+// What a descriptor of `name` resolving to `(resolved name, reference)` is locked to
+fn resolve_berry(name: &str, (resolved, reference): (&str, &str), version: Option<&str>) -> Option<Locked> {
+  let (protocol, rest) = reference.split_once(':')?;
+  match protocol {
+    "workspace" => Some(Locked::Local),
+    // An alias resolves to a package under another name, its version is not `name`'s
+    "npm" if resolved == name => Some(Locked::Version(version.unwrap_or(rest).to_owned())),
+    // `patch:lodash@npm%3A4.17.21#./.yarn/patches/lodash.patch::version=4.17.21&hash=...` is the npm package it
+    // patches as far as licenses go
+    "patch" => {
+      let patched = rest.split('#').next()?.replace("%3A", ":");
+      let (patched_name, patched_reference) = split_descriptor(&patched)?;
+      resolve_berry(name, (patched_name, patched_reference), None).filter(|_| resolved == name)
+    },
+    _ => None,
+  }
+}
+
+// `@scope/name@npm:^1.0.0` -> ("@scope/name", "npm:^1.0.0"), the first `@` of a scoped name being part of it
+fn split_descriptor(descriptor: &str) -> Option<(&str, &str)> {
+  let at = descriptor.get(1..)?.find('@')? + 1;
+  Some((&descriptor[..at], &descriptor[at + 1..]))
+}
+
+// `npm:`, `workspace:`, `patch:` and the like; git and tarball URLs have a `:` too but are no ranges either way
+fn has_protocol(range: &str) -> bool {
+  range.split_once(':').is_some_and(|(protocol, _)| {
+    !protocol.is_empty() && protocol.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '+')
+  })
+}
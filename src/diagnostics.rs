@@ -0,0 +1,173 @@
+use anyhow::{Context, Result};
+use std::{
+  collections::{BTreeMap, HashMap},
+  fmt, fs,
+  sync::Mutex,
+};
+
+// Distinct messages shown per category in the end-of-run recap unless --verbose
+const RECAP_LIMIT: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Category {
+  FetchFailure,
+  ParseFailure,
+  UnknownLicense,
+  ScanIssue,
+  // `workspace:`/`catalog:` specifiers that leaked into published metadata
+  MalformedSpecifier,
+  RepoMismatch,
+  // Resolved versions node_modules or go.sum don't have
+  InstalledMismatch,
+  SelfAudit,
+  // Links --verify-links found dead, one message per link
+  DeadLink,
+  // Local paths from manifests that lead out of the scanned root, one message per entry
+  RefusedPath,
+  // --revalidate-sample rows whose license changed since the baseline, one message per package
+  MetadataDrift,
+}
+
+impl fmt::Display for Category {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Category::FetchFailure => write!(f, "Fetch failures"),
+      Category::ParseFailure => write!(f, "Parse failures"),
+      Category::UnknownLicense => write!(f, "Unknown licenses"),
+      Category::ScanIssue => write!(f, "Scan issues"),
+      Category::MalformedSpecifier => write!(f, "Malformed upstream specifiers"),
+      Category::RepoMismatch => write!(f, "Repository mismatches"),
+      Category::InstalledMismatch => write!(f, "Manifest vs installed"),
+      Category::SelfAudit => write!(f, "Self audit findings"),
+      Category::DeadLink => write!(f, "Dead links"),
+      Category::RefusedPath => write!(f, "Refused local paths"),
+      Category::MetadataDrift => write!(f, "Metadata drift"),
+    }
+  }
+}
+
+// Distinct messages in the order they first came up, with how often each did
+#[derive(Default)]
+struct CategoryLog {
+  messages: Vec<String>,
+  counts: HashMap<String, usize>,
+}
+
+// Every warning of the run goes through here: printed as it happens, then recapped grouped and deduplicated.
+// The --fail-on-* checks count from the same place so CI and the recap agree.
+#[derive(Default)]
+pub struct Diagnostics {
+  logs: Mutex<BTreeMap<Category, CategoryLog>>,
+  // Recorded only, for callers that hand the warnings on instead of printing them
+  quiet: bool,
+}
+
+impl Diagnostics {
+  pub fn quiet() -> Self {
+    Self {
+      quiet: true,
+      ..Self::default()
+    }
+  }
+
+  pub fn warn(&self, category: Category, message: impl Into<String>) {
+    let message = message.into();
+    if !self.quiet {
+      println!("{}", message);
+    }
+
+    if let Ok(mut logs) = self.logs.lock() {
+      let log = logs.entry(category).or_default();
+      match log.counts.get_mut(&message) {
+        Some(count) => *count += 1,
+        None => {
+          log.counts.insert(message.clone(), 1);
+          log.messages.push(message);
+        },
+      }
+    }
+  }
+
+  // Distinct messages, e.g. packages flagged
+  pub fn count(&self, category: Category) -> usize {
+    self
+      .logs
+      .lock()
+      .map(|logs| logs.get(&category).map_or(0, |log| log.messages.len()))
+      .unwrap_or_default()
+  }
+
+  // Distinct messages by category, each in the order it first came up
+  pub fn warnings(&self) -> Vec<(Category, String)> {
+    let Ok(logs) = self.logs.lock() else {
+      return Vec::new();
+    };
+    logs
+      .iter()
+      .flat_map(|(category, log)| log.messages.iter().map(|message| (*category, message.clone())))
+      .collect()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.logs.lock().map(|logs| logs.is_empty()).unwrap_or(true)
+  }
+
+  // `log` is where the full list was written, mentioned when the recap is cut short
+  pub fn print_recap(&self, verbose: bool, log: Option<&str>) {
+    let lines = self.lines(if verbose { None } else { Some(RECAP_LIMIT) });
+    if lines.is_empty() {
+      return;
+    }
+
+    println!("Warnings:");
+    for line in &lines {
+      println!("{}", line);
+    }
+    if let (false, Some(log)) = (verbose, log) {
+      println!("Full list in {}", log);
+    }
+  }
+
+  // Leaves no stale log behind on a clean run. Each line goes through `scrub` first, the log being written next to
+  // the report.
+  pub fn write_log(&self, path: &str, scrub: impl Fn(&str) -> String) -> Result<bool> {
+    if self.is_empty() {
+      let _ = fs::remove_file(path);
+      return Ok(false);
+    }
+
+    let mut content = self
+      .lines(None)
+      .iter()
+      .map(|line| scrub(line))
+      .collect::<Vec<_>>()
+      .join("\n");
+    content.push('\n');
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path))?;
+    Ok(true)
+  }
+
+  fn lines(&self, limit: Option<usize>) -> Vec<String> {
+    let Ok(logs) = self.logs.lock() else {
+      return Vec::new();
+    };
+
+    let mut lines = Vec::new();
+    for (category, log) in logs.iter() {
+      lines.push(format!("{} ({}):", category, log.messages.len()));
+      let shown = limit.unwrap_or(log.messages.len());
+      for message in log.messages.iter().take(shown) {
+        // Messages may span lines, keep the continuation under the bullet
+        let text = message.replace('\n', "\n    ");
+        match log.counts[message] {
+          1 => lines.push(format!("  {}", text)),
+          count => lines.push(format!("  {} (x{})", text, count)),
+        }
+      }
+      if log.messages.len() > shown {
+        lines.push(format!("  and {} more", log.messages.len() - shown));
+      }
+    }
+    lines
+  }
+}
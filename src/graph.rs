@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fmt::Write as _,
+  fs,
+};
+
+// Parent -> child edges between package names, collected from lockfiles and manifests
+#[derive(Default)]
+pub struct DependencyGraph {
+  children: BTreeMap<String, BTreeSet<String>>,
+  parents: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl DependencyGraph {
+  pub fn add_edge(&mut self, parent: &str, child: &str) {
+    if parent == child {
+      return;
+    }
+
+    self
+      .children
+      .entry(parent.to_owned())
+      .or_default()
+      .insert(child.to_owned());
+    self
+      .parents
+      .entry(child.to_owned())
+      .or_default()
+      .insert(parent.to_owned());
+  }
+
+  // Direct parents of a package, capped at `limit` entries with a "+K more" tail
+  pub fn required_by(&self, name: &str, limit: usize) -> Option<String> {
+    let parents = self.parents.get(name)?;
+
+    let mut shown: Vec<&str> = parents.iter().take(limit).map(String::as_str).collect();
+    let more = parents.len().saturating_sub(limit);
+    let tail = format!("+{} more", more);
+    if more > 0 {
+      shown.push(&tail);
+    }
+
+    Some(shown.join(", "))
+  }
+
+  // The same edges under other names, e.g. pseudonyms for internal packages
+  pub fn renamed(&self, rename: impl Fn(&str) -> String) -> Self {
+    let mut graph = Self::default();
+    for (parent, children) in &self.children {
+      for child in children {
+        graph.add_edge(&rename(parent), &rename(child));
+      }
+    }
+    graph
+  }
+
+  pub fn write_dot(&self, path: &str) -> Result<()> {
+    let mut out = String::from("digraph dependencies {\n");
+    for (parent, children) in &self.children {
+      for child in children {
+        let _ = writeln!(out, "  \"{}\" -> \"{}\";", escape(parent), escape(child));
+      }
+    }
+    out.push_str("}\n");
+
+    fs::write(path, out).with_context(|| format!("Failed to write dependency graph: {}", path))
+  }
+}
+
+fn escape(name: &str) -> String {
+  name.replace('\\', "\\\\").replace('"', "\\\"")
+}
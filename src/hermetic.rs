@@ -0,0 +1,63 @@
+use std::{
+  fs, io,
+  path::Path,
+  sync::atomic::{AtomicBool, Ordering},
+};
+
+// --hermetic, always on in builds with the `hermetic` feature
+static ENABLED: AtomicBool = AtomicBool::new(cfg!(feature = "hermetic"));
+
+// Set once while parsing the arguments, before anything reads the disk
+pub fn enable() {
+  ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+  ENABLED.load(Ordering::Relaxed)
+}
+
+/// The only way to the disk for what a run keeps outside the scanned directory and the outputs: the license data
+/// and the branch cache of the user data directory, the credentials file and the keyring. A hermetic run has none
+/// of them (no user data directory, embedded license data, tokens from the environment only), so when something
+/// still reaches for one it's a bug, and the call fails instead of touching the disk.
+///
+/// ```
+/// use depsfetch::hermetic;
+/// use std::path::Path;
+///
+/// hermetic::enable();
+/// let err = hermetic::read(Path::new("/home/user/.netrc")).unwrap_err();
+/// assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+/// ```
+pub fn check(path: &Path) -> io::Result<()> {
+  match is_enabled() {
+    true => Err(io::Error::new(
+      io::ErrorKind::PermissionDenied,
+      format!(
+        "hermetic run, {} is outside the scanned directory and the outputs",
+        path.display()
+      ),
+    )),
+    false => Ok(()),
+  }
+}
+
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+  check(path)?;
+  fs::read(path)
+}
+
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+  check(path)?;
+  fs::read_to_string(path)
+}
+
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+  check(path)?;
+  fs::write(path, contents)
+}
+
+pub fn create_dir_all(path: &Path) -> io::Result<()> {
+  check(path)?;
+  fs::create_dir_all(path)
+}
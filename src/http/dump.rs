@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use reqwest::{header::HeaderMap, Request, StatusCode, Url};
+use std::{
+  fmt::Write as _,
+  fs,
+  path::PathBuf,
+  sync::atomic::{AtomicUsize, Ordering},
+};
+
+const MAX_BODY_BYTES: usize = 64 * 1024;
+const REDACTED: &str = "<redacted>";
+const SENSITIVE_HEADERS: &[&str] = &["authorization", "proxy-authorization", "cookie", "set-cookie"];
+const SENSITIVE_QUERY_MARKERS: &[&str] = &["token", "key", "secret", "password", "auth", "sig"];
+
+pub struct HttpDump {
+  dir: PathBuf,
+  label: String,
+  counter: AtomicUsize,
+}
+
+impl HttpDump {
+  pub fn new(dir: &str) -> Result<Self> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create HTTP dump directory: {}", dir))?;
+
+    Ok(Self {
+      dir: PathBuf::from(dir),
+      label: dir.trim_end_matches('/').to_owned(),
+      counter: AtomicUsize::new(0),
+    })
+  }
+
+  pub fn next_id(&self) -> usize {
+    self.counter.fetch_add(1, Ordering::SeqCst) + 1
+  }
+
+  pub fn reference(&self, id: usize) -> String {
+    format!("{}/{:04}", self.label, id)
+  }
+
+  pub fn write_request(&self, id: usize, request: &Request) {
+    let mut out = format!("> {} {}\n", request.method(), redact_url(request.url()));
+    write_headers(&mut out, "> ", request.headers());
+    self.write(id, &out, false);
+  }
+
+  pub fn write_response(&self, id: usize, status: StatusCode, url: &str, headers: &HeaderMap, body: &[u8]) {
+    let mut out = format!("\n< {}\n", status);
+    if let Ok(url) = Url::parse(url) {
+      let _ = writeln!(out, "< final url: {}", redact_url(&url));
+    }
+    write_headers(&mut out, "< ", headers);
+    out.push('\n');
+
+    let shown = &body[..body.len().min(MAX_BODY_BYTES)];
+    out.push_str(&String::from_utf8_lossy(shown));
+    if body.len() > shown.len() {
+      let _ = write!(out, "\n[truncated: {} of {} bytes shown]", shown.len(), body.len());
+    }
+    out.push('\n');
+
+    self.write(id, &out, true);
+  }
+
+  pub fn write_error(&self, id: usize, err: &reqwest::Error) {
+    // reqwest errors embed the request URL, so redact it before it reaches the dump
+    let message = match err.url() {
+      Some(url) => err.to_string().replace(url.as_str(), &redact_url(url)),
+      None => err.to_string(),
+    };
+    self.write(id, &format!("\n< error: {}\n", message), true);
+  }
+
+  fn write(&self, id: usize, content: &str, append: bool) {
+    let path = self.dir.join(format!("{:04}.txt", id));
+    let result = if append {
+      fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&path)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, content.as_bytes()))
+    } else {
+      fs::write(&path, content)
+    };
+
+    if let Err(err) = result {
+      println!("Can't write HTTP dump {}: {}", path.display(), err);
+    }
+  }
+}
+
+fn write_headers(out: &mut String, prefix: &str, headers: &HeaderMap) {
+  for (name, value) in headers {
+    let value = if SENSITIVE_HEADERS.contains(&name.as_str()) {
+      REDACTED.to_owned()
+    } else {
+      String::from_utf8_lossy(value.as_bytes()).into_owned()
+    };
+    let _ = writeln!(out, "{}{}: {}", prefix, name, value);
+  }
+}
+
+fn redact_url(url: &Url) -> String {
+  let mut url = url.clone();
+
+  if !url.username().is_empty() {
+    let _ = url.set_username(REDACTED);
+  }
+  if url.password().is_some() {
+    let _ = url.set_password(Some(REDACTED));
+  }
+
+  if url.query().is_some() {
+    let pairs: Vec<(String, String)> = url
+      .query_pairs()
+      .map(|(key, value)| {
+        let lower = key.to_lowercase();
+        if SENSITIVE_QUERY_MARKERS.iter().any(|marker| lower.contains(marker)) {
+          (key.into_owned(), REDACTED.to_owned())
+        } else {
+          (key.into_owned(), value.into_owned())
+        }
+      })
+      .collect();
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+  }
+
+  url.to_string()
+}
@@ -0,0 +1,241 @@
+use std::{cell::RefCell, collections::BTreeMap, future::Future, sync::Mutex, time::Duration};
+
+// Latency buckets are a quarter of a doubling wide from 1 ms up, a percentile is at most 19% above the real one
+const BUCKETS_PER_DOUBLING: f64 = 4.0;
+
+tokio::task_local! {
+  // What the task being measured has asked so far, merged into the metrics it runs under once it ends
+  static TASK: RefCell<Accumulator>;
+}
+
+// What a request was for, the classes the latency table has a line each for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EndpointClass {
+  NpmVersion,
+  NpmPackument,
+  GithubLicense,
+  PkgGoDev,
+  // HEAD requests and reads of a page's first bytes: link checks, license file probes, go-get lookups
+  Probe,
+  Other,
+}
+
+impl EndpointClass {
+  // The ones a URL tells apart on its own; the npm registry can live anywhere, its requests say what they are
+  pub fn of_url(url: &str) -> Self {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+      return Self::Other;
+    };
+    match parsed.host_str() {
+      Some("api.github.com") if parsed.path().starts_with("/repos/") && parsed.path().ends_with("/license") => {
+        Self::GithubLicense
+      },
+      Some("pkg.go.dev") => Self::PkgGoDev,
+      _ => Self::Other,
+    }
+  }
+
+  pub fn label(self) -> &'static str {
+    match self {
+      Self::NpmVersion => "npm version",
+      Self::NpmPackument => "npm packument",
+      Self::GithubLicense => "GitHub license API",
+      Self::PkgGoDev => "pkg.go.dev",
+      Self::Probe => "probe",
+      Self::Other => "other",
+    }
+  }
+
+  // The run info setting its line is listed under
+  fn setting(self) -> &'static str {
+    match self {
+      Self::NpmVersion => "http_npm_version",
+      Self::NpmPackument => "http_npm_packument",
+      Self::GithubLicense => "http_github_license",
+      Self::PkgGoDev => "http_pkg_go_dev",
+      Self::Probe => "http_probe",
+      Self::Other => "http_other",
+    }
+  }
+}
+
+// Requests of one class and how long they took
+#[derive(Debug, Clone, Default)]
+pub struct EndpointStats {
+  pub requests: u64,
+  // No response at all, or an error status other than 404 (which the resolution takes as an answer)
+  pub errors: u64,
+  // Requests per latency bucket, the histogram only grows as far as the slowest one
+  buckets: Vec<u64>,
+}
+
+impl EndpointStats {
+  fn record(&mut self, latency: Duration, failed: bool) {
+    self.requests += 1;
+    self.errors += u64::from(failed);
+    let bucket = bucket(latency);
+    if self.buckets.len() <= bucket {
+      self.buckets.resize(bucket + 1, 0);
+    }
+    self.buckets[bucket] += 1;
+  }
+
+  fn merge(&mut self, other: &EndpointStats) {
+    self.requests += other.requests;
+    self.errors += other.errors;
+    if self.buckets.len() < other.buckets.len() {
+      self.buckets.resize(other.buckets.len(), 0);
+    }
+    for (count, other) in self.buckets.iter_mut().zip(&other.buckets) {
+      *count += other;
+    }
+  }
+
+  pub fn p50(&self) -> Option<Duration> {
+    self.percentile(0.5)
+  }
+
+  pub fn p95(&self) -> Option<Duration> {
+    self.percentile(0.95)
+  }
+
+  // The upper bound of the bucket the request at that rank fell in
+  fn percentile(&self, share: f64) -> Option<Duration> {
+    let rank = ((self.requests as f64 * share).ceil() as u64).max(1);
+    let mut seen = 0;
+    for (bucket, count) in self.buckets.iter().enumerate() {
+      seen += count;
+      if seen >= rank {
+        return Some(upper_bound(bucket));
+      }
+    }
+    None
+  }
+
+  fn describe(&self) -> String {
+    format!(
+      "{} request(s), {} error(s), p50 {}, p95 {}",
+      self.requests,
+      self.errors,
+      millis(self.p50()),
+      millis(self.p95())
+    )
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Accumulator {
+  classes: BTreeMap<EndpointClass, EndpointStats>,
+}
+
+impl Accumulator {
+  fn record(&mut self, class: EndpointClass, latency: Duration, failed: bool) {
+    self.classes.entry(class).or_default().record(latency, failed);
+  }
+
+  fn merge(&mut self, other: &Accumulator) {
+    for (class, stats) in &other.classes {
+      self.classes.entry(*class).or_default().merge(stats);
+    }
+  }
+}
+
+/// Count, errors and latency percentiles of the client's requests per endpoint class. A task run through
+/// `measured` keeps its own tally and merges it once when it ends, so concurrent tasks never wait on each other;
+/// requests made outside one go straight to the shared tally.
+///
+/// ```
+/// use depsfetch::http::{EndpointClass, HttpClient};
+///
+/// # async fn example(client: &HttpClient) -> anyhow::Result<()> {
+/// client.metrics().measured(client.get("https://registry.npmjs.org/left-pad")).await?;
+/// if let Some(other) = client.metrics().snapshot().get(&EndpointClass::Other) {
+///   println!("{} request(s), p95 {:?}", other.requests, other.p95());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct HttpMetrics {
+  merged: Mutex<Accumulator>,
+}
+
+impl HttpMetrics {
+  // Runs `future` with a tally of its own, merged into these metrics when it's done
+  pub async fn measured<F: Future>(&self, future: F) -> F::Output {
+    let (output, task) = TASK
+      .scope(RefCell::default(), async {
+        let output = future.await;
+        (output, TASK.with(RefCell::take))
+      })
+      .await;
+    self.merged.lock().unwrap_or_else(|err| err.into_inner()).merge(&task);
+    output
+  }
+
+  pub(super) fn record(&self, class: EndpointClass, latency: Duration, failed: bool) {
+    if TASK
+      .try_with(|task| task.borrow_mut().record(class, latency, failed))
+      .is_err()
+    {
+      self
+        .merged
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .record(class, latency, failed);
+    }
+  }
+
+  // The finished tasks, plus the calling one's requests so far when it's measured
+  pub fn snapshot(&self) -> BTreeMap<EndpointClass, EndpointStats> {
+    let mut snapshot = self.merged.lock().unwrap_or_else(|err| err.into_inner()).clone();
+    let _ = TASK.try_with(|task| snapshot.merge(&task.borrow()));
+    snapshot.classes
+  }
+
+  // A line per class for the run info sheet
+  pub fn run_info(&self) -> Vec<(&'static str, String, &'static str)> {
+    self
+      .snapshot()
+      .into_iter()
+      .map(|(class, stats)| (class.setting(), stats.describe(), ""))
+      .collect()
+  }
+
+  pub fn print_summary(&self) {
+    let snapshot = self.snapshot();
+    if snapshot.is_empty() {
+      return;
+    }
+    println!("HTTP requests by endpoint (requests / errors / p50 / p95):");
+    for (class, stats) in &snapshot {
+      println!(
+        "  {:<18} {:>5} / {} / {} / {}",
+        class.label(),
+        stats.requests,
+        stats.errors,
+        millis(stats.p50()),
+        millis(stats.p95())
+      );
+    }
+  }
+}
+
+fn bucket(latency: Duration) -> usize {
+  let ms = latency.as_secs_f64() * 1000.0;
+  match ms > 1.0 {
+    true => (ms.log2() * BUCKETS_PER_DOUBLING).ceil() as usize,
+    false => 0,
+  }
+}
+
+fn upper_bound(bucket: usize) -> Duration {
+  Duration::from_secs_f64(2f64.powf(bucket as f64 / BUCKETS_PER_DOUBLING) / 1000.0)
+}
+
+fn millis(latency: Option<Duration>) -> String {
+  match latency {
+    Some(latency) => format!("{:.0} ms", latency.as_secs_f64() * 1000.0),
+    None => "-".to_owned(),
+  }
+}
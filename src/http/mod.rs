@@ -0,0 +1,343 @@
+mod dump;
+mod metrics;
+mod tls;
+
+use self::dump::HttpDump;
+use anyhow::{Context, Result};
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use std::{collections::BTreeMap, future::Future, pin::Pin, time::Instant};
+use thiserror::Error;
+
+pub use self::{
+  metrics::{EndpointClass, EndpointStats, HttpMetrics},
+  tls::TlsPolicy,
+};
+
+// crates.io and the GitHub API turn away requests that don't say who is asking
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+// What every request fails with under --offline
+#[derive(Error, Debug)]
+#[error("Request to {0} not sent, --offline is set")]
+pub struct OfflineError(pub String);
+
+pub struct HttpClient {
+  client: reqwest::Client,
+  dump: Option<HttpDump>,
+  // Host -> bearer token, sent to that host when the caller gives no authorization of its own
+  tokens: BTreeMap<String, String>,
+  metrics: HttpMetrics,
+  // Every request fails before it is sent
+  offline: bool,
+}
+
+pub struct HttpResponse {
+  pub status: StatusCode,
+  pub url: String,
+  pub content_type: Option<String>,
+  // What the server says the body weighs, whether or not it was read
+  pub content_length: Option<u64>,
+  // The first `limit` bytes only when read with `get_prefix`
+  pub body: Vec<u8>,
+  pub dump_id: Option<String>,
+}
+
+// What a `MetadataBackend` answers with
+pub type BackendFuture<'a> = Pin<Box<dyn Future<Output = Result<HttpResponse>> + Send + 'a>>;
+
+// One request the resolution makes, the same shapes `HttpClient` offers
+pub struct MetadataRequest<'a> {
+  pub method: Method,
+  pub url: &'a str,
+  pub authorization: Option<&'a str>,
+  pub accept: Option<&'a str>,
+  // Only the first `limit` bytes of the body are needed
+  pub limit: Option<usize>,
+  // Which line of the latency table it counts toward
+  pub class: EndpointClass,
+}
+
+impl<'a> MetadataRequest<'a> {
+  pub fn get(url: &'a str) -> Self {
+    Self {
+      method: Method::GET,
+      url,
+      authorization: None,
+      accept: None,
+      limit: None,
+      class: EndpointClass::of_url(url),
+    }
+  }
+}
+
+// Where resolution gets registry metadata from. `HttpClient` is the one the binary uses; an embedding tool can
+// put its own client, cache or fixtures behind it. Only `fetch` needs implementing.
+pub trait MetadataBackend: Send + Sync {
+  fn fetch<'a>(&'a self, request: MetadataRequest<'a>) -> BackendFuture<'a>;
+
+  fn get<'a>(&'a self, url: &'a str) -> BackendFuture<'a> {
+    self.fetch(MetadataRequest::get(url))
+  }
+
+  fn get_authorized<'a>(&'a self, url: &'a str, authorization: Option<&'a str>) -> BackendFuture<'a> {
+    self.fetch(MetadataRequest {
+      authorization,
+      ..MetadataRequest::get(url)
+    })
+  }
+
+  fn get_json<'a>(&'a self, url: &'a str, authorization: Option<&'a str>) -> BackendFuture<'a> {
+    self.fetch(MetadataRequest {
+      authorization,
+      accept: Some("application/json"),
+      ..MetadataRequest::get(url)
+    })
+  }
+
+  fn get_prefix<'a>(&'a self, url: &'a str, limit: usize) -> BackendFuture<'a> {
+    self.fetch(MetadataRequest {
+      limit: Some(limit),
+      class: EndpointClass::Probe,
+      ..MetadataRequest::get(url)
+    })
+  }
+
+  fn head<'a>(&'a self, url: &'a str) -> BackendFuture<'a> {
+    self.fetch(MetadataRequest {
+      method: Method::HEAD,
+      class: EndpointClass::Probe,
+      ..MetadataRequest::get(url)
+    })
+  }
+}
+
+impl HttpClient {
+  pub fn new(debug_http: &Option<String>) -> Result<Self> {
+    let dump = match debug_http {
+      Some(dir) => Some(HttpDump::new(dir)?),
+      None => None,
+    };
+
+    let client = reqwest::Client::builder()
+      .user_agent(USER_AGENT)
+      .build()
+      .context("Failed to build HTTP client")?;
+
+    Ok(Self {
+      client,
+      dump,
+      tokens: BTreeMap::new(),
+      metrics: HttpMetrics::default(),
+      offline: false,
+    })
+  }
+
+  pub fn with_tokens(mut self, tokens: BTreeMap<String, String>) -> Self {
+    self.tokens = tokens;
+    self
+  }
+
+  // Nothing leaves the machine: packages not served from a baseline fail to fetch as with no network at all
+  pub fn with_offline(mut self, offline: bool) -> Self {
+    self.offline = offline;
+    self
+  }
+
+  // Every request verifies certificates against the policy's roots and pins instead of the system store
+  pub fn with_tls(mut self, policy: TlsPolicy) -> Result<Self> {
+    self.client = reqwest::Client::builder()
+      .user_agent(USER_AGENT)
+      .use_preconfigured_tls(policy.client_config()?)
+      .build()
+      .context("Failed to build HTTP client")?;
+    Ok(self)
+  }
+
+  pub fn metrics(&self) -> &HttpMetrics {
+    &self.metrics
+  }
+
+  pub async fn get(&self, url: &str) -> Result<HttpResponse> {
+    self.get_authorized(url, None).await
+  }
+
+  // `authorization` is sent as is; the dump redacts it like any other credential header
+  pub async fn get_authorized(&self, url: &str, authorization: Option<&str>) -> Result<HttpResponse> {
+    MetadataBackend::get_authorized(self, url, authorization).await
+  }
+
+  // Asks for plain JSON explicitly, for servers that otherwise negotiate a trimmed representation
+  pub async fn get_json(&self, url: &str, authorization: Option<&str>) -> Result<HttpResponse> {
+    MetadataBackend::get_json(self, url, authorization).await
+  }
+
+  // Stops reading the body after `limit` bytes, for probes that only need to look at the start of a page
+  pub async fn get_prefix(&self, url: &str, limit: usize) -> Result<HttpResponse> {
+    MetadataBackend::get_prefix(self, url, limit).await
+  }
+
+  // Status and final URL only, for checking that a link still leads somewhere
+  pub async fn head(&self, url: &str) -> Result<HttpResponse> {
+    MetadataBackend::head(self, url).await
+  }
+
+  // Sends the request and counts it toward its class, failures included
+  async fn execute(&self, request: MetadataRequest<'_>) -> Result<HttpResponse> {
+    if self.offline {
+      return Err(OfflineError(request.url.to_owned()).into());
+    }
+    let class = request.class;
+    let started = Instant::now();
+    let response = self.send(request).await;
+    let failed = match &response {
+      Ok(response) => {
+        (response.status.is_client_error() && response.status != StatusCode::NOT_FOUND)
+          || response.status.is_server_error()
+      },
+      Err(_) => true,
+    };
+    self.metrics.record(class, started.elapsed(), failed);
+    response
+  }
+
+  async fn send(&self, request: MetadataRequest<'_>) -> Result<HttpResponse> {
+    let MetadataRequest {
+      method,
+      url,
+      authorization,
+      accept,
+      limit,
+      ..
+    } = request;
+    // The API host goes with the site's token, as `api.github.com` does with `github.com`'s
+    let host_token = reqwest::Url::parse(url).ok().and_then(|parsed| {
+      let host = parsed.host_str()?;
+      self
+        .tokens
+        .get(host)
+        .or_else(|| host.strip_prefix("api.").and_then(|site| self.tokens.get(site)))
+        .cloned()
+    });
+    let authorization = authorization
+      .map(str::to_owned)
+      .or_else(|| host_token.map(|token| format!("Bearer {}", token)));
+
+    let mut request = self.client.request(method, url);
+    if let Some(authorization) = authorization {
+      request = request.header(reqwest::header::AUTHORIZATION, authorization);
+    }
+    if let Some(accept) = accept {
+      request = request.header(reqwest::header::ACCEPT, accept);
+    }
+    let request = request
+      .build()
+      .with_context(|| format!("Failed to build request for {}", url))?;
+    let dump_id = self.dump.as_ref().map(|dump| dump.next_id());
+
+    if let (Some(dump), Some(id)) = (&self.dump, dump_id) {
+      dump.write_request(id, &request);
+    }
+
+    let mut response = match self.client.execute(request).await {
+      Ok(response) => response,
+      Err(err) => {
+        if let (Some(dump), Some(id)) = (&self.dump, dump_id) {
+          dump.write_error(id, &err);
+        }
+        return Err(err).with_context(|| format!("Request to {} failed{}", url, self.dump_ref(dump_id)));
+      },
+    };
+
+    let status = response.status();
+    let final_url = response.url().to_string();
+    let headers = response.headers().clone();
+    let content_type = headers
+      .get(reqwest::header::CONTENT_TYPE)
+      .and_then(|value| value.to_str().ok())
+      .map(str::to_owned);
+    // From the header itself: a HEAD response has no body to size
+    let content_length = headers
+      .get(reqwest::header::CONTENT_LENGTH)
+      .and_then(|value| value.to_str().ok())
+      .and_then(|value| value.parse().ok());
+    let body = match limit {
+      Some(limit) => {
+        let mut body = Vec::new();
+        while body.len() < limit {
+          let chunk = response
+            .chunk()
+            .await
+            .with_context(|| format!("Failed to read response body from {}{}", url, self.dump_ref(dump_id)))?;
+          match chunk {
+            Some(chunk) => body.extend_from_slice(&chunk),
+            None => break,
+          }
+        }
+        body.truncate(limit);
+        body
+      },
+      None => response
+        .bytes()
+        .await
+        .with_context(|| format!("Failed to read response body from {}{}", url, self.dump_ref(dump_id)))?
+        .to_vec(),
+    };
+
+    if let (Some(dump), Some(id)) = (&self.dump, dump_id) {
+      dump.write_response(id, status, &final_url, &headers, &body);
+    }
+
+    Ok(HttpResponse {
+      status,
+      url: final_url,
+      content_type,
+      content_length,
+      body,
+      dump_id: dump_id.and_then(|id| self.dump.as_ref().map(|dump| dump.reference(id))),
+    })
+  }
+
+  fn dump_ref(&self, dump_id: Option<usize>) -> String {
+    match (&self.dump, dump_id) {
+      (Some(dump), Some(id)) => format!(" (see {})", dump.reference(id)),
+      _ => String::new(),
+    }
+  }
+}
+
+impl MetadataBackend for HttpClient {
+  fn fetch<'a>(&'a self, request: MetadataRequest<'a>) -> BackendFuture<'a> {
+    Box::pin(self.execute(request))
+  }
+}
+
+impl HttpResponse {
+  // What a backend without a server behind it answers with, a fixture or a cache miss
+  pub fn new(status: StatusCode, url: &str, body: impl Into<Vec<u8>>) -> Self {
+    Self {
+      status,
+      url: url.to_owned(),
+      content_type: None,
+      content_length: None,
+      body: body.into(),
+      dump_id: None,
+    }
+  }
+
+  pub fn text(&self) -> String {
+    String::from_utf8_lossy(&self.body).into_owned()
+  }
+
+  pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+    serde_json::from_slice(&self.body).with_context(|| format!("Failed to parse JSON from {}", self.url))
+  }
+
+  // Suffix for warning messages pointing at the dumped exchange, empty when dumping is off
+  pub fn dump_ref(&self) -> String {
+    match &self.dump_id {
+      Some(id) => format!(" (see {})", id),
+      None => String::new(),
+    }
+  }
+}
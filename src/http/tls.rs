@@ -0,0 +1,195 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use ring::digest::{digest, SHA256};
+use rustls::{
+  client::{
+    danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    WebPkiServerVerifier,
+  },
+  crypto::ring::default_provider,
+  pki_types::{CertificateDer, ServerName, UnixTime},
+  ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use std::{collections::BTreeMap, fs::File, io::BufReader, sync::Arc};
+
+// `--pin` values may be written the way HPKP and curl's --pinnedpubkey spell them
+const PIN_PREFIX: &str = "sha256//";
+
+// `--ca-bundle` replaces the trusted roots, `--pin` additionally requires one of the listed SPKI SHA-256 digests
+// (base64) from a host's end-entity certificate. Through a proxy the TLS session still ends at the host, so pins
+// apply to what the host presents after CONNECT.
+pub struct TlsPolicy {
+  roots: RootCertStore,
+  pins: BTreeMap<String, Vec<String>>,
+}
+
+impl TlsPolicy {
+  pub fn load(ca_bundle: &str, pins: &Option<Vec<String>>) -> Result<Self> {
+    let file = File::open(ca_bundle).with_context(|| format!("Failed to open CA bundle {}", ca_bundle))?;
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut BufReader::new(file)) {
+      let cert = cert.with_context(|| format!("Failed to read CA bundle {}", ca_bundle))?;
+      roots.add(cert).with_context(|| {
+        format!(
+          "CA bundle {} holds a certificate that can't be a trust anchor",
+          ca_bundle
+        )
+      })?;
+    }
+    if roots.is_empty() {
+      bail!("CA bundle {} has no certificates", ca_bundle);
+    }
+
+    let mut by_host: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for pin in pins.iter().flatten() {
+      let (host, fingerprint) = pin
+        .split_once('=')
+        .ok_or_else(|| anyhow!("--pin `{}` is not HOST=SPKI-SHA256", pin))?;
+      let fingerprint = fingerprint.trim().trim_start_matches(PIN_PREFIX);
+      if STANDARD.decode(fingerprint).map(|digest| digest.len()) != Ok(32) {
+        bail!(
+          "--pin `{}`: expected the base64 SHA-256 of the certificate's public key",
+          pin
+        );
+      }
+      by_host
+        .entry(host.trim().to_lowercase())
+        .or_default()
+        .push(fingerprint.to_owned());
+    }
+
+    Ok(Self { roots, pins: by_host })
+  }
+
+  pub fn describe(&self) -> String {
+    match self.pins.is_empty() {
+      true => format!("{} root(s)", self.roots.len()),
+      false => format!(
+        "{} root(s), pinned: {}",
+        self.roots.len(),
+        self.pins.keys().cloned().collect::<Vec<_>>().join(", ")
+      ),
+    }
+  }
+
+  pub fn client_config(self) -> Result<ClientConfig> {
+    let provider = Arc::new(default_provider());
+    let inner = WebPkiServerVerifier::builder_with_provider(Arc::new(self.roots), provider.clone())
+      .build()
+      .context("Failed to set up certificate verification")?;
+    let verifier = PinnedVerifier { inner, pins: self.pins };
+
+    Ok(
+      ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .context("Failed to set up TLS")?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth(),
+    )
+  }
+}
+
+// The usual chain and name checks, then the pins of the host if it has any
+#[derive(Debug)]
+struct PinnedVerifier {
+  inner: Arc<WebPkiServerVerifier>,
+  pins: BTreeMap<String, Vec<String>>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+  fn verify_server_cert(
+    &self,
+    end_entity: &CertificateDer<'_>,
+    intermediates: &[CertificateDer<'_>],
+    server_name: &ServerName<'_>,
+    ocsp_response: &[u8],
+    now: UnixTime,
+  ) -> Result<ServerCertVerified, rustls::Error> {
+    self
+      .inner
+      .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)?;
+
+    let host = match server_name {
+      ServerName::DnsName(name) => name.as_ref().to_lowercase(),
+      other => other.to_str().into_owned(),
+    };
+    let Some(pins) = self.pins.get(&host) else {
+      return Ok(ServerCertVerified::assertion());
+    };
+    let fingerprint = spki_sha256(end_entity)
+      .ok_or_else(|| rustls::Error::General(format!("Can't read the public key of {}'s certificate", host)))?;
+    if !pins.contains(&fingerprint) {
+      return Err(rustls::Error::General(format!(
+        "certificate pin mismatch for {}: its key is sha256//{}, pinned {}",
+        host,
+        fingerprint,
+        pins.join(", ")
+      )));
+    }
+
+    Ok(ServerCertVerified::assertion())
+  }
+
+  fn verify_tls12_signature(
+    &self,
+    message: &[u8],
+    cert: &CertificateDer<'_>,
+    dss: &DigitallySignedStruct,
+  ) -> Result<HandshakeSignatureValid, rustls::Error> {
+    self.inner.verify_tls12_signature(message, cert, dss)
+  }
+
+  fn verify_tls13_signature(
+    &self,
+    message: &[u8],
+    cert: &CertificateDer<'_>,
+    dss: &DigitallySignedStruct,
+  ) -> Result<HandshakeSignatureValid, rustls::Error> {
+    self.inner.verify_tls13_signature(message, cert, dss)
+  }
+
+  fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+    self.inner.supported_verify_schemes()
+  }
+}
+
+// Base64 SHA-256 of the DER SubjectPublicKeyInfo, the same value `openssl x509 -pubkey | openssl pkey -pubin
+// -outform der | openssl dgst -sha256 -binary | base64` prints
+fn spki_sha256(cert: &[u8]) -> Option<String> {
+  let (_, certificate, _) = der_element(cert)?;
+  let (_, mut tbs, _) = der_element(certificate)?;
+  // Optional explicit version, then serial, signature algorithm, issuer, validity and subject
+  if tbs.first() == Some(&0xa0) {
+    tbs = der_element(tbs)?.2;
+  }
+  for _ in 0..5 {
+    tbs = der_element(tbs)?.2;
+  }
+  let (spki, _, _) = der_element(tbs)?;
+
+  Some(STANDARD.encode(digest(&SHA256, spki)))
+}
+
+// The first DER element of `der`: (whole element, its content, what follows it)
+fn der_element(der: &[u8]) -> Option<(&[u8], &[u8], &[u8])> {
+  let length_byte = *der.get(1)?;
+  let (header, length) = match length_byte {
+    0..=0x7f => (2, length_byte as usize),
+    0x81..=0x84 => {
+      let count = (length_byte & 0x7f) as usize;
+      let bytes = der.get(2..2 + count)?;
+      (
+        2 + count,
+        bytes.iter().fold(0usize, |length, byte| (length << 8) | *byte as usize),
+      )
+    },
+    _ => return None,
+  };
+  let end = header.checked_add(length)?;
+  if end > der.len() {
+    return None;
+  }
+
+  Some((&der[..end], &der[header..end], &der[end..]))
+}
@@ -0,0 +1,258 @@
+use crate::{
+  config::{self, CONFIG_FILE},
+  deps::{detect::DetectedManifests, manifests::MANIFEST_KINDS},
+};
+use anyhow::{bail, Context, Result};
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fmt::Write,
+  fs,
+  path::Path,
+};
+use walkdir::WalkDir;
+
+// Directories holding installed or built copies of dependencies: suggested as excludes, never walked into
+const BULKY_DIRS: &[(&str, &str)] = &[
+  ("node_modules", "installed npm packages"),
+  ("vendor", "vendored Go modules, gems or Composer packages"),
+  ("target", "Cargo or Maven build output"),
+  (".venv", "Python virtual environment"),
+  ("venv", "Python virtual environment"),
+  ("dist", "build output"),
+  ("build", "build output"),
+  (".dart_tool", "Dart and Flutter tool output"),
+  (".build", "SwiftPM build output"),
+  ("Pods", "CocoaPods installed pods"),
+];
+
+// What a walk of the tree turned up, without reading anything beyond the manifests' names
+#[derive(Default)]
+struct Scan {
+  manifests: BTreeMap<&'static str, usize>,
+  bulky: BTreeSet<(&'static str, &'static str)>,
+  npm_scopes: BTreeSet<String>,
+  go_prefixes: BTreeSet<String>,
+  // package.json names and go.mod module paths
+  packages: BTreeSet<String>,
+  detected: DetectedManifests,
+}
+
+// The tree's own packages and the scopes and module prefixes they publish under, what --redact-internal hides
+pub struct FirstParty {
+  pub packages: BTreeSet<String>,
+  pub namespaces: BTreeSet<String>,
+}
+
+pub fn first_party(directory: &str) -> Result<FirstParty> {
+  let scan = scan(Path::new(directory))?;
+  Ok(FirstParty {
+    packages: scan.packages,
+    namespaces: scan.npm_scopes.into_iter().chain(scan.go_prefixes).collect(),
+  })
+}
+
+// `depsfetch init`: a commented config proposing settings for what the tree contains, written to the directory
+// where runs on it pick it up
+pub fn write_config(directory: &str, force: bool) -> Result<()> {
+  let path = Path::new(directory).join(CONFIG_FILE);
+  if path.exists() && !force {
+    bail!("{} already exists, pass --force to overwrite it", path.display());
+  }
+
+  let scan = scan(Path::new(directory))?;
+  let content = render(&scan);
+  fs::write(&path, &content).with_context(|| format!("Failed to write {}", path.display()))?;
+  let path = path.display().to_string();
+  // A proposal the loader rejects would only fail on the next run
+  config::load(&path, None).with_context(|| format!("The generated {} doesn't load, this is a bug", path))?;
+
+  for (kind, count) in &scan.manifests {
+    println!("Found {} {} file(s)", count, kind);
+  }
+  for suggestion in scan.detected.suggestions() {
+    println!("{}", suggestion);
+  }
+  println!(
+    "Config written to {}, review it and run `depsfetch {}`",
+    path, directory
+  );
+  Ok(())
+}
+
+fn scan(root: &Path) -> Result<Scan> {
+  if !root.is_dir() {
+    bail!("{} is not a directory", root.display());
+  }
+
+  let mut scan = Scan::default();
+  // Bulky directories are only seen from the outside, their manifests aren't the repository's own
+  let mut bulky_dirs = BTreeSet::new();
+  let entries = WalkDir::new(root).into_iter().filter_entry(|entry| {
+    let name = entry.file_name().to_string_lossy();
+    if !entry.file_type().is_dir() || entry.depth() == 0 {
+      return true;
+    }
+    if let Some(dir) = bulky(&name) {
+      bulky_dirs.insert(dir);
+      return false;
+    }
+    name != ".git"
+  });
+  for entry in entries.filter_map(Result::ok) {
+    let name = entry.file_name().to_string_lossy();
+    if entry.file_type().is_dir() {
+      continue;
+    }
+    scan.detected.observe(&name);
+    let kind = match name.as_ref() {
+      name if name.starts_with("requirements") && name.ends_with(".txt") => Some("requirements.txt"),
+      name => MANIFEST_KINDS.iter().copied().find(|kind| *kind == name),
+    };
+    let Some(kind) = kind else {
+      continue;
+    };
+    *scan.manifests.entry(kind).or_default() += 1;
+
+    match kind {
+      "package.json" => {
+        let name = fs::read(entry.path())
+          .ok()
+          .and_then(|content| serde_json::from_slice::<serde_json::Value>(&content).ok())
+          .and_then(|package| package.get("name")?.as_str().map(str::to_owned));
+        let scope = name
+          .as_deref()
+          .and_then(|name| Some(name.split_once('/').filter(|_| name.starts_with('@'))?.0.to_owned()));
+        scan.npm_scopes.extend(scope);
+        scan.packages.extend(name);
+      },
+      "go.mod" => {
+        let module = fs::read_to_string(entry.path()).ok().and_then(|content| {
+          content
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("module "))
+            .map(|module| module.trim().trim_matches('"').to_owned())
+        });
+        scan.go_prefixes.extend(module.as_deref().and_then(go_prefix));
+        scan.packages.extend(module);
+      },
+      _ => {},
+    }
+  }
+
+  scan.bulky = bulky_dirs;
+
+  Ok(scan)
+}
+
+fn bulky(name: &str) -> Option<(&'static str, &'static str)> {
+  BULKY_DIRS.iter().copied().find(|(dir, _)| *dir == name)
+}
+
+// `github.com/acme/service` -> `github.com/acme/`, the part other modules of the organization share
+fn go_prefix(module: &str) -> Option<String> {
+  let mut parts = module.splitn(3, '/');
+  let (host, owner) = (parts.next()?, parts.next()?);
+  Some(format!("{}/{}", host, owner))
+}
+
+fn render(scan: &Scan) -> String {
+  let mut out = String::new();
+  let _ = writeln!(
+    out,
+    "# Written by `depsfetch init`. Runs on this directory load it unless --config points elsewhere; flags and\n\
+     # COLLECT_LIC_* variables still take precedence."
+  );
+
+  out.push('\n');
+  if scan.bulky.is_empty() {
+    let _ = writeln!(
+      out,
+      "# No installed or built dependency directories found\n# exclude = []"
+    );
+  } else {
+    let _ = writeln!(
+      out,
+      "# Installed and built copies of dependencies, not this repository's manifests"
+    );
+    let _ = writeln!(out, "exclude = [");
+    for (dir, reason) in &scan.bulky {
+      let _ = writeln!(
+        out,
+        "  {{ pattern = {}, reason = {} }},",
+        quote(&format!("/{}/", regex::escape(dir))),
+        quote(reason)
+      );
+    }
+    let _ = writeln!(out, "]");
+  }
+
+  out.push('\n');
+  let _ = writeln!(
+    out,
+    "# This repository's own packages, uncomment to leave them out of the report"
+  );
+  if scan.npm_scopes.is_empty() && scan.go_prefixes.is_empty() {
+    let _ = writeln!(
+      out,
+      "# skip_scope = [{{ pattern = \"@your-scope\", reason = \"first-party packages\" }}]"
+    );
+  }
+  for (key, namespaces, reason) in [
+    ("skip_scope", &scan.npm_scopes, "first-party npm scope"),
+    ("skip_prefix", &scan.go_prefixes, "first-party Go modules"),
+  ] {
+    if namespaces.is_empty() {
+      continue;
+    }
+    let _ = writeln!(out, "# {} = [", key);
+    for namespace in namespaces {
+      let _ = writeln!(
+        out,
+        "#   {{ pattern = {}, reason = {} }},",
+        quote(namespace),
+        quote(reason)
+      );
+    }
+    let _ = writeln!(out, "# ]");
+  }
+  let _ = writeln!(
+    out,
+    "# Or drop every dependency that names a package.json under the root\n# exclude_private = true"
+  );
+
+  out.push('\n');
+  let _ = writeln!(
+    out,
+    "# Tokens don't belong here: store them with `depsfetch auth set <host>` or set GITHUB_TOKEN / NPM_AUTH_TOKEN\n\
+     # npm_registry = \"https://registry.example.com/\"\n\
+     # npm_auth = \"bearer\"\n\
+     # scope_registry = [\"@ourorg=https://npm.pkg.github.com\"]"
+  );
+
+  out.push('\n');
+  let _ = writeln!(
+    out,
+    "# License policy\n\
+     # exclude_licenses = [\"GPL-3.0-only\"]\n\
+     # require_osi_approved = true\n\
+     # fail_on_repo_mismatch = true"
+  );
+
+  let suggestions = scan.detected.suggestions();
+  if !suggestions.is_empty() {
+    out.push('\n');
+  }
+  for suggestion in suggestions {
+    let _ = writeln!(out, "# {}", suggestion);
+  }
+  for (kind, count) in &scan.manifests {
+    let _ = writeln!(out, "\n# {} file(s) found", count);
+    let _ = writeln!(out, "[manifests.{}]\nenabled = true", quote(kind));
+  }
+  out
+}
+
+// A TOML basic string; its escapes are JSON's
+fn quote(value: &str) -> String {
+  serde_json::Value::from(value).to_string()
+}
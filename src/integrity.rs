@@ -0,0 +1,128 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use ring::{
+  digest,
+  signature::{self, Ed25519KeyPair, UnparsedPublicKey},
+};
+use std::{fmt::Write as _, fs, path::Path};
+
+pub const MANIFEST_FILE: &str = "deps_report.sha256";
+
+// DER prefix of an Ed25519 SubjectPublicKeyInfo, followed by the raw 32-byte key
+const ED25519_SPKI_PREFIX: &[u8] = &[0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00];
+
+pub struct SigningKey(Ed25519KeyPair);
+
+impl SigningKey {
+  // Loads a PEM PKCS#8 Ed25519 private key (`openssl genpkey -algorithm ed25519`).
+  // Errors only ever name the file, never its contents.
+  pub fn load(path: &str) -> Result<Self> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read signing key: {}", path))?;
+    let der = pem_body(&content, "PRIVATE KEY")
+      .ok_or_else(|| anyhow!("Signing key {} is not a PEM-encoded PKCS#8 private key", path))?;
+    let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(&der)
+      .map_err(|err| anyhow!("Signing key {} is not a valid Ed25519 key: {}", path, err))?;
+
+    Ok(Self(key_pair))
+  }
+}
+
+// Writes a sha256sum-compatible manifest of the emitted files plus a detached `.sig` when a key is given
+pub fn write_manifest(manifest: &str, files: &[String], key: Option<&SigningKey>) -> Result<()> {
+  let mut out = String::new();
+  for file in files {
+    let bytes = fs::read(file).with_context(|| format!("Failed to read {} for checksum", file))?;
+    let _ = writeln!(out, "{}  {}", sha256_hex(&bytes), file);
+  }
+  fs::write(manifest, &out).with_context(|| format!("Failed to write checksum manifest: {}", manifest))?;
+
+  if let Some(SigningKey(key_pair)) = key {
+    let signature = key_pair.sign(out.as_bytes());
+    let signature_path = signature_path(manifest);
+    fs::write(&signature_path, format!("{}\n", STANDARD.encode(signature.as_ref())))
+      .with_context(|| format!("Failed to write manifest signature: {}", signature_path))?;
+  }
+
+  Ok(())
+}
+
+// Recomputes every hash in the manifest (paths relative to its directory) and checks the signature if asked
+pub fn verify_report(manifest: &str, public_key: Option<&str>) -> Result<()> {
+  let content = fs::read(manifest).with_context(|| format!("Failed to read checksum manifest: {}", manifest))?;
+
+  if let Some(public_key) = public_key {
+    verify_signature(manifest, &content, public_key)?;
+    println!("Signature OK: {}", signature_path(manifest));
+  }
+
+  let base_dir = Path::new(manifest).parent().unwrap_or(Path::new(""));
+  let text = String::from_utf8(content).with_context(|| format!("Checksum manifest {} is not UTF-8", manifest))?;
+  let mut total = 0;
+  let mut failed = 0;
+  for (idx, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+    let (expected, file) = line
+      .split_once("  ")
+      .ok_or_else(|| anyhow!("{}:{}: malformed manifest line", manifest, idx + 1))?;
+    total += 1;
+
+    match fs::read(base_dir.join(file)) {
+      Ok(bytes) if sha256_hex(&bytes) == expected => println!("OK {}", file),
+      Ok(_) => {
+        failed += 1;
+        println!("FAILED {}: checksum mismatch", file);
+      },
+      Err(err) => {
+        failed += 1;
+        println!("FAILED {}: {}", file, err);
+      },
+    }
+  }
+
+  if failed > 0 {
+    bail!("{} of {} file(s) failed verification", failed, total);
+  }
+  Ok(())
+}
+
+fn verify_signature(manifest: &str, content: &[u8], public_key: &str) -> Result<()> {
+  let key_pem = fs::read_to_string(public_key).with_context(|| format!("Failed to read public key: {}", public_key))?;
+  let raw_key = pem_body(&key_pem, "PUBLIC KEY")
+    .and_then(|der| der.strip_prefix(ED25519_SPKI_PREFIX).map(<[u8]>::to_vec))
+    .filter(|key| key.len() == 32)
+    .ok_or_else(|| anyhow!("Public key {} is not a PEM-encoded Ed25519 public key", public_key))?;
+
+  let signature_path = signature_path(manifest);
+  let encoded = fs::read_to_string(&signature_path)
+    .with_context(|| format!("Failed to read manifest signature: {}", signature_path))?;
+  let signature = STANDARD
+    .decode(encoded.trim())
+    .with_context(|| format!("Manifest signature {} is not valid base64", signature_path))?;
+
+  UnparsedPublicKey::new(&signature::ED25519, raw_key)
+    .verify(content, &signature)
+    .map_err(|_| anyhow!("Signature {} does not match {}", signature_path, manifest))
+}
+
+fn pem_body(content: &str, label: &str) -> Option<Vec<u8>> {
+  let begin = format!("-----BEGIN {}-----", label);
+  let end = format!("-----END {}-----", label);
+  let start = content.find(&begin)? + begin.len();
+  let stop = start + content[start..].find(&end)?;
+  let body: String = content[start..stop].split_whitespace().collect();
+
+  STANDARD.decode(body).ok()
+}
+
+pub fn signature_path(manifest: &str) -> String {
+  format!("{}.sig", manifest)
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+  digest::digest(&digest::SHA256, bytes)
+    .as_ref()
+    .iter()
+    .fold(String::new(), |mut hex, byte| {
+      let _ = write!(hex, "{:02x}", byte);
+      hex
+    })
+}
@@ -0,0 +1,20 @@
+// Everything the binary is made of. Public so the benches (and tools embedding the scan) can drive the pieces
+// without going through the command line.
+pub mod cache;
+pub mod cli;
+pub mod config;
+pub mod credentials;
+pub mod deps;
+pub mod diagnostics;
+pub mod graph;
+pub mod hermetic;
+pub mod http;
+pub mod init;
+pub mod integrity;
+pub mod license_data;
+pub mod report;
+pub mod resolve;
+pub mod tui;
+pub mod types;
+pub mod version;
+pub mod watch;
@@ -0,0 +1,333 @@
+use crate::{hermetic, http::HttpClient, types::LicenseFlag};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::{BTreeMap, HashMap, HashSet},
+  env, fmt,
+  path::{Path, PathBuf},
+};
+
+// Bump when the layout of license_data.json changes; older binaries refuse newer files
+pub const DATA_FORMAT_VERSION: u32 = 1;
+
+const DATA_FILE: &str = "license_data.json";
+const TEXT_DIR: &str = "text";
+const EMBEDDED: &[u8] = include_bytes!("../data/license_data.json");
+const SPDX_LIST_URL: &str = "https://raw.githubusercontent.com/spdx/license-list-data/main/json/licenses.json";
+const SPDX_TEXT_URL: &str = "https://raw.githubusercontent.com/spdx/license-list-data/main/text";
+
+#[derive(Serialize, Deserialize)]
+struct DataFile {
+  format_version: u32,
+  spdx_version: String,
+  licenses: Vec<String>,
+  // Ids whose terms reach into derived works, what the trend reports count as copyleft
+  #[serde(default)]
+  copyleft: Vec<String>,
+  // Ids the SPDX list marks `isOsiApproved` and `isFsfLibre`
+  #[serde(default)]
+  osi_approved: Vec<String>,
+  #[serde(default)]
+  fsf_libre: Vec<String>,
+  #[serde(default)]
+  aliases: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct SpdxList {
+  #[serde(rename = "licenseListVersion")]
+  version: String,
+  licenses: Vec<SpdxLicense>,
+}
+
+#[derive(Deserialize)]
+struct SpdxLicense {
+  #[serde(rename = "licenseId")]
+  id: String,
+  #[serde(rename = "isOsiApproved", default)]
+  osi_approved: bool,
+  // Missing for licenses the FSF hasn't looked at
+  #[serde(rename = "isFsfLibre", default)]
+  fsf_libre: bool,
+}
+
+// Single entry point for SPDX ids and aliases: a refreshed copy in the data directory wins over the embedded one
+pub struct LicenseData {
+  source: String,
+  spdx_version: String,
+  // Upper-cased id or alias -> canonical SPDX id
+  lookup: HashMap<String, String>,
+  copyleft: HashSet<String>,
+  osi_approved: HashSet<String>,
+  fsf_libre: HashSet<String>,
+}
+
+// Coarse license class for the summary breakdowns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LicenseCategory {
+  Permissive,
+  Copyleft,
+  // No license, or one that isn't a known SPDX id or alias
+  Unknown,
+}
+
+impl fmt::Display for LicenseCategory {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      LicenseCategory::Permissive => write!(f, "permissive"),
+      LicenseCategory::Copyleft => write!(f, "copyleft"),
+      LicenseCategory::Unknown => write!(f, "unknown"),
+    }
+  }
+}
+
+impl LicenseData {
+  pub fn load(data_dir: Option<&Path>) -> Result<Self> {
+    if let Some(path) = data_dir.map(|dir| dir.join(DATA_FILE)) {
+      hermetic::check(&path).context("Failed to look for license data")?;
+      if path.is_file() {
+        let content =
+          hermetic::read(&path).with_context(|| format!("Failed to read license data: {}", path.display()))?;
+        return Self::parse(&content, &path.display().to_string());
+      }
+    }
+
+    Self::parse(EMBEDDED, "embedded")
+  }
+
+  // `$XDG_DATA_HOME/collect_lic_info`, falling back to `~/.local/share/collect_lic_info`. Hermetic runs have none.
+  pub fn default_dir() -> Option<PathBuf> {
+    if hermetic::is_enabled() {
+      return None;
+    }
+    let base = env::var_os("XDG_DATA_HOME")
+      .map(PathBuf::from)
+      .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+    Some(base.join("collect_lic_info"))
+  }
+
+  pub fn describe(&self) -> String {
+    format!(
+      "{} (SPDX {}, format {})",
+      self.source, self.spdx_version, DATA_FORMAT_VERSION
+    )
+  }
+
+  // Canonical SPDX id for a known id or alias, otherwise the trimmed, upper-cased input
+  pub fn normalize(&self, license: &str) -> String {
+    let key = normalize_key(license);
+    self.lookup.get(&key).cloned().unwrap_or(key)
+  }
+
+  pub fn is_known(&self, license: &str) -> bool {
+    self.lookup.contains_key(&normalize_key(license))
+  }
+
+  // Takes a canonical id, as returned by `normalize`
+  pub fn is_copyleft(&self, id: &str) -> bool {
+    self.copyleft.contains(id)
+  }
+
+  // Unknown as soon as one branch is, copyleft as soon as one branch is
+  pub fn category(&self, license: Option<&str>) -> LicenseCategory {
+    let ids = license.map(|license| self.expression_ids(license)).unwrap_or_default();
+    if ids.is_empty() || !ids.iter().all(|id| self.is_known(id)) {
+      LicenseCategory::Unknown
+    } else if ids.iter().any(|id| self.is_copyleft(id)) {
+      LicenseCategory::Copyleft
+    } else {
+      LicenseCategory::Permissive
+    }
+  }
+
+  pub fn osi_approved(&self, license: Option<&str>) -> Option<LicenseFlag> {
+    self.flag(license, &self.osi_approved)
+  }
+
+  pub fn fsf_libre(&self, license: Option<&str>) -> Option<LicenseFlag> {
+    self.flag(license, &self.fsf_libre)
+  }
+
+  // Yes or No when every branch agrees, Mixed when they don't, None as soon as one branch is unknown
+  fn flag(&self, license: Option<&str>, listed: &HashSet<String>) -> Option<LicenseFlag> {
+    let ids = license.map(|license| self.expression_ids(license)).unwrap_or_default();
+    if ids.is_empty() || !ids.iter().all(|id| self.is_known(id)) {
+      return None;
+    }
+    let approved = ids.iter().filter(|id| listed.contains(*id)).count();
+    Some(match approved {
+      0 => LicenseFlag::No,
+      approved if approved == ids.len() => LicenseFlag::Yes,
+      _ => LicenseFlag::Mixed,
+    })
+  }
+
+  // Normalized ids of every AND/OR branch of an expression
+  pub fn expression_ids(&self, expression: &str) -> Vec<String> {
+    let expression = expression.replace(['(', ')'], " ");
+    let mut branches = Vec::new();
+    let mut current = Vec::new();
+    for token in expression.split_whitespace() {
+      if token.eq_ignore_ascii_case("OR") || token.eq_ignore_ascii_case("AND") {
+        branches.push(current.join(" "));
+        current.clear();
+      } else {
+        current.push(token);
+      }
+    }
+    branches.push(current.join(" "));
+
+    branches
+      .iter()
+      .filter(|branch| !branch.is_empty())
+      .map(|branch| self.normalize(branch))
+      .collect()
+  }
+
+  fn parse(content: &[u8], source: &str) -> Result<Self> {
+    let data: DataFile =
+      serde_json::from_slice(content).with_context(|| format!("Failed to parse license data: {}", source))?;
+    if data.format_version != DATA_FORMAT_VERSION {
+      bail!(
+        "License data {} has format version {}, this build reads version {}",
+        source,
+        data.format_version,
+        DATA_FORMAT_VERSION
+      );
+    }
+    if data.licenses.is_empty() {
+      bail!("License data {} lists no licenses", source);
+    }
+
+    let mut lookup: HashMap<String, String> = data.licenses.iter().map(|id| (normalize_key(id), id.clone())).collect();
+    for (alias, id) in &data.aliases {
+      if !data.licenses.contains(id) {
+        return Err(anyhow!(
+          "License data {}: alias `{}` points at unknown license `{}`",
+          source,
+          alias,
+          id
+        ));
+      }
+      lookup.insert(normalize_key(alias), id.clone());
+    }
+    let lists = [
+      ("copyleft", &data.copyleft),
+      ("osi_approved", &data.osi_approved),
+      ("fsf_libre", &data.fsf_libre),
+    ];
+    for (list, ids) in lists {
+      if let Some(unknown) = ids.iter().find(|id| !data.licenses.contains(id)) {
+        bail!(
+          "License data {}: {} entry `{}` is not a listed license",
+          source,
+          list,
+          unknown
+        );
+      }
+    }
+
+    Ok(Self {
+      source: source.to_owned(),
+      spdx_version: data.spdx_version,
+      lookup,
+      copyleft: data.copyleft.into_iter().collect(),
+      osi_approved: data.osi_approved.into_iter().collect(),
+      fsf_libre: data.fsf_libre.into_iter().collect(),
+    })
+  }
+}
+
+// Downloads the current SPDX license list and license texts into `dir`, keeping the embedded aliases and copyleft list.
+// The approval flags come from the downloaded list.
+pub async fn update_data(client: &HttpClient, dir: &Path) -> Result<()> {
+  println!("Fetch {}", SPDX_LIST_URL);
+  let response = client.get(SPDX_LIST_URL).await?;
+  if !response.status.is_success() {
+    bail!(
+      "Failed to fetch SPDX license list: HTTP {}{}",
+      response.status,
+      response.dump_ref()
+    );
+  }
+  let list: SpdxList = response.json()?;
+
+  let embedded: DataFile = serde_json::from_slice(EMBEDDED).context("Failed to parse embedded license data")?;
+  let flagged = |flag: fn(&SpdxLicense) -> bool| {
+    let mut ids = list
+      .licenses
+      .iter()
+      .filter(|license| flag(license))
+      .map(|license| license.id.clone())
+      .collect::<Vec<_>>();
+    ids.sort();
+    ids
+  };
+  let osi_approved = flagged(|license| license.osi_approved);
+  let fsf_libre = flagged(|license| license.fsf_libre);
+  let mut licenses: Vec<String> = list.licenses.into_iter().map(|license| license.id).collect();
+  licenses.sort();
+  let aliases = embedded
+    .aliases
+    .into_iter()
+    .filter(|(_, id)| licenses.contains(id))
+    .collect();
+  let copyleft = embedded
+    .copyleft
+    .into_iter()
+    .filter(|id| licenses.contains(id))
+    .collect();
+  let data = DataFile {
+    format_version: DATA_FORMAT_VERSION,
+    spdx_version: list.version,
+    licenses,
+    copyleft,
+    osi_approved,
+    fsf_libre,
+    aliases,
+  };
+
+  let text_dir = dir.join(TEXT_DIR);
+  hermetic::create_dir_all(&text_dir).with_context(|| format!("Failed to create {}", text_dir.display()))?;
+  let mut missing = 0;
+  for id in &data.licenses {
+    let url = format!("{}/{}.txt", SPDX_TEXT_URL, id);
+    match client.get(&url).await {
+      Ok(response) if response.status.is_success() => {
+        let path = text_dir.join(format!("{}.txt", id));
+        hermetic::write(&path, &response.body).with_context(|| format!("Failed to write {}", path.display()))?;
+      },
+      Ok(response) => {
+        missing += 1;
+        println!(
+          "Can't fetch license text for {}: HTTP {}{}",
+          id,
+          response.status,
+          response.dump_ref()
+        );
+      },
+      Err(err) => {
+        missing += 1;
+        println!("Can't fetch license text for {}: {:#}", id, err);
+      },
+    }
+  }
+
+  // Written last so a partial download never shadows the embedded copy
+  let path = dir.join(DATA_FILE);
+  let content = serde_json::to_vec_pretty(&data).context("Failed to serialize license data")?;
+  hermetic::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+
+  println!(
+    "Saved SPDX {} ({} licenses, {} text(s) missing) to {}",
+    data.spdx_version,
+    data.licenses.len(),
+    missing,
+    dir.display()
+  );
+  Ok(())
+}
+
+fn normalize_key(license: &str) -> String {
+  license.split_whitespace().collect::<Vec<_>>().join(" ").to_uppercase()
+}
@@ -1,28 +1,885 @@
-mod cli;
-mod deps;
-mod report;
-mod types;
+use anyhow::{bail, Context, Result};
+use cache::BranchCache;
+use cli::{Args, AuthAction, CacheAction, Command, DEFAULT_NOTICES_MIN_SIZE};
+use deps::{
+  actions_deps::ActionsParser,
+  cargo_deps::CargoParser,
+  cocoapods_deps::CocoaPodsParser,
+  conan_deps::ConanParser,
+  dart_deps::DartParser,
+  deno_deps::DenoParser,
+  dependency_list::DependencyList,
+  detect::DetectedManifests,
+  filter::PackageFilter,
+  go_deps::GoParser,
+  gradle_deps::GradleParser,
+  helm_deps::HelmParser,
+  installed::InstalledVersions,
+  js_deps::JsParser,
+  manifests::{ManifestFilter, ReparseScope},
+  manual,
+  maven_deps::MavenParser,
+  nuget_deps::NugetParser,
+  patterns::{validate_patterns, NamespaceSet, PatternSet},
+  php_deps::PhpParser,
+  py_deps::PyParser,
+  ruby_deps::RubyParser,
+  sbom::{EmptyRoot, SbomImport},
+  sheets::{SheetNames, SheetRouter},
+  swift_deps::SwiftParser,
+  terraform_deps::TerraformParser,
+};
+use depsfetch::{
+  cache, cli, credentials, deps, diagnostics, graph, hermetic, http, init, integrity, license_data, report, tui, types,
+  version, watch,
+};
+use diagnostics::{Category, Diagnostics};
+use graph::DependencyGraph;
+use http::{HttpClient, TlsPolicy};
+use integrity::{SigningKey, MANIFEST_FILE};
+use license_data::LicenseData;
+use report::{
+  Baseline, CanonicalMap, DedupStrategy, LicenseFilter, LicensePolicy, NoticesProbe, NpmRegistry, PostProcess,
+  ProjectSplit, Redaction, ReportFormat, ReportGenerator, ReportWriter, ResolutionEvent, Revalidation, Reviews,
+  StreamFormat, StreamWriter, TargetPlatform, TwinDetector, WriterOutput, REVIEW_FILE,
+};
+use std::{
+  collections::HashSet,
+  env, fs,
+  path::{Path, PathBuf},
+  sync::{mpsc, Arc},
+  thread::{self, JoinHandle},
+  time::Duration,
+};
+use tui::Tui;
+use types::{Ecosystem, LicenseConfidence};
+use version::PrereleasePolicy;
+use watch::{merge_table, CycleDelta, ManifestWatcher, ParsedManifests, WatchState};
 
-use anyhow::Result;
-use cli::Args;
-use deps::{go_deps::GoParser, js_deps::JsParser};
-use report::ReportGenerator;
+const WARNINGS_FILE: &str = "warnings.log";
+const GITHUB_TOKEN_ENV: &str = "GITHUB_TOKEN";
 
 #[tokio::main]
 async fn main() -> Result<()> {
   let args = Args::parse_args()?;
-  let report_generator = ReportGenerator::new("deps_report.xlsx")?;
+  match &args.command {
+    Some(Command::VerifyReport { manifest, public_key }) => {
+      return integrity::verify_report(manifest, public_key.as_deref());
+    },
+    Some(Command::History { dir, csv, xlsx }) => {
+      let license_data = LicenseData::load(LicenseData::default_dir().as_deref())?;
+      return report::generate_history(dir, csv, xlsx, &license_data);
+    },
+    Some(Command::Auth { .. } | Command::UpdateData { .. } | Command::Cache { .. }) if hermetic::is_enabled() => {
+      bail!("Hermetic builds keep no credentials, license data or cache outside the scanned directory");
+    },
+    Some(Command::Auth { action }) => {
+      return match action {
+        AuthAction::Set { host } => credentials::set(host),
+        AuthAction::List => credentials::list(),
+        AuthAction::Remove { host } => credentials::remove(host),
+      };
+    },
+    Some(Command::UpdateData { dir }) => {
+      let dir = match dir {
+        Some(dir) => PathBuf::from(dir),
+        None => LicenseData::default_dir().context("Can't determine the user data directory, pass one explicitly")?,
+      };
+      return license_data::update_data(&with_tls_policy(HttpClient::new(&None)?, &args)?, &dir).await;
+    },
+    Some(Command::Cache { action }) => {
+      return match action {
+        CacheAction::ShowBranches => cache::show_branches(),
+      };
+    },
+    Some(Command::Schema { name }) => return report::print_schema(name.as_deref()),
+    Some(Command::Init { directory, force }) => return init::write_config(directory, *force),
+    Some(Command::ImportReview {
+      reviewed,
+      directory,
+      review_file,
+    }) => {
+      let review_file = match review_file {
+        Some(path) => PathBuf::from(path),
+        None => Path::new(directory).join(REVIEW_FILE),
+      };
+      return report::import_review(reviewed, &review_file);
+    },
+    None => {},
+  }
 
-  // Process JavaScript dependencies
-  let js_parser = JsParser::new(&args.directory, &args.exclude, &args.skip)?;
-  let web_deps = js_parser.parse().await?;
-  report_generator.generate_js_report("Web", web_deps).await?;
+  match args.watch {
+    true => watch(&args).await,
+    false => run(&args, None).await,
+  }
+}
+
+// --watch: the first report, then one more per batch of manifest changes until Ctrl-C. Each cycle is a whole run
+// with its own client, caches and outputs. The previous cycle's rows carry over to spare their resolution, and what
+// it parsed so only the changed manifests are read again.
+async fn watch(args: &Args) -> Result<()> {
+  let directory = args.directory.as_deref().context("Missing directory to scan")?;
+  let mut watcher = ManifestWatcher::new(directory)?;
+  let mut state = WatchState::default();
+  let mut first = true;
+  loop {
+    let previous = state.rows.clone();
+    // Dropping a cycle midway leaves the last complete outputs, the writer saves nothing it didn't finish
+    let finished = tokio::select! {
+      result = run(args, Some(&mut state)) => Some(result),
+      _ = tokio::signal::ctrl_c() => None,
+    };
+    let failed = match finished {
+      None => break,
+      Some(Err(err)) => {
+        println!("Report failed: {:#}", err);
+        true
+      },
+      Some(Ok(())) if !first => {
+        println!("{}", CycleDelta::between(&previous, &state.rows));
+        false
+      },
+      Some(Ok(())) => false,
+    };
+    first = false;
+
+    println!("Watching {} for manifest changes, Ctrl-C to stop", directory);
+    let changed = tokio::select! {
+      changed = watcher.next_change() => changed,
+      _ = tokio::signal::ctrl_c() => None,
+    };
+    let Some(changed) = changed else {
+      break;
+    };
+    for path in &changed {
+      println!("Changed: {}", path.display());
+    }
+    // A failed cycle may not have kept what it parsed, the next one reads everything again
+    state.changed = (!failed).then_some(changed);
+  }
+
+  println!("Stopped watching {}", directory);
+  Ok(())
+}
+
+// One report. `previous` is only given by --watch: the last cycle's rows and parsed manifests, replaced by this run's
+// once written.
+async fn run(args: &Args, mut previous: Option<&mut WatchState>) -> Result<()> {
+  if let Some(requirement) = &args.require_version {
+    version::require(requirement)?;
+  }
+  let empty_root;
+  let directory = match (args.directory.as_deref(), &args.from_list, &args.import_sbom) {
+    (Some(directory), _, _) => directory,
+    (None, Some(list), _) => {
+      let list = match list.as_str() {
+        "-" => "stdin",
+        path => path,
+      };
+      println!("No directory to scan, reporting the packages of {}", list);
+      empty_root = EmptyRoot::create()?;
+      empty_root.path()
+    },
+    (None, None, Some(sbom)) => {
+      println!("No directory to scan, reporting the components of {}", sbom);
+      empty_root = EmptyRoot::create()?;
+      empty_root.path()
+    },
+    (None, None, None) => bail!("Missing directory to scan"),
+  };
+  validate_patterns(&[
+    ("--exclude", &args.exclude),
+    ("--skip", &args.skip),
+    ("--nested-notices-package", &args.nested_notices_package),
+  ])?;
+  let canonical_map = match &args.canonical_map {
+    Some(path) => Some(CanonicalMap::load(path)?),
+    None => None,
+  };
+  let target_platform = match &args.target_platform {
+    Some(platform) => Some(TargetPlatform::parse(platform)?),
+    None => None,
+  };
+  let min_confidence = match &args.min_confidence {
+    Some(level) => Some(LicenseConfidence::parse(level)?),
+    None => None,
+  };
+  let dedup = match &args.dedup {
+    Some(strategy) => DedupStrategy::parse(strategy)?,
+    None => DedupStrategy::All,
+  };
+  let prerelease = match &args.prerelease {
+    Some(policy) => PrereleasePolicy::parse(policy)?,
+    None => PrereleasePolicy::default(),
+  };
+  let sheet_router = SheetRouter::new(
+    &args.sheet,
+    &SheetNames {
+      js: args.js_sheet_name.as_deref(),
+      go: args.go_sheet_name.as_deref(),
+      rust: args.rust_sheet_name.as_deref(),
+      python: args.python_sheet_name.as_deref(),
+      java: args.java_sheet_name.as_deref(),
+      ruby: args.ruby_sheet_name.as_deref(),
+      php: args.php_sheet_name.as_deref(),
+      dotnet: args.dotnet_sheet_name.as_deref(),
+      dart: args.dart_sheet_name.as_deref(),
+      swift: args.swift_sheet_name.as_deref(),
+      cpp: args.cpp_sheet_name.as_deref(),
+      deno: args.deno_sheet_name.as_deref(),
+      helm: args.helm_sheet_name.as_deref(),
+      terraform: args.terraform_sheet_name.as_deref(),
+    },
+  )?;
+  let sbom = match &args.import_sbom {
+    Some(path) => Some(SbomImport::load(path)?),
+    None => None,
+  };
+  let mut manual_rows = manual::rows(&args.manual_components)?;
+  let package_filter = PackageFilter::new(&args.filter)?;
+  // Stored tokens fill in for hosts the environment doesn't cover
+  let (mut tokens, credential_store) = credentials::load_stored();
+  if let Ok(token) = env::var(GITHUB_TOKEN_ENV) {
+    tokens.insert("github.com".to_owned(), token);
+  }
+  let diagnostics = Arc::new(Diagnostics::default());
+  let dependency_list = match (&args.from_list, &args.list_ecosystem) {
+    (Some(path), Some(ecosystem)) => Some(DependencyList::load(path, ecosystem, &diagnostics)?),
+    _ => None,
+  };
+  let npm_registry = NpmRegistry::new(args.npm_registry.as_deref(), args.npm_auth.as_deref(), &tokens)?
+    .with_strict_versions(args.strict_versions)
+    .with_prerelease(prerelease)
+    .with_diagnostics(diagnostics.clone());
+  let signing_key = match &args.sign_key {
+    Some(path) => Some(SigningKey::load(path)?),
+    None => None,
+  };
+  let license_data = LicenseData::load(
+    args
+      .license_data
+      .as_ref()
+      .map(PathBuf::from)
+      .or_else(LicenseData::default_dir)
+      .as_deref(),
+  )?;
+  let mut run_info = args.run_info();
+  run_info.push(("license_data", license_data.describe(), ""));
+  run_info.push((
+    "credentials",
+    match credential_store {
+      Some(store) => format!("{}: {}", store, tokens.keys().cloned().collect::<Vec<_>>().join(", ")),
+      None => tokens.keys().cloned().collect::<Vec<_>>().join(", "),
+    },
+    "",
+  ));
+  let client = with_tls_policy(
+    HttpClient::new(&args.debug_http)?
+      .with_tokens(tokens)
+      .with_offline(args.offline),
+    args,
+  )?;
+  run_info.push(("version", version::RUNNING.to_owned(), ""));
+  // Sheets cut to Excel's limit, under the names they were given
+  for (short, full) in sheet_router.legend() {
+    run_info.push(("sheet_name", format!("{} = {}", short, full), ""));
+  }
+  if args.check_version {
+    let latest = version::check_latest(&client).await;
+    run_info.push(("latest_version", latest.unwrap_or_else(|| "unknown".to_owned()), ""));
+  }
+  // Checked when parsing the arguments already
+  let report_format = ReportFormat::select(args.format.as_deref())?;
+  let mut report_writer = report_format.build(ReportWriter::new(report_format.file)?, !args.no_sort)?;
+  let mut outputs = Vec::new();
+
+  let exclude = Arc::new(PatternSet::compile("--exclude", &args.exclude)?);
+  let skip = Arc::new(PatternSet::compile("--skip", &args.skip)?);
+  let skip_namespaces = Arc::new(NamespaceSet::new(&args.skip_scope, &args.skip_prefix)?);
+  let manifests = Arc::new(ManifestFilter::new(&args.manifests, &args.manifest_exclude)?);
+  let js_parser = JsParser::new(
+    directory,
+    exclude.clone(),
+    skip.clone(),
+    manifests.clone(),
+    diagnostics.clone(),
+  )?
+  .exclude_first_party(args.exclude_private)
+  .transitive(args.transitive)
+  .skip_namespaces(skip_namespaces.clone());
+  let go_parser = GoParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?
+    .skip_namespaces(skip_namespaces.clone());
+  let cargo_parser = CargoParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?;
+  let py_parser = PyParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?
+    .include_pipenv_dev(args.pipenv_dev);
+  let maven_parser = MavenParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?
+    .include_test_scope(args.maven_test);
+  let gradle_parser = GradleParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?
+    .include_test_configurations(args.maven_test);
+  let ruby_parser = RubyParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?;
+  let php_parser = PhpParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?;
+  let nuget_parser = NugetParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?;
+  let dart_parser = DartParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?;
+  let swift_parser = SwiftParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?;
+  let pods_parser = CocoaPodsParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?;
+  let conan_parser = ConanParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?;
+  let deno_parser = DenoParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?;
+  let helm_parser = HelmParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?;
+  let terraform_parser = TerraformParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?;
+  let actions_parser = ActionsParser::new(directory, exclude.clone(), manifests.clone(), diagnostics.clone())?;
+
+  let redaction = match args.redact_internal {
+    // The key was checked when parsing the arguments already
+    true => Some(Arc::new(Redaction::new(
+      args.redact_key.as_deref().unwrap_or_default(),
+      init::first_party(directory)?,
+      &args.internal_namespace,
+    ))),
+    false => None,
+  };
+
+  if let Some(graph_path) = &args.graph {
+    let mut graph = DependencyGraph::default();
+    js_parser.collect_graph(&mut graph)?;
+    go_parser.collect_graph(&mut graph, args.use_go_tool)?;
+    if let Some(redaction) = &redaction {
+      graph = graph.renamed(|name| redaction.name(name));
+    }
+    graph.write_dot(graph_path)?;
+    outputs.push(graph_path.clone());
+    report_writer = report_writer.with_graph(graph);
+  }
+  if args.funding {
+    report_writer = report_writer.with_funding();
+  }
+  if args.provenance {
+    report_writer = report_writer.with_provenance();
+  }
+  if args.repo_mismatch {
+    report_writer = report_writer.with_repo_mismatch();
+  }
+  if args.verify_installed {
+    report_writer = report_writer.with_installed_mismatch();
+  }
+  if args.nested_notices {
+    report_writer = report_writer.with_third_party_notices();
+  }
+  if args.verify_links {
+    report_writer = report_writer.with_link_status();
+  }
+  if args.confidence {
+    report_writer = report_writer.with_confidence();
+  }
+  if args.provenance_check {
+    report_writer = report_writer.with_attestation();
+  }
+  if args.sizes {
+    report_writer = report_writer.with_sizes();
+  }
+  if args.license_flags {
+    report_writer = report_writer.with_license_flags();
+  }
+  if args.review_columns {
+    let review_file = match &args.review_file {
+      Some(path) => PathBuf::from(path),
+      None => Path::new(directory).join(REVIEW_FILE),
+    };
+    let reviews = Reviews::load(&review_file)?;
+    println!(
+      "Review columns pre-filled from {} ({} decision(s))",
+      review_file.display(),
+      reviews.len()
+    );
+    report_writer = report_writer.with_review_columns(reviews);
+  }
+  report_writer = report_writer.with_policy(LicensePolicy {
+    require_osi_approved: args.require_osi_approved,
+    require_fsf_libre: args.require_fsf_libre,
+  });
+  if let Some(confidence) = min_confidence {
+    report_writer = report_writer.with_min_confidence(confidence);
+  }
+  report_writer = report_writer.with_dedup(dedup);
+  if args.collapse_by_repo {
+    report_writer = report_writer.with_repo_groups();
+  }
+  if args.detect_twins {
+    report_writer = report_writer.with_twins(TwinDetector::new(&args.twin_pairs)?, args.count_twins);
+  }
+  if let Some(path) = &args.csv {
+    report_writer = report_writer.with_stream(StreamWriter::create(path, StreamFormat::Csv, !args.no_sort)?);
+  }
+  if let Some(path) = &args.jsonl {
+    let mut stream = StreamWriter::create(path, StreamFormat::JsonLines, !args.no_sort)?;
+    if let Some(filter) = &package_filter {
+      stream = stream.with_filters(filter.filters());
+    }
+    report_writer = report_writer.with_stream(stream);
+  }
+  if let Some(licenses) = &args.exclude_licenses {
+    report_writer = report_writer.with_license_filter(LicenseFilter::new(licenses, &license_data));
+  }
+  report_writer = report_writer.with_license_data(license_data);
+  if let Some(command) = &args.post_process {
+    report_writer = report_writer.with_post_process(PostProcess::new(
+      command,
+      Duration::from_secs(args.post_process_timeout),
+    ));
+  }
+  if target_platform.is_some() {
+    report_writer = report_writer.with_platform();
+  }
+  if args.constant_memory {
+    report_writer = report_writer.with_constant_memory();
+  }
+  if args.no_hyperlink_format {
+    report_writer = report_writer.without_hyperlink_format();
+  }
+  if let Some(limit) = args.link_limit {
+    report_writer = report_writer.with_link_limit(limit);
+  }
+  if args.split_on_link_limit {
+    report_writer = report_writer.with_split_on_link_limit();
+  }
+  if let (Some(dir), Some(map)) = (&args.split_by_project, &canonical_map) {
+    report_writer = report_writer.with_project_split(ProjectSplit::new(dir, map.clone())?, !args.no_combined);
+  }
+
+  if previous.is_some() {
+    report_writer = report_writer.keep_rows();
+  }
+  if let Some(redaction) = &redaction {
+    report_writer = report_writer.with_redaction(Arc::clone(redaction));
+  }
+
+  // Loaded before anything is scanned, so a stale baseline stops the run right away. A --watch cycle after the
+  // first trusts the rows of the one before instead.
+  let baseline = match (&previous, &args.trust_baseline) {
+    (Some(state), _) if !state.rows.is_empty() => Some(Arc::new(Baseline::from_rows("previous cycle", &state.rows))),
+    (_, Some(path)) => Some(Arc::new(Baseline::load(path, args.max_baseline_age)?)),
+    _ => None,
+  }; // Taken for the run: a cycle that fails leaves nothing half merged behind
+  let (mut parsed, reparse) = match previous.as_deref_mut() {
+    Some(state) => (
+      std::mem::take(&mut state.parsed),
+      state.changed.as_ref().map(ReparseScope::new),
+    ),
+    None => (ParsedManifests::default(), None),
+  };
+  let root = fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+  let npm_registry = npm_registry.with_scope_registries(&args.scope_registry, &js_parser.npmrc_files())?;
+  // Scopes routed by .npmrc files too, not only the settings
+  for scope in npm_registry.describe_scopes() {
+    run_info.push(("scope_route", scope, ""));
+  }
+  let (sender, writer) = report_writer.spawn();
+  let branch_cache = Arc::new(BranchCache::load());
+  let mut report_generator = ReportGenerator::new(&client, sender, diagnostics.clone())
+    .with_branch_cache(Arc::clone(&branch_cache))
+    .with_npm_registry(npm_registry)
+    .with_prerelease(prerelease)
+    .with_yarn_caches(js_parser.yarn_caches());
+  if let Some(platform) = target_platform {
+    report_generator = report_generator.with_target_platform(platform, args.strict_platform);
+  }
+  if args.provenance_check {
+    report_generator = report_generator.with_attestation_check();
+  }
+  if args.sizes {
+    report_generator = report_generator.with_sizes();
+  }
+  if args.verify_installed {
+    report_generator = report_generator.with_installed_check(InstalledVersions::new(directory)?);
+  }
+  if args.nested_notices {
+    let packages = PatternSet::compile("--nested-notices-package", &args.nested_notices_package)?;
+    let min_size = args.nested_notices_min_size.unwrap_or(DEFAULT_NOTICES_MIN_SIZE);
+    report_generator = report_generator.with_nested_notices(NoticesProbe::new(directory, min_size, packages)?);
+  }
+  if let Some(baseline) = &baseline {
+    println!("Trusting baseline {}", baseline.describe());
+    report_generator = report_generator.with_trusted_baseline(Arc::clone(baseline));
+    if let Some(percent) = args.revalidate_sample {
+      let revalidation = Revalidation::new(percent, args.revalidate_seed)?;
+      println!(
+        "Revalidating {}; --revalidate-seed {} draws the same packages again",
+        revalidation.describe(),
+        revalidation.seed()
+      );
+      run_info.push(("revalidation", revalidation.describe(), ""));
+      report_generator = report_generator.with_revalidation(revalidation);
+    }
+  }
+  if args.verify_links {
+    report_generator = report_generator.with_link_check(args.replace_dead_links);
+  }
+  if let Some(path) = &args.notice {
+    report_generator = report_generator.with_notice(path, redaction.clone());
+  }
+  if args.final_retry_window > 0 {
+    report_generator = report_generator.with_final_retry();
+  }
+  // The generator prints nothing itself, what it reports is shown by the table or as plain progress lines
+  let (events, receiver) = mpsc::channel();
+  let cancel = Arc::default();
+  report_generator = report_generator.with_events(events, Arc::clone(&cancel));
+  let progress = match args.tui {
+    true => Tui::spawn(receiver, cancel),
+    false => print_progress(receiver),
+  };
+
+  let resolution = async {
+    for sheet in sheet_router.sheets() {
+      report_generator.announce_sheet(sheet)?;
+    }
+
+    // A --watch cycle reads the changed manifests, the earlier cycles' parse stands in for the others
+    manifests.set_reparse_scope(reparse.clone());
+    let mut detected = DetectedManifests::default();
+    let mut web_deps = js_parser.parse(&mut detected).await?;
+    for suggestion in detected.suggestions() {
+      println!("{}", suggestion);
+    }
+    run_info.push(("unparsed_manifests", detected.describe(), ""));
+    // deno.json's `npm:` imports are resolved and listed like package.json dependencies
+    let deno_deps = deno_parser.parse().await?;
+    for entry in deno_parser.npm_imports().into_values() {
+      entry.insert_into(&mut web_deps);
+    }
+    // Maven and Gradle dependencies are both resolved from Maven Central
+    let mut java_deps = maven_parser.parse().await?;
+    for (_, dep) in gradle_parser.parse().await? {
+      dep.insert_into(&mut java_deps);
+    }
+
+    // Each ecosystem's packages and the rows its manifests settle without a lookup (direct references, local
+    // replacements), in the order they are resolved
+    let scans = [
+      (Ecosystem::Npm, web_deps, Vec::new()),
+      (Ecosystem::Go, go_parser.parse().await?, go_parser.local_replacements()?),
+      (Ecosystem::Cargo, cargo_parser.parse().await?, Vec::new()),
+      (Ecosystem::Pypi, py_parser.parse().await?, py_parser.direct_references()),
+      (Ecosystem::Maven, java_deps, Vec::new()),
+      (
+        Ecosystem::Gem,
+        ruby_parser.parse().await?,
+        ruby_parser.direct_references(),
+      ),
+      (Ecosystem::Composer, php_parser.parse().await?, Vec::new()),
+      (Ecosystem::Nuget, nuget_parser.parse().await?, Vec::new()),
+      (
+        Ecosystem::Pub,
+        dart_parser.parse().await?,
+        dart_parser.direct_references(),
+      ),
+      (
+        Ecosystem::Swift,
+        swift_parser.parse().await?,
+        swift_parser.direct_references(),
+      ),
+      (
+        Ecosystem::Cocoapods,
+        pods_parser.parse().await?,
+        pods_parser.direct_references(),
+      ),
+      (Ecosystem::Conan, conan_parser.parse().await?, Vec::new()),
+      (Ecosystem::Deno, deno_deps, Vec::new()),
+      (Ecosystem::Helm, helm_parser.parse().await?, Vec::new()),
+      (Ecosystem::Terraform, terraform_parser.parse().await?, Vec::new()),
+    ];
+    let actions_deps = match args.github_actions {
+      true => {
+        let deps = actions_parser.parse().await?;
+        Some(
+          parsed
+            .merge(Ecosystem::GithubActions, &root, reparse.as_ref(), deps, Vec::new())
+            .0,
+        )
+      },
+      false => None,
+    };
+    manifests.set_reparse_scope(None);
+    // What the resolvers need from the lockfiles besides the packages
+    report_generator.use_composer_locks(merge_table(&mut parsed.composer_locks, php_parser.locked_packages()));
+    report_generator.use_pub_hosts(merge_table(&mut parsed.pub_hosts, dart_parser.hosted_elsewhere()));
+    report_generator.use_swift_pins(merge_table(&mut parsed.swift_pins, swift_parser.pins()));
+    report_generator.use_conan_channels(merge_table(&mut parsed.conan_channels, conan_parser.channels()));
+    report_generator.use_helm_repositories(merge_table(&mut parsed.helm_repositories, helm_parser.repositories()));
 
-  // Process Go dependencies
-  let go_parser = GoParser::new(&args.directory, &args.exclude)?;
-  let go_deps = go_parser.parse().await?;
-  report_generator.generate_go_report("Backend", go_deps).await?;
+    let mut scanned = HashSet::new();
+    for (ecosystem, deps, settled) in scans {
+      let (mut deps, mut settled) = parsed.merge(ecosystem, &root, reparse.as_ref(), deps, settled);
+      if let Some(list) = &dependency_list {
+        list.merge_into(ecosystem, &mut deps);
+      }
+      let mut imported = match &sbom {
+        Some(sbom) => sbom.merge_into(ecosystem, &mut deps, args.re_resolve_imported),
+        None => Vec::new(),
+      };
+      scanned.extend(deps.keys().cloned());
+      scanned.extend(settled.iter().map(|row| row.name.clone()));
+      if let Some(filter) = &package_filter {
+        filter.retain(ecosystem, &mut deps);
+        filter.retain_rows(&mut imported);
+        filter.retain_rows(&mut settled);
+      }
+      for (sheets, deps) in sheet_router.route(ecosystem, deps) {
+        report_generator.generate_report(ecosystem, &sheets, deps).await?;
+      }
+      let sheet = sheet_router.default_sheet(ecosystem);
+      report_generator.generate_imported_rows(sheet, imported, false)?;
+      report_generator.generate_imported_rows(sheet, settled, false)?;
+    }
 
-  report_generator.save()?;
+    // Process GitHub Actions, on a sheet of their own that --sheet doesn't route to
+    if let Some(mut actions_deps) = actions_deps {
+      report_generator.announce_sheet("CI Actions")?;
+      scanned.extend(actions_deps.values().map(|dep| dep.name.clone()));
+      if let Some(filter) = &package_filter {
+        filter.retain(Ecosystem::GithubActions, &mut actions_deps);
+      }
+      report_generator
+        .generate_report(Ecosystem::GithubActions, &["CI Actions"], actions_deps)
+        .await?;
+    }
+
+    if args.final_retry_window > 0 {
+      let (rescued, retried) = report_generator
+        .retry_failed(Duration::from_secs(args.final_retry_window))
+        .await?;
+      run_info.push(("final_retry", format!("rescued {} of {}", rescued, retried), ""));
+    }
+    if let Some(baseline) = &baseline {
+      let (served, resolved) = baseline.counts();
+      println!("Baseline: {} package(s) served from it, {} resolved", served, resolved);
+      run_info.push(("baseline", format!("{} served, {} resolved", served, resolved), ""));
+      if args.revalidate_sample.is_some() {
+        let (sampled, drifted) = report_generator.finish_revalidation()?;
+        println!("Revalidation: {} of {} baseline package(s) drifted", drifted, sampled);
+        run_info.push(("revalidated", format!("{} drifted of {}", drifted, sampled), ""));
+      }
+    }
+
+    if let Some(sbom) = &sbom {
+      let mut unknown_rows = sbom.unknown_rows();
+      if let Some(filter) = &package_filter {
+        filter.retain_rows(&mut unknown_rows);
+      }
+      report_generator.generate_imported_rows("Imported", unknown_rows, true)?;
+    }
+    if !manual_rows.is_empty() {
+      manual::warn_duplicates(&manual_rows, &scanned, &diagnostics);
+      if let Some(filter) = &package_filter {
+        filter.retain_rows(&mut manual_rows);
+      }
+      report_generator.generate_imported_rows("Manual", manual_rows, true)?;
+    }
+    // Ahead of the sheets built from the rows the writer has
+    if args.verify_links {
+      let (checked, dead) = report_generator.verify_links().await?;
+      run_info.push(("link_check", format!("{} dead of {}", dead, checked), ""));
+    }
+    if args.notice.is_some() {
+      let (texts, packages) = report_generator.write_notice().await?;
+      run_info.push((
+        "notice",
+        format!("{} unique text(s) for {} package(s)", texts, packages),
+        "",
+      ));
+    }
+    report_generator.release_held()?;
+
+    if let Some(canonical_map) = canonical_map {
+      report_generator.generate_projects_report("Projects", canonical_map)?;
+    }
+    if args.by_license {
+      report_generator.generate_by_license_report("By license", args.by_license_collapse.unwrap_or(0))?;
+    }
+
+    if args.self_audit {
+      let mut self_audit = js_parser.self_audit()?;
+      self_audit.extend(go_parser.self_audit()?);
+
+      println!("Self audit: {} finding(s)", self_audit.len());
+      for finding in &self_audit {
+        diagnostics.warn(
+          Category::SelfAudit,
+          format!("{} ({}): {}", finding.manifest, finding.package, finding.issue),
+        );
+      }
+      report_generator.generate_self_audit_report("Self audit", self_audit)?;
+    }
+
+    if !args.exclusion_rules.is_empty() {
+      // Within one option the rules are in pattern order, just like its counters
+      let mut counters = [
+        ("--exclude", exclude.filtered_counts().into_iter()),
+        ("--skip", skip.filtered_counts().into_iter()),
+        (
+          "--skip-scope",
+          skip_namespaces.filtered_counts("--skip-scope").into_iter(),
+        ),
+        (
+          "--skip-prefix",
+          skip_namespaces.filtered_counts("--skip-prefix").into_iter(),
+        ),
+      ];
+      let mut rules = Vec::new();
+      for rule in &args.exclusion_rules {
+        let filtered = counters
+          .iter_mut()
+          .find(|(option, _)| *option == rule.option)
+          .and_then(|(_, counts)| counts.next())
+          .unwrap_or_default();
+        if filtered == 0 {
+          diagnostics.warn(
+            Category::ScanIssue,
+            format!("{} `{}` did not filter anything", rule.option, rule.pattern),
+          );
+        }
+        rules.push((rule.clone(), filtered));
+      }
+      // Packages left out, by the option that did it; --exclude filters manifest paths instead
+      let skipped = ["--skip", "--skip-scope", "--skip-prefix"]
+        .into_iter()
+        .map(|option| {
+          let count = rules
+            .iter()
+            .filter(|(rule, _)| rule.option == option)
+            .map(|(_, filtered)| filtered)
+            .sum::<usize>();
+          (option, count)
+        })
+        .filter(|(_, count)| *count > 0)
+        .map(|(option, count)| format!("{} by {}", count, option))
+        .collect::<Vec<_>>();
+      if !skipped.is_empty() {
+        println!("Skipped packages: {}", skipped.join(", "));
+      }
+      report_generator.generate_exclusions_report("Exclusions", rules)?;
+    }
+
+    if manifests.is_configured() {
+      let tallies = manifests.tallies();
+      println!("Manifests by kind (found / excluded by kind rules):");
+      for tally in &tallies {
+        println!(
+          "  {:<18} {:>5} / {}{}",
+          tally.kind,
+          tally.found,
+          tally.excluded,
+          if tally.enabled { "" } else { " (disabled)" }
+        );
+      }
+      report_generator.generate_manifests_report("Manifests", tallies)?;
+    }
+
+    report_generator.generate_summary_report("Summary")?;
+    run_info.extend(client.metrics().run_info());
+    // Kept for the bundle's manifest
+    report_generator.generate_run_info("Run info", run_info.clone())?;
+    report_generator.finish()
+  };
+  // The resolution's requests are tallied as one task
+  let resolved = client.metrics().measured(resolution).await;
+  // The generator and its event sender are gone by now, the progress thread ends on its own
+  let _ = progress.join();
+  // Lookups made before a failure are still good for the next run
+  if let Err(err) = branch_cache.save() {
+    println!("Failed to save the branch cache: {:#}", err);
+  }
+
+  // A failed writer hangs up on the resolution, so its error is the one worth reporting
+  let written = writer.await.context("Report writer panicked")??;
+  resolved?;
+  let WriterOutput {
+    streams,
+    split_files,
+    violations,
+    rows,
+  } = written.context("Report writer stopped without saving the report")?;
+  if let Some(previous) = previous {
+    previous.rows = rows;
+    previous.parsed = parsed;
+  }
+  // Local only: never one of the outputs, so neither checksummed nor bundled
+  if let (Some(redaction), Some(path)) = (&redaction, &args.redaction_map) {
+    redaction.write_map(path)?;
+    println!("Redaction map written to {}, keep it out of what you share", path);
+  }
+  outputs.extend(streams);
+  outputs.extend(split_files);
+  outputs.extend(args.notice.clone());
+  if report_format.workbook && !args.no_combined {
+    outputs.push(report_format.file.to_owned());
+  }
+
+  // Hash the final bytes once every output is closed
+  integrity::write_manifest(MANIFEST_FILE, &outputs, signing_key.as_ref())?;
+  if let Some(path) = &args.bundle {
+    let mut files = outputs.clone();
+    files.push(MANIFEST_FILE.to_owned());
+    if signing_key.is_some() {
+      files.push(integrity::signature_path(MANIFEST_FILE));
+    }
+    let run_info = match &redaction {
+      Some(redaction) => redaction.run_info(run_info.clone()),
+      None => run_info.clone(),
+    };
+    report::write_bundle(path, &files, &run_info)?;
+    println!("Bundled {} file(s) into {}", files.len(), path);
+  }
+
+  client.metrics().print_summary();
+  // After the writer's summary, and ahead of the failure checks that count from the same warnings
+  let logged = diagnostics.write_log(WARNINGS_FILE, |line| match &redaction {
+    Some(redaction) => redaction.scrub(line),
+    None => line.to_owned(),
+  })?;
+  diagnostics.print_recap(args.verbose, logged.then_some(WARNINGS_FILE));
+
+  let repo_mismatches = diagnostics.count(Category::RepoMismatch);
+  if args.fail_on_repo_mismatch && repo_mismatches > 0 {
+    bail!("{} package(s) flagged for a repository mismatch", repo_mismatches);
+  }
+  let dead_links = diagnostics.count(Category::DeadLink);
+  if args.fail_on_dead_links && dead_links > 0 {
+    bail!("{} dead link(s) in the report", dead_links);
+  }
+  let findings = diagnostics.count(Category::SelfAudit);
+  if args.fail_on_self_audit && findings > 0 {
+    bail!("Self audit found {} issue(s) in first-party manifests", findings);
+  }
+  if !violations.is_empty() {
+    for violation in &violations {
+      println!("License policy: {}", violation);
+    }
+    bail!("{} package(s) break the license policy", violations.len());
+  }
   Ok(())
 }
+
+// Progress of a run without --tui
+fn print_progress(events: mpsc::Receiver<ResolutionEvent>) -> JoinHandle<()> {
+  thread::spawn(move || {
+    for event in events {
+      match event {
+        ResolutionEvent::Fetching { name, .. } => println!("Fetch license for {}", name),
+        ResolutionEvent::Note { message } => println!("{}", message),
+        _ => {},
+      }
+    }
+  })
+}
+
+fn with_tls_policy(client: HttpClient, args: &Args) -> Result<HttpClient> {
+  match &args.ca_bundle {
+    Some(bundle) => {
+      let policy = TlsPolicy::load(bundle, &args.pin)?;
+      println!("TLS: trusting {}", policy.describe());
+      client.with_tls(policy)
+    },
+    None => Ok(client),
+  }
+}
@@ -0,0 +1,299 @@
+use crate::integrity::sha256_hex;
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fmt, fs,
+};
+
+// Hashed texts are keyed by this many hex digits, plenty to tell license texts apart
+const KEY_DIGITS: usize = 16;
+const SEPARATOR: &str = "================================================================================";
+
+lazy_static! {
+  // `Copyright`, `Copr.`, `(c)` or `©` opening a line, comment markers aside
+  static ref OPENING_REGEX: Regex =
+    Regex::new(r"(?i)^(?:portions\s+)?(?:copyright\b|copr\.|\(c\)|©)").expect("Failed to compile copyright regex");
+  static ref MARKER_REGEX: Regex =
+    Regex::new(r"(?i)\bcopyright\b|\bcopr\.|\(c\)|©").expect("Failed to compile copyright marker regex");
+  // `2015`, `2015-2020`, `2015 - present`, `2015,2017-19`
+  static ref YEARS_REGEX: Regex =
+    Regex::new(concat!(
+      r"(?i)^\d{4}(?:\s*[-–]\s*(?:\d{4}|\d{2}|present))?",
+      r"(?:\s*,\s*\d{4}(?:\s*[-–]\s*(?:\d{4}|\d{2}|present))?)*"
+    ))
+    .expect("Failed to compile copyright years regex");
+  static ref RESERVED_REGEX: Regex =
+    Regex::new(r"(?i)\s*all rights reserved\.?$").expect("Failed to compile rights reserved regex");
+}
+
+// First words after `Copyright` that make it prose about copyright rather than a statement of one
+const PROSE_WORDS: &[&str] = &[
+  "holder",
+  "holders",
+  "owner",
+  "owners",
+  "notice",
+  "notices",
+  "and",
+  "law",
+  "laws",
+  "statement",
+  "license",
+];
+
+// One holder's claim: `Copyright (c) 2015-2020 Jane Doe <jane@example.com>`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Copyright {
+  pub years: Option<String>,
+  pub holder: String,
+}
+
+impl fmt::Display for Copyright {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.years {
+      Some(years) => write!(f, "Copyright (c) {} {}", years, self.holder),
+      None => write!(f, "Copyright (c) {}", self.holder),
+    }
+  }
+}
+
+// The copyright statements of a license text, in the order they come and once each. Lines like
+// `Copyright (c) 2015, 2017-2019 Jane Doe, 2020 Acme Inc.` hold one statement per holder.
+pub fn copyrights(text: &str) -> Vec<Copyright> {
+  let mut found = Vec::new();
+  for copyright in text.lines().flat_map(parse_line) {
+    if !found.contains(&copyright) {
+      found.push(copyright);
+    }
+  }
+  found
+}
+
+// The text with its copyright lines dropped and all whitespace collapsed, what identical licenses have in common
+pub fn normalize_text(text: &str) -> String {
+  text
+    .lines()
+    .filter(|line| !is_copyright_line(line))
+    .flat_map(str::split_whitespace)
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+// Groups license texts that only differ in their copyright lines and whitespace
+pub fn text_key(text: &str) -> String {
+  sha256_hex(normalize_text(text).as_bytes())[..KEY_DIGITS].to_owned()
+}
+
+fn is_copyright_line(line: &str) -> bool {
+  !parse_line(line).is_empty() || RESERVED_REGEX.is_match(line.trim()) && line.split_whitespace().count() <= 4
+}
+
+fn parse_line(line: &str) -> Vec<Copyright> {
+  let line = line
+    .trim()
+    .trim_start_matches(['#', '/', '*', ';', '-', '!', '"', '%'])
+    .trim();
+  let Some(opening) = OPENING_REGEX.find(line) else {
+    return Vec::new();
+  };
+  let statement = MARKER_REGEX.replace_all(line, " ");
+  let statement = statement.split_whitespace().collect::<Vec<_>>().join(" ");
+  let statement = RESERVED_REGEX.replace(&statement, "");
+  let statement = statement.trim_matches(|ch: char| ch.is_whitespace() || matches!(ch, ',' | ';' | ':'));
+
+  let first_word = statement
+    .split(|ch: char| !ch.is_alphanumeric())
+    .next()
+    .unwrap_or_default()
+    .to_lowercase();
+  let bare_c = opening.as_str().eq_ignore_ascii_case("(c)");
+  let prose = PROSE_WORDS.contains(&first_word.as_str())
+    || statement.starts_with(|ch: char| ch.is_lowercase() || matches!(ch, '<' | '[' | '{'));
+  // A lone `(c)` opens list items too (`(c) You must retain ...`), only a year makes it a statement
+  if statement.is_empty() || prose || bare_c && !statement.starts_with(|ch: char| ch.is_ascii_digit()) {
+    return Vec::new();
+  }
+
+  statements(statement)
+    .into_iter()
+    .filter_map(|part| {
+      let years = YEARS_REGEX.find(part).map(|years| tidy_years(years.as_str()));
+      let holder = match &years {
+        Some(_) => &part[YEARS_REGEX.find(part)?.end()..],
+        None => part,
+      };
+      let holder = holder
+        .trim_start_matches(|ch: char| ch.is_whitespace() || matches!(ch, ',' | '.' | ':' | '-'))
+        .trim_start_matches("by ")
+        .trim_end_matches(|ch: char| ch.is_whitespace() || matches!(ch, ',' | ';'));
+      (!holder.is_empty()).then(|| Copyright {
+        years,
+        holder: holder.to_owned(),
+      })
+    })
+    .collect()
+}
+
+// `2015 Jane Doe, 2020 Acme Inc.; Bob` -> each holder with its years
+fn statements(statement: &str) -> Vec<&str> {
+  let mut parts = Vec::new();
+  for part in statement.split(';') {
+    let mut start = 0;
+    for (idx, _) in part.match_indices(", ") {
+      let (before, after) = (&part[start..idx], &part[idx + 2..]);
+      // A comma between years belongs to them, one before a year once a holder was named starts the next claim
+      let named = YEARS_REGEX
+        .find(before.trim())
+        .is_none_or(|years| years.end() < before.trim().len());
+      if named && after.starts_with(|ch: char| ch.is_ascii_digit()) && YEARS_REGEX.is_match(after) {
+        parts.push(part[start..idx].trim());
+        start = idx + 2;
+      }
+    }
+    parts.push(part[start..].trim());
+  }
+  parts.retain(|part| !part.is_empty());
+  parts
+}
+
+fn tidy_years(years: &str) -> String {
+  years
+    .split(',')
+    .map(|range| range.split(['-', '–']).map(str::trim).collect::<Vec<_>>().join("-"))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+struct NoticeText {
+  // As the first package shipped it, minus its copyright lines
+  text: String,
+  licenses: BTreeSet<String>,
+  // `name@version` -> its copyright statements
+  packages: BTreeMap<String, Vec<Copyright>>,
+}
+
+// The NOTICE file: every distinct license text once, with the packages that ship it and their copyright lines
+#[derive(Default)]
+pub struct Notice {
+  texts: BTreeMap<String, NoticeText>,
+  // `name@version` -> license, for packages whose text couldn't be had
+  without_text: BTreeMap<String, Option<String>>,
+}
+
+impl Notice {
+  // Returns the text's key
+  pub fn add(&mut self, package: &str, license: Option<&str>, text: &str) -> String {
+    let key = text_key(text);
+    let entry = self.texts.entry(key.clone()).or_insert_with(|| NoticeText {
+      text: without_copyright_lines(text),
+      licenses: BTreeSet::new(),
+      packages: BTreeMap::new(),
+    });
+    entry.licenses.extend(license.map(str::to_owned));
+    entry.packages.insert(package.to_owned(), copyrights(text));
+    key
+  }
+
+  pub fn add_without_text(&mut self, package: &str, license: Option<&str>) {
+    self.without_text.insert(package.to_owned(), license.map(str::to_owned));
+  }
+
+  // Distinct texts, and the packages they cover
+  pub fn counts(&self) -> (usize, usize) {
+    let packages = self.texts.values().map(|text| text.packages.len()).sum();
+    (self.texts.len(), packages)
+  }
+
+  // The most shared texts first, ties by key so unchanged inputs give the same file
+  pub fn render(&self) -> String {
+    let (texts, packages) = self.counts();
+    let mut out = format!(
+      "THIRD-PARTY NOTICES\n\n{} unique license text(s) cover {} package(s)",
+      texts, packages
+    );
+    if !self.without_text.is_empty() {
+      out.push_str(&format!(
+        ", {} package(s) without a license text",
+        self.without_text.len()
+      ));
+    }
+    out.push('\n');
+
+    let mut groups = self.texts.iter().collect::<Vec<_>>();
+    groups.sort_by(|(a_key, a), (b_key, b)| b.packages.len().cmp(&a.packages.len()).then(a_key.cmp(b_key)));
+    for (key, group) in groups {
+      let licenses = match group.licenses.is_empty() {
+        true => "Unknown license".to_owned(),
+        false => group.licenses.iter().cloned().collect::<Vec<_>>().join(", "),
+      };
+      out.push_str(&format!(
+        "\n{}\n{} (text {}), {} package(s)\n{}\n\n{}\n\nUsed by:\n",
+        SEPARATOR,
+        licenses,
+        key,
+        group.packages.len(),
+        SEPARATOR,
+        group.text
+      ));
+      for (package, copyrights) in &group.packages {
+        out.push_str(&format!("  {}\n", package));
+        for copyright in copyrights {
+          out.push_str(&format!("    {}\n", copyright));
+        }
+      }
+    }
+
+    if !self.without_text.is_empty() {
+      out.push_str(&format!("\n{}\nWithout a license text\n{}\n\n", SEPARATOR, SEPARATOR));
+      for (package, license) in &self.without_text {
+        out.push_str(&format!(
+          "  {} ({})\n",
+          package,
+          license.as_deref().unwrap_or("unknown license")
+        ));
+      }
+    }
+    out
+  }
+
+  // Written next to `path` and renamed over it once complete, like the other outputs
+  pub fn write(&self, path: &str) -> Result<()> {
+    let partial = format!("{}.partial", path);
+    fs::write(&partial, self.render()).with_context(|| format!("Failed to write {}", partial))?;
+    fs::rename(&partial, path).with_context(|| format!("Failed to move the NOTICE file to {}", path))
+  }
+}
+
+fn without_copyright_lines(text: &str) -> String {
+  text
+    .lines()
+    .filter(|line| !is_copyright_line(line))
+    .map(str::trim_end)
+    .collect::<Vec<_>>()
+    .join("\n")
+    .trim_matches('\n')
+    .to_owned()
+}
+
+// Where the raw text behind a license link is: code hosts' blob pages become their raw files
+pub fn text_url(license_url: &str) -> String {
+  if let Some(rest) = license_url.strip_prefix("https://github.com/") {
+    if let Some((repo, path)) = rest.split_once("/blob/") {
+      return format!("https://raw.githubusercontent.com/{}/{}", repo, path);
+    }
+  }
+  match license_url.starts_with("https://gitlab.com/") {
+    true => license_url.replacen("/blob/", "/raw/", 1),
+    false => license_url.to_owned(),
+  }
+}
+
+// A fetched license file, unless it turned out to be a web page
+pub fn license_text(body: &[u8], content_type: Option<&str>) -> Option<String> {
+  let text = String::from_utf8_lossy(body);
+  let html =
+    content_type.is_some_and(|content_type| content_type.contains("html")) || text.trim_start().starts_with('<');
+  (!html && !text.trim().is_empty()).then(|| text.into_owned())
+}
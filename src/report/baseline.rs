@@ -0,0 +1,245 @@
+use super::{history::first_schema_version, schema::REPORT_SCHEMA_VERSION};
+use crate::types::{DepsEntry, Ecosystem, ReportRow};
+use anyhow::{bail, Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use std::{
+  collections::{HashMap, HashSet},
+  fs,
+  path::Path,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+  },
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+// Provenance of a row copied from a baseline, followed by the baseline's date
+const PROVENANCE_PREFIX: &str = "baseline ";
+
+lazy_static! {
+  static ref DATE_REGEX: Regex = Regex::new(r"(\d{4})-(\d{2})-(\d{2})").expect("Failed to compile date regex");
+}
+
+#[derive(Deserialize)]
+struct BaselineLine {
+  #[serde(default = "first_schema_version")]
+  schema_version: u32,
+  #[serde(flatten)]
+  row: ReportRow,
+  // A --collapse-by-repo line carries every package of its group, its own fields being the first one's
+  collapsed_by_repo: Option<BaselineGroup>,
+}
+
+#[derive(Deserialize)]
+struct BaselineGroup {
+  packages: Vec<ReportRow>,
+}
+
+// Rows of an earlier --jsonl report that --trust-baseline copies instead of resolving again: only exact
+// ecosystem, name and version matches with a known license. Counts how many packages it served and how many
+// were left to resolve.
+pub struct Baseline {
+  path: String,
+  date: String,
+  rows: HashMap<(Ecosystem, String, String), ReportRow>,
+  // Rows revalidation found out of date, resolved again like packages the baseline doesn't have
+  invalidated: Mutex<HashSet<(Ecosystem, String, String)>>,
+  served: AtomicUsize,
+  resolved: AtomicUsize,
+}
+
+impl Baseline {
+  // Dated by the YYYY-MM-DD in the file name, like archived reports for `history`, otherwise by when the file was
+  // written. Refused once that's more than `max_age_days` ago; rows a chain of baselines carried over from an
+  // older one are resolved again past the same age.
+  pub fn load(path: &str, max_age_days: Option<u64>) -> Result<Self> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read baseline report: {}", path))?;
+    let day = baseline_day(Path::new(path))?;
+    let today = now() / SECONDS_PER_DAY;
+    let date = format_day(day);
+    let too_old = |day: u64| max_age_days.is_some_and(|max_age| today.saturating_sub(day) > max_age);
+    if too_old(day) {
+      bail!(
+        "Baseline {} is from {}, older than --max-baseline-age {} day(s); resolve without it to write a new one",
+        path,
+        date,
+        max_age_days.unwrap_or_default()
+      );
+    }
+
+    let mut rows = HashMap::new();
+    for (idx, line) in content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+      let BaselineLine {
+        schema_version,
+        row,
+        collapsed_by_repo,
+      } = serde_json::from_str(line)
+        .with_context(|| format!("Line {} of baseline {} is not a report row", idx + 1, path))?;
+      if schema_version > REPORT_SCHEMA_VERSION {
+        bail!(
+          "Line {} of baseline {} has schema version {}, this build reads versions up to {}",
+          idx + 1,
+          path,
+          schema_version,
+          REPORT_SCHEMA_VERSION
+        );
+      }
+      let packages = match collapsed_by_repo {
+        Some(group) => group.packages,
+        None => vec![row],
+      };
+      for mut row in packages {
+        if row.license.is_none() {
+          continue;
+        }
+
+        // A row this baseline copied itself keeps the date it was resolved on
+        match row
+          .provenance
+          .iter()
+          .find_map(|entry| entry.strip_prefix(PROVENANCE_PREFIX))
+        {
+          Some(inherited) if parse_day(inherited).is_some_and(too_old) => continue,
+          Some(_) => {},
+          None => row.provenance.push(format!("{}{}", PROVENANCE_PREFIX, date)),
+        }
+        // Rows of the same package on several sheets are the same resolution
+        rows.insert((row.ecosystem, row.name.clone(), row.version.clone()), row);
+      }
+    }
+
+    Ok(Self {
+      path: path.to_owned(),
+      date,
+      rows,
+      invalidated: Mutex::default(),
+      served: AtomicUsize::new(0),
+      resolved: AtomicUsize::new(0),
+    })
+  }
+
+  // Rows of the previous --watch cycle, dated today: its packages are only resolved again once their version changes
+  pub fn from_rows(label: &str, previous: &[ReportRow]) -> Self {
+    let rows = previous
+      .iter()
+      .filter(|row| row.license.is_some())
+      .map(|row| ((row.ecosystem, row.name.clone(), row.version.clone()), row.clone()))
+      .collect();
+    Self {
+      path: label.to_owned(),
+      date: format_day(now() / SECONDS_PER_DAY),
+      rows,
+      invalidated: Mutex::default(),
+      served: AtomicUsize::new(0),
+      resolved: AtomicUsize::new(0),
+    }
+  }
+
+  // The baseline's row for exactly this version, with the scope this run found; None means it gets resolved
+  pub fn lookup(&self, ecosystem: Ecosystem, dep: &DepsEntry) -> Option<ReportRow> {
+    let key = (ecosystem, dep.name.clone(), dep.version.clone());
+    let invalidated = self
+      .invalidated
+      .lock()
+      .map(|invalidated| invalidated.contains(&key))
+      .unwrap_or_default();
+    let Some(row) = self.rows.get(&key).filter(|_| !invalidated) else {
+      self.resolved.fetch_add(1, Ordering::Relaxed);
+      return None;
+    };
+
+    self.served.fetch_add(1, Ordering::Relaxed);
+    let mut row = row.clone();
+    row.scope = dep.scope;
+    // Checked against this run's tree again
+    row.installed_mismatch = None;
+    row.link_status = None;
+    Some(row)
+  }
+
+  pub fn invalidate(&self, ecosystem: Ecosystem, name: &str, version: &str) {
+    if let Ok(mut invalidated) = self.invalidated.lock() {
+      invalidated.insert((ecosystem, name.to_owned(), version.to_owned()));
+    }
+  }
+
+  // Packages served from the baseline and packages resolved
+  pub fn counts(&self) -> (usize, usize) {
+    (
+      self.served.load(Ordering::Relaxed),
+      self.resolved.load(Ordering::Relaxed),
+    )
+  }
+
+  pub fn describe(&self) -> String {
+    format!("{} ({}, {} known package(s))", self.path, self.date, self.rows.len())
+  }
+}
+
+fn baseline_day(path: &Path) -> Result<u64> {
+  let name = path
+    .file_name()
+    .map(|name| name.to_string_lossy().into_owned())
+    .unwrap_or_default();
+  if let Some(day) = parse_day(&name) {
+    return Ok(day);
+  }
+
+  let modified = fs::metadata(path)
+    .and_then(|metadata| metadata.modified())
+    .with_context(|| format!("Failed to read the modification time of baseline {}", path.display()))?;
+  let seconds = modified
+    .duration_since(UNIX_EPOCH)
+    .map(|elapsed| elapsed.as_secs())
+    .unwrap_or_default();
+  Ok(seconds / SECONDS_PER_DAY)
+}
+
+// Days since 1970-01-01 of the first YYYY-MM-DD in `text`
+fn parse_day(text: &str) -> Option<u64> {
+  let captures = DATE_REGEX.captures(text)?;
+  let (year, month, day) = (
+    captures[1].parse::<i64>().ok()?,
+    captures[2].parse::<i64>().ok()?,
+    captures[3].parse::<i64>().ok()?,
+  );
+  if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+    return None;
+  }
+
+  // Howard Hinnant's days_from_civil, years starting in March so the leap day comes last
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = year.div_euclid(400);
+  let year_of_era = year - era * 400;
+  let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+  let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+  u64::try_from(era * 146_097 + day_of_era - 719_468).ok()
+}
+
+// The inverse of `parse_day`
+fn format_day(days: u64) -> String {
+  let days = days as i64 + 719_468;
+  let era = days.div_euclid(146_097);
+  let day_of_era = days - era * 146_097;
+  let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let shifted_month = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+  let month = if shifted_month < 10 {
+    shifted_month + 3
+  } else {
+    shifted_month - 9
+  };
+  let year = year_of_era + era * 400 + i64::from(month <= 2);
+  format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|elapsed| elapsed.as_secs())
+    .unwrap_or_default()
+}
@@ -0,0 +1,97 @@
+use super::schema::{BundleManifest, BundledFile, RunSetting, BUNDLE_SCHEMA_VERSION};
+use crate::{integrity::sha256_hex, version};
+use anyhow::{bail, Context, Result};
+use std::{
+  collections::BTreeMap,
+  fs::{self, File},
+  io::Write,
+  iter,
+  path::{Component, Path},
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, DateTime, ZipWriter};
+
+pub const BUNDLE_MANIFEST: &str = "MANIFEST.json";
+
+// One zip of the run's outputs for a release, MANIFEST.json first and the rest sorted by name. Every entry gets
+// the same timestamp (1980-01-01, the earliest a zip can hold) and permissions, so the archive only depends on the
+// bytes of the files: unchanged outputs give a byte-identical bundle. Workbooks record when they were written,
+// unlike the JSON and CSV outputs. Written next to `path` and renamed over it once complete, like the workbook.
+pub fn write_bundle(path: &str, files: &[String], run_info: &[(&str, String, &str)]) -> Result<()> {
+  let mut entries = BTreeMap::new();
+  for file in files {
+    let name = entry_name(file);
+    let bytes = fs::read(file).with_context(|| format!("Failed to read {} for the bundle", file))?;
+    if name == BUNDLE_MANIFEST || entries.insert(name.clone(), bytes).is_some() {
+      bail!("Can't bundle {}: the archive already has an entry named {}", file, name);
+    }
+  }
+
+  let manifest = BundleManifest {
+    schema_version: BUNDLE_SCHEMA_VERSION,
+    depsfetch_version: version::RUNNING,
+    files: entries
+      .iter()
+      .map(|(name, bytes)| BundledFile {
+        path: name.clone(),
+        size: bytes.len() as u64,
+        sha256: sha256_hex(bytes),
+      })
+      .collect(),
+    run_info: run_info
+      .iter()
+      .map(|(setting, value, source)| RunSetting { setting, value, source })
+      .collect(),
+  };
+  let manifest = serde_json::to_vec_pretty(&manifest).context("Failed to serialize the bundle manifest")?;
+
+  let partial = format!("{}.partial", path);
+  let written = File::create(&partial)
+    .with_context(|| format!("Failed to create {}", partial))
+    .and_then(|file| write_entries(file, &manifest, &entries))
+    .and_then(|_| fs::rename(&partial, path).with_context(|| format!("Failed to move the bundle to {}", path)));
+  if written.is_err() {
+    let _ = fs::remove_file(&partial);
+  }
+  written.with_context(|| format!("Failed to write bundle: {}", path))
+}
+
+fn write_entries(file: File, manifest: &[u8], entries: &BTreeMap<String, Vec<u8>>) -> Result<()> {
+  let mut zip = ZipWriter::new(file);
+  let options = SimpleFileOptions::default()
+    .compression_method(CompressionMethod::Deflated)
+    .last_modified_time(DateTime::default())
+    .unix_permissions(0o644);
+  let entries = iter::once((BUNDLE_MANIFEST, manifest))
+    .chain(entries.iter().map(|(name, bytes)| (name.as_str(), bytes.as_slice())));
+  for (name, bytes) in entries {
+    zip
+      .start_file(name, options)
+      .with_context(|| format!("Failed to add {} to the bundle", name))?;
+    zip
+      .write_all(bytes)
+      .with_context(|| format!("Failed to write {} into the bundle", name))?;
+  }
+  zip.finish().context("Failed to finish the archive")?;
+
+  Ok(())
+}
+
+// The output's relative path with `/` separators, or only its file name when it's written outside the working
+// directory
+fn entry_name(file: &str) -> String {
+  let path = Path::new(file);
+  let parts = path
+    .components()
+    .filter(|component| *component != Component::CurDir)
+    .map(|component| match component {
+      Component::Normal(part) => Some(part.to_string_lossy()),
+      _ => None,
+    })
+    .collect::<Option<Vec<_>>>();
+  match parts {
+    Some(parts) if !parts.is_empty() => parts.join("/"),
+    _ => path
+      .file_name()
+      .map_or_else(|| file.to_owned(), |name| name.to_string_lossy().into_owned()),
+  }
+}
@@ -0,0 +1,73 @@
+use super::constants::{BY_LICENSE_NONE, BY_LICENSE_OTHER};
+use crate::{
+  license_data::{LicenseCategory, LicenseData},
+  types::ReportRow,
+};
+use std::collections::{BTreeMap, HashSet};
+
+// One license and the packages under it; the collapsed tail mixes licenses, its rows show their own
+pub struct LicenseSection<'a> {
+  pub title: String,
+  pub category: Option<LicenseCategory>,
+  pub rows: Vec<&'a ReportRow>,
+  pub collapsed: bool,
+}
+
+// Every package once however many sheets list it, copyleft first, then unknown, then permissive, each by license
+// name. Licenses with at most `collapse_up_to` packages go to a trailing "other" section, 0 keeps them all apart.
+pub fn group_rows<'a>(rows: &[&'a ReportRow], data: &LicenseData, collapse_up_to: usize) -> Vec<LicenseSection<'a>> {
+  let mut seen = HashSet::new();
+  let mut by_license: BTreeMap<String, (String, LicenseCategory, Vec<&ReportRow>)> = BTreeMap::new();
+  for row in rows.iter().copied() {
+    if !seen.insert((row.ecosystem, &row.name, &row.version)) {
+      continue;
+    }
+    let (key, title) = match row.license.as_deref() {
+      Some(license) if data.is_known(license) => (data.normalize(license), data.normalize(license)),
+      Some(license) => (data.normalize(license), license.trim().to_owned()),
+      None => (String::new(), BY_LICENSE_NONE.to_owned()),
+    };
+    by_license
+      .entry(key)
+      .or_insert_with(|| (title, data.category(row.license.as_deref()), Vec::new()))
+      .2
+      .push(row);
+  }
+
+  let mut sections = Vec::new();
+  let mut other = Vec::new();
+  for (title, category, mut rows) in by_license.into_values() {
+    rows.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    if rows.len() <= collapse_up_to {
+      other.extend(rows);
+      continue;
+    }
+    sections.push(LicenseSection {
+      title,
+      category: Some(category),
+      rows,
+      collapsed: false,
+    });
+  }
+  sections.sort_by(|a, b| (a.category.map(severity), &a.title).cmp(&(b.category.map(severity), &b.title)));
+
+  if !other.is_empty() {
+    other.sort_by(|a, b| (&a.license, &a.name, &a.version).cmp(&(&b.license, &b.name, &b.version)));
+    sections.push(LicenseSection {
+      title: format!("{} {}", BY_LICENSE_OTHER, collapse_up_to),
+      category: None,
+      rows: other,
+      collapsed: true,
+    });
+  }
+  sections
+}
+
+// What legal review looks at first
+fn severity(category: LicenseCategory) -> u8 {
+  match category {
+    LicenseCategory::Copyleft => 0,
+    LicenseCategory::Unknown => 1,
+    LicenseCategory::Permissive => 2,
+  }
+}
@@ -0,0 +1,110 @@
+use super::{constants::WRITE_ERROR_PLACEHOLDER, error::ReportError};
+use xlsxwriter::{Format, Worksheet, XlsxError};
+
+// How much of an offending value makes it into the log
+const LOGGED_VALUE_CHARS: usize = 80;
+
+// Cells and rows the workbook refused; one bad value costs a cell, not the whole report
+#[derive(Debug, Default)]
+pub struct WriteErrors {
+  pub cells: usize,
+  pub rows: usize,
+  row_cells: usize,
+  row_failed: usize,
+}
+
+impl WriteErrors {
+  // A failed cell gets a placeholder so the gap is visible on the sheet
+  pub fn write(&mut self, worksheet: &mut Worksheet<'_>, row: u32, col: u16, value: &str, format: Option<&Format>) {
+    if let Err(err) = worksheet.write_string(row, col, &sanitize(value), format) {
+      self.failed(worksheet, row, col, value, err);
+    }
+    self.row_cells += 1;
+  }
+
+  pub fn write_number(
+    &mut self,
+    worksheet: &mut Worksheet<'_>,
+    row: u32,
+    col: u16,
+    value: f64,
+    format: Option<&Format>,
+  ) {
+    if let Err(err) = worksheet.write_number(row, col, value, format) {
+      self.failed(worksheet, row, col, &value.to_string(), err);
+    }
+    self.row_cells += 1;
+  }
+
+  // `value` is what the formula stands for, it only shows up in the log
+  pub fn write_formula(
+    &mut self,
+    worksheet: &mut Worksheet<'_>,
+    row: u32,
+    col: u16,
+    formula: &str,
+    value: &str,
+    format: Option<&Format>,
+  ) {
+    if let Err(err) = worksheet.write_formula(row, col, formula, format) {
+      self.failed(worksheet, row, col, value, err);
+    }
+    self.row_cells += 1;
+  }
+
+  pub fn write_url(&mut self, worksheet: &mut Worksheet<'_>, row: u32, col: u16, url: &str, format: Option<&Format>) {
+    if let Err(err) = worksheet.write_url(row, col, url, format) {
+      self.failed(worksheet, row, col, url, err);
+    }
+    self.row_cells += 1;
+  }
+
+  fn failed(&mut self, worksheet: &mut Worksheet<'_>, row: u32, col: u16, value: &str, err: XlsxError) {
+    self.cells += 1;
+    self.row_failed += 1;
+    println!(
+      "{}",
+      ReportError::WorksheetError(format!(
+        "row {}, column {}, value {:?}: {}",
+        row + 1,
+        col + 1,
+        truncate(value),
+        err
+      ))
+    );
+    let _ = worksheet.write_string(row, col, WRITE_ERROR_PLACEHOLDER, None);
+  }
+
+  pub fn print_summary(&self) {
+    if self.cells > 0 {
+      println!(
+        "Write errors: {} cell(s) replaced with {}, {} row(s) lost entirely",
+        self.cells, WRITE_ERROR_PLACEHOLDER, self.rows
+      );
+    }
+  }
+
+  // Closes the sheet row written since the last call; it counts as lost when none of its cells made it
+  pub fn end_row(&mut self) {
+    if self.row_cells > 0 && self.row_failed == self.row_cells {
+      self.rows += 1;
+    }
+    self.row_cells = 0;
+    self.row_failed = 0;
+  }
+}
+
+// Control characters (bar tab and line breaks) and the noncharacters are not allowed in XML
+fn sanitize(value: &str) -> String {
+  value
+    .chars()
+    .filter(|ch| (!ch.is_control() || matches!(ch, '\t' | '\n' | '\r')) && !matches!(ch, '\u{fffe}' | '\u{ffff}'))
+    .collect()
+}
+
+fn truncate(value: &str) -> String {
+  match value.char_indices().nth(LOGGED_VALUE_CHARS) {
+    Some((end, _)) => format!("{}...", &value[..end]),
+    None => value.to_owned(),
+  }
+}
@@ -0,0 +1,59 @@
+use super::constants::{
+  ATTESTATION_HEADER, CONFIDENCE_HEADER, ECOSYSTEM_HEADER, FSF_LIBRE_HEADER, FUNDING_HEADER, INSTALLED_MISMATCH_HEADER,
+  LINK_STATUS_HEADER, OSI_APPROVED_HEADER, OTHER_VERSIONS_HEADER, PLATFORM_HEADER, PROVENANCE_HEADER,
+  REPO_MISMATCH_HEADER, REQUIRED_BY_HEADER, REVIEWER_NOTES_HEADER, REVIEW_STATUS_HEADER, SIZE_HEADER,
+  THIRD_PARTY_NOTICES_HEADER, VARIANT_OF_HEADER,
+};
+
+// Optional columns appended after HEADERS, in the order they were enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtraColumn {
+  RequiredBy,
+  Funding,
+  Provenance,
+  Platform,
+  RepoMismatch,
+  Confidence,
+  Attestation,
+  // Versions a --dedup row stands for besides its own
+  OtherVersions,
+  OsiApproved,
+  FsfLibre,
+  InstalledMismatch,
+  ThirdPartyNotices,
+  LinkStatus,
+  // Written as a number, not through `extra_column_value`, so it sorts by size
+  Size,
+  // What `import-review` matches rows by, with the name and version
+  Ecosystem,
+  // A dropdown of the review statuses
+  ReviewStatus,
+  ReviewerNotes,
+  // The primary package of a twin, see --detect-twins
+  VariantOf,
+}
+
+impl ExtraColumn {
+  pub fn header(&self) -> &'static str {
+    match self {
+      ExtraColumn::RequiredBy => REQUIRED_BY_HEADER,
+      ExtraColumn::Funding => FUNDING_HEADER,
+      ExtraColumn::Provenance => PROVENANCE_HEADER,
+      ExtraColumn::Platform => PLATFORM_HEADER,
+      ExtraColumn::RepoMismatch => REPO_MISMATCH_HEADER,
+      ExtraColumn::Confidence => CONFIDENCE_HEADER,
+      ExtraColumn::Attestation => ATTESTATION_HEADER,
+      ExtraColumn::OtherVersions => OTHER_VERSIONS_HEADER,
+      ExtraColumn::OsiApproved => OSI_APPROVED_HEADER,
+      ExtraColumn::FsfLibre => FSF_LIBRE_HEADER,
+      ExtraColumn::InstalledMismatch => INSTALLED_MISMATCH_HEADER,
+      ExtraColumn::ThirdPartyNotices => THIRD_PARTY_NOTICES_HEADER,
+      ExtraColumn::LinkStatus => LINK_STATUS_HEADER,
+      ExtraColumn::Size => SIZE_HEADER,
+      ExtraColumn::Ecosystem => ECOSYSTEM_HEADER,
+      ExtraColumn::ReviewStatus => REVIEW_STATUS_HEADER,
+      ExtraColumn::ReviewerNotes => REVIEWER_NOTES_HEADER,
+      ExtraColumn::VariantOf => VARIANT_OF_HEADER,
+    }
+  }
+}
@@ -1,3 +1,123 @@
 pub const HEADERS: [&str; 5] = ["Имя", "Версия", "Источник", "Тип лицензии", "Ссылка на лицензию"];
 
+pub const REQUIRED_BY_HEADER: &str = "Требуется для";
+
+pub const FUNDING_HEADER: &str = "Финансирование";
+
+pub const PROVENANCE_HEADER: &str = "Происхождение";
+
+pub const PLATFORM_HEADER: &str = "Ограничения платформы";
+
+pub const REPO_MISMATCH_HEADER: &str = "Несовпадение репозитория";
+
+pub const INSTALLED_MISMATCH_HEADER: &str = "Манифест и установленная версия";
+
+pub const THIRD_PARTY_NOTICES_HEADER: &str = "Уведомления о стороннем коде";
+
+pub const LINK_STATUS_HEADER: &str = "Доступность ссылок";
+
+pub const CONFIDENCE_HEADER: &str = "Достоверность лицензии";
+
+pub const ATTESTATION_HEADER: &str = "Аттестация публикации";
+
+pub const OTHER_VERSIONS_HEADER: &str = "Другие версии";
+
+pub const OSI_APPROVED_HEADER: &str = "Одобрена OSI";
+
+pub const FSF_LIBRE_HEADER: &str = "Свободная по FSF";
+
+pub const SIZE_HEADER: &str = "Размер";
+
+pub const ECOSYSTEM_HEADER: &str = "Экосистема";
+
+pub const REVIEW_STATUS_HEADER: &str = "Статус проверки";
+
+pub const REVIEWER_NOTES_HEADER: &str = "Комментарий проверяющего";
+
+pub const VARIANT_OF_HEADER: &str = "Вариант пакета";
+
+pub const MAX_REQUIRED_BY: usize = 5;
+// Hyperlinks Excel keeps per worksheet, it drops the rest without a word
+pub const MAX_SHEET_HYPERLINKS: usize = 65_530;
+// Longest URL a hyperlink can hold, longer ones are written as text
+pub const MAX_HYPERLINK_CHARS: usize = 2_079;
+
+pub const PROJECT_HEADERS: [&str; 5] = ["Проект", "Пакет", "Экосистема", "Версия", "Тип лицензии"];
+
+pub const BY_LICENSE_HEADERS: [&str; 5] = ["Лицензия", "Экосистема", "Пакет", "Версия", "Ссылка на лицензию"];
+
+pub const BY_LICENSE_NONE: &str = "Без лицензии";
+
+pub const BY_LICENSE_OTHER: &str = "Прочие лицензии, пакетов на лицензию не больше";
+
+pub const BY_LICENSE_PACKAGES: &str = "пакетов";
+
+pub const REPO_GROUP_PACKAGES: &str = "пакетов из одного репозитория, свёрнуто --collapse-by-repo";
+
+// Excel's default, rows of a group are only given one to set their outline level
+pub const ROW_HEIGHT: f64 = 15.0;
+
+pub const SELF_AUDIT_HEADERS: [&str; 3] = ["Манифест", "Пакет", "Проблема"];
+
+pub const SUMMARY_TOTAL: &str = "Всего пакетов";
+
+pub const SUMMARY_WITHOUT_LICENSE: &str = "Без лицензии";
+
+pub const SUMMARY_NON_SPDX: &str = "Лицензия не из SPDX";
+
+pub const SUMMARY_REPO_MISMATCH: &str = "Несовпадение репозитория";
+
+pub const SUMMARY_THIRD_PARTY_NOTICES: &str = "С уведомлениями о стороннем коде";
+
+pub const SUMMARY_DEAD_LINKS: &str = "С нерабочими ссылками";
+
+pub const SUMMARY_METADATA_DRIFT: &str = "Расхождение с базовым отчётом (изменилось / перепроверено)";
+
+pub const SUMMARY_COLLAPSED_BY_REPO: &str = "Свёрнуто по репозиторию (пакетов / групп), в итогах учтены все";
+
+pub const SUMMARY_FILTERED: &str = "Скрыто фильтром лицензий";
+
+pub const SUMMARY_DISTINCT: &str = "Уникальных пакетов (включая транзитивные)";
+
+pub const SUMMARY_BREAKDOWNS: [&str; 3] = ["Категория лицензии", "Тип зависимости", "Экосистема"];
+
+pub const SUMMARY_BREAKDOWN_HEADERS: [&str; 2] = ["Количество", "Доля"];
+
+pub const SUMMARY_SIZES: &str = "Размер по экосистемам";
+
+pub const SUMMARY_LARGEST: &str = "Самые большие пакеты";
+
+pub const SUMMARY_ATTESTATION: &str = "Аттестация публикации";
+
+pub const SUMMARY_CELL_ERRORS: &str = "Ячеек с ошибкой записи";
+
+pub const SUMMARY_ROW_ERRORS: &str = "Строк с ошибкой записи";
+
+pub const WRITE_ERROR_PLACEHOLDER: &str = "<write error>";
+
+pub const HISTORY_HEADERS: [&str; 4] = ["Отчёт", "Всего пакетов", "Неизвестная лицензия", "Копилефт"];
+
+pub const EXCLUSION_HEADERS: [&str; 6] = ["Опция", "Шаблон", "Причина", "Добавил", "Дата", "Отфильтровано"];
+
+pub const MANIFEST_HEADERS: [&str; 4] = ["Тип манифеста", "Включён", "Найдено", "Исключено правилами типа"];
+
+pub const MANIFEST_ENABLED: &str = "да";
+
+pub const MANIFEST_DISABLED: &str = "нет";
+
+pub const LINKS_HEADERS: [&str; 2] = ["Ссылка", "Ячеек"];
+
+pub const RUN_INFO_HEADERS: [&str; 3] = ["Параметр", "Значение", "Источник"];
+
 pub const LICENSE_FILES: &[&str] = &["LICENSE.txt", "LICENSE", "LICENSE.md", "license"];
+
+// Where packages bundling other projects' code credit it, probed like LICENSE_FILES
+pub const NOTICE_FILES: &[&str] = &[
+  "ThirdPartyNotices.txt",
+  "ThirdPartyNotices.md",
+  "THIRD_PARTY_NOTICES.md",
+  "THIRD-PARTY-NOTICES.txt",
+  "NOTICE",
+  "NOTICE.txt",
+  "NOTICE.md",
+];
@@ -0,0 +1,142 @@
+use crate::types::{Ecosystem, ReportRow};
+use anyhow::{bail, Result};
+use std::{cmp::Ordering, collections::HashMap};
+
+// How the sheets show a package required in several versions. The streams always get every row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupStrategy {
+  All,
+  Highest,
+  Lowest,
+}
+
+impl DedupStrategy {
+  pub const NAMES: &'static [&'static str] = &["all", "highest", "lowest"];
+
+  pub fn parse(value: &str) -> Result<Self> {
+    match value {
+      "all" => Ok(DedupStrategy::All),
+      "highest" => Ok(DedupStrategy::Highest),
+      "lowest" => Ok(DedupStrategy::Lowest),
+      _ => bail!(
+        "Unknown dedup strategy `{}`, expected one of: {}",
+        value,
+        Self::NAMES.join(", ")
+      ),
+    }
+  }
+}
+
+// Keeps one row per (ecosystem, name), returning the versions it stands for besides its own
+pub fn collapse(rows: &mut Vec<ReportRow>, strategy: DedupStrategy) -> HashMap<(Ecosystem, String), Vec<String>> {
+  let mut others = HashMap::new();
+  if strategy == DedupStrategy::All {
+    return others;
+  }
+
+  let mut kept: Vec<ReportRow> = Vec::with_capacity(rows.len());
+  let mut index = HashMap::new();
+  for row in rows.drain(..) {
+    let key = (row.ecosystem, row.name.clone());
+    let Some(&idx) = index.get(&key) else {
+      index.insert(key, kept.len());
+      kept.push(row);
+      continue;
+    };
+
+    let wins = matches!(
+      (strategy, compare_versions(&row.version, &kept[idx].version)),
+      (DedupStrategy::Highest, Ordering::Greater) | (DedupStrategy::Lowest, Ordering::Less)
+    );
+    let loser = if wins {
+      std::mem::replace(&mut kept[idx], row).version
+    } else {
+      row.version
+    };
+    if loser != kept[idx].version {
+      others.entry(key).or_insert_with(Vec::new).push(loser);
+    }
+  }
+
+  for versions in others.values_mut() {
+    versions.sort_by(|a, b| compare_versions(a, b));
+    versions.dedup();
+  }
+  *rows = kept;
+  others
+}
+
+// Semver precedence with an optional `v` prefix, build metadata ignored. Go pseudo-versions are semver
+// pre-releases and order by their timestamp. Anything that isn't semver sorts below every semver version, by plain
+// string comparison among its kind. Equal precedence falls back to the string so the order is total.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+  let order = match (parse(a), parse(b)) {
+    (Some(a), Some(b)) => a
+      .core
+      .cmp(&b.core)
+      .then_with(|| compare_pre_release(&a.pre_release, &b.pre_release)),
+    (Some(_), None) => Ordering::Greater,
+    (None, Some(_)) => Ordering::Less,
+    (None, None) => Ordering::Equal,
+  };
+  order.then_with(|| a.cmp(b))
+}
+
+struct Version<'a> {
+  core: [u64; 3],
+  pre_release: Vec<&'a str>,
+}
+
+fn parse(version: &str) -> Option<Version<'_>> {
+  let version = version.trim();
+  let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+  let version = version.split_once('+').map_or(version, |(version, _)| version);
+  let (core, pre_release) = match version.split_once('-') {
+    Some((core, pre_release)) => (core, pre_release.split('.').collect::<Vec<_>>()),
+    None => (version, Vec::new()),
+  };
+  if pre_release.iter().any(|identifier| identifier.is_empty()) {
+    return None;
+  }
+
+  let mut numbers = core.split('.');
+  let mut parsed = [0; 3];
+  for slot in parsed.iter_mut() {
+    let number = numbers.next()?;
+    if number.is_empty() || !number.bytes().all(|byte| byte.is_ascii_digit()) {
+      return None;
+    }
+    *slot = number.parse().ok()?;
+  }
+  if numbers.next().is_some() {
+    return None;
+  }
+
+  Some(Version {
+    core: parsed,
+    pre_release,
+  })
+}
+
+// A release is above its pre-releases; numeric identifiers compare as numbers and below alphanumeric ones
+fn compare_pre_release(a: &[&str], b: &[&str]) -> Ordering {
+  match (a.is_empty(), b.is_empty()) {
+    (true, true) => return Ordering::Equal,
+    (true, false) => return Ordering::Greater,
+    (false, true) => return Ordering::Less,
+    (false, false) => {},
+  }
+
+  for (a, b) in a.iter().zip(b) {
+    let order = match (a.parse::<u64>(), b.parse::<u64>()) {
+      (Ok(a), Ok(b)) => a.cmp(&b),
+      (Ok(_), Err(_)) => Ordering::Less,
+      (Err(_), Ok(_)) => Ordering::Greater,
+      (Err(_), Err(_)) => a.cmp(b),
+    };
+    if order != Ordering::Equal {
+      return order;
+    }
+  }
+  a.len().cmp(&b.len())
+}
@@ -8,6 +8,10 @@ pub enum ReportError {
   #[error("Failed to fetch package information: {0}")]
   PackageFetchError(String),
 
+  #[cfg(feature = "xlsx")]
   #[error("Worksheet operation failed: {0}")]
   WorksheetError(String),
+
+  #[error("Pinned version {1} of {0} was unpublished")]
+  UnpublishedVersion(String, String),
 }
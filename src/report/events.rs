@@ -0,0 +1,35 @@
+use crate::types::Ecosystem;
+
+// What the resolution reports about each package as it goes, for a front-end such as --tui to render. The rows
+// themselves still only travel to the writer, and nothing on the resolution path prints on its own.
+#[derive(Debug, Clone)]
+pub enum ResolutionEvent {
+  Queued {
+    ecosystem: Ecosystem,
+    name: String,
+    version: String,
+  },
+  Fetching {
+    ecosystem: Ecosystem,
+    name: String,
+  },
+  Resolved {
+    ecosystem: Ecosystem,
+    name: String,
+    license: Option<String>,
+  },
+  // Not installable on the --target-platform
+  Excluded {
+    ecosystem: Ecosystem,
+    name: String,
+  },
+  // A final retry may still resolve it
+  Failed {
+    ecosystem: Ecosystem,
+    name: String,
+  },
+  // About the run rather than one package: platform exclusions, the final retry, the link check
+  Note {
+    message: String,
+  },
+}
@@ -0,0 +1,121 @@
+use super::{
+  stream::{StreamFormat, StreamWriter},
+  writer::ReportWriter,
+};
+use anyhow::{anyhow, bail, Result};
+
+// A `--format` choice: the file it writes and how the writer is set up for it. Each is behind the cargo feature
+// of the same name, builds for musl or ARM runners can leave out xlsx and its C library.
+pub struct ReportFormat {
+  pub name: &'static str,
+  pub file: &'static str,
+  // Whether the file is the combined workbook rather than one of the writer's streams
+  pub workbook: bool,
+  pub available: bool,
+  build: fn(ReportWriter, &'static str, bool) -> Result<ReportWriter>,
+}
+
+// In order of preference for the default
+pub const REPORT_FORMATS: &[ReportFormat] = &[
+  ReportFormat {
+    name: "xlsx",
+    file: "deps_report.xlsx",
+    workbook: true,
+    available: cfg!(feature = "xlsx"),
+    build: build_workbook,
+  },
+  ReportFormat {
+    name: "json",
+    file: "deps_report.jsonl",
+    workbook: false,
+    available: cfg!(feature = "json"),
+    build: build_json,
+  },
+  ReportFormat {
+    name: "csv",
+    file: "deps_report.csv",
+    workbook: false,
+    available: cfg!(feature = "csv"),
+    build: build_csv,
+  },
+];
+
+impl ReportFormat {
+  // The requested format, or the best one this build has
+  pub fn select(name: Option<&str>) -> Result<&'static ReportFormat> {
+    let Some(name) = name else {
+      return REPORT_FORMATS.iter().find(|format| format.available).ok_or_else(|| {
+        anyhow!(
+          "This build has no report format, rebuild with one of the features: {}",
+          names(false)
+        )
+      });
+    };
+
+    let format = REPORT_FORMATS
+      .iter()
+      .find(|format| format.name == name)
+      .ok_or_else(|| {
+        anyhow!(
+          "Unsupported report format `{}`, expected one of: {}",
+          name,
+          names(false)
+        )
+      })?;
+    if !format.available {
+      bail!(
+        "This build has no {} support (built without the `{}` feature), pass one of these to --format instead: {}",
+        name,
+        name,
+        names(true)
+      );
+    }
+    Ok(format)
+  }
+
+  // For options writing a side output in one of the formats, like --csv
+  pub fn require(name: &str, option: &str) -> Result<()> {
+    match REPORT_FORMATS.iter().find(|format| format.name == name) {
+      Some(format) if !format.available => bail!(
+        "{} needs {} support, this build was made without the `{}` feature",
+        option,
+        name,
+        name
+      ),
+      _ => Ok(()),
+    }
+  }
+
+  pub fn build(&self, writer: ReportWriter, sort: bool) -> Result<ReportWriter> {
+    (self.build)(writer, self.file, sort)
+  }
+}
+
+fn names(available_only: bool) -> String {
+  REPORT_FORMATS
+    .iter()
+    .filter(|format| format.available || !available_only)
+    .map(|format| format.name)
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+fn build_workbook(writer: ReportWriter, _: &'static str, _: bool) -> Result<ReportWriter> {
+  Ok(writer)
+}
+
+fn build_json(writer: ReportWriter, file: &'static str, sort: bool) -> Result<ReportWriter> {
+  Ok(
+    writer
+      .without_workbook()
+      .with_stream(StreamWriter::create(file, StreamFormat::JsonLines, sort)?),
+  )
+}
+
+fn build_csv(writer: ReportWriter, file: &'static str, sort: bool) -> Result<ReportWriter> {
+  Ok(
+    writer
+      .without_workbook()
+      .with_stream(StreamWriter::create(file, StreamFormat::Csv, sort)?),
+  )
+}
@@ -18,10 +18,41 @@ impl WorkbookFormatter {
 
     formats.insert("url".to_owned(), url_format);
 
+    let mut bold_format = Format::new();
+    bold_format.set_bold();
+    formats.insert("bold".to_owned(), bold_format);
+
+    let mut percent_format = Format::new();
+    percent_format.set_num_format("0.0%");
+    formats.insert("percent".to_owned(), percent_format);
+
+    // Byte counts stay numbers for sorting, shown in decimal units like `human_size`
+    let mut size_format = Format::new();
+    size_format.set_num_format(r#"[<1000]0" B";[<1000000]0.0," kB";0.0,," MB""#);
+    formats.insert("size".to_owned(), size_format);
+
     Self { formats }
   }
 
+  // URL cells are written plain, one style less per workbook
+  pub fn without_url_format(mut self) -> Self {
+    self.formats.remove("url");
+    self
+  }
+
   pub fn url_format(&self) -> Option<&Format> {
     self.formats.get("url")
   }
+
+  pub fn bold_format(&self) -> Option<&Format> {
+    self.formats.get("bold")
+  }
+
+  pub fn percent_format(&self) -> Option<&Format> {
+    self.formats.get("percent")
+  }
+
+  pub fn size_format(&self) -> Option<&Format> {
+    self.formats.get("size")
+  }
 }
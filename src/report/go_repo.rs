@@ -0,0 +1,101 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+  static ref GO_IMPORT_REGEX: Regex =
+    Regex::new(r#"<meta\s+name="go-import"\s+content="([^"]*)""#).expect("Failed to compile go-import regex");
+  // `v0.0.0-20200101120000-abcdef123456` and the `vX.Y.Z-pre.0.<time>-<hash>` forms
+  static ref PSEUDO_VERSION_REGEX: Regex =
+    Regex::new(r"[.-]\d{14}-([0-9a-f]{12})$").expect("Failed to compile pseudo-version regex");
+}
+
+// Hosts whose `<repo>/blob/<ref>/<path>` URLs the license probe can follow
+const BLOB_HOSTS: &[&str] = &["https://github.com/", "https://gitlab.com/"];
+
+// Where a module's files live: the repository and the module's directory in it, empty for the root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleRepo {
+  pub url: String,
+  pub subpath: String,
+}
+
+// `github.com/org/mono/modules/api/v2` -> https://github.com/org/mono and "modules/api", without a lookup
+pub fn github_repo(module: &str) -> Option<ModuleRepo> {
+  let segments = module.split('/').collect::<Vec<_>>();
+  if segments.first() != Some(&"github.com") || segments.len() < 3 {
+    return None;
+  }
+
+  Some(ModuleRepo {
+    url: format!("https://{}", segments[..3].join("/")),
+    subpath: module_directory(&segments[3..]),
+  })
+}
+
+// The repository a vanity import path (`go.uber.org/zap`) names in its `?go-get=1` page, when it's a git repository
+// on a host with blob URLs
+pub fn go_import(page: &str, module: &str) -> Option<ModuleRepo> {
+  GO_IMPORT_REGEX.captures_iter(page).find_map(|captures| {
+    let mut fields = captures[1].split_whitespace();
+    let (prefix, vcs, repo) = (fields.next()?, fields.next()?, fields.next()?);
+    let rest = module.strip_prefix(prefix)?;
+    if vcs != "git" || !(rest.is_empty() || rest.starts_with('/')) {
+      return None;
+    }
+    let url = repo.trim_end_matches('/').trim_end_matches(".git");
+    if !BLOB_HOSTS.iter().any(|host| url.starts_with(host)) {
+      return None;
+    }
+
+    let segments = rest
+      .split('/')
+      .filter(|segment| !segment.is_empty())
+      .collect::<Vec<_>>();
+    Some(ModuleRepo {
+      url: url.to_owned(),
+      subpath: module_directory(&segments),
+    })
+  })
+}
+
+// The git ref a module version was published from: the commit of a pseudo-version, otherwise the tag, prefixed
+// with the module's directory for a nested module. None for anything that isn't a version.
+pub fn version_ref(version: &str, subpath: &str) -> Option<String> {
+  if !version.starts_with('v') {
+    return None;
+  }
+  if let Some(captures) = PSEUDO_VERSION_REGEX.captures(version) {
+    return Some(captures[1].to_owned());
+  }
+
+  let tag = version.trim_end_matches("+incompatible");
+  Some(match subpath {
+    "" => tag.to_owned(),
+    subpath => format!("{}/{}", subpath, tag),
+  })
+}
+
+// A major version suffix names a branch line, not a directory
+fn module_directory(segments: &[&str]) -> String {
+  let segments = match segments.split_last() {
+    Some((last, rest)) if last.strip_prefix('v').is_some_and(|major| major.parse::<u32>().is_ok()) => rest,
+    _ => segments,
+  };
+  segments.join("/")
+}
+
+// How the module proxy spells a module path or version: it serves case-insensitive file systems, so every
+// uppercase letter becomes `!` and its lowercase (`github.com/Azure/go-autorest` -> `github.com/!azure/go-autorest`)
+pub fn proxy_escape(path: &str) -> String {
+  let mut escaped = String::with_capacity(path.len());
+  for ch in path.chars() {
+    match ch.is_ascii_uppercase() {
+      true => {
+        escaped.push('!');
+        escaped.push(ch.to_ascii_lowercase());
+      },
+      false => escaped.push(ch),
+    }
+  }
+  escaped
+}
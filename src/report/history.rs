@@ -0,0 +1,202 @@
+#[cfg(feature = "xlsx")]
+use super::{constants::HISTORY_HEADERS, formatter::WorkbookFormatter};
+use super::{schema::REPORT_SCHEMA_VERSION, stream::csv_field};
+use crate::license_data::LicenseData;
+use anyhow::{bail, Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use std::{fs, path::Path};
+#[cfg(feature = "xlsx")]
+use xlsxwriter::{chart::ChartType, Workbook};
+
+#[cfg(feature = "xlsx")]
+const HISTORY_SHEET: &str = "Trend";
+// Rows a chart takes up, so the next one starts below it
+#[cfg(feature = "xlsx")]
+const CHART_HEIGHT: u32 = 16;
+
+lazy_static! {
+  static ref DATE_REGEX: Regex = Regex::new(r"\d{4}-\d{2}-\d{2}").expect("Failed to compile date regex");
+}
+
+// The part of an archived JSON lines row the trends need
+#[derive(Deserialize)]
+struct HistoryRow {
+  // Rows written before the field existed use the first layout
+  #[serde(default = "first_schema_version")]
+  schema_version: u32,
+  license: Option<String>,
+  // A --collapse-by-repo line stands for this many packages, all with its license
+  collapsed_by_repo: Option<HistoryGroup>,
+}
+
+#[derive(Deserialize)]
+struct HistoryGroup {
+  package_count: usize,
+}
+
+struct ReportMetrics {
+  label: String,
+  total: usize,
+  unknown_license: usize,
+  copyleft: usize,
+}
+
+// Trend summary over a directory of archived `--jsonl` reports, ordered by the date in their file names
+pub fn generate_history(dir: &str, csv_path: &str, xlsx_path: &str, data: &LicenseData) -> Result<()> {
+  let mut paths = fs::read_dir(dir)
+    .with_context(|| format!("Failed to read report directory: {}", dir))?
+    .filter_map(Result::ok)
+    .map(|entry| entry.path())
+    .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+    .collect::<Vec<_>>();
+  paths.sort_by_key(|path| report_label(path));
+
+  let mut history = Vec::new();
+  for path in &paths {
+    match load_metrics(path, data) {
+      Ok(metrics) => history.push(metrics),
+      Err(err) => println!("Skipping report {}: {:#}", path.display(), err),
+    }
+  }
+  if history.is_empty() {
+    println!("No usable reports in {}", dir);
+  }
+
+  write_csv(csv_path, &history)?;
+  let mut written = vec![csv_path];
+  if write_workbook(xlsx_path, &history)? {
+    written.push(xlsx_path);
+  }
+
+  for metrics in &history {
+    println!(
+      "{}: {} package(s), {} with an unknown license, {} copyleft",
+      metrics.label, metrics.total, metrics.unknown_license, metrics.copyleft
+    );
+  }
+  println!(
+    "Trend for {} report(s) written to {}",
+    history.len(),
+    written.join(" and ")
+  );
+  Ok(())
+}
+
+// Classified with today's license data, so reports written before a category existed still count the same way
+fn load_metrics(path: &Path, data: &LicenseData) -> Result<ReportMetrics> {
+  let content = fs::read_to_string(path).context("Failed to read report")?;
+  let mut metrics = ReportMetrics {
+    label: report_label(path),
+    total: 0,
+    unknown_license: 0,
+    copyleft: 0,
+  };
+
+  for (idx, line) in content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+    let row: HistoryRow =
+      serde_json::from_str(line).with_context(|| format!("Line {} is not a report row", idx + 1))?;
+    // Every version so far has `license` as it's read here; a newer report may have moved it
+    if row.schema_version > REPORT_SCHEMA_VERSION {
+      bail!(
+        "line {} has schema version {}, this build reads versions up to {}",
+        idx + 1,
+        row.schema_version,
+        REPORT_SCHEMA_VERSION
+      );
+    }
+
+    let packages = row.collapsed_by_repo.map_or(1, |group| group.package_count);
+    metrics.total += packages;
+    let ids = row
+      .license
+      .as_deref()
+      .map(|license| data.expression_ids(license))
+      .unwrap_or_default();
+    if ids.is_empty() || !ids.iter().all(|id| data.is_known(id)) {
+      metrics.unknown_license += packages;
+    }
+    if ids.iter().any(|id| data.is_copyleft(id)) {
+      metrics.copyleft += packages;
+    }
+  }
+
+  Ok(metrics)
+}
+
+fn write_csv(path: &str, history: &[ReportMetrics]) -> Result<()> {
+  let mut out = String::from("report,total,unknown_license,copyleft\n");
+  for metrics in history {
+    out.push_str(&format!(
+      "{},{},{},{}\n",
+      csv_field(&metrics.label),
+      metrics.total,
+      metrics.unknown_license,
+      metrics.copyleft
+    ));
+  }
+
+  fs::write(path, out).with_context(|| format!("Failed to write {}", path))
+}
+
+#[cfg(feature = "xlsx")]
+fn write_workbook(path: &str, history: &[ReportMetrics]) -> Result<bool> {
+  let workbook = Workbook::new(path).context("Failed to create workbook")?;
+  let formatter = WorkbookFormatter::new();
+  let mut worksheet = workbook
+    .add_worksheet(Some(HISTORY_SHEET))
+    .context("Failed to create worksheet")?;
+
+  for (col, header) in HISTORY_HEADERS.iter().enumerate() {
+    worksheet
+      .write_string(0, col as u16, header, formatter.bold_format())
+      .context("Failed to write header")?;
+  }
+  for (idx, metrics) in history.iter().enumerate() {
+    let row = (idx + 1) as u32;
+    worksheet.write_string(row, 0, &metrics.label, None)?;
+    worksheet.write_number(row, 1, metrics.total as f64, None)?;
+    worksheet.write_number(row, 2, metrics.unknown_license as f64, None)?;
+    worksheet.write_number(row, 3, metrics.copyleft as f64, None)?;
+  }
+
+  // One line chart per metric, stacked to the right of the table
+  if !history.is_empty() {
+    let last_row = history.len() + 1;
+    let categories = format!("={}!$A$2:$A${}", HISTORY_SHEET, last_row);
+    for (idx, (column, header)) in ["B", "C", "D"].iter().zip(&HISTORY_HEADERS[1..]).enumerate() {
+      let values = format!("={}!${}$2:${}${}", HISTORY_SHEET, column, column, last_row);
+      let mut chart = workbook.add_chart(ChartType::Line);
+      chart.add_series(Some(&categories), Some(&values))?.set_name(header)?;
+      chart.add_title(header)?;
+      worksheet.insert_chart(idx as u32 * CHART_HEIGHT, HISTORY_HEADERS.len() as u16 + 1, &chart)?;
+    }
+  }
+
+  workbook.close().context("Failed to save workbook")?;
+  Ok(true)
+}
+
+// The CSV has the same numbers, only the charts are missing
+#[cfg(not(feature = "xlsx"))]
+fn write_workbook(path: &str, _: &[ReportMetrics]) -> Result<bool> {
+  println!("Not writing {}: this build has no xlsx support", path);
+  Ok(false)
+}
+
+// The date in the file name (`deps-2024-05-01.jsonl`), or the file name itself
+fn report_label(path: &Path) -> String {
+  let name = path
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().into_owned())
+    .unwrap_or_default();
+  match DATE_REGEX.find(&name) {
+    Some(date) => date.as_str().to_owned(),
+    None => name,
+  }
+}
+
+pub(super) fn first_schema_version() -> u32 {
+  1
+}
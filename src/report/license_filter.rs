@@ -0,0 +1,28 @@
+use crate::license_data::LicenseData;
+use std::collections::BTreeSet;
+
+// Hides rows with uninteresting licenses from the human readable sheets
+pub struct LicenseFilter {
+  licenses: BTreeSet<String>,
+}
+
+impl LicenseFilter {
+  pub fn new(licenses: &[String], data: &LicenseData) -> Self {
+    Self {
+      licenses: licenses.iter().map(|license| data.normalize(license)).collect(),
+    }
+  }
+
+  // An expression only matches when every one of its branches is in the list
+  pub fn matches(&self, data: &LicenseData, license: &str) -> bool {
+    let branches = data.expression_ids(license);
+    !branches.is_empty() && branches.iter().all(|branch| self.licenses.contains(branch))
+  }
+
+  pub fn rule(&self) -> String {
+    format!(
+      "--exclude-licenses {}",
+      self.licenses.iter().cloned().collect::<Vec<_>>().join(",")
+    )
+  }
+}
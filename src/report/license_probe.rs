@@ -0,0 +1,86 @@
+use crate::http::HttpResponse;
+
+// Enough of a GitHub blob page to get past its <head>
+pub const PROBE_PREFIX: usize = 32 * 1024;
+
+// Where a license link may still point after redirects
+const CODE_HOSTS: &[&str] = &[
+  "github.com",
+  "raw.githubusercontent.com",
+  "gitlab.com",
+  "bitbucket.org",
+  "codeberg.org",
+];
+// Lower-cased; GitHub serves these on a 200 as well as on its 451
+const UNAVAILABLE_SIGNATURES: &[&str] = &[
+  "repository unavailable due to dmca takedown",
+  "access to this repository has been disabled",
+  "this repository has been disabled",
+  "unavailable for legal reasons",
+];
+const PARKING_SIGNATURES: &[&str] = &[
+  "this domain is for sale",
+  "this domain may be for sale",
+  "buy this domain",
+  "domain parking",
+  "parked free",
+  "sedoparking",
+  "parkingcrew",
+];
+const LICENSE_MARKERS: &[&str] = &[
+  "license",
+  "licence",
+  "copyright",
+  "permission is hereby granted",
+  "licensed under",
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProbeOutcome {
+  Found,
+  Missing,
+  // The repository is taken down; every other candidate would say the same
+  Unavailable(String),
+  // Redirected off the code host; the rest of the candidates would end up there too
+  OffHost(String),
+  // A page that answered 200 but isn't a license file
+  Rejected(String),
+}
+
+// A 200 only counts when the page is still on a code host, is text and reads like a license
+pub fn classify(response: &HttpResponse) -> ProbeOutcome {
+  let prefix = String::from_utf8_lossy(&response.body).to_lowercase();
+  if response.status == reqwest::StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS {
+    return ProbeOutcome::Unavailable("HTTP 451".to_owned());
+  }
+  if let Some(signature) = UNAVAILABLE_SIGNATURES
+    .iter()
+    .find(|signature| prefix.contains(**signature))
+  {
+    return ProbeOutcome::Unavailable(format!("page says \"{}\"", signature));
+  }
+  if response.status != reqwest::StatusCode::OK {
+    return ProbeOutcome::Missing;
+  }
+
+  let host = reqwest::Url::parse(&response.url)
+    .ok()
+    .and_then(|url| url.host_str().map(str::to_owned))
+    .unwrap_or_default();
+  if !CODE_HOSTS.contains(&host.as_str()) {
+    return ProbeOutcome::OffHost(format!("redirected to {}", host));
+  }
+  match &response.content_type {
+    Some(content_type) if content_type.starts_with("text/") => {},
+    Some(content_type) => return ProbeOutcome::Rejected(format!("content type {}", content_type)),
+    None => return ProbeOutcome::Rejected("no content type".to_owned()),
+  }
+  if let Some(signature) = PARKING_SIGNATURES.iter().find(|signature| prefix.contains(**signature)) {
+    return ProbeOutcome::Rejected(format!("parked page (\"{}\")", signature));
+  }
+  if !LICENSE_MARKERS.iter().any(|marker| prefix.contains(marker)) {
+    return ProbeOutcome::Rejected("no license text".to_owned());
+  }
+
+  ProbeOutcome::Found
+}
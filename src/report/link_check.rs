@@ -0,0 +1,63 @@
+use super::license_probe::PROBE_PREFIX;
+use crate::{http::MetadataBackend, types::ReportRow};
+use reqwest::StatusCode;
+
+// Provenance the license probe leaves on rows whose license link it built from the repository
+const CONSTRUCTED_PREFIX: &str = "license file: ";
+const CONSTRUCTED_ROOT: &str = "repository root";
+const CONSTRUCTED_DIRECTORIES: &[&str] = &["package directory ", "module directory "];
+
+// The row's links that end up in the report; local paths and free-form funding text aren't links
+pub fn row_links(row: &ReportRow) -> Vec<String> {
+  [
+    Some(row.homepage.as_str()),
+    row.license_url.as_deref(),
+    row.funding.as_deref(),
+    row.third_party_notices.as_deref(),
+  ]
+  .into_iter()
+  .flatten()
+  .filter(|link| link.starts_with("https://") || link.starts_with("http://"))
+  .map(str::to_owned)
+  .collect()
+}
+
+// The repository and package directory the license probe built the row's license link from, None when the link
+// was declared by the package or taken from somewhere else
+pub fn constructed_license(row: &ReportRow) -> Option<(String, Option<String>)> {
+  let (repo, _) = row.license_url.as_deref()?.split_once("/blob/")?;
+  let directory = row
+    .provenance
+    .iter()
+    .filter_map(|entry| entry.strip_prefix(CONSTRUCTED_PREFIX))
+    .find_map(|level| match level == CONSTRUCTED_ROOT {
+      true => Some(None),
+      false => CONSTRUCTED_DIRECTORIES
+        .iter()
+        .find_map(|prefix| level.strip_prefix(prefix))
+        .map(|directory| Some(directory.to_owned())),
+    })?;
+  Some((repo.to_owned(), directory))
+}
+
+// Why the link is dead, None when it answers with a success once redirects are followed. Servers that refuse HEAD
+// are asked for the start of the page instead.
+pub async fn check(client: &dyn MetadataBackend, link: &str) -> Option<String> {
+  let response = match client.head(link).await {
+    Ok(response)
+      if matches!(
+        response.status,
+        StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED | StatusCode::FORBIDDEN
+      ) =>
+    {
+      client.get_prefix(link, PROBE_PREFIX).await
+    },
+    response => response,
+  };
+
+  match response {
+    Ok(response) if response.status.is_success() => None,
+    Ok(response) => Some(response.status.to_string()),
+    Err(err) => Some(err.root_cause().to_string()),
+  }
+}
@@ -0,0 +1,65 @@
+use crate::types::ReportRow;
+use std::collections::HashMap;
+
+pub const LINKS_SHEET: &str = "Links";
+// Shorter URLs take less room inline than a reference to the Links sheet
+const MIN_LINK_CHARS: usize = 24;
+
+// Homepage and license URLs that several rows share (monorepo packages mostly), written once on the Links sheet
+// with each cell a formula pointing there. Only pays off in constant memory mode: otherwise the shared string
+// table already stores every distinct string once.
+#[derive(Default)]
+pub struct LinkTable {
+  rows: HashMap<String, u32>,
+  // In order of first use, with how many cells point at each
+  links: Vec<(String, usize)>,
+}
+
+impl LinkTable {
+  pub fn collect<'a>(rows: impl IntoIterator<Item = &'a ReportRow>) -> Self {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut order = Vec::new();
+    for row in rows {
+      for url in [Some(row.homepage.as_str()), row.license_url.as_deref()]
+        .into_iter()
+        .flatten()
+      {
+        if url.chars().count() < MIN_LINK_CHARS {
+          continue;
+        }
+        let count = counts.entry(url).or_default();
+        if *count == 0 {
+          order.push(url);
+        }
+        *count += 1;
+      }
+    }
+
+    let links = order
+      .into_iter()
+      .filter(|url| counts[url] > 1)
+      .map(|url| (url.to_owned(), counts[url]))
+      .collect::<Vec<_>>();
+    // Row 0 of the sheet holds the headers
+    let rows = links
+      .iter()
+      .enumerate()
+      .map(|(idx, (url, _))| (url.clone(), (idx + 1) as u32))
+      .collect();
+
+    Self { rows, links }
+  }
+
+  // The formula standing in for `url`, None when the URL is written as is
+  pub fn formula(&self, url: &str) -> Option<String> {
+    self.rows.get(url).map(|row| format!("={}!A{}", LINKS_SHEET, row + 1))
+  }
+
+  pub fn links(&self) -> &[(String, usize)] {
+    &self.links
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.links.is_empty()
+  }
+}
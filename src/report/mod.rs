@@ -1,205 +1,802 @@
+mod attribution;
+mod baseline;
+mod bundle;
+#[cfg(feature = "xlsx")]
+mod by_license;
+#[cfg(feature = "xlsx")]
+mod cells;
+#[cfg_attr(not(feature = "xlsx"), allow(dead_code))]
+mod columns;
+#[cfg_attr(not(feature = "xlsx"), allow(dead_code))]
 mod constants;
+mod dedup;
 mod error;
+mod events;
+mod formats;
+#[cfg(feature = "xlsx")]
 mod formatter;
-
-use self::{
-  constants::{HEADERS, LICENSE_FILES},
-  error::ReportError,
-  formatter::WorkbookFormatter,
+mod go_repo;
+mod history;
+mod license_filter;
+mod license_probe;
+mod link_check;
+#[cfg(feature = "xlsx")]
+mod links;
+mod notices;
+mod npm_registry;
+mod platform;
+mod policy;
+mod post_process;
+#[cfg_attr(not(feature = "xlsx"), allow(dead_code))]
+mod projects;
+mod redact;
+mod repo_check;
+mod repo_groups;
+mod resolver;
+mod revalidate;
+mod review;
+mod schema;
+#[cfg_attr(not(feature = "xlsx"), allow(dead_code))]
+mod split;
+mod stream;
+mod twins;
+mod writer;
+
+use self::{constants::LICENSE_FILES, resolver::LicenseProbe};
+use crate::{
+  cache::BranchCache,
+  deps::{
+    installed::InstalledVersions, manifests::ManifestTally, patterns::ExclusionRule, php_deps::ComposerPackage,
+    swift_deps::SwiftPin, yarn_berry::YarnCache,
+  },
+  diagnostics::{Category, Diagnostics},
+  http::MetadataBackend,
+  resolve,
+  types::{DepsEntry, Ecosystem, LinkStatus, ReportRow, SelfAuditFinding},
+  version::PrereleasePolicy,
+};
+use anyhow::{anyhow, bail, Result};
+use futures_util::StreamExt;
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::Sender,
+    Arc, Mutex,
+  },
+  time::{Duration, Instant},
+};
+use tokio::sync::Semaphore;
+
+pub use self::{
+  attribution::{copyrights, text_key, text_url, Copyright, Notice},
+  baseline::Baseline,
+  bundle::write_bundle,
+  dedup::{compare_versions, DedupStrategy},
+  events::ResolutionEvent,
+  formats::{ReportFormat, REPORT_FORMATS},
+  history::generate_history,
+  license_filter::LicenseFilter,
+  notices::NoticesProbe,
+  npm_registry::NpmRegistry,
+  platform::TargetPlatform,
+  policy::LicensePolicy,
+  post_process::PostProcess,
+  projects::CanonicalMap,
+  redact::Redaction,
+  repo_check::repo_mismatch,
+  resolver::{go_license_name, Resolution, Resolver},
+  revalidate::{Revalidated, Revalidation},
+  review::{import_review, Reviews, REVIEW_FILE},
+  schema::{print_schema, CollapsedRows, ReportLine, Schema, StatsDocument, BUNDLE, PROJECTS, REPORT, STATS},
+  split::ProjectSplit,
+  stream::{StreamFormat, StreamWriter},
+  twins::{Twin, TwinDetector},
+  writer::{ReportWriter, ResolvedRow, Section, WriterMessage, WriterOutput},
 };
-use crate::types::{DepsEntry, PackageInfo};
-use anyhow::{anyhow, Context, Result};
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::collections::HashMap;
-use xlsxwriter::{Workbook, Worksheet};
-
-lazy_static! {
-  static ref REPO_REGEX: Regex = Regex::new(r"^.*:(.*)\.[a-z#\.]*$").expect("Failed to compile repository regex");
-  static ref LICENSE_REGEX: Regex =
-    Regex::new(r###"<div id="#lic-0">(.*)</div>"###).expect("Failed to compile license regex");
+
+// Resolves dependencies and hands the rows to the `ReportWriter` thread, never touching the workbook itself
+pub struct ReportGenerator<'a> {
+  resolver: Resolver<'a>,
+  writer: Sender<WriterMessage>,
+  diagnostics: Arc<Diagnostics>,
+  // Fetch failures are held for `retry_failed` instead of being reported right away
+  final_retry: bool,
+  failed: Vec<FailedDependency>,
+  events: Option<Sender<ResolutionEvent>>,
+  // Set by the front-end to stop before the next package
+  cancel: Arc<AtomicBool>,
+  // Held by each package while it resolves
+  permits: Arc<Semaphore>,
+  installed: Option<InstalledVersions>,
+  baseline: Option<Arc<Baseline>>,
+  revalidation: Option<Revalidation>,
+  // Rows held for `verify_links` and `write_notice` until `release_held` sends them
+  held: Option<Mutex<Vec<ResolvedRow>>>,
+  replace_dead_links: bool,
+  // Where the NOTICE file goes, and what keeps internal names out of it
+  notice: Option<(String, Option<Arc<Redaction>>)>,
 }
 
-pub struct ReportGenerator {
-  workbook: Workbook,
-  formatter: WorkbookFormatter,
+// A package whose fetch failed in the main pass, with the sheets it goes to and the warning it would get
+struct FailedDependency {
+  ecosystem: Ecosystem,
+  sheets: Vec<&'static str>,
+  dep: DepsEntry,
+  message: String,
 }
 
-impl ReportGenerator {
-  pub fn new(filename: &str) -> Result<Self> {
-    let workbook = Workbook::new(filename).context("Failed to create workbook")?;
+impl<'a> ReportGenerator<'a> {
+  pub fn new(client: &'a dyn MetadataBackend, writer: Sender<WriterMessage>, diagnostics: Arc<Diagnostics>) -> Self {
+    Self {
+      resolver: Resolver::new(client, Arc::clone(&diagnostics)),
+      writer,
+      diagnostics,
+      final_retry: false,
+      failed: Vec::new(),
+      events: None,
+      cancel: Arc::default(),
+      permits: Arc::new(Semaphore::new(1)),
+      installed: None,
+      baseline: None,
+      revalidation: None,
+      held: None,
+      replace_dead_links: false,
+      notice: None,
+    }
+  }
 
-    let formatter = WorkbookFormatter::new();
+  // Shared with the caller, which saves it once the run is done; an in-memory one of its own otherwise
+  pub fn with_branch_cache(mut self, branches: Arc<BranchCache>) -> Self {
+    self.resolver.branches = branches;
+    self
+  }
 
-    Ok(Self { workbook, formatter })
+  pub fn with_npm_registry(mut self, registry: NpmRegistry) -> Self {
+    self.resolver.registry = registry;
+    self
   }
 
-  pub async fn generate_js_report(&self, sheet_name: &str, deps: HashMap<String, DepsEntry>) -> Result<()> {
-    let mut worksheet = self.create_worksheet(sheet_name)?;
-    self.write_headers(&mut worksheet)?;
+  pub fn with_prerelease(mut self, policy: PrereleasePolicy) -> Self {
+    self.resolver.prerelease = policy;
+    self
+  }
 
-    for (row, (_, dep)) in deps.into_iter().enumerate() {
-      self
-        .process_js_dependency(&mut worksheet, (row + 1) as u32, &dep)
-        .await
-        .with_context(|| format!("Failed to process JS dependency: {}", dep.name))?;
-    }
+  // npm packages found in these caches are resolved from the zips instead of the registry
+  pub fn with_yarn_caches(mut self, caches: Vec<YarnCache>) -> Self {
+    self.resolver.yarn_caches = caches;
+    self
+  }
 
-    Ok(())
+  // PHP packages found in these composer.lock entries are resolved from them instead of Packagist. Set once the
+  // lock files are parsed, in the middle of the run.
+  pub fn use_composer_locks(&mut self, locks: HashMap<(String, String), ComposerPackage>) {
+    self.resolver.composer_locks = locks;
   }
 
-  pub async fn generate_go_report(&self, sheet_name: &str, deps: HashMap<String, DepsEntry>) -> Result<()> {
-    let mut worksheet = self.create_worksheet(sheet_name)?;
-    self.write_headers(&mut worksheet)?;
+  // Dart packages served by these repositories instead of pub.dev, by name. Set once pubspec.lock is parsed.
+  pub fn use_pub_hosts(&mut self, hosts: HashMap<String, String>) {
+    self.resolver.pub_hosts = hosts;
+  }
 
-    for (row, (_, dep)) in deps.into_iter().enumerate() {
-      self
-        .process_go_dependency(&mut worksheet, (row + 1) as u32, &dep)
-        .await
-        .with_context(|| format!("Failed to process Go dependency: {}", dep.name))?;
-    }
+  // Where the SwiftPM packages are checked out from, by identity. Set once Package.resolved is parsed.
+  pub fn use_swift_pins(&mut self, pins: HashMap<String, SwiftPin>) {
+    self.resolver.swift_pins = pins;
+  }
 
-    Ok(())
+  // user/channel of the Conan references that had one, by name. Set once the conanfiles are parsed.
+  pub fn use_conan_channels(&mut self, channels: HashMap<String, String>) {
+    self.resolver.conan_channels = channels;
   }
 
-  pub fn save(self) -> Result<()> {
-    self.workbook.close().context("Failed to save workbook")
+  // The chart repository of each Helm chart dependency, by name. Set once the charts are parsed.
+  pub fn use_helm_repositories(&mut self, repositories: HashMap<String, String>) {
+    self.resolver.helm_repositories = repositories;
+  }
+
+  // Ask the registry for the provenance attestation of every resolved npm version
+  pub fn with_attestation_check(mut self) -> Self {
+    self.resolver.check_attestations = true;
+    self
+  }
+
+  // Record the unpacked size of npm packages and the zip size of Go modules on their rows
+  pub fn with_sizes(mut self) -> Self {
+    self.resolver.sizes = true;
+    self
+  }
+
+  // Resolved npm and Go versions are compared with node_modules and go.sum, differences are noted on the row
+  pub fn with_installed_check(mut self, installed: InstalledVersions) -> Self {
+    self.installed = Some(installed);
+    self
+  }
+
+  // Look for third-party notices files in the npm packages the probe selects, linked on the row
+  pub fn with_nested_notices(mut self, probe: NoticesProbe) -> Self {
+    self.resolver.notices = Some(probe);
+    self
+  }
+
+  // Packages the baseline already resolved at exactly their version are copied from it without a request.
+  // Shared with the caller, which reports its counts.
+  pub fn with_trusted_baseline(mut self, baseline: Arc<Baseline>) -> Self {
+    self.baseline = Some(baseline);
+    self
   }
 
-  fn create_worksheet(&self, name: &str) -> Result<Worksheet> {
+  // A sample of the rows the baseline has is resolved again instead, see `Revalidation`
+  pub fn with_revalidation(mut self, revalidation: Revalidation) -> Self {
+    self.revalidation = Some(revalidation);
     self
-      .workbook
-      .add_worksheet(Some(name))
-      .context("Failed to create worksheet")
   }
 
-  fn write_headers(&self, worksheet: &mut Worksheet) -> Result<()> {
-    for (col, header) in HEADERS.iter().enumerate() {
-      worksheet
-        .write_string(0, col as u16, header, None)
-        .context("Failed to write header")?;
+  // Rows wait for `verify_links` to check their links; dead license links the probe built are probed again for
+  // a working one when `replace` is set
+  pub fn with_link_check(mut self, replace: bool) -> Self {
+    self.held = Some(Mutex::new(Vec::new()));
+    self.replace_dead_links = replace;
+    self
+  }
+
+  // Rows wait for `write_notice` to fetch their license texts, each row gets the key of its text
+  pub fn with_notice(mut self, path: &str, redaction: Option<Arc<Redaction>>) -> Self {
+    self.held = Some(Mutex::new(Vec::new()));
+    self.notice = Some((path.to_owned(), redaction));
+    self
+  }
+
+  pub fn with_final_retry(mut self) -> Self {
+    self.final_retry = true;
+    self
+  }
+
+  // Packages resolve side by side, as many as there are permits; one at a time otherwise
+  pub fn with_permits(mut self, permits: Arc<Semaphore>) -> Self {
+    self.permits = permits;
+    self
+  }
+
+  // Progress of every package goes to `events`, resolution stops once `cancel` is set
+  pub fn with_events(mut self, events: Sender<ResolutionEvent>, cancel: Arc<AtomicBool>) -> Self {
+    self.resolver.events = Some(events.clone());
+    self.events = Some(events);
+    self.cancel = cancel;
+    self
+  }
+
+  // Exclude npm packages whose os/cpu can't match the target, only optional ones unless strict
+  pub fn with_target_platform(mut self, platform: TargetPlatform, strict: bool) -> Self {
+    self.resolver.target_platform = Some(platform);
+    self.resolver.strict_platform = strict;
+    self
+  }
+
+  // Dependency sheets are announced up front, in the order they should appear in the workbook
+  pub fn announce_sheet(&self, sheet_name: &'static str) -> Result<()> {
+    self.send(WriterMessage::Section(Section::Dependencies(sheet_name)))
+  }
+
+  // Each dependency is resolved once and its row listed on every sheet in `sheets`
+  pub async fn generate_report(
+    &mut self,
+    ecosystem: Ecosystem,
+    sheets: &[&'static str],
+    deps: HashMap<String, DepsEntry>,
+  ) -> Result<()> {
+    self.queue(ecosystem, &deps);
+    let mut pending = Vec::new();
+    for (_, dep) in deps {
+      if !self.send_baseline_row(sheets, ecosystem, &dep)? {
+        pending.push(dep);
+      }
+    }
+
+    // Rows go out as they resolve, in the order the packages came
+    let (mut excluded, mut failed) = (Vec::new(), Vec::new());
+    let this = &*self;
+    let mut resolved = resolve::in_order(pending, Arc::clone(&self.permits), |dep| async move {
+      this.start(ecosystem, &dep)?;
+      let resolution = this.resolver.resolve(ecosystem, &dep).await?;
+      Ok::<_, anyhow::Error>((dep, resolution))
+    });
+    while let Some(resolved) = resolved.next().await {
+      let (dep, resolution) = resolved?;
+      this.emit_outcome(ecosystem, &dep, &resolution);
+      match resolution {
+        Resolution::Row(row) => this.send_row(sheets, this.check_installed(&dep, *row))?,
+        Resolution::Excluded(constraints) => {
+          excluded.push(format!("{}@{} ({}): {}", dep.name, dep.version, dep.scope, constraints))
+        },
+        Resolution::FetchFailed(message) if this.final_retry => failed.push(FailedDependency {
+          ecosystem,
+          sheets: sheets.to_vec(),
+          dep,
+          message,
+        }),
+        Resolution::FetchFailed(message) => this.diagnostics.warn(Category::FetchFailure, message),
+        Resolution::Skipped => {},
+      }
+    }
+    drop(resolved);
+    self.failed.extend(failed);
+
+    if let (Some(platform), false) = (&self.resolver.target_platform, excluded.is_empty()) {
+      excluded.sort();
+      self.note(format!(
+        "Excluded {} package(s) not installable on {}-{}:\n  {}",
+        excluded.len(),
+        platform.os,
+        platform.cpu,
+        excluded.join("\n  ")
+      ));
     }
+
     Ok(())
   }
 
-  async fn process_js_dependency(&self, worksheet: &mut Worksheet<'_>, row: u32, dep: &DepsEntry) -> Result<()> {
-    let package_info = self.fetch_npm_package_info(dep).await;
-    if let Err(err) = package_info {
-      println!(
-        "Can't parse response for {}@{}. Skip this package. Error: {}",
-        dep.name, dep.version, err
-      );
+  // One more pass over the packages whose fetch failed: transient failures (cold DNS, a proxy warming up)
+  // cluster at the start of a run. Packages still failing, or not reached before `window` runs out, are warned
+  // about as usual. Returns how many were rescued out of how many were retried.
+  pub async fn retry_failed(&mut self, window: Duration) -> Result<(usize, usize)> {
+    let failed = std::mem::take(&mut self.failed);
+    if failed.is_empty() {
+      return Ok((0, 0));
+    }
 
-      return Ok(());
+    self.note(format!(
+      "Final retry of {} package(s) that failed to fetch",
+      failed.len()
+    ));
+    let deadline = Instant::now() + window;
+    let retried = failed.len();
+    let mut rescued = 0;
+    for FailedDependency {
+      ecosystem,
+      sheets,
+      dep,
+      message,
+    } in failed
+    {
+      self.start(ecosystem, &dep)?;
+      let remaining = deadline.saturating_duration_since(Instant::now());
+      let resolved = match remaining.is_zero() {
+        true => None,
+        false => tokio::time::timeout(remaining, self.resolver.resolve(ecosystem, &dep))
+          .await
+          .ok()
+          .transpose()?,
+      };
+
+      match &resolved {
+        Some(resolution) => self.emit_outcome(ecosystem, &dep, resolution),
+        None => self.emit(ResolutionEvent::Failed {
+          ecosystem,
+          name: dep.name.clone(),
+        }),
+      }
+      match resolved {
+        Some(Resolution::Row(row)) => {
+          rescued += 1;
+          self.send_row(&sheets, self.check_installed(&dep, *row))?;
+        },
+        Some(Resolution::Excluded(constraints)) => self.note(format!(
+          "Excluded {}@{} ({}): {}",
+          dep.name, dep.version, dep.scope, constraints
+        )),
+        Some(Resolution::FetchFailed(message)) => self.diagnostics.warn(Category::FetchFailure, message),
+        Some(Resolution::Skipped) => {},
+        None => self.diagnostics.warn(Category::FetchFailure, message),
+      }
     }
 
-    let package_info = package_info.unwrap();
-    let repo_url = self.validate_repository_url(&package_info).await?;
+    self.note(format!("Final retry rescued {} of {} package(s)", rescued, retried));
+    Ok((rescued, retried))
+  }
 
-    self.write_js_dependency_info(worksheet, row, &package_info)?;
-    self.find_and_write_license_url(worksheet, row, &repo_url).await?;
+  // Checks every distinct link of the held rows once and records the status on them. Returns how many links
+  // were checked and how many are still dead.
+  pub async fn verify_links(&mut self) -> Result<(usize, usize)> {
+    if self.held.is_none() {
+      return Ok((0, 0));
+    }
+    let mut rows = self.take_held();
+    let links = rows
+      .iter()
+      .flat_map(|resolved| link_check::row_links(&resolved.row))
+      .collect::<BTreeSet<_>>();
+
+    self.note(format!("Verifying {} link(s)", links.len()));
+    let mut dead = HashMap::new();
+    for link in &links {
+      if self.cancel.load(Ordering::Relaxed) {
+        bail!("Link check cancelled before {}", link);
+      }
+      if let Some(reason) = link_check::check(self.resolver.client, link).await {
+        dead.insert(link.clone(), reason);
+      }
+    }
 
-    Ok(())
+    // Probed once per dead link, however many sheets list its row
+    let mut replacements = HashMap::new();
+    // Link -> why, and the packages listing it
+    let mut still_dead = BTreeMap::<String, (String, BTreeSet<String>)>::new();
+    for ResolvedRow { row, .. } in &mut rows {
+      let mut status = LinkStatus::default();
+      for link in link_check::row_links(row) {
+        let Some(reason) = dead.get(&link) else {
+          continue;
+        };
+        let constructed = match self.replace_dead_links && row.license_url.as_deref() == Some(link.as_str()) {
+          true => link_check::constructed_license(row),
+          false => None,
+        };
+        if let Some((repo, directory)) = constructed {
+          if !replacements.contains_key(&link) {
+            let replacement = self.replace_license_link(&repo, directory.as_deref(), &link).await;
+            replacements.insert(link.clone(), replacement);
+          }
+        }
+
+        match replacements.get(&link).cloned().flatten() {
+          Some((url, provenance)) => {
+            status.replaced.push(format!("{} -> {}", link, url));
+            row.provenance.extend(provenance);
+            row
+              .provenance
+              .push(format!("dead license link replaced: {} ({})", link, reason));
+            row.license_url = Some(url);
+          },
+          None => {
+            status.dead.push(format!("{} ({})", link, reason));
+            still_dead
+              .entry(link)
+              .or_insert_with(|| (reason.clone(), BTreeSet::new()))
+              .1
+              .insert(format!("{}@{}", row.name, row.version));
+          },
+        }
+      }
+      row.link_status = Some(status);
+    }
+
+    for (link, (reason, packages)) in &still_dead {
+      self.diagnostics.warn(
+        Category::DeadLink,
+        format!(
+          "Dead link {} ({}) for {}",
+          link,
+          reason,
+          packages.iter().cloned().collect::<Vec<_>>().join(", ")
+        ),
+      );
+    }
+    let replaced = replacements
+      .values()
+      .filter(|replacement| replacement.is_some())
+      .count();
+    self.note(format!(
+      "Link check: {} of {} link(s) dead, {} dead license link(s) replaced",
+      still_dead.len(),
+      links.len(),
+      replaced
+    ));
+
+    self.held = Some(Mutex::new(rows));
+    Ok((links.len(), still_dead.len()))
   }
 
-  async fn process_go_dependency(&self, worksheet: &mut Worksheet<'_>, row: u32, dep: &DepsEntry) -> Result<()> {
-    self.write_go_dependency_info(worksheet, row, dep)?;
-    self.fetch_and_write_go_license(worksheet, row, dep).await?;
+  // Fetches the license text behind each distinct license link of the held rows once, keys the rows by their
+  // text and writes the NOTICE file. Returns the number of distinct texts and the packages they cover.
+  pub async fn write_notice(&mut self) -> Result<(usize, usize)> {
+    let (Some((path, redaction)), Some(_)) = (self.notice.clone(), &self.held) else {
+      return Ok((0, 0));
+    };
+    let mut rows = self.take_held();
+    let urls = rows
+      .iter()
+      .filter_map(|resolved| resolved.row.license_url.clone())
+      .collect::<BTreeSet<_>>();
+
+    self.note(format!("Fetching {} license text(s) for {}", urls.len(), path));
+    let mut texts = HashMap::new();
+    for url in urls {
+      if self.cancel.load(Ordering::Relaxed) {
+        bail!("NOTICE file cancelled before {}", url);
+      }
+      let text = match self.resolver.client.get(&attribution::text_url(&url)).await {
+        Ok(response) if response.status.is_success() => {
+          attribution::license_text(&response.body, response.content_type.as_deref())
+        },
+        _ => None,
+      };
+      texts.insert(url, text);
+    }
 
-    Ok(())
+    // A package listed on several sheets is one entry
+    let mut notice = Notice::default();
+    for ResolvedRow { row, .. } in &mut rows {
+      let shown = match &redaction {
+        Some(redaction) => redaction.row(row.clone()),
+        None => row.clone(),
+      };
+      let package = format!("{}@{}", shown.name, shown.version);
+      let text = shown
+        .license_url
+        .as_ref()
+        .and_then(|url| texts.get(url))
+        .cloned()
+        .flatten();
+      match text {
+        Some(text) => row.license_text_key = Some(notice.add(&package, shown.license.as_deref(), &text)),
+        None => notice.add_without_text(&package, shown.license.as_deref()),
+      }
+    }
+    notice.write(&path)?;
+    let (unique, packages) = notice.counts();
+    self.note(format!(
+      "NOTICE: {} unique license text(s) cover {} package(s), written to {}",
+      unique, packages, path
+    ));
+
+    self.held = Some(Mutex::new(rows));
+    Ok((unique, packages))
   }
 
-  async fn fetch_npm_package_info(&self, dep: &DepsEntry) -> Result<PackageInfo> {
-    let url = format!("https://registry.npmjs.org/{}/{}", dep.name, dep.version);
+  fn take_held(&mut self) -> Vec<ResolvedRow> {
+    self
+      .held
+      .take()
+      .map(|held| held.into_inner().unwrap_or_default())
+      .unwrap_or_default()
+  }
 
-    println!("Fetch {}", url);
+  // Sends the rows held for the checks above, ahead of the sheets built from them
+  pub fn release_held(&mut self) -> Result<()> {
+    for resolved in self.take_held() {
+      self.send(WriterMessage::Row(Box::new(resolved)))?;
+    }
+    Ok(())
+  }
 
-    reqwest::get(&url)
-      .await
-      .with_context(|| anyhow!(ReportError::PackageFetchError(format!("Can't fetch package {}", url))))?
-      .json::<PackageInfo>()
+  // A working license link from the probe that built the dead one: the cached default branch is the likely
+  // culprit, the other license file names and levels come after it
+  async fn replace_license_link(
+    &self,
+    repo_url: &str,
+    directory: Option<&str>,
+    dead: &str,
+  ) -> Option<(String, Vec<String>)> {
+    self.resolver.branches.invalidate(repo_url);
+    let mut provenance = Vec::new();
+    match self
+      .resolver
+      .probe_repository(repo_url, directory, LICENSE_FILES, &mut provenance)
       .await
-      .context("Failed to parse NPM package info")
+    {
+      Ok(LicenseProbe::Found(url, _)) if url != dead => Some((url, provenance)),
+      _ => None,
+    }
   }
 
-  async fn validate_repository_url(&self, package_info: &PackageInfo) -> Result<String> {
-    let captures = REPO_REGEX
-      .captures(&package_info.repository.url)
-      .ok_or(ReportError::InvalidRepoUrl)?;
+  fn emit(&self, event: ResolutionEvent) {
+    if let Some(events) = &self.events {
+      // A front-end that went away doesn't stop the run
+      let _ = events.send(event);
+    }
+  }
 
-    let repo_url = format!("https:{}", &captures[1]);
-    let response = reqwest::get(&repo_url)
-      .await
-      .context("Failed to validate repository URL")?;
+  // Run-level progress, printed by the binary and left to the caller otherwise
+  fn note(&self, message: String) {
+    self.emit(ResolutionEvent::Note { message });
+  }
 
-    Ok(match response.status() {
-      reqwest::StatusCode::OK => response.url().to_string(),
-      _ => repo_url,
-    })
+  fn queue(&self, ecosystem: Ecosystem, deps: &HashMap<String, DepsEntry>) {
+    for dep in deps.values() {
+      self.emit(ResolutionEvent::Queued {
+        ecosystem,
+        name: dep.name.clone(),
+        version: dep.version.clone(),
+      });
+    }
   }
 
-  fn write_js_dependency_info(&self, worksheet: &mut Worksheet, row: u32, package_info: &PackageInfo) -> Result<()> {
-    worksheet.write_string(row, 0, &package_info.name, None)?;
-    worksheet.write_string(row, 1, &package_info.version, None)?;
-    worksheet.write_string(row, 2, &package_info.homepage, self.formatter.url_format())?;
-    worksheet.write_string(row, 3, &package_info.license, None)?;
+  // Nothing is sent to the writer after a cancellation, so no partial report is saved
+  fn start(&self, ecosystem: Ecosystem, dep: &DepsEntry) -> Result<()> {
+    if self.cancel.load(Ordering::Relaxed) {
+      bail!("Resolution cancelled before {}", dep.name);
+    }
+    self.emit(ResolutionEvent::Fetching {
+      ecosystem,
+      name: dep.name.clone(),
+    });
     Ok(())
   }
 
-  async fn find_and_write_license_url(&self, worksheet: &mut Worksheet<'_>, row: u32, repo_url: &str) -> Result<()> {
-    for license_file in LICENSE_FILES {
-      let license_url = format!("{}/blob/master/{}", repo_url, license_file);
-      let response = reqwest::get(&license_url).await?;
+  fn emit_outcome(&self, ecosystem: Ecosystem, dep: &DepsEntry, resolution: &Resolution) {
+    let name = dep.name.clone();
+    self.emit(match resolution {
+      Resolution::Row(row) => ResolutionEvent::Resolved {
+        ecosystem,
+        name,
+        license: row.license.clone(),
+      },
+      Resolution::Excluded(_) => ResolutionEvent::Excluded { ecosystem, name },
+      Resolution::FetchFailed(_) | Resolution::Skipped => ResolutionEvent::Failed { ecosystem, name },
+    });
+  }
+
+  fn check_installed(&self, dep: &DepsEntry, mut row: ReportRow) -> ReportRow {
+    let Some(installed) = &self.installed else {
+      return row;
+    };
+    // Notes like "(pinned 1.2.3 unpublished; ...)" follow the version
+    let version = row.version.split(' ').next().unwrap_or_default();
+    if let Some(mismatch) = installed.mismatch(row.ecosystem, &row.name, version, &dep.sources) {
+      self.diagnostics.warn(
+        Category::InstalledMismatch,
+        format!("{}@{} resolved, but {}", row.name, version, mismatch),
+      );
+      row.installed_mismatch = Some(mismatch);
+    }
+    row
+  }
 
-      if response.status() == reqwest::StatusCode::OK {
-        worksheet.write_string(row, 4, &license_url, self.formatter.url_format())?;
-        break;
+  // Whether the trusted baseline had the row, which is then sent like a resolved one. A row drawn for
+  // revalidation is held back and the package resolved as if the baseline didn't have it.
+  fn send_baseline_row(&self, sheets: &[&'static str], ecosystem: Ecosystem, dep: &DepsEntry) -> Result<bool> {
+    let Some(row) = self
+      .baseline
+      .as_ref()
+      .and_then(|baseline| baseline.lookup(ecosystem, dep))
+    else {
+      return Ok(false);
+    };
+    let row = self.check_installed(dep, row);
+    if let Some(revalidation) = &self.revalidation {
+      if revalidation.is_sampled(ecosystem, &dep.name, &dep.version) {
+        revalidation.hold(sheets, row);
+        return Ok(false);
       }
     }
-    Ok(())
+
+    self.start(ecosystem, dep)?;
+    self.emit(ResolutionEvent::Resolved {
+      ecosystem,
+      name: dep.name.clone(),
+      license: row.license.clone(),
+    });
+    self.send_row(sheets, row)?;
+    Ok(true)
   }
 
-  fn write_go_dependency_info(&self, worksheet: &mut Worksheet<'_>, row: u32, dep: &DepsEntry) -> Result<()> {
-    worksheet.write_string(row, 0, &dep.name, None)?;
-    worksheet.write_string(row, 1, &dep.version, None)?;
-    worksheet.write_string(
-      row,
-      2,
-      &format!("https://pkg.go.dev/{}", dep.name),
-      self.formatter.url_format(),
-    )?;
+  fn send_row(&self, sheets: &[&'static str], row: ReportRow) -> Result<()> {
+    let row = match &self.revalidation {
+      Some(revalidation) => self.check_drift(revalidation, row),
+      None => row,
+    };
+    if let Some((&last, rest)) = sheets.split_last() {
+      for &sheet in rest {
+        self.send_resolved(ResolvedRow {
+          sheet,
+          row: row.clone(),
+        })?;
+      }
+      self.send_resolved(ResolvedRow { sheet: last, row })?;
+    }
+
     Ok(())
   }
 
-  async fn fetch_and_write_go_license(&self, worksheet: &mut Worksheet<'_>, row: u32, dep: &DepsEntry) -> Result<()> {
-    let lic_url = format!("https://pkg.go.dev/{}?tab=licenses", dep.name);
-
-    println!("Fetch license for {}", dep.name);
-
-    let resp = reqwest::get(&lic_url).await?;
-    if resp.status() == reqwest::StatusCode::OK {
-      let response = resp.text().await?;
-
-      if let Some(lic) = LICENSE_REGEX.captures(&response) {
-        worksheet
-          .write_string(row, 3, lic.get(1).unwrap().as_str(), None)
-          .map_err(|_| {
-            anyhow!(ReportError::WorksheetError(
-              "Can't write license type information".to_owned(),
-            ))
-          })?;
-        worksheet
-          .write_string(row, 4, &lic_url, self.formatter.url_format())
-          .map_err(|_| {
-            anyhow!(ReportError::WorksheetError(
-              "Can't write license link information".to_owned(),
-            ))
-          })?;
-      } else {
-        println!("Can't found license for {}", dep.name);
-      }
+  // A fresh row of a package the baseline was held back for: a license that changed since is the fresh one,
+  // dropped from the baseline for the rest of the run
+  fn check_drift(&self, revalidation: &Revalidation, row: ReportRow) -> ReportRow {
+    match revalidation.compare(row) {
+      Revalidated::Fresh(row) => row,
+      Revalidated::Kept(row) => row,
+      Revalidated::Drifted(mut row, before) => {
+        let version = row.version.split(' ').next().unwrap_or_default().to_owned();
+        if let Some(baseline) = &self.baseline {
+          baseline.invalidate(row.ecosystem, &row.name, &version);
+        }
+        self.diagnostics.warn(
+          Category::MetadataDrift,
+          format!(
+            "METADATA DRIFT: {}@{} ({}) was {} in the baseline, now {}",
+            row.name,
+            version,
+            row.ecosystem,
+            before,
+            row.license.as_deref().unwrap_or_default()
+          ),
+        );
+        row.provenance.push(format!("revalidated: baseline had {}", before));
+        row
+      },
+    }
+  }
+
+  // Baseline rows held back for revalidation whose fresh resolution failed go out as they were, then the writer
+  // gets the counts. Returns how many rows were resolved again and how many had drifted.
+  pub fn finish_revalidation(&self) -> Result<(usize, usize)> {
+    let Some(revalidation) = &self.revalidation else {
+      return Ok((0, 0));
+    };
+    for (sheets, row) in revalidation.unanswered() {
+      self.send_row(&sheets, row)?;
+    }
+    let (sampled, drifted) = revalidation.counts();
+    self.send(WriterMessage::Revalidated(sampled, drifted))?;
+    Ok((sampled, drifted))
+  }
+
+  // Rows go to the writer as they resolve, unless they are held for `verify_links` or `write_notice`
+  fn send_resolved(&self, resolved: ResolvedRow) -> Result<()> {
+    match &self.held {
+      Some(held) => {
+        held.lock().expect("Held rows lock poisoned").push(resolved);
+        Ok(())
+      },
+      None => self.send(WriterMessage::Row(Box::new(resolved))),
+    }
+  }
+
+  // Rows that need no resolution, e.g. SBOM components that already assert a license.
+  // `announce` starts a new sheet, otherwise the rows join one already generated.
+  pub fn generate_imported_rows(&self, sheet_name: &'static str, rows: Vec<ReportRow>, announce: bool) -> Result<()> {
+    if announce {
+      self.announce_sheet(sheet_name)?;
+    }
+    for row in rows {
+      self.send_resolved(ResolvedRow { sheet: sheet_name, row })?;
     }
 
     Ok(())
   }
+
+  // Sheets the writer builds on its own: projects from the rows it kept, summary from its stats
+  pub fn generate_projects_report(&self, sheet_name: &'static str, map: CanonicalMap) -> Result<()> {
+    self.send(WriterMessage::Section(Section::Projects(sheet_name, map)))
+  }
+
+  pub fn generate_by_license_report(&self, sheet_name: &'static str, collapse_up_to: usize) -> Result<()> {
+    self.send(WriterMessage::Section(Section::ByLicense(sheet_name, collapse_up_to)))
+  }
+
+  pub fn generate_self_audit_report(&self, sheet_name: &'static str, findings: Vec<SelfAuditFinding>) -> Result<()> {
+    self.send(WriterMessage::Section(Section::SelfAudit(sheet_name, findings)))
+  }
+
+  pub fn generate_exclusions_report(&self, sheet_name: &'static str, rules: Vec<(ExclusionRule, usize)>) -> Result<()> {
+    self.send(WriterMessage::Section(Section::Exclusions(sheet_name, rules)))
+  }
+
+  pub fn generate_manifests_report(&self, sheet_name: &'static str, tallies: Vec<ManifestTally>) -> Result<()> {
+    self.send(WriterMessage::Section(Section::Manifests(sheet_name, tallies)))
+  }
+
+  pub fn generate_summary_report(&self, sheet_name: &'static str) -> Result<()> {
+    self.send(WriterMessage::Section(Section::Summary(sheet_name)))
+  }
+
+  pub fn generate_run_info(
+    &self,
+    sheet_name: &'static str,
+    settings: Vec<(&'static str, String, &'static str)>,
+  ) -> Result<()> {
+    self.send(WriterMessage::Section(Section::RunInfo(sheet_name, settings)))
+  }
+
+  // Tells the writer the report is complete; dropping the generator without it leaves no workbook behind
+  pub fn finish(self) -> Result<()> {
+    self.send(WriterMessage::Finish)
+  }
+
+  // The writer only hangs up after a failure of its own, which the caller gets from its handle
+  fn send(&self, message: WriterMessage) -> Result<()> {
+    self
+      .writer
+      .send(message)
+      .map_err(|_| anyhow!("Report writer stopped before the report was complete"))
+  }
 }
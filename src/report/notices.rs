@@ -0,0 +1,69 @@
+use crate::deps::{audit::is_notice_file_name, patterns::PatternSet};
+use anyhow::{Context, Result};
+use std::{
+  collections::BTreeSet,
+  fs,
+  path::{Path, PathBuf},
+};
+
+const MEGABYTE: u64 = 1024 * 1024;
+
+// Which npm packages --nested-notices looks into: big ones are the likely bundlers, the rest only when named
+pub struct NoticesProbe {
+  root_path: PathBuf,
+  min_size: u64,
+  packages: PatternSet,
+}
+
+impl NoticesProbe {
+  // `min_size_mb` is compared with the unpacked size the registry publishes
+  pub fn new(directory: &str, min_size_mb: u64, packages: PatternSet) -> Result<Self> {
+    let root_path =
+      fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+
+    Ok(Self {
+      root_path,
+      min_size: min_size_mb * MEGABYTE,
+      packages,
+    })
+  }
+
+  pub fn applies(&self, name: &str, unpacked_size: Option<u64>) -> bool {
+    self.packages.is_match(name) || unpacked_size.is_some_and(|size| size >= self.min_size)
+  }
+
+  // A notices file in the installed copy of exactly this version, the closest node_modules/<name> from a manifest's
+  // directory up to the root. The tarball itself isn't downloaded.
+  pub fn installed(&self, name: &str, version: &str, sources: &BTreeSet<String>) -> Option<String> {
+    sources.iter().find_map(|source| {
+      let dir = self.root_path.join(source);
+      let package_dir = dir
+        .parent()?
+        .ancestors()
+        .take_while(|ancestor| ancestor.starts_with(&self.root_path))
+        .map(|ancestor| ancestor.join("node_modules").join(name))
+        .find(|package_dir| package_dir.join("package.json").is_file())?;
+      if installed_version(&package_dir).as_deref() != Some(version) {
+        return None;
+      }
+
+      fs::read_dir(&package_dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .find(|file| is_notice_file_name(file))
+    })
+  }
+}
+
+// The file as published, for a copy found installed or in a Yarn cache
+pub fn published_url(name: &str, version: &str, file: &str) -> String {
+  format!("https://unpkg.com/{}@{}/{}", name, version, file)
+}
+
+fn installed_version(package_dir: &Path) -> Option<String> {
+  let content = fs::read(package_dir.join("package.json")).ok()?;
+  let package = serde_json::from_slice::<serde_json::Value>(&content).ok()?;
+  package.get("version")?.as_str().map(str::to_owned)
+}
@@ -0,0 +1,74 @@
+use crate::types::PackageInfo;
+use anyhow::{anyhow, Result};
+
+// Target an npm package has to install on, written as `<os>-<cpu>` (e.g. `linux-x64`)
+#[derive(Debug, Clone)]
+pub struct TargetPlatform {
+  pub os: String,
+  pub cpu: String,
+}
+
+impl TargetPlatform {
+  pub fn parse(value: &str) -> Result<Self> {
+    match value.split_once('-') {
+      Some((os, cpu)) if !os.is_empty() && !cpu.is_empty() => Ok(Self {
+        os: os.to_owned(),
+        cpu: cpu.to_owned(),
+      }),
+      _ => Err(anyhow!(
+        "Invalid target platform `{}`, expected <os>-<cpu> like linux-x64",
+        value
+      )),
+    }
+  }
+
+  // Same rules as npm: any negated match rejects, otherwise a positive list must name the target
+  pub fn matches(&self, package_info: &PackageInfo) -> bool {
+    allows(&package_info.os, &self.os) && allows(&package_info.cpu, &self.cpu)
+  }
+}
+
+// Human readable `os`/`cpu`/`engines` restrictions, None for unrestricted packages
+pub fn describe_constraints(package_info: &PackageInfo) -> Option<String> {
+  let mut parts = Vec::new();
+  if !package_info.os.is_empty() {
+    parts.push(format!("os: {}", package_info.os.join(", ")));
+  }
+  if !package_info.cpu.is_empty() {
+    parts.push(format!("cpu: {}", package_info.cpu.join(", ")));
+  }
+
+  let engines = match &package_info.engines {
+    Some(serde_json::Value::Object(engines)) => engines
+      .iter()
+      .map(|(engine, range)| match range.as_str() {
+        Some(range) => format!("{} {}", engine, range),
+        None => engine.clone(),
+      })
+      .collect(),
+    Some(serde_json::Value::Array(engines)) => engines.iter().filter_map(|e| e.as_str().map(str::to_owned)).collect(),
+    _ => Vec::new(),
+  };
+  if !engines.is_empty() {
+    parts.push(format!("engines: {}", engines.join(", ")));
+  }
+
+  Some(parts.join("; ")).filter(|constraints| !constraints.is_empty())
+}
+
+fn allows(constraints: &[String], target: &str) -> bool {
+  let mut has_positive = false;
+  let mut matched = false;
+  for constraint in constraints {
+    match constraint.strip_prefix('!') {
+      Some(negated) if negated == target => return false,
+      Some(_) => {},
+      None => {
+        has_positive = true;
+        matched |= constraint == target;
+      },
+    }
+  }
+
+  !has_positive || matched
+}
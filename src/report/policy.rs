@@ -0,0 +1,43 @@
+use crate::types::{LicenseFlag, ReportRow};
+
+// Requirements on the normalized license flags. Rows breaking one still land in the report, the run then exits with
+// an error. Unknown licenses are left to the unknown-license warnings.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LicensePolicy {
+  pub require_osi_approved: bool,
+  pub require_fsf_libre: bool,
+}
+
+impl LicensePolicy {
+  pub fn is_active(&self) -> bool {
+    self.require_osi_approved || self.require_fsf_libre
+  }
+
+  // What the row's license lacks, None when it meets the policy
+  pub fn violation(&self, row: &ReportRow) -> Option<String> {
+    let mut missing = Vec::new();
+    if self.require_osi_approved && breaks(row.osi_approved) {
+      missing.push("OSI approved");
+    }
+    if self.require_fsf_libre && breaks(row.fsf_libre) {
+      missing.push("FSF libre");
+    }
+    if missing.is_empty() {
+      return None;
+    }
+
+    Some(format!(
+      "{} {}@{}: {} is not {}",
+      row.ecosystem,
+      row.name,
+      row.version,
+      row.license.as_deref().unwrap_or_default(),
+      missing.join(" or ")
+    ))
+  }
+}
+
+// An expression whose branches disagree can't be shown to comply
+fn breaks(flag: Option<LicenseFlag>) -> bool {
+  matches!(flag, Some(LicenseFlag::No | LicenseFlag::Mixed))
+}
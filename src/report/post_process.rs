@@ -0,0 +1,174 @@
+use super::writer::ResolvedRow;
+use crate::types::ReportRow;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::HashSet,
+  io::{BufRead, BufReader, Write},
+  process::{Command, Stdio},
+  thread,
+  time::{Duration, Instant},
+};
+
+// Bump when the line format changes; scripts echo the version they speak and a mismatch aborts the run
+const SCHEMA_VERSION: u32 = 1;
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// External command that sees every resolved row before anything is written and may rewrite, drop or add rows
+pub struct PostProcess {
+  command: String,
+  timeout: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Handshake {
+  schema_version: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Line {
+  sheet: String,
+  row: ReportRow,
+}
+
+impl PostProcess {
+  pub fn new(command: &str, timeout: Duration) -> Self {
+    Self {
+      command: command.to_owned(),
+      timeout,
+    }
+  }
+
+  // stdin: a handshake line, then one `{"sheet", "row"}` object per line. stdout: the same handshake echoed back,
+  // then the rows to keep. Rows that weren't sent in are marked as coming from the script.
+  pub fn run(&self, sheets: &[&'static str], rows: Vec<ResolvedRow>) -> Result<Vec<ResolvedRow>> {
+    let sent = rows
+      .iter()
+      .map(|resolved| row_key(resolved.sheet, &resolved.row))
+      .collect::<HashSet<_>>();
+
+    let mut input = serde_json::to_string(&Handshake {
+      schema_version: SCHEMA_VERSION,
+    })?;
+    input.push('\n');
+    for resolved in &rows {
+      let line = serde_json::to_string(&Line {
+        sheet: resolved.sheet.to_owned(),
+        row: resolved.row.clone(),
+      })
+      .context("Failed to serialize report row")?;
+      input.push_str(&line);
+      input.push('\n');
+    }
+
+    println!("Post-process {} row(s) with {}", rows.len(), self.command);
+    let output = self.execute(input)?;
+
+    let mut lines = output.lines().filter(|line| !line.trim().is_empty());
+    let handshake = lines.next().ok_or_else(|| {
+      anyhow!(
+        "Post-process {} printed nothing, expected a schema handshake",
+        self.command
+      )
+    })?;
+    let handshake: Handshake = serde_json::from_str(handshake)
+      .with_context(|| format!("Post-process {}: first line is not a schema handshake", self.command))?;
+    if handshake.schema_version != SCHEMA_VERSION {
+      bail!(
+        "Post-process {} speaks schema version {}, expected {}",
+        self.command,
+        handshake.schema_version,
+        SCHEMA_VERSION
+      );
+    }
+
+    let mut processed = Vec::new();
+    for (idx, line) in lines.enumerate() {
+      let line: Line = serde_json::from_str(line).with_context(|| {
+        format!(
+          "Post-process {}: row {} is not a valid report row",
+          self.command,
+          idx + 1
+        )
+      })?;
+      let sheet = sheets
+        .iter()
+        .copied()
+        .find(|sheet| *sheet == line.sheet)
+        .ok_or_else(|| {
+          anyhow!(
+            "Post-process {}: row {} targets unknown sheet {}",
+            self.command,
+            idx + 1,
+            line.sheet
+          )
+        })?;
+
+      let mut row = line.row;
+      if !sent.contains(&row_key(sheet, &row)) {
+        row.provenance.push("source: post-process".to_owned());
+      }
+      processed.push(ResolvedRow { sheet, row });
+    }
+
+    println!(
+      "Post-process kept or added {} row(s) out of {}",
+      processed.len(),
+      rows.len()
+    );
+    Ok(processed)
+  }
+
+  fn execute(&self, input: String) -> Result<String> {
+    let mut child = Command::new(&self.command)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .spawn()
+      .with_context(|| format!("Failed to start post-process {}", self.command))?;
+
+    // Feed and drain on their own threads so a script that answers as it reads can't deadlock on full pipes
+    let mut stdin = child.stdin.take().context("Post-process stdin is not piped")?;
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let stdout = child.stdout.take().context("Post-process stdout is not piped")?;
+    let reader = thread::spawn(move || {
+      let mut output = String::new();
+      BufReader::new(stdout).lines().try_for_each(|line| {
+        output.push_str(&line?);
+        output.push('\n');
+        Ok::<_, std::io::Error>(())
+      })?;
+      Ok::<_, std::io::Error>(output)
+    });
+
+    let deadline = Instant::now() + self.timeout;
+    let status = loop {
+      if let Some(status) = child.try_wait().context("Failed to wait for post-process")? {
+        break status;
+      }
+      if Instant::now() >= deadline {
+        let _ = child.kill();
+        let _ = child.wait();
+        bail!(
+          "Post-process {} did not finish within {}s",
+          self.command,
+          self.timeout.as_secs()
+        );
+      }
+      thread::sleep(POLL_INTERVAL);
+    };
+    if !status.success() {
+      bail!("Post-process {} failed: {}", self.command, status);
+    }
+
+    // A script that exits without reading everything closes the pipe on us, its output is what counts
+    let _ = writer.join();
+    reader
+      .join()
+      .map_err(|_| anyhow!("Post-process output reader panicked"))?
+      .with_context(|| format!("Failed to read output of post-process {}", self.command))
+  }
+}
+
+fn row_key<'a>(sheet: &'a str, row: &'a ReportRow) -> (&'a str, String, &'a str, &'a str) {
+  (sheet, row.ecosystem.to_string(), &row.name, &row.version)
+}
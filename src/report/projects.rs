@@ -0,0 +1,113 @@
+use crate::types::ReportRow;
+use anyhow::{anyhow, Context, Result};
+use glob::Pattern;
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap},
+  fs,
+};
+
+// Maps ecosystem-specific package names (exact or glob) to a canonical project name
+#[derive(Clone)]
+pub struct CanonicalMap {
+  exact: HashMap<String, String>,
+  globs: Vec<(Pattern, String)>,
+}
+
+pub struct Project<'a> {
+  pub name: String,
+  pub packages: Vec<&'a ReportRow>,
+}
+
+impl CanonicalMap {
+  pub fn load(path: &str) -> Result<Self> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read canonical map: {}", path))?;
+    let entries: HashMap<String, String> =
+      toml::from_str(&content).with_context(|| format!("Failed to parse canonical map: {}", path))?;
+
+    let mut exact = HashMap::new();
+    let mut globs = Vec::new();
+    let mut errors = Vec::new();
+    for (pattern, project) in entries {
+      if pattern.contains(['*', '?', '[']) {
+        match Pattern::new(&pattern) {
+          Ok(compiled) => globs.push((compiled, project)),
+          Err(err) => errors.push(format!(
+            "{}: key `{}`: {} at position {}",
+            path, pattern, err.msg, err.pos
+          )),
+        }
+      } else {
+        exact.insert(pattern, project);
+      }
+    }
+
+    if !errors.is_empty() {
+      errors.sort();
+      return Err(anyhow!(
+        "Failed to compile {} glob pattern(s) in canonical map:\n  {}",
+        errors.len(),
+        errors.join("\n  ")
+      ));
+    }
+
+    // The most specific (longest) glob wins when several match
+    globs.sort_by(|(a, _), (b, _)| b.as_str().len().cmp(&a.as_str().len()).then(a.cmp(b)));
+
+    Ok(Self { exact, globs })
+  }
+
+  pub fn project_for<'a>(&'a self, name: &'a str) -> &'a str {
+    if let Some(project) = self.exact.get(name) {
+      return project;
+    }
+
+    self
+      .globs
+      .iter()
+      .find(|(pattern, _)| pattern.matches(name))
+      .map_or(name, |(_, project)| project.as_str())
+  }
+
+  // Every project name the map assigns to, sorted
+  pub fn projects(&self) -> impl Iterator<Item = &str> {
+    self
+      .exact
+      .values()
+      .chain(self.globs.iter().map(|(_, project)| project))
+      .map(String::as_str)
+      .collect::<BTreeSet<_>>()
+      .into_iter()
+  }
+}
+
+impl Project<'_> {
+  pub fn versions(&self) -> Vec<String> {
+    unique(self.packages.iter().map(|package| package.version.clone()))
+  }
+
+  pub fn licenses(&self) -> Vec<String> {
+    unique(self.packages.iter().filter_map(|package| package.license.clone()))
+  }
+}
+
+pub fn group_rows<'a>(rows: &'a [ReportRow], map: &CanonicalMap) -> Vec<Project<'a>> {
+  let mut grouped: BTreeMap<String, Vec<&'a ReportRow>> = BTreeMap::new();
+  for row in rows {
+    grouped
+      .entry(map.project_for(&row.name).to_owned())
+      .or_default()
+      .push(row);
+  }
+
+  grouped
+    .into_iter()
+    .map(|(name, mut packages)| {
+      packages.sort_by(|a, b| (a.ecosystem, &a.name, &a.version).cmp(&(b.ecosystem, &b.name, &b.version)));
+      Project { name, packages }
+    })
+    .collect()
+}
+
+fn unique(values: impl Iterator<Item = String>) -> Vec<String> {
+  values.collect::<BTreeSet<_>>().into_iter().collect()
+}
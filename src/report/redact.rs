@@ -0,0 +1,160 @@
+use super::writer::Section;
+use crate::{
+  init::FirstParty,
+  types::{ReportRow, SelfAuditFinding},
+};
+use anyhow::{Context, Result};
+use ring::hmac;
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fs,
+  sync::Mutex,
+};
+
+const PSEUDONYM_PREFIX: &str = "internal-";
+// Hex digits of the HMAC kept: 48 bits, no tree has enough packages for two to collide
+const PSEUDONYM_DIGITS: usize = 12;
+
+// --redact-internal: first-party packages and everything under their scopes and module prefixes get a pseudonym,
+// the HMAC of the name under the run's key, so reports made with the same key still diff
+pub struct Redaction {
+  key: hmac::Key,
+  packages: BTreeSet<String>,
+  namespaces: Vec<String>,
+  // Pseudonym -> what it stands for, for --redaction-map
+  map: Mutex<BTreeMap<String, String>>,
+}
+
+impl Redaction {
+  pub fn new(key: &str, first_party: FirstParty, namespaces: &Option<Vec<String>>) -> Self {
+    let namespaces = first_party
+      .namespaces
+      .into_iter()
+      .chain(namespaces.iter().flatten().cloned())
+      .map(|namespace| namespace.trim().trim_end_matches('/').to_owned())
+      .filter(|namespace| !namespace.is_empty())
+      .collect::<BTreeSet<_>>();
+    let redaction = Self {
+      key: hmac::Key::new(hmac::HMAC_SHA256, key.as_bytes()),
+      packages: first_party.packages,
+      namespaces: namespaces.into_iter().collect(),
+      map: Mutex::new(BTreeMap::new()),
+    };
+    // Known up front so free text naming them is scrubbed before any row arrived
+    for original in redaction.packages.iter().chain(&redaction.namespaces) {
+      redaction.pseudonym(original);
+    }
+    redaction
+  }
+
+  // One of the tree's own packages, or at or below one of the namespaces on whole segments like --skip-prefix
+  pub fn is_internal(&self, name: &str) -> bool {
+    self.packages.contains(name)
+      || self.namespaces.iter().any(|namespace| {
+        name
+          .strip_prefix(namespace.as_str())
+          .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+      })
+  }
+
+  // The pseudonym of an internal name, any other name as it is
+  pub fn name(&self, name: &str) -> String {
+    match self.is_internal(name) {
+      true => self.pseudonym(name),
+      false => name.to_owned(),
+    }
+  }
+
+  // Links and notes of an internal package lead back to it, only what the license review needs is kept
+  pub fn row(&self, mut row: ReportRow) -> ReportRow {
+    if !self.is_internal(&row.name) {
+      return row;
+    }
+    row.name = self.pseudonym(&row.name);
+    row.homepage = String::new();
+    row.license_url = None;
+    row.funding = None;
+    row.repo_mismatch = None;
+    row.installed_mismatch = None;
+    row.third_party_notices = None;
+    row.link_status = None;
+    row.provenance = vec!["redacted: internal package".to_owned()];
+    row
+  }
+
+  // Sheets that aren't rows quote names and patterns in free text: run info, self audit findings, exclusion rules
+  #[cfg_attr(not(feature = "xlsx"), allow(dead_code))]
+  pub fn section(&self, section: Section) -> Section {
+    match section {
+      Section::RunInfo(name, settings) => Section::RunInfo(name, self.run_info(settings)),
+      Section::SelfAudit(name, findings) => Section::SelfAudit(
+        name,
+        findings
+          .into_iter()
+          .map(|finding| SelfAuditFinding {
+            manifest: self.scrub(&finding.manifest),
+            package: self.name(&finding.package),
+            issue: self.scrub(&finding.issue),
+          })
+          .collect(),
+      ),
+      Section::Exclusions(name, rules) => Section::Exclusions(
+        name,
+        rules
+          .into_iter()
+          .map(|(mut rule, count)| {
+            rule.pattern = self.scrub(&rule.pattern);
+            rule.reason = rule.reason.map(|reason| self.scrub(&reason));
+            (rule, count)
+          })
+          .collect(),
+      ),
+      section => section,
+    }
+  }
+
+  // Setting values name scopes, prefixes and patterns; the bundle's manifest carries them as well
+  pub fn run_info(
+    &self,
+    settings: Vec<(&'static str, String, &'static str)>,
+  ) -> Vec<(&'static str, String, &'static str)> {
+    settings
+      .into_iter()
+      .map(|(key, value, source)| (key, self.scrub(&value), source))
+      .collect()
+  }
+
+  pub fn write_map(&self, path: &str) -> Result<()> {
+    let map = self.map.lock().map(|map| map.clone()).unwrap_or_default();
+    let content = serde_json::to_string_pretty(&map).context("Failed to serialize the redaction map")?;
+    fs::write(path, content + "\n").with_context(|| format!("Failed to write {}", path))
+  }
+
+  fn pseudonym(&self, original: &str) -> String {
+    let tag = hmac::sign(&self.key, original.as_bytes());
+    let digest = tag
+      .as_ref()
+      .iter()
+      .map(|byte| format!("{:02x}", byte))
+      .collect::<String>();
+    let pseudonym = format!("{}{}", PSEUDONYM_PREFIX, &digest[..PSEUDONYM_DIGITS]);
+    if let Ok(mut map) = self.map.lock() {
+      map.insert(pseudonym.clone(), original.to_owned());
+    }
+    pseudonym
+  }
+
+  // Every internal name handed out so far, longest first so a package goes before the namespace it's under
+  pub fn scrub(&self, text: &str) -> String {
+    let Ok(map) = self.map.lock() else {
+      return text.to_owned();
+    };
+    let mut originals = map.iter().collect::<Vec<_>>();
+    originals.sort_by_key(|(_, original)| std::cmp::Reverse(original.len()));
+    originals
+      .into_iter()
+      .fold(text.to_owned(), |text, (pseudonym, original)| {
+        text.replace(original.as_str(), pseudonym)
+      })
+  }
+}
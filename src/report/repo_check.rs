@@ -0,0 +1,52 @@
+// npm scope -> GitHub owner pairs that differ by design
+const KNOWN_MISMATCHES: &[(&str, &str)] = &[
+  ("types", "DefinitelyTyped"),
+  ("google-cloud", "googleapis"),
+  ("fluentui", "microsoft"),
+  ("vscode", "microsoft"),
+];
+
+// Heads-up for a package whose declared repository doesn't look like it belongs to the publisher.
+// Only a reporting hint: forks, renames and transfers trip it too.
+pub fn repo_mismatch(package: &str, repository_url: &str, repo_missing: bool) -> Option<String> {
+  if repo_missing {
+    return Some("declared repository not found".to_owned());
+  }
+
+  let scope = package.strip_prefix('@')?.split_once('/')?.0;
+  let owner = github_owner(repository_url)?;
+  if related(scope, owner) {
+    return None;
+  }
+
+  Some(format!("scope @{} published from github.com/{}", scope, owner))
+}
+
+fn related(scope: &str, owner: &str) -> bool {
+  let scope = simplify(scope);
+  let owner = simplify(owner);
+
+  // "getsentry" for @sentry, "vuejs" for @vue and the like
+  scope.contains(&owner)
+    || owner.contains(&scope)
+    || KNOWN_MISMATCHES
+      .iter()
+      .any(|(known_scope, known_owner)| simplify(known_scope) == scope && simplify(known_owner) == owner)
+}
+
+fn github_owner(url: &str) -> Option<&str> {
+  let (_, path) = url.split_once("github.com")?;
+  path
+    .trim_start_matches([':', '/'])
+    .split('/')
+    .next()
+    .filter(|owner| !owner.is_empty())
+}
+
+fn simplify(name: &str) -> String {
+  name
+    .chars()
+    .filter(|c| c.is_ascii_alphanumeric())
+    .map(|c| c.to_ascii_lowercase())
+    .collect()
+}
@@ -0,0 +1,102 @@
+use crate::{
+  license_data::LicenseData,
+  types::{Ecosystem, ReportRow},
+};
+use std::collections::HashMap;
+
+// Hosts whose URLs name the repository in their first two path segments (GitLab may nest groups deeper)
+const CODE_HOSTS: &[&str] = &["github.com", "gitlab.com", "bitbucket.org", "codeberg.org"];
+// Where a GitLab link leaves the project path for a file or directory in it
+const GITLAB_DEEP_LINKS: &[&str] = &["-", "blob", "tree", "raw"];
+
+// Rows of a sheet as --collapse-by-repo lays them out: a repository with the rows sharing it and their license,
+// or a row standing on its own
+pub struct RepoGroup<'a> {
+  // None for a single row
+  pub repository: Option<String>,
+  pub rows: Vec<&'a ReportRow>,
+}
+
+// Rows in their order, except that every row sharing an earlier one's repository and license follows it. Only
+// rows of the same ecosystem with a license and a repository on a known code host are grouped; rows of one
+// repository with different licenses end up in different groups.
+pub fn group_rows<'a>(rows: impl IntoIterator<Item = &'a ReportRow>, data: &LicenseData) -> Vec<RepoGroup<'a>> {
+  let mut groups: Vec<RepoGroup<'a>> = Vec::new();
+  let mut index: HashMap<(Ecosystem, String, String), usize> = HashMap::new();
+  for row in rows {
+    let Some(key) = group_key(row, data) else {
+      groups.push(RepoGroup {
+        repository: None,
+        rows: vec![row],
+      });
+      continue;
+    };
+    match index.get(&key) {
+      Some(idx) => groups[*idx].rows.push(row),
+      None => {
+        index.insert(key.clone(), groups.len());
+        groups.push(RepoGroup {
+          repository: Some(key.1),
+          rows: vec![row],
+        });
+      },
+    }
+  }
+
+  for group in groups.iter_mut().filter(|group| group.rows.len() < 2) {
+    group.repository = None;
+  }
+  groups
+}
+
+fn group_key(row: &ReportRow, data: &LicenseData) -> Option<(Ecosystem, String, String)> {
+  let license = data.normalize(row.license.as_deref()?);
+  Some((row.ecosystem, row_repository(row)?, license))
+}
+
+// The repository a row's license link or homepage points into
+pub fn row_repository(row: &ReportRow) -> Option<String> {
+  row
+    .license_url
+    .as_deref()
+    .and_then(repository_of)
+    .or_else(|| repository_of(&row.homepage))
+}
+
+// `https://host/owner/repo` for a link into a repository on a known code host: git+, git:// and scp-like ssh
+// forms, .git suffixes, any case, deep links to files or directories. None for anything else, registry pages
+// included, so packages that only link to their own page never group.
+pub fn repository_of(url: &str) -> Option<String> {
+  let url = url.trim().trim_start_matches("git+");
+  let rest = match url.strip_prefix("git@") {
+    Some(rest) => rest.replacen(':', "/", 1),
+    None => ["https://", "http://", "git://", "ssh://"]
+      .iter()
+      .find_map(|scheme| url.strip_prefix(scheme))?
+      .to_owned(),
+  };
+  let rest = rest.strip_prefix("git@").unwrap_or(&rest);
+  let path = rest.split(['#', '?']).next().unwrap_or_default().to_lowercase();
+
+  let mut parts = path.split('/').filter(|part| !part.is_empty());
+  let host = parts.next()?;
+  let host = host.strip_prefix("www.").unwrap_or(host);
+  if !CODE_HOSTS.contains(&host) {
+    return None;
+  }
+  let segments = match host {
+    "gitlab.com" => parts
+      .take_while(|part| !GITLAB_DEEP_LINKS.contains(part))
+      .collect::<Vec<_>>(),
+    _ => parts.take(2).collect(),
+  };
+  if segments.len() < 2 {
+    return None;
+  }
+  let repository = segments.join("/");
+  Some(format!(
+    "https://{}/{}",
+    host,
+    repository.strip_suffix(".git").unwrap_or(&repository)
+  ))
+}
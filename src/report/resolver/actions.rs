@@ -0,0 +1,56 @@
+use super::Resolver;
+use crate::{
+  deps::actions_deps,
+  diagnostics::Category,
+  types::{DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::Result;
+
+impl Resolver<'_> {
+  // An action is a GitHub repository, or a directory of one; the license is the repository's, as GitHub
+  // detected it on the default branch rather than at the ref the workflow pins
+  pub async fn resolve_action_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let repo = actions_deps::repository(&dep.name).unwrap_or_else(|| dep.name.clone());
+    let repository = format!("https://github.com/{}", repo);
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::GithubActions,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: repository.clone(),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+
+    row.license = self.fetch_github_license(&repo).await;
+    if row.license.is_some() {
+      row.license_confidence = Some(LicenseConfidence::Detected);
+      row.provenance.push("license: GitHub license API".to_owned());
+    }
+    if let Some((license_url, _)) = self.find_license_url(&repository, None, &mut row.provenance).await? {
+      row.provenance.push("license file: repository root".to_owned());
+      row.license_url = Some(license_url);
+    }
+    if row.license.is_none() {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No license for {}@{}", dep.name, dep.version),
+      );
+    }
+
+    Ok(row)
+  }
+}
@@ -0,0 +1,109 @@
+use super::Resolver;
+use crate::{
+  diagnostics::Category,
+  types::{DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::Result;
+
+const CRATES_IO_API: &str = "https://crates.io/api/v1/crates";
+
+impl Resolver<'_> {
+  // The license crates.io has for the version, a requirement without a lock file gets the crate's newest one
+  pub async fn resolve_rust_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let url = match dep.version.as_str() {
+      "*" => format!("{}/{}", CRATES_IO_API, dep.name),
+      version => format!("{}/{}/{}", CRATES_IO_API, dep.name, version),
+    };
+
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Cargo,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: format!("https://crates.io/crates/{}", dep.name),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+
+    let response = match self.client.get_json(&url, None).await {
+      Ok(response) => response,
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::FetchFailure,
+          format!("Can't fetch {}@{} from crates.io: {:#}", dep.name, dep.version, err),
+        );
+        row.provenance.push("failed: crates.io unreachable".to_owned());
+        return Ok(row);
+      },
+    };
+    let info = match response.status {
+      reqwest::StatusCode::OK => response.json::<serde_json::Value>().ok(),
+      _ => None,
+    };
+    let Some(info) = info else {
+      self.diagnostics.warn(
+        Category::FetchFailure,
+        format!(
+          "Can't fetch {}@{} from crates.io: {}{}",
+          dep.name,
+          dep.version,
+          response.status,
+          response.dump_ref()
+        ),
+      );
+      row
+        .provenance
+        .push(format!("failed: crates.io answered {}", response.status));
+      return Ok(row);
+    };
+
+    // `{"version": {..}}` for one version, `{"crate": {..}, "versions": [newest, ..]}` for the crate
+    let version = info.get("version").or_else(|| info.get("versions")?.get(0));
+    let field = |name: &str| {
+      version
+        .and_then(|version| version.get(name))
+        .or_else(|| info.get("crate")?.get(name))
+        .and_then(|value| value.as_str())
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+    };
+    row.provenance.push("metadata: crates.io".to_owned());
+    if let Some(num) = version.and_then(|version| version.get("num")?.as_str()) {
+      row.version = num.to_owned();
+    }
+    row.license = field("license");
+    row.license_confidence = row.license.as_ref().map(|_| LicenseConfidence::Declared);
+    if row.license.is_none() {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No license on crates.io for {}@{}", dep.name, row.version),
+      );
+    }
+
+    let repository = field("repository").map(|repository| repository.trim_end_matches(".git").to_owned());
+    if let Some(homepage) = field("homepage").or_else(|| repository.clone()) {
+      row.homepage = homepage;
+    }
+    if let Some(repository) = repository.filter(|repository| repository.starts_with("https://github.com/")) {
+      if let Some((license_url, _)) = self.find_license_url(&repository, None, &mut row.provenance).await? {
+        row.provenance.push("license file: repository root".to_owned());
+        row.license_url = Some(license_url);
+      }
+    }
+
+    Ok(row)
+  }
+}
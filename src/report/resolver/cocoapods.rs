@@ -0,0 +1,98 @@
+use super::Resolver;
+use crate::{
+  diagnostics::Category,
+  report::repo_groups,
+  types::{DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::Result;
+
+const COCOAPODS_TRUNK: &str = "https://trunk.cocoapods.org/api/v1";
+// Podspec license types for the common licenses, compared case-insensitively
+const POD_LICENSES: &[(&str, &str)] = &[
+  ("MIT License", "MIT"),
+  ("The MIT License", "MIT"),
+  ("Apache 2.0", "Apache-2.0"),
+  ("Apache 2", "Apache-2.0"),
+  ("Apache License, Version 2.0", "Apache-2.0"),
+  ("Apache License 2.0", "Apache-2.0"),
+  ("BSD 3-Clause", "BSD-3-Clause"),
+  ("New BSD", "BSD-3-Clause"),
+  ("BSD 2-Clause", "BSD-2-Clause"),
+  ("zlib", "Zlib"),
+];
+
+impl Resolver<'_> {
+  // The podspec trunk has for the version: the license it declares, and the repository it's built from, probed for
+  // the license file like for gems
+  pub async fn resolve_cocoapods_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Cocoapods,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: format!("https://cocoapods.org/pods/{}", dep.name),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+
+    let api = format!("{}/pods/{}/specs/{}", COCOAPODS_TRUNK, dep.name, dep.version);
+    let Some(spec) = self.fetch_package_json(dep, &api, &mut row).await else {
+      return Ok(row);
+    };
+    row.provenance.push("metadata: CocoaPods trunk".to_owned());
+    if let Some(homepage) = spec
+      .get("homepage")
+      .and_then(|homepage| homepage.as_str())
+      .filter(|homepage| !homepage.is_empty())
+    {
+      row.homepage = homepage.to_owned();
+    }
+
+    // `"MIT"`, or `{ "type": "MIT", "file": "LICENSE" }`
+    let license = spec
+      .get("license")
+      .and_then(|license| license.as_str().or_else(|| license.get("type")?.as_str()))
+      .map(str::trim)
+      .filter(|license| !license.is_empty());
+    row.license = license.map(|license| {
+      POD_LICENSES
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(license))
+        .map_or(license, |(_, id)| *id)
+        .to_owned()
+    });
+    match row.license {
+      Some(_) => row.license_confidence = Some(LicenseConfidence::Declared),
+      None => self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No license for {}@{}", dep.name, dep.version),
+      ),
+    }
+
+    let repository = spec
+      .get("source")
+      .and_then(|source| source.get("git")?.as_str())
+      .and_then(repo_groups::repository_of);
+    if let Some(repository) = repository.filter(|repository| repository.starts_with("https://github.com/")) {
+      if let Some((license_url, _)) = self.find_license_url(&repository, None, &mut row.provenance).await? {
+        row.provenance.push("license file: repository root".to_owned());
+        row.license_url = Some(license_url);
+      }
+    }
+
+    Ok(row)
+  }
+}
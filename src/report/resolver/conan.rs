@@ -0,0 +1,146 @@
+use super::Resolver;
+use crate::{
+  deps::{conan_deps, yaml},
+  diagnostics::Category,
+  report::repo_groups,
+  types::{DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::{Context, Result};
+
+// ConanCenter's recipes: config.yml maps each version to the folder holding its conanfile.py
+const CONAN_CENTER_INDEX: &str = "https://raw.githubusercontent.com/conan-io/conan-center-index/master/recipes";
+
+impl Resolver<'_> {
+  // The ConanCenter recipe of the package: the license its conanfile.py declares and its homepage, probed for the
+  // license file when it's on GitHub. A user/channel reference is looked up by name all the same, it's most often
+  // a team's build of the ConanCenter package.
+  pub async fn resolve_conan_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Conan,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: format!("https://conan.io/center/recipes/{}", dep.name),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+    if let Some(channel) = self.conan_channels.get(&dep.name) {
+      row.provenance.push(format!("referenced as @{}", channel));
+    }
+
+    let config_url = format!("{}/{}/config.yml", CONAN_CENTER_INDEX, dep.name);
+    let Some(config) = self.fetch_recipe_file(dep, &config_url, &mut row).await else {
+      return Ok(row);
+    };
+    let versions = yaml::Node::parse(&config)
+      .with_context(|| format!("Failed to parse {}", config_url))?
+      .get("versions")
+      .map(|versions| {
+        versions
+          .entries
+          .iter()
+          .filter_map(|(version, entry)| Some((version.clone(), entry.str("folder")?.to_owned())))
+          .collect::<Vec<_>>()
+      })
+      .unwrap_or_default();
+    // Ranges and versions ConanCenter dropped take the first folder listed, the newest by the index's convention
+    let folder = match versions.iter().find(|(version, _)| *version == dep.version) {
+      Some((_, folder)) => folder.clone(),
+      None => {
+        let Some((_, folder)) = versions.first() else {
+          self.diagnostics.warn(
+            Category::UnknownLicense,
+            format!("{} lists no versions for {}", config_url, dep.name),
+          );
+          return Ok(row);
+        };
+        row.provenance.push(format!(
+          "version {} not in ConanCenter, recipe folder {}",
+          dep.version, folder
+        ));
+        folder.clone()
+      },
+    };
+
+    let recipe_url = format!("{}/{}/{}/conanfile.py", CONAN_CENTER_INDEX, dep.name, folder);
+    let Some(recipe) = self.fetch_recipe_file(dep, &recipe_url, &mut row).await else {
+      return Ok(row);
+    };
+    row.provenance.push("metadata: ConanCenter recipe".to_owned());
+    if let Some(homepage) = conan_deps::recipe_homepage(&recipe) {
+      row.homepage = homepage;
+    }
+    row.license = conan_deps::recipe_license(&recipe);
+    match row.license {
+      Some(_) => row.license_confidence = Some(LicenseConfidence::Declared),
+      None => self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No license for {}@{}", dep.name, dep.version),
+      ),
+    }
+
+    if let Some(repository) =
+      repo_groups::repository_of(&row.homepage).filter(|repository| repository.starts_with("https://github.com/"))
+    {
+      if let Some((license_url, _)) = self.find_license_url(&repository, None, &mut row.provenance).await? {
+        row.provenance.push("license file: repository root".to_owned());
+        row.license_url = Some(license_url);
+      }
+    }
+
+    Ok(row)
+  }
+
+  async fn fetch_recipe_file(&self, dep: &DepsEntry, url: &str, row: &mut ReportRow) -> Option<String> {
+    let response = match self.client.get(url).await {
+      Ok(response) => response,
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::FetchFailure,
+          format!("Can't fetch {}@{} from {}: {:#}", dep.name, dep.version, url, err),
+        );
+        row.provenance.push("failed: ConanCenter unreachable".to_owned());
+        return None;
+      },
+    };
+    match response.status {
+      reqwest::StatusCode::OK => Some(response.text()),
+      reqwest::StatusCode::NOT_FOUND => {
+        self.diagnostics.warn(
+          Category::UnknownLicense,
+          format!("{} is not a ConanCenter recipe, its license isn't looked up", dep.name),
+        );
+        row.provenance.push("not on ConanCenter".to_owned());
+        None
+      },
+      status => {
+        self.diagnostics.warn(
+          Category::FetchFailure,
+          format!(
+            "Can't fetch {}@{} from {}: {}{}",
+            dep.name,
+            dep.version,
+            url,
+            status,
+            response.dump_ref()
+          ),
+        );
+        row.provenance.push(format!("failed: ConanCenter answered {}", status));
+        None
+      },
+    }
+  }
+}
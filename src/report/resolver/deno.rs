@@ -0,0 +1,308 @@
+use super::Resolver;
+use crate::{
+  deps::{
+    audit::is_license_file_name,
+    deno_deps::{self, ANY_VERSION},
+  },
+  diagnostics::Category,
+  report::repo_groups,
+  types::{DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+  version::{self, PrereleasePolicy, VersionReq},
+};
+use anyhow::Result;
+
+const JSR: &str = "https://jsr.io";
+const JSR_API: &str = "https://api.jsr.io";
+// Files of a published JSR package that can declare its license, in the order Deno reads them
+const JSR_CONFIGS: &[&str] = &["/jsr.json", "/jsr.jsonc", "/deno.json", "/deno.jsonc", "/package.json"];
+// deno.land/x records the repository and ref each module version was published from
+const DENO_LAND_CDN: &str = "https://cdn.deno.land";
+
+impl Resolver<'_> {
+  // A JSR package by the license its published config declares, a URL module by the repository it comes from.
+  // Either is probed for the license file when its repository is known.
+  pub async fn resolve_deno_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Deno,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: deno_deps::homepage(&dep.name),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+
+    // URL modules are their own homepage
+    let jsr = row.homepage != dep.name;
+    let repository = match jsr {
+      true => match self.resolve_jsr_package(dep, &mut row).await {
+        Some(repository) => repository,
+        None => return Ok(row),
+      },
+      false => self.deno_module_repository(dep, &mut row).await,
+    };
+    if let Some(repository) = &repository {
+      if let Some(repo) = repository
+        .strip_prefix("https://github.com/")
+        .filter(|_| row.license.is_none())
+      {
+        row.license = self.fetch_github_license(repo).await;
+        if row.license.is_some() {
+          row.license_confidence = Some(LicenseConfidence::Detected);
+          row.provenance.push("license: GitHub license API".to_owned());
+        }
+      }
+      if row.license_url.is_none() {
+        if let Some((license_url, _)) = self.find_license_url(repository, None, &mut row.provenance).await? {
+          row.provenance.push("license file: repository root".to_owned());
+          row.license_url = Some(license_url);
+        }
+      }
+    }
+    // A URL module without a repository was warned about already
+    if row.license.is_none() && (jsr || repository.is_some()) {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No license for {}@{}", dep.name, row.version),
+      );
+    }
+
+    Ok(row)
+  }
+
+  // The version the requirement picks from the package's meta.json, then the license field of the config published
+  // with it and its license file. The repository linked in the package settings, if any, is returned; None when
+  // the package couldn't be looked up at all.
+  async fn resolve_jsr_package(&self, dep: &DepsEntry, row: &mut ReportRow) -> Option<Option<String>> {
+    let meta_url = format!("{}/{}/meta.json", JSR, dep.name);
+    let meta = self.fetch_deno_json(dep, &meta_url, "JSR", row).await?;
+    let Some(version) = jsr_version(&meta, &dep.version, self.prerelease) else {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!(
+          "No version of {} on JSR matches {} (pre-releases: {})",
+          dep.name, dep.version, self.prerelease
+        ),
+      );
+      row.provenance.push(format!(
+        "no JSR version matches {} (pre-releases: {})",
+        dep.version, self.prerelease
+      ));
+      return None;
+    };
+    row.provenance.push("metadata: JSR".to_owned());
+    if version != dep.version {
+      row.provenance.push(format!(
+        "version: resolved {} to {}{} (pre-releases: {})",
+        dep.version,
+        version,
+        version::describe_prerelease(&version),
+        self.prerelease
+      ));
+    }
+
+    let files_url = format!("{}/{}/{}_meta.json", JSR, dep.name, version);
+    if let Some(files) = self.fetch_deno_json(dep, &files_url, "JSR", row).await {
+      let paths = files
+        .get("manifest")
+        .and_then(serde_json::Value::as_object)
+        .map(|manifest| manifest.keys().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+      for config in JSR_CONFIGS
+        .iter()
+        .filter(|config| paths.iter().any(|path| path == *config))
+      {
+        let url = format!("{}/{}/{}{}", JSR, dep.name, version, config);
+        if let Some(license) = self.fetch_jsr_license(&url).await {
+          row.license = Some(license);
+          row.license_confidence = Some(LicenseConfidence::Declared);
+          row.provenance.push(format!(
+            "license: {} of the published package",
+            config.trim_start_matches('/')
+          ));
+          break;
+        }
+      }
+      // Only the package root, like the repository probe
+      if let Some(license_file) = paths
+        .iter()
+        .find(|path| path.matches('/').count() == 1 && is_license_file_name(path.trim_start_matches('/')))
+      {
+        row.license_url = Some(format!("{}/{}/{}{}", JSR, dep.name, version, license_file));
+        row.provenance.push("license file: published package".to_owned());
+      }
+    }
+
+    // The repository is a package setting, it isn't part of what was published
+    let (scope, package) = dep.name.trim_start_matches('@').split_once('/')?;
+    let package_url = format!("{}/scopes/{}/packages/{}", JSR_API, scope, package);
+    let repository = self
+      .fetch_deno_json(dep, &package_url, "JSR", row)
+      .await
+      .and_then(|package| {
+        let repository = package.get("githubRepository")?;
+        Some(format!(
+          "https://github.com/{}/{}",
+          repository.get("owner")?.as_str()?,
+          repository.get("name")?.as_str()?
+        ))
+      });
+    Some(repository)
+  }
+
+  // The `license` of a config file published with a JSR package; best effort, the repository is the fallback
+  async fn fetch_jsr_license(&self, url: &str) -> Option<String> {
+    let response = self.client.get_json(url, None).await.ok()?;
+    if response.status != reqwest::StatusCode::OK {
+      return None;
+    }
+    let config = serde_json::from_str::<serde_json::Value>(&deno_deps::strip_jsonc(&response.text())).ok()?;
+    config
+      .get("license")?
+      .as_str()
+      .map(str::trim)
+      .filter(|license| !license.is_empty())
+      .map(str::to_owned)
+  }
+
+  // A URL module's repository: the URL itself when it's on a code host, else where deno.land/x says the version
+  // was published from
+  async fn deno_module_repository(&self, dep: &DepsEntry, row: &mut ReportRow) -> Option<String> {
+    if let Some(repository) = repo_groups::repository_of(&dep.name) {
+      row.provenance.push("metadata: module URL".to_owned());
+      return Some(repository);
+    }
+    let Some(path) = dep.name.strip_prefix("https://deno.land/") else {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!(
+          "{} isn't on deno.land or a code host we know; its license isn't looked up",
+          dep.name
+        ),
+      );
+      return None;
+    };
+    let module = path.strip_prefix("x/").unwrap_or(path);
+
+    let version = match dep.version.as_str() {
+      ANY_VERSION => {
+        let url = format!("{}/{}/meta/versions.json", DENO_LAND_CDN, module);
+        let versions = self.fetch_deno_json(dep, &url, "deno.land", row).await?;
+        let latest = versions.get("latest")?.as_str()?.to_owned();
+        row.provenance.push(format!("unversioned import, latest is {}", latest));
+        latest
+      },
+      version => version.to_owned(),
+    };
+    let url = format!("{}/{}/versions/{}/meta/meta.json", DENO_LAND_CDN, module, version);
+    let meta = self.fetch_deno_json(dep, &url, "deno.land", row).await?;
+    let upload = meta.get("upload_options")?;
+    let repository = upload.get("repository")?.as_str()?;
+    match upload.get("type").and_then(serde_json::Value::as_str) {
+      Some("github") => {
+        row.provenance.push("metadata: deno.land/x".to_owned());
+        Some(format!("https://github.com/{}", repository))
+      },
+      kind => {
+        self.diagnostics.warn(
+          Category::UnknownLicense,
+          format!(
+            "{} was published from {} ({}), its license isn't looked up",
+            dep.name,
+            repository,
+            kind.unwrap_or("unknown host")
+          ),
+        );
+        None
+      },
+    }
+  }
+
+  async fn fetch_deno_json(
+    &self,
+    dep: &DepsEntry,
+    url: &str,
+    registry: &str,
+    row: &mut ReportRow,
+  ) -> Option<serde_json::Value> {
+    let response = match self.client.get_json(url, None).await {
+      Ok(response) => response,
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::FetchFailure,
+          format!("Can't fetch {}@{} from {}: {:#}", dep.name, dep.version, url, err),
+        );
+        row.provenance.push(format!("failed: {} unreachable", registry));
+        return None;
+      },
+    };
+    match response.status {
+      reqwest::StatusCode::OK => match response.json::<serde_json::Value>() {
+        Ok(body) => Some(body),
+        Err(err) => {
+          self.diagnostics.warn(
+            Category::FetchFailure,
+            format!("Can't parse {}: {:#}{}", url, err, response.dump_ref()),
+          );
+          None
+        },
+      },
+      reqwest::StatusCode::NOT_FOUND => {
+        self.diagnostics.warn(
+          Category::UnknownLicense,
+          format!(
+            "{}@{} is not on {}, its license isn't looked up",
+            dep.name, dep.version, registry
+          ),
+        );
+        row.provenance.push(format!("not on {}", registry));
+        None
+      },
+      status => {
+        self.diagnostics.warn(
+          Category::FetchFailure,
+          format!(
+            "Can't fetch {}@{} from {}: {}{}",
+            dep.name,
+            dep.version,
+            url,
+            status,
+            response.dump_ref()
+          ),
+        );
+        row.provenance.push(format!("failed: {} answered {}", registry, status));
+        None
+      },
+    }
+  }
+}
+
+// The version of a JSR package `requested` stands for: itself when published, else the newest non-yanked one the
+// requirement matches under the policy (a requirement that doesn't parse takes the latest)
+fn jsr_version(meta: &serde_json::Value, requested: &str, policy: PrereleasePolicy) -> Option<String> {
+  let versions = meta.get("versions")?.as_object()?;
+  if versions.contains_key(requested) {
+    return Some(requested.to_owned());
+  }
+  let Ok(requirement) = VersionReq::parse(requested, Ecosystem::Deno) else {
+    return meta.get("latest")?.as_str().map(str::to_owned);
+  };
+  let candidates = versions
+    .iter()
+    .filter(|(_, info)| !info.get("yanked").and_then(serde_json::Value::as_bool).unwrap_or(false))
+    .map(|(version, _)| version.as_str());
+  version::highest_match(candidates, &requirement, policy).map(str::to_owned)
+}
@@ -0,0 +1,195 @@
+use super::{LicenseProbe, Resolver};
+use crate::{
+  diagnostics::Category,
+  report::{
+    constants::LICENSE_FILES,
+    go_repo::{self, ModuleRepo},
+    license_probe::PROBE_PREFIX,
+  },
+  types::{DepsEntry, Ecosystem, LicenseConfidence, LicenseLevel, ReportRow},
+};
+use anyhow::Result;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+  static ref LICENSE_REGEX: Regex =
+    Regex::new(r###"<div id="#lic-0">(.*?)</div>"###).expect("Failed to compile license regex");
+}
+
+// Serves the module zips whose size --sizes records
+const GO_PROXY: &str = "https://proxy.golang.org";
+
+impl Resolver<'_> {
+  pub async fn resolve_go_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let (license, canonical) = self.fetch_go_license(dep).await?;
+    if let Some(canonical) = &canonical {
+      self.diagnostics.warn(
+        Category::ScanIssue,
+        format!("{} redirects to {}, the require uses a stale path", dep.name, canonical),
+      );
+    }
+
+    // pkg.go.dev renders the text but isn't its source, lacks versions it hasn't indexed and may attribute the root
+    // license to a module nested in a larger repository; its tab is only the link when no file is found
+    let mut provenance = Vec::new();
+    let license_page = license
+      .as_ref()
+      .map(|_| format!("https://pkg.go.dev/{}?tab=licenses", dep.name));
+    let license_url = match self.find_go_license_file(dep, &mut provenance).await? {
+      Some(url) => {
+        if let Some(page) = &license_page {
+          provenance.push(format!("license page: {}", page));
+        }
+        Some(url)
+      },
+      None => license_page,
+    };
+    let size = match self.sizes {
+      true => self.fetch_go_size(dep).await,
+      false => None,
+    };
+
+    Ok(ReportRow {
+      ecosystem: Ecosystem::Go,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: canonical.unwrap_or_else(|| format!("https://pkg.go.dev/{}", dep.name)),
+      license_confidence: license.as_ref().map(|_| LicenseConfidence::Scraped),
+      license,
+      license_url,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size,
+      provenance,
+    })
+  }
+
+  // The license file in the module's repository, at the ref the version was published from when the repository
+  // still has it and on the default branch otherwise
+  async fn find_go_license_file(&self, dep: &DepsEntry, provenance: &mut Vec<String>) -> Result<Option<String>> {
+    let repo = match go_repo::github_repo(&dep.name) {
+      Some(repo) => Some(repo),
+      None => self.resolve_vanity_import(&dep.name).await,
+    };
+    let Some(repo) = repo else {
+      return Ok(None);
+    };
+    let directory = Some(repo.subpath.as_str()).filter(|subpath| !subpath.is_empty());
+
+    let pinned = match go_repo::version_ref(&dep.version, &repo.subpath) {
+      Some(git_ref) => match self
+        .probe_files(&repo.url, &git_ref, directory, LICENSE_FILES, provenance)
+        .await?
+      {
+        LicenseProbe::Found(url, level) => {
+          provenance.push(format!("license ref: {}", git_ref));
+          Some((url, level))
+        },
+        // The tag may be gone or never pushed, the default branch is still better than nothing
+        LicenseProbe::Missing => None,
+        LicenseProbe::Stopped => return Ok(None),
+      },
+      None => None,
+    };
+    let found = match pinned {
+      Some(found) => Some(found),
+      None => self.find_license_url(&repo.url, directory, provenance).await?,
+    };
+
+    Ok(found.map(|(url, level)| {
+      provenance.push(match (level, directory) {
+        (LicenseLevel::Directory, Some(directory)) => format!("license file: module directory {}", directory),
+        _ => "license file: repository root".to_owned(),
+      });
+      url
+    }))
+  }
+
+  // What the go command asks a vanity import host for; a failed lookup leaves the module to pkg.go.dev
+  async fn resolve_vanity_import(&self, module: &str) -> Option<ModuleRepo> {
+    let response = self
+      .client
+      .get_prefix(&format!("https://{}?go-get=1", module), PROBE_PREFIX)
+      .await
+      .ok()?;
+    if response.status != reqwest::StatusCode::OK {
+      return None;
+    }
+    go_repo::go_import(&response.text(), module)
+  }
+
+  // The license pkg.go.dev reports, plus the module page it redirected to (a module path in its canonical casing)
+  async fn fetch_go_license(&self, dep: &DepsEntry) -> Result<(Option<String>, Option<String>)> {
+    let lic_url = format!("https://pkg.go.dev/{}?tab=licenses", dep.name);
+
+    let resp = self.client.get(&lic_url).await?;
+    if resp.status == reqwest::StatusCode::OK {
+      let canonical = resp
+        .url
+        .split_once('?')
+        .map(|(page, _)| page.to_owned())
+        .filter(|page| !page.ends_with(&format!("/{}", dep.name)));
+      let response = resp.text();
+
+      if let Some(license) = go_license_name(&response) {
+        return Ok((Some(license), canonical));
+      }
+
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("Can't found license for {}{}", dep.name, resp.dump_ref()),
+      );
+      return Ok((None, canonical));
+    }
+
+    Ok((None, None))
+  }
+
+  // Content-Length of the zip the module proxy serves for the version, blank on the row when it doesn't say
+  async fn fetch_go_size(&self, dep: &DepsEntry) -> Option<u64> {
+    let url = format!(
+      "{}/{}/@v/{}.zip",
+      GO_PROXY,
+      go_repo::proxy_escape(&dep.name),
+      go_repo::proxy_escape(&dep.version)
+    );
+    match self.client.head(&url).await {
+      Ok(response) if response.status == reqwest::StatusCode::OK => response.content_length,
+      Ok(response) => {
+        self.diagnostics.warn(
+          Category::FetchFailure,
+          format!(
+            "Can't get the size of {}@{}: the Go proxy answered {}{}",
+            dep.name,
+            dep.version,
+            response.status,
+            response.dump_ref()
+          ),
+        );
+        None
+      },
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::FetchFailure,
+          format!("Can't get the size of {}@{}: {:#}", dep.name, dep.version, err),
+        );
+        None
+      },
+    }
+  }
+}
+
+// The first license pkg.go.dev lists on the licenses tab
+pub fn go_license_name(page: &str) -> Option<String> {
+  LICENSE_REGEX.captures(page).map(|captures| captures[1].to_owned())
+}
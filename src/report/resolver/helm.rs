@@ -0,0 +1,187 @@
+use super::Resolver;
+use crate::{
+  deps::yaml,
+  diagnostics::Category,
+  report::repo_groups,
+  types::{DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+  version::{self, VersionReq},
+};
+use anyhow::Result;
+use std::sync::Arc;
+
+impl Resolver<'_> {
+  // The chart's entry in its repository's index.yaml: the license an annotation declares, its home, and its
+  // sources, the first one on a code host probed for the license file like the JS path does
+  pub async fn resolve_helm_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let repository = self.helm_repositories.get(&dep.name).cloned().unwrap_or_default();
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Helm,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: repository.clone(),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+    // oci:// registries have no index.yaml, `@name` and `alias:name` are a `helm repo add` on someone's machine
+    if !repository.starts_with("https://") && !repository.starts_with("http://") {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!(
+          "{}@{} comes from {}, which has no index.yaml to look it up in",
+          dep.name,
+          dep.version,
+          match repository.is_empty() {
+            true => "an unknown chart repository",
+            false => &repository,
+          }
+        ),
+      );
+      return Ok(row);
+    }
+    row.provenance.push(format!("chart repository: {}", repository));
+
+    // Charts from the same repository share its index.yaml, fetched once
+    let index = match self.helm_index(&repository) {
+      Some(index) => index,
+      None => {
+        let index = self.fetch_helm_index(&repository).await.map(Arc::new);
+        self
+          .helm_indexes
+          .lock()
+          .expect("Helm index cache lock poisoned")
+          .insert(repository.clone(), index.clone());
+        index
+      },
+    };
+    let Some(index) = index else {
+      row.provenance.push("failed: index.yaml unavailable".to_owned());
+      return Ok(row);
+    };
+    let entries = index
+      .get("entries")
+      .and_then(|entries| entries.get(&dep.name))
+      .map_or(&[][..], |chart| &chart.items);
+    // Chart.lock versions are exact, Chart.yaml ones can be ranges
+    let entry = entries
+      .iter()
+      .find(|entry| entry.str("version") == Some(dep.version.as_str()))
+      .or_else(|| {
+        let requirement = VersionReq::parse(&dep.version, Ecosystem::Helm).ok()?;
+        let versions = entries.iter().filter_map(|entry| entry.str("version"));
+        let version = version::highest_match(versions, &requirement, self.prerelease)?;
+        entries.iter().find(|entry| entry.str("version") == Some(version))
+      });
+    let Some(entry) = entry else {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No version of {} in {} matches {}", dep.name, repository, dep.version),
+      );
+      row.provenance.push(format!("no chart version matches {}", dep.version));
+      return Ok(row);
+    };
+    row.provenance.push("metadata: chart repository index.yaml".to_owned());
+    let version = entry.str("version").unwrap_or_default();
+    if version != dep.version {
+      row.provenance.push(format!("resolved {} to {}", dep.version, version));
+    }
+    // Artifact Hub's annotation, or the one Bitnami puts on its charts
+    let declared = entry.get("annotations").and_then(|annotations| {
+      annotations
+        .str("artifacthub.io/license")
+        .or_else(|| annotations.str("licenses"))
+    });
+    if let Some(license) = declared {
+      row.license = Some(license.to_owned());
+      row.license_confidence = Some(LicenseConfidence::Declared);
+      row.provenance.push("license: chart annotation".to_owned());
+    }
+    let home = entry.str("home").map(str::to_owned);
+    let source_repository = entry
+      .items("sources")
+      .iter()
+      .filter_map(|source| source.value.as_deref())
+      .chain(home.as_deref())
+      .find_map(repo_groups::repository_of);
+    if let Some(home) = home {
+      row.homepage = home;
+    }
+
+    if let Some(repository) = &source_repository {
+      if let Some(repo) = repository
+        .strip_prefix("https://github.com/")
+        .filter(|_| row.license.is_none())
+      {
+        row.license = self.fetch_github_license(repo).await;
+        if row.license.is_some() {
+          row.license_confidence = Some(LicenseConfidence::Detected);
+          row.provenance.push("license: GitHub license API".to_owned());
+        }
+      }
+      if let Some((license_url, _)) = self.find_license_url(repository, None, &mut row.provenance).await? {
+        row.provenance.push("license file: repository root".to_owned());
+        row.license_url = Some(license_url);
+      }
+    }
+    if row.license.is_none() {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No license for {}@{}", dep.name, dep.version),
+      );
+    }
+
+    Ok(row)
+  }
+
+  // The repository's index.yaml when it was fetched before, `Some(None)` when that failed
+  fn helm_index(&self, repository: &str) -> Option<Option<Arc<yaml::Node>>> {
+    self
+      .helm_indexes
+      .lock()
+      .expect("Helm index cache lock poisoned")
+      .get(repository)
+      .cloned()
+  }
+
+  async fn fetch_helm_index(&self, repository: &str) -> Option<yaml::Node> {
+    let url = format!("{}/index.yaml", repository);
+    let response = match self.client.get(&url).await {
+      Ok(response) => response,
+      Err(err) => {
+        self
+          .diagnostics
+          .warn(Category::FetchFailure, format!("Can't fetch {}: {:#}", url, err));
+        return None;
+      },
+    };
+    if response.status != reqwest::StatusCode::OK {
+      self.diagnostics.warn(
+        Category::FetchFailure,
+        format!("Can't fetch {}: {}{}", url, response.status, response.dump_ref()),
+      );
+      return None;
+    }
+    match yaml::Node::parse(&response.text()) {
+      Ok(index) => Some(index),
+      Err(err) => {
+        self
+          .diagnostics
+          .warn(Category::ParseFailure, format!("Can't parse {}: {:#}", url, err));
+        None
+      },
+    }
+  }
+}
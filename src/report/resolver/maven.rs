@@ -0,0 +1,192 @@
+use super::Resolver;
+use crate::{
+  deps::maven_deps::{self, Pom},
+  diagnostics::Category,
+  types::{DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::{anyhow, bail, Context, Result};
+
+const MAVEN_CENTRAL: &str = "https://repo1.maven.org/maven2";
+// Parent POMs looked at for an artifact whose own POM lists no <licenses>
+const MAVEN_PARENT_LOOKUPS: usize = 3;
+// POM license names for the common licenses, compared case-insensitively
+const MAVEN_LICENSES: &[(&str, &str)] = &[
+  ("The Apache Software License, Version 2.0", "Apache-2.0"),
+  ("Apache License, Version 2.0", "Apache-2.0"),
+  ("Apache License 2.0", "Apache-2.0"),
+  ("Apache 2.0", "Apache-2.0"),
+  ("Apache 2", "Apache-2.0"),
+  ("The MIT License", "MIT"),
+  ("MIT License", "MIT"),
+  ("MIT", "MIT"),
+  ("Eclipse Public License - v 1.0", "EPL-1.0"),
+  ("Eclipse Public License 1.0", "EPL-1.0"),
+  ("Eclipse Public License - v 2.0", "EPL-2.0"),
+  ("Eclipse Public License v2.0", "EPL-2.0"),
+  ("Eclipse Distribution License - v 1.0", "BSD-3-Clause"),
+  ("New BSD License", "BSD-3-Clause"),
+  ("The BSD 3-Clause License", "BSD-3-Clause"),
+  ("BSD-3-Clause", "BSD-3-Clause"),
+  ("BSD 2-Clause License", "BSD-2-Clause"),
+  ("GNU Lesser General Public License, version 2.1", "LGPL-2.1-only"),
+  (
+    "GNU General Public License, version 2 with the GNU Classpath Exception",
+    "GPL-2.0-only WITH Classpath-exception-2.0",
+  ),
+  (
+    "CDDL + GPLv2 with classpath exception",
+    "CDDL-1.1 OR GPL-2.0-only WITH Classpath-exception-2.0",
+  ),
+  ("Mozilla Public License, Version 2.0", "MPL-2.0"),
+  ("Public Domain, per Creative Commons CC0", "CC0-1.0"),
+];
+
+impl Resolver<'_> {
+  // The <licenses> of the artifact's POM on Maven Central, or of the closest parent POM declaring any: many
+  // projects only state them once in a shared parent
+  pub async fn resolve_java_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Maven,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: format!("https://central.sonatype.com/artifact/{}", dep.name.replace(':', "/")),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+
+    if row.version == "*" {
+      match self.maven_latest_release(&dep.name).await {
+        Ok(version) => row.version = version,
+        Err(err) => {
+          self.diagnostics.warn(
+            Category::FetchFailure,
+            format!(
+              "Can't find the latest release of {} on Maven Central: {:#}",
+              dep.name, err
+            ),
+          );
+          row.provenance.push("failed: Maven Central release unknown".to_owned());
+          return Ok(row);
+        },
+      }
+    }
+
+    let mut coordinates = (dep.name.clone(), row.version.clone());
+    let mut repository = None;
+    for depth in 0..=MAVEN_PARENT_LOOKUPS {
+      let pom = match self.fetch_pom(&coordinates.0, &coordinates.1).await {
+        Ok(pom) => pom,
+        Err(err) => {
+          self.diagnostics.warn(
+            Category::FetchFailure,
+            format!(
+              "Can't fetch the POM of {}@{} from Maven Central: {:#}",
+              coordinates.0, coordinates.1, err
+            ),
+          );
+          if depth == 0 {
+            row.provenance.push("failed: Maven Central POM unavailable".to_owned());
+            return Ok(row);
+          }
+          break;
+        },
+      };
+      if depth == 0 {
+        row.provenance.push("metadata: Maven Central".to_owned());
+        if let Some(homepage) = pom.homepage() {
+          row.homepage = homepage;
+        }
+        repository = pom.repository();
+      }
+
+      if !pom.licenses.is_empty() {
+        let licenses = pom
+          .licenses
+          .iter()
+          .filter_map(|license| {
+            let name = license.name.as_deref().or(license.url.as_deref())?;
+            Some(
+              MAVEN_LICENSES
+                .iter()
+                .find(|(known, _)| known.eq_ignore_ascii_case(name))
+                .map_or(name, |(_, id)| *id),
+            )
+          })
+          .collect::<Vec<_>>();
+        row.license = Some(licenses.join(" OR ")).filter(|license| !license.is_empty());
+        row.license_url = pom.licenses.iter().find_map(|license| license.url.clone());
+        if row.license_url.is_some() {
+          row.provenance.push("license file: POM".to_owned());
+        }
+        if depth > 0 {
+          row.provenance.push(format!("license: parent POM {}", coordinates.0));
+        }
+        break;
+      }
+      match pom.parent {
+        Some(maven_deps::ParentRef {
+          group_id,
+          artifact_id,
+          version: Some(version),
+          ..
+        }) => coordinates = (format!("{}:{}", group_id, artifact_id), version),
+        _ => break,
+      }
+    }
+
+    row.license_confidence = row.license.as_ref().map(|_| LicenseConfidence::Declared);
+    if row.license.is_none() {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No license in the Maven Central POM of {}@{}", dep.name, row.version),
+      );
+    }
+    if let Some(repository) = repository.filter(|repository| repository.starts_with("https://github.com/")) {
+      if row.license_url.is_none() {
+        if let Some((license_url, _)) = self.find_license_url(&repository, None, &mut row.provenance).await? {
+          row.provenance.push("license file: repository root".to_owned());
+          row.license_url = Some(license_url);
+        }
+      }
+    }
+
+    Ok(row)
+  }
+
+  async fn fetch_pom(&self, name: &str, version: &str) -> Result<Pom> {
+    let path = maven_deps::repository_path(name).ok_or_else(|| anyhow!("{} is not groupId:artifactId", name))?;
+    let artifact_id = name.rsplit(':').next().unwrap_or(name);
+    let url = format!("{}/{}/{}/{}-{}.pom", MAVEN_CENTRAL, path, version, artifact_id, version);
+    let response = self.client.get(&url).await?;
+    if response.status != reqwest::StatusCode::OK {
+      bail!("Maven Central answered {}{}", response.status, response.dump_ref());
+    }
+    Pom::parse(&response.text()).with_context(|| format!("Failed to parse {}", url))
+  }
+
+  async fn maven_latest_release(&self, name: &str) -> Result<String> {
+    let path = maven_deps::repository_path(name).ok_or_else(|| anyhow!("{} is not groupId:artifactId", name))?;
+    let url = format!("{}/{}/maven-metadata.xml", MAVEN_CENTRAL, path);
+    let response = self.client.get(&url).await?;
+    if response.status != reqwest::StatusCode::OK {
+      bail!("Maven Central answered {}{}", response.status, response.dump_ref());
+    }
+    maven_deps::latest_release(&response.text())
+      .with_context(|| format!("Failed to parse {}", url))?
+      .ok_or_else(|| anyhow!("{} lists no release", url))
+  }
+}
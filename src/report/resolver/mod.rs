@@ -0,0 +1,424 @@
+mod actions;
+mod cargo;
+mod cocoapods;
+mod conan;
+mod deno;
+mod go;
+mod helm;
+mod maven;
+mod npm;
+mod nuget;
+mod packagist;
+mod pub_dev;
+mod pypi;
+mod rubygems;
+mod swift;
+mod terraform;
+
+use crate::{
+  cache::BranchCache,
+  deps::{php_deps::ComposerPackage, swift_deps::SwiftPin, yaml, yarn_berry::YarnCache},
+  diagnostics::{Category, Diagnostics},
+  http::{MetadataBackend, OfflineError},
+  report::{
+    constants::LICENSE_FILES,
+    events::ResolutionEvent,
+    license_probe::{self, ProbeOutcome, PROBE_PREFIX},
+    notices::NoticesProbe,
+    npm_registry::NpmRegistry,
+    platform::TargetPlatform,
+  },
+  types::{DepsEntry, Ecosystem, LicenseLevel, ReportRow},
+  version::PrereleasePolicy,
+};
+use anyhow::{bail, Context, Result};
+use std::{
+  collections::HashMap,
+  future::Future,
+  pin::Pin,
+  sync::{mpsc::Sender, Arc, Mutex},
+};
+
+pub use self::go::go_license_name;
+
+const GITHUB_API: &str = "https://api.github.com";
+// Used when the default branch can't be looked up, GitHub redirects it once the branch was renamed
+const FALLBACK_BRANCH: &str = "master";
+
+// What an ecosystem's lookup answers with
+type ResolveFuture<'a> = Pin<Box<dyn Future<Output = Result<Resolution>> + Send + 'a>>;
+type ResolveFn = for<'r> fn(&'r Resolver<'r>, &'r DepsEntry) -> ResolveFuture<'r>;
+
+// Each ecosystem's lookup, and what its packages are called when one fails
+const RESOLVERS: &[(Ecosystem, &str, ResolveFn)] = &[
+  (Ecosystem::Npm, "JS dependency", |resolver, dep| {
+    Box::pin(resolver.resolve_js_dependency(dep))
+  }),
+  (Ecosystem::Go, "Go dependency", |resolver, dep| {
+    row(resolver.resolve_go_dependency(dep))
+  }),
+  (Ecosystem::Cargo, "Rust dependency", |resolver, dep| {
+    row(resolver.resolve_rust_dependency(dep))
+  }),
+  (Ecosystem::Pypi, "Python dependency", |resolver, dep| {
+    row(resolver.resolve_python_dependency(dep))
+  }),
+  (Ecosystem::Maven, "Java dependency", |resolver, dep| {
+    row(resolver.resolve_java_dependency(dep))
+  }),
+  (Ecosystem::Gem, "Ruby dependency", |resolver, dep| {
+    row(resolver.resolve_ruby_dependency(dep))
+  }),
+  (Ecosystem::Composer, "PHP dependency", |resolver, dep| {
+    row(resolver.resolve_php_dependency(dep))
+  }),
+  (Ecosystem::Nuget, "NuGet dependency", |resolver, dep| {
+    row(resolver.resolve_nuget_dependency(dep))
+  }),
+  (Ecosystem::Pub, "Dart dependency", |resolver, dep| {
+    row(resolver.resolve_dart_dependency(dep))
+  }),
+  (Ecosystem::Swift, "Swift dependency", |resolver, dep| {
+    row(resolver.resolve_swift_dependency(dep))
+  }),
+  (Ecosystem::Cocoapods, "CocoaPods dependency", |resolver, dep| {
+    row(resolver.resolve_cocoapods_dependency(dep))
+  }),
+  (Ecosystem::Conan, "Conan dependency", |resolver, dep| {
+    row(resolver.resolve_conan_dependency(dep))
+  }),
+  (Ecosystem::Deno, "Deno dependency", |resolver, dep| {
+    row(resolver.resolve_deno_dependency(dep))
+  }),
+  (Ecosystem::Helm, "Helm chart", |resolver, dep| {
+    row(resolver.resolve_helm_dependency(dep))
+  }),
+  (Ecosystem::Terraform, "Terraform provider", |resolver, dep| {
+    row(resolver.resolve_terraform_dependency(dep))
+  }),
+  (Ecosystem::GithubActions, "GitHub Action", |resolver, dep| {
+    row(resolver.resolve_action_dependency(dep))
+  }),
+];
+
+// Looks packages up in their registries and builds their rows, one module per ecosystem. It keeps no rows and
+// sends none: the `ReportGenerator` and `resolve` both drive it, each with the diagnostics its warnings go to.
+pub struct Resolver<'a> {
+  pub(super) client: &'a dyn MetadataBackend,
+  pub(super) registry: NpmRegistry,
+  pub(super) target_platform: Option<TargetPlatform>,
+  pub(super) strict_platform: bool,
+  pub(super) yarn_caches: Vec<YarnCache>,
+  pub(super) composer_locks: HashMap<(String, String), ComposerPackage>,
+  pub(super) pub_hosts: HashMap<String, String>,
+  pub(super) swift_pins: HashMap<String, SwiftPin>,
+  pub(super) conan_channels: HashMap<String, String>,
+  pub(super) helm_repositories: HashMap<String, String>,
+  // Repository -> its index.yaml, `None` when it couldn't be had
+  helm_indexes: Mutex<HashMap<String, Option<Arc<yaml::Node>>>>,
+  pub(super) check_attestations: bool,
+  // Which pre-releases JSR ranges may pick; the npm registry carries its own
+  pub(super) prerelease: PrereleasePolicy,
+  pub(super) diagnostics: Arc<Diagnostics>,
+  pub(super) events: Option<Sender<ResolutionEvent>>,
+  pub(super) branches: Arc<BranchCache>,
+  pub(super) notices: Option<NoticesProbe>,
+  pub(super) sizes: bool,
+}
+
+// What looking a package up came to
+pub enum Resolution {
+  Row(Box<ReportRow>),
+  // Constraints that can't match the target platform
+  Excluded(String),
+  // The request failed, which may go away later in the run
+  FetchFailed(String),
+  Skipped,
+}
+
+pub(super) enum LicenseProbe {
+  Found(String, LicenseLevel),
+  // Every candidate 404ed, which a default branch that no longer exists also causes
+  Missing,
+  // Rejected or unavailable, already noted in the provenance
+  Stopped,
+}
+
+impl<'a> Resolver<'a> {
+  pub fn new(client: &'a dyn MetadataBackend, diagnostics: Arc<Diagnostics>) -> Self {
+    Self {
+      client,
+      registry: NpmRegistry::default(),
+      target_platform: None,
+      strict_platform: false,
+      yarn_caches: Vec::new(),
+      composer_locks: HashMap::new(),
+      pub_hosts: HashMap::new(),
+      swift_pins: HashMap::new(),
+      conan_channels: HashMap::new(),
+      helm_repositories: HashMap::new(),
+      helm_indexes: Mutex::default(),
+      check_attestations: false,
+      prerelease: PrereleasePolicy::default(),
+      diagnostics,
+      events: None,
+      branches: Arc::default(),
+      notices: None,
+      sizes: false,
+    }
+  }
+
+  // Shared with the caller, which saves it once the run is done; an in-memory one of its own otherwise
+  pub fn with_branch_cache(mut self, branches: Arc<BranchCache>) -> Self {
+    self.branches = branches;
+    self
+  }
+
+  pub fn with_npm_registry(mut self, registry: NpmRegistry) -> Self {
+    self.registry = registry;
+    self
+  }
+
+  pub fn with_prerelease(mut self, policy: PrereleasePolicy) -> Self {
+    self.prerelease = policy;
+    self
+  }
+
+  // The lookup of `ecosystem` for one package
+  pub async fn resolve(&self, ecosystem: Ecosystem, dep: &DepsEntry) -> Result<Resolution> {
+    let Some((_, what, resolve)) = RESOLVERS.iter().find(|(of, ..)| *of == ecosystem) else {
+      bail!("{} packages have no registry to resolve them from", ecosystem);
+    };
+    match resolve(self, dep).await {
+      // Nothing to look at rather than something wrong: the package fails to fetch as npm packages do, where other
+      // errors would stop the run
+      Err(err) if err.chain().any(|cause| cause.is::<OfflineError>()) => Ok(Resolution::FetchFailed(format!(
+        "Can't fetch {} {}@{}: {:#}",
+        what, dep.name, dep.version, err
+      ))),
+      resolution => resolution.with_context(|| format!("Failed to process {}: {}", what, dep.name)),
+    }
+  }
+
+  // Run-level progress, printed by the binary and left to the caller otherwise
+  fn note(&self, message: String) {
+    if let Some(events) = &self.events {
+      let _ = events.send(ResolutionEvent::Note { message });
+    }
+  }
+
+  // The SPDX id GitHub detected in the repository's license file; NOASSERTION is a license it doesn't know
+  async fn fetch_github_license(&self, repo: &str) -> Option<String> {
+    let response = self
+      .client
+      .get_json(&format!("{}/repos/{}/license", GITHUB_API, repo), None)
+      .await
+      .ok()?;
+    if response.status != reqwest::StatusCode::OK {
+      return None;
+    }
+    let body = response.json::<serde_json::Value>().ok()?;
+    body
+      .get("license")?
+      .get("spdx_id")?
+      .as_str()
+      .filter(|id| *id != "NOASSERTION")
+      .map(str::to_owned)
+  }
+
+  async fn fetch_package_json(&self, dep: &DepsEntry, url: &str, row: &mut ReportRow) -> Option<serde_json::Value> {
+    let response = match self.client.get_json(url, None).await {
+      Ok(response) => response,
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::FetchFailure,
+          format!("Can't fetch {}@{} from {}: {:#}", dep.name, dep.version, url, err),
+        );
+        row.provenance.push("failed: package repository unreachable".to_owned());
+        return None;
+      },
+    };
+    let body = match response.status {
+      reqwest::StatusCode::OK => response.json::<serde_json::Value>().ok(),
+      _ => None,
+    };
+    if body.is_none() {
+      self.diagnostics.warn(
+        Category::FetchFailure,
+        format!(
+          "Can't fetch {}@{} from {}: {}{}",
+          dep.name,
+          dep.version,
+          url,
+          response.status,
+          response.dump_ref()
+        ),
+      );
+      row
+        .provenance
+        .push(format!("failed: package repository answered {}", response.status));
+    }
+    body
+  }
+
+  async fn find_license_url(
+    &self,
+    repo_url: &str,
+    directory: Option<&str>,
+    provenance: &mut Vec<String>,
+  ) -> Result<Option<(String, LicenseLevel)>> {
+    Ok(
+      match self
+        .probe_repository(repo_url, directory, LICENSE_FILES, provenance)
+        .await?
+      {
+        LicenseProbe::Found(url, level) => Some((url, level)),
+        LicenseProbe::Missing | LicenseProbe::Stopped => None,
+      },
+    )
+  }
+
+  // Probe the package's own directory inside the repository first, then the repository root, on the default branch,
+  // for the first of `files`. A cached branch that finds nothing is looked up again, and the probe repeated once if
+  // it changed.
+  pub(super) async fn probe_repository(
+    &self,
+    repo_url: &str,
+    directory: Option<&str>,
+    files: &[&str],
+    provenance: &mut Vec<String>,
+  ) -> Result<LicenseProbe> {
+    let (branch, cached) = match self.branches.get(repo_url) {
+      Some(branch) => (branch, true),
+      None => (self.discover_branch(repo_url).await, false),
+    };
+
+    let probe = match self
+      .probe_files(repo_url, &branch, directory, files, provenance)
+      .await?
+    {
+      LicenseProbe::Missing if cached => {
+        self.branches.invalidate(repo_url);
+        let current = self.discover_branch(repo_url).await;
+        if current == branch {
+          LicenseProbe::Missing
+        } else {
+          provenance.push(format!("default branch changed from {} to {}", branch, current));
+          self
+            .probe_files(repo_url, &current, directory, files, provenance)
+            .await?
+        }
+      },
+      probe => probe,
+    };
+
+    Ok(probe)
+  }
+
+  // The default branch from the GitHub API, cached on success. Other hosts and failed lookups get the fallback.
+  async fn discover_branch(&self, repo_url: &str) -> String {
+    let branch = match BranchCache::key(repo_url).and_then(|key| key.strip_prefix("github.com/").map(str::to_owned)) {
+      Some(repo) => match self
+        .client
+        .get_json(&format!("{}/repos/{}", GITHUB_API, repo), None)
+        .await
+      {
+        Ok(response) if response.status == reqwest::StatusCode::OK => response
+          .json::<serde_json::Value>()
+          .ok()
+          .and_then(|info| info.get("default_branch")?.as_str().map(str::to_owned)),
+        _ => None,
+      },
+      None => None,
+    };
+
+    match branch {
+      Some(branch) => {
+        self.branches.insert(repo_url, &branch);
+        branch
+      },
+      None => FALLBACK_BRANCH.to_owned(),
+    }
+  }
+
+  // Pages that answer but aren't license files (parked domains, takedowns) are noted in `provenance`
+  async fn probe_files(
+    &self,
+    repo_url: &str,
+    branch: &str,
+    directory: Option<&str>,
+    files: &[&str],
+    provenance: &mut Vec<String>,
+  ) -> Result<LicenseProbe> {
+    let mut levels = Vec::new();
+    if let Some(directory) = directory.map(|dir| dir.trim_matches('/')).filter(|dir| !dir.is_empty()) {
+      levels.push((
+        format!("{}/blob/{}/{}", repo_url, branch, directory),
+        LicenseLevel::Directory,
+      ));
+    }
+    levels.push((format!("{}/blob/{}", repo_url, branch), LicenseLevel::Root));
+
+    let mut rejected = false;
+
+    for (base_url, level) in levels {
+      for license_file in files {
+        let license_url = format!("{}/{}", base_url, license_file);
+        let response = self.client.get_prefix(&license_url, PROBE_PREFIX).await?;
+
+        match license_probe::classify(&response) {
+          ProbeOutcome::Found => return Ok(LicenseProbe::Found(license_url, level)),
+          ProbeOutcome::Missing => {},
+          ProbeOutcome::Unavailable(reason) => {
+            self.diagnostics.warn(
+              Category::FetchFailure,
+              format!(
+                "Repository {} is unavailable: {}{}",
+                repo_url,
+                reason,
+                response.dump_ref()
+              ),
+            );
+            provenance.push(format!("failed: repository unavailable ({})", reason));
+            return Ok(LicenseProbe::Stopped);
+          },
+          ProbeOutcome::OffHost(reason) => {
+            self.diagnostics.warn(
+              Category::FetchFailure,
+              format!(
+                "Not using {} as license link: {}{}",
+                license_url,
+                reason,
+                response.dump_ref()
+              ),
+            );
+            provenance.push(format!("license probe rejected: {}", reason));
+            return Ok(LicenseProbe::Stopped);
+          },
+          ProbeOutcome::Rejected(reason) => {
+            self.diagnostics.warn(
+              Category::FetchFailure,
+              format!(
+                "Not using {} as license link: {}{}",
+                license_url,
+                reason,
+                response.dump_ref()
+              ),
+            );
+            provenance.push(format!("license probe rejected {}: {}", license_file, reason));
+            rejected = true;
+          },
+        }
+      }
+    }
+    Ok(match rejected {
+      true => LicenseProbe::Stopped,
+      false => LicenseProbe::Missing,
+    })
+  }
+}
+
+// The lookups that always build a row
+fn row<'r>(resolving: impl Future<Output = Result<ReportRow>> + Send + 'r) -> ResolveFuture<'r> {
+  Box::pin(async move { resolving.await.map(|row| Resolution::Row(Box::new(row))) })
+}
@@ -0,0 +1,311 @@
+use super::{LicenseProbe, Resolution, Resolver, FALLBACK_BRANCH};
+use crate::{
+  deps::yarn_berry::CachedPackage,
+  diagnostics::Category,
+  report::{
+    constants::{LICENSE_FILES, NOTICE_FILES},
+    error::ReportError,
+    notices::{self, NoticesProbe},
+    npm_registry::RegistryDialect,
+    platform, repo_check,
+  },
+  types::{DepScope, DepsEntry, Ecosystem, LicenseConfidence, LicenseLevel, PackageInfo, ReportRow},
+};
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+  static ref REPO_REGEX: Regex = Regex::new(r"^.*:(.*)\.[a-z#\.]*$").expect("Failed to compile repository regex");
+}
+
+impl Resolver<'_> {
+  pub async fn resolve_js_dependency(&self, dep: &DepsEntry) -> Result<Resolution> {
+    let cached = self
+      .yarn_caches
+      .iter()
+      .find_map(|cache| cache.lookup(&dep.name, &dep.version));
+    let package_info = match cached {
+      Some(cached) => Ok((cached.package_info(), None, None, None, None, None)),
+      None => self
+        .registry
+        .fetch(self.client, &dep.name, &dep.version)
+        .await
+        .map(|fetched| {
+          (
+            fetched.package_info,
+            Some(fetched.dialect),
+            fetched.registry,
+            fetched.unpublished,
+            fetched.leaked_specifier,
+            fetched.selection,
+          )
+        }),
+    };
+    if let Err(err) = package_info {
+      // With --strict-versions an unpublished pin is a failed row, not a silent gap
+      if let Some(ReportError::UnpublishedVersion(name, version)) = err.downcast_ref::<ReportError>() {
+        self.diagnostics.warn(Category::FetchFailure, err.to_string());
+        return Ok(Resolution::Row(Box::new(ReportRow {
+          ecosystem: Ecosystem::Npm,
+          name: name.clone(),
+          version: version.clone(),
+          scope: dep.scope,
+          homepage: format!("https://www.npmjs.com/package/{}", name),
+          license: None,
+          license_confidence: None,
+          license_url: None,
+          funding: None,
+          platform: None,
+          repo_mismatch: None,
+          installed_mismatch: None,
+          third_party_notices: None,
+          link_status: None,
+          license_text_key: None,
+          attestation: None,
+          osi_approved: None,
+          fsf_libre: None,
+          size: None,
+          provenance: vec![format!("failed: pinned {} unpublished", version)],
+        })));
+      }
+      let message = format!(
+        "Can't parse response for {}@{}. Skip this package. Error: {:#}",
+        dep.name, dep.version, err
+      );
+      // Anything but a failed request went wrong reading what came back
+      if let Some(ReportError::PackageFetchError(_)) = err.downcast_ref::<ReportError>() {
+        return Ok(Resolution::FetchFailed(message));
+      }
+      self.diagnostics.warn(Category::ParseFailure, message);
+
+      return Ok(Resolution::Skipped);
+    }
+
+    let (package_info, dialect, registry, unpublished, leaked_specifier, selection) = package_info.unwrap();
+    let platform = platform::describe_constraints(&package_info);
+    if let (Some(target), Some(constraints)) = (&self.target_platform, &platform) {
+      let applies = self.strict_platform || dep.scope == DepScope::Optional;
+      if applies && !target.matches(&package_info) {
+        return Ok(Resolution::Excluded(constraints.clone()));
+      }
+    }
+
+    let mut provenance = Vec::new();
+    let version = match (&unpublished, &leaked_specifier) {
+      (Some(pinned), _) => {
+        let note = format!("pinned {} unpublished; showing {}", pinned, package_info.version);
+        provenance.push(format!("version: {}", note));
+        format!("{} ({})", package_info.version, note)
+      },
+      (None, Some(specifier)) => {
+        let note = format!("malformed upstream specifier {}; showing latest", specifier);
+        provenance.push(format!("version: {}", note));
+        self.diagnostics.warn(
+          Category::MalformedSpecifier,
+          format!(
+            "{}@{} leaked from a pnpm workspace, resolving latest {}",
+            dep.name, specifier, package_info.version
+          ),
+        );
+        format!("{} ({})", package_info.version, note)
+      },
+      (None, None) => package_info.version.clone(),
+    };
+    if let Some(selection) = selection {
+      provenance.push(format!("version: {}", selection));
+    }
+    let dialect = dialect.filter(|dialect| *dialect != RegistryDialect::Version);
+    if let Some(dialect) = dialect {
+      self.note(format!(
+        "Registry dialect for {}@{}: {}",
+        dep.name, dep.version, dialect
+      ));
+    }
+    // A scope's own registry is named, the default one is in the run info
+    match (registry, dialect) {
+      (Some(registry), Some(dialect)) => provenance.push(format!("metadata: npm registry {} ({})", registry, dialect)),
+      (Some(registry), None) => provenance.push(format!("metadata: npm registry {}", registry)),
+      (None, Some(dialect)) => provenance.push(format!("metadata: npm registry ({})", dialect)),
+      (None, None) => {},
+    }
+    let (license_url, repo_missing, probed_repo) = match cached {
+      // The license text sits inside the zip, there is nothing to link to
+      Some(cached) => {
+        provenance.push(format!("metadata: yarn cache {}", cached.zip));
+        if let Some(entry) = &cached.license_entry {
+          provenance.push(format!("license file: yarn cache {}:{}", cached.zip, entry));
+        }
+        (None, false, None)
+      },
+      None => self.resolve_npm_license_url(&package_info, &mut provenance).await?,
+    };
+    let third_party_notices = match &self.notices {
+      Some(notices) if notices.applies(&package_info.name, package_info.dist.unpacked_size) => {
+        self
+          .find_notices(
+            notices,
+            dep,
+            &package_info,
+            cached,
+            probed_repo.as_deref(),
+            &mut provenance,
+          )
+          .await?
+      },
+      _ => None,
+    };
+
+    let funding = package_info.funding.as_ref().map(|funding| funding.urls().join(", "));
+    let size = package_info.dist.unpacked_size.filter(|_| self.sizes);
+    let license = Some(package_info.license).filter(|license| !license.is_empty());
+    let license_confidence = license.as_ref().map(|_| LicenseConfidence::Declared);
+    let repo_mismatch = repo_check::repo_mismatch(&package_info.name, &package_info.repository.url, repo_missing);
+    if let Some(reason) = &repo_mismatch {
+      self.diagnostics.warn(
+        Category::RepoMismatch,
+        format!(
+          "Repository mismatch for {}@{}: {}",
+          package_info.name, package_info.version, reason
+        ),
+      );
+    }
+    let attestation = if self.check_attestations {
+      Some(
+        self
+          .registry
+          .attestation(self.client, &package_info.name, &package_info.version)
+          .await,
+      )
+    } else {
+      None
+    };
+
+    Ok(Resolution::Row(Box::new(ReportRow {
+      ecosystem: Ecosystem::Npm,
+      name: package_info.name,
+      version,
+      scope: dep.scope,
+      homepage: package_info.homepage,
+      license,
+      license_confidence,
+      license_url,
+      funding,
+      platform,
+      repo_mismatch,
+      installed_mismatch: None,
+      third_party_notices,
+      link_status: None,
+      license_text_key: None,
+      attestation,
+      osi_approved: None,
+      fsf_libre: None,
+      size,
+      provenance,
+    })))
+  }
+
+  // License link plus whether the declared repository turned out not to exist, and the repository when it can be
+  // probed for other files (the license probe didn't stop on it)
+  async fn resolve_npm_license_url(
+    &self,
+    package_info: &PackageInfo,
+    provenance: &mut Vec<String>,
+  ) -> Result<(Option<String>, bool, Option<String>)> {
+    // Private registries (GitHub Packages among them) serve packages published without a repository, the row
+    // keeps its license and goes without a link
+    if package_info.repository.url.trim().is_empty() {
+      provenance.push("license file: no repository declared".to_owned());
+      return Ok((None, false, None));
+    }
+    let (repo_url, repo_status) = self.validate_repository_url(package_info).await?;
+    let repo_missing = repo_status == reqwest::StatusCode::NOT_FOUND;
+    let directory = package_info.repository.directory.as_deref();
+
+    let (license_url, probed_repo) = match self
+      .probe_repository(&repo_url, directory, LICENSE_FILES, provenance)
+      .await?
+    {
+      LicenseProbe::Found(license_url, level) => {
+        provenance.push(match (level, directory) {
+          (LicenseLevel::Directory, Some(directory)) => format!("license file: package directory {}", directory),
+          _ => "license file: repository root".to_owned(),
+        });
+        (Some(license_url), Some(repo_url))
+      },
+      LicenseProbe::Missing => (None, Some(repo_url)),
+      LicenseProbe::Stopped => (None, None),
+    };
+
+    Ok((license_url, repo_missing, probed_repo))
+  }
+
+  // The published package first (its Yarn cache zip or installed copy), then the repository like the license
+  async fn find_notices(
+    &self,
+    notices: &NoticesProbe,
+    dep: &DepsEntry,
+    package_info: &PackageInfo,
+    cached: Option<&CachedPackage>,
+    repo_url: Option<&str>,
+    provenance: &mut Vec<String>,
+  ) -> Result<Option<String>> {
+    let published = match cached {
+      Some(cached) => cached.notices_file.clone(),
+      None => notices.installed(&package_info.name, &package_info.version, &dep.sources),
+    };
+    if let Some(file) = published {
+      provenance.push(format!("third-party notices: published package {}", file));
+      return Ok(Some(notices::published_url(
+        &package_info.name,
+        &package_info.version,
+        &file,
+      )));
+    }
+
+    let Some(repo_url) = repo_url else {
+      return Ok(None);
+    };
+    // The license probe just settled the branch, a miss here is no reason to look it up again
+    let branch = self
+      .branches
+      .get(repo_url)
+      .unwrap_or_else(|| FALLBACK_BRANCH.to_owned());
+    let directory = package_info.repository.directory.as_deref();
+    Ok(
+      match self
+        .probe_files(repo_url, &branch, directory, NOTICE_FILES, provenance)
+        .await?
+      {
+        LicenseProbe::Found(url, level) => {
+          provenance.push(match (level, directory) {
+            (LicenseLevel::Directory, Some(directory)) => {
+              format!("third-party notices: package directory {}", directory)
+            },
+            _ => "third-party notices: repository root".to_owned(),
+          });
+          Some(url)
+        },
+        LicenseProbe::Missing | LicenseProbe::Stopped => None,
+      },
+    )
+  }
+
+  async fn validate_repository_url(&self, package_info: &PackageInfo) -> Result<(String, reqwest::StatusCode)> {
+    let captures = REPO_REGEX
+      .captures(&package_info.repository.url)
+      .ok_or(ReportError::InvalidRepoUrl)?;
+
+    let repo_url = format!("https:{}", &captures[1]);
+    let response = self
+      .client
+      .get(&repo_url)
+      .await
+      .context("Failed to validate repository URL")?;
+
+    Ok(match response.status {
+      reqwest::StatusCode::OK => (response.url, response.status),
+      status => (repo_url, status),
+    })
+  }
+}
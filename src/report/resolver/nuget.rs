@@ -0,0 +1,181 @@
+use super::Resolver;
+use crate::{
+  deps::nuget_deps,
+  diagnostics::Category,
+  types::{DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::Result;
+
+const NUGET_REGISTRATION: &str = "https://api.nuget.org/v3/registration5-semver1";
+const NUGET_FLAT_CONTAINER: &str = "https://api.nuget.org/v3-flatcontainer";
+// Packages declaring only a license expression link it here
+const NUGET_LICENSE_HOST: &str = "https://licenses.nuget.org/";
+// The license URL NuGet shows for a license file packed into the .nupkg
+const NUGET_PACKED_LICENSE: &str = "https://aka.ms/deprecateLicenseUrl";
+
+impl Resolver<'_> {
+  // The license expression or license URL of the version's catalog entry, reached through its registration leaf
+  pub async fn resolve_nuget_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Nuget,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: format!("https://www.nuget.org/packages/{}", dep.name),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+
+    let id = dep.name.to_lowercase();
+    if dep.version.contains('*') {
+      let url = format!("{}/{}/index.json", NUGET_FLAT_CONTAINER, id);
+      let Some(index) = self.fetch_nuget_json(dep, &url, &mut row).await else {
+        return Ok(row);
+      };
+      let versions = index
+        .get("versions")
+        .and_then(|versions| versions.as_array())
+        .map(|versions| {
+          versions
+            .iter()
+            .filter_map(|version| version.as_str())
+            .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+      match nuget_deps::floating_match(&dep.version, versions.into_iter()) {
+        Some(version) => row.version = version,
+        None => {
+          self.diagnostics.warn(
+            Category::FetchFailure,
+            format!("No NuGet version of {} matches {}", dep.name, dep.version),
+          );
+          row.provenance.push("failed: no matching version on NuGet".to_owned());
+          return Ok(row);
+        },
+      }
+    }
+
+    let url = format!(
+      "{}/{}/{}.json",
+      NUGET_REGISTRATION,
+      id,
+      nuget_deps::normalize_version(&row.version)
+    );
+    let Some(leaf) = self.fetch_nuget_json(dep, &url, &mut row).await else {
+      return Ok(row);
+    };
+    // Inlined in registration pages, a link from a leaf
+    let entry = match leaf.get("catalogEntry") {
+      Some(serde_json::Value::String(url)) => match self.fetch_nuget_json(dep, url, &mut row).await {
+        Some(entry) => entry,
+        None => return Ok(row),
+      },
+      Some(entry) => entry.clone(),
+      None => leaf,
+    };
+
+    let field = |name: &str| {
+      entry
+        .get(name)
+        .and_then(|value| value.as_str())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+    };
+    row.provenance.push("metadata: NuGet".to_owned());
+    let license_url = field("licenseUrl");
+    row.license = field("licenseExpression").or_else(|| {
+      let expression = license_url.as_deref()?.strip_prefix(NUGET_LICENSE_HOST)?;
+      Some(expression.replace("%20", " ")).filter(|expression| !expression.is_empty())
+    });
+    match (&row.license, license_url) {
+      (Some(_), _) => row.license_confidence = Some(LicenseConfidence::Declared),
+      (None, Some(url)) if url == NUGET_PACKED_LICENSE => {
+        row
+          .provenance
+          .push("license: file packed in the .nupkg, not read".to_owned());
+        self.diagnostics.warn(
+          Category::UnknownLicense,
+          format!(
+            "{}@{} ships its license as a file in the package, which isn't read",
+            dep.name, row.version
+          ),
+        );
+      },
+      (None, Some(url)) => {
+        self.diagnostics.warn(
+          Category::UnknownLicense,
+          format!("{}@{} only links its license: {}", dep.name, row.version, url),
+        );
+        row.license_url = Some(url);
+      },
+      (None, None) => self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No license on NuGet for {}@{}", dep.name, row.version),
+      ),
+    }
+
+    let project_url = field("projectUrl").map(|url| url.trim_end_matches('/').trim_end_matches(".git").to_owned());
+    if let Some(project_url) = &project_url {
+      row.homepage = project_url.clone();
+    }
+    if let Some(repository) = project_url.filter(|url| url.starts_with("https://github.com/")) {
+      if row.license_url.is_none() {
+        if let Some((license_url, _)) = self.find_license_url(&repository, None, &mut row.provenance).await? {
+          row.provenance.push("license file: repository root".to_owned());
+          row.license_url = Some(license_url);
+        }
+      }
+    }
+
+    Ok(row)
+  }
+
+  // None once the failure is warned about and noted on the row
+  async fn fetch_nuget_json(&self, dep: &DepsEntry, url: &str, row: &mut ReportRow) -> Option<serde_json::Value> {
+    let response = match self.client.get_json(url, None).await {
+      Ok(response) => response,
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::FetchFailure,
+          format!("Can't fetch {}@{} from NuGet: {:#}", dep.name, dep.version, err),
+        );
+        row.provenance.push("failed: NuGet unreachable".to_owned());
+        return None;
+      },
+    };
+    let body = match response.status {
+      reqwest::StatusCode::OK => response.json::<serde_json::Value>().ok(),
+      _ => None,
+    };
+    if body.is_none() {
+      self.diagnostics.warn(
+        Category::FetchFailure,
+        format!(
+          "Can't fetch {}@{} from NuGet: {}{}",
+          dep.name,
+          dep.version,
+          response.status,
+          response.dump_ref()
+        ),
+      );
+      row
+        .provenance
+        .push(format!("failed: NuGet answered {}", response.status));
+    }
+    body
+  }
+}
@@ -0,0 +1,134 @@
+use super::Resolver;
+use crate::{
+  deps::php_deps::{self, ComposerPackage},
+  diagnostics::Category,
+  types::{DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::Result;
+
+const PACKAGIST_API: &str = "https://repo.packagist.org/p2";
+
+impl Resolver<'_> {
+  // A composer.lock entry already has the license and source; a composer.json constraint is resolved to the newest
+  // stable version Packagist has for it
+  pub async fn resolve_php_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Composer,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: format!("https://packagist.org/packages/{}", dep.name),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+
+    let package = match self.composer_locks.get(&(dep.name.clone(), dep.version.clone())) {
+      Some(package) => {
+        row.provenance.push("metadata: composer.lock".to_owned());
+        package.clone()
+      },
+      None => match self.fetch_packagist_version(dep, &mut row).await {
+        Some((version, package)) => {
+          row.provenance.push("metadata: Packagist".to_owned());
+          row.version = version;
+          package
+        },
+        None => return Ok(row),
+      },
+    };
+
+    row.license = package.license();
+    row.license_confidence = row.license.as_ref().map(|_| LicenseConfidence::Declared);
+    if row.license.is_none() {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No license for {}@{}", dep.name, row.version),
+      );
+    }
+
+    let repository = package.source_url();
+    if let Some(homepage) = package
+      .homepage
+      .clone()
+      .filter(|homepage| !homepage.is_empty())
+      .or_else(|| repository.clone())
+    {
+      row.homepage = homepage;
+    }
+    if let Some(repository) = repository.filter(|repository| repository.starts_with("https://github.com/")) {
+      if let Some((license_url, _)) = self.find_license_url(&repository, None, &mut row.provenance).await? {
+        row.provenance.push("license file: repository root".to_owned());
+        row.license_url = Some(license_url);
+      }
+    }
+
+    Ok(row)
+  }
+
+  // None once the failure is warned about and noted on the row; a constraint no version matches falls back to
+  // the newest stable one
+  async fn fetch_packagist_version(&self, dep: &DepsEntry, row: &mut ReportRow) -> Option<(String, ComposerPackage)> {
+    let url = format!("{}/{}.json", PACKAGIST_API, dep.name);
+    let response = match self.client.get_json(&url, None).await {
+      Ok(response) => response,
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::FetchFailure,
+          format!("Can't fetch {}@{} from Packagist: {:#}", dep.name, dep.version, err),
+        );
+        row.provenance.push("failed: Packagist unreachable".to_owned());
+        return None;
+      },
+    };
+    let body = match response.status {
+      reqwest::StatusCode::OK => response.json::<serde_json::Value>().ok(),
+      _ => None,
+    };
+    let Some(body) = body else {
+      self.diagnostics.warn(
+        Category::FetchFailure,
+        format!(
+          "Can't fetch {}@{} from Packagist: {}{}",
+          dep.name,
+          dep.version,
+          response.status,
+          response.dump_ref()
+        ),
+      );
+      row
+        .provenance
+        .push(format!("failed: Packagist answered {}", response.status));
+      return None;
+    };
+
+    let version = php_deps::packagist_version(&body, &dep.name, &dep.version);
+    if version.is_some() {
+      return version;
+    }
+    self.diagnostics.warn(
+      Category::FetchFailure,
+      format!(
+        "No Packagist version of {} matches {}, using the latest",
+        dep.name, dep.version
+      ),
+    );
+    let latest = php_deps::packagist_version(&body, &dep.name, "*");
+    if latest.is_none() {
+      row.provenance.push("failed: no stable version on Packagist".to_owned());
+    }
+    latest
+  }
+}
@@ -0,0 +1,160 @@
+use super::Resolver;
+use crate::{
+  deps::py_deps,
+  diagnostics::Category,
+  types::{DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::Result;
+
+const PUB_DEV: &str = "https://pub.dev";
+// pub.dev's lowercased license tags for the common licenses, others are kept as tagged
+const PUB_LICENSES: &[(&str, &str)] = &[
+  ("mit", "MIT"),
+  ("apache-2.0", "Apache-2.0"),
+  ("bsd-3-clause", "BSD-3-Clause"),
+  ("bsd-2-clause", "BSD-2-Clause"),
+  ("mpl-2.0", "MPL-2.0"),
+  ("isc", "ISC"),
+  ("zlib", "Zlib"),
+  ("unlicense", "Unlicense"),
+];
+
+impl Resolver<'_> {
+  // The version's pubspec from the package document, which lists every version; a pubspec.yaml constraint picks
+  // the newest release it allows. pub.dev's analysis of the package gives the license, the repository is probed
+  // for the license file like for gems.
+  pub async fn resolve_dart_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let host = self.pub_hosts.get(&dep.name);
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Pub,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: format!("{}/packages/{}", host.map_or(PUB_DEV, String::as_str), dep.name),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+
+    let api = format!("{}/api/packages/{}", host.map_or(PUB_DEV, String::as_str), dep.name);
+    let Some(package) = self.fetch_package_json(dep, &api, &mut row).await else {
+      return Ok(row);
+    };
+    let versions = package
+      .get("versions")
+      .and_then(|versions| versions.as_array())
+      .map(Vec::as_slice)
+      .unwrap_or_default();
+    let picked = match versions
+      .iter()
+      .any(|entry| version_of(entry) == Some(dep.version.as_str()))
+    {
+      true => Some(dep.version.clone()),
+      false => py_deps::highest_matching(&dep.version, versions.iter().filter_map(version_of)),
+    };
+    let entry = match &picked {
+      Some(version) => versions
+        .iter()
+        .find(|entry| version_of(entry) == Some(version.as_str())),
+      None => {
+        self.diagnostics.warn(
+          Category::FetchFailure,
+          format!("No version of {} matches {}, using the latest", dep.name, dep.version),
+        );
+        package.get("latest")
+      },
+    };
+    if let Some(version) = entry.and_then(version_of) {
+      row.version = version.to_owned();
+    }
+    row.provenance.push(match host {
+      Some(host) => format!("metadata: {}", host),
+      None => "metadata: pub.dev".to_owned(),
+    });
+
+    let pubspec = entry.and_then(|entry| entry.get("pubspec"));
+    let field = |name: &str| {
+      pubspec
+        .and_then(|pubspec| pubspec.get(name))
+        .and_then(|value| value.as_str())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.trim_end_matches('/').trim_end_matches(".git").to_owned())
+    };
+    let repository = field("repository");
+    if let Some(homepage) = field("homepage").or_else(|| repository.clone()) {
+      row.homepage = homepage;
+    }
+
+    // Only pub.dev analyzes packages, and only their latest version
+    if host.is_none() {
+      row.license = self.fetch_pub_license(&dep.name).await;
+      if row.license.is_some() {
+        row.license_confidence = Some(LicenseConfidence::Detected);
+        row
+          .provenance
+          .push("license: pub.dev analysis of the latest version".to_owned());
+      }
+    }
+    if row.license.is_none() {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No license for {}@{}", dep.name, row.version),
+      );
+    }
+
+    let repository = repository.or_else(|| field("homepage"));
+    if let Some(repository) = repository.filter(|repository| repository.starts_with("https://github.com/")) {
+      if let Some((license_url, _)) = self.find_license_url(&repository, None, &mut row.provenance).await? {
+        row.provenance.push("license file: repository root".to_owned());
+        row.license_url = Some(license_url);
+      }
+    }
+
+    Ok(row)
+  }
+
+  // The `license:<id>` tags of pub.dev's score, several being licenses that all apply; tags saying how the
+  // licenses are classified aren't licenses
+  async fn fetch_pub_license(&self, name: &str) -> Option<String> {
+    let response = self
+      .client
+      .get_json(&format!("{}/api/packages/{}/score", PUB_DEV, name), None)
+      .await
+      .ok()?;
+    if response.status != reqwest::StatusCode::OK {
+      return None;
+    }
+    let score = response.json::<serde_json::Value>().ok()?;
+    let licenses = score
+      .get("tags")?
+      .as_array()?
+      .iter()
+      .filter_map(|tag| tag.as_str()?.strip_prefix("license:"))
+      .filter(|license| !matches!(*license, "osi-approved" | "fsf-libre" | "unknown"))
+      .map(|license| {
+        PUB_LICENSES
+          .iter()
+          .find(|(tag, _)| *tag == license)
+          .map_or(license, |(_, id)| *id)
+      })
+      .collect::<Vec<_>>();
+    Some(licenses.join(" AND ")).filter(|license| !license.is_empty())
+  }
+}
+
+// The version of an entry in a pub.dev package document's `versions`
+fn version_of(entry: &serde_json::Value) -> Option<&str> {
+  entry.get("version")?.as_str()
+}
@@ -0,0 +1,203 @@
+use super::Resolver;
+use crate::{
+  deps::py_deps,
+  diagnostics::Category,
+  types::{DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::Result;
+
+const PYPI_API: &str = "https://pypi.org/pypi";
+// `License :: OSI Approved :: <name>` classifiers that name one license unambiguously
+const PYPI_CLASSIFIERS: &[(&str, &str)] = &[
+  ("MIT License", "MIT"),
+  ("ISC License (ISCL)", "ISC"),
+  ("Mozilla Public License 2.0 (MPL 2.0)", "MPL-2.0"),
+  ("GNU General Public License v2 or later (GPLv2+)", "GPL-2.0-or-later"),
+  ("GNU General Public License v3 or later (GPLv3+)", "GPL-3.0-or-later"),
+  (
+    "GNU Lesser General Public License v3 or later (LGPLv3+)",
+    "LGPL-3.0-or-later",
+  ),
+  (
+    "GNU Affero General Public License v3 or later (AGPLv3+)",
+    "AGPL-3.0-or-later",
+  ),
+  ("Boost Software License 1.0 (BSL-1.0)", "BSL-1.0"),
+  ("The Unlicense (Unlicense)", "Unlicense"),
+  ("zlib/libpng License", "Zlib"),
+];
+
+impl Resolver<'_> {
+  // The license PyPI has for the release: the PEP 639 expression, a short `license` field, then the classifiers
+  pub async fn resolve_python_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let release = match py_deps::is_range(&dep.version) {
+      true => self.python_range_release(dep).await,
+      false => Some(dep.version.clone()),
+    };
+    let url = match release.as_deref() {
+      None | Some("*") => format!("{}/{}/json", PYPI_API, dep.name),
+      Some(version) => format!("{}/{}/{}/json", PYPI_API, dep.name, version),
+    };
+
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Pypi,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: format!("https://pypi.org/project/{}/", dep.name),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+
+    let response = match self.client.get_json(&url, None).await {
+      Ok(response) => response,
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::FetchFailure,
+          format!("Can't fetch {}@{} from PyPI: {:#}", dep.name, dep.version, err),
+        );
+        row.provenance.push("failed: PyPI unreachable".to_owned());
+        return Ok(row);
+      },
+    };
+    let info = match response.status {
+      reqwest::StatusCode::OK => response
+        .json::<serde_json::Value>()
+        .ok()
+        .and_then(|body| body.get("info").cloned()),
+      _ => None,
+    };
+    let Some(info) = info else {
+      self.diagnostics.warn(
+        Category::FetchFailure,
+        format!(
+          "Can't fetch {}@{} from PyPI: {}{}",
+          dep.name,
+          dep.version,
+          response.status,
+          response.dump_ref()
+        ),
+      );
+      row
+        .provenance
+        .push(format!("failed: PyPI answered {}", response.status));
+      return Ok(row);
+    };
+
+    let field = |name: &str| {
+      info
+        .get(name)
+        .and_then(|value| value.as_str())
+        .map(str::trim)
+        .filter(|value| !value.is_empty() && *value != "UNKNOWN")
+        .map(str::to_owned)
+    };
+    let project_url = |names: &[&str]| {
+      let urls = info.get("project_urls")?.as_object()?;
+      urls
+        .iter()
+        .find(|(label, _)| names.iter().any(|name| label.eq_ignore_ascii_case(name)))
+        .and_then(|(_, url)| url.as_str())
+        .map(str::to_owned)
+    };
+    row.provenance.push("metadata: PyPI".to_owned());
+    if let Some(version) = field("version") {
+      row.version = version;
+    }
+
+    // Some projects paste the whole license text into `license`
+    let license = field("license_expression")
+      .or_else(|| field("license").filter(|license| license.len() <= 100 && !license.contains('\n')))
+      .or_else(|| {
+        let classifiers = info
+          .get("classifiers")?
+          .as_array()?
+          .iter()
+          .filter_map(|classifier| classifier.as_str()?.strip_prefix("License :: "))
+          .map(|classifier| classifier.rsplit(" :: ").next().unwrap_or(classifier))
+          .map(|name| {
+            PYPI_CLASSIFIERS
+              .iter()
+              .find(|(classifier, _)| *classifier == name)
+              .map_or(name, |(_, id)| *id)
+          })
+          .collect::<Vec<_>>();
+        if !classifiers.is_empty() {
+          row.provenance.push("license: PyPI classifiers".to_owned());
+        }
+        Some(classifiers.join(" OR ")).filter(|license| !license.is_empty())
+      });
+    row.license = license;
+    row.license_confidence = row.license.as_ref().map(|_| LicenseConfidence::Declared);
+    if row.license.is_none() {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No license on PyPI for {}@{}", dep.name, row.version),
+      );
+    }
+
+    let repository = project_url(&["Source", "Source Code", "Repository", "Code"])
+      .map(|repository| repository.trim_end_matches('/').trim_end_matches(".git").to_owned());
+    if let Some(homepage) = field("home_page")
+      .or_else(|| project_url(&["Homepage", "Home"]))
+      .or_else(|| field("project_url"))
+    {
+      row.homepage = homepage;
+    }
+    if let Some(repository) = repository.filter(|repository| repository.starts_with("https://github.com/")) {
+      if let Some((license_url, _)) = self.find_license_url(&repository, None, &mut row.provenance).await? {
+        row.provenance.push("license file: repository root".to_owned());
+        row.license_url = Some(license_url);
+      }
+    }
+
+    Ok(row)
+  }
+
+  // The newest release a pyproject.toml range allows, from the project's release list with yanked releases left
+  // out. None falls back to the latest release; an unreachable PyPI is warned about by the fetch that follows.
+  async fn python_range_release(&self, dep: &DepsEntry) -> Option<String> {
+    let url = format!("{}/{}/json", PYPI_API, dep.name);
+    let response = self.client.get_json(&url, None).await.ok()?;
+    if response.status != reqwest::StatusCode::OK {
+      return None;
+    }
+    let body = response.json::<serde_json::Value>().ok()?;
+    let releases = body.get("releases")?.as_object()?;
+    let available = releases
+      .iter()
+      .filter(|(_, files)| {
+        files.as_array().is_some_and(|files| {
+          files
+            .iter()
+            .any(|file| file.get("yanked") != Some(&serde_json::Value::Bool(true)))
+        })
+      })
+      .map(|(version, _)| version.as_str());
+
+    let release = py_deps::highest_matching(&dep.version, available);
+    if release.is_none() {
+      self.diagnostics.warn(
+        Category::FetchFailure,
+        format!(
+          "No PyPI release of {} matches {}, using the latest",
+          dep.name, dep.version
+        ),
+      );
+    }
+    release
+  }
+}
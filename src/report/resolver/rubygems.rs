@@ -0,0 +1,110 @@
+use super::Resolver;
+use crate::{
+  diagnostics::Category,
+  types::{DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::Result;
+
+const RUBYGEMS_API: &str = "https://rubygems.org/api/v2/rubygems";
+
+impl Resolver<'_> {
+  // The licenses rubygems.org has for the locked version; several are alternatives, as the gemspec's `licenses`
+  // usually means
+  pub async fn resolve_ruby_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let url = format!("{}/{}/versions/{}.json", RUBYGEMS_API, dep.name, dep.version);
+
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Gem,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: format!("https://rubygems.org/gems/{}", dep.name),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+
+    let response = match self.client.get_json(&url, None).await {
+      Ok(response) => response,
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::FetchFailure,
+          format!("Can't fetch {}@{} from rubygems.org: {:#}", dep.name, dep.version, err),
+        );
+        row.provenance.push("failed: rubygems.org unreachable".to_owned());
+        return Ok(row);
+      },
+    };
+    let info = match response.status {
+      reqwest::StatusCode::OK => response.json::<serde_json::Value>().ok(),
+      _ => None,
+    };
+    let Some(info) = info else {
+      self.diagnostics.warn(
+        Category::FetchFailure,
+        format!(
+          "Can't fetch {}@{} from rubygems.org: {}{}",
+          dep.name,
+          dep.version,
+          response.status,
+          response.dump_ref()
+        ),
+      );
+      row
+        .provenance
+        .push(format!("failed: rubygems.org answered {}", response.status));
+      return Ok(row);
+    };
+
+    let field = |name: &str| {
+      info
+        .get(name)
+        .and_then(|value| value.as_str())
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+    };
+    row.provenance.push("metadata: rubygems.org".to_owned());
+    let licenses = info
+      .get("licenses")
+      .and_then(|licenses| licenses.as_array())
+      .into_iter()
+      .flatten()
+      .filter_map(|license| license.as_str())
+      .filter(|license| !license.is_empty())
+      .collect::<Vec<_>>();
+    row.license = Some(licenses.join(" OR ")).filter(|license| !license.is_empty());
+    row.license_confidence = row.license.as_ref().map(|_| LicenseConfidence::Declared);
+    if row.license.is_none() {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No license on rubygems.org for {}@{}", dep.name, dep.version),
+      );
+    }
+
+    let repository = field("source_code_uri").map(|repository| repository.trim_end_matches(".git").to_owned());
+    if let Some(homepage) = field("homepage_uri").or_else(|| repository.clone()) {
+      row.homepage = homepage;
+    }
+    let repository = repository.or_else(|| field("homepage_uri"));
+    if let Some(repository) = repository.filter(|repository| repository.starts_with("https://github.com/")) {
+      if let Some((license_url, _)) = self.find_license_url(&repository, None, &mut row.provenance).await? {
+        row.provenance.push("license file: repository root".to_owned());
+        row.license_url = Some(license_url);
+      }
+    }
+
+    Ok(row)
+  }
+}
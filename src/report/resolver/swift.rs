@@ -0,0 +1,79 @@
+use super::Resolver;
+use crate::{
+  diagnostics::Category,
+  report::repo_groups,
+  types::{DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::Result;
+
+impl Resolver<'_> {
+  // SwiftPM has no registry metadata to ask: the GitHub license API names the license of repositories on GitHub,
+  // and the repository is probed for the license file on any code host
+  pub async fn resolve_swift_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let pin = self.swift_pins.get(&dep.name);
+    let location = pin.map_or("", |pin| pin.location.as_str());
+    let repository = repo_groups::repository_of(location);
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Swift,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: repository
+        .clone()
+        .unwrap_or_else(|| location.trim_end_matches(".git").to_owned()),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+    row
+      .provenance
+      .push(format!("metadata: Package.resolved pin of {}", location));
+    if let Some((branch, revision)) = pin.and_then(|pin| pin.branch.as_ref().zip(pin.revision.as_ref())) {
+      row
+        .provenance
+        .push(format!("pinned to branch {} at {}", branch, revision));
+    }
+
+    let Some(repository) = repository else {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!(
+          "{} is checked out from {}, which isn't on a code host we know; its license isn't looked up",
+          dep.name, location
+        ),
+      );
+      return Ok(row);
+    };
+    if let Some(repo) = repository.strip_prefix("https://github.com/") {
+      row.license = self.fetch_github_license(repo).await;
+      if row.license.is_some() {
+        row.license_confidence = Some(LicenseConfidence::Detected);
+        row.provenance.push("license: GitHub license API".to_owned());
+      }
+    }
+    if let Some((license_url, _)) = self.find_license_url(&repository, None, &mut row.provenance).await? {
+      row.provenance.push("license file: repository root".to_owned());
+      row.license_url = Some(license_url);
+    }
+    if row.license.is_none() {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No license for {}@{}", dep.name, row.version),
+      );
+    }
+
+    Ok(row)
+  }
+}
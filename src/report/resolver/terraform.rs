@@ -0,0 +1,124 @@
+use super::Resolver;
+use crate::{
+  deps::terraform_deps::{self, PUBLIC_REGISTRY},
+  diagnostics::Category,
+  report::repo_groups,
+  types::{DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use anyhow::Result;
+
+// jsr.io serves the versions and files of each package, api.jsr.io its settings, the linked repository among them
+const TERRAFORM_REGISTRY_API: &str = "https://registry.terraform.io/v1/providers";
+
+impl Resolver<'_> {
+  // The Terraform Registry only knows which repository a provider is built from, its license is that
+  // repository's, found the way a chart's is. Providers of other registries are listed without a license.
+  pub async fn resolve_terraform_dependency(&self, dep: &DepsEntry) -> Result<ReportRow> {
+    let mut row = ReportRow {
+      ecosystem: Ecosystem::Terraform,
+      name: dep.name.clone(),
+      version: dep.version.clone(),
+      scope: dep.scope,
+      homepage: terraform_deps::homepage(&dep.name, &dep.version),
+      license: None,
+      license_confidence: None,
+      license_url: None,
+      funding: None,
+      platform: None,
+      repo_mismatch: None,
+      installed_mismatch: None,
+      third_party_notices: None,
+      link_status: None,
+      license_text_key: None,
+      attestation: None,
+      osi_approved: None,
+      fsf_libre: None,
+      size: None,
+      provenance: Vec::new(),
+    };
+    let (namespace, name) = match terraform_deps::address_parts(&dep.name) {
+      Some((PUBLIC_REGISTRY, namespace, name)) => (namespace, name),
+      Some((host, _, _)) => {
+        self.diagnostics.warn(
+          Category::UnknownLicense,
+          format!(
+            "{}@{} comes from the private registry {}, its license isn't looked up",
+            dep.name, dep.version, host
+          ),
+        );
+        row.provenance.push(format!("private registry: {}", host));
+        return Ok(row);
+      },
+      None => {
+        self.diagnostics.warn(
+          Category::ParseFailure,
+          format!("{} is not a provider address", dep.name),
+        );
+        return Ok(row);
+      },
+    };
+
+    let url = format!("{}/{}/{}/{}", TERRAFORM_REGISTRY_API, namespace, name, dep.version);
+    let response = match self.client.get_json(&url, None).await {
+      Ok(response) => response,
+      Err(err) => {
+        self.diagnostics.warn(
+          Category::FetchFailure,
+          format!(
+            "Can't fetch {}@{} from the Terraform Registry: {:#}",
+            dep.name, dep.version, err
+          ),
+        );
+        row.provenance.push("failed: Terraform Registry unreachable".to_owned());
+        return Ok(row);
+      },
+    };
+    let info = match response.status {
+      reqwest::StatusCode::OK => response.json::<serde_json::Value>().ok(),
+      _ => None,
+    };
+    let Some(info) = info else {
+      self.diagnostics.warn(
+        Category::FetchFailure,
+        format!(
+          "Can't fetch {}@{} from the Terraform Registry: {}{}",
+          dep.name,
+          dep.version,
+          response.status,
+          response.dump_ref()
+        ),
+      );
+      row
+        .provenance
+        .push(format!("failed: Terraform Registry answered {}", response.status));
+      return Ok(row);
+    };
+    row.provenance.push("metadata: Terraform Registry".to_owned());
+
+    let source_repository = info
+      .get("source")
+      .and_then(serde_json::Value::as_str)
+      .and_then(repo_groups::repository_of);
+    if let Some(repository) = &source_repository {
+      if let Some(repo) = repository.strip_prefix("https://github.com/") {
+        row.license = self.fetch_github_license(repo).await;
+        if row.license.is_some() {
+          row.license_confidence = Some(LicenseConfidence::Detected);
+          row.provenance.push("license: GitHub license API".to_owned());
+        }
+      }
+      if let Some((license_url, _)) = self.find_license_url(repository, None, &mut row.provenance).await? {
+        row.provenance.push("license file: repository root".to_owned());
+        row.license_url = Some(license_url);
+      }
+    }
+    if row.license.is_none() {
+      self.diagnostics.warn(
+        Category::UnknownLicense,
+        format!("No license for {}@{}", dep.name, dep.version),
+      );
+    }
+
+    Ok(row)
+  }
+}
@@ -0,0 +1,128 @@
+use crate::types::{Ecosystem, ReportRow};
+use anyhow::{bail, Result};
+use ring::digest::{digest, SHA256};
+use std::{
+  collections::HashMap,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+  },
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+// Samples are drawn in steps of a ten-thousandth of a percent
+const SAMPLE_SCALE: u64 = 1_000_000;
+
+type Key = (Ecosystem, String, String);
+
+// What a fresh resolution of a held back baseline row came to
+pub enum Revalidated {
+  // Same license, or a package the baseline never held back: the fresh row goes on
+  Fresh(ReportRow),
+  // The fresh row, with the license the baseline had
+  Drifted(ReportRow, String),
+  // The fresh lookup found no license, the baseline's row stands
+  Kept(ReportRow),
+}
+
+/// --revalidate-sample: a share of the rows the trusted baseline would serve is resolved again, so a license the
+/// publisher fixed for an already-published version doesn't stay stale forever. Which packages are drawn depends
+/// only on the seed and the package, so a rerun with the same seed checks the same ones.
+///
+/// ```
+/// use depsfetch::{report::Revalidation, types::Ecosystem};
+///
+/// let revalidation = Revalidation::new(25.0, Some(7)).unwrap();
+/// let drawn = revalidation.is_sampled(Ecosystem::Npm, "left-pad", "1.3.0");
+/// assert_eq!(Revalidation::new(25.0, Some(7)).unwrap().is_sampled(Ecosystem::Npm, "left-pad", "1.3.0"), drawn);
+/// ```
+pub struct Revalidation {
+  percent: f64,
+  seed: u64,
+  // Baseline rows held back for a fresh resolution, with the sheets they were for
+  held: Mutex<HashMap<Key, (Vec<&'static str>, ReportRow)>>,
+  sampled: AtomicUsize,
+  drifted: AtomicUsize,
+}
+
+impl Revalidation {
+  // Without a seed the run draws its own, printed so it can be passed again
+  pub fn new(percent: f64, seed: Option<u64>) -> Result<Self> {
+    if !(0.0..=100.0).contains(&percent) {
+      bail!("--revalidate-sample {} is not a percentage between 0 and 100", percent);
+    }
+    let seed = seed.unwrap_or_else(|| {
+      SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default()
+    });
+
+    Ok(Self {
+      percent,
+      seed,
+      held: Mutex::default(),
+      sampled: AtomicUsize::new(0),
+      drifted: AtomicUsize::new(0),
+    })
+  }
+
+  pub fn seed(&self) -> u64 {
+    self.seed
+  }
+
+  pub fn describe(&self) -> String {
+    format!("{}% of baseline rows, seed {}", self.percent, self.seed)
+  }
+
+  pub fn is_sampled(&self, ecosystem: Ecosystem, name: &str, version: &str) -> bool {
+    let hash = digest(
+      &SHA256,
+      format!("{}/{}/{}@{}", self.seed, ecosystem, name, version).as_bytes(),
+    );
+    let draw = u64::from_be_bytes(hash.as_ref()[..8].try_into().unwrap_or_default()) % SAMPLE_SCALE;
+    (draw as f64) < self.percent / 100.0 * SAMPLE_SCALE as f64
+  }
+
+  // Keeps the baseline's row of a drawn package until its fresh row arrives
+  pub fn hold(&self, sheets: &[&'static str], row: ReportRow) {
+    self.sampled.fetch_add(1, Ordering::Relaxed);
+    let key = (row.ecosystem, row.name.clone(), row.version.clone());
+    self.lock().insert(key, (sheets.to_vec(), row));
+  }
+
+  pub fn compare(&self, fresh: ReportRow) -> Revalidated {
+    // Notes like "(pinned 1.2.3 unpublished; ...)" follow the version
+    let version = fresh.version.split(' ').next().unwrap_or_default().to_owned();
+    let Some((_, stored)) = self.lock().remove(&(fresh.ecosystem, fresh.name.clone(), version)) else {
+      return Revalidated::Fresh(fresh);
+    };
+    match (&fresh.license, &stored.license) {
+      (None, _) => Revalidated::Kept(stored),
+      (Some(license), Some(before)) if license != before => {
+        self.drifted.fetch_add(1, Ordering::Relaxed);
+        Revalidated::Drifted(fresh, before.clone())
+      },
+      _ => Revalidated::Fresh(fresh),
+    }
+  }
+
+  // Held rows no fresh row came for, the lookup having failed; they go out as the baseline had them
+  pub fn unanswered(&self) -> Vec<(Vec<&'static str>, ReportRow)> {
+    let mut rows = self.lock().drain().map(|(_, held)| held).collect::<Vec<_>>();
+    rows.sort_by(|(_, row), (_, other)| (row.ecosystem, &row.name).cmp(&(other.ecosystem, &other.name)));
+    rows
+  }
+
+  // Rows resolved again and those whose license changed
+  pub fn counts(&self) -> (usize, usize) {
+    (
+      self.sampled.load(Ordering::Relaxed),
+      self.drifted.load(Ordering::Relaxed),
+    )
+  }
+
+  fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Key, (Vec<&'static str>, ReportRow)>> {
+    self.held.lock().unwrap_or_else(|err| err.into_inner())
+  }
+}
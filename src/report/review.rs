@@ -0,0 +1,342 @@
+use super::constants::{ECOSYSTEM_HEADER, HEADERS, REVIEWER_NOTES_HEADER, REVIEW_STATUS_HEADER};
+use crate::{deps::xml::Element, types::ReportRow};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::{BTreeMap, HashSet},
+  fs::{self, File},
+  io::Read,
+  path::Path,
+};
+use zip::ZipArchive;
+
+// Kept next to the config in the scanned directory, `depsfetch import-review` writes it
+pub const REVIEW_FILE: &str = ".collect_lic_info.review.toml";
+// The choices of the status dropdown, as they are stored
+pub const REVIEW_STATUSES: [&str; 3] = ["Approved", "Rejected", "Needs info"];
+
+// (ecosystem, name, version), the ecosystem as the JSON outputs spell it
+type ReviewKey = (String, String, String);
+
+// A reviewer's decision on one package version
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewEntry {
+  pub ecosystem: String,
+  pub name: String,
+  pub version: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub status: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub notes: Option<String>,
+}
+
+// `[[review]]` tables, sorted so the file diffs well when committed
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ReviewFile {
+  #[serde(default)]
+  review: Vec<ReviewEntry>,
+}
+
+// The decisions of earlier reviews, pre-filled into the review columns of the next report. Rows of the scan are
+// marked as they're published, what's left unmarked no longer matches a scanned package.
+#[derive(Debug, Default)]
+pub struct Reviews {
+  entries: BTreeMap<ReviewKey, ReviewEntry>,
+  seen: HashSet<ReviewKey>,
+}
+
+impl Reviews {
+  // Empty when there's no file yet
+  pub fn load(path: &Path) -> Result<Self> {
+    if !path.exists() {
+      return Ok(Self::default());
+    }
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let file: ReviewFile = toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+    let mut reviews = Self::default();
+    for entry in file.review {
+      let key = (entry.ecosystem.clone(), entry.name.clone(), entry.version.clone());
+      if reviews.entries.insert(key, entry).is_some() {
+        bail!("{} lists a package version twice", path.display());
+      }
+    }
+    Ok(reviews)
+  }
+
+  pub fn save(&self, path: &Path) -> Result<()> {
+    let file = ReviewFile {
+      review: self.entries.values().cloned().collect(),
+    };
+    let content = toml::to_string_pretty(&file).context("Failed to serialize the review decisions")?;
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub fn get(&self, row: &ReportRow) -> Option<&ReviewEntry> {
+    self.entries.get(&key(row))
+  }
+
+  pub fn mark(&mut self, row: &ReportRow) {
+    let key = key(row);
+    if self.entries.contains_key(&key) {
+      self.seen.insert(key);
+    }
+  }
+
+  // Decisions about package versions no marked row had, `name@version (ecosystem)`
+  pub fn stale(&self) -> Vec<String> {
+    self
+      .entries
+      .iter()
+      .filter(|(key, _)| !self.seen.contains(*key))
+      .map(|((ecosystem, name, version), _)| format!("{}@{} ({})", name, version, ecosystem))
+      .collect()
+  }
+}
+
+fn key(row: &ReportRow) -> ReviewKey {
+  (row.ecosystem.to_string(), row.name.clone(), row.version.clone())
+}
+
+/// Reads the review columns of a workbook written with --review-columns back into the decisions file, so the
+/// next run carries them forward. The workbook is the truth for the rows it has: a status or note changed there
+/// replaces the stored one, a row with both cleared drops its decision, and decisions about rows the workbook
+/// doesn't have are kept.
+pub fn import_review(reviewed: &str, review_file: &Path) -> Result<()> {
+  let rows = read_review_rows(Path::new(reviewed))?;
+  let mut reviews = Reviews::load(review_file)?;
+
+  let (mut added, mut updated, mut removed, mut skipped) = (0, 0, 0, 0);
+  for (sheet, entry) in rows {
+    let key = (entry.ecosystem.clone(), entry.name.clone(), entry.version.clone());
+    let status = match entry.status.as_deref().map(normalize_status) {
+      Some(None) => {
+        println!(
+          "{}: {}@{} has the status `{}`, not one of {}; skipped",
+          sheet,
+          entry.name,
+          entry.version,
+          entry.status.unwrap_or_default(),
+          REVIEW_STATUSES.join(", ")
+        );
+        skipped += 1;
+        continue;
+      },
+      Some(Some(status)) => Some(status.to_owned()),
+      None => None,
+    };
+    let entry = ReviewEntry { status, ..entry };
+    match (
+      entry.status.is_none() && entry.notes.is_none(),
+      reviews.entries.get(&key),
+    ) {
+      (true, Some(_)) => {
+        reviews.entries.remove(&key);
+        removed += 1;
+      },
+      (true, None) => {},
+      (false, Some(stored)) if *stored == entry => {},
+      (false, stored) => {
+        match stored {
+          Some(_) => updated += 1,
+          None => added += 1,
+        }
+        reviews.entries.insert(key, entry);
+      },
+    }
+  }
+
+  reviews.save(review_file)?;
+  println!(
+    "Review decisions: {} added, {} updated, {} removed, {} skipped; {} in {}",
+    added,
+    updated,
+    removed,
+    skipped,
+    reviews.len(),
+    review_file.display()
+  );
+  Ok(())
+}
+
+// Excel users retype the dropdown's values, "needs info" is still "Needs info"
+fn normalize_status(status: &str) -> Option<&'static str> {
+  REVIEW_STATUSES
+    .into_iter()
+    .find(|known| known.eq_ignore_ascii_case(status.trim()))
+}
+
+// (sheet, decision) for every row of a dependency sheet with the review columns, the status and notes as written
+fn read_review_rows(path: &Path) -> Result<Vec<(String, ReviewEntry)>> {
+  let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+  let mut archive = ZipArchive::new(file).with_context(|| format!("{} is not an xlsx workbook", path.display()))?;
+  let shared_strings = match archive.index_for_name("xl/sharedStrings.xml") {
+    Some(_) => shared_strings(&read_part(&mut archive, "xl/sharedStrings.xml")?)?,
+    None => Vec::new(),
+  };
+
+  let mut entries = Vec::new();
+  let mut reviewed_sheets = 0;
+  for (sheet, part) in worksheets(&mut archive)? {
+    let rows = sheet_rows(&read_part(&mut archive, &part)?, &shared_strings)
+      .with_context(|| format!("Failed to read sheet {}", sheet))?;
+    let Some(header) = rows.first() else {
+      continue;
+    };
+    let column = |name: &str| header.iter().position(|cell| cell == name);
+    let (Some(name), Some(version), Some(ecosystem), Some(status), Some(notes)) = (
+      column(HEADERS[0]),
+      column(HEADERS[1]),
+      column(ECOSYSTEM_HEADER),
+      column(REVIEW_STATUS_HEADER),
+      column(REVIEWER_NOTES_HEADER),
+    ) else {
+      continue;
+    };
+    reviewed_sheets += 1;
+
+    let cell = |row: &[String], col: usize| {
+      row
+        .get(col)
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+    };
+    for row in &rows[1..] {
+      // Repository group titles of --collapse-by-repo have no version or ecosystem
+      let (Some(name), Some(version), Some(ecosystem)) = (cell(row, name), cell(row, version), cell(row, ecosystem))
+      else {
+        continue;
+      };
+      entries.push((
+        sheet.clone(),
+        ReviewEntry {
+          ecosystem,
+          name,
+          version,
+          status: cell(row, status),
+          notes: cell(row, notes),
+        },
+      ));
+    }
+  }
+
+  if reviewed_sheets == 0 {
+    bail!(
+      "{} has no sheet with review columns, write the report with --review-columns",
+      path.display()
+    );
+  }
+  Ok(entries)
+}
+
+fn read_part(archive: &mut ZipArchive<File>, name: &str) -> Result<String> {
+  let mut content = String::new();
+  archive
+    .by_name(name)
+    .with_context(|| format!("Failed to find {}", name))?
+    .read_to_string(&mut content)
+    .with_context(|| format!("Failed to read {}", name))?;
+  Ok(content)
+}
+
+// (sheet name, part) in workbook order, the parts found through the workbook's relationships
+fn worksheets(archive: &mut ZipArchive<File>) -> Result<Vec<(String, String)>> {
+  let workbook = Element::parse(&read_part(archive, "xl/workbook.xml")?).context("Failed to parse workbook.xml")?;
+  let relationships =
+    Element::parse(&read_part(archive, "xl/_rels/workbook.xml.rels")?).context("Failed to parse workbook.xml.rels")?;
+  let targets = relationships
+    .children("Relationship")
+    .filter_map(|relationship| Some((relationship.attribute("Id")?, relationship.attribute("Target")?)))
+    .collect::<BTreeMap<_, _>>();
+
+  let sheets = workbook
+    .child("sheets")
+    .ok_or_else(|| anyhow!("workbook.xml lists no sheets"))?;
+  Ok(
+    sheets
+      .children("sheet")
+      .filter_map(|sheet| {
+        let target = targets.get(&sheet.attribute("id")?)?;
+        // Relative to xl/ unless absolute within the package
+        let part = match target.strip_prefix('/') {
+          Some(absolute) => absolute.to_owned(),
+          None => format!("xl/{}", target),
+        };
+        Some((sheet.attribute("name")?, part))
+      })
+      .collect(),
+  )
+}
+
+// Rich text strings are runs of <r><t>, plain ones a single <t>
+fn shared_strings(content: &str) -> Result<Vec<String>> {
+  let table = Element::parse(content).context("Failed to parse sharedStrings.xml")?;
+  Ok(table.children("si").map(text_runs).collect())
+}
+
+fn text_runs(element: &Element) -> String {
+  match element.name.as_str() {
+    "t" => element.text.clone(),
+    // Phonetic hints of East Asian text aren't part of the value
+    "rPh" => String::new(),
+    _ => element.children.iter().map(text_runs).collect(),
+  }
+}
+
+// Cell values by row, placed by their column reference since empty cells are left out
+fn sheet_rows(content: &str, shared_strings: &[String]) -> Result<Vec<Vec<String>>> {
+  let worksheet = Element::parse(content)?;
+  let Some(data) = worksheet.child("sheetData") else {
+    return Ok(Vec::new());
+  };
+
+  let mut rows = Vec::new();
+  for row in data.children("row") {
+    let mut values = Vec::new();
+    for (idx, cell) in row.children("c").enumerate() {
+      let col = cell
+        .attribute("r")
+        .and_then(|reference| column_index(&reference))
+        .unwrap_or(idx);
+      let value = match cell.attribute("t").as_deref() {
+        Some("s") => cell
+          .text_of("v")
+          .and_then(|idx| idx.parse::<usize>().ok())
+          .and_then(|idx| shared_strings.get(idx).cloned())
+          .unwrap_or_default(),
+        Some("inlineStr") => cell.child("is").map(text_runs).unwrap_or_default(),
+        _ => cell.text_of("v").unwrap_or_default(),
+      };
+      if values.len() <= col {
+        values.resize(col + 1, String::new());
+      }
+      values[col] = value;
+    }
+    // Rows are numbered from 1 and empty ones skipped, only the header's position matters
+    rows.push(values);
+  }
+  Ok(rows)
+}
+
+// `AB12` -> 27
+fn column_index(reference: &str) -> Option<usize> {
+  let letters = reference
+    .chars()
+    .take_while(char::is_ascii_alphabetic)
+    .collect::<String>();
+  if letters.is_empty() {
+    return None;
+  }
+  let number = letters.chars().fold(0, |number, letter| {
+    number * 26 + (letter.to_ascii_uppercase() as usize - 'A' as usize + 1)
+  });
+  Some(number - 1)
+}
@@ -0,0 +1,298 @@
+use crate::types::ReportRow;
+use anyhow::{anyhow, bail, Context, Result};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+// Carried by every JSON lines row; bump when the row layout changes so `history` can tell old reports apart
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+pub const PROJECTS_SCHEMA_VERSION: u32 = 1;
+pub const BUNDLE_SCHEMA_VERSION: u32 = 1;
+pub const STATS_SCHEMA_VERSION: u32 = 1;
+
+// A JSON output together with its JSON Schema. The documents are kept by hand next to the structs below, printed
+// by `depsfetch schema <name>` and checked against what the structs serialize to by tests/schema.rs.
+pub struct Schema {
+  pub name: &'static str,
+  pub version: u32,
+  document: &'static str,
+}
+
+pub const REPORT: Schema = Schema {
+  name: "report",
+  version: REPORT_SCHEMA_VERSION,
+  document: include_str!("../../data/schemas/report.json"),
+};
+
+pub const PROJECTS: Schema = Schema {
+  name: "projects",
+  version: PROJECTS_SCHEMA_VERSION,
+  document: include_str!("../../data/schemas/projects.json"),
+};
+
+pub const BUNDLE: Schema = Schema {
+  name: "bundle",
+  version: BUNDLE_SCHEMA_VERSION,
+  document: include_str!("../../data/schemas/bundle.json"),
+};
+
+pub const STATS: Schema = Schema {
+  name: "stats",
+  version: STATS_SCHEMA_VERSION,
+  document: include_str!("../../data/schemas/stats.json"),
+};
+
+pub const SCHEMAS: &[Schema] = &[REPORT, PROJECTS, BUNDLE, STATS];
+
+lazy_static! {
+  // Parsed documents by schema name
+  static ref DOCUMENTS: HashMap<&'static str, Value> = SCHEMAS
+    .iter()
+    .map(|schema| {
+      let document = serde_json::from_str(schema.document).expect("Failed to parse embedded JSON schema");
+      (schema.name, document)
+    })
+    .collect();
+}
+
+// A line of the JSON lines report
+#[derive(Serialize)]
+pub struct ReportLine<'a> {
+  pub schema_version: u32,
+  // Active --filter patterns, so a scoped report can't pass for a complete one
+  #[serde(skip_serializing_if = "<[String]>::is_empty")]
+  pub filters: &'a [String],
+  #[serde(flatten)]
+  pub row: &'a ReportRow,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub collapsed_by_repo: Option<CollapsedRows<'a>>,
+}
+
+// --collapse-by-repo: the packages a line stands for, all with the same repository and license
+#[derive(Serialize)]
+pub struct CollapsedRows<'a> {
+  pub repository: &'a str,
+  pub package_count: usize,
+  pub packages: Vec<&'a ReportRow>,
+}
+
+// The per-project counterpart of the JSON lines output, same rows nested under their project
+#[derive(Serialize)]
+pub struct ProjectIndex<'a> {
+  pub schema_version: u32,
+  pub projects: Vec<IndexedProject<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct IndexedProject<'a> {
+  pub name: &'a str,
+  pub file: String,
+  pub rows: Vec<&'a ReportRow>,
+}
+
+// MANIFEST.json of a --bundle archive
+#[derive(Serialize)]
+pub struct BundleManifest<'a> {
+  pub schema_version: u32,
+  pub depsfetch_version: &'a str,
+  pub files: Vec<BundledFile>,
+  pub run_info: Vec<RunSetting<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct BundledFile {
+  pub path: String,
+  pub size: u64,
+  pub sha256: String,
+}
+
+// One line of the run info sheet
+#[derive(Serialize)]
+pub struct RunSetting<'a> {
+  pub setting: &'a str,
+  pub value: &'a str,
+  pub source: &'a str,
+}
+
+// <report>.stats.json: what the Summary sheet counts, for tools that don't read workbooks
+#[derive(Serialize)]
+pub struct StatsDocument {
+  pub schema_version: u32,
+  pub stats: Stats,
+}
+
+#[derive(Serialize)]
+pub struct Stats {
+  pub rows: usize,
+  pub without_license: usize,
+  pub non_spdx: usize,
+  pub repo_mismatches: usize,
+  pub filtered: usize,
+  pub breakdown: BreakdownStats,
+}
+
+// Over the distinct entries of the lockfiles, so transitive dependencies are always in
+#[derive(Serialize)]
+pub struct BreakdownStats {
+  pub distinct: usize,
+  pub transitive_included: bool,
+  pub by_license: Vec<Share>,
+  pub by_scope: Vec<Share>,
+  pub by_ecosystem: Vec<Share>,
+}
+
+#[derive(Serialize)]
+pub struct Share {
+  pub label: String,
+  pub count: usize,
+  // Of the distinct entries, rounded to one decimal like the console summary
+  pub percent: f64,
+}
+
+impl Schema {
+  pub fn find(name: &str) -> Result<&'static Schema> {
+    SCHEMAS.iter().find(|schema| schema.name == name).ok_or_else(|| {
+      anyhow!(
+        "Unknown schema `{}`, expected one of: {}",
+        name,
+        SCHEMAS.iter().map(|schema| schema.name).collect::<Vec<_>>().join(", ")
+      )
+    })
+  }
+
+  // Serialized `value` checked against the document, a failure means the struct and the document drifted apart
+  pub fn check<T: Serialize>(&self, value: &T) -> Result<()> {
+    let value = serde_json::to_value(value).context("Failed to serialize JSON output")?;
+    let document = &DOCUMENTS[self.name];
+    validate(document, document, &value, "").map(drop).with_context(|| {
+      format!(
+        "Output doesn't match the {} schema version {}, this is a bug",
+        self.name, self.version
+      )
+    })
+  }
+}
+
+// `depsfetch schema`: the names and versions, or one document
+pub fn print_schema(name: Option<&str>) -> Result<()> {
+  match name {
+    Some(name) => print!("{}", Schema::find(name)?.document),
+    None => {
+      for schema in SCHEMAS {
+        println!("{} (version {})", schema.name, schema.version);
+      }
+    },
+  }
+  Ok(())
+}
+
+// The subset of JSON Schema the documents use: $ref, allOf, type, const, enum, required, properties,
+// additionalProperties and unevaluatedProperties (only `false`) and items. Returns the fields of an object value
+// that `schema` and the schemas it applies have a property for.
+fn validate(document: &Value, schema: &Value, value: &Value, path: &str) -> Result<BTreeSet<String>> {
+  let mut evaluated = BTreeSet::new();
+  if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+    let (id, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+    let target = if id.is_empty() {
+      document
+    } else {
+      // Another document, by its `$id`
+      DOCUMENTS
+        .values()
+        .find(|document| document["$id"] == id)
+        .ok_or_else(|| anyhow!("unknown schema reference {}", reference))?
+    };
+    let resolved = target
+      .pointer(pointer)
+      .ok_or_else(|| anyhow!("unresolved schema reference {}", reference))?;
+    evaluated.extend(validate(target, resolved, value, path)?);
+  }
+  for part in schema.get("allOf").and_then(Value::as_array).into_iter().flatten() {
+    evaluated.extend(validate(document, part, value, path)?);
+  }
+
+  if let Some(types) = schema.get("type") {
+    let types = match types {
+      Value::Array(types) => types.iter().filter_map(Value::as_str).collect::<Vec<_>>(),
+      other => other.as_str().into_iter().collect(),
+    };
+    if !types.iter().any(|expected| type_matches(expected, value)) {
+      bail!(
+        "{}: expected {}, found {}",
+        pointer_or_root(path),
+        types.join(" or "),
+        value
+      );
+    }
+  }
+  if let Some(expected) = schema.get("const") {
+    if expected != value {
+      bail!("{}: expected {}, found {}", pointer_or_root(path), expected, value);
+    }
+  }
+  if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+    if !allowed.contains(value) {
+      bail!("{}: {} is not one of the allowed values", pointer_or_root(path), value);
+    }
+  }
+
+  if let Value::Object(object) = value {
+    for field in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+      let field = field.as_str().unwrap_or_default();
+      if !object.contains_key(field) {
+        bail!("{}: missing required field {}", pointer_or_root(path), field);
+      }
+    }
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (field, property) in properties.into_iter().flatten() {
+      if let Some(field_value) = object.get(field) {
+        validate(document, property, field_value, &format!("{}/{}", path, field))?;
+      }
+    }
+    let own = properties
+      .into_iter()
+      .flat_map(|properties| properties.keys().cloned())
+      .collect::<BTreeSet<_>>();
+    evaluated.extend(own.iter().cloned());
+    // `additionalProperties` only knows this schema's properties, `unevaluatedProperties` those of the schemas it
+    // applies as well
+    let closed = |keyword: &str| schema.get(keyword) == Some(&Value::Bool(false));
+    let known = match (closed("additionalProperties"), closed("unevaluatedProperties")) {
+      (true, _) => Some(&own),
+      (false, true) => Some(&evaluated),
+      (false, false) => None,
+    };
+    if let Some(field) = known.and_then(|known| object.keys().find(|field| !known.contains(*field))) {
+      bail!("{}: unexpected field {}", pointer_or_root(path), field);
+    }
+  }
+  if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+    for (idx, item) in items.iter().enumerate() {
+      validate(document, item_schema, item, &format!("{}/{}", path, idx))?;
+    }
+  }
+
+  Ok(evaluated)
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+  match expected {
+    "null" => value.is_null(),
+    "boolean" => value.is_boolean(),
+    "object" => value.is_object(),
+    "array" => value.is_array(),
+    "number" => value.is_number(),
+    "integer" => value.is_u64() || value.is_i64(),
+    "string" => value.is_string(),
+    _ => false,
+  }
+}
+
+fn pointer_or_root(path: &str) -> &str {
+  if path.is_empty() {
+    "/"
+  } else {
+    path
+  }
+}
@@ -0,0 +1,96 @@
+use super::{
+  projects::CanonicalMap,
+  schema::{IndexedProject, ProjectIndex, PROJECTS_SCHEMA_VERSION},
+};
+use anyhow::{Context, Result};
+use std::{
+  collections::HashSet,
+  fs,
+  path::{Path, PathBuf},
+};
+
+const INDEX_FILE: &str = "projects.json";
+
+// One workbook per canonical project in `dir`, next to or instead of the combined report
+pub struct ProjectSplit {
+  pub dir: PathBuf,
+  pub map: CanonicalMap,
+}
+
+impl ProjectSplit {
+  pub fn new(dir: &str, map: CanonicalMap) -> Result<Self> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create split directory {}", dir))?;
+    Ok(Self {
+      dir: PathBuf::from(dir),
+      map,
+    })
+  }
+
+  // Sanitized names that only differ by case or by the replaced characters would overwrite each other on some
+  // file systems, later projects get a numeric suffix
+  pub fn file_paths(&self, names: &[&str]) -> Vec<PathBuf> {
+    let mut taken = HashSet::new();
+    names
+      .iter()
+      .map(|name| {
+        let stem = sanitize(name);
+        let mut candidate = stem.clone();
+        let mut suffix = 1;
+        while !taken.insert(candidate.to_lowercase()) {
+          suffix += 1;
+          candidate = format!("{}-{}", stem, suffix);
+        }
+        if candidate != stem {
+          println!(
+            "Project {} collides with another project file name, writing it as {}.xlsx",
+            name, candidate
+          );
+        }
+        self.dir.join(format!("{}.xlsx", candidate))
+      })
+      .collect()
+  }
+
+  // Mapped projects none of the resolved packages belongs to, so a missing file isn't a silent gap
+  pub fn report_empty(&self, written: &[&str]) {
+    for name in self.map.projects().filter(|name| !written.contains(name)) {
+      println!("Project {} has no dependencies in this report, no file written", name);
+    }
+  }
+
+  pub fn write_index(&self, projects: Vec<IndexedProject<'_>>) -> Result<String> {
+    let path = self.dir.join(INDEX_FILE);
+    let index = ProjectIndex {
+      schema_version: PROJECTS_SCHEMA_VERSION,
+      projects,
+    };
+    fs::write(&path, serde_json::to_string_pretty(&index)?)
+      .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(path.display().to_string())
+  }
+}
+
+pub fn file_name(path: &Path) -> String {
+  path
+    .file_name()
+    .map(|name| name.to_string_lossy().into_owned())
+    .unwrap_or_default()
+}
+
+// Letters, digits, `.`, `-` and `_`; anything else (scopes, slashes, spaces) becomes `_`
+fn sanitize(name: &str) -> String {
+  let stem = name
+    .chars()
+    .map(|ch| match ch {
+      'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => ch,
+      _ => '_',
+    })
+    .collect::<String>();
+  let stem = stem.trim_matches(['.', '_']);
+  if stem.is_empty() {
+    "project".to_owned()
+  } else {
+    stem.to_owned()
+  }
+}
@@ -0,0 +1,340 @@
+use super::{
+  repo_groups,
+  schema::{
+    BreakdownStats, CollapsedRows, ReportLine, Share, Stats, StatsDocument, REPORT_SCHEMA_VERSION, STATS_SCHEMA_VERSION,
+  },
+};
+use crate::{
+  license_data::{LicenseCategory, LicenseData},
+  types::{AttestationStatus, DepScope, Ecosystem, ReportRow},
+};
+use anyhow::{Context, Result};
+use std::{
+  collections::{BTreeMap, HashSet},
+  fs::{self, File},
+  io::{BufWriter, Write},
+};
+
+// How many entries the summary lists as the largest
+const LARGEST_PACKAGES: usize = 10;
+
+const CSV_HEADERS: &[&str] = &[
+  "ecosystem",
+  "name",
+  "version",
+  "homepage",
+  "license",
+  "license_url",
+  "license_confidence",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+  Csv,
+  JsonLines,
+}
+
+// Line-oriented output. Rows are only buffered when they have to be sorted or grouped first.
+pub struct StreamWriter {
+  path: String,
+  format: StreamFormat,
+  out: BufWriter<File>,
+  sort: bool,
+  // JSON lines only: rows sharing a repository and license go out as one line, see `hold_for_repo_groups`
+  repo_groups: bool,
+  pending: Vec<ReportRow>,
+  filters: Vec<String>,
+}
+
+// Counters updated as rows are emitted, so they stay correct without keeping the rows around
+#[derive(Debug, Default)]
+pub struct ReportStats {
+  pub rows: usize,
+  pub without_license: usize,
+  // Declared licenses that aren't SPDX ids or known aliases
+  pub non_spdx: usize,
+  pub repo_mismatches: usize,
+  // Rows --nested-notices found a third-party notices file for
+  pub third_party_notices: usize,
+  // Rows with a link --verify-links found dead, None when it didn't check them
+  pub dead_links: Option<usize>,
+  // --revalidate-sample: baseline packages resolved again and those whose license had changed
+  pub revalidated: Option<(usize, usize)>,
+  // --collapse-by-repo: groups on the sheets and the rows they hold, the other counts still see every row
+  pub collapsed_by_repo: Option<(usize, usize)>,
+  // --detect-twins: variants linked to their primary and the rows they had, taken out of `rows` and
+  // `by_ecosystem` unless --count-twins
+  pub twins: Option<(usize, usize)>,
+  // Rows kept out of the sheets by the license filter, still counted above
+  pub filtered: usize,
+  pub by_ecosystem: BTreeMap<Ecosystem, usize>,
+  pub by_attestation: BTreeMap<AttestationStatus, usize>,
+  // The breakdowns count each (ecosystem, name, version) once, however many sheets list it
+  pub breakdown: Breakdown,
+  // --sizes only, counted once per entry like the breakdowns
+  pub sizes: Option<SizeStats>,
+}
+
+// Lockfile entries, so transitive dependencies are included alongside direct ones
+#[derive(Debug, Default)]
+pub struct Breakdown {
+  pub distinct: usize,
+  pub by_category: BTreeMap<LicenseCategory, usize>,
+  pub by_scope: BTreeMap<DepScope, usize>,
+  pub by_ecosystem: BTreeMap<Ecosystem, usize>,
+  seen: HashSet<(Ecosystem, String, String)>,
+}
+
+// Bytes per ecosystem and the largest entries, from the rows that had a size
+#[derive(Debug, Default)]
+pub struct SizeStats {
+  pub by_ecosystem: BTreeMap<Ecosystem, u64>,
+  // (size, ecosystem, name, version), largest first
+  pub largest: Vec<(u64, Ecosystem, String, String)>,
+}
+
+impl StreamWriter {
+  pub fn create(path: &str, format: StreamFormat, sort: bool) -> Result<Self> {
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path))?;
+    let mut writer = Self {
+      path: path.to_owned(),
+      format,
+      out: BufWriter::new(file),
+      sort,
+      repo_groups: false,
+      pending: Vec::new(),
+      filters: Vec::new(),
+    };
+
+    if format == StreamFormat::Csv {
+      writer.write_line(&CSV_HEADERS.join(","))?;
+    }
+    Ok(writer)
+  }
+
+  pub fn with_filters(mut self, filters: &[String]) -> Self {
+    self.filters = filters.to_vec();
+    self
+  }
+
+  // --collapse-by-repo: JSON lines rows are kept until `finish` groups them, CSV stays one row per package
+  pub fn hold_for_repo_groups(&mut self) {
+    self.repo_groups = self.format == StreamFormat::JsonLines;
+  }
+
+  pub fn push(&mut self, row: &ReportRow) -> Result<()> {
+    if self.sort || self.repo_groups {
+      self.pending.push(row.clone());
+      return Ok(());
+    }
+
+    self.write_row(row)
+  }
+
+  // JSON lines only: where the stats go, `deps_report.jsonl` -> `deps_report.stats.json`
+  pub fn stats_path(&self) -> Option<String> {
+    if self.format != StreamFormat::JsonLines {
+      return None;
+    }
+    let stem = self.path.strip_suffix(".jsonl").unwrap_or(&self.path);
+    Some(format!("{}.stats.json", stem))
+  }
+
+  // Rows held back for `finish`, none unless they are sorted or grouped
+  pub fn buffered(&self) -> usize {
+    self.pending.len()
+  }
+
+  pub fn finish(mut self, data: &LicenseData) -> Result<String> {
+    let mut pending = std::mem::take(&mut self.pending);
+    if self.sort {
+      pending.sort_by(|a, b| (a.ecosystem, &a.name, &a.version).cmp(&(b.ecosystem, &b.name, &b.version)));
+    }
+    match self.repo_groups {
+      true => {
+        for group in repo_groups::group_rows(&pending, data) {
+          let collapsed = group.repository.as_deref().map(|repository| CollapsedRows {
+            repository,
+            package_count: group.rows.len(),
+            packages: group.rows.clone(),
+          });
+          self.write_line_for(group.rows[0], collapsed)?;
+        }
+      },
+      false => {
+        for row in &pending {
+          self.write_row(row)?;
+        }
+      },
+    }
+
+    self
+      .out
+      .flush()
+      .with_context(|| format!("Failed to write {}", self.path))?;
+    Ok(self.path)
+  }
+
+  fn write_row(&mut self, row: &ReportRow) -> Result<()> {
+    self.write_line_for(row, None)
+  }
+
+  // A grouped JSON line carries its first row's fields and every row of the group, that one included
+  fn write_line_for(&mut self, row: &ReportRow, collapsed: Option<CollapsedRows<'_>>) -> Result<()> {
+    let line = match self.format {
+      StreamFormat::Csv => [
+        row.ecosystem.to_string().as_str(),
+        &row.name,
+        &row.version,
+        &row.homepage,
+        row.license.as_deref().unwrap_or_default(),
+        row.license_url.as_deref().unwrap_or_default(),
+        &row
+          .license_confidence
+          .map(|confidence| confidence.to_string())
+          .unwrap_or_default(),
+      ]
+      .iter()
+      .map(|field| csv_field(field))
+      .collect::<Vec<_>>()
+      .join(","),
+      StreamFormat::JsonLines => {
+        let line = ReportLine {
+          schema_version: REPORT_SCHEMA_VERSION,
+          filters: &self.filters,
+          row,
+          collapsed_by_repo: collapsed,
+        };
+        serde_json::to_string(&line).context("Failed to serialize report row")?
+      },
+    };
+
+    self.write_line(&line)
+  }
+
+  fn write_line(&mut self, line: &str) -> Result<()> {
+    writeln!(self.out, "{}", line).with_context(|| format!("Failed to write {}", self.path))
+  }
+}
+
+impl ReportStats {
+  pub fn record(&mut self, row: &ReportRow, category: LicenseCategory) {
+    self.rows += 1;
+    if row.license.is_none() {
+      self.without_license += 1;
+    }
+    *self.by_ecosystem.entry(row.ecosystem).or_default() += 1;
+    if let Some(status) = row.attestation {
+      *self.by_attestation.entry(status).or_default() += 1;
+    }
+    let first = self.breakdown.record(row, category);
+    if let (true, Some(sizes), Some(size)) = (first, self.sizes.as_mut(), row.size) {
+      sizes.record(row, size);
+    }
+  }
+
+  pub fn document(&self) -> StatsDocument {
+    let breakdown = &self.breakdown;
+    let [by_license, by_scope, by_ecosystem] = breakdown.blocks().map(|entries| {
+      entries
+        .into_iter()
+        .map(|(label, count)| Share {
+          label,
+          count,
+          percent: (breakdown.share(count) * 1000.0).round() / 10.0,
+        })
+        .collect()
+    });
+    StatsDocument {
+      schema_version: STATS_SCHEMA_VERSION,
+      stats: Stats {
+        rows: self.rows,
+        without_license: self.without_license,
+        non_spdx: self.non_spdx,
+        repo_mismatches: self.repo_mismatches,
+        filtered: self.filtered,
+        breakdown: BreakdownStats {
+          distinct: breakdown.distinct,
+          transitive_included: true,
+          by_license,
+          by_scope,
+          by_ecosystem,
+        },
+      },
+    }
+  }
+
+  pub fn write(&self, path: &str) -> Result<String> {
+    let document = serde_json::to_string_pretty(&self.document()).context("Failed to serialize report stats")?;
+    fs::write(path, document).with_context(|| format!("Failed to write {}", path))?;
+    Ok(path.to_owned())
+  }
+}
+
+impl SizeStats {
+  fn record(&mut self, row: &ReportRow, size: u64) {
+    *self.by_ecosystem.entry(row.ecosystem).or_default() += size;
+    self
+      .largest
+      .push((size, row.ecosystem, row.name.clone(), row.version.clone()));
+    self
+      .largest
+      .sort_by(|a, b| b.0.cmp(&a.0).then_with(|| (a.1, &a.2, &a.3).cmp(&(b.1, &b.2, &b.3))));
+    self.largest.truncate(LARGEST_PACKAGES);
+  }
+}
+
+impl Breakdown {
+  // False when the entry was already counted
+  fn record(&mut self, row: &ReportRow, category: LicenseCategory) -> bool {
+    if !self.seen.insert((row.ecosystem, row.name.clone(), row.version.clone())) {
+      return false;
+    }
+
+    self.distinct += 1;
+    *self.by_category.entry(category).or_default() += 1;
+    *self.by_scope.entry(row.scope).or_default() += 1;
+    *self.by_ecosystem.entry(row.ecosystem).or_default() += 1;
+    true
+  }
+
+  // Share of the distinct entries, 0 when there are none
+  pub fn share(&self, count: usize) -> f64 {
+    if self.distinct == 0 {
+      0.0
+    } else {
+      count as f64 / self.distinct as f64
+    }
+  }
+
+  // (label, count) per block: license category, scope, ecosystem
+  pub fn blocks(&self) -> [Vec<(String, usize)>; 3] {
+    fn entries<K: ToString>(counts: &BTreeMap<K, usize>) -> Vec<(String, usize)> {
+      counts.iter().map(|(key, count)| (key.to_string(), *count)).collect()
+    }
+
+    [
+      entries(&self.by_category),
+      entries(&self.by_scope),
+      entries(&self.by_ecosystem),
+    ]
+  }
+}
+
+// `1234567` -> `1.2 MB`, decimal units as npm prints them
+pub fn human_size(bytes: u64) -> String {
+  match bytes {
+    0..1_000 => format!("{} B", bytes),
+    1_000..1_000_000 => format!("{:.1} kB", bytes as f64 / 1e3),
+    1_000_000..1_000_000_000 => format!("{:.1} MB", bytes as f64 / 1e6),
+    _ => format!("{:.1} GB", bytes as f64 / 1e9),
+  }
+}
+
+pub fn csv_field(value: &str) -> String {
+  if value.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", value.replace('"', "\"\""))
+  } else {
+    value.to_owned()
+  }
+}
@@ -0,0 +1,132 @@
+use super::repo_groups;
+use crate::{
+  license_data::LicenseData,
+  types::{Ecosystem, ReportRow},
+};
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Builds of one project published under a second name: `<name><suffix>` is a variant of `<name>`
+const TWIN_SUFFIXES: &[&str] = &["-es", "-esm", "-cjs", "-compat"];
+// (primary, variant) pairs known to be one project, linked without the repository check; `twin_pairs` in the
+// config adds to them
+const CURATED_TWINS: &[(&str, &str)] = &[("lodash", "lodash-es"), ("rxjs", "rxjs-compat")];
+
+// A variant linked to its primary, with the rows it had
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Twin {
+  pub ecosystem: Ecosystem,
+  pub primary: String,
+  pub variant: String,
+  pub rows: usize,
+}
+
+// What the rows of one package name had, over all its versions and sheets
+#[derive(Default)]
+struct Seen {
+  rows: usize,
+  licenses: BTreeSet<String>,
+  unlicensed: bool,
+  repositories: BTreeSet<String>,
+}
+
+/// Finds packages that are another package under a second name (`lodash-es` for `lodash`), so legal sees one
+/// upstream. A name with one of the known suffixes is a variant of the name without it when both declare the same
+/// repository; curated pairs skip that check. Either way every row of both names has to carry the same license,
+/// twins with differing or unknown licenses stay apart.
+///
+/// ```
+/// use depsfetch::{license_data::LicenseData, report::TwinDetector, types::ReportRow};
+///
+/// # fn example(rows: &[ReportRow]) -> anyhow::Result<()> {
+/// let data = LicenseData::load(None)?;
+/// let mut detector = TwinDetector::new(&Some(vec!["vendor-lib=vendor-lib-legacy".to_owned()]))?;
+/// for row in rows {
+///   detector.observe(row, &data);
+/// }
+/// for twin in detector.twins() {
+///   println!("{} is a build of {}", twin.variant, twin.primary);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct TwinDetector {
+  curated: Vec<(String, String)>,
+  seen: BTreeMap<(Ecosystem, String), Seen>,
+}
+
+impl TwinDetector {
+  // `pairs` are the config's `primary=variant` entries
+  pub fn new(pairs: &Option<Vec<String>>) -> Result<Self> {
+    let mut curated = CURATED_TWINS
+      .iter()
+      .map(|(primary, variant)| (primary.to_string(), variant.to_string()))
+      .collect::<Vec<_>>();
+    for pair in pairs.iter().flatten() {
+      let (primary, variant) = pair
+        .split_once('=')
+        .map(|(primary, variant)| (primary.trim(), variant.trim()))
+        .filter(|(primary, variant)| !primary.is_empty() && !variant.is_empty() && primary != variant)
+        .ok_or_else(|| anyhow!("twin_pairs entry `{}` is not PRIMARY=VARIANT", pair))?;
+      curated.push((primary.to_owned(), variant.to_owned()));
+    }
+
+    Ok(Self {
+      curated,
+      seen: BTreeMap::new(),
+    })
+  }
+
+  pub fn observe(&mut self, row: &ReportRow, data: &LicenseData) {
+    let seen = self.seen.entry((row.ecosystem, row.name.clone())).or_default();
+    seen.rows += 1;
+    match &row.license {
+      Some(license) => {
+        seen.licenses.insert(data.normalize(license));
+      },
+      None => seen.unlicensed = true,
+    }
+    seen.repositories.extend(repo_groups::row_repository(row));
+  }
+
+  // Variants of the observed packages, by name
+  pub fn twins(&self) -> Vec<Twin> {
+    let mut twins = Vec::new();
+    for ((ecosystem, variant), seen) in &self.seen {
+      let curated = self
+        .curated
+        .iter()
+        .filter(|(_, curated)| curated == variant)
+        .map(|(primary, _)| (primary.as_str(), true));
+      let suffixed = TWIN_SUFFIXES
+        .iter()
+        .filter_map(|suffix| variant.strip_suffix(suffix))
+        .filter(|primary| !primary.is_empty())
+        .map(|primary| (primary, false));
+      let linked = curated.chain(suffixed).find(|(primary, curated)| {
+        self
+          .seen
+          .get(&(*ecosystem, primary.to_string()))
+          .is_some_and(|primary| same_license(primary, seen) && (*curated || shared_repository(primary, seen)))
+      });
+      if let Some((primary, _)) = linked {
+        twins.push(Twin {
+          ecosystem: *ecosystem,
+          primary: primary.to_owned(),
+          variant: variant.clone(),
+          rows: seen.rows,
+        });
+      }
+    }
+    twins
+  }
+}
+
+// One license on every row of both, never an unknown one
+fn same_license(primary: &Seen, variant: &Seen) -> bool {
+  !primary.unlicensed && !variant.unlicensed && primary.licenses.len() == 1 && primary.licenses == variant.licenses
+}
+
+fn shared_repository(primary: &Seen, variant: &Seen) -> bool {
+  !primary.repositories.is_disjoint(&variant.repositories)
+}
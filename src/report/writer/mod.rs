@@ -0,0 +1,650 @@
+#[cfg(feature = "xlsx")]
+mod workbook;
+
+use super::{
+  columns::ExtraColumn,
+  constants::MAX_SHEET_HYPERLINKS,
+  dedup::{self, DedupStrategy},
+  license_filter::LicenseFilter,
+  policy::LicensePolicy,
+  post_process::PostProcess,
+  projects::CanonicalMap,
+  redact::Redaction,
+  repo_groups,
+  review::Reviews,
+  split::ProjectSplit,
+  stream::{human_size, ReportStats, SizeStats, StreamWriter},
+  twins::TwinDetector,
+};
+use crate::{
+  deps::{manifests::ManifestTally, patterns::ExclusionRule},
+  graph::DependencyGraph,
+  license_data::LicenseData,
+  types::{Ecosystem, LicenseConfidence, ReportRow, SelfAuditFinding},
+};
+use anyhow::{bail, Result};
+use std::{
+  collections::{BTreeSet, HashMap},
+  sync::{
+    mpsc::{self, Receiver, Sender},
+    Arc,
+  },
+};
+use tokio::task::JoinHandle;
+
+pub struct ResolvedRow {
+  pub sheet: &'static str,
+  pub row: ReportRow,
+}
+
+pub enum WriterMessage {
+  // Boxed, rows are by far the biggest message
+  Row(Box<ResolvedRow>),
+  Section(Section),
+  // --revalidate-sample: baseline rows resolved again and how many of them had drifted, for the summary
+  Revalidated(usize, usize),
+  // Without it the writer treats the run as interrupted and saves nothing
+  Finish,
+}
+
+// Sheets in the order they were announced
+pub enum Section {
+  Dependencies(&'static str),
+  Projects(&'static str, CanonicalMap),
+  // Packages grouped under their license; licenses with at most this many packages share a tail section
+  ByLicense(&'static str, usize),
+  SelfAudit(&'static str, Vec<SelfAuditFinding>),
+  // Each --exclude/--skip rule with how many paths or names it filtered
+  Exclusions(&'static str, Vec<(ExclusionRule, usize)>),
+  // Manifests found and dropped per kind by the type-specific rules
+  Manifests(&'static str, Vec<ManifestTally>),
+  Summary(&'static str),
+  // Setting, value and where it came from; the source is empty for facts about the run
+  RunInfo(&'static str, Vec<(&'static str, String, &'static str)>),
+}
+
+pub struct WriterOutput {
+  // Paths of the finished stream outputs
+  pub streams: Vec<String>,
+  // Per-project workbooks and their JSON index
+  pub split_files: Vec<String>,
+  // Packages breaking the license policy, once each
+  pub violations: Vec<String>,
+  // Every published row, only kept with `keep_rows` for the next --watch cycle
+  pub rows: Vec<ReportRow>,
+}
+
+// Owns everything that ends up in the workbook; runs on its own thread so the resolution never touches a Worksheet.
+// Builds without the `xlsx` feature keep the workbook settings but only ever write the streams.
+#[cfg_attr(not(feature = "xlsx"), allow(dead_code))]
+pub struct ReportWriter {
+  filename: String,
+  graph: Option<DependencyGraph>,
+  extra_columns: Vec<ExtraColumn>,
+  license_filter: Option<LicenseFilter>,
+  license_data: LicenseData,
+  min_confidence: Option<LicenseConfidence>,
+  post_process: Option<PostProcess>,
+  streams: Vec<StreamWriter>,
+  split: Option<ProjectSplit>,
+  // Whether the combined workbook is written at all, only ever false with a split
+  combined: bool,
+  constant_memory: bool,
+  hyperlink_format: bool,
+  // Hyperlinks per dependency sheet; past it URL cells are plain text, or the rows go on a continuation sheet
+  link_limit: usize,
+  split_on_link_limit: bool,
+  dedup: DedupStrategy,
+  // Versions each collapsed row stands for, filled once the sheets are complete
+  other_versions: HashMap<(Ecosystem, String), Vec<String>>,
+  collapse_by_repo: bool,
+  twins: Option<TwinDetector>,
+  // Whether variants still count in the summary's totals
+  count_twins: bool,
+  // (ecosystem, variant) -> primary, filled once the sheets are complete
+  variant_of: HashMap<(Ecosystem, String), String>,
+  policy: LicensePolicy,
+  violations: BTreeSet<String>,
+  kept_rows: Option<Vec<ReportRow>>,
+  redaction: Option<Arc<Redaction>>,
+  reviews: Option<Reviews>,
+  stats: ReportStats,
+}
+
+impl ReportWriter {
+  pub fn new(filename: &str) -> Result<Self> {
+    Ok(Self {
+      filename: filename.to_owned(),
+      graph: None,
+      extra_columns: Vec::new(),
+      license_filter: None,
+      license_data: LicenseData::load(None)?,
+      min_confidence: None,
+      post_process: None,
+      streams: Vec::new(),
+      split: None,
+      combined: true,
+      constant_memory: false,
+      hyperlink_format: true,
+      link_limit: MAX_SHEET_HYPERLINKS,
+      split_on_link_limit: false,
+      dedup: DedupStrategy::All,
+      other_versions: HashMap::new(),
+      collapse_by_repo: false,
+      twins: None,
+      count_twins: false,
+      variant_of: HashMap::new(),
+      policy: LicensePolicy::default(),
+      violations: BTreeSet::new(),
+      kept_rows: None,
+      redaction: None,
+      reviews: None,
+      stats: ReportStats::default(),
+    })
+  }
+
+  pub fn with_graph(mut self, graph: DependencyGraph) -> Self {
+    self.graph = Some(graph);
+    self.extra_columns.push(ExtraColumn::RequiredBy);
+    self
+  }
+
+  pub fn with_funding(mut self) -> Self {
+    self.extra_columns.push(ExtraColumn::Funding);
+    self
+  }
+
+  pub fn with_provenance(mut self) -> Self {
+    self.extra_columns.push(ExtraColumn::Provenance);
+    self
+  }
+
+  pub fn with_license_data(mut self, data: LicenseData) -> Self {
+    self.license_data = data;
+    self
+  }
+
+  // Keep rows whose license matches out of the sheets, they still count and reach the stream outputs
+  pub fn with_license_filter(mut self, filter: LicenseFilter) -> Self {
+    self.license_filter = Some(filter);
+    self
+  }
+
+  pub fn with_repo_mismatch(mut self) -> Self {
+    self.extra_columns.push(ExtraColumn::RepoMismatch);
+    self
+  }
+
+  // Every resolved row is also handed to these writers as soon as it arrives
+  pub fn with_stream(mut self, writer: StreamWriter) -> Self {
+    self.streams.push(writer);
+    self
+  }
+
+  pub fn with_confidence(mut self) -> Self {
+    self.extra_columns.push(ExtraColumn::Confidence);
+    self
+  }
+
+  pub fn with_attestation(mut self) -> Self {
+    self.extra_columns.push(ExtraColumn::Attestation);
+    self
+  }
+
+  // Licenses resolved with less confidence are reported as unknown, the value is kept in the provenance
+  pub fn with_min_confidence(mut self, confidence: LicenseConfidence) -> Self {
+    self.min_confidence = Some(confidence);
+    self
+  }
+
+  // Rows are held back until the resolution is done and go through the script before anything sees them
+  pub fn with_post_process(mut self, post_process: PostProcess) -> Self {
+    self.post_process = Some(post_process);
+    self
+  }
+
+  // A workbook per canonical project with only its rows and its own summary
+  pub fn with_project_split(mut self, split: ProjectSplit, combined: bool) -> Self {
+    self.split = Some(split);
+    self.combined = combined;
+    self
+  }
+
+  pub fn with_platform(mut self) -> Self {
+    self.extra_columns.push(ExtraColumn::Platform);
+    self
+  }
+
+  // Rows go to disk as they are written and repeated URLs to the Links sheet, for workbooks too big to build in
+  // memory
+  pub fn with_constant_memory(mut self) -> Self {
+    self.constant_memory = true;
+    self
+  }
+
+  pub fn without_hyperlink_format(mut self) -> Self {
+    self.hyperlink_format = false;
+    self
+  }
+
+  // Never above what Excel keeps anyway
+  pub fn with_link_limit(mut self, limit: usize) -> Self {
+    self.link_limit = limit.min(MAX_SHEET_HYPERLINKS);
+    self
+  }
+
+  pub fn with_split_on_link_limit(mut self) -> Self {
+    self.split_on_link_limit = true;
+    self
+  }
+
+  // One row per package on the sheets, the streams still list every version
+  pub fn with_dedup(mut self, strategy: DedupStrategy) -> Self {
+    self.dedup = strategy;
+    if strategy != DedupStrategy::All {
+      self.extra_columns.push(ExtraColumn::OtherVersions);
+    }
+    self
+  }
+
+  // OSI approved and FSF libre columns, the flags themselves are on every row of the JSON output
+  pub fn with_license_flags(mut self) -> Self {
+    self.extra_columns.push(ExtraColumn::OsiApproved);
+    self.extra_columns.push(ExtraColumn::FsfLibre);
+    self
+  }
+
+  pub fn with_installed_mismatch(mut self) -> Self {
+    self.extra_columns.push(ExtraColumn::InstalledMismatch);
+    self
+  }
+
+  // The column and its count in the summary
+  pub fn with_third_party_notices(mut self) -> Self {
+    self.extra_columns.push(ExtraColumn::ThirdPartyNotices);
+    self
+  }
+
+  // The column; the summary counts rows with a dead link from the status itself
+  pub fn with_link_status(mut self) -> Self {
+    self.extra_columns.push(ExtraColumn::LinkStatus);
+    self
+  }
+
+  // The column, plus totals per ecosystem and the largest packages in the summary
+  pub fn with_sizes(mut self) -> Self {
+    self.extra_columns.push(ExtraColumn::Size);
+    self.stats.sizes = Some(SizeStats::default());
+    self
+  }
+
+  // Ecosystem, review status and reviewer notes columns, pre-filled with the decisions of earlier reviews
+  pub fn with_review_columns(mut self, reviews: Reviews) -> Self {
+    self.extra_columns.push(ExtraColumn::Ecosystem);
+    self.extra_columns.push(ExtraColumn::ReviewStatus);
+    self.extra_columns.push(ExtraColumn::ReviewerNotes);
+    self.reviews = Some(reviews);
+    self
+  }
+
+  // Rows sharing a repository and license fold into one outlined group on the sheets and one JSON line; the
+  // summary and the CSV output still count and list every row
+  pub fn with_repo_groups(mut self) -> Self {
+    self.collapse_by_repo = true;
+    self
+  }
+
+  // Variants of another package are marked on the sheets and, unless `counted`, left out of the summary's totals.
+  // The streams get rows before all of them are in and list every row as it is.
+  pub fn with_twins(mut self, detector: TwinDetector, counted: bool) -> Self {
+    self.twins = Some(detector);
+    self.count_twins = counted;
+    self.extra_columns.push(ExtraColumn::VariantOf);
+    self
+  }
+
+  pub fn with_policy(mut self, policy: LicensePolicy) -> Self {
+    self.policy = policy;
+    self
+  }
+
+  // Hand every row back with the output, license-filtered ones included, so --watch can compare and reuse them
+  pub fn keep_rows(mut self) -> Self {
+    self.kept_rows = Some(Vec::new());
+    self
+  }
+
+  // Internal packages are redacted as they arrive, before any output, count or check sees them
+  pub fn with_redaction(mut self, redaction: Arc<Redaction>) -> Self {
+    self.redaction = Some(redaction);
+    self
+  }
+
+  // For report formats that are a stream of their own
+  pub fn without_workbook(mut self) -> Self {
+    self.combined = false;
+    self
+  }
+
+  pub fn spawn(self) -> (Sender<WriterMessage>, JoinHandle<Result<Option<WriterOutput>>>) {
+    let (sender, receiver) = mpsc::channel();
+    let handle = tokio::task::spawn_blocking(move || self.run(receiver));
+    (sender, handle)
+  }
+
+  // None when the sender hung up without finishing, the resolution side knows why
+  fn run(mut self, receiver: Receiver<WriterMessage>) -> Result<Option<WriterOutput>> {
+    let mut sections = Vec::new();
+    let mut sheets: Vec<(&'static str, Vec<ReportRow>)> = Vec::new();
+    let mut held_back = Vec::new();
+    let mut finished = false;
+    if self.collapse_by_repo {
+      for stream in self.streams.iter_mut() {
+        stream.hold_for_repo_groups();
+      }
+    }
+    for message in receiver {
+      match message {
+        WriterMessage::Row(resolved) if self.post_process.is_some() => held_back.push(*resolved),
+        WriterMessage::Row(resolved) => self.accept(*resolved, &mut sheets)?,
+        WriterMessage::Section(section) => {
+          if let Section::Dependencies(name) = section {
+            sheets.push((name, Vec::new()));
+          }
+          sections.push(section);
+        },
+        WriterMessage::Revalidated(sampled, drifted) => self.stats.revalidated = Some((sampled, drifted)),
+        WriterMessage::Finish => {
+          finished = true;
+          break;
+        },
+      }
+    }
+    if !finished {
+      return Ok(None);
+    }
+    if let Some(post_process) = self.post_process.take() {
+      let names = sheets.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+      for resolved in post_process.run(&names, held_back)? {
+        self.accept(resolved, &mut sheets)?;
+      }
+    }
+    // Free text on the sheets is scrubbed of every internal name the rows brought up
+    #[cfg(feature = "xlsx")]
+    if let Some(redaction) = &self.redaction {
+      sections = sections.into_iter().map(|section| redaction.section(section)).collect();
+    }
+
+    // Arrival order depends on the network, the sheets don't
+    for (_, rows) in sheets.iter_mut() {
+      rows.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    }
+    for (_, rows) in sheets.iter_mut() {
+      for (key, versions) in dedup::collapse(rows, self.dedup) {
+        let merged = self.other_versions.entry(key).or_default();
+        merged.extend(versions);
+        merged.sort_by(|a, b| dedup::compare_versions(a, b));
+        merged.dedup();
+      }
+    }
+    if self.collapse_by_repo {
+      let grouped = sheets
+        .iter()
+        .flat_map(|(_, rows)| repo_groups::group_rows(rows, &self.license_data))
+        .filter(|group| group.repository.is_some())
+        .fold((0, 0), |(groups, rows), group| (groups + 1, rows + group.rows.len()));
+      self.stats.collapsed_by_repo = Some(grouped);
+    }
+    if let Some(twins) = self.twins.as_ref().map(TwinDetector::twins) {
+      let rows = twins.iter().map(|twin| twin.rows).sum();
+      if !self.count_twins {
+        self.stats.rows -= rows;
+        for twin in &twins {
+          if let Some(count) = self.stats.by_ecosystem.get_mut(&twin.ecosystem) {
+            *count -= twin.rows;
+          }
+        }
+      }
+      self.stats.twins = Some((twins.len(), rows));
+      self.variant_of = twins
+        .into_iter()
+        .map(|twin| ((twin.ecosystem, twin.variant), twin.primary))
+        .collect();
+    }
+
+    #[cfg(feature = "xlsx")]
+    let (split_files, errors) = self.write_workbooks(&sections, &sheets)?;
+    #[cfg(not(feature = "xlsx"))]
+    let split_files = Vec::new();
+
+    self.print_summary();
+    self.print_stale_reviews();
+    #[cfg(feature = "xlsx")]
+    errors.print_summary();
+    // Each JSON lines report gets the summary's numbers in a file next to it
+    let stats_files = self
+      .streams
+      .iter()
+      .filter_map(StreamWriter::stats_path)
+      .collect::<Vec<_>>();
+    let mut streams = self
+      .streams
+      .into_iter()
+      .map(|stream| stream.finish(&self.license_data))
+      .collect::<Result<Vec<_>>>()?;
+    for path in stats_files {
+      streams.push(self.stats.write(&path)?);
+    }
+
+    Ok(Some(WriterOutput {
+      streams,
+      split_files,
+      violations: self.violations.into_iter().collect(),
+      rows: self.kept_rows.unwrap_or_default(),
+    }))
+  }
+
+  fn accept(&mut self, resolved: ResolvedRow, sheets: &mut [(&'static str, Vec<ReportRow>)]) -> Result<()> {
+    let row = match &self.redaction {
+      Some(redaction) => redaction.row(resolved.row),
+      None => resolved.row,
+    };
+    let mut row = self.apply_min_confidence(row);
+    row.osi_approved = self.license_data.osi_approved(row.license.as_deref());
+    row.fsf_libre = self.license_data.fsf_libre(row.license.as_deref());
+    if self.publish(&row)? {
+      match sheets.iter_mut().find(|(name, _)| *name == resolved.sheet) {
+        Some((_, rows)) => rows.push(row),
+        None => bail!("Row for sheet {} arrived before the sheet", resolved.sheet),
+      }
+    }
+
+    Ok(())
+  }
+
+  fn apply_min_confidence(&self, mut row: ReportRow) -> ReportRow {
+    let (Some(min), Some(confidence)) = (self.min_confidence, row.license_confidence) else {
+      return row;
+    };
+    if confidence < min {
+      if let Some(license) = row.license.take() {
+        row
+          .provenance
+          .push(format!("license {} dropped: {} is below {}", license, confidence, min));
+      }
+      row.license_confidence = None;
+    }
+    row
+  }
+
+  // Machine readable outputs and the stats get every row, the sheet only what the license filter lets through.
+  // Returns whether the row belongs on the sheet.
+  fn publish(&mut self, row: &ReportRow) -> Result<bool> {
+    let filtered = match (&self.license_filter, &row.license) {
+      (Some(filter), Some(license)) => filter.matches(&self.license_data, license),
+      _ => false,
+    };
+
+    count_row(&self.license_data, &mut self.stats, row);
+    if let Some(twins) = self.twins.as_mut() {
+      twins.observe(row, &self.license_data);
+    }
+    if let Some(reviews) = self.reviews.as_mut() {
+      reviews.mark(row);
+    }
+    if let Some(kept) = self.kept_rows.as_mut() {
+      kept.push(row.clone());
+    }
+    if let Some(violation) = self.policy.violation(row) {
+      self.violations.insert(violation);
+    }
+    for stream in self.streams.iter_mut() {
+      stream.push(row)?;
+    }
+    if filtered {
+      self.stats.filtered += 1;
+      return Ok(false);
+    }
+
+    Ok(true)
+  }
+
+  // Whether the packages were probed at all, so a zero count is worth showing
+  fn has_notices(&self) -> bool {
+    self.extra_columns.contains(&ExtraColumn::ThirdPartyNotices)
+  }
+
+  // Earlier decisions about package versions this scan no longer has, left in the file for a reviewer to drop
+  fn print_stale_reviews(&self) {
+    let Some(reviews) = &self.reviews else {
+      return;
+    };
+    let stale = reviews.stale();
+    if !stale.is_empty() {
+      println!(
+        "Review: {} of {} decision(s) are stale, no scanned package matches them: {}",
+        stale.len(),
+        reviews.len(),
+        stale.join(", ")
+      );
+    }
+  }
+
+  fn print_summary(&self) {
+    let stats = &self.stats;
+    let by_ecosystem = stats
+      .by_ecosystem
+      .iter()
+      .map(|(ecosystem, count)| format!("{}: {}", ecosystem, count))
+      .collect::<Vec<_>>();
+
+    if by_ecosystem.is_empty() {
+      println!("Summary: no packages resolved");
+      return;
+    }
+
+    println!(
+      "Summary: {} package(s) ({}), {} without license, {} with a non-SPDX license, {} repository mismatch(es)",
+      stats.rows,
+      by_ecosystem.join(", "),
+      stats.without_license,
+      stats.non_spdx,
+      stats.repo_mismatches
+    );
+    if !stats.by_attestation.is_empty() {
+      let counts = stats
+        .by_attestation
+        .iter()
+        .map(|(status, count)| format!("{}: {}", status, count))
+        .collect::<Vec<_>>();
+      println!("Provenance attestations: {}", counts.join(", "));
+    }
+    if self.has_notices() {
+      println!(
+        "Third-party notices: found for {} package(s)",
+        stats.third_party_notices
+      );
+    }
+    if let Some(dead) = stats.dead_links {
+      println!("Link check: {} row(s) with a dead link", dead);
+    }
+    if let Some((sampled, drifted)) = stats.revalidated {
+      println!(
+        "Metadata drift: {} of {} revalidated baseline package(s)",
+        drifted, sampled
+      );
+    }
+    if let Some((groups, rows)) = stats.collapsed_by_repo {
+      println!(
+        "Collapsed by repository: {} row(s) shown as {} group(s) on the sheets, the counts above include every row",
+        rows, groups
+      );
+    }
+    if let Some((variants, rows)) = stats.twins {
+      println!(
+        "Twins: {} package(s) marked as a variant of another, their {} row(s) {}",
+        variants,
+        rows,
+        match self.count_twins {
+          true => "included in the counts above",
+          false => "left out of the counts above",
+        }
+      );
+    }
+    if let Some(filter) = &self.license_filter {
+      println!("Hidden from the sheets: {} row(s) by {}", stats.filtered, filter.rule());
+    }
+    if let Some(sizes) = &stats.sizes {
+      let totals = sizes
+        .by_ecosystem
+        .iter()
+        .map(|(ecosystem, bytes)| format!("{}: {}", ecosystem, human_size(*bytes)))
+        .collect::<Vec<_>>();
+      let largest = sizes
+        .largest
+        .iter()
+        .map(|(bytes, _, name, version)| format!("{}@{} {}", name, version, human_size(*bytes)))
+        .collect::<Vec<_>>();
+      match totals.is_empty() {
+        true => println!("Sizes: none reported"),
+        false => println!("Sizes: {}; largest: {}", totals.join(", "), largest.join(", ")),
+      }
+    }
+    let breakdown = &stats.breakdown;
+    let blocks = ["license", "scope", "ecosystem"]
+      .iter()
+      .zip(breakdown.blocks())
+      .map(|(block, entries)| {
+        let entries = entries
+          .iter()
+          .map(|(label, count)| format!("{} {} ({:.1}%)", label, count, breakdown.share(*count) * 100.0))
+          .collect::<Vec<_>>();
+        format!("by {}: {}", block, entries.join(", "))
+      })
+      .collect::<Vec<_>>();
+    println!(
+      "Breakdown over {} distinct package(s), transitive included; {}",
+      breakdown.distinct,
+      blocks.join("; ")
+    );
+  }
+}
+
+// Everything the summary counts about a row, whichever workbook it ends up in
+fn count_row(data: &LicenseData, stats: &mut ReportStats, row: &ReportRow) {
+  stats.record(row, data.category(row.license.as_deref()));
+  if row.repo_mismatch.is_some() {
+    stats.repo_mismatches += 1;
+  }
+  if row.third_party_notices.is_some() {
+    stats.third_party_notices += 1;
+  }
+  if let Some(status) = &row.link_status {
+    *stats.dead_links.get_or_insert(0) += usize::from(!status.dead.is_empty());
+  }
+  if let Some(license) = &row.license {
+    if !data.expression_ids(license).iter().all(|id| data.is_known(id)) {
+      stats.non_spdx += 1;
+    }
+  }
+}
@@ -0,0 +1,913 @@
+use super::{count_row, ReportWriter, Section};
+use crate::{
+  deps::{manifests::ManifestTally, patterns::ExclusionRule, sheets::shorten_sheet_names},
+  license_data::LicenseData,
+  report::{
+    by_license,
+    cells::WriteErrors,
+    columns::ExtraColumn,
+    constants::{
+      BY_LICENSE_HEADERS, BY_LICENSE_NONE, BY_LICENSE_PACKAGES, EXCLUSION_HEADERS, HEADERS, LINKS_HEADERS,
+      MANIFEST_DISABLED, MANIFEST_ENABLED, MANIFEST_HEADERS, MAX_HYPERLINK_CHARS, MAX_REQUIRED_BY, PROJECT_HEADERS,
+      REPO_GROUP_PACKAGES, REVIEW_STATUS_HEADER, ROW_HEIGHT, RUN_INFO_HEADERS, SELF_AUDIT_HEADERS, SUMMARY_ATTESTATION,
+      SUMMARY_BREAKDOWNS, SUMMARY_BREAKDOWN_HEADERS, SUMMARY_CELL_ERRORS, SUMMARY_COLLAPSED_BY_REPO,
+      SUMMARY_DEAD_LINKS, SUMMARY_DISTINCT, SUMMARY_FILTERED, SUMMARY_LARGEST, SUMMARY_METADATA_DRIFT,
+      SUMMARY_NON_SPDX, SUMMARY_REPO_MISMATCH, SUMMARY_ROW_ERRORS, SUMMARY_SIZES, SUMMARY_THIRD_PARTY_NOTICES,
+      SUMMARY_TOTAL, SUMMARY_WITHOUT_LICENSE,
+    },
+    formatter::WorkbookFormatter,
+    license_filter::LicenseFilter,
+    links::{LinkTable, LINKS_SHEET},
+    projects::{self, CanonicalMap},
+    repo_groups,
+    review::REVIEW_STATUSES,
+    schema::IndexedProject,
+    split::{self, ProjectSplit},
+    stream::{ReportStats, SizeStats},
+  },
+  types::{ReportRow, SelfAuditFinding},
+};
+use anyhow::{Context, Result};
+use std::fs;
+use xlsxwriter::{
+  worksheet::{
+    validation::{DataValidation, DataValidationErrorType, DataValidationType, ErrorAlertOptions},
+    RowColOptions,
+  },
+  Format, Workbook, Worksheet, XlsxError,
+};
+
+// The link table of the workbook, and the hyperlinks written on one worksheet against the limit
+struct SheetLinks<'a> {
+  table: &'a LinkTable,
+  // None when URL cells are plain text anyway
+  limit: Option<usize>,
+  sheet: String,
+  written: usize,
+  // The switch to plain text is logged once per sheet
+  warned: bool,
+}
+
+impl<'a> SheetLinks<'a> {
+  fn new(table: &'a LinkTable, limit: Option<usize>, sheet: &str) -> Self {
+    Self {
+      table,
+      limit,
+      sheet: sheet.to_owned(),
+      written: 0,
+      warned: false,
+    }
+  }
+
+  // A continuation sheet starts from zero
+  fn restart(&mut self, sheet: &str) {
+    *self = Self::new(self.table, self.limit, sheet);
+  }
+
+  // Hyperlinks writing `urls` would add
+  fn needed<'u>(&self, urls: impl IntoIterator<Item = &'u str>) -> usize {
+    match self.limit {
+      Some(_) => urls
+        .into_iter()
+        .filter(|url| self.table.formula(url).is_none() && is_hyperlink(url))
+        .count(),
+      None => 0,
+    }
+  }
+
+  fn has_room(&self, needed: usize) -> bool {
+    self.limit.is_none_or(|limit| self.written + needed <= limit)
+  }
+
+  // Whether `url` goes in as a hyperlink and counts; past the limit it is written as plain text
+  fn take(&mut self, url: &str) -> bool {
+    if self.limit.is_none() || !is_hyperlink(url) {
+      return false;
+    }
+    if self.has_room(1) {
+      self.written += 1;
+      return true;
+    }
+    if !self.warned {
+      println!(
+        "Sheet {} reached {} hyperlinks, the rest of its URLs are written as plain text",
+        self.sheet, self.written
+      );
+      self.warned = true;
+    }
+    false
+  }
+}
+
+// Everything xlsx about the writer, left out of builds without the `xlsx` feature
+impl ReportWriter {
+  // The combined workbook and the per-project ones, with the cells the combined one refused
+  pub(super) fn write_workbooks(
+    &self,
+    sections: &[Section],
+    sheets: &[(&'static str, Vec<ReportRow>)],
+  ) -> Result<(Vec<String>, WriteErrors)> {
+    let mut errors = WriteErrors::default();
+    if self.combined {
+      self.write_combined(sections, sheets, &mut errors)?;
+    }
+    let split_files = match &self.split {
+      Some(split) => self.write_split(split, sheets)?,
+      None => Vec::new(),
+    };
+
+    Ok((split_files, errors))
+  }
+
+  fn write_combined(
+    &self,
+    sections: &[Section],
+    sheets: &[(&'static str, Vec<ReportRow>)],
+    errors: &mut WriteErrors,
+  ) -> Result<()> {
+    let partial = partial_path(&self.filename);
+    let workbook = self.create_workbook(&partial).context("Failed to create workbook")?;
+    let formatter = self.formatter();
+    let links = self.link_table(sheets.iter().flat_map(|(_, rows)| rows));
+    for section in sections {
+      match section {
+        Section::Dependencies(name) => {
+          let rows = sheets
+            .iter()
+            .find(|(sheet, _)| sheet == name)
+            .map_or(&[][..], |(_, rows)| rows);
+          self.write_dependencies(&workbook, &formatter, errors, name, rows, &links)?;
+        },
+        Section::Projects(name, map) => {
+          let rows = sheets
+            .iter()
+            .flat_map(|(_, rows)| rows.iter().cloned())
+            .collect::<Vec<_>>();
+          write_projects(&workbook, &formatter, errors, name, &rows, map)?;
+        },
+        Section::ByLicense(name, collapse_up_to) => {
+          let rows = sheets.iter().flat_map(|(_, rows)| rows.iter()).collect::<Vec<_>>();
+          write_by_license(
+            &workbook,
+            &formatter,
+            errors,
+            name,
+            &rows,
+            &self.license_data,
+            *collapse_up_to,
+          )?;
+        },
+        Section::SelfAudit(name, findings) => write_self_audit(&workbook, &formatter, errors, name, findings)?,
+        Section::Exclusions(name, rules) => write_exclusions(&workbook, &formatter, errors, name, rules)?,
+        Section::Manifests(name, tallies) => write_manifests(&workbook, &formatter, errors, name, tallies)?,
+        Section::Summary(name) => write_summary(
+          &workbook,
+          &formatter,
+          errors,
+          name,
+          &self.stats,
+          self.has_notices(),
+          self.license_filter.as_ref(),
+        )?,
+        Section::RunInfo(name, settings) => write_run_info(&workbook, &formatter, errors, name, settings)?,
+      }
+    }
+    write_links(&workbook, &formatter, errors, &links)?;
+
+    workbook.close().context("Failed to save workbook")?;
+    fs::rename(&partial, &self.filename).with_context(|| format!("Failed to move the workbook to {}", self.filename))
+  }
+
+  // The dependency sheets narrowed to each project's rows, in the combined report's sheet order
+  fn write_split(&self, split: &ProjectSplit, sheets: &[(&'static str, Vec<ReportRow>)]) -> Result<Vec<String>> {
+    let rows = sheets
+      .iter()
+      .flat_map(|(_, rows)| rows.iter().cloned())
+      .collect::<Vec<_>>();
+    let projects = projects::group_rows(&rows, &split.map);
+    let names = projects.iter().map(|project| project.name.as_str()).collect::<Vec<_>>();
+    let paths = split.file_paths(&names);
+
+    let formatter = self.formatter();
+    let mut written = Vec::new();
+    let mut index = Vec::new();
+    for (project, path) in projects.iter().zip(&paths) {
+      let path_str = path.display().to_string();
+      let partial = partial_path(&path_str);
+      let workbook = self
+        .create_workbook(&partial)
+        .with_context(|| format!("Failed to create workbook {}", path_str))?;
+      let mut errors = WriteErrors::default();
+      let mut stats = ReportStats {
+        sizes: self.stats.sizes.as_ref().map(|_| SizeStats::default()),
+        ..ReportStats::default()
+      };
+      let project_sheets = sheets
+        .iter()
+        .map(|(sheet, sheet_rows)| {
+          let project_rows = sheet_rows
+            .iter()
+            .filter(|row| split.map.project_for(&row.name) == project.name)
+            .cloned()
+            .collect::<Vec<_>>();
+          (*sheet, project_rows)
+        })
+        .filter(|(_, project_rows)| !project_rows.is_empty())
+        .collect::<Vec<_>>();
+      let links = self.link_table(project_sheets.iter().flat_map(|(_, rows)| rows));
+      for (sheet, project_rows) in &project_sheets {
+        for row in project_rows {
+          count_row(&self.license_data, &mut stats, row);
+        }
+        self.write_dependencies(&workbook, &formatter, &mut errors, sheet, project_rows, &links)?;
+      }
+      write_summary(
+        &workbook,
+        &formatter,
+        &mut errors,
+        "Summary",
+        &stats,
+        self.has_notices(),
+        None,
+      )?;
+      write_links(&workbook, &formatter, &mut errors, &links)?;
+      workbook
+        .close()
+        .with_context(|| format!("Failed to save workbook {}", path_str))?;
+      fs::rename(&partial, path).with_context(|| format!("Failed to move the workbook to {}", path_str))?;
+
+      println!("Wrote {} ({} row(s))", path_str, project.packages.len());
+      index.push(IndexedProject {
+        name: &project.name,
+        file: split::file_name(path),
+        rows: project.packages.clone(),
+      });
+      written.push(path_str);
+    }
+
+    split.report_empty(&names);
+    written.push(split.write_index(index)?);
+    Ok(written)
+  }
+
+  fn create_workbook(&self, path: &str) -> Result<Workbook, XlsxError> {
+    Workbook::new_with_options(path, self.constant_memory, None, false)
+  }
+
+  fn formatter(&self) -> WorkbookFormatter {
+    match self.hyperlink_format {
+      true => WorkbookFormatter::new(),
+      false => WorkbookFormatter::new().without_url_format(),
+    }
+  }
+
+  fn link_table<'a>(&self, rows: impl IntoIterator<Item = &'a ReportRow>) -> LinkTable {
+    match self.constant_memory {
+      true => LinkTable::collect(rows),
+      false => LinkTable::default(),
+    }
+  }
+
+  fn write_dependencies(
+    &self,
+    workbook: &Workbook,
+    formatter: &WorkbookFormatter,
+    errors: &mut WriteErrors,
+    sheet_name: &str,
+    rows: &[ReportRow],
+    links: &LinkTable,
+  ) -> Result<()> {
+    let limit = self.hyperlink_format.then_some(self.link_limit);
+    let mut links = SheetLinks::new(links, limit, sheet_name);
+    let mut worksheet = self.create_dependency_sheet(workbook, sheet_name)?;
+    let mut part = 1;
+    let mut line = 1;
+
+    if !self.collapse_by_repo {
+      for row in rows {
+        if self.spills(&links, line, links.needed(row_urls(row))) {
+          self.add_review_dropdown(&mut worksheet, line - 1)?;
+          part += 1;
+          worksheet = self.continue_sheet(workbook, &mut links, sheet_name, part)?;
+          line = 1;
+        }
+        self.write_row(&mut worksheet, formatter, errors, &mut links, line, row);
+        errors.end_row();
+        line += 1;
+      }
+      return self.add_review_dropdown(&mut worksheet, line - 1);
+    }
+
+    // A bold row per group above its rows, which are hidden one outline level down. Groups stay on one sheet.
+    for group in repo_groups::group_rows(rows, &self.license_data) {
+      let urls = group
+        .repository
+        .as_deref()
+        .into_iter()
+        .chain(group.rows.iter().flat_map(|row| row_urls(row)));
+      if self.spills(&links, line, links.needed(urls)) {
+        self.add_review_dropdown(&mut worksheet, line - 1)?;
+        part += 1;
+        worksheet = self.continue_sheet(workbook, &mut links, sheet_name, part)?;
+        line = 1;
+      }
+      let level = match &group.repository {
+        Some(repository) => {
+          worksheet
+            .set_row_opt(line, ROW_HEIGHT, None, &RowColOptions::new(false, 0, true))
+            .context("Failed to set up a repository group")?;
+          let title = format!("{} ({} {})", repository, group.rows.len(), REPO_GROUP_PACKAGES);
+          errors.write(&mut worksheet, line, 0, &title, formatter.bold_format());
+          write_link(
+            &mut worksheet,
+            errors,
+            &mut links,
+            line,
+            2,
+            repository,
+            formatter.url_format(),
+          );
+          if let Some(license) = &group.rows[0].license {
+            errors.write(&mut worksheet, line, 3, license, None);
+          }
+          errors.end_row();
+          line += 1;
+          Some(RowColOptions::new(true, 1, false))
+        },
+        None => None,
+      };
+      for row in group.rows {
+        if let Some(level) = &level {
+          worksheet
+            .set_row_opt(line, ROW_HEIGHT, None, level)
+            .context("Failed to set up a repository group")?;
+        }
+        self.write_row(&mut worksheet, formatter, errors, &mut links, line, row);
+        errors.end_row();
+        line += 1;
+      }
+    }
+
+    self.add_review_dropdown(&mut worksheet, line - 1)
+  }
+
+  fn create_dependency_sheet<'a>(&self, workbook: &'a Workbook, sheet_name: &str) -> Result<Worksheet<'a>> {
+    let mut worksheet = create_worksheet(workbook, sheet_name)?;
+    for (col, header) in HEADERS.iter().enumerate() {
+      worksheet
+        .write_string(0, col as u16, header, None)
+        .context("Failed to write header")?;
+    }
+    for (idx, column) in self.extra_columns.iter().enumerate() {
+      worksheet
+        .write_string(0, (HEADERS.len() + idx) as u16, column.header(), None)
+        .context("Failed to write header")?;
+    }
+    if self.collapse_by_repo {
+      worksheet.outline_settings(true, false, true, false);
+    }
+    Ok(worksheet)
+  }
+
+  // With --split-on-link-limit, rows that don't fit the sheet's hyperlinks go on the next part; a sheet always
+  // takes its first rows, whatever they need
+  fn spills(&self, links: &SheetLinks<'_>, line: u32, needed: usize) -> bool {
+    self.split_on_link_limit && line > 1 && !links.has_room(needed)
+  }
+
+  // `Name (2)`, `Name (3)`, ... fitted into Excel's sheet name length
+  fn continue_sheet<'a>(
+    &self,
+    workbook: &'a Workbook,
+    links: &mut SheetLinks<'_>,
+    sheet_name: &str,
+    part: usize,
+  ) -> Result<Worksheet<'a>> {
+    let name = shorten_sheet_names(&[&format!("{} ({})", sheet_name, part)])?.remove(0);
+    println!(
+      "Sheet {} reached {} hyperlinks, continued on {}",
+      links.sheet, links.written, name
+    );
+    links.restart(&name);
+    self.create_dependency_sheet(workbook, &name)
+  }
+
+  // The status cells of rows 1..=last_row only take the review statuses, picked from a dropdown
+  fn add_review_dropdown(&self, worksheet: &mut Worksheet<'_>, last_row: u32) -> Result<()> {
+    let Some(idx) = self
+      .extra_columns
+      .iter()
+      .position(|column| *column == ExtraColumn::ReviewStatus)
+    else {
+      return Ok(());
+    };
+    if last_row == 0 {
+      return Ok(());
+    }
+    let col = (HEADERS.len() + idx) as u16;
+    let validation = DataValidation::new(
+      DataValidationType::List {
+        ignore_blank: true,
+        dropdown: true,
+        values: REVIEW_STATUSES.iter().map(|status| status.to_string()).collect(),
+      },
+      None,
+      Some(ErrorAlertOptions {
+        style: DataValidationErrorType::Stop,
+        title: REVIEW_STATUS_HEADER.to_owned(),
+        message: REVIEW_STATUSES.join(" / "),
+      }),
+    );
+    worksheet
+      .data_validation_range(1, col, last_row, col, &validation)
+      .context("Failed to add the review status dropdown")
+  }
+
+  fn write_row(
+    &self,
+    worksheet: &mut Worksheet<'_>,
+    formatter: &WorkbookFormatter,
+    errors: &mut WriteErrors,
+    links: &mut SheetLinks<'_>,
+    row: u32,
+    report_row: &ReportRow,
+  ) {
+    errors.write(worksheet, row, 0, &report_row.name, None);
+    errors.write(worksheet, row, 1, &report_row.version, None);
+    write_link(
+      worksheet,
+      errors,
+      links,
+      row,
+      2,
+      &report_row.homepage,
+      formatter.url_format(),
+    );
+
+    if let Some(license) = &report_row.license {
+      errors.write(worksheet, row, 3, license, None);
+    }
+    if let Some(license_url) = &report_row.license_url {
+      write_link(worksheet, errors, links, row, 4, license_url, formatter.url_format());
+    }
+    for (idx, column) in self.extra_columns.iter().enumerate() {
+      let col = (HEADERS.len() + idx) as u16;
+      match (column, report_row.size) {
+        // Bytes shown through a number format, so the column sorts by size
+        (ExtraColumn::Size, Some(size)) => {
+          errors.write_number(worksheet, row, col, size as f64, formatter.size_format())
+        },
+        _ => {
+          if let Some(value) = self.extra_column_value(*column, report_row) {
+            errors.write(worksheet, row, col, &value, None);
+          }
+        },
+      }
+    }
+  }
+
+  fn extra_column_value(&self, column: ExtraColumn, report_row: &ReportRow) -> Option<String> {
+    match column {
+      ExtraColumn::RequiredBy => self
+        .graph
+        .as_ref()
+        .and_then(|graph| graph.required_by(&report_row.name, MAX_REQUIRED_BY)),
+      ExtraColumn::Funding => report_row.funding.clone().filter(|funding| !funding.is_empty()),
+      ExtraColumn::Provenance => Some(report_row.provenance.join("; ")).filter(|provenance| !provenance.is_empty()),
+      ExtraColumn::Platform => report_row.platform.clone(),
+      ExtraColumn::RepoMismatch => report_row.repo_mismatch.clone(),
+      ExtraColumn::Confidence => report_row.license_confidence.map(|confidence| confidence.to_string()),
+      ExtraColumn::Attestation => report_row.attestation.map(|status| status.to_string()),
+      ExtraColumn::OtherVersions => self
+        .other_versions
+        .get(&(report_row.ecosystem, report_row.name.clone()))
+        .map(|versions| versions.join(", ")),
+      ExtraColumn::OsiApproved => report_row.osi_approved.map(|flag| flag.to_string()),
+      ExtraColumn::FsfLibre => report_row.fsf_libre.map(|flag| flag.to_string()),
+      ExtraColumn::InstalledMismatch => report_row.installed_mismatch.clone(),
+      ExtraColumn::ThirdPartyNotices => report_row.third_party_notices.clone(),
+      ExtraColumn::LinkStatus => report_row.link_status.as_ref().map(ToString::to_string),
+      // A number cell, see `write_row`
+      ExtraColumn::Size => None,
+      ExtraColumn::Ecosystem => Some(report_row.ecosystem.to_string()),
+      ExtraColumn::ReviewStatus => self
+        .reviews
+        .as_ref()
+        .and_then(|reviews| reviews.get(report_row)?.status.clone()),
+      ExtraColumn::ReviewerNotes => self
+        .reviews
+        .as_ref()
+        .and_then(|reviews| reviews.get(report_row)?.notes.clone()),
+      ExtraColumn::VariantOf => self
+        .variant_of
+        .get(&(report_row.ecosystem, report_row.name.clone()))
+        .map(|primary| format!("variant of {}", primary)),
+    }
+  }
+}
+
+// Every URL cell goes through here: a formula for URLs on the Links sheet, a hyperlink while the sheet has room for
+// one, plain text otherwise
+fn write_link(
+  worksheet: &mut Worksheet<'_>,
+  errors: &mut WriteErrors,
+  links: &mut SheetLinks<'_>,
+  row: u32,
+  col: u16,
+  url: &str,
+  format: Option<&Format>,
+) {
+  match links.table.formula(url) {
+    Some(formula) => errors.write_formula(worksheet, row, col, &formula, url, format),
+    None if links.take(url) => errors.write_url(worksheet, row, col, url, format),
+    None => errors.write(worksheet, row, col, url, format),
+  }
+}
+
+// The URL cells of a dependency row
+fn row_urls(row: &ReportRow) -> impl Iterator<Item = &str> {
+  std::iter::once(row.homepage.as_str()).chain(row.license_url.as_deref())
+}
+
+// Anything else, or a URL Excel can't hold as a link, stays text
+fn is_hyperlink(url: &str) -> bool {
+  (url.starts_with("https://") || url.starts_with("http://"))
+    && url.chars().count() <= MAX_HYPERLINK_CHARS
+    && !url.chars().any(char::is_control)
+}
+
+fn write_summary(
+  workbook: &Workbook,
+  formatter: &WorkbookFormatter,
+  errors: &mut WriteErrors,
+  sheet_name: &str,
+  stats: &ReportStats,
+  notices: bool,
+  license_filter: Option<&LicenseFilter>,
+) -> Result<()> {
+  let mut lines = vec![(SUMMARY_TOTAL.to_owned(), stats.rows.to_string())];
+  lines.extend(
+    stats
+      .by_ecosystem
+      .iter()
+      .map(|(ecosystem, count)| (ecosystem.to_string(), count.to_string())),
+  );
+  lines.push((SUMMARY_WITHOUT_LICENSE.to_owned(), stats.without_license.to_string()));
+  lines.push((SUMMARY_NON_SPDX.to_owned(), stats.non_spdx.to_string()));
+  lines.push((SUMMARY_REPO_MISMATCH.to_owned(), stats.repo_mismatches.to_string()));
+  if notices {
+    lines.push((
+      SUMMARY_THIRD_PARTY_NOTICES.to_owned(),
+      stats.third_party_notices.to_string(),
+    ));
+  }
+  if let Some(dead) = stats.dead_links {
+    lines.push((SUMMARY_DEAD_LINKS.to_owned(), dead.to_string()));
+  }
+  if let Some((sampled, drifted)) = stats.revalidated {
+    lines.push((SUMMARY_METADATA_DRIFT.to_owned(), format!("{} / {}", drifted, sampled)));
+  }
+  if let Some((groups, rows)) = stats.collapsed_by_repo {
+    lines.push((SUMMARY_COLLAPSED_BY_REPO.to_owned(), format!("{} / {}", rows, groups)));
+  }
+  lines.extend(
+    stats
+      .by_attestation
+      .iter()
+      .map(|(status, count)| (format!("{}: {}", SUMMARY_ATTESTATION, status), count.to_string())),
+  );
+  if let Some(filter) = license_filter {
+    lines.push((
+      SUMMARY_FILTERED.to_owned(),
+      format!("{} ({})", stats.filtered, filter.rule()),
+    ));
+  }
+  // Sheets written after this one can't add to the counts, the console summary has the final numbers
+  lines.push((SUMMARY_CELL_ERRORS.to_owned(), errors.cells.to_string()));
+  lines.push((SUMMARY_ROW_ERRORS.to_owned(), errors.rows.to_string()));
+
+  let mut worksheet = create_worksheet(workbook, sheet_name)?;
+  for (row, (label, value)) in lines.iter().enumerate() {
+    errors.write(&mut worksheet, row as u32, 0, label, formatter.bold_format());
+    errors.write(&mut worksheet, row as u32, 1, value, None);
+    errors.end_row();
+  }
+
+  // Pivot-style blocks below the totals, shares as percentage cells
+  let breakdown = &stats.breakdown;
+  let mut row = lines.len() as u32 + 1;
+  errors.write(&mut worksheet, row, 0, SUMMARY_DISTINCT, formatter.bold_format());
+  errors.write_number(&mut worksheet, row, 1, breakdown.distinct as f64, None);
+  errors.end_row();
+  for (title, entries) in SUMMARY_BREAKDOWNS.iter().zip(breakdown.blocks()) {
+    row += 2;
+    errors.write(&mut worksheet, row, 0, title, formatter.bold_format());
+    for (col, header) in SUMMARY_BREAKDOWN_HEADERS.iter().enumerate() {
+      errors.write(&mut worksheet, row, (col + 1) as u16, header, formatter.bold_format());
+    }
+    errors.end_row();
+
+    for (label, count) in entries {
+      row += 1;
+      errors.write(&mut worksheet, row, 0, &label, None);
+      errors.write_number(&mut worksheet, row, 1, count as f64, None);
+      errors.write_number(
+        &mut worksheet,
+        row,
+        2,
+        breakdown.share(count),
+        formatter.percent_format(),
+      );
+      errors.end_row();
+    }
+  }
+
+  // --sizes: bytes in the size format like the column, totals per ecosystem then the largest entries
+  if let Some(sizes) = &stats.sizes {
+    let totals = sizes
+      .by_ecosystem
+      .iter()
+      .map(|(ecosystem, bytes)| (ecosystem.to_string(), *bytes))
+      .collect::<Vec<_>>();
+    let largest = sizes
+      .largest
+      .iter()
+      .map(|(bytes, ecosystem, name, version)| (format!("{}@{} ({})", name, version, ecosystem), *bytes))
+      .collect::<Vec<_>>();
+    for (title, entries) in [(SUMMARY_SIZES, totals), (SUMMARY_LARGEST, largest)] {
+      row += 2;
+      errors.write(&mut worksheet, row, 0, title, formatter.bold_format());
+      errors.end_row();
+      for (label, bytes) in entries {
+        row += 1;
+        errors.write(&mut worksheet, row, 0, &label, None);
+        errors.write_number(&mut worksheet, row, 1, bytes as f64, formatter.size_format());
+        errors.end_row();
+      }
+    }
+  }
+
+  Ok(())
+}
+
+fn write_by_license(
+  workbook: &Workbook,
+  formatter: &WorkbookFormatter,
+  errors: &mut WriteErrors,
+  sheet_name: &str,
+  rows: &[&ReportRow],
+  data: &LicenseData,
+  collapse_up_to: usize,
+) -> Result<()> {
+  let mut worksheet = create_worksheet(workbook, sheet_name)?;
+  for (col, header) in BY_LICENSE_HEADERS.iter().enumerate() {
+    worksheet
+      .write_string(0, col as u16, header, None)
+      .context("Failed to write header")?;
+  }
+
+  let mut row = 1;
+  for section in by_license::group_rows(rows, data, collapse_up_to) {
+    let title = match section.category {
+      Some(category) => format!(
+        "{} ({}, {} {})",
+        section.title,
+        category,
+        section.rows.len(),
+        BY_LICENSE_PACKAGES
+      ),
+      None => format!("{} ({} {})", section.title, section.rows.len(), BY_LICENSE_PACKAGES),
+    };
+    errors.write(&mut worksheet, row, 0, &title, formatter.bold_format());
+    errors.end_row();
+    row += 1;
+
+    for package in &section.rows {
+      if section.collapsed {
+        errors.write(
+          &mut worksheet,
+          row,
+          0,
+          package.license.as_deref().unwrap_or(BY_LICENSE_NONE),
+          None,
+        );
+      }
+      errors.write(&mut worksheet, row, 1, &package.ecosystem.to_string(), None);
+      errors.write(&mut worksheet, row, 2, &package.name, None);
+      errors.write(&mut worksheet, row, 3, &package.version, None);
+      if let Some(url) = &package.license_url {
+        errors.write(&mut worksheet, row, 4, url, None);
+      }
+      errors.end_row();
+      row += 1;
+    }
+  }
+
+  Ok(())
+}
+
+fn write_projects(
+  workbook: &Workbook,
+  formatter: &WorkbookFormatter,
+  errors: &mut WriteErrors,
+  sheet_name: &str,
+  rows: &[ReportRow],
+  map: &CanonicalMap,
+) -> Result<()> {
+  let mut worksheet = create_worksheet(workbook, sheet_name)?;
+  for (col, header) in PROJECT_HEADERS.iter().enumerate() {
+    worksheet
+      .write_string(0, col as u16, header, None)
+      .context("Failed to write header")?;
+  }
+
+  let mut row = 1;
+  for project in projects::group_rows(rows, map) {
+    errors.write(&mut worksheet, row, 0, &project.name, formatter.bold_format());
+    errors.write(
+      &mut worksheet,
+      row,
+      3,
+      &project.versions().join(", "),
+      formatter.bold_format(),
+    );
+    errors.write(
+      &mut worksheet,
+      row,
+      4,
+      &project.licenses().join(", "),
+      formatter.bold_format(),
+    );
+    errors.end_row();
+    row += 1;
+
+    for package in &project.packages {
+      errors.write(&mut worksheet, row, 1, &package.name, None);
+      errors.write(&mut worksheet, row, 2, &package.ecosystem.to_string(), None);
+      errors.write(&mut worksheet, row, 3, &package.version, None);
+      if let Some(license) = &package.license {
+        errors.write(&mut worksheet, row, 4, license, None);
+      }
+      errors.end_row();
+      row += 1;
+    }
+  }
+
+  Ok(())
+}
+
+fn write_self_audit(
+  workbook: &Workbook,
+  formatter: &WorkbookFormatter,
+  errors: &mut WriteErrors,
+  sheet_name: &str,
+  findings: &[SelfAuditFinding],
+) -> Result<()> {
+  let mut worksheet = create_worksheet(workbook, sheet_name)?;
+  for (col, header) in SELF_AUDIT_HEADERS.iter().enumerate() {
+    worksheet
+      .write_string(0, col as u16, header, formatter.bold_format())
+      .context("Failed to write header")?;
+  }
+
+  for (row, finding) in findings.iter().enumerate() {
+    errors.write(&mut worksheet, (row + 1) as u32, 0, &finding.manifest, None);
+    errors.write(&mut worksheet, (row + 1) as u32, 1, &finding.package, None);
+    errors.write(&mut worksheet, (row + 1) as u32, 2, &finding.issue, None);
+    errors.end_row();
+  }
+
+  Ok(())
+}
+
+fn write_exclusions(
+  workbook: &Workbook,
+  formatter: &WorkbookFormatter,
+  errors: &mut WriteErrors,
+  sheet_name: &str,
+  rules: &[(ExclusionRule, usize)],
+) -> Result<()> {
+  let mut worksheet = create_worksheet(workbook, sheet_name)?;
+  for (col, header) in EXCLUSION_HEADERS.iter().enumerate() {
+    worksheet
+      .write_string(0, col as u16, header, formatter.bold_format())
+      .context("Failed to write header")?;
+  }
+
+  for (idx, (rule, filtered)) in rules.iter().enumerate() {
+    let row = (idx + 1) as u32;
+    errors.write(&mut worksheet, row, 0, rule.option, None);
+    errors.write(&mut worksheet, row, 1, &rule.pattern, None);
+    for (col, value) in [&rule.reason, &rule.added_by, &rule.date].into_iter().enumerate() {
+      if let Some(value) = value {
+        errors.write(&mut worksheet, row, (col + 2) as u16, value, None);
+      }
+    }
+    errors.write_number(&mut worksheet, row, 5, *filtered as f64, None);
+    errors.end_row();
+  }
+
+  Ok(())
+}
+
+fn write_manifests(
+  workbook: &Workbook,
+  formatter: &WorkbookFormatter,
+  errors: &mut WriteErrors,
+  sheet_name: &str,
+  tallies: &[ManifestTally],
+) -> Result<()> {
+  let mut worksheet = create_worksheet(workbook, sheet_name)?;
+  for (col, header) in MANIFEST_HEADERS.iter().enumerate() {
+    worksheet
+      .write_string(0, col as u16, header, formatter.bold_format())
+      .context("Failed to write header")?;
+  }
+
+  for (idx, tally) in tallies.iter().enumerate() {
+    let row = (idx + 1) as u32;
+    errors.write(&mut worksheet, row, 0, tally.kind, None);
+    errors.write(
+      &mut worksheet,
+      row,
+      1,
+      if tally.enabled {
+        MANIFEST_ENABLED
+      } else {
+        MANIFEST_DISABLED
+      },
+      None,
+    );
+    errors.write_number(&mut worksheet, row, 2, tally.found as f64, None);
+    errors.write_number(&mut worksheet, row, 3, tally.excluded as f64, None);
+    errors.end_row();
+  }
+
+  Ok(())
+}
+
+fn write_run_info(
+  workbook: &Workbook,
+  formatter: &WorkbookFormatter,
+  errors: &mut WriteErrors,
+  sheet_name: &str,
+  settings: &[(&str, String, &str)],
+) -> Result<()> {
+  let mut worksheet = create_worksheet(workbook, sheet_name)?;
+  for (col, header) in RUN_INFO_HEADERS.iter().enumerate() {
+    worksheet
+      .write_string(0, col as u16, header, formatter.bold_format())
+      .context("Failed to write header")?;
+  }
+
+  for (row, (name, value, source)) in settings.iter().enumerate() {
+    errors.write(&mut worksheet, (row + 1) as u32, 0, name, None);
+    errors.write(&mut worksheet, (row + 1) as u32, 1, value, None);
+    if !source.is_empty() {
+      errors.write(&mut worksheet, (row + 1) as u32, 2, source, None);
+    }
+    errors.end_row();
+  }
+
+  Ok(())
+}
+
+// Nothing to write unless some URL was shared
+fn write_links(
+  workbook: &Workbook,
+  formatter: &WorkbookFormatter,
+  errors: &mut WriteErrors,
+  links: &LinkTable,
+) -> Result<()> {
+  if links.is_empty() {
+    return Ok(());
+  }
+
+  let mut worksheet = create_worksheet(workbook, LINKS_SHEET)?;
+  for (col, header) in LINKS_HEADERS.iter().enumerate() {
+    worksheet
+      .write_string(0, col as u16, header, formatter.bold_format())
+      .context("Failed to write header")?;
+  }
+
+  for (idx, (url, cells)) in links.links().iter().enumerate() {
+    let row = (idx + 1) as u32;
+    errors.write(&mut worksheet, row, 0, url, None);
+    errors.write_number(&mut worksheet, row, 1, *cells as f64, None);
+    errors.end_row();
+  }
+
+  Ok(())
+}
+
+fn create_worksheet<'a>(workbook: &'a Workbook, name: &str) -> Result<Worksheet<'a>> {
+  workbook
+    .add_worksheet(Some(name))
+    .with_context(|| format!("Failed to create worksheet {}", name))
+}
+
+// Workbooks are built next to their target and renamed over it once complete, a reader (or a --watch cycle) never
+// finds half of one
+fn partial_path(path: &str) -> String {
+  format!("{}.partial", path)
+}
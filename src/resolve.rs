@@ -0,0 +1,159 @@
+use crate::{
+  cache::BranchCache,
+  diagnostics::{Category, Diagnostics},
+  http::MetadataBackend,
+  report::{NpmRegistry, Resolution, Resolver},
+  types::{DepsEntry, Ecosystem, ReportRow},
+  version::PrereleasePolicy,
+};
+use futures_util::{stream, Stream, StreamExt};
+use std::{future::Future, sync::Arc};
+use tokio::sync::Semaphore;
+
+// How `resolve` goes about it: the ecosystem the entries belong to, and what bounds it
+pub struct ResolveOptions {
+  ecosystem: Ecosystem,
+  permits: Option<Arc<Semaphore>>,
+  registry: NpmRegistry,
+  prerelease: PrereleasePolicy,
+  branches: Arc<BranchCache>,
+}
+
+impl ResolveOptions {
+  pub fn new(ecosystem: Ecosystem) -> Self {
+    Self {
+      ecosystem,
+      permits: None,
+      registry: NpmRegistry::default(),
+      prerelease: PrereleasePolicy::default(),
+      branches: Arc::default(),
+    }
+  }
+
+  // Entries resolve side by side, each holding a permit while it does; without permits they go one at a time.
+  // Shared with the caller's other work, it bounds everything the caller runs alongside, several `resolve` calls
+  // included.
+  pub fn with_permits(mut self, permits: Arc<Semaphore>) -> Self {
+    self.permits = Some(permits);
+    self
+  }
+
+  // Which pre-releases ranges and dist-tags may resolve to, npm's rule unless set
+  pub fn with_prerelease(mut self, policy: PrereleasePolicy) -> Self {
+    self.prerelease = policy;
+    self
+  }
+
+  // npm packages come from this registry instead of public npm
+  pub fn with_npm_registry(mut self, registry: NpmRegistry) -> Self {
+    self.registry = registry;
+    self
+  }
+
+  // Repository default branches found so far, e.g. `BranchCache::load()` to reuse and keep them between runs (the
+  // caller saves it). Without one they are looked up again and forgotten after the call, nothing touches the disk.
+  pub fn with_branch_cache(mut self, branches: Arc<BranchCache>) -> Self {
+    self.branches = branches;
+    self
+  }
+}
+
+// One entry as `resolve` found it: its row unless the registry had nothing to build one from (an npm package not
+// installable on the platform), and the warnings its lookup raised
+#[derive(Debug)]
+pub struct ResolvedDependency {
+  pub entry: DepsEntry,
+  pub row: Option<ReportRow>,
+  pub warnings: Vec<(Category, String)>,
+}
+
+/// Looks up the license of every entry through `backend`, as many at once as the permits allow and one after the
+/// other without them, and returns them in the order they came. Nothing is spawned, nothing printed, and nothing
+/// read from or written to disk: what went wrong with an entry is in its warnings, failures included.
+///
+/// ```
+/// use depsfetch::{
+///   http::HttpClient,
+///   resolve::{resolve, ResolveOptions},
+///   types::{DepScope, DepsEntry, Ecosystem},
+/// };
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let serde = DepsEntry {
+///   name: "serde".to_owned(),
+///   version: "1.0.0".to_owned(),
+///   scope: DepScope::Runtime,
+///   sources: Default::default(),
+/// };
+/// let resolved = resolve([serde], &HttpClient::new(&None)?, ResolveOptions::new(Ecosystem::Cargo)).await;
+/// println!("{:?}", resolved[0].row.as_ref().and_then(|row| row.license.as_deref()));
+/// # Ok(())
+/// # }
+/// ```
+pub async fn resolve(
+  entries: impl IntoIterator<Item = DepsEntry>,
+  backend: &dyn MetadataBackend,
+  opts: ResolveOptions,
+) -> Vec<ResolvedDependency> {
+  let permits = opts.permits.clone().unwrap_or_else(|| Arc::new(Semaphore::new(1)));
+  in_order(entries.into_iter().collect(), permits, |entry| {
+    resolve_entry(entry, backend, &opts)
+  })
+  .collect()
+  .await
+}
+
+// Runs `resolve_one` over the entries, each once it holds a permit, and yields the outcomes in the entries' order.
+// The caller polls them, nothing is spawned. `resolve` and the report generator both go through it.
+pub(crate) fn in_order<'a, T, F>(
+  entries: Vec<DepsEntry>,
+  permits: Arc<Semaphore>,
+  mut resolve_one: impl FnMut(DepsEntry) -> F + 'a,
+) -> impl Stream<Item = T> + 'a
+where
+  F: Future<Output = T> + 'a,
+  T: 'a,
+{
+  stream::iter(entries)
+    .map(move |entry| {
+      let (permits, resolving) = (Arc::clone(&permits), resolve_one(entry));
+      async move {
+        // A closed semaphore bounds nothing anymore
+        let _permit = permits.acquire_owned().await.ok();
+        resolving.await
+      }
+    })
+    .buffered(usize::MAX)
+}
+
+// A resolver of its own per entry, so the warnings it collects are the entry's alone
+async fn resolve_entry(entry: DepsEntry, backend: &dyn MetadataBackend, opts: &ResolveOptions) -> ResolvedDependency {
+  let diagnostics = Arc::new(Diagnostics::quiet());
+  let resolver = Resolver::new(backend, Arc::clone(&diagnostics))
+    .with_branch_cache(Arc::clone(&opts.branches))
+    .with_npm_registry(
+      opts
+        .registry
+        .clone()
+        .with_prerelease(opts.prerelease)
+        .with_diagnostics(Arc::clone(&diagnostics)),
+    )
+    .with_prerelease(opts.prerelease);
+  let row = match resolver.resolve(opts.ecosystem, &entry).await {
+    Ok(Resolution::Row(row)) => Some(*row),
+    Ok(Resolution::FetchFailed(message)) => {
+      diagnostics.warn(Category::FetchFailure, message);
+      None
+    },
+    Ok(Resolution::Excluded(_) | Resolution::Skipped) => None,
+    Err(err) => {
+      diagnostics.warn(Category::FetchFailure, format!("{:#}", err));
+      None
+    },
+  };
+  ResolvedDependency {
+    entry,
+    row,
+    warnings: diagnostics.warnings(),
+  }
+}
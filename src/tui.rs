@@ -0,0 +1,303 @@
+use crate::{report::ResolutionEvent, types::Ecosystem};
+use std::{
+  collections::HashMap,
+  env,
+  fmt::Write as _,
+  io::{self, IsTerminal, Read, Write},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{Receiver, RecvTimeoutError},
+    Arc,
+  },
+  thread::{self, JoinHandle},
+  time::{Duration, Instant},
+};
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+// Used when the shell doesn't export LINES/COLUMNS
+const DEFAULT_SIZE: (usize, usize) = (24, 100);
+// Header bar plus the table header
+const CHROME_LINES: usize = 3;
+const ENTER_SCREEN: &str = "\x1b[?1049h\x1b[?25l";
+const LEAVE_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+const CLEAR: &str = "\x1b[H\x1b[2J";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+  Queued,
+  Fetching,
+  Resolved,
+  Excluded,
+  Failed,
+}
+
+impl Status {
+  fn label(self) -> &'static str {
+    match self {
+      Status::Queued => "queued",
+      Status::Fetching => "fetching",
+      Status::Resolved => "resolved",
+      Status::Excluded => "excluded",
+      Status::Failed => "failed",
+    }
+  }
+}
+
+struct Package {
+  ecosystem: Ecosystem,
+  name: String,
+  version: String,
+  status: Status,
+  license: Option<String>,
+  // Event count when the row last changed, the table shows the most recent ones
+  touched: usize,
+}
+
+// Everything the table shows, built from the events alone
+#[derive(Default)]
+struct Board {
+  packages: Vec<Package>,
+  index: HashMap<(Ecosystem, String), usize>,
+  events: usize,
+}
+
+impl Board {
+  fn apply(&mut self, event: ResolutionEvent) {
+    self.events += 1;
+    let (ecosystem, name, status, license) = match event {
+      ResolutionEvent::Queued {
+        ecosystem,
+        name,
+        version,
+      } => {
+        let key = (ecosystem, name.clone());
+        if !self.index.contains_key(&key) {
+          self.index.insert(key, self.packages.len());
+          self.packages.push(Package {
+            ecosystem,
+            name,
+            version,
+            status: Status::Queued,
+            license: None,
+            touched: self.events,
+          });
+        }
+        return;
+      },
+      ResolutionEvent::Fetching { ecosystem, name } => (ecosystem, name, Status::Fetching, None),
+      ResolutionEvent::Resolved {
+        ecosystem,
+        name,
+        license,
+      } => (ecosystem, name, Status::Resolved, license),
+      ResolutionEvent::Excluded { ecosystem, name } => (ecosystem, name, Status::Excluded, None),
+      ResolutionEvent::Failed { ecosystem, name } => (ecosystem, name, Status::Failed, None),
+      // Only packages have a row in the table
+      ResolutionEvent::Note { .. } => return,
+    };
+
+    if let Some(&idx) = self.index.get(&(ecosystem, name)) {
+      let package = &mut self.packages[idx];
+      package.status = status;
+      package.license = license;
+      package.touched = self.events;
+    }
+  }
+
+  fn count(&self, status: Status) -> usize {
+    self.packages.iter().filter(|package| package.status == status).count()
+  }
+
+  fn header(&self) -> String {
+    format!(
+      "{} package(s): {} queued, {} fetching, {} resolved, {} excluded, {} failed",
+      self.packages.len(),
+      self.count(Status::Queued),
+      self.count(Status::Fetching),
+      self.count(Status::Resolved),
+      self.count(Status::Excluded),
+      self.count(Status::Failed)
+    )
+  }
+
+  // Whole-screen frame: header bar, then the packages that changed last
+  fn frame(&self, (lines, columns): (usize, usize), hint: &str) -> String {
+    let mut recent = self
+      .packages
+      .iter()
+      .filter(|package| package.status != Status::Queued)
+      .collect::<Vec<_>>();
+    recent.sort_by_key(|package| std::cmp::Reverse(package.touched));
+    recent.truncate(lines.saturating_sub(CHROME_LINES));
+
+    let mut frame = String::from(CLEAR);
+    let _ = writeln!(frame, "{}  ({})\r", fit(&self.header(), columns), hint);
+    let _ = writeln!(frame, "\r");
+    let _ = writeln!(
+      frame,
+      "{}\r",
+      fit(
+        &format!(
+          "{:<9} {:<4} {:<40} {:<16} {}",
+          "STATUS", "ECO", "PACKAGE", "VERSION", "LICENSE"
+        ),
+        columns
+      )
+    );
+    for package in recent {
+      let line = format!(
+        "{:<9} {:<4} {:<40} {:<16} {}",
+        package.status.label(),
+        package.ecosystem.to_string(),
+        package.name,
+        package.version,
+        package.license.as_deref().unwrap_or("")
+      );
+      let _ = writeln!(frame, "{}\r", fit(&line, columns));
+    }
+    frame
+  }
+
+  // What is left to look at once the table is gone: failures and packages without a license
+  fn summary(&self) -> Vec<String> {
+    let mut lines = vec![self.header()];
+    for package in &self.packages {
+      let problem = match (package.status, &package.license) {
+        (Status::Failed, _) => "failed",
+        (Status::Resolved, None) => "no license",
+        (Status::Queued | Status::Fetching, _) => "not resolved",
+        _ => continue,
+      };
+      lines.push(format!(
+        "  {} {}@{}: {}",
+        package.ecosystem, package.name, package.version, problem
+      ));
+    }
+    lines
+  }
+}
+
+// Live table of the resolution on the terminal's alternate screen, an alternative to the scrolling log. Runs on
+// its own thread until the sender side of `events` is gone, then prints the summary on the normal screen.
+pub struct Tui;
+
+impl Tui {
+  pub fn is_supported() -> bool {
+    io::stdout().is_terminal() && io::stdin().is_terminal()
+  }
+
+  pub fn spawn(events: Receiver<ResolutionEvent>, cancel: Arc<AtomicBool>) -> JoinHandle<()> {
+    let keys = KeyMode::enable();
+    // Without single-key input a key press arrives with Enter
+    let hint = match keys.is_enabled() {
+      true => "q cancels",
+      false => "q + Enter cancels",
+    };
+    let input_cancel = cancel.clone();
+    thread::spawn(move || {
+      for byte in io::stdin().lock().bytes() {
+        match byte {
+          Ok(b'q' | b'Q') => {
+            input_cancel.store(true, Ordering::Relaxed);
+            break;
+          },
+          Ok(_) => {},
+          Err(_) => break,
+        }
+      }
+    });
+
+    thread::spawn(move || {
+      let size = terminal_size();
+      let mut board = Board::default();
+      let mut stdout = io::stdout();
+      let _ = write!(stdout, "{}", ENTER_SCREEN);
+      let mut drawn = Instant::now() - REDRAW_INTERVAL;
+      loop {
+        match events.recv_timeout(REDRAW_INTERVAL) {
+          Ok(event) => board.apply(event),
+          Err(RecvTimeoutError::Timeout) => {},
+          Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if drawn.elapsed() >= REDRAW_INTERVAL {
+          let _ = write!(stdout, "{}", board.frame(size, hint));
+          let _ = stdout.flush();
+          drawn = Instant::now();
+        }
+      }
+
+      let _ = write!(stdout, "{}", LEAVE_SCREEN);
+      drop(keys);
+      if cancel.load(Ordering::Relaxed) {
+        println!("Cancelled, no report written");
+      }
+      for line in board.summary() {
+        println!("{}", line);
+      }
+    })
+  }
+}
+
+// Keys reach the input thread one at a time and unechoed while the table is up, Ctrl-C still interrupts. The
+// terminal's own mode is put back when dropped.
+#[cfg(unix)]
+struct KeyMode(Option<libc::termios>);
+
+#[cfg(unix)]
+impl KeyMode {
+  fn enable() -> Self {
+    // SAFETY: termios is plain data, filled in by tcgetattr before it is read
+    let mut saved = unsafe { std::mem::zeroed::<libc::termios>() };
+    if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut saved) } != 0 {
+      return Self(None);
+    }
+    let mut single_keys = saved;
+    single_keys.c_lflag &= !(libc::ICANON | libc::ECHO);
+    single_keys.c_cc[libc::VMIN] = 1;
+    single_keys.c_cc[libc::VTIME] = 0;
+    match unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &single_keys) } {
+      0 => Self(Some(saved)),
+      _ => Self(None),
+    }
+  }
+
+  fn is_enabled(&self) -> bool {
+    self.0.is_some()
+  }
+}
+
+#[cfg(unix)]
+impl Drop for KeyMode {
+  fn drop(&mut self) {
+    if let Some(saved) = &self.0 {
+      unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, saved) };
+    }
+  }
+}
+
+// Elsewhere input stays line by line
+#[cfg(not(unix))]
+struct KeyMode;
+
+#[cfg(not(unix))]
+impl KeyMode {
+  fn enable() -> Self {
+    Self
+  }
+
+  fn is_enabled(&self) -> bool {
+    false
+  }
+}
+
+fn terminal_size() -> (usize, usize) {
+  let read = |name: &str| env::var(name).ok().and_then(|value| value.parse().ok());
+  (
+    read("LINES").unwrap_or(DEFAULT_SIZE.0),
+    read("COLUMNS").unwrap_or(DEFAULT_SIZE.1),
+  )
+}
+
+fn fit(line: &str, columns: usize) -> String {
+  line.chars().take(columns).collect()
+}
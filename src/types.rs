@@ -1,35 +1,433 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use anyhow::{bail, Result};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use std::{
+  collections::{BTreeSet, HashMap},
+  fmt,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DepsEntry {
   pub name: String,
   pub version: String,
+  pub scope: DepScope,
+  // Manifests (relative to the scanned root) that declare it, what --sheet routes by
+  pub sources: BTreeSet<String>,
+}
+
+impl DepsEntry {
+  // A later manifest still decides the version, the sources of every manifest are kept
+  pub fn insert_into(mut self, dependencies: &mut HashMap<String, DepsEntry>) {
+    if let Some(previous) = dependencies.remove(&self.name) {
+      self.sources.extend(previous.sources);
+    }
+    dependencies.insert(self.name.clone(), self);
+  }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DepScope {
+  #[default]
+  Runtime,
+  Peer,
+  Optional,
+  // Only listed on request, e.g. Pipfile.lock's develop packages with --pipenv-dev
+  Dev,
+}
+
+impl fmt::Display for DepScope {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      DepScope::Runtime => write!(f, "runtime"),
+      DepScope::Peer => write!(f, "peer"),
+      DepScope::Optional => write!(f, "optional"),
+      DepScope::Dev => write!(f, "dev"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+  Npm,
+  Go,
+  Cargo,
+  Pypi,
+  Maven,
+  Gem,
+  Composer,
+  Nuget,
+  Pub,
+  Swift,
+  Cocoapods,
+  Conan,
+  // JSR packages and URL imports of deno.json; its `npm:` imports are npm packages
+  Deno,
+  // Chart dependencies of Helm charts
+  Helm,
+  // Providers pinned by .terraform.lock.hcl
+  Terraform,
+  // Third-party actions of GitHub workflows, with --github-actions
+  #[serde(rename = "github-actions")]
+  GithubActions,
+  // Imported from an SBOM with a purl type we don't parse
+  Unknown,
+  // Listed in the config's `[[manual_component]]` sections
+  Manual,
+}
+
+impl fmt::Display for Ecosystem {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Ecosystem::Npm => write!(f, "npm"),
+      Ecosystem::Go => write!(f, "go"),
+      Ecosystem::Cargo => write!(f, "cargo"),
+      Ecosystem::Pypi => write!(f, "pypi"),
+      Ecosystem::Maven => write!(f, "maven"),
+      Ecosystem::Gem => write!(f, "gem"),
+      Ecosystem::Composer => write!(f, "composer"),
+      Ecosystem::Nuget => write!(f, "nuget"),
+      Ecosystem::Pub => write!(f, "pub"),
+      Ecosystem::Swift => write!(f, "swift"),
+      Ecosystem::Cocoapods => write!(f, "cocoapods"),
+      Ecosystem::Conan => write!(f, "conan"),
+      Ecosystem::Deno => write!(f, "deno"),
+      Ecosystem::Helm => write!(f, "helm"),
+      Ecosystem::Terraform => write!(f, "terraform"),
+      Ecosystem::GithubActions => write!(f, "github-actions"),
+      Ecosystem::Unknown => write!(f, "unknown"),
+      Ecosystem::Manual => write!(f, "manual"),
+    }
+  }
+}
+
+// How much a resolved license can be trusted, weakest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LicenseConfidence {
+  // Taken from a page meant for humans, e.g. pkg.go.dev
+  Scraped,
+  // Recognized from the text of a license file on disk
+  Detected,
+  // Stated by the package itself in its registry metadata or manifest
+  Declared,
+}
+
+impl LicenseConfidence {
+  pub const NAMES: &'static [&'static str] = &["scraped", "detected", "declared"];
+
+  pub fn parse(value: &str) -> Result<Self> {
+    match value {
+      "scraped" => Ok(LicenseConfidence::Scraped),
+      "detected" => Ok(LicenseConfidence::Detected),
+      "declared" => Ok(LicenseConfidence::Declared),
+      _ => bail!(
+        "Unknown license confidence `{}`, expected one of: {}",
+        value,
+        Self::NAMES.join(", ")
+      ),
+    }
+  }
+}
+
+// Whether the registry holds a Sigstore provenance attestation for the exact version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttestationStatus {
+  Attested,
+  NotAttested,
+  // The attestation endpoint failed or answered something unexpected
+  Unknown,
+}
+
+impl fmt::Display for AttestationStatus {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      AttestationStatus::Attested => write!(f, "attested"),
+      AttestationStatus::NotAttested => write!(f, "not attested"),
+      AttestationStatus::Unknown => write!(f, "unknown"),
+    }
+  }
+}
+
+// Whether a license is on one of the SPDX list's approval lists; an expression whose branches disagree is Mixed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LicenseFlag {
+  #[serde(rename = "yes")]
+  Yes,
+  #[serde(rename = "no")]
+  No,
+  #[serde(rename = "n/a")]
+  Mixed,
+}
+
+impl fmt::Display for LicenseFlag {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      LicenseFlag::Yes => write!(f, "yes"),
+      LicenseFlag::No => write!(f, "no"),
+      LicenseFlag::Mixed => write!(f, "n/a"),
+    }
+  }
+}
+
+impl fmt::Display for LicenseConfidence {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      LicenseConfidence::Scraped => write!(f, "scraped"),
+      LicenseConfidence::Detected => write!(f, "detected"),
+      LicenseConfidence::Declared => write!(f, "declared"),
+    }
+  }
+}
+
+// Resolved dependency as it lands in the report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportRow {
+  pub ecosystem: Ecosystem,
+  pub name: String,
+  pub version: String,
+  // Rows from post-process scripts written before the field existed are runtime
+  #[serde(default)]
+  pub scope: DepScope,
+  pub homepage: String,
+  pub license: Option<String>,
+  // Set whenever `license` is
+  pub license_confidence: Option<LicenseConfidence>,
+  pub license_url: Option<String>,
+  pub funding: Option<String>,
+  pub platform: Option<String>,
+  pub repo_mismatch: Option<String>,
+  // Only set with --verify-installed, when node_modules or go.sum disagree with `version`
+  #[serde(default)]
+  pub installed_mismatch: Option<String>,
+  // Only set with --nested-notices: link to the package's third-party notices file. Left out of JSON rows when
+  // unset, reports written before the field existed still match the schema
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub third_party_notices: Option<String>,
+  // Only set with --verify-links, left out of JSON rows otherwise like `third_party_notices`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub link_status: Option<LinkStatus>,
+  // Only set with --notice: the key of the license text in the NOTICE file, shared by rows with the same text
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub license_text_key: Option<String>,
+  // Only set when --provenance-check asked the registry
+  #[serde(default)]
+  pub attestation: Option<AttestationStatus>,
+  // Filled in by the writer from the SPDX list once the license is normalized, None for unknown licenses
+  #[serde(default)]
+  pub osi_approved: Option<LicenseFlag>,
+  #[serde(default)]
+  pub fsf_libre: Option<LicenseFlag>,
+  // Only set with --sizes: npm's unpacked size or the Go module zip, in bytes
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub size: Option<u64>,
+  // Where each piece of the row came from, e.g. which level supplied the license file
+  #[serde(default)]
+  pub provenance: Vec<String>,
+}
+
+// What --verify-links found for a row's links; neither list set means every link answered
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkStatus {
+  // `<url> (<status or error>)` of each link that didn't
+  #[serde(default)]
+  pub dead: Vec<String>,
+  // `<dead url> -> <url>` of constructed license links the probe found a working replacement for
+  #[serde(default)]
+  pub replaced: Vec<String>,
+}
+
+impl fmt::Display for LinkStatus {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut parts = Vec::new();
+    if !self.dead.is_empty() {
+      parts.push(format!("dead: {}", self.dead.join(", ")));
+    }
+    if !self.replaced.is_empty() {
+      parts.push(format!("replaced: {}", self.replaced.join(", ")));
+    }
+    match parts.is_empty() {
+      true => write!(f, "ok"),
+      false => write!(f, "{}", parts.join("; ")),
+    }
+  }
+}
+
+// Which level of the repository supplied the license file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LicenseLevel {
+  Directory,
+  Root,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct PackageJson {
+  pub name: Option<String>,
   pub dependencies: Option<HashMap<String, String>>,
   #[serde(rename = "peerDependencies")]
   pub peer_dependencies: Option<HashMap<String, String>>,
+  #[serde(rename = "optionalDependencies")]
+  pub optional_dependencies: Option<HashMap<String, String>>,
+  // Only read for the self audit; legacy manifests use an object instead of a string
+  pub license: Option<serde_json::Value>,
+  pub repository: Option<serde_json::Value>,
+}
+
+// Problem found in one of the scanned tree's own manifests
+#[derive(Debug, Clone)]
+pub struct SelfAuditFinding {
+  pub manifest: String,
+  pub package: String,
+  pub issue: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackageLock {
+  pub name: Option<String>,
+  // lockfileVersion 2/3: flat map keyed by install path ("" is the root project)
+  #[serde(default)]
+  pub packages: HashMap<String, PackageLockEntry>,
+  // lockfileVersion 1: nested tree keyed by package name
+  #[serde(default)]
+  pub dependencies: HashMap<String, PackageLockV1Entry>,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct PackageLockEntry {
+  pub name: Option<String>,
+  pub version: Option<String>,
+  // A tarball URL, or a local path for packages installed from the disk
+  pub resolved: Option<String>,
+  // A symlink to a workspace member or a `file:` directory, whose own entry is keyed by its path
+  #[serde(default)]
+  pub link: bool,
+  // Only installed for devDependencies (`devOptional`: for devDependencies or as an optional dependency)
+  #[serde(default)]
+  pub dev: bool,
+  #[serde(default, rename = "devOptional")]
+  pub dev_optional: bool,
+  #[serde(default)]
+  pub optional: bool,
+  #[serde(default)]
+  pub peer: bool,
+  #[serde(default)]
+  pub dependencies: HashMap<String, String>,
+  #[serde(default, rename = "peerDependencies")]
+  pub peer_dependencies: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackageLockV1Entry {
+  pub version: Option<String>,
+  #[serde(default)]
+  pub requires: HashMap<String, String>,
+  #[serde(default)]
+  pub dependencies: HashMap<String, PackageLockV1Entry>,
+}
+
+// Only the license is needed for a row; everything else enriches it and a field that is missing or malformed
+// (abbreviated packuments, hand-edited private registries) is left empty instead of failing the package.
+// Name and version are filled in from the request when absent.
 #[derive(Serialize, Deserialize)]
 pub struct PackageInfo {
+  #[serde(default)]
   pub name: String,
+  #[serde(default)]
   pub version: String,
+  // Private registries (Artifactory, Verdaccio) pass through whatever was published: missing, or an object
+  #[serde(default, deserialize_with = "declared_license")]
   pub license: String,
+  #[serde(default, deserialize_with = "lenient")]
   pub homepage: String,
+  #[serde(default, deserialize_with = "lenient")]
   pub bugs: PakageBugs,
+  #[serde(default, deserialize_with = "lenient")]
   pub repository: PackageRepo,
+  #[serde(default, deserialize_with = "lenient")]
+  pub funding: Option<PackageFunding>,
+  #[serde(default, deserialize_with = "lenient")]
+  pub os: Vec<String>,
+  #[serde(default, deserialize_with = "lenient")]
+  pub cpu: Vec<String>,
+  // Usually a map of engine -> range, very old packages publish an array
+  pub engines: Option<serde_json::Value>,
+  #[serde(default, deserialize_with = "lenient")]
+  pub dist: PackageDist,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
+pub struct PackageDist {
+  // Size of the unpacked tarball in bytes, missing from old and some private registries' documents
+  #[serde(rename = "unpackedSize")]
+  pub unpacked_size: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(from = "RepoField")]
 pub struct PackageRepo {
   pub url: String,
+  // Set by packages published from a monorepo subdirectory
+  pub directory: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RepoField {
+  Url(String),
+  Entry { url: String, directory: Option<String> },
+}
+
+impl From<RepoField> for PackageRepo {
+  fn from(field: RepoField) -> Self {
+    match field {
+      RepoField::Url(url) => PackageRepo { url, directory: None },
+      RepoField::Entry { url, directory } => PackageRepo { url, directory },
+    }
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LicenseField {
+  Id(String),
+  Entry {
+    #[serde(rename = "type")]
+    kind: String,
+  },
+}
+
+fn declared_license<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+  Ok(match lenient::<D, Option<LicenseField>>(deserializer)? {
+    Some(LicenseField::Id(license)) | Some(LicenseField::Entry { kind: license }) => license,
+    None => String::new(),
+  })
+}
+
+// Any JSON is accepted, a value of the wrong shape reads as the default
+fn lenient<'de, D: Deserializer<'de>, T: DeserializeOwned + Default>(deserializer: D) -> Result<T, D::Error> {
+  let value = serde_json::Value::deserialize(deserializer)?;
+  Ok(serde_json::from_value(value).unwrap_or_default())
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum PackageFunding {
+  Url(String),
+  Entry { url: String },
+  List(Vec<PackageFunding>),
+}
+
+impl PackageFunding {
+  pub fn urls(&self) -> Vec<&str> {
+    match self {
+      PackageFunding::Url(url) | PackageFunding::Entry { url } => vec![url.as_str()],
+      PackageFunding::List(entries) => entries.iter().flat_map(|entry| entry.urls()).collect(),
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Default)]
 pub struct PakageBugs {
   pub url: String,
 }
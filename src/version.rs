@@ -0,0 +1,452 @@
+use crate::{http::HttpClient, types::Ecosystem};
+use anyhow::{anyhow, Context, Result};
+use std::{cmp::Ordering, fmt, time::Duration};
+
+pub const RUNNING: &str = env!("CARGO_PKG_VERSION");
+// Redirects to `.../releases/tag/<tag>`, which needs neither the API nor a token
+const LATEST_RELEASE_URL: &str = "https://github.com/KyKyPy3/collect_lic_info/releases/latest";
+const CHECK_TIMEOUT: Duration = Duration::from_secs(1);
+
+// A release or pre-release; build metadata is dropped, it doesn't take part in precedence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+  major: u64,
+  minor: u64,
+  patch: u64,
+  // Dot separated identifiers after the `-`, empty for a release
+  pre: Vec<Identifier>,
+}
+
+// Numeric identifiers sort below alphanumeric ones, the derived order is semver's
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier {
+  Numeric(u64),
+  Alphanumeric(String),
+}
+
+// Whether a range may pick a pre-release. npm and cargo only let a range match pre-releases of the
+// major.minor.patch it names one of; `allow` lets any pre-release within bounds match, `deny` none at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PrereleasePolicy {
+  Allow,
+  Deny,
+  #[default]
+  OnlyWhenRequested,
+}
+
+// Requirement in the syntax npm and cargo share: comparators (`=`, `>`, `>=`, `<`, `<=`, `~`, `^`) separated by
+// commas or spaces, `||` between alternatives, `1.2.x` wildcards and `1.2.3 - 2.3.4` hyphen ranges. Where they
+// differ is a bare version: `^` in cargo, `=` in npm and the registries that follow it, so `1.2` is `1.2.x` there.
+pub struct VersionReq {
+  source: String,
+  // Any of the sets, every comparator of the set
+  alternatives: Vec<Vec<Comparator>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+  Exact,
+  Greater,
+  GreaterEq,
+  Less,
+  LessEq,
+}
+
+// What every operator, wildcard and hyphen range comes down to
+struct Comparator {
+  op: Op,
+  version: Version,
+  // A partial version's lower bound, the pre-releases of its floor count as above it under `allow`
+  widened: bool,
+}
+
+impl Version {
+  pub fn parse(value: &str) -> Result<Self> {
+    let (parts, pre) = parse_partial(value)?;
+    match parts[..] {
+      [major, minor, patch] => Ok(Self {
+        major,
+        minor,
+        patch,
+        pre,
+      }),
+      _ => Err(anyhow!("Version `{}` needs major.minor.patch", value)),
+    }
+  }
+
+  pub fn is_prerelease(&self) -> bool {
+    !self.pre.is_empty()
+  }
+
+  fn core(&self) -> (u64, u64, u64) {
+    (self.major, self.minor, self.patch)
+  }
+
+  // The lowest version there is with this major.minor.patch: `1.2.3-0`
+  fn lowest(major: u64, minor: u64, patch: u64) -> Self {
+    Self {
+      major,
+      minor,
+      patch,
+      pre: vec![Identifier::Numeric(0)],
+    }
+  }
+}
+
+impl Ord for Version {
+  fn cmp(&self, other: &Self) -> Ordering {
+    // A release ranks above its pre-releases
+    self
+      .core()
+      .cmp(&other.core())
+      .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => self.pre.cmp(&other.pre),
+      })
+  }
+}
+
+impl PartialOrd for Version {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl fmt::Display for Version {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+    for (idx, identifier) in self.pre.iter().enumerate() {
+      let separator = if idx == 0 { '-' } else { '.' };
+      match identifier {
+        Identifier::Numeric(number) => write!(f, "{}{}", separator, number)?,
+        Identifier::Alphanumeric(text) => write!(f, "{}{}", separator, text)?,
+      }
+    }
+    Ok(())
+  }
+}
+
+impl PrereleasePolicy {
+  pub const NAMES: &'static [&'static str] = &["allow", "deny", "only-when-requested"];
+
+  pub fn parse(value: &str) -> Result<Self> {
+    match value {
+      "allow" => Ok(PrereleasePolicy::Allow),
+      "deny" => Ok(PrereleasePolicy::Deny),
+      "only-when-requested" => Ok(PrereleasePolicy::OnlyWhenRequested),
+      _ => Err(anyhow!(
+        "Unknown pre-release policy `{}`, expected one of: {}",
+        value,
+        Self::NAMES.join(", ")
+      )),
+    }
+  }
+}
+
+impl fmt::Display for PrereleasePolicy {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = match self {
+      PrereleasePolicy::Allow => "allow",
+      PrereleasePolicy::Deny => "deny",
+      PrereleasePolicy::OnlyWhenRequested => "only-when-requested",
+    };
+    write!(f, "{}", name)
+  }
+}
+
+impl VersionReq {
+  // `ecosystem` decides what a bare version means, cargo's reading for Cargo and npm's for anything else
+  pub fn parse(value: &str, ecosystem: Ecosystem) -> Result<Self> {
+    let bare = match ecosystem {
+      Ecosystem::Cargo => "^",
+      _ => "=",
+    };
+    let alternatives = value
+      .split("||")
+      .map(|set| parse_set(set, bare).with_context(|| format!("Invalid version requirement `{}`", value)))
+      .collect::<Result<Vec<_>>>()?;
+
+    Ok(Self {
+      source: value.to_owned(),
+      alternatives,
+    })
+  }
+
+  // Whether `version` satisfies the requirement, its pre-release (if any) allowed by `policy`
+  pub fn matches(&self, version: &Version, policy: PrereleasePolicy) -> bool {
+    self.alternatives.iter().any(|set| {
+      set.iter().all(|comparator| comparator.matches(version, policy))
+        && (!version.is_prerelease()
+          || match policy {
+            PrereleasePolicy::Allow => true,
+            PrereleasePolicy::Deny => false,
+            // `-0` upper bounds name the pre-release too, but nothing of theirs is below them
+            PrereleasePolicy::OnlyWhenRequested => set
+              .iter()
+              .any(|comparator| comparator.version.is_prerelease() && comparator.version.core() == version.core()),
+          })
+    })
+  }
+}
+
+impl fmt::Display for VersionReq {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.source)
+  }
+}
+
+impl Comparator {
+  fn matches(&self, version: &Version, policy: PrereleasePolicy) -> bool {
+    if self.widened && policy == PrereleasePolicy::Allow {
+      return *version >= Version::lowest(self.version.major, self.version.minor, self.version.patch);
+    }
+    match self.op {
+      Op::Exact => *version == self.version,
+      Op::Greater => *version > self.version,
+      Op::GreaterEq => *version >= self.version,
+      Op::Less => *version < self.version,
+      Op::LessEq => *version <= self.version,
+    }
+  }
+}
+
+// `, a pre-release` after a version a range picked that is one, so a surprising row explains itself
+pub fn describe_prerelease(version: &str) -> &'static str {
+  match Version::parse(version) {
+    Ok(version) if version.is_prerelease() => ", a pre-release",
+    _ => "",
+  }
+}
+
+/// The highest of `versions` the requirement matches under `policy`, what isn't a version skipped. The one place
+/// ranges are resolved against a list of published versions, whatever the registry.
+///
+/// ```
+/// use depsfetch::{types::Ecosystem, version::{highest_match, PrereleasePolicy, VersionReq}};
+///
+/// let published = ["1.2.0", "1.2.5", "1.9.0"];
+/// let npm = VersionReq::parse("1.2", Ecosystem::Npm).unwrap();
+/// let cargo = VersionReq::parse("1.2", Ecosystem::Cargo).unwrap();
+/// assert_eq!(highest_match(published, &npm, PrereleasePolicy::default()), Some("1.2.5"));
+/// assert_eq!(highest_match(published, &cargo, PrereleasePolicy::default()), Some("1.9.0"));
+/// ```
+pub fn highest_match<'a>(
+  versions: impl IntoIterator<Item = &'a str>,
+  requirement: &VersionReq,
+  policy: PrereleasePolicy,
+) -> Option<&'a str> {
+  versions
+    .into_iter()
+    .filter_map(|key| Some((Version::parse(key).ok()?, key)))
+    .filter(|(version, _)| requirement.matches(version, policy))
+    .max_by(|(left, _), (right, _)| left.cmp(right))
+    .map(|(_, key)| key)
+}
+
+// Fails when the running binary is not what the pipeline pinned
+pub fn require(requirement: &str) -> Result<()> {
+  // The binary is a Rust crate, its requirement reads like one in Cargo.toml
+  let requirement = VersionReq::parse(requirement, Ecosystem::Cargo)?;
+  if !requirement.matches(&Version::parse(RUNNING)?, PrereleasePolicy::default()) {
+    return Err(anyhow!(
+      "depsfetch {} does not satisfy --require-version {}",
+      RUNNING,
+      requirement
+    ));
+  }
+
+  Ok(())
+}
+
+// Latest release tag, printing a notice when it is newer than this build. Never fails: an offline runner
+// or a slow network only costs a second and a note.
+pub async fn check_latest(client: &HttpClient) -> Option<String> {
+  let latest = match fetch_latest(client).await {
+    Ok(latest) => latest,
+    Err(err) => {
+      println!("Version check skipped: {:#}", err);
+      return None;
+    },
+  };
+
+  match (Version::parse(RUNNING), Version::parse(&latest)) {
+    (Ok(running), Ok(newest)) if newest > running => {
+      println!("depsfetch {} is out of date, the latest release is {}", running, newest);
+    },
+    (_, Err(err)) => println!("Version check: can't compare release tag: {:#}", err),
+    _ => {},
+  }
+  Some(latest)
+}
+
+async fn fetch_latest(client: &HttpClient) -> Result<String> {
+  let response = tokio::time::timeout(CHECK_TIMEOUT, client.get(LATEST_RELEASE_URL))
+    .await
+    .map_err(|_| anyhow!("no answer within {}s", CHECK_TIMEOUT.as_secs()))??;
+  let (_, tag) = response
+    .url
+    .rsplit_once("/releases/tag/")
+    .context("no release has been published")?;
+
+  Ok(tag.trim_start_matches('v').to_owned())
+}
+
+// `1`, `1.2.x`, `v1.2.3-rc.1+build`: the numbers up to a wildcard and the pre-release identifiers, build metadata
+// dropped
+fn parse_partial(value: &str) -> Result<(Vec<u64>, Vec<Identifier>)> {
+  let version = value.trim().trim_start_matches('v');
+  let version = version.split('+').next().unwrap_or(version);
+  let (core, pre) = match version.split_once('-') {
+    Some((core, pre)) => (core, Some(pre)),
+    None => (version, None),
+  };
+  let mut parts = Vec::new();
+  for part in core.split('.') {
+    if matches!(part, "x" | "X" | "*") {
+      break;
+    }
+    parts.push(
+      part
+        .parse::<u64>()
+        .with_context(|| format!("`{}` is not a version", value))?,
+    );
+  }
+  if parts.len() > 3 {
+    return Err(anyhow!("`{}` is not a version", value));
+  }
+  let Some(pre) = pre else {
+    return Ok((parts, Vec::new()));
+  };
+  if parts.len() < 3 || pre.split('.').any(str::is_empty) {
+    return Err(anyhow!("`{}` is not a version", value));
+  }
+  let pre = pre
+    .split('.')
+    .map(|identifier| match identifier.parse::<u64>() {
+      Ok(number) => Identifier::Numeric(number),
+      Err(_) => Identifier::Alphanumeric(identifier.to_owned()),
+    })
+    .collect();
+
+  Ok((parts, pre))
+}
+
+// `bare` is the operator of a version written without one
+fn parse_set(set: &str, bare: &str) -> Result<Vec<Comparator>> {
+  let tokens = set
+    .split(|c: char| c == ',' || c.is_whitespace())
+    .filter(|token| !token.is_empty())
+    .collect::<Vec<_>>();
+  if let [from, "-", to] = tokens[..] {
+    let mut comparators = parse_comparator(&format!(">={}", from), bare)?;
+    comparators.extend(parse_comparator(&format!("<={}", to), bare)?);
+    return Ok(comparators);
+  }
+
+  let mut comparators = Vec::new();
+  // `>= 1.2.3`: an operator on its own goes with the version after it
+  let mut operator = None;
+  for token in tokens {
+    if OPERATORS.contains(&token) {
+      operator = Some(token);
+      continue;
+    }
+    let comparator = match operator.take() {
+      Some(operator) => format!("{}{}", operator, token),
+      None => token.to_owned(),
+    };
+    comparators.extend(parse_comparator(&comparator, bare)?);
+  }
+  if let Some(operator) = operator {
+    return Err(anyhow!("`{}` has no version after it", operator));
+  }
+  Ok(comparators)
+}
+
+// Longest first so `>=` isn't read as `>`
+const OPERATORS: &[&str] = &["~>", "<=", ">=", "=", "<", ">", "~", "^"];
+
+fn parse_comparator(comparator: &str, bare: &str) -> Result<Vec<Comparator>> {
+  let (op, version) = OPERATORS
+    .iter()
+    .find_map(|op| comparator.strip_prefix(op).map(|version| (*op, version)))
+    // A wildcard is `=` whatever the syntax: `1.2.x` is 1.2.anything in cargo as well
+    .unwrap_or_else(
+      || match comparator.split('.').any(|part| matches!(part, "x" | "X" | "*")) {
+        true => ("=", comparator),
+        false => (bare, comparator),
+      },
+    );
+  let (parts, pre) = parse_partial(version)?;
+  // `*`, `x`, `~x`: anything
+  if parts.is_empty() {
+    return Ok(Vec::new());
+  }
+  let part = |idx: usize| parts.get(idx).copied().unwrap_or(0);
+  let exact = parts.len() == 3;
+  let floor = Version {
+    major: part(0),
+    minor: part(1),
+    patch: part(2),
+    pre,
+  };
+  // The first release past everything the written parts cover: `1.2` -> 1.3.0
+  let next = match parts.len() {
+    1 => (part(0) + 1, 0, 0),
+    2 => (part(0), part(1) + 1, 0),
+    _ => (part(0), part(1), part(2) + 1),
+  };
+  let written = |op| Comparator {
+    op,
+    version: floor.clone(),
+    widened: false,
+  };
+  // Upper bounds stop below the pre-releases of the version they name
+  let below = |(major, minor, patch): (u64, u64, u64)| Comparator {
+    op: Op::Less,
+    version: Version::lowest(major, minor, patch),
+    widened: false,
+  };
+  // Lower bounds of partial versions take in the pre-releases below them when those are allowed: `^1.2` and
+  // 1.2.0-rc.1, like node-semver's includePrerelease
+  let at_least = |version: Version| Comparator {
+    op: Op::GreaterEq,
+    version,
+    widened: !exact,
+  };
+
+  Ok(match op {
+    "=" if exact => vec![written(Op::Exact)],
+    "=" => vec![below(next), at_least(floor)],
+    ">" if exact => vec![written(Op::Greater)],
+    ">" => vec![at_least(Version {
+      major: next.0,
+      minor: next.1,
+      patch: next.2,
+      pre: Vec::new(),
+    })],
+    ">=" => vec![at_least(floor)],
+    "<" if exact => vec![written(Op::Less)],
+    "<" => vec![below(floor.core())],
+    "<=" if exact => vec![written(Op::LessEq)],
+    "<=" => vec![below(next)],
+    "~" | "~>" if parts.len() == 1 => vec![below(next), at_least(floor)],
+    "~" | "~>" => {
+      let upper = (part(0), part(1) + 1, 0);
+      vec![below(upper), at_least(floor)]
+    },
+    // Caret: the left-most non-zero part may not change
+    _ => {
+      let upper = if part(0) > 0 || parts.len() == 1 {
+        (part(0) + 1, 0, 0)
+      } else if part(1) > 0 || parts.len() == 2 {
+        (0, part(1) + 1, 0)
+      } else {
+        next
+      };
+      vec![below(upper), at_least(floor)]
+    },
+  })
+}
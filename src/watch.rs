@@ -0,0 +1,236 @@
+use crate::{
+  deps::{
+    manifests::{ReparseScope, MANIFEST_KINDS},
+    php_deps::ComposerPackage,
+    swift_deps::SwiftPin,
+  },
+  types::{DepsEntry, Ecosystem, ReportRow},
+};
+use anyhow::{Context, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap},
+  fmt, fs,
+  hash::Hash,
+  path::{Component, Path, PathBuf},
+  time::Duration,
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+// Read by the parsers next to the manifest kinds, so changing one changes what gets resolved as well
+const COMPANION_FILES: &[&str] = &["poetry.lock", "go.sum", "Cargo.lock", "Gemfile", "Podfile"];
+// Package managers write a lock file and its manifest one after the other; changes this close are one cycle
+const DEBOUNCE: Duration = Duration::from_millis(500);
+// Entries of each kind the delta lists before it only counts
+const DELTA_LISTED: usize = 10;
+
+// Changes to recognized manifests under the scanned directory, for --watch. Installed copies under node_modules
+// are not the tree's own manifests and are left out, an `npm install` would otherwise trigger a cycle per package.
+pub struct ManifestWatcher {
+  // Notifications stop once it's dropped
+  _watcher: RecommendedWatcher,
+  changes: UnboundedReceiver<PathBuf>,
+}
+
+impl ManifestWatcher {
+  pub fn new(directory: &str) -> Result<Self> {
+    let (sender, changes) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+      let Ok(event) = event else {
+        return;
+      };
+      if matches!(event.kind, EventKind::Access(_)) {
+        return;
+      }
+      for path in event.paths.into_iter().filter(|path| is_manifest(path)) {
+        // The receiver only goes away when watching stops
+        let _ = sender.send(path);
+      }
+    })
+    .context("Failed to set up watching for manifest changes")?;
+    // Changes are reported under the path watched, the parsers walk the canonical one
+    let root =
+      fs::canonicalize(directory).with_context(|| format!("Failed to canonicalize directory: {}", directory))?;
+    watcher
+      .watch(&root, RecursiveMode::Recursive)
+      .with_context(|| format!("Failed to watch {}", directory))?;
+
+    Ok(Self {
+      _watcher: watcher,
+      changes,
+    })
+  }
+
+  // The manifests changed since the last call, once nothing changed for a moment; None when watching stopped
+  pub async fn next_change(&mut self) -> Option<BTreeSet<PathBuf>> {
+    let mut changed = BTreeSet::from([self.changes.recv().await?]);
+    while let Ok(Some(path)) = tokio::time::timeout(DEBOUNCE, self.changes.recv()).await {
+      changed.insert(path);
+    }
+    Some(changed)
+  }
+}
+
+fn is_manifest(path: &Path) -> bool {
+  if path
+    .components()
+    .any(|component| component == Component::Normal("node_modules".as_ref()))
+  {
+    return false;
+  }
+  let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+    return false;
+  };
+  MANIFEST_KINDS
+    .iter()
+    .chain(COMPANION_FILES)
+    .any(|kind| match kind.strip_prefix('*') {
+      Some(suffix) => name.ends_with(suffix),
+      None => name == *kind,
+    })
+}
+
+// What one --watch cycle hands to the next
+#[derive(Default)]
+pub struct WatchState {
+  // The last report's rows, trusted like a baseline
+  pub rows: Vec<ReportRow>,
+  pub parsed: ParsedManifests,
+  // Manifests changed since the last cycle; None for the first, which reads everything
+  pub changed: Option<BTreeSet<PathBuf>>,
+}
+
+// What the parsers found in each manifest, kept between --watch cycles so a cycle only reads the manifests its
+// changes touch. Warnings about the others were given by the cycle that read them.
+#[derive(Default)]
+pub struct ParsedManifests {
+  deps: HashMap<Ecosystem, HashMap<String, DepsEntry>>,
+  // Rows settled without a lookup, each with the manifest it came from
+  settled: HashMap<Ecosystem, Vec<(String, ReportRow)>>,
+  // What the resolvers need from the lockfiles, see `merge_table`
+  pub composer_locks: HashMap<(String, String), ComposerPackage>,
+  pub pub_hosts: HashMap<String, String>,
+  pub swift_pins: HashMap<String, SwiftPin>,
+  pub conan_channels: HashMap<String, String>,
+  pub helm_repositories: HashMap<String, String>,
+}
+
+impl ParsedManifests {
+  // `ecosystem`'s packages and settled rows from the manifests in `scope`, everything when None, completed with the
+  // earlier cycles' from the others. A package whose version changed takes the one just read.
+  pub fn merge(
+    &mut self,
+    ecosystem: Ecosystem,
+    root: &Path,
+    scope: Option<&ReparseScope>,
+    deps: HashMap<String, DepsEntry>,
+    settled: Vec<(String, ReportRow)>,
+  ) -> (HashMap<String, DepsEntry>, Vec<ReportRow>) {
+    let reparsed = |source: &str| scope.is_none_or(|scope| scope.covers(&root.join(source)));
+
+    let known = self.deps.entry(ecosystem).or_default();
+    known.retain(|_, entry| {
+      entry.sources.retain(|source| !reparsed(source));
+      !entry.sources.is_empty()
+    });
+    for entry in deps.into_values() {
+      entry.insert_into(known);
+    }
+
+    let rows = self.settled.entry(ecosystem).or_default();
+    rows.retain(|(source, _)| !reparsed(source));
+    rows.extend(settled);
+    // A package several manifests settle is reported once, as the parsers do within a run
+    let mut settled = BTreeMap::new();
+    for (_, row) in rows.iter() {
+      settled.entry(row.name.as_str()).or_insert_with(|| row.clone());
+    }
+
+    (known.clone(), settled.into_values().collect())
+  }
+}
+
+// A lookup table the parsers collect for the resolvers: the earlier cycles' entries updated by this one's. One left
+// behind by a removed package is never looked up.
+pub fn merge_table<K: Eq + Hash + Clone, V: Clone>(known: &mut HashMap<K, V>, read: HashMap<K, V>) -> HashMap<K, V> {
+  known.extend(read);
+  known.clone()
+}
+
+// What a --watch cycle changed in the report: packages that came and went, and known ones whose license differs
+#[derive(Debug, Default)]
+pub struct CycleDelta {
+  added: Vec<String>,
+  removed: Vec<String>,
+  relicensed: Vec<String>,
+}
+
+impl CycleDelta {
+  pub fn between(previous: &[ReportRow], current: &[ReportRow]) -> Self {
+    let previous = licenses_by_package(previous);
+    let current = licenses_by_package(current);
+    let mut delta = Self::default();
+    for (key, license) in &current {
+      match previous.get(key) {
+        None => delta
+          .added
+          .push(format!("{} ({})", describe_package(key), describe_license(license))),
+        Some(before) if before != license => delta.relicensed.push(format!(
+          "{} ({} -> {})",
+          describe_package(key),
+          describe_license(before),
+          describe_license(license)
+        )),
+        Some(_) => {},
+      }
+    }
+    delta.removed = previous
+      .keys()
+      .filter(|key| !current.contains_key(*key))
+      .map(describe_package)
+      .collect();
+    delta
+  }
+}
+
+impl fmt::Display for CycleDelta {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Delta: {} added, {} removed, {} relicensed",
+      self.added.len(),
+      self.removed.len(),
+      self.relicensed.len()
+    )?;
+    for (sign, entries) in [("+", &self.added), ("-", &self.removed), ("~", &self.relicensed)] {
+      for entry in entries.iter().take(DELTA_LISTED) {
+        write!(f, "\n  {} {}", sign, entry)?;
+      }
+      if entries.len() > DELTA_LISTED {
+        write!(f, "\n  {} ... {} more", sign, entries.len() - DELTA_LISTED)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+// Rows of one package on several sheets are the same resolution
+fn licenses_by_package(rows: &[ReportRow]) -> BTreeMap<(Ecosystem, &str, &str), Option<&str>> {
+  rows
+    .iter()
+    .map(|row| {
+      (
+        (row.ecosystem, row.name.as_str(), row.version.as_str()),
+        row.license.as_deref(),
+      )
+    })
+    .collect()
+}
+
+fn describe_package((ecosystem, name, version): &(Ecosystem, &str, &str)) -> String {
+  format!("{}:{}@{}", ecosystem, name, version)
+}
+
+fn describe_license(license: &Option<&str>) -> String {
+  license.unwrap_or("unknown").to_owned()
+}
@@ -0,0 +1,264 @@
+mod common;
+
+use common::{depsfetch, scratch_dir, write, MockServer, Response};
+use depsfetch::report::{copyrights, text_key, text_url, Copyright, Notice};
+use std::fs;
+
+const MIT: &str = "Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the \"Software\"), to deal
+in the Software without restriction.
+
+THE SOFTWARE IS PROVIDED \"AS IS\", WITHOUT WARRANTY OF ANY KIND.";
+
+const BSD_CLAUSE: &str = "1. Redistributions of source code must retain the above
+   copyright notice, this list of conditions and the following disclaimer.
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS \"AS IS\"";
+
+fn copyright(years: Option<&str>, holder: &str) -> Copyright {
+  Copyright {
+    years: years.map(str::to_owned),
+    holder: holder.to_owned(),
+  }
+}
+
+// Headers as packages ship them
+#[test]
+fn copyright_lines_are_parsed() {
+  let cases: &[(&str, &[Copyright])] = &[
+    ("Copyright (c) 2015 Steve Mao", &[copyright(Some("2015"), "Steve Mao")]),
+    (
+      "Copyright (c) Sindre Sorhus <sindresorhus@gmail.com> (https://sindresorhus.com)",
+      &[copyright(
+        None,
+        "Sindre Sorhus <sindresorhus@gmail.com> (https://sindresorhus.com)",
+      )],
+    ),
+    (
+      "Copyright © 2012–2020 Jane Doe",
+      &[copyright(Some("2012-2020"), "Jane Doe")],
+    ),
+    (
+      "Copyright 2009-2015 Google Inc. All rights reserved.",
+      &[copyright(Some("2009-2015"), "Google Inc.")],
+    ),
+    (
+      "Copyright 2009 The Go Authors.",
+      &[copyright(Some("2009"), "The Go Authors.")],
+    ),
+    (
+      "Copyright (C) 2007 Free Software Foundation, Inc. <https://fsf.org/>",
+      &[copyright(
+        Some("2007"),
+        "Free Software Foundation, Inc. <https://fsf.org/>",
+      )],
+    ),
+    (
+      " * Copyright (c) 2014-present, Facebook, Inc.",
+      &[copyright(Some("2014-present"), "Facebook, Inc.")],
+    ),
+    (
+      "# (c) 2016, 2018 Joe Bloggs",
+      &[copyright(Some("2016, 2018"), "Joe Bloggs")],
+    ),
+    // One line, several holders
+    (
+      "(c) 2015, 2017-2019 Jane Doe, 2020 Acme Inc.",
+      &[
+        copyright(Some("2015, 2017-2019"), "Jane Doe"),
+        copyright(Some("2020"), "Acme Inc."),
+      ],
+    ),
+    (
+      "Copyright 2018 Jane Doe; Bob Roe",
+      &[copyright(Some("2018"), "Jane Doe"), copyright(None, "Bob Roe")],
+    ),
+    ("COPYRIGHT (C) 2001 BY ACME", &[copyright(Some("2001"), "BY ACME")]),
+    // Prose about copyright, list items and template placeholders are no statements
+    (BSD_CLAUSE, &[]),
+    ("(c) You must retain, in the Source form of any Derivative Works", &[]),
+    ("Copyright [yyyy] [name of copyright owner]", &[]),
+    ("Copyright (C) <year>  <name of author>", &[]),
+    ("copyright law, and the following", &[]),
+    ("Copyright", &[]),
+  ];
+  for (text, expected) in cases {
+    assert_eq!(copyrights(text), *expected, "{:?}", text);
+  }
+
+  // Every line of a text, each holder once
+  let text = "Copyright 2010-2017 Mike Bostock\nCopyright 2001 Jane Roe\n\nCopyright 2010-2017 Mike Bostock\n";
+  assert_eq!(
+    copyrights(text),
+    [
+      copyright(Some("2010-2017"), "Mike Bostock"),
+      copyright(Some("2001"), "Jane Roe")
+    ]
+  );
+  assert_eq!(
+    copyright(Some("2015"), "Steve Mao").to_string(),
+    "Copyright (c) 2015 Steve Mao"
+  );
+}
+
+#[test]
+fn texts_differing_in_copyright_and_whitespace_share_a_key() {
+  let jane = format!("MIT License\n\nCopyright (c) 2015 Jane Doe\n\n{}", MIT);
+  let bob = format!(
+    "MIT License\n\nCopyright (c) 2019-2021 Bob Roe\nAll rights reserved.\n\n{}\n",
+    MIT.replace('\n', "\n  ")
+  );
+  assert_eq!(text_key(&jane), text_key(&bob));
+  assert_eq!(text_key(&jane).len(), 16);
+  assert_ne!(text_key(&jane), text_key(&jane.replace("free of charge", "for a fee")));
+}
+
+#[test]
+fn notice_lists_each_text_once() {
+  let mut notice = Notice::default();
+  let jane = notice.add(
+    "left-pad@1.3.0",
+    Some("MIT"),
+    &format!("Copyright (c) 2015 Jane Doe\n\n{}", MIT),
+  );
+  let bob = notice.add(
+    "is-odd@1.0.0",
+    Some("MIT"),
+    &format!("Copyright (c) 2019 Bob Roe\n\n{}", MIT),
+  );
+  let apache = notice.add(
+    "tslib@2.0.0",
+    Some("Apache-2.0"),
+    "Apache License\nVersion 2.0, January 2004",
+  );
+  notice.add_without_text("internal-1@1.0.0", Some("MIT"));
+  assert_eq!(jane, bob);
+  assert_ne!(jane, apache);
+  assert_eq!(notice.counts(), (2, 3));
+
+  let rendered = notice.render();
+  assert!(
+    rendered.contains("2 unique license text(s) cover 3 package(s), 1 package(s) without a license text"),
+    "{}",
+    rendered
+  );
+  assert_eq!(rendered.matches("Permission is hereby granted").count(), 1);
+  // The shared text first, its copyright lines only under each package
+  assert!(rendered.find(&format!("MIT (text {}), 2 package(s)", jane)) < rendered.find("Apache-2.0 (text"));
+  assert!(rendered
+    .contains("  is-odd@1.0.0\n    Copyright (c) 2019 Bob Roe\n  left-pad@1.3.0\n    Copyright (c) 2015 Jane Doe\n"));
+  assert!(!rendered.contains("\nCopyright (c) 2015 Jane Doe"));
+  let separator = "=".repeat(80);
+  assert!(rendered.ends_with(&format!(
+    "Without a license text\n{}\n\n  internal-1@1.0.0 (MIT)\n",
+    separator
+  )));
+}
+
+#[test]
+fn license_links_lead_to_raw_texts() {
+  assert_eq!(
+    text_url("https://github.com/stevemao/left-pad/blob/master/LICENSE"),
+    "https://raw.githubusercontent.com/stevemao/left-pad/master/LICENSE"
+  );
+  assert_eq!(
+    text_url("https://gitlab.com/o/r/-/blob/main/LICENSE.md"),
+    "https://gitlab.com/o/r/-/raw/main/LICENSE.md"
+  );
+  assert_eq!(
+    text_url("https://unpkg.com/left-pad@1.3.0/LICENSE"),
+    "https://unpkg.com/left-pad@1.3.0/LICENSE"
+  );
+}
+
+// Manual components with license links on the mock server, two of them with the same text
+#[test]
+fn notice_file_is_written_and_keys_reach_the_json_rows() {
+  let server = MockServer::start(|request| match request.path.as_str() {
+    "/jane" => Response::json(format!("Copyright (c) 2015 Jane Doe\n\n{}", MIT)),
+    "/bob" => Response::json(format!("Copyright (c) 2019 Bob Roe\n\n{}", MIT)),
+    "/page" => Response::json("<!DOCTYPE html><html></html>"),
+    _ => Response::status(404),
+  });
+  let base = scratch_dir("attribution");
+  write(&base.join("app/package.json"), "{}");
+  let component = |name: &str, path: &str| {
+    format!(
+      "[[manual_component]]\nname = \"{}\"\nversion = \"1.0.0\"\nlicense = \"MIT\"\nlicense_url = \"{}{}\"\n",
+      name, server.base, path
+    )
+  };
+  write(
+    &base.join("config.toml"),
+    &[
+      component("sdk-a", "/jane"),
+      component("sdk-b", "/bob"),
+      component("sdk-c", "/page"),
+      component("sdk-d", "/gone"),
+    ]
+    .join("\n"),
+  );
+  let output = depsfetch(
+    &base,
+    &[
+      "app",
+      "--config",
+      "config.toml",
+      "--format",
+      "json",
+      "--jsonl",
+      "rows.jsonl",
+      "--notice",
+      "NOTICE",
+      "--final-retry-window",
+      "0",
+    ],
+    &[],
+  );
+  assert!(
+    output.status.success(),
+    "{}{}",
+    String::from_utf8_lossy(&output.stdout),
+    String::from_utf8_lossy(&output.stderr)
+  );
+
+  let notice = fs::read_to_string(base.join("NOTICE")).unwrap();
+  assert!(
+    notice.contains("1 unique license text(s) cover 2 package(s), 2 package(s) without a license text"),
+    "{}",
+    notice
+  );
+  assert_eq!(notice.matches("Permission is hereby granted").count(), 1);
+  assert!(
+    notice.contains("  sdk-c@1.0.0 (MIT)\n  sdk-d@1.0.0 (MIT)\n"),
+    "{}",
+    notice
+  );
+
+  let keys = fs::read_to_string(base.join("rows.jsonl"))
+    .unwrap()
+    .lines()
+    .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+    .map(|row| {
+      (
+        row["name"].as_str().unwrap().to_owned(),
+        row["license_text_key"].as_str().map(str::to_owned),
+      )
+    })
+    .collect::<Vec<_>>();
+  let key = keys[0].1.clone().unwrap();
+  assert!(notice.contains(&format!("MIT (text {}), 2 package(s)", key)));
+  assert_eq!(
+    keys,
+    [
+      ("sdk-a".to_owned(), Some(key.clone())),
+      ("sdk-b".to_owned(), Some(key)),
+      ("sdk-c".to_owned(), None),
+      ("sdk-d".to_owned(), None),
+    ]
+  );
+  // One of the outputs, so it is checksummed
+  assert!(fs::read_to_string(base.join("deps_report.sha256"))
+    .unwrap()
+    .contains("NOTICE"));
+  let _ = fs::remove_dir_all(&base);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::{scratch_dir, write};
+use depsfetch::report::write_bundle;
+use std::{fs, path::Path};
+
+fn bundle(dir: &Path, name: &str, files: &[&str]) -> anyhow::Result<Vec<u8>> {
+  let path = dir.join(name).display().to_string();
+  let files = files
+    .iter()
+    .map(|file| dir.join(file).display().to_string())
+    .collect::<Vec<_>>();
+  let run_info = [
+    ("format", "json".to_owned(), "flag"),
+    ("version", "0.1.0".to_owned(), ""),
+  ];
+  write_bundle(&path, &files, &run_info)?;
+  Ok(fs::read(&path).unwrap())
+}
+
+#[test]
+fn unchanged_outputs_give_a_byte_identical_bundle() {
+  let dir = scratch_dir("bundle-identical");
+  write(&dir.join("deps_report.jsonl"), "{\"name\":\"left-pad\"}\n");
+  write(&dir.join("NOTICE"), "left-pad: WTFPL\n");
+
+  let first = bundle(&dir, "first.zip", &["deps_report.jsonl", "NOTICE"]).unwrap();
+  // Files written later, listed in another order
+  std::thread::sleep(std::time::Duration::from_millis(1100));
+  write(&dir.join("NOTICE"), "left-pad: WTFPL\n");
+  let second = bundle(&dir, "second.zip", &["NOTICE", "deps_report.jsonl"]).unwrap();
+  assert_eq!(first, second);
+
+  write(&dir.join("NOTICE"), "left-pad: MIT\n");
+  let changed = bundle(&dir, "changed.zip", &["NOTICE", "deps_report.jsonl"]).unwrap();
+  assert_ne!(first, changed);
+  let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn failed_bundle_leaves_the_previous_one_alone() {
+  let dir = scratch_dir("bundle-failed");
+  write(&dir.join("NOTICE"), "left-pad: WTFPL\n");
+  let previous = bundle(&dir, "release.zip", &["NOTICE"]).unwrap();
+
+  assert!(bundle(&dir, "release.zip", &["NOTICE", "missing.jsonl"]).is_err());
+  // Two outputs under one entry name
+  write(&dir.join("nested/NOTICE"), "other\n");
+  let outside = dir.join("nested/NOTICE").display().to_string();
+  let path = dir.join("release.zip").display().to_string();
+  assert!(write_bundle(&path, &[dir.join("NOTICE").display().to_string(), outside], &[]).is_err());
+
+  assert_eq!(fs::read(dir.join("release.zip")).unwrap(), previous);
+  assert!(!dir.join("release.zip.partial").exists());
+
+  // Written in full, but the rename into place fails
+  write(&dir.join("occupied.zip/keep"), "");
+  assert!(bundle(&dir, "occupied.zip", &["NOTICE"]).is_err());
+  assert!(!dir.join("occupied.zip.partial").exists());
+  let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use common::{scratch_dir, write};
+use depsfetch::{
+  cache::BranchCache,
+  http::{BackendFuture, HttpResponse, MetadataBackend, MetadataRequest},
+  resolve::{resolve, ResolveOptions, ResolvedDependency},
+  types::{DepScope, DepsEntry, Ecosystem},
+};
+use reqwest::StatusCode;
+use std::{
+  collections::BTreeSet,
+  env, fs,
+  sync::{Arc, Mutex},
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+const REPO: &str = "https://github.com/o/r";
+const API: &str = "https://api.github.com/repos/o/r";
+
+// npm and GitHub: left-pad lives in o/r, whose default branch is `main` and whose LICENSE is only there (or nowhere
+// with `licensed` off). Every URL asked for is kept.
+struct Repository {
+  licensed: bool,
+  asked: Mutex<Vec<String>>,
+}
+
+impl Repository {
+  fn new(licensed: bool) -> Self {
+    Self {
+      licensed,
+      asked: Mutex::default(),
+    }
+  }
+
+  fn asked(&self, prefix: &str) -> Vec<String> {
+    let asked = self.asked.lock().unwrap();
+    asked.iter().filter(|url| url.starts_with(prefix)).cloned().collect()
+  }
+}
+
+impl MetadataBackend for Repository {
+  fn fetch<'a>(&'a self, request: MetadataRequest<'a>) -> BackendFuture<'a> {
+    self.asked.lock().unwrap().push(request.url.to_owned());
+    let ok = |body: &str| HttpResponse::new(StatusCode::OK, request.url, body.to_owned());
+    let mut response = match request.url {
+      url if url.ends_with("/left-pad/1.3.0") => {
+        ok(r#"{"license": "MIT", "repository": "git+https://github.com/o/r.git"}"#)
+      },
+      API => ok(r#"{"default_branch": "main"}"#),
+      REPO => ok(""),
+      "https://github.com/o/r/blob/main/LICENSE" if self.licensed => {
+        ok("MIT License\n\nPermission is hereby granted, free of charge")
+      },
+      _ => HttpResponse::new(StatusCode::NOT_FOUND, request.url, ""),
+    };
+    response.content_type = Some("text/plain".to_owned());
+    Box::pin(async move { Ok(response) })
+  }
+}
+
+fn left_pad(backend: &Repository, branches: &Arc<BranchCache>) -> ResolvedDependency {
+  let entry = DepsEntry {
+    name: "left-pad".to_owned(),
+    version: "1.3.0".to_owned(),
+    scope: DepScope::Runtime,
+    sources: BTreeSet::new(),
+  };
+  let options = ResolveOptions::new(Ecosystem::Npm).with_branch_cache(Arc::clone(branches));
+  let runtime = tokio::runtime::Runtime::new().unwrap();
+  runtime.block_on(resolve([entry], backend, options)).remove(0)
+}
+
+fn now() -> u64 {
+  SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+// The cached branch is wrong: its probe finds nothing, so the entry is dropped, the branch looked up again and the
+// probe repeated once on it
+#[test]
+fn stale_branch_is_invalidated_and_the_probe_retried() {
+  let backend = Repository::new(true);
+  let branches = Arc::new(BranchCache::default());
+  branches.insert(REPO, "master");
+  let resolved = left_pad(&backend, &branches);
+
+  let row = resolved.row.as_ref().unwrap();
+  assert_eq!(
+    row.license_url.as_deref(),
+    Some("https://github.com/o/r/blob/main/LICENSE")
+  );
+  assert!(
+    row
+      .provenance
+      .contains(&"default branch changed from master to main".to_owned()),
+    "{:?}",
+    row.provenance
+  );
+  assert_eq!(branches.get(REPO).as_deref(), Some("main"));
+  assert_eq!(backend.asked(API).len(), 1);
+  // Every candidate file on the stale branch, then on the new one up to the license
+  let probes = backend.asked("https://github.com/o/r/blob/");
+  assert_eq!(probes.iter().filter(|url| url.contains("/blob/master/")).count(), 4);
+  assert_eq!(
+    probes.last().map(String::as_str),
+    Some("https://github.com/o/r/blob/main/LICENSE")
+  );
+
+  // Now cached right, the next package goes straight to the license
+  let backend = Repository::new(true);
+  left_pad(&backend, &branches);
+  assert!(backend.asked(API).is_empty());
+  assert_eq!(
+    backend.asked("https://github.com/o/r/blob/"),
+    [
+      "https://github.com/o/r/blob/main/LICENSE.txt",
+      "https://github.com/o/r/blob/main/LICENSE",
+    ]
+  );
+}
+
+// A right branch in a repository without a license: looked up again, but the same branch isn't probed twice
+#[test]
+fn unchanged_branch_is_not_probed_again() {
+  let backend = Repository::new(false);
+  let branches = Arc::new(BranchCache::default());
+  branches.insert(REPO, "main");
+  let resolved = left_pad(&backend, &branches);
+
+  let row = resolved.row.as_ref().unwrap();
+  assert_eq!(row.license_url, None);
+  assert!(!row
+    .provenance
+    .iter()
+    .any(|line| line.starts_with("default branch changed")));
+  assert_eq!(backend.asked(API).len(), 1);
+  assert_eq!(backend.asked("https://github.com/o/r/blob/").len(), 4);
+  assert_eq!(branches.get(REPO).as_deref(), Some("main"));
+}
+
+// The cache file between runs: an expired or unreadable one is looked up again and written back fresh. One test, as
+// both cases point XDG_DATA_HOME at their own directory.
+#[test]
+fn expired_and_corrupt_entries_are_fetched_again() {
+  let data = scratch_dir("branch-cache");
+  env::set_var("XDG_DATA_HOME", &data);
+  let cache_file = data.join("collect_lic_info/cache.json");
+  let expired = now() - 31 * 24 * 60 * 60;
+  let files = [
+    format!(
+      r#"{{"branches": {{"github.com/o/r": {{"branch": "master", "checked": {}}}}}}}"#,
+      expired
+    ),
+    "{\"branches\": [garbage".to_owned(),
+  ];
+
+  for content in files {
+    write(&cache_file, &content);
+    let branches = Arc::new(BranchCache::load());
+    assert_eq!(branches.get(REPO), None, "{}", content);
+
+    let backend = Repository::new(true);
+    let resolved = left_pad(&backend, &branches);
+    assert_eq!(backend.asked(API).len(), 1, "{}", content);
+    assert_eq!(
+      resolved.row.as_ref().unwrap().license_url.as_deref(),
+      Some("https://github.com/o/r/blob/main/LICENSE")
+    );
+    branches.save().unwrap();
+
+    let saved = serde_json::from_slice::<serde_json::Value>(&fs::read(&cache_file).unwrap()).unwrap();
+    let entry = &saved["branches"]["github.com/o/r"];
+    assert_eq!(entry["branch"], "main", "{}", saved);
+    assert!(entry["checked"].as_u64().unwrap() >= now() - 60, "{}", saved);
+    // The next run has it
+    assert_eq!(BranchCache::load().get(REPO).as_deref(), Some("main"));
+  }
+  env::remove_var("XDG_DATA_HOME");
+  let _ = fs::remove_dir_all(&data);
+}
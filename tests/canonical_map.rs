@@ -0,0 +1,74 @@
+#![cfg(feature = "xlsx")]
+
+mod common;
+
+use common::{depsfetch, scratch_dir, workbook::read_workbook, MockServer, Response};
+use serde_json::json;
+use std::{fs, path::PathBuf};
+
+// tests/fixtures/canonical: web depends on protobufjs, @grpc/grpc-js and left-pad from npm, svc on the Go
+// protobuf and grpc modules, replaced by local forks in svc/third_party so their licenses are read from disk.
+// projects.toml puts each pair under one project.
+fn fixture(path: &str) -> String {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    .join("tests/fixtures/canonical")
+    .join(path)
+    .display()
+    .to_string()
+}
+
+#[test]
+fn packages_of_both_ecosystems_share_a_project() {
+  let server = MockServer::start(|request| {
+    let path = request
+      .path
+      .trim_start_matches('/')
+      .replace("%2f", "/")
+      .replace("%2F", "/");
+    let (name, version) = path.rsplit_once('/').unwrap_or((&path, "latest"));
+    let license = match name {
+      "protobufjs" => "BSD-3-Clause",
+      "@grpc/grpc-js" => "Apache-2.0",
+      _ => "WTFPL",
+    };
+    let homepage = format!("https://example.com/{}", name);
+    Response::json(json!({"name": name, "version": version, "license": license, "homepage": homepage}).to_string())
+  });
+  let base = scratch_dir("canonical-map");
+  let (tree, map) = (fixture(""), fixture("projects.toml"));
+  let args = [
+    &tree,
+    "--canonical-map",
+    &map,
+    "--npm-registry",
+    &server.base,
+    "--final-retry-window",
+    "0",
+  ];
+  let output = depsfetch(&base, &args, &[]);
+  assert!(
+    output.status.success(),
+    "{}{}",
+    String::from_utf8_lossy(&output.stdout),
+    String::from_utf8_lossy(&output.stderr)
+  );
+
+  let sheets = read_workbook(&base.join("deps_report.xlsx"));
+  let projects = sheets.iter().find(|sheet| sheet.name == "Projects").unwrap();
+  let rows = projects.rows.iter().map(|row| row.join(" | ")).collect::<Vec<_>>();
+  assert_eq!(
+    rows,
+    [
+      "Проект | Пакет | Экосистема | Версия | Тип лицензии",
+      "grpc |  |  | 1.10.0, v1.62.0 | Apache-2.0",
+      " | @grpc/grpc-js | npm | 1.10.0 | Apache-2.0",
+      " | google.golang.org/grpc | go | v1.62.0 | Apache-2.0",
+      "left-pad |  |  | 1.3.0 | WTFPL",
+      " | left-pad | npm | 1.3.0 | WTFPL",
+      "protobuf |  |  | 7.2.5, v1.33.0 | BSD-3-Clause",
+      " | protobufjs | npm | 7.2.5 | BSD-3-Clause",
+      " | google.golang.org/protobuf | go | v1.33.0 | BSD-3-Clause",
+    ]
+  );
+  let _ = fs::remove_dir_all(&base);
+}
@@ -0,0 +1,258 @@
+// Helpers shared by the integration tests; each test file uses its own subset
+#![allow(dead_code)]
+
+pub mod workbook;
+
+use depsfetch::types::{DepScope, Ecosystem, ReportRow};
+use std::{
+  collections::BTreeMap,
+  env, fs,
+  io::{BufRead, BufReader, Read, Write},
+  net::{TcpListener, TcpStream},
+  path::{Path, PathBuf},
+  process::{Command, Output},
+  sync::{Arc, Mutex},
+  thread,
+};
+
+// An empty directory of its own under the system temp dir, emptied again by the next run of the same test
+pub fn scratch_dir(name: &str) -> PathBuf {
+  let dir = env::temp_dir().join(format!("depsfetch-test-{}-{}", name, std::process::id()));
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+  dir
+}
+
+pub fn write(path: &Path, content: &str) {
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).unwrap();
+  }
+  fs::write(path, content).unwrap();
+}
+
+// What the mock server was asked: method, path and headers (names lowercased)
+#[derive(Debug, Clone)]
+pub struct Request {
+  pub method: String,
+  pub path: String,
+  pub headers: BTreeMap<String, String>,
+}
+
+pub struct Response {
+  pub status: u16,
+  pub content_type: &'static str,
+  pub body: String,
+  // Sent after content-type, e.g. a redirect's location
+  pub headers: Vec<(&'static str, String)>,
+}
+
+impl Response {
+  pub fn json(body: impl Into<String>) -> Self {
+    Self {
+      status: 200,
+      content_type: "application/json",
+      body: body.into(),
+      headers: Vec::new(),
+    }
+  }
+
+  pub fn status(status: u16) -> Self {
+    Self {
+      status,
+      content_type: "text/plain",
+      body: String::new(),
+      headers: Vec::new(),
+    }
+  }
+
+  pub fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+    self.headers.push((name, value.into()));
+    self
+  }
+}
+
+// An HTTP/1.1 server on a free local port answering every request through `handler` on a thread of its own,
+// for as long as the test runs
+pub struct MockServer {
+  pub base: String,
+  requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockServer {
+  pub fn start(handler: impl Fn(&Request) -> Response + Send + Sync + 'static) -> Self {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let (handler, seen) = (Arc::new(handler), Arc::clone(&requests));
+    thread::spawn(move || {
+      for stream in listener.incoming().flatten() {
+        let (handler, seen) = (Arc::clone(&handler), Arc::clone(&seen));
+        thread::spawn(move || serve(stream, handler.as_ref(), &seen));
+      }
+    });
+    Self { base, requests }
+  }
+
+  pub fn requests(&self) -> Vec<Request> {
+    self.requests.lock().unwrap().clone()
+  }
+}
+
+fn serve(stream: TcpStream, handler: &(dyn Fn(&Request) -> Response + Send + Sync), seen: &Mutex<Vec<Request>>) {
+  let mut reader = BufReader::new(stream.try_clone().unwrap());
+  let mut line = String::new();
+  if reader.read_line(&mut line).unwrap_or_default() == 0 {
+    return;
+  }
+  let mut parts = line.split_whitespace();
+  let (method, path) = (
+    parts.next().unwrap_or_default().to_owned(),
+    parts.next().unwrap_or_default().to_owned(),
+  );
+  let mut headers = BTreeMap::new();
+  loop {
+    line.clear();
+    if reader.read_line(&mut line).unwrap_or_default() == 0 || line.trim().is_empty() {
+      break;
+    }
+    if let Some((name, value)) = line.split_once(':') {
+      headers.insert(name.trim().to_lowercase(), value.trim().to_owned());
+    }
+  }
+  if let Some(length) = headers
+    .get("content-length")
+    .and_then(|length| length.parse::<u64>().ok())
+  {
+    let _ = reader.by_ref().take(length).read_to_end(&mut Vec::new());
+  }
+
+  let request = Request { method, path, headers };
+  let response = handler(&request);
+  seen.lock().unwrap().push(request.clone());
+  let body = match request.method.as_str() {
+    "HEAD" => "",
+    _ => response.body.as_str(),
+  };
+  let headers = response
+    .headers
+    .iter()
+    .map(|(name, value)| format!("{}: {}\r\n", name, value))
+    .collect::<String>();
+  let mut stream = stream;
+  let _ = write!(
+    stream,
+    "HTTP/1.1 {} Mock\r\ncontent-type: {}\r\n{}content-length: {}\r\nconnection: close\r\n\r\n{}",
+    response.status,
+    response.content_type,
+    headers,
+    response.body.len(),
+    body
+  );
+}
+
+// Every file under `dir`, recursively
+pub fn files(dir: &Path) -> Vec<PathBuf> {
+  walkdir::WalkDir::new(dir)
+    .into_iter()
+    .flatten()
+    .filter(|entry| entry.file_type().is_file())
+    .map(|entry| entry.into_path())
+    .collect()
+}
+
+// The file's bytes, and for a zip (the workbook, the bundle) every entry's uncompressed bytes as well
+pub fn contents(path: &Path) -> Vec<u8> {
+  let mut content = fs::read(path).unwrap();
+  if let Ok(mut archive) = zip::ZipArchive::new(fs::File::open(path).unwrap()) {
+    for idx in 0..archive.len() {
+      archive.by_index(idx).unwrap().read_to_end(&mut content).unwrap();
+    }
+  }
+  content
+}
+
+pub fn contains(haystack: &[u8], needle: &str) -> bool {
+  haystack.windows(needle.len()).any(|window| window == needle.as_bytes())
+}
+
+// The binary run in `cwd` with nothing from the caller's environment but PATH, and `envs` on top
+pub fn depsfetch(cwd: &Path, args: &[&str], envs: &[(&str, &str)]) -> Output {
+  Command::new(env!("CARGO_BIN_EXE_depsfetch"))
+    .args(args)
+    .current_dir(cwd)
+    .env_clear()
+    .env("PATH", env::var_os("PATH").unwrap_or_default())
+    .env("HOME", cwd)
+    .envs(envs.iter().copied())
+    .output()
+    .unwrap()
+}
+
+// The effective settings of a scan as (setting, value, source), read back from the MANIFEST.json of a bundle
+pub fn run_info(cwd: &Path, args: &[&str], envs: &[(&str, &str)]) -> Vec<(String, String, String)> {
+  let bundle = cwd.join("run_info.zip");
+  let bundle_arg = bundle.display().to_string();
+  let mut args = args.to_vec();
+  args.extend(["--bundle", bundle_arg.as_str(), "--final-retry-window", "0"]);
+  let output = depsfetch(cwd, &args, envs);
+  assert!(
+    output.status.success(),
+    "{}{}",
+    String::from_utf8_lossy(&output.stdout),
+    String::from_utf8_lossy(&output.stderr)
+  );
+
+  let mut archive = zip::ZipArchive::new(fs::File::open(&bundle).unwrap()).unwrap();
+  let mut manifest = String::new();
+  archive
+    .by_name("MANIFEST.json")
+    .unwrap()
+    .read_to_string(&mut manifest)
+    .unwrap();
+  let _ = fs::remove_file(&bundle);
+  let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+  manifest["run_info"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|entry| {
+      let field = |name: &str| entry[name].as_str().unwrap_or_default().to_owned();
+      (field("setting"), field("value"), field("source"))
+    })
+    .collect()
+}
+
+// The value and source `run_info` recorded for `setting`
+pub fn setting(run_info: &[(String, String, String)], setting: &str) -> (String, String) {
+  run_info
+    .iter()
+    .find(|(name, _, _)| name == setting)
+    .map(|(_, value, source)| (value.clone(), source.clone()))
+    .unwrap_or_else(|| panic!("No {} in the run info", setting))
+}
+
+// A resolved row as the generator hands it to the writer, homepage and license link derived from the name
+pub fn report_row(ecosystem: Ecosystem, name: &str, version: &str, license: Option<&str>) -> ReportRow {
+  ReportRow {
+    ecosystem,
+    name: name.to_owned(),
+    version: version.to_owned(),
+    scope: DepScope::Runtime,
+    homepage: format!("https://example.com/{}", name),
+    license: license.map(str::to_owned),
+    license_confidence: None,
+    license_url: license.map(|_| format!("https://example.com/{}/LICENSE", name)),
+    funding: None,
+    platform: None,
+    repo_mismatch: None,
+    installed_mismatch: None,
+    third_party_notices: None,
+    link_status: None,
+    license_text_key: None,
+    attestation: None,
+    osi_approved: None,
+    fsf_libre: None,
+    size: None,
+    provenance: Vec::new(),
+  }
+}
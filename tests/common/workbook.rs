@@ -0,0 +1,117 @@
+// Just enough of an xlsx reader to check what the writer put on its sheets
+use regex::Regex;
+use std::{fs, io::Read, path::Path};
+
+pub struct Sheet {
+  pub name: String,
+  // Every row up to the last one written, cells as displayed: shared and inline strings, formula results, numbers
+  pub rows: Vec<Vec<String>>,
+  // Cells holding a hyperlink, like `C2`
+  pub hyperlinks: Vec<String>,
+}
+
+impl Sheet {
+  // The cell at `row` and `col`, both counted from 0
+  pub fn cell(&self, row: usize, col: usize) -> &str {
+    self
+      .rows
+      .get(row)
+      .and_then(|cells| cells.get(col))
+      .map_or("", String::as_str)
+  }
+
+  // Rows below the header
+  pub fn body(&self) -> &[Vec<String>] {
+    self.rows.get(1..).unwrap_or_default()
+  }
+}
+
+// The sheets of the workbook at `path`, in their tab order
+pub fn read_workbook(path: &Path) -> Vec<Sheet> {
+  let mut archive = zip::ZipArchive::new(fs::File::open(path).unwrap()).unwrap();
+  let mut part = |name: &str| {
+    let mut content = String::new();
+    if let Ok(mut file) = archive.by_name(name) {
+      file.read_to_string(&mut content).unwrap();
+    }
+    content
+  };
+
+  let shared = Regex::new(r"(?s)<si>.*?</si>")
+    .unwrap()
+    .find_iter(&part("xl/sharedStrings.xml"))
+    .map(|si| text(si.as_str()))
+    .collect::<Vec<_>>();
+  let names = Regex::new(r#"<sheet name="([^"]*)""#)
+    .unwrap()
+    .captures_iter(&part("xl/workbook.xml"))
+    .map(|captures| unescape(&captures[1]))
+    .collect::<Vec<_>>();
+
+  let cell = Regex::new(r#"(?s)<c r="([A-Z]+)(\d+)"([^>]*?)(?:/>|>(.*?)</c>)"#).unwrap();
+  let value = Regex::new(r"(?s)<v>(.*?)</v>").unwrap();
+  let hyperlink = Regex::new(r#"<hyperlink ref="([A-Z]+\d+)""#).unwrap();
+  names
+    .into_iter()
+    .enumerate()
+    .map(|(idx, name)| {
+      let xml = part(&format!("xl/worksheets/sheet{}.xml", idx + 1));
+      let mut rows: Vec<Vec<String>> = Vec::new();
+      for captures in cell.captures_iter(&xml) {
+        let (row, col) = (captures[2].parse::<usize>().unwrap() - 1, column(&captures[1]));
+        let body = captures.get(4).map_or("", |body| body.as_str());
+        let shown = match captures[3].contains(r#"t="s""#) {
+          true => shared[value.captures(body).unwrap()[1].parse::<usize>().unwrap()].clone(),
+          false if body.contains("<is>") => text(body),
+          false => value.captures(body).map(|v| unescape(&v[1])).unwrap_or_default(),
+        };
+        if rows.len() <= row {
+          rows.resize(row + 1, Vec::new());
+        }
+        if rows[row].len() <= col {
+          rows[row].resize(col + 1, String::new());
+        }
+        rows[row][col] = shown;
+      }
+      let hyperlinks = hyperlink
+        .captures_iter(&xml)
+        .map(|captures| captures[1].to_owned())
+        .collect();
+      Sheet { name, rows, hyperlinks }
+    })
+    .collect()
+}
+
+// `A` is 0, `AA` is 26
+fn column(letters: &str) -> usize {
+  letters
+    .bytes()
+    .fold(0, |col, letter| col * 26 + (letter - b'A') as usize + 1)
+    - 1
+}
+
+// The text runs of a shared or inline string
+fn text(xml: &str) -> String {
+  Regex::new(r"(?s)<t(?: [^>]*)?>(.*?)</t>")
+    .unwrap()
+    .captures_iter(xml)
+    .map(|captures| unescape(&captures[1]))
+    .collect()
+}
+
+// XML entities, and the `_x000D_` escapes OOXML uses for characters XML can't hold as they are
+fn unescape(xml: &str) -> String {
+  let escaped = Regex::new(r"_x([0-9A-Fa-f]{4})_").unwrap();
+  let xml = escaped.replace_all(xml, |captures: &regex::Captures| {
+    u32::from_str_radix(&captures[1], 16)
+      .ok()
+      .and_then(char::from_u32)
+      .map_or_else(|| captures[0].to_owned(), String::from)
+  });
+  xml
+    .replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&quot;", "\"")
+    .replace("&apos;", "'")
+    .replace("&amp;", "&")
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use common::scratch_dir;
+use depsfetch::{
+  http::{BackendFuture, HttpResponse, MetadataBackend, MetadataRequest},
+  report::{ReportWriter, ResolvedRow, Section, WriterMessage},
+  resolve::{resolve, ResolveOptions},
+  types::{DepScope, DepsEntry, Ecosystem, LicenseConfidence, ReportRow},
+};
+use reqwest::StatusCode;
+use std::{collections::BTreeSet, fs};
+
+// JSR and GitHub: @acme/path is MIT, declared in its published jsr.json when `declared` is set; otherwise only its
+// GitHub repository says so
+struct Jsr {
+  declared: bool,
+}
+
+impl MetadataBackend for Jsr {
+  fn fetch<'a>(&'a self, request: MetadataRequest<'a>) -> BackendFuture<'a> {
+    let ok = |body: &str| HttpResponse::new(StatusCode::OK, request.url, body.to_owned());
+    let response = match request.url {
+      "https://jsr.io/@acme/path/meta.json" => ok(r#"{"latest": "1.0.0", "versions": {"1.0.0": {}}}"#),
+      "https://jsr.io/@acme/path/1.0.0_meta.json" if self.declared => {
+        ok(r#"{"manifest": {"/jsr.json": {}, "/mod.ts": {}}}"#)
+      },
+      "https://jsr.io/@acme/path/1.0.0_meta.json" => ok(r#"{"manifest": {"/mod.ts": {}}}"#),
+      "https://jsr.io/@acme/path/1.0.0/jsr.json" => ok(r#"{"name": "@acme/path", "license": "MIT"}"#),
+      "https://api.jsr.io/scopes/acme/packages/path" => {
+        ok(r#"{"githubRepository": {"owner": "acme", "name": "path"}}"#)
+      },
+      "https://api.github.com/repos/acme/path/license" => ok(r#"{"license": {"spdx_id": "MIT"}}"#),
+      _ => HttpResponse::new(StatusCode::NOT_FOUND, request.url, ""),
+    };
+    Box::pin(async move { Ok(response) })
+  }
+}
+
+async fn resolve_path(declared: bool) -> ReportRow {
+  let entry = DepsEntry {
+    name: "@acme/path".to_owned(),
+    version: "1.0.0".to_owned(),
+    scope: DepScope::Runtime,
+    sources: BTreeSet::new(),
+  };
+  let mut resolved = resolve([entry], &Jsr { declared }, ResolveOptions::new(Ecosystem::Deno)).await;
+  resolved.remove(0).row.unwrap()
+}
+
+// The row as the writer keeps it with --min-confidence declared
+async fn written(row: ReportRow, name: &str) -> ReportRow {
+  let dir = scratch_dir(&format!("confidence-{}", name));
+  let path = dir.join("report.xlsx").display().to_string();
+  let writer = ReportWriter::new(&path)
+    .unwrap()
+    .with_min_confidence(LicenseConfidence::Declared)
+    .keep_rows();
+  let (sender, handle) = writer.spawn();
+  sender
+    .send(WriterMessage::Section(Section::Dependencies("Deno")))
+    .unwrap();
+  sender
+    .send(WriterMessage::Row(Box::new(ResolvedRow { sheet: "Deno", row })))
+    .unwrap();
+  sender.send(WriterMessage::Finish).unwrap();
+  let mut output = handle.await.unwrap().unwrap().unwrap();
+  let _ = fs::remove_dir_all(&dir);
+  output.rows.remove(0)
+}
+
+// The same package with and without a license declared in what it published: the second source finds the same
+// license with less confidence, and the minimum confidence then turns it unknown
+#[tokio::test]
+async fn second_source_flips_the_confidence() {
+  let declared = resolve_path(true).await;
+  assert_eq!(declared.license.as_deref(), Some("MIT"));
+  assert_eq!(declared.license_confidence, Some(LicenseConfidence::Declared));
+  assert!(declared
+    .provenance
+    .contains(&"license: jsr.json of the published package".to_owned()));
+
+  let detected = resolve_path(false).await;
+  assert_eq!(detected.license.as_deref(), Some("MIT"));
+  assert_eq!(detected.license_confidence, Some(LicenseConfidence::Detected));
+  assert!(detected.provenance.contains(&"license: GitHub license API".to_owned()));
+
+  let kept = written(declared, "declared").await;
+  assert_eq!(kept.license.as_deref(), Some("MIT"));
+  assert_eq!(kept.license_confidence, Some(LicenseConfidence::Declared));
+
+  let dropped = written(detected, "detected").await;
+  assert_eq!((dropped.license, dropped.license_confidence), (None, None));
+  assert!(
+    dropped
+      .provenance
+      .contains(&"license MIT dropped: detected is below declared".to_owned()),
+    "{:?}",
+    dropped.provenance
+  );
+}
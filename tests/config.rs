@@ -0,0 +1,103 @@
+mod common;
+
+use common::{run_info, scratch_dir, setting, write};
+use depsfetch::config::{self, CONFIG_FILE};
+use std::fs;
+
+const CONFIG: &str = r#"
+dedup = "highest"
+nested_notices_min_size = 7
+skip = ["^left-pad$"]
+columns = ["funding", "confidence"]
+funding = false
+
+[profile.legal]
+dedup = "lowest"
+skip = ["^is-odd$", "^is-even$"]
+
+[profile.broken]
+columns = ["funding", "colour"]
+"#;
+
+#[test]
+fn profile_overrides_the_base_setting_by_setting() {
+  let dir = scratch_dir("config-profile");
+  let path = dir.join("config.toml");
+  write(&path, CONFIG);
+  let path = path.display().to_string();
+
+  let base = config::load(&path, None).unwrap();
+  assert_eq!(base.dedup.as_deref(), Some("highest"));
+  // An explicit setting wins over the columns shorthand of the same layer
+  assert_eq!(
+    (base.funding, base.confidence, base.provenance),
+    (Some(false), Some(true), Some(false))
+  );
+
+  let legal = config::load(&path, Some("legal")).unwrap();
+  assert_eq!(legal.dedup.as_deref(), Some("lowest"));
+  // Lists are replaced, not appended to; what the profile doesn't mention stays
+  assert_eq!(legal.skip.map(|skip| skip.len()), Some(2));
+  assert_eq!(legal.nested_notices_min_size, Some(7));
+  assert_eq!(legal.confidence, Some(true));
+
+  let err = config::load(&path, Some("audit")).err().unwrap().to_string();
+  assert_eq!(err, "Unknown profile `audit`, available: broken, legal");
+  let err = format!("{:#}", config::load(&path, Some("broken")).err().unwrap());
+  assert!(
+    err.contains("profile `broken`") && err.contains("Unknown column `colour`"),
+    "{}",
+    err
+  );
+  let _ = fs::remove_dir_all(&dir);
+}
+
+// Flag over environment over the config's profile over its base over the default, each recorded with its source
+#[test]
+fn flags_beat_env_beats_config() {
+  let dir = scratch_dir("config-precedence");
+  fs::create_dir_all(dir.join("project")).unwrap();
+  write(&dir.join("config.toml"), CONFIG);
+  let scan = |args: &[&str], envs: &[(&str, &str)]| {
+    let mut all = vec!["project"];
+    all.extend(args);
+    setting(&run_info(&dir, &all, envs), "dedup")
+  };
+  let pair = |value: &str, source: &str| (value.to_owned(), source.to_owned());
+
+  assert_eq!(scan(&[], &[]), pair("all", "default"));
+  assert_eq!(scan(&["--config", "config.toml"], &[]), pair("highest", "config"));
+  assert_eq!(
+    scan(&["--config", "config.toml", "--profile", "legal"], &[]),
+    pair("lowest", "config")
+  );
+  let env = [("COLLECT_LIC_DEDUP", "all")];
+  assert_eq!(
+    scan(&["--config", "config.toml", "--profile", "legal"], &env),
+    pair("all", "env")
+  );
+  assert_eq!(
+    scan(&["--config", "config.toml", "--dedup", "highest"], &env),
+    pair("highest", "flag")
+  );
+  // The profile can come from the environment too
+  assert_eq!(
+    scan(&["--config", "config.toml"], &[("COLLECT_LIC_PROFILE", "legal")]),
+    pair("lowest", "config")
+  );
+  let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn config_is_found_in_the_scanned_directory() {
+  let dir = scratch_dir("config-discovery");
+  write(&dir.join("project").join(CONFIG_FILE), "dedup = \"lowest\"\n");
+  let info = run_info(&dir, &["project"], &[]);
+  assert_eq!(setting(&info, "dedup"), ("lowest".to_owned(), "config".to_owned()));
+  assert_eq!(setting(&info, "config").0, format!("project/{}", CONFIG_FILE));
+
+  // Never in a hermetic run
+  let info = run_info(&dir, &["project", "--hermetic"], &[]);
+  assert_eq!(setting(&info, "dedup"), ("all".to_owned(), "default".to_owned()));
+  let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::{contains, contents, files, scratch_dir, write, MockServer, Response};
+use std::{
+  fs,
+  io::Write,
+  path::Path,
+  process::{Command, Output, Stdio},
+};
+
+const STORED_SENTINEL: &str = "sentinel-stored-7f3a9c";
+const GITHUB_SENTINEL: &str = "sentinel-github-52be10";
+const PASSPHRASE: &str = "test passphrase";
+
+// The binary with nothing from the caller's environment but PATH, its user data under `data`
+fn depsfetch(cwd: &Path, data: &Path, args: &[&str], stdin: &str) -> Output {
+  let mut child = Command::new(env!("CARGO_BIN_EXE_depsfetch"))
+    .args(args)
+    .current_dir(cwd)
+    .env_clear()
+    .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+    .env("HOME", data)
+    .env("XDG_DATA_HOME", data)
+    .env("DEPSFETCH_CREDENTIALS_PASSPHRASE", PASSPHRASE)
+    .env("GITHUB_TOKEN", GITHUB_SENTINEL)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+  child.wait_with_output().unwrap()
+}
+
+// A stored registry token and GITHUB_TOKEN are used, yet neither shows up in the console, the report, the run info,
+// the warnings log, the HTTP dump, the checksums or the bundle, including for requests that fail
+#[test]
+#[cfg_attr(feature = "keyring", ignore = "would store the sentinel in the system keyring")]
+fn sentinel_tokens_stay_out_of_every_output() {
+  let registry = MockServer::start(|request| match request.path.as_str() {
+    "/left-pad/1.3.0" => Response::json(r#"{"name":"left-pad","version":"1.3.0","license":"WTFPL"}"#),
+    "/is-odd/3.0.1" => Response::status(500),
+    _ => Response::json("not json"),
+  });
+  let root = scratch_dir("credentials");
+  let (data, out) = (root.join("data"), root.join("out"));
+  write(
+    &out.join("project/package.json"),
+    r#"{"name":"app","version":"1.0.0","dependencies":{"left-pad":"1.3.0","is-odd":"3.0.1","is-even":"1.0.0"}}"#,
+  );
+
+  let stored = depsfetch(
+    &out,
+    &data,
+    &["auth", "set", "127.0.0.1"],
+    &format!("{}\n", STORED_SENTINEL),
+  );
+  assert!(stored.status.success(), "{}", String::from_utf8_lossy(&stored.stderr));
+  let listed = depsfetch(&out, &data, &["auth", "list"], "");
+  assert!(String::from_utf8_lossy(&listed.stdout).contains("127.0.0.1"));
+
+  let run = depsfetch(
+    &out,
+    &data,
+    &[
+      "project",
+      "--npm-registry",
+      &registry.base,
+      "--npm-auth",
+      "bearer",
+      "--final-retry-window",
+      "0",
+      "--debug-http",
+      "http-dump",
+      "--jsonl",
+      "rows.jsonl",
+      "--csv",
+      "rows.csv",
+      "--graph",
+      "graph.dot",
+      "--bundle",
+      "release.zip",
+    ],
+    "",
+  );
+
+  // The token did its job
+  let requests = registry.requests();
+  assert!(!requests.is_empty());
+  for request in &requests {
+    assert_eq!(
+      request.headers.get("authorization").map(String::as_str),
+      Some(format!("Bearer {}", STORED_SENTINEL).as_str())
+    );
+  }
+  let mut outputs = vec![("stdout".to_owned(), run.stdout), ("stderr".to_owned(), run.stderr)];
+  outputs.extend(
+    files(&root)
+      .iter()
+      .map(|path| (path.display().to_string(), contents(path))),
+  );
+  assert!(outputs.iter().any(|(name, _)| name.ends_with("rows.jsonl")));
+  assert!(outputs.iter().any(|(name, _)| name.ends_with("release.zip")));
+  for (name, content) in &outputs {
+    for sentinel in [STORED_SENTINEL, GITHUB_SENTINEL] {
+      assert!(!contains(content, sentinel), "{} leaks {}", name, sentinel);
+    }
+  }
+  let _ = fs::remove_dir_all(&root);
+}
+
+// Owner-only from the first write, and still so once a second token rewrites it
+#[test]
+#[cfg(unix)]
+#[cfg_attr(feature = "keyring", ignore = "would store the token in the system keyring")]
+fn credentials_file_is_owner_only() {
+  use std::os::unix::fs::PermissionsExt;
+
+  let root = scratch_dir("credentials-mode");
+  let data = root.join("data");
+  fs::create_dir_all(&data).unwrap();
+  let credentials = data.join("collect_lic_info/credentials.json");
+  for host in ["registry.example.com", "git.example.com"] {
+    let stored = depsfetch(&root, &data, &["auth", "set", host], "token\n");
+    assert!(stored.status.success(), "{}", String::from_utf8_lossy(&stored.stderr));
+    let mode = fs::metadata(&credentials).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600, "{:o}", mode);
+  }
+  assert!(!data.join("collect_lic_info/credentials.json.partial").exists());
+  let listed = String::from_utf8_lossy(&depsfetch(&root, &data, &["auth", "list"], "").stdout).into_owned();
+  assert!(
+    listed.contains("registry.example.com") && listed.contains("git.example.com"),
+    "{}",
+    listed
+  );
+  let _ = fs::remove_dir_all(&root);
+}
@@ -0,0 +1,99 @@
+use depsfetch::report::{compare_versions, DedupStrategy};
+use std::cmp::Ordering;
+
+// Every version below is above all the ones before it
+fn assert_ascending(versions: &[&str]) {
+  for (idx, lower) in versions.iter().enumerate() {
+    for higher in &versions[idx + 1..] {
+      assert_eq!(
+        compare_versions(lower, higher),
+        Ordering::Less,
+        "{} < {}",
+        lower,
+        higher
+      );
+      assert_eq!(
+        compare_versions(higher, lower),
+        Ordering::Greater,
+        "{} > {}",
+        higher,
+        lower
+      );
+    }
+  }
+}
+
+#[test]
+fn pre_releases_order_below_their_release() {
+  // The semver spec's own example, then numbers past a single digit
+  assert_ascending(&[
+    "1.0.0-alpha",
+    "1.0.0-alpha.1",
+    "1.0.0-alpha.beta",
+    "1.0.0-beta",
+    "1.0.0-beta.2",
+    "1.0.0-beta.11",
+    "1.0.0-rc.1",
+    "1.0.0",
+    "1.0.1-0",
+    "1.0.1",
+    "1.2.0",
+    "1.10.0",
+    "10.0.0",
+  ]);
+}
+
+#[test]
+fn build_metadata_and_v_prefix_only_break_ties() {
+  // Same precedence: the order is still total, by the string, so no two spellings compare equal
+  for (a, b) in [
+    ("1.0.0+build.2", "1.0.0+build.10"),
+    ("v1.0.0", "1.0.0"),
+    ("V2.0.0", "v2.0.0"),
+  ] {
+    assert_ne!(compare_versions(a, b), Ordering::Equal, "{} vs {}", a, b);
+    assert_eq!(compare_versions(a, b), a.cmp(b));
+  }
+  // But never against a real difference in precedence
+  assert_ascending(&[
+    "1.0.0+zzz",
+    "v1.0.1",
+    "1.0.2+build",
+    "V1.1.0-rc.1+exp.sha.5114f85",
+    "v1.1.0",
+  ]);
+  assert_eq!(compare_versions("1.0.0", "1.0.0"), Ordering::Equal);
+}
+
+#[test]
+fn go_pseudo_versions_order_by_timestamp() {
+  assert_ascending(&[
+    "v0.0.0-20190101000000-abcdef123456",
+    "v0.0.0-20210305120000-0123456789ab",
+    "v0.1.0",
+    // A pseudo-version after a tag is a pre-release of the next patch
+    "v0.1.1-0.20220101000000-fedcba987654",
+    "v0.1.1",
+  ]);
+}
+
+#[test]
+fn non_semver_sorts_below_semver() {
+  assert_ascending(&[
+    "1.0", "abc", "latest", // Leading junk and empty pre-release identifiers aren't semver either
+    "x1.0.0", "0.0.1",
+  ]);
+  assert_eq!(compare_versions("1.0.0-", "0.0.1"), Ordering::Less);
+  assert_eq!(compare_versions("1.0.0-a..b", "0.0.1"), Ordering::Less);
+  assert_eq!(compare_versions("1.2.3.4", "0.0.1"), Ordering::Less);
+}
+
+#[test]
+fn strategies_parse() {
+  assert_eq!(DedupStrategy::parse("highest").unwrap(), DedupStrategy::Highest);
+  assert_eq!(DedupStrategy::parse("lowest").unwrap(), DedupStrategy::Lowest);
+  assert_eq!(
+    DedupStrategy::parse("newest").err().unwrap().to_string(),
+    "Unknown dedup strategy `newest`, expected one of: all, highest, lowest"
+  );
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{depsfetch, run_info, scratch_dir, setting, write};
+use std::{fs, path::PathBuf};
+
+// A project without dependencies, so a scan needs no network
+fn project(name: &str) -> PathBuf {
+  let root = scratch_dir(name);
+  write(
+    &root.join("project/package.json"),
+    r#"{"name":"app","version":"1.0.0","dependencies":{}}"#,
+  );
+  root
+}
+
+// Each list option splits its COLLECT_LIC_ value the way its flag splits a value: on its own delimiter where it has
+// one, on `;` otherwise
+#[test]
+fn env_lists_split_on_their_delimiter() {
+  let root = project("env-lists");
+  let info = run_info(
+    &root,
+    &["project"],
+    &[
+      ("COLLECT_LIC_SKIP", "^left-pad$ ^is-odd$"),
+      ("COLLECT_LIC_EXCLUDE_LICENSES", "MIT,ISC"),
+      ("COLLECT_LIC_FILTER", "npm:left-*;npm:is-*"),
+      ("COLLECT_LIC_SHEET", "Web=web/**;Api=api/**"),
+    ],
+  );
+  let env = |value: &str| (value.to_owned(), "env".to_owned());
+  assert_eq!(setting(&info, "skip"), env("^left-pad$ ^is-odd$"));
+  assert_eq!(setting(&info, "exclude_licenses"), env("MIT ISC"));
+  assert_eq!(setting(&info, "filter"), env("npm:left-* npm:is-*"));
+  assert_eq!(setting(&info, "sheet"), env("Web=web/** Api=api/**"));
+
+  // Every part is validated on its own, so an error names the bad part and not the whole value
+  let skip = depsfetch(&root, &["project"], &[("COLLECT_LIC_SKIP", "( [")]);
+  assert!(!skip.status.success());
+  let stderr = String::from_utf8_lossy(&skip.stderr);
+  assert!(stderr.contains("Failed to compile 2 regex pattern(s)"), "{}", stderr);
+  let sheet = depsfetch(&root, &["project"], &[("COLLECT_LIC_SHEET", "Web=web/**;bad")]);
+  assert!(!sheet.status.success());
+  let stderr = String::from_utf8_lossy(&sheet.stderr);
+  assert!(stderr.contains("--sheet `bad` is not NAME=GLOB"), "{}", stderr);
+  let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn flags_override_env_and_sources_are_recorded() {
+  let root = project("env-sources");
+  let envs = [
+    ("COLLECT_LIC_SKIP", "^left-pad$"),
+    ("COLLECT_LIC_VERBOSE", "true"),
+    ("COLLECT_LIC_REDACT_KEY", "env-secret-key"),
+  ];
+  let info = run_info(&root, &["project", "--skip", "^is-odd$"], &envs);
+  assert_eq!(setting(&info, "directory"), ("project".to_owned(), "flag".to_owned()));
+  assert_eq!(setting(&info, "skip"), ("^is-odd$".to_owned(), "flag".to_owned()));
+  assert_eq!(setting(&info, "verbose"), ("true".to_owned(), "env".to_owned()));
+  assert_eq!(setting(&info, "no_sort"), ("false".to_owned(), "default".to_owned()));
+  // A secret from the environment is recorded as set, not with its value
+  assert_eq!(setting(&info, "redact_key"), ("***".to_owned(), "env".to_owned()));
+  assert!(info.iter().all(|(_, value, _)| !value.contains("env-secret-key")));
+  let _ = fs::remove_dir_all(&root);
+}
@@ -0,0 +1,157 @@
+use anyhow::anyhow;
+use depsfetch::{
+  diagnostics::Diagnostics,
+  http::{BackendFuture, HttpResponse, MetadataBackend, MetadataRequest},
+  report::{ReportGenerator, ResolutionEvent, TargetPlatform, WriterMessage},
+  types::{DepScope, DepsEntry, Ecosystem},
+};
+use reqwest::StatusCode;
+use std::{
+  collections::{BTreeSet, HashMap},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{self, Receiver},
+    Arc,
+  },
+  time::Duration,
+};
+use tokio::sync::Semaphore;
+
+// npm: left-pad resolves, fsevents only installs on macOS, gone doesn't exist, and flaky's connection fails until
+// `up` is set
+#[derive(Default)]
+struct Registry {
+  up: AtomicBool,
+}
+
+impl MetadataBackend for Registry {
+  fn fetch<'a>(&'a self, request: MetadataRequest<'a>) -> BackendFuture<'a> {
+    let ok = |body: &str| Ok(HttpResponse::new(StatusCode::OK, request.url, body.to_owned()));
+    let response = match request.url {
+      url if url.ends_with("/left-pad/1.3.0") => ok(r#"{"license": "WTFPL"}"#),
+      url if url.ends_with("/fsevents/2.3.3") => ok(r#"{"license": "MIT", "os": ["darwin"]}"#),
+      url if url.contains("/flaky") => match self.up.load(Ordering::Relaxed) {
+        true => ok(r#"{"license": "ISC"}"#),
+        false => Err(anyhow!("connection reset by peer")),
+      },
+      _ => Ok(HttpResponse::new(StatusCode::NOT_FOUND, request.url, "")),
+    };
+    Box::pin(async move { response })
+  }
+}
+
+fn deps(specs: &[&str]) -> HashMap<String, DepsEntry> {
+  specs
+    .iter()
+    .map(|spec| {
+      let (name, version) = spec.split_once('@').unwrap();
+      let entry = DepsEntry {
+        name: name.to_owned(),
+        version: version.to_owned(),
+        scope: DepScope::Runtime,
+        sources: BTreeSet::new(),
+      };
+      (name.to_owned(), entry)
+    })
+    .collect()
+}
+
+// Package events as (kind, name), run notes left out
+fn steps(events: &Receiver<ResolutionEvent>) -> Vec<(&'static str, String)> {
+  events
+    .try_iter()
+    .filter_map(|event| match event {
+      ResolutionEvent::Queued { name, .. } => Some(("queued", name)),
+      ResolutionEvent::Fetching { name, .. } => Some(("fetching", name)),
+      ResolutionEvent::Resolved { name, .. } => Some(("resolved", name)),
+      ResolutionEvent::Excluded { name, .. } => Some(("excluded", name)),
+      ResolutionEvent::Failed { name, .. } => Some(("failed", name)),
+      ResolutionEvent::Note { .. } => None,
+    })
+    .collect()
+}
+
+fn of(steps: &[(&'static str, String)], name: &str) -> Vec<&'static str> {
+  steps
+    .iter()
+    .filter(|(_, package)| package == name)
+    .map(|(kind, _)| *kind)
+    .collect()
+}
+
+// Names of the rows the writer got
+fn rows(writer: &Receiver<WriterMessage>) -> Vec<String> {
+  writer
+    .try_iter()
+    .filter_map(|message| match message {
+      WriterMessage::Row(resolved) => Some(resolved.row.name),
+      _ => None,
+    })
+    .collect()
+}
+
+fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+  tokio::runtime::Runtime::new().unwrap().block_on(future)
+}
+
+// Packages resolving side by side still each go queued -> fetching -> their outcome, and every package is queued
+// before the first one is fetched
+#[test]
+fn every_package_is_queued_fetched_then_settled() {
+  let registry = Registry::default();
+  let (writer, written) = mpsc::channel();
+  let (events, received) = mpsc::channel();
+  let mut generator = ReportGenerator::new(&registry, writer, Arc::new(Diagnostics::quiet()))
+    .with_target_platform(TargetPlatform::parse("linux-x64").unwrap(), true)
+    .with_permits(Arc::new(Semaphore::new(4)))
+    .with_events(events, Arc::default());
+  let specs = ["left-pad@1.3.0", "fsevents@2.3.3", "gone@1.0.0", "flaky@1.0.0"];
+  block_on(generator.generate_report(Ecosystem::Npm, &["Web"], deps(&specs))).unwrap();
+
+  let steps = steps(&received);
+  assert_eq!(steps.len(), 12, "{:?}", steps);
+  assert_eq!(of(&steps, "left-pad"), ["queued", "fetching", "resolved"]);
+  assert_eq!(of(&steps, "fsevents"), ["queued", "fetching", "excluded"]);
+  assert_eq!(of(&steps, "gone"), ["queued", "fetching", "failed"]);
+  assert_eq!(of(&steps, "flaky"), ["queued", "fetching", "failed"]);
+  let last_queued = steps.iter().rposition(|(kind, _)| *kind == "queued").unwrap();
+  let first_fetching = steps.iter().position(|(kind, _)| *kind == "fetching").unwrap();
+  assert!(last_queued < first_fetching, "{:?}", steps);
+  // Only a resolved package reaches the writer
+  assert_eq!(rows(&written), ["left-pad"]);
+}
+
+// The final retry fetches a failed package again and settles it a second time
+#[test]
+fn final_retry_settles_a_failed_package_again() {
+  let registry = Registry::default();
+  let (writer, written) = mpsc::channel();
+  let (events, received) = mpsc::channel();
+  let mut generator = ReportGenerator::new(&registry, writer, Arc::new(Diagnostics::quiet()))
+    .with_final_retry()
+    .with_events(events, Arc::default());
+  block_on(generator.generate_report(Ecosystem::Npm, &["Web"], deps(&["flaky@1.0.0"]))).unwrap();
+  assert_eq!(of(&steps(&received), "flaky"), ["queued", "fetching", "failed"]);
+
+  registry.up.store(true, Ordering::Relaxed);
+  let rescued = block_on(generator.retry_failed(Duration::from_secs(10))).unwrap();
+  assert_eq!(rescued, (1, 1));
+  assert_eq!(of(&steps(&received), "flaky"), ["fetching", "resolved"]);
+  assert_eq!(rows(&written), ["flaky"]);
+}
+
+// Once cancelled, packages are still queued but none is fetched and nothing reaches the writer
+#[test]
+fn cancelled_run_fetches_nothing() {
+  let registry = Registry::default();
+  let (writer, written) = mpsc::channel();
+  let (events, received) = mpsc::channel();
+  let cancel = Arc::new(AtomicBool::new(true));
+  let mut generator =
+    ReportGenerator::new(&registry, writer, Arc::new(Diagnostics::quiet())).with_events(events, Arc::clone(&cancel));
+  let result = block_on(generator.generate_report(Ecosystem::Npm, &["Web"], deps(&["left-pad@1.3.0"])));
+
+  assert!(result.unwrap_err().to_string().contains("cancelled"));
+  assert_eq!(steps(&received), [("queued", "left-pad".to_owned())]);
+  assert!(rows(&written).is_empty());
+}
@@ -0,0 +1,187 @@
+mod common;
+
+use common::{depsfetch, report_row, scratch_dir, write, MockServer, Response};
+use depsfetch::{
+  deps::filter::PackageFilter,
+  types::{DepScope, DepsEntry, Ecosystem},
+};
+use serde_json::{json, Value};
+use std::{
+  collections::{BTreeSet, HashMap},
+  fs,
+};
+
+fn filter(filters: &[&str]) -> PackageFilter {
+  let filters = filters.iter().map(|filter| filter.to_string()).collect();
+  PackageFilter::new(&Some(filters)).unwrap().unwrap()
+}
+
+fn entry(name: &str) -> (String, DepsEntry) {
+  let dep = DepsEntry {
+    name: name.to_owned(),
+    version: "1.0.0".to_owned(),
+    scope: DepScope::Runtime,
+    sources: BTreeSet::new(),
+  };
+  (format!("{}@1.0.0", name), dep)
+}
+
+#[test]
+fn names_are_kept_or_dropped_per_pattern() {
+  let cases = [
+    // Plain globs apply to every ecosystem
+    (&["@aws-sdk/*"][..], Ecosystem::Npm, "@aws-sdk/client-s3", true),
+    (&["@aws-sdk/*"], Ecosystem::Npm, "@aws-sdkx/client-s3", false),
+    (&["@aws-sdk/*"], Ecosystem::Npm, "aws-sdk", false),
+    (&["tokio-*"], Ecosystem::Cargo, "tokio-util", true),
+    (&["tokio-*"], Ecosystem::Cargo, "tokio", false),
+    // A prefix keeps the pattern to its ecosystem
+    (&["go:golang.org/x/*"], Ecosystem::Go, "golang.org/x/text", true),
+    (&["go:golang.org/x/*"], Ecosystem::Npm, "golang.org/x/text", false),
+    (&["go:golang.org/x/*"], Ecosystem::Go, "github.com/golang/x/text", false),
+    (&["maven:org.slf4j:*"], Ecosystem::Maven, "org.slf4j:slf4j-api", true),
+    (
+      &["maven:org.slf4j:*"],
+      Ecosystem::Maven,
+      "ch.qos.logback:logback-core",
+      false,
+    ),
+    // Between slashes it's a regex, unanchored unless anchored
+    (&["npm:/^@types\\//"], Ecosystem::Npm, "@types/node", true),
+    (&["npm:/^@types\\//"], Ecosystem::Npm, "not-@types/node", false),
+    (&["npm:/^@types\\//"], Ecosystem::Go, "@types/node", false),
+    (&["/sdk/"], Ecosystem::Pypi, "azure-sdk-core", true),
+    // Any one pattern is enough
+    (&["left-*", "go:golang.org/x/*"], Ecosystem::Npm, "left-pad", true),
+    (
+      &["left-*", "go:golang.org/x/*"],
+      Ecosystem::Go,
+      "golang.org/x/net",
+      true,
+    ),
+    (&["left-*", "go:golang.org/x/*"], Ecosystem::Npm, "right-pad", false),
+  ];
+  for (filters, ecosystem, name, kept) in cases {
+    assert_eq!(
+      filter(filters).matches(ecosystem, name),
+      kept,
+      "{:?} on {} {}",
+      filters,
+      ecosystem,
+      name
+    );
+  }
+}
+
+#[test]
+fn no_filter_and_bad_patterns() {
+  assert!(PackageFilter::new(&None).unwrap().is_none());
+  assert!(PackageFilter::new(&Some(Vec::new())).unwrap().is_none());
+
+  let error = |filter: &str| match PackageFilter::new(&Some(vec![filter.to_owned()])) {
+    Ok(_) => panic!("{} was accepted", filter),
+    Err(err) => err.to_string(),
+  };
+  assert!(
+    error("npm:/(unclosed/").starts_with("--filter `npm:/(unclosed/`: invalid regex: "),
+    "{}",
+    error("npm:/(unclosed/")
+  );
+  assert!(
+    error("go:golang.org/[x").starts_with("--filter `go:golang.org/[x`: invalid glob: "),
+    "{}",
+    error("go:golang.org/[x")
+  );
+}
+
+// Collected dependencies and rows that skip resolution go through the same patterns
+#[test]
+fn retain_keeps_only_matching_dependencies_and_rows() {
+  let filter = filter(&["@aws-sdk/*", "go:golang.org/x/*"]);
+  assert_eq!(filter.filters(), ["@aws-sdk/*", "go:golang.org/x/*"]);
+
+  let mut deps = ["@aws-sdk/client-s3", "@aws-sdk/util", "lodash", "golang.org/x/text"]
+    .into_iter()
+    .map(entry)
+    .collect::<HashMap<_, _>>();
+  filter.retain(Ecosystem::Npm, &mut deps);
+  let mut kept = deps.into_values().map(|dep| dep.name).collect::<Vec<_>>();
+  kept.sort();
+  assert_eq!(kept, ["@aws-sdk/client-s3", "@aws-sdk/util"]);
+
+  let mut rows = vec![
+    report_row(Ecosystem::Npm, "@aws-sdk/client-s3", "3.0.0", Some("Apache-2.0")),
+    report_row(Ecosystem::Npm, "lodash", "4.17.21", Some("MIT")),
+    report_row(Ecosystem::Go, "golang.org/x/text", "v0.14.0", Some("BSD-3-Clause")),
+    report_row(Ecosystem::Go, "github.com/pkg/errors", "v0.9.1", Some("BSD-2-Clause")),
+  ];
+  filter.retain_rows(&mut rows);
+  let kept = rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>();
+  assert_eq!(kept, ["@aws-sdk/client-s3", "golang.org/x/text"]);
+}
+
+// A scan with --filter reports only the matching packages, minus what --skip takes out
+#[test]
+fn scan_reports_only_filtered_packages() {
+  let server = MockServer::start(|request| {
+    let name = request
+      .path
+      .trim_start_matches('/')
+      .split('/')
+      .next()
+      .unwrap_or_default()
+      .to_owned();
+    Response::json(json!({"name": name, "version": "1.0.0", "license": "MIT"}).to_string())
+  });
+  let base = scratch_dir("filter-scan");
+  write(
+    &base.join("app/package.json"),
+    r#"{"dependencies": {"left-pad": "1.0.0", "is-odd": "1.0.0", "is-even": "1.0.0"}}"#,
+  );
+  let args = [
+    "app",
+    "--format",
+    "json",
+    "--jsonl",
+    "rows.jsonl",
+    "--npm-registry",
+    &server.base,
+    "--final-retry-window",
+    "0",
+    "--filter",
+    "is-*",
+    "--skip",
+    "^is-even$",
+  ];
+  let output = depsfetch(&base, &args, &[]);
+  let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+  assert!(
+    output.status.success(),
+    "{}{}",
+    stdout,
+    String::from_utf8_lossy(&output.stderr)
+  );
+
+  let rows = fs::read_to_string(base.join("rows.jsonl"))
+    .unwrap()
+    .lines()
+    .map(|line| serde_json::from_str::<Value>(line).unwrap())
+    .collect::<Vec<_>>();
+  assert_eq!(rows.len(), 1, "{:?}", rows);
+  assert_eq!(rows[0]["name"], "is-odd");
+  assert_eq!(rows[0]["filters"], json!(["is-*"]));
+  // Only what's left after --skip is counted, and nothing filtered out is asked for
+  assert!(
+    stdout.contains("--filter kept 1 of 2 npm dependencies, filtered out 1"),
+    "{}",
+    stdout
+  );
+  let requested = server
+    .requests()
+    .into_iter()
+    .map(|request| request.path)
+    .filter(|path| path.starts_with("/left-pad") || path.starts_with("/is-even"))
+    .collect::<Vec<_>>();
+  assert!(requested.is_empty(), "{:?}", requested);
+  let _ = fs::remove_dir_all(&base);
+}
@@ -0,0 +1,12 @@
+module example.com/svc
+
+go 1.21
+
+require (
+	google.golang.org/grpc v1.62.0
+	google.golang.org/protobuf v1.33.0
+)
+
+replace google.golang.org/grpc => ./third_party/grpc
+
+replace google.golang.org/protobuf => ./third_party/protobuf
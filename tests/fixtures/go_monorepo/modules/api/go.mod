@@ -0,0 +1,3 @@
+module github.com/org/mono/modules/api
+
+go 1.22
@@ -0,0 +1,3 @@
+module github.com/org/mono/modules/cli
+
+go 1.22
@@ -0,0 +1,9 @@
+module example.com/svc
+
+go 1.22
+
+require (
+	example.com/api v0.0.0-00010101000000-000000000000
+	github.com/upstream/yaml v1.2.0
+	golang.org/x/text v0.14.0
+)
@@ -0,0 +1,5 @@
+module example.com/tools
+
+go 1.22
+
+require example.com/api v1.0.0
@@ -0,0 +1,10 @@
+module example.com/svc
+
+go 1.21
+
+require (
+	golang.org/x/text v0.14.0
+	github.com/pkg/errors v0.9.1
+	github.com/google/uuid v1.6.0
+	gopkg.in/yaml.v3 v3.0.1
+)
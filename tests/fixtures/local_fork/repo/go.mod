@@ -0,0 +1,12 @@
+module example.com/app
+
+go 1.21
+
+require (
+	github.com/upstream/yaml v1.2.0
+	example.com/outside v0.3.0
+)
+
+replace github.com/upstream/yaml => ./third_party/yaml
+
+replace example.com/outside => ../outside/lib
@@ -0,0 +1,3 @@
+module github.com/upstream/yaml
+
+go 1.21
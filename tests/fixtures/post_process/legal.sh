@@ -0,0 +1,29 @@
+#!/bin/sh
+# Stands in for a legal team's --post-process script in tests/post_process.rs; FIXTURE_MODE picks how it behaves
+case "$FIXTURE_MODE" in
+  wrong-schema)
+    cat >/dev/null
+    echo '{"schema_version":99}'
+    exit 0
+    ;;
+  fail)
+    cat >/dev/null
+    echo 'contract database unreachable' >&2
+    exit 3
+    ;;
+  garbage)
+    read -r handshake
+    echo "$handshake"
+    echo 'not a row'
+    exit 0
+    ;;
+  hang)
+    exec sleep 30
+    ;;
+esac
+
+read -r handshake
+echo "$handshake"
+# Internal license tags become SPDX ids, `drop-me` goes and a vendored SDK joins the Web sheet
+sed -e '/"name":"drop-me"/d' -e 's/"license":"LicenseRef-Internal-A"/"license":"MIT"/'
+echo '{"sheet":"Web","row":{"ecosystem":"npm","name":"vendored-sdk","version":"2.0.0","homepage":"https://example.com/sdk","license":"Apache-2.0"}}'
@@ -0,0 +1,3 @@
+#!/usr/bin/env node
+/* eslint-disable */
+// A stand-in for the generated Plug'n'Play loader, only its presence matters
@@ -0,0 +1,225 @@
+use depsfetch::{
+  http::{BackendFuture, HttpResponse, MetadataBackend, MetadataRequest},
+  report::go_license_name,
+  resolve::{resolve, ResolveOptions},
+  types::{DepScope, DepsEntry, Ecosystem, ReportRow},
+};
+use reqwest::StatusCode;
+use std::{
+  collections::{BTreeSet, HashMap},
+  sync::Mutex,
+};
+
+const LICENSE_TAB: &str = r##"<section><div id="#lic-0">MIT</div><div id="#lic-1">Apache-2.0</div></section>"##;
+
+// Serves the given URLs and pkg.go.dev's licenses tab for every module, 404 for the rest. Every URL asked for is
+// kept.
+struct Hosts {
+  served: HashMap<String, String>,
+  asked: Mutex<Vec<String>>,
+}
+
+impl Hosts {
+  fn new(served: &[(&str, &str)]) -> Self {
+    Self {
+      served: served
+        .iter()
+        .map(|(url, body)| (url.to_string(), body.to_string()))
+        .collect(),
+      asked: Mutex::new(Vec::new()),
+    }
+  }
+
+  fn asked(&self) -> Vec<String> {
+    self.asked.lock().unwrap().clone()
+  }
+}
+
+impl MetadataBackend for Hosts {
+  fn fetch<'a>(&'a self, request: MetadataRequest<'a>) -> BackendFuture<'a> {
+    let url = request.url;
+    self.asked.lock().unwrap().push(url.to_owned());
+    let body = match self.served.get(url) {
+      Some(body) => Some(body.clone()),
+      None if url.starts_with("https://pkg.go.dev/") => Some(LICENSE_TAB.to_owned()),
+      None => None,
+    };
+    let mut response = match body {
+      Some(body) => HttpResponse::new(StatusCode::OK, url, body),
+      None => HttpResponse::new(StatusCode::NOT_FOUND, url, ""),
+    };
+    response.content_type = Some(
+      match url.starts_with("https://api.github.com/") {
+        true => "application/json",
+        false => "text/plain",
+      }
+      .to_owned(),
+    );
+    Box::pin(async move { Ok(response) })
+  }
+}
+
+fn resolve_module(hosts: &Hosts, module: &str, version: &str) -> ReportRow {
+  let entry = DepsEntry {
+    name: module.to_owned(),
+    version: version.to_owned(),
+    scope: DepScope::Runtime,
+    sources: BTreeSet::new(),
+  };
+  let runtime = tokio::runtime::Runtime::new().unwrap();
+  let mut resolved = runtime.block_on(resolve([entry], hosts, ResolveOptions::new(Ecosystem::Go)));
+  resolved.remove(0).row.unwrap()
+}
+
+#[test]
+fn license_name_is_the_first_on_the_tab() {
+  let cases = [
+    (LICENSE_TAB, Some("MIT")),
+    (
+      r##"<div id="#lic-0">BSD-3-Clause, MIT</div>"##,
+      Some("BSD-3-Clause, MIT"),
+    ),
+    (
+      r##"<p>License</p>\n<div id="#lic-0">Apache-2.0</div>"##,
+      Some("Apache-2.0"),
+    ),
+    // Not indexed, or a page without the tab
+    ("<h1>Not Found</h1>", None),
+    (r#"<div id="lic-0">MIT</div>"#, None),
+    ("", None),
+  ];
+  for (page, license) in cases {
+    assert_eq!(go_license_name(page).as_deref(), license, "{}", page);
+  }
+}
+
+// Where the license file is looked for, by how the module sits in its repository: (module, version, the license file
+// served and linked, the ref it was found at)
+#[test]
+fn license_file_is_found_at_the_published_ref() {
+  let cases = [
+    // The repository root, at the version's tag
+    (
+      "github.com/o/lib",
+      "v1.2.0",
+      "https://github.com/o/lib/blob/v1.2.0/LICENSE",
+      Some("v1.2.0"),
+    ),
+    // A major version suffix is a branch line, not a directory
+    (
+      "github.com/o/lib/v2",
+      "v2.1.0",
+      "https://github.com/o/lib/blob/v2.1.0/LICENSE",
+      Some("v2.1.0"),
+    ),
+    // A module nested in a directory is tagged with the directory
+    (
+      "github.com/o/mono/tools/gen",
+      "v0.4.0",
+      "https://github.com/o/mono/blob/tools/gen/v0.4.0/tools/gen/LICENSE",
+      Some("tools/gen/v0.4.0"),
+    ),
+    // Pre-modules major versions are tagged without the suffix
+    (
+      "github.com/o/old",
+      "v3.0.0+incompatible",
+      "https://github.com/o/old/blob/v3.0.0/LICENSE.md",
+      Some("v3.0.0"),
+    ),
+    // A pseudo-version names the commit
+    (
+      "github.com/o/lib",
+      "v0.0.0-20200101120000-abcdef123456",
+      "https://github.com/o/lib/blob/abcdef123456/LICENSE",
+      Some("abcdef123456"),
+    ),
+    // The module cache spells this github.com/!burnt!sushi/toml, the repository keeps its case
+    (
+      "github.com/BurntSushi/toml",
+      "v1.3.2",
+      "https://github.com/BurntSushi/toml/blob/v1.3.2/COPYING",
+      None,
+    ),
+  ];
+  for (module, version, license_file, git_ref) in cases {
+    let hosts = Hosts::new(&[
+      (license_file, "license text"),
+      (
+        "https://api.github.com/repos/BurntSushi/toml",
+        r#"{"default_branch": "master"}"#,
+      ),
+      ("https://github.com/BurntSushi/toml/blob/master/LICENSE", "license text"),
+    ]);
+    let row = resolve_module(&hosts, module, version);
+
+    let expected = match git_ref {
+      Some(_) => license_file,
+      // COPYING isn't a license file name, the root LICENSE on the default branch is
+      None => "https://github.com/BurntSushi/toml/blob/master/LICENSE",
+    };
+    assert_eq!(row.license_url.as_deref(), Some(expected), "{}@{}", module, version);
+    assert_eq!(row.license.as_deref(), Some("MIT"));
+    let tab = format!("license page: https://pkg.go.dev/{}?tab=licenses", module);
+    assert!(row.provenance.contains(&tab), "{:?}", row.provenance);
+    match git_ref {
+      Some(git_ref) => assert_eq!(row.provenance[0], format!("license ref: {}", git_ref)),
+      None => assert!(
+        !row.provenance.iter().any(|line| line.starts_with("license ref:")),
+        "{:?}",
+        row.provenance
+      ),
+    }
+    assert!(
+      !hosts.asked().iter().any(|url| url.contains('!')),
+      "{:?}",
+      hosts.asked()
+    );
+  }
+}
+
+// A tag that was never pushed leaves the default branch
+#[test]
+fn missing_tag_falls_back_to_the_default_branch() {
+  let hosts = Hosts::new(&[
+    ("https://api.github.com/repos/o/lib", r#"{"default_branch": "main"}"#),
+    ("https://github.com/o/lib/blob/main/LICENSE", "license text"),
+  ]);
+  let row = resolve_module(&hosts, "github.com/o/lib", "v1.2.0");
+
+  assert_eq!(
+    row.license_url.as_deref(),
+    Some("https://github.com/o/lib/blob/main/LICENSE")
+  );
+  assert_eq!(
+    row.provenance,
+    [
+      "license file: repository root",
+      "license page: https://pkg.go.dev/github.com/o/lib?tab=licenses",
+    ]
+  );
+}
+
+// Vanity paths are looked up through their go-get page; a repository without blob URLs keeps the licenses tab
+#[test]
+fn vanity_imports_are_resolved_to_their_repository() {
+  let zap = r#"<meta name="go-import" content="go.uber.org/zap git https://github.com/uber-go/zap">"#;
+  let hosts = Hosts::new(&[
+    ("https://go.uber.org/zap?go-get=1", zap),
+    ("https://github.com/uber-go/zap/blob/v1.27.0/LICENSE", "license text"),
+  ]);
+  let row = resolve_module(&hosts, "go.uber.org/zap", "v1.27.0");
+  assert_eq!(
+    row.license_url.as_deref(),
+    Some("https://github.com/uber-go/zap/blob/v1.27.0/LICENSE")
+  );
+  assert_eq!(row.homepage, "https://pkg.go.dev/go.uber.org/zap");
+
+  let elsewhere = r#"<meta name="go-import" content="example.org/lib git https://git.example.org/lib">"#;
+  let hosts = Hosts::new(&[("https://example.org/lib?go-get=1", elsewhere)]);
+  let row = resolve_module(&hosts, "example.org/lib", "v1.0.0");
+  assert_eq!(
+    row.license_url.as_deref(),
+    Some("https://pkg.go.dev/example.org/lib?tab=licenses")
+  );
+  assert!(row.provenance.is_empty(), "{:?}", row.provenance);
+}
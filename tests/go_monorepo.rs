@@ -0,0 +1,113 @@
+use depsfetch::{
+  http::{BackendFuture, HttpResponse, MetadataBackend, MetadataRequest},
+  resolve::{resolve, ResolveOptions},
+  types::{DepScope, DepsEntry, Ecosystem, ReportRow},
+};
+use reqwest::StatusCode;
+use std::{collections::BTreeSet, fs, path::PathBuf, sync::Mutex};
+
+// tests/fixtures/go_monorepo: github.com/org/mono, MIT at the root and Apache-2.0 in modules/api, with
+// modules/cli going without a license of its own. Every module is tagged <directory>/v1.2.0.
+fn fixture(path: &str) -> PathBuf {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    .join("tests/fixtures/go_monorepo")
+    .join(path)
+}
+
+// GitHub serving the fixture tree at every module's tag, and pkg.go.dev attributing the root license to all of
+// them. Every file asked for is kept.
+#[derive(Default)]
+struct Mono(Mutex<Vec<String>>);
+
+impl Mono {
+  fn probed(&self) -> Vec<String> {
+    self.0.lock().unwrap().clone()
+  }
+}
+
+impl MetadataBackend for Mono {
+  fn fetch<'a>(&'a self, request: MetadataRequest<'a>) -> BackendFuture<'a> {
+    let url = request.url;
+    let tagged = url
+      .strip_prefix("https://github.com/org/mono/blob/modules/")
+      .and_then(|rest| rest.split_once("/v1.2.0/"));
+    let body = match tagged {
+      Some((module, path)) => {
+        self.0.lock().unwrap().push(format!("{}: {}", module, path));
+        fs::read_to_string(fixture(path)).ok()
+      },
+      None if url.starts_with("https://pkg.go.dev/") => Some(r##"<div id="#lic-0">MIT</div>"##.to_owned()),
+      None => None,
+    };
+    let mut response = match body {
+      Some(body) => HttpResponse::new(StatusCode::OK, url, body),
+      None => HttpResponse::new(StatusCode::NOT_FOUND, url, ""),
+    };
+    response.content_type = Some("text/plain".to_owned());
+    Box::pin(async move { Ok(response) })
+  }
+}
+
+fn resolve_module(backend: &Mono, module: &str) -> ReportRow {
+  let entry = DepsEntry {
+    name: module.to_owned(),
+    version: "v1.2.0".to_owned(),
+    scope: DepScope::Runtime,
+    sources: BTreeSet::new(),
+  };
+  let runtime = tokio::runtime::Runtime::new().unwrap();
+  let mut resolved = runtime.block_on(resolve([entry], backend, ResolveOptions::new(Ecosystem::Go)));
+  resolved.remove(0).row.unwrap()
+}
+
+// The module's own LICENSE is the link, not the root's that pkg.go.dev shows
+#[test]
+fn submodule_license_wins_over_the_root() {
+  let backend = Mono::default();
+  let row = resolve_module(&backend, "github.com/org/mono/modules/api");
+
+  assert_eq!(
+    row.license_url.as_deref(),
+    Some("https://github.com/org/mono/blob/modules/api/v1.2.0/modules/api/LICENSE")
+  );
+  assert_eq!(
+    row.provenance,
+    [
+      "license ref: modules/api/v1.2.0",
+      "license file: module directory modules/api",
+      "license page: https://pkg.go.dev/github.com/org/mono/modules/api?tab=licenses",
+    ]
+  );
+  assert_eq!(
+    backend.probed(),
+    ["api: modules/api/LICENSE.txt", "api: modules/api/LICENSE"]
+  );
+}
+
+// Without one in its directory the module gets the root license file
+#[test]
+fn submodule_without_a_license_falls_back_to_the_root() {
+  let backend = Mono::default();
+  let row = resolve_module(&backend, "github.com/org/mono/modules/cli");
+
+  assert_eq!(
+    row.license_url.as_deref(),
+    Some("https://github.com/org/mono/blob/modules/cli/v1.2.0/LICENSE")
+  );
+  assert!(
+    row.provenance.contains(&"license file: repository root".to_owned()),
+    "{:?}",
+    row.provenance
+  );
+  assert_eq!(
+    backend.probed(),
+    [
+      "cli: modules/cli/LICENSE.txt",
+      "cli: modules/cli/LICENSE",
+      "cli: modules/cli/LICENSE.md",
+      "cli: modules/cli/license",
+      "cli: LICENSE.txt",
+      "cli: LICENSE",
+    ]
+  );
+}
@@ -0,0 +1,169 @@
+use depsfetch::{
+  config::ManifestConfig,
+  deps::{
+    go_deps::GoParser,
+    go_work::{parse_go_work, GoWork, WorkReplace},
+    manifests::ManifestFilter,
+    patterns::PatternSet,
+  },
+  diagnostics::{Category, Diagnostics},
+};
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  path::PathBuf,
+  sync::Arc,
+};
+
+// tests/fixtures/go_work: a go.work using svc and api, which svc requires, and ../elsewhere outside the tree, and
+// replacing github.com/upstream/yaml by third_party/yaml. tools requires api too, without being in the workspace.
+fn parser(manifests: BTreeMap<String, ManifestConfig>) -> (GoParser, Arc<Diagnostics>, Arc<ManifestFilter>) {
+  let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/go_work");
+  let diagnostics = Arc::new(Diagnostics::quiet());
+  let manifests = Arc::new(ManifestFilter::new(&manifests, &None).unwrap());
+  let parser = GoParser::new(
+    root.to_str().unwrap(),
+    Arc::new(PatternSet::compile("--exclude", &None).unwrap()),
+    manifests.clone(),
+    diagnostics.clone(),
+  )
+  .unwrap();
+  (parser, diagnostics, manifests)
+}
+
+fn sources(parser: &GoParser) -> BTreeMap<String, (String, BTreeSet<String>)> {
+  let deps = tokio::runtime::Runtime::new()
+    .unwrap()
+    .block_on(parser.parse())
+    .unwrap();
+  deps
+    .into_values()
+    .map(|dep| (dep.name, (dep.version, dep.sources)))
+    .collect()
+}
+
+fn set(items: &[&str]) -> BTreeSet<String> {
+  items.iter().map(|item| item.to_string()).collect()
+}
+
+#[test]
+fn go_work_directives_are_read() {
+  let content = r#"
+go 1.22
+toolchain go1.22.1
+
+use ./svc // the service
+use (
+  ./api
+  "./with space"
+)
+
+replace (
+  example.com/a v1.0.0 => ../a
+  example.com/b => example.com/c v1.2.0
+)
+replace "example.com/d" => /abs/d
+"#;
+  let replace = |module: &str, version: Option<&str>, target: &str| WorkReplace {
+    module: module.to_owned(),
+    version: version.map(str::to_owned),
+    target: target.to_owned(),
+  };
+  let work = parse_go_work(content).unwrap();
+  assert_eq!(
+    work,
+    GoWork {
+      uses: vec!["./svc".to_owned(), "./api".to_owned(), "./with space".to_owned()],
+      replaces: vec![
+        replace("example.com/a", Some("v1.0.0"), "../a"),
+        replace("example.com/b", None, "example.com/c"),
+        replace("example.com/d", None, "/abs/d"),
+      ],
+    }
+  );
+  let local = work.replaces.iter().map(WorkReplace::is_local).collect::<Vec<_>>();
+  assert_eq!(local, [true, false, true]);
+
+  for (content, error) in [
+    ("use (\n  ./svc\n", "unclosed `use (` block"),
+    ("use ./a ./b\n", "line 1: expected `use DIR`"),
+    (
+      "go 1.22\nreplace example.com/a ../a\n",
+      "line 2: expected `replace MODULE => TARGET`",
+    ),
+  ] {
+    let err = parse_go_work(content).unwrap_err().to_string();
+    assert!(err.starts_with(error), "{}", err);
+  }
+}
+
+// Requires on a workspace member are first-party for the other members only, the fork replaced by go.work is read
+// from its directory and the member outside the tree is refused
+#[test]
+fn workspace_members_are_not_fetched() {
+  let (parser, diagnostics, _) = parser(BTreeMap::new());
+  let deps = sources(&parser);
+  assert_eq!(
+    deps,
+    BTreeMap::from([
+      (
+        "example.com/api".to_owned(),
+        ("v1.0.0".to_owned(), set(&["tools/go.mod"]))
+      ),
+      (
+        "golang.org/x/text".to_owned(),
+        ("v0.14.0".to_owned(), set(&["api/go.mod", "svc/go.mod"]))
+      ),
+    ])
+  );
+
+  let rows = parser.local_replacements().unwrap();
+  assert_eq!(rows.len(), 1, "{:?}", rows);
+  let (source, row) = &rows[0];
+  assert_eq!(source, "go.work");
+  assert_eq!(row.name, "github.com/upstream/yaml");
+  // The version svc requires
+  assert_eq!(row.version, "v1.2.0");
+  assert_eq!(row.license.as_deref(), Some("Apache-2.0"));
+  assert_eq!(
+    row.provenance,
+    [
+      "forked locally: replaced by third_party/yaml",
+      "license file: third_party/yaml/LICENSE",
+    ]
+  );
+
+  assert_eq!(
+    diagnostics.warnings(),
+    [(
+      Category::RefusedPath,
+      "go.work uses ../elsewhere, which leads out of the scanned root; requires on that module are resolved like \
+       any other"
+        .to_owned()
+    )]
+  );
+}
+
+// With go.work switched off each module stands alone
+#[test]
+fn disabled_go_work_leaves_modules_standalone() {
+  let disabled = ManifestConfig {
+    enabled: Some(false),
+    exclude: None,
+  };
+  let (parser, diagnostics, manifests) = parser(BTreeMap::from([("go.work".to_owned(), disabled)]));
+  let deps = sources(&parser);
+  assert_eq!(
+    deps.keys().collect::<Vec<_>>(),
+    ["example.com/api", "github.com/upstream/yaml", "golang.org/x/text"]
+  );
+  assert_eq!(deps["example.com/api"].1, set(&["svc/go.mod", "tools/go.mod"]));
+  assert!(parser.local_replacements().unwrap().is_empty());
+  assert!(diagnostics.is_empty(), "{:?}", diagnostics.warnings());
+
+  let tally = manifests
+    .tallies()
+    .into_iter()
+    .find(|tally| tally.kind == "go.work")
+    .unwrap();
+  assert_eq!((tally.enabled, tally.found, tally.excluded), (false, 1, 1));
+}
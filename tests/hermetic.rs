@@ -0,0 +1,97 @@
+mod common;
+
+use common::scratch_dir;
+use depsfetch::{
+  cache::BranchCache,
+  credentials,
+  deps::{detect::DetectedManifests, js_deps::JsParser, manifests::ManifestFilter, patterns::PatternSet},
+  hermetic,
+  http::{BackendFuture, HttpResponse, MetadataBackend, MetadataRequest},
+  license_data::LicenseData,
+  resolve::{resolve, ResolveOptions},
+  types::Ecosystem,
+};
+use reqwest::StatusCode;
+use std::{collections::BTreeMap, env, fs, path::Path, sync::Arc};
+use walkdir::WalkDir;
+
+// Every path under `dir` with its size, what a stray write would change
+fn snapshot(dir: &Path) -> Vec<(String, u64)> {
+  let mut entries = WalkDir::new(dir)
+    .into_iter()
+    .map(|entry| entry.unwrap())
+    .map(|entry| (entry.path().display().to_string(), entry.metadata().unwrap().len()))
+    .collect::<Vec<_>>();
+  entries.sort();
+  entries
+}
+
+// The registry as a fixture: everything is MIT
+struct Registry;
+
+impl MetadataBackend for Registry {
+  fn fetch<'a>(&'a self, request: MetadataRequest<'a>) -> BackendFuture<'a> {
+    let body = r#"{"version": "1.0.0", "license": "MIT", "repository": {"url": "https://github.com/o/r.git"}}"#;
+    let response = HttpResponse::new(StatusCode::OK, request.url, body);
+    Box::pin(async move { Ok(response) })
+  }
+}
+
+// A fixture scan as a hermetic run does it, with a read-only HOME that must stay empty. Hermetic mode is for the
+// whole process, so this is the only test in the file
+#[test]
+fn hermetic_scan_leaves_home_alone() {
+  let base = scratch_dir("hermetic");
+  let (home, repo) = (base.join("home"), base.join("repo"));
+  fs::create_dir_all(&home).unwrap();
+  fs::create_dir_all(repo.join("app")).unwrap();
+  fs::write(
+    repo.join("app/package.json"),
+    r#"{"dependencies": {"left-pad": "1.0.0", "is-odd": "1.0.0"}}"#,
+  )
+  .unwrap();
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(&home, fs::Permissions::from_mode(0o555)).unwrap();
+  }
+  env::set_var("HOME", &home);
+  env::remove_var("XDG_DATA_HOME");
+  let (home_before, repo_before) = (snapshot(&home), snapshot(&repo));
+
+  hermetic::enable();
+  assert_eq!(LicenseData::default_dir(), None);
+  let license_data = LicenseData::load(LicenseData::default_dir().as_deref()).unwrap();
+  assert!(license_data.describe().starts_with("embedded"));
+  assert_eq!(credentials::load_stored(), (BTreeMap::new(), None));
+
+  let parser = JsParser::new(
+    repo.to_str().unwrap(),
+    Arc::new(PatternSet::compile("--exclude", &None).unwrap()),
+    Arc::new(PatternSet::compile("--skip", &None).unwrap()),
+    Arc::new(ManifestFilter::new(&BTreeMap::new(), &None).unwrap()),
+    Arc::default(),
+  )
+  .unwrap();
+  tokio::runtime::Runtime::new().unwrap().block_on(async {
+    let deps = parser.parse(&mut DetectedManifests::default()).await.unwrap();
+    let resolved = resolve(deps.into_values(), &Registry, ResolveOptions::new(Ecosystem::Npm)).await;
+    assert_eq!(resolved.len(), 2);
+    assert!(resolved
+      .iter()
+      .all(|dep| dep.row.as_ref().unwrap().license.as_deref() == Some("MIT")));
+  });
+  // The branch cache only lives for the run
+  let cache = BranchCache::load();
+  cache.insert("https://github.com/owner/repo", "main");
+  cache.save().unwrap();
+
+  assert_eq!(snapshot(&home), home_before);
+  assert_eq!(snapshot(&repo), repo_before);
+  // A path that slips through anyway is refused, not written
+  let err = hermetic::write(&home.join(".local/share/collect_lic_info/cache.json"), "{}").unwrap_err();
+  assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+  assert!(hermetic::read(&home.join(".netrc")).is_err());
+  assert_eq!(snapshot(&home), home_before);
+  fs::remove_dir_all(&base).unwrap();
+}
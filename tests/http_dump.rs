@@ -0,0 +1,93 @@
+mod common;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use common::{contains, depsfetch, files, scratch_dir, write, MockServer, Response};
+use std::fs;
+
+const BEARER_SECRET: &str = "sentinel-bearer-0c41d2";
+const USERINFO_SECRET: &str = "sentinel-userinfo-9e27b5";
+const QUERY_SECRET: &str = "sentinel-query-61fa08";
+const COOKIE_SECRET: &str = "sentinel-cookie-d3a7e4";
+
+// A registry reached with a bearer token and a password in its URL, that sets a cookie and redirects left-pad to a
+// URL holding a token: every one of them is used, and --debug-http writes none of them
+#[test]
+fn credentials_are_redacted_in_the_dump() {
+  let registry = MockServer::start(|request| match request.path.as_str() {
+    "/left-pad/1.3.0" => Response::status(302)
+      .with_header("location", format!("/left-pad/1.3.0?token={}&v=1", QUERY_SECRET))
+      .with_header("set-cookie", format!("session={}; Path=/", COOKIE_SECRET)),
+    path if path.starts_with("/left-pad/1.3.0?") => {
+      Response::json(r#"{"name":"left-pad","version":"1.3.0","license":"WTFPL"}"#)
+        .with_header("set-cookie", format!("session={}; Path=/", COOKIE_SECRET))
+    },
+    _ => Response::status(404),
+  });
+  let base = scratch_dir("http-dump");
+  write(
+    &base.join("app/package.json"),
+    r#"{"dependencies": {"left-pad": "1.3.0"}}"#,
+  );
+  let url = registry
+    .base
+    .replace("http://", &format!("http://depsfetch:{}@", USERINFO_SECRET));
+  let args = [
+    "app",
+    "--format",
+    "json",
+    "--npm-registry",
+    &url,
+    "--npm-auth",
+    "bearer",
+    "--final-retry-window",
+    "0",
+    "--debug-http",
+    "http-dump",
+  ];
+  let output = depsfetch(&base, &args, &[("NPM_AUTH_TOKEN", BEARER_SECRET)]);
+  assert!(
+    output.status.success(),
+    "{}{}",
+    String::from_utf8_lossy(&output.stdout),
+    String::from_utf8_lossy(&output.stderr)
+  );
+
+  // The credentials reached the registry
+  let requests = registry.requests();
+  assert_eq!(requests.len(), 2, "{:?}", requests);
+  for request in &requests {
+    assert_eq!(
+      request.headers.get("authorization").map(String::as_str),
+      Some(format!("Bearer {}", BEARER_SECRET).as_str())
+    );
+  }
+  assert!(requests[1].path.contains(QUERY_SECRET), "{:?}", requests);
+
+  let dumps = files(&base.join("http-dump"));
+  assert_eq!(dumps.len(), 1, "{:?}", dumps);
+  let dump = fs::read_to_string(&dumps[0]).unwrap();
+  // The client moves the URL's user and password into a Basic authorization header before the request is dumped
+  let basic = STANDARD.encode(format!("depsfetch:{}", USERINFO_SECRET));
+  for secret in [BEARER_SECRET, USERINFO_SECRET, &basic, QUERY_SECRET, COOKIE_SECRET] {
+    assert!(
+      !contains(dump.as_bytes(), secret),
+      "the dump leaks {}:\n{}",
+      secret,
+      dump
+    );
+  }
+  // Redacted, not left out
+  assert_eq!(dump.matches("> authorization: <redacted>\n").count(), 2, "{}", dump);
+  assert!(
+    dump.starts_with(&format!("> GET {}/left-pad/1.3.0\n", registry.base)),
+    "{}",
+    dump
+  );
+  for redacted in [
+    "< set-cookie: <redacted>\n",
+    "/left-pad/1.3.0?token=%3Credacted%3E&v=1\n",
+  ] {
+    assert!(dump.contains(redacted), "{} not in\n{}", redacted, dump);
+  }
+  let _ = fs::remove_dir_all(&base);
+}
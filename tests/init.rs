@@ -0,0 +1,102 @@
+mod common;
+
+use common::{depsfetch, scratch_dir, write};
+use depsfetch::config::{self, CONFIG_FILE};
+use std::fs;
+
+// `init` on a tree with an npm package under a scope, a Go module, and installed copies of both
+#[test]
+fn generated_config_loads_with_the_proposed_defaults() {
+  let base = scratch_dir("init-round-trip");
+  let repo = base.join("repo");
+  write(
+    &repo.join("web/package.json"),
+    r#"{"name": "@acme/web", "private": true}"#,
+  );
+  write(
+    &repo.join("web/node_modules/left-pad/package.json"),
+    r#"{"name": "left-pad", "version": "1.3.0"}"#,
+  );
+  write(&repo.join("svc/go.mod"), "module github.com/acme/svc\n\ngo 1.21\n");
+  write(&repo.join("svc/vendor/modules.txt"), "# github.com/pkg/errors v0.9.1\n");
+
+  let output = depsfetch(&base, &["init", "repo"], &[]);
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  assert!(
+    output.status.success(),
+    "{}{}",
+    stdout,
+    String::from_utf8_lossy(&output.stderr)
+  );
+  assert!(stdout.contains("Found 1 go.mod file(s)"), "{}", stdout);
+  assert!(stdout.contains("Found 1 package.json file(s)"), "{}", stdout);
+
+  let path = repo.join(CONFIG_FILE);
+  let loaded = config::load(&path.display().to_string(), None).unwrap();
+  // The installed copies are excluded, each with its reason
+  let exclude = loaded
+    .exclude
+    .unwrap()
+    .into_iter()
+    .map(|entry| {
+      let rule = entry.into_rule("--exclude");
+      (rule.pattern, rule.reason.unwrap_or_default())
+    })
+    .collect::<Vec<_>>();
+  assert_eq!(
+    exclude,
+    [
+      ("/node_modules/".to_owned(), "installed npm packages".to_owned()),
+      (
+        "/vendor/".to_owned(),
+        "vendored Go modules, gems or Composer packages".to_owned()
+      ),
+    ]
+  );
+  // Every manifest kind found is enabled, the node_modules copy isn't counted
+  let manifests = loaded.manifests.unwrap();
+  assert_eq!(manifests.keys().collect::<Vec<_>>(), ["go.mod", "package.json"]);
+  assert!(manifests.values().all(|manifest| manifest.enabled == Some(true)));
+  // The rest is only proposed in comments, so every other setting keeps its default
+  assert!(loaded.skip_scope.is_none() && loaded.skip_prefix.is_none() && loaded.exclude_private.is_none());
+  assert!(loaded.npm_registry.is_none() && loaded.npm_auth.is_none() && loaded.scope_registry.is_none());
+  assert!(loaded.exclude_licenses.is_none() && loaded.require_osi_approved.is_none());
+  assert!(loaded.fail_on_repo_mismatch.is_none() && loaded.format.is_none());
+
+  // The commented proposals load too once uncommented
+  let content = fs::read_to_string(&path).unwrap();
+  assert!(content.contains("#   { pattern = \"@acme\", reason = \"first-party npm scope\" },"));
+  let uncommented = content
+    .lines()
+    .map(|line| match line.strip_prefix("# ") {
+      // Settings and list entries; the prose starts in upper case or with a digit
+      Some(setting) if setting.starts_with(|c: char| c.is_ascii_lowercase() || c == ' ' || c == ']') => setting,
+      _ => line,
+    })
+    .collect::<Vec<_>>()
+    .join("\n");
+  let uncommented_path = base.join("uncommented.toml");
+  write(&uncommented_path, &uncommented);
+  let proposed = config::load(&uncommented_path.display().to_string(), None).unwrap();
+  let patterns = |entries: Option<Vec<config::PatternEntry>>| {
+    entries
+      .unwrap()
+      .into_iter()
+      .map(|entry| entry.into_rule("--skip").pattern)
+      .collect::<Vec<_>>()
+  };
+  assert_eq!(patterns(proposed.skip_scope), ["@acme"]);
+  assert_eq!(patterns(proposed.skip_prefix), ["github.com/acme"]);
+  assert_eq!(proposed.exclude_private, Some(true));
+  assert_eq!(proposed.require_osi_approved, Some(true));
+
+  // A second run leaves the reviewed file alone
+  let output = depsfetch(&base, &["init", "repo"], &[]);
+  assert!(!output.status.success());
+  assert!(
+    String::from_utf8_lossy(&output.stderr).contains("already exists, pass --force to overwrite it"),
+    "{}",
+    String::from_utf8_lossy(&output.stderr)
+  );
+  let _ = fs::remove_dir_all(&base);
+}
@@ -0,0 +1,82 @@
+use depsfetch::{deps::installed::InstalledVersions, types::Ecosystem};
+use std::{collections::BTreeSet, path::PathBuf};
+
+// tests/fixtures/installed: web/node_modules shared by web and web/app, which has its own is-odd; docs with nothing
+// installed; svc with a go.sum, api without one
+fn installed() -> InstalledVersions {
+  let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/installed");
+  InstalledVersions::new(&root.display().to_string()).unwrap()
+}
+
+fn sources(manifests: &[&str]) -> BTreeSet<String> {
+  manifests.iter().map(|manifest| manifest.to_string()).collect()
+}
+
+#[test]
+fn node_modules_versions_are_compared() {
+  let installed = installed();
+  let mismatch = |name: &str, version: &str, manifests: &[&str]| {
+    installed.mismatch(Ecosystem::Npm, name, version, &sources(manifests))
+  };
+
+  assert_eq!(mismatch("left-pad", "1.3.0", &["web/package.json"]), None);
+  assert_eq!(
+    mismatch("left-pad", "1.3.1", &["web/package.json"]),
+    Some("installed 1.3.0 in web/node_modules".to_owned())
+  );
+  // Found in the parent's node_modules, scoped names included
+  assert_eq!(mismatch("left-pad", "1.3.0", &["web/app/package.json"]), None);
+  assert_eq!(mismatch("@scope/pkg", "2.0.0", &["web/app/package.json"]), None);
+  // The closest copy wins: web/app gets its own is-odd, web the shared one
+  assert_eq!(mismatch("is-odd", "3.0.1", &["web/app/package.json"]), None);
+  assert_eq!(
+    mismatch("is-odd", "3.0.1", &["web/package.json", "web/app/package.json"]),
+    Some("installed 1.0.0 in web/node_modules".to_owned())
+  );
+  assert_eq!(
+    mismatch("is-even", "1.0.0", &["web/app/package.json"]),
+    Some("not installed in node_modules".to_owned())
+  );
+  // Nothing installed to compare with
+  assert_eq!(mismatch("left-pad", "9.9.9", &["docs/package.json"]), None);
+}
+
+#[test]
+fn go_sum_entries_are_compared() {
+  let installed = installed();
+  let mismatch = |name: &str, version: &str, manifests: &[&str]| {
+    installed.mismatch(Ecosystem::Go, name, version, &sources(manifests))
+  };
+
+  assert_eq!(mismatch("golang.org/x/text", "v0.14.0", &["svc/go.mod"]), None);
+  // A /go.mod line alone counts as the version
+  assert_eq!(mismatch("github.com/pkg/errors", "v0.9.1", &["svc/go.mod"]), None);
+  assert_eq!(
+    mismatch("golang.org/x/text", "v0.15.0", &["svc/go.mod"]),
+    Some("svc/go.sum has v0.13.0, v0.14.0".to_owned())
+  );
+  assert_eq!(
+    mismatch("github.com/google/uuid", "v1.6.0", &["svc/go.mod"]),
+    Some("svc/go.sum has v1.5.0".to_owned())
+  );
+  assert_eq!(
+    mismatch("gopkg.in/yaml.v3", "v3.0.1", &["svc/go.mod"]),
+    Some("missing from svc/go.sum".to_owned())
+  );
+  // No go.sum next to api/go.mod
+  assert_eq!(mismatch("golang.org/x/text", "v0.15.0", &["api/go.mod"]), None);
+  assert_eq!(
+    mismatch("golang.org/x/text", "v0.15.0", &["api/go.mod", "svc/go.mod"]),
+    Some("svc/go.sum has v0.13.0, v0.14.0".to_owned())
+  );
+}
+
+// Other ecosystems have nothing installed to compare with
+#[test]
+fn other_ecosystems_are_not_compared() {
+  let installed = installed();
+  assert_eq!(
+    installed.mismatch(Ecosystem::Cargo, "left-pad", "0.0.1", &sources(&["web/package.json"])),
+    None
+  );
+}
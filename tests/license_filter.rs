@@ -0,0 +1,167 @@
+mod common;
+
+use common::{report_row, scratch_dir};
+use depsfetch::{
+  license_data::LicenseData,
+  report::{
+    LicenseFilter, LicensePolicy, ReportWriter, ResolvedRow, Section, StreamFormat, StreamWriter, WriterMessage,
+  },
+  types::{Ecosystem, ReportRow},
+};
+use std::{fs, path::Path};
+
+fn filter(licenses: &[&str], data: &LicenseData) -> LicenseFilter {
+  LicenseFilter::new(
+    &licenses.iter().map(|license| license.to_string()).collect::<Vec<_>>(),
+    data,
+  )
+}
+
+// A Web sheet of npm packages: the permissive ones, one WTFPL package and two that have to stay
+fn rows() -> Vec<ReportRow> {
+  vec![
+    report_row(Ecosystem::Npm, "react", "18.2.0", Some("MIT")),
+    report_row(Ecosystem::Npm, "left-pad", "1.3.0", Some("WTFPL")),
+    report_row(Ecosystem::Npm, "chalk", "5.3.0", Some("mit")),
+    report_row(Ecosystem::Npm, "dual", "1.0.0", Some("MIT OR GPL-3.0-only")),
+    report_row(Ecosystem::Npm, "readline-sync", "1.4.10", Some("GPL-3.0-only")),
+    report_row(Ecosystem::Npm, "internal-utils", "0.1.0", None),
+  ]
+}
+
+// `rows` through the writer thread with --exclude-licenses MIT,WTFPL and --require-osi-approved, plus a JSON lines
+// stream at `dir`/rows.jsonl
+async fn write_filtered(dir: &Path, writer: ReportWriter) -> (Vec<String>, Vec<ReportRow>) {
+  let data = LicenseData::load(None).unwrap();
+  let stream = dir.join("rows.jsonl").display().to_string();
+  let writer = writer
+    .with_license_filter(filter(&["MIT", "WTFPL"], &data))
+    .with_policy(LicensePolicy {
+      require_osi_approved: true,
+      require_fsf_libre: false,
+    })
+    .with_stream(StreamWriter::create(&stream, StreamFormat::JsonLines, true).unwrap())
+    .keep_rows();
+  let (sender, handle) = writer.spawn();
+  sender
+    .send(WriterMessage::Section(Section::Dependencies("Web")))
+    .unwrap();
+  for row in rows() {
+    sender
+      .send(WriterMessage::Row(Box::new(ResolvedRow { sheet: "Web", row })))
+      .unwrap();
+  }
+  sender
+    .send(WriterMessage::Section(Section::Summary("Summary")))
+    .unwrap();
+  sender.send(WriterMessage::Finish).unwrap();
+  let output = handle.await.unwrap().unwrap().unwrap();
+  (output.violations, output.rows)
+}
+
+fn streamed(dir: &Path) -> Vec<String> {
+  fs::read_to_string(dir.join("rows.jsonl"))
+    .unwrap()
+    .lines()
+    .map(|line| serde_json::from_str::<ReportRow>(line).unwrap().name)
+    .collect()
+}
+
+// Ids are compared normalized, and an expression only matches when each of its branches is listed
+#[test]
+fn expressions_match_only_when_every_branch_is_listed() {
+  let data = LicenseData::load(None).unwrap();
+  let filter = filter(&["mit", " Apache 2.0 ", "BSD-3-Clause", "isc"], &data);
+  assert_eq!(filter.rule(), "--exclude-licenses Apache-2.0,BSD-3-Clause,ISC,MIT");
+
+  let cases = [
+    ("MIT", true),
+    ("mit", true),
+    ("Apache-2.0", true),
+    ("Apache 2.0", true),
+    ("MIT OR Apache-2.0", true),
+    ("(MIT AND BSD-3-Clause)", true),
+    ("MIT OR GPL-3.0-only", false),
+    ("Apache-2.0 AND LGPL-2.1-only", false),
+    ("GPL-3.0-only", false),
+    ("BSD-2-Clause", false),
+    ("", false),
+  ];
+  for (license, matches) in cases {
+    assert_eq!(filter.matches(&data, license), matches, "{}", license);
+  }
+}
+
+// The filter only decides what the sheets show: a hidden row is still checked against the policy, kept for --watch
+// and written to the machine readable outputs
+#[tokio::test]
+async fn filtered_rows_still_reach_the_policy_and_the_streams() {
+  let dir = scratch_dir("license-filter-policy");
+  let path = dir.join("report.xlsx").display().to_string();
+  let (violations, kept) = write_filtered(&dir, ReportWriter::new(&path).unwrap().without_workbook()).await;
+
+  // left-pad is hidden, WTFPL still isn't OSI approved
+  assert_eq!(violations, ["npm left-pad@1.3.0: WTFPL is not OSI approved"]);
+  let names = |rows: &[ReportRow]| {
+    let mut names = rows.iter().map(|row| row.name.clone()).collect::<Vec<_>>();
+    names.sort();
+    names
+  };
+  assert_eq!(kept.len(), rows().len());
+  assert_eq!(names(&kept), names(&rows()));
+  assert_eq!(streamed(&dir), names(&rows()));
+  let _ = fs::remove_dir_all(&dir);
+}
+
+// With an extra column and a workbook per project next to the combined one, the hidden rows are left off every
+// dependency sheet, and the Summary still counts them
+#[cfg(feature = "xlsx")]
+#[tokio::test]
+async fn filtered_rows_are_left_off_every_sheet() {
+  use common::workbook::{read_workbook, Sheet};
+  use depsfetch::report::{CanonicalMap, ProjectSplit};
+
+  let dir = scratch_dir("license-filter-sheets");
+  let map = dir.join("projects.toml");
+  fs::write(&map, "\"react*\" = \"ui\"\n\"readline-sync\" = \"ui\"\n").unwrap();
+  let split = ProjectSplit::new(
+    &dir.join("projects").display().to_string(),
+    CanonicalMap::load(&map.display().to_string()).unwrap(),
+  )
+  .unwrap();
+  let path = dir.join("report.xlsx");
+  let writer = ReportWriter::new(&path.display().to_string())
+    .unwrap()
+    .with_confidence()
+    .with_project_split(split, true);
+  write_filtered(&dir, writer).await;
+
+  let sheet = |sheets: &[Sheet], name: &str| {
+    let sheet = sheets.iter().find(|sheet| sheet.name == name).unwrap();
+    sheet.rows.clone()
+  };
+  let names = |rows: &[Vec<String>]| rows[1..].iter().map(|row| row[0].clone()).collect::<Vec<_>>();
+  let combined = read_workbook(&path);
+  let web = sheet(&combined, "Web");
+  assert_eq!(names(&web), ["dual", "internal-utils", "readline-sync"]);
+  // The extra column is there, on rows numbered without gaps
+  assert!(web[0].contains(&"Достоверность лицензии".to_owned()), "{:?}", web[0]);
+  assert!(web.iter().all(|row| !row.is_empty()), "{:?}", web);
+
+  let project = read_workbook(&dir.join("projects/ui.xlsx"));
+  assert_eq!(names(&sheet(&project, "Web")), ["readline-sync"]);
+
+  let summary = sheet(&combined, "Summary");
+  let line = |label: &str| {
+    summary
+      .iter()
+      .find(|row| row.first().map(String::as_str) == Some(label))
+      .map(|row| row[1].clone())
+  };
+  assert_eq!(line("Всего пакетов").as_deref(), Some("6"));
+  assert_eq!(
+    line("Скрыто фильтром лицензий").as_deref(),
+    Some("3 (--exclude-licenses MIT,WTFPL)")
+  );
+  let _ = fs::remove_dir_all(&dir);
+}
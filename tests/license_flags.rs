@@ -0,0 +1,92 @@
+mod common;
+
+use common::{report_row, scratch_dir};
+use depsfetch::{
+  license_data::LicenseData,
+  report::{ReportWriter, ResolvedRow, Section, WriterMessage},
+  types::{Ecosystem, LicenseFlag},
+};
+use std::fs;
+
+use LicenseFlag::{Mixed, No, Yes};
+
+// (license, OSI approved, FSF libre) against the bundled license data
+#[test]
+fn spdx_ids_map_to_their_flags() {
+  let data = LicenseData::load(None).unwrap();
+  let cases = [
+    ("MIT", Some(Yes), Some(Yes)),
+    ("Apache-2.0", Some(Yes), Some(Yes)),
+    ("GPL-3.0-or-later", Some(Yes), Some(Yes)),
+    // Approved by one of the two only
+    ("0BSD", Some(Yes), Some(No)),
+    ("MIT-0", Some(Yes), Some(No)),
+    ("WTFPL", Some(No), Some(Yes)),
+    ("CC0-1.0", Some(No), Some(Yes)),
+    ("BSD-4-Clause", Some(No), Some(Yes)),
+    // Known, approved by neither
+    ("CC-BY-3.0", Some(No), Some(No)),
+    ("CDDL-1.1", Some(No), Some(No)),
+    // Aliases and case are normalized first
+    ("apache 2.0", Some(Yes), Some(Yes)),
+    ("GPL-2.0+", Some(Yes), Some(Yes)),
+    ("mit", Some(Yes), Some(Yes)),
+    // Every branch of an expression has to agree
+    ("MIT OR Apache-2.0", Some(Yes), Some(Yes)),
+    ("(MIT AND WTFPL)", Some(Mixed), Some(Yes)),
+    ("0BSD OR CC0-1.0", Some(Mixed), Some(Mixed)),
+    ("CC-BY-3.0 OR CDDL-1.1", Some(No), Some(No)),
+    // Unknown as soon as one branch is
+    ("Made-Up-1.0", None, None),
+    ("MIT OR Made-Up-1.0", None, None),
+    ("", None, None),
+  ];
+  for (license, osi, fsf) in cases {
+    assert_eq!(
+      (data.osi_approved(Some(license)), data.fsf_libre(Some(license))),
+      (osi, fsf),
+      "{}",
+      license
+    );
+  }
+  assert_eq!((data.osi_approved(None), data.fsf_libre(None)), (None, None));
+}
+
+// The writer sets both flags on every row it's given
+#[tokio::test]
+async fn writer_flags_every_row() {
+  let dir = scratch_dir("license-flags");
+  let path = dir.join("deps_report.xlsx").display().to_string();
+  let writer = ReportWriter::new(&path).unwrap().without_workbook().keep_rows();
+  let (sender, handle) = writer.spawn();
+  sender
+    .send(WriterMessage::Section(Section::Dependencies("Web")))
+    .unwrap();
+  for (name, license) in [
+    ("left-pad", Some("WTFPL")),
+    ("dual", Some("MIT OR WTFPL")),
+    ("internal", None),
+  ] {
+    let row = report_row(Ecosystem::Npm, name, "1.0.0", license);
+    sender
+      .send(WriterMessage::Row(Box::new(ResolvedRow { sheet: "Web", row })))
+      .unwrap();
+  }
+  sender.send(WriterMessage::Finish).unwrap();
+  let output = handle.await.unwrap().unwrap().unwrap();
+
+  let flags = output
+    .rows
+    .iter()
+    .map(|row| (row.name.as_str(), row.osi_approved, row.fsf_libre))
+    .collect::<Vec<_>>();
+  assert_eq!(
+    flags,
+    [
+      ("left-pad", Some(No), Some(Yes)),
+      ("dual", Some(Mixed), Some(Yes)),
+      ("internal", None, None),
+    ]
+  );
+  let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,168 @@
+use depsfetch::{
+  http::{BackendFuture, HttpResponse, MetadataBackend, MetadataRequest},
+  resolve::{resolve, ResolveOptions, ResolvedDependency},
+  types::{DepScope, DepsEntry, Ecosystem},
+};
+use reqwest::StatusCode;
+use std::{collections::BTreeSet, sync::Mutex};
+
+// tests/fixtures/license_probe: a license file and the pages abandoned repositories answer with instead
+const LICENSE: &str = include_str!("fixtures/license_probe/LICENSE");
+const PARKED: &str = include_str!("fixtures/license_probe/parked.html");
+const TAKEN_DOWN: &str = include_str!("fixtures/license_probe/dmca.html");
+const MARKETING: &str = include_str!("fixtures/license_probe/marketing.html");
+
+// npm and GitHub: every package is MIT with its own repository on `main`, whose candidate license files answer with
+// the pages below. Every URL asked for is kept.
+#[derive(Default)]
+struct Pages(Mutex<Vec<String>>);
+
+impl Pages {
+  fn asked(&self, prefix: &str) -> Vec<String> {
+    let asked = self.0.lock().unwrap();
+    asked.iter().filter(|url| url.starts_with(prefix)).cloned().collect()
+  }
+}
+
+impl MetadataBackend for Pages {
+  fn fetch<'a>(&'a self, request: MetadataRequest<'a>) -> BackendFuture<'a> {
+    self.0.lock().unwrap().push(request.url.to_owned());
+    let page = |status: StatusCode, content_type: &str, body: &str| {
+      let mut response = HttpResponse::new(status, request.url, body.to_owned());
+      response.content_type = Some(content_type.to_owned());
+      response
+    };
+    let html = |body: &str| page(StatusCode::OK, "text/html; charset=utf-8", body);
+    let text = |body: &str| page(StatusCode::OK, "text/plain; charset=utf-8", body);
+
+    let url = request.url;
+    let package = url.split('/').nth(4).unwrap_or_default();
+    let response = match url.strip_prefix(&format!("https://github.com/o/{}/blob/main/", package)) {
+      Some(file) => match (package, file) {
+        ("walks-on", "LICENSE.txt") => html(PARKED),
+        ("walks-on", "LICENSE") => html(MARKETING),
+        ("walks-on", "LICENSE.md") => text(LICENSE),
+        // GitHub serves its takedown notice on a 200 too
+        ("taken-down", _) => html(TAKEN_DOWN),
+        ("legal", _) => page(StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS, "text/html", ""),
+        // The repository's domain lapsed and now redirects to a parking page
+        ("moved", _) => HttpResponse {
+          url: "https://example-lib.dev/".to_owned(),
+          ..html(PARKED)
+        },
+        ("nothing", _) => html(MARKETING),
+        _ => page(StatusCode::NOT_FOUND, "text/html", ""),
+      },
+      None if url.starts_with("https://registry.npmjs.org/") => {
+        let name = url.split('/').nth(3).unwrap_or_default();
+        let document = format!(
+          r#"{{"license": "MIT", "repository": "git+https://github.com/o/{}.git"}}"#,
+          name
+        );
+        page(StatusCode::OK, "application/json", &document)
+      },
+      None if url.starts_with("https://api.github.com/repos/o/") => {
+        page(StatusCode::OK, "application/json", r#"{"default_branch": "main"}"#)
+      },
+      None if url.starts_with("https://github.com/o/") => html(""),
+      None => page(StatusCode::NOT_FOUND, "text/html", ""),
+    };
+    Box::pin(async move { Ok(response) })
+  }
+}
+
+fn entry(name: &str) -> DepsEntry {
+  DepsEntry {
+    name: name.to_owned(),
+    version: "1.0.0".to_owned(),
+    scope: DepScope::Runtime,
+    sources: BTreeSet::new(),
+  }
+}
+
+fn probe(backend: &Pages, name: &str) -> ResolvedDependency {
+  let runtime = tokio::runtime::Runtime::new().unwrap();
+  let mut resolved = runtime.block_on(resolve([entry(name)], backend, ResolveOptions::new(Ecosystem::Npm)));
+  resolved.remove(0)
+}
+
+fn license_url(resolved: &ResolvedDependency) -> Option<&str> {
+  resolved.row.as_ref().unwrap().license_url.as_deref()
+}
+
+// What the probe noted in the row's provenance
+fn probe_notes(resolved: &ResolvedDependency) -> Vec<&str> {
+  let provenance = &resolved.row.as_ref().unwrap().provenance;
+  provenance
+    .iter()
+    .map(String::as_str)
+    .filter(|note| note.starts_with("license probe") || note.starts_with("failed: repository"))
+    .collect()
+}
+
+// Rejected pages don't stop the probe: each is noted and the next candidate file is tried until one reads like a
+// license
+#[test]
+fn probe_walks_past_pages_that_are_not_licenses() {
+  let backend = Pages::default();
+  let resolved = probe(&backend, "walks-on");
+
+  assert_eq!(
+    license_url(&resolved),
+    Some("https://github.com/o/walks-on/blob/main/LICENSE.md")
+  );
+  assert_eq!(
+    probe_notes(&resolved),
+    [
+      "license probe rejected LICENSE.txt: parked page (\"this domain is for sale\")",
+      "license probe rejected LICENSE: no license text",
+    ]
+  );
+  assert_eq!(
+    backend.asked("https://github.com/o/walks-on/blob/"),
+    [
+      "https://github.com/o/walks-on/blob/main/LICENSE.txt",
+      "https://github.com/o/walks-on/blob/main/LICENSE",
+      "https://github.com/o/walks-on/blob/main/LICENSE.md",
+    ]
+  );
+
+  // Not one of them reads like a license: every candidate is noted, none becomes the link
+  let resolved = probe(&backend, "nothing");
+  assert_eq!(license_url(&resolved), None);
+  assert_eq!(
+    probe_notes(&resolved),
+    [
+      "license probe rejected LICENSE.txt: no license text",
+      "license probe rejected LICENSE: no license text",
+      "license probe rejected LICENSE.md: no license text",
+      "license probe rejected license: no license text",
+    ]
+  );
+}
+
+// A taken-down repository or one that moved off its code host stops the probe at the first page
+#[test]
+fn unavailable_and_redirected_repositories_stop_the_probe() {
+  let cases = [
+    (
+      "taken-down",
+      "failed: repository unavailable (page says \"repository unavailable due to dmca takedown\")",
+    ),
+    ("legal", "failed: repository unavailable (HTTP 451)"),
+    ("moved", "license probe rejected: redirected to example-lib.dev"),
+  ];
+  for (name, note) in cases {
+    let backend = Pages::default();
+    let resolved = probe(&backend, name);
+
+    assert_eq!(license_url(&resolved), None, "{}", name);
+    assert_eq!(probe_notes(&resolved), [note], "{}", name);
+    assert_eq!(
+      backend.asked(&format!("https://github.com/o/{}/blob/", name)).len(),
+      1,
+      "{}",
+      name
+    );
+  }
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::{depsfetch, scratch_dir};
+use serde_json::{json, Value};
+use std::{fs, path::PathBuf};
+
+// tests/fixtures/local_fork: `repo` replaces github.com/upstream/yaml by its fork in third_party/yaml, which keeps
+// the upstream Apache-2.0 LICENSE, and example.com/outside by a directory next to the repository
+fn fixture() -> PathBuf {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/local_fork")
+}
+
+// The fork is reported under the module it replaces with the license classified from its directory, the
+// replacement outside the scanned root is reported without reading anything there
+#[test]
+fn local_fork_is_reported_with_the_upstream_license() {
+  let base = scratch_dir("local-fork");
+  let repo = fixture().join("repo").display().to_string();
+  let output = depsfetch(
+    &base,
+    &[&repo, "--jsonl", "rows.jsonl", "--final-retry-window", "0"],
+    &[],
+  );
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  assert!(
+    output.status.success(),
+    "{}{}",
+    stdout,
+    String::from_utf8_lossy(&output.stderr)
+  );
+
+  let mut rows = fs::read_to_string(base.join("rows.jsonl"))
+    .unwrap()
+    .lines()
+    .map(|line| serde_json::from_str::<Value>(line).unwrap())
+    .collect::<Vec<_>>();
+  rows.sort_by_key(|row| row["name"].as_str().unwrap().to_owned());
+  assert_eq!(rows.len(), 2, "{:?}", rows);
+
+  let (outside, fork) = (&rows[0], &rows[1]);
+  assert_eq!(fork["name"], "github.com/upstream/yaml");
+  assert_eq!(fork["version"], "v1.2.0");
+  assert_eq!(fork["license"], "Apache-2.0");
+  assert_eq!(fork["license_confidence"], "detected");
+  assert_eq!(
+    fork["provenance"],
+    json!([
+      "forked locally: replaced by third_party/yaml",
+      "license file: third_party/yaml/LICENSE",
+    ])
+  );
+
+  assert_eq!(outside["name"], "example.com/outside");
+  assert_eq!(outside["version"], "v0.3.0");
+  // Its MIT LICENSE is never read
+  assert_eq!(outside["license"], Value::Null);
+  assert_eq!(
+    outside["provenance"],
+    json!(["unresolved local replacement: ../outside/lib leads out of the scanned root"])
+  );
+  let log = fs::read_to_string(base.join("warnings.log")).unwrap();
+  assert!(
+    log.contains("example.com/outside is replaced by ../outside/lib, which leads out of the scanned root"),
+    "{}",
+    log
+  );
+  let _ = fs::remove_dir_all(&base);
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use common::{depsfetch, scratch_dir, write};
+use depsfetch::deps::local_path::{is_contained, safe_join, PathRefusal};
+use std::{fs, path::Path};
+
+// An adversarial checkout under `base`: the scanned `repo` with an app and a package next to it, and a secret
+// outside the root that every escape vector aims at
+fn checkout(base: &Path) {
+  fs::create_dir_all(base.join("repo/app")).unwrap();
+  fs::create_dir_all(base.join("repo/packages/util")).unwrap();
+  write(&base.join("outside/secret"), "secret-package==6.6.6\n");
+  write(&base.join("outside/lib/go.mod"), "module example.com/secret\n");
+  #[cfg(unix)]
+  {
+    let app = base.join("repo/app");
+    std::os::unix::fs::symlink(base.join("outside"), app.join("vendor")).unwrap();
+    std::os::unix::fs::symlink(base.join("outside/secret"), app.join("LICENSE")).unwrap();
+    std::os::unix::fs::symlink(base.join("repo/packages"), app.join("linked")).unwrap();
+  }
+}
+
+#[test]
+fn safe_join_refuses_every_escape() {
+  let base = scratch_dir("safe-join");
+  checkout(&base);
+  let root = fs::canonicalize(base.join("repo")).unwrap();
+  let app = root.join("app");
+
+  assert_eq!(
+    safe_join(&root, &app, "../packages/util").unwrap(),
+    root.join("packages/util")
+  );
+  assert!(safe_join(&root, &app, "./../app/../packages/./util").is_ok());
+  // Out of the root and back in: where the path ends up is what counts
+  assert!(safe_join(&root, &app, "../../repo/packages/util").is_ok());
+
+  let refused = |relative: &str| safe_join(&root, &app, relative).unwrap_err();
+  assert!(matches!(refused("../../outside/secret"), PathRefusal::Traversal));
+  assert!(matches!(
+    refused("../../../../../../etc/passwd"),
+    PathRefusal::Traversal
+  ));
+  assert!(matches!(refused("/etc/passwd"), PathRefusal::Absolute));
+  assert!(matches!(
+    refused(&root.join("packages/util").display().to_string()),
+    PathRefusal::Absolute
+  ));
+  assert!(matches!(refused("../packages/missing"), PathRefusal::Missing(_)));
+  assert!(!refused("../packages/missing").is_escape());
+  assert!(refused("/etc/passwd").is_escape());
+
+  #[cfg(unix)]
+  {
+    assert!(matches!(refused("vendor"), PathRefusal::SymlinkEscape(_)));
+    assert!(matches!(refused("vendor/secret"), PathRefusal::SymlinkEscape(_)));
+    assert!(matches!(refused("LICENSE"), PathRefusal::SymlinkEscape(_)));
+    assert_eq!(
+      refused("LICENSE").to_string(),
+      format!(
+        "goes through a symlink out of the scanned root (to {})",
+        fs::canonicalize(base.join("outside/secret")).unwrap().display()
+      )
+    );
+    // A symlink that stays inside the root is followed
+    assert_eq!(
+      safe_join(&root, &app, "linked/util").unwrap(),
+      root.join("packages/util")
+    );
+    assert!(is_contained(&root, &app.join("linked/util")));
+    assert!(!is_contained(&root, &app.join("LICENSE")));
+  }
+  let _ = fs::remove_dir_all(&base);
+}
+
+// A scan of the adversarial checkout reads nothing outside it, says what it refused and counts it in the recap
+#[test]
+#[cfg(unix)]
+fn adversarial_repo_is_scanned_without_leaving_the_root() {
+  let base = scratch_dir("adversarial");
+  checkout(&base);
+  let outside = fs::canonicalize(base.join("outside")).unwrap();
+  write(
+    &base.join("repo/app/requirements.txt"),
+    &format!(
+      "-r ../../outside/secret\n-r {}\n-r vendor/secret\n-r LICENSE\n-r missing.txt\n-r linked/util/requirements.txt\n",
+      outside.join("secret").display()
+    ),
+  );
+  write(&base.join("repo/packages/util/requirements.txt"), "# nothing pinned\n");
+  write(
+    &base.join("repo/svc/go.mod"),
+    "module example.com/svc\n\ngo 1.21\n\nrequire (\n  example.com/lib v1.0.0\n  example.com/vendored v1.0.0\n)\n\n\
+     replace example.com/lib => ../../outside/lib\n\nreplace example.com/vendored => ../app/vendor/lib\n",
+  );
+
+  let output = depsfetch(
+    &base,
+    &["repo", "--jsonl", "rows.jsonl", "--final-retry-window", "0"],
+    &[],
+  );
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  assert!(output.status.success(), "{}", stdout);
+
+  // Six escapes, and the missing include as an ordinary scan issue; the console shows the first few, the log all
+  let recap = &stdout[stdout.find("Warnings:").unwrap()..];
+  assert!(recap.contains("Refused local paths (6):"), "{}", recap);
+  assert!(recap.contains("Scan issues (1):"), "{}", recap);
+  let log = fs::read_to_string(base.join("warnings.log")).unwrap();
+  for expected in [
+    "app/requirements.txt includes ../../outside/secret, which leads out of the scanned root",
+    "which is absolute",
+    "app/requirements.txt includes vendor/secret, which goes through a symlink out of the scanned root",
+    "app/requirements.txt includes LICENSE, which goes through a symlink out of the scanned root",
+    "example.com/lib is replaced by ../../outside/lib, which leads out of the scanned root",
+    "example.com/vendored is replaced by ../app/vendor/lib, which goes through a symlink out of the scanned root",
+  ] {
+    assert!(log.contains(expected), "{} not in {}", expected, log);
+  }
+
+  // The replaced modules are rows that say why they weren't followed, nothing from outside became a row
+  let rows = fs::read_to_string(base.join("rows.jsonl")).unwrap();
+  assert_eq!(rows.lines().count(), 2, "{}", rows);
+  assert!(rows.contains("unresolved local replacement: ../../outside/lib leads out of the scanned root"));
+  assert!(!rows.contains("secret"), "{}", rows);
+  let _ = fs::remove_dir_all(&base);
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use common::{MockServer, Response};
+use depsfetch::http::{EndpointClass, HttpClient};
+use std::{sync::Arc, thread, time::Duration};
+
+// `/<ms>` answers 200 after that long, `/fail` answers 500 right away
+fn delaying_server() -> MockServer {
+  MockServer::start(|request| match request.path.trim_start_matches('/').parse() {
+    Ok(ms) => {
+      thread::sleep(Duration::from_millis(ms));
+      Response::json("{}")
+    },
+    Err(_) => Response::status(500),
+  })
+}
+
+#[test]
+fn concurrent_tasks_merge_their_tallies() {
+  let server = delaying_server();
+  let base = server.base.clone();
+  let client = Arc::new(HttpClient::new(&None).unwrap());
+  tokio::runtime::Runtime::new().unwrap().block_on(async {
+    // Nine quick requests and a slow one, a task each, all at once; probes in a task of their own
+    let mut tasks = (0..10)
+      .map(|idx| {
+        let (client, url) = (
+          Arc::clone(&client),
+          format!("{}/{}", base, if idx == 0 { 200 } else { 20 }),
+        );
+        tokio::spawn(async move {
+          client.metrics().measured(client.get(&url)).await.unwrap();
+        })
+      })
+      .collect::<Vec<_>>();
+    let (probes, url) = (Arc::clone(&client), format!("{}/50", base));
+    tasks.push(tokio::spawn(async move {
+      probes
+        .metrics()
+        .measured(async {
+          for _ in 0..4 {
+            probes.get_prefix(&url, 1).await.unwrap();
+          }
+          probes.head(&format!("{}/fail", base)).await.unwrap();
+        })
+        .await
+    }));
+    for task in tasks {
+      task.await.unwrap();
+    }
+  });
+
+  let metrics = client.metrics().snapshot();
+  let other = &metrics[&EndpointClass::Other];
+  assert_eq!((other.requests, other.errors), (10, 0));
+  // 20 ms lands in the bucket up to 22.6 ms, 200 ms in the one up to 215 ms. A loaded machine (the whole test suite
+  // at once) may push either several buckets further, the median still stays well clear of the slow request.
+  let (p50, p95) = (other.p50().unwrap(), other.p95().unwrap());
+  assert!(
+    p50 >= Duration::from_millis(20) && p50 < Duration::from_millis(100),
+    "{:?}",
+    p50
+  );
+  assert!(
+    p95 >= Duration::from_millis(200) && p95 < Duration::from_millis(305),
+    "{:?}",
+    p95
+  );
+  let probe = &metrics[&EndpointClass::Probe];
+  assert_eq!((probe.requests, probe.errors), (5, 1));
+  let p95 = probe.p95().unwrap();
+  assert!(
+    p95 >= Duration::from_millis(50) && p95 < Duration::from_millis(150),
+    "{:?}",
+    p95
+  );
+
+  let run_info = client.metrics().run_info();
+  assert_eq!(
+    run_info.iter().map(|(setting, _, _)| *setting).collect::<Vec<_>>(),
+    ["http_probe", "http_other"]
+  );
+  assert!(
+    run_info[0].1.starts_with("5 request(s), 1 error(s), p50 "),
+    "{}",
+    run_info[0].1
+  );
+}
+
+// Requests outside a measured task count right away; a measured one's show in its own snapshot before it ends
+#[test]
+fn unmeasured_requests_go_straight_to_the_tally() {
+  let server = delaying_server();
+  let client = HttpClient::new(&None).unwrap();
+  assert!(client.metrics().snapshot().is_empty());
+  assert!(client.metrics().run_info().is_empty());
+  tokio::runtime::Runtime::new().unwrap().block_on(async {
+    client.get(&format!("{}/0", server.base)).await.unwrap();
+    assert_eq!(client.metrics().snapshot()[&EndpointClass::Other].requests, 1);
+    client
+      .metrics()
+      .measured(async {
+        client.get(&format!("{}/0", server.base)).await.unwrap();
+        assert_eq!(client.metrics().snapshot()[&EndpointClass::Other].requests, 2);
+      })
+      .await;
+  });
+  assert_eq!(client.metrics().snapshot()[&EndpointClass::Other].requests, 2);
+}
+
+#[test]
+fn urls_tell_some_classes_apart() {
+  assert_eq!(
+    EndpointClass::of_url("https://api.github.com/repos/o/r/license"),
+    EndpointClass::GithubLicense
+  );
+  assert_eq!(
+    EndpointClass::of_url("https://api.github.com/repos/o/r"),
+    EndpointClass::Other
+  );
+  assert_eq!(
+    EndpointClass::of_url("https://pkg.go.dev/golang.org/x/text"),
+    EndpointClass::PkgGoDev
+  );
+  // The npm registry can live anywhere, its requests say what they are
+  assert_eq!(
+    EndpointClass::of_url("https://registry.npmjs.org/left-pad"),
+    EndpointClass::Other
+  );
+  assert_eq!(EndpointClass::of_url("not a url"), EndpointClass::Other);
+}
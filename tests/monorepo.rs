@@ -0,0 +1,121 @@
+use depsfetch::{
+  http::{BackendFuture, HttpResponse, MetadataBackend, MetadataRequest},
+  resolve::{resolve, ResolveOptions, ResolvedDependency},
+  types::{DepScope, DepsEntry, Ecosystem},
+};
+use reqwest::StatusCode;
+use std::{collections::BTreeSet, fs, path::PathBuf, sync::Mutex};
+
+// tests/fixtures/monorepo: the o/mono repository on `main`, MIT at the root and Apache-2.0 in packages/core. Each
+// package's package.json is what the registry serves for it.
+fn fixture(path: &str) -> PathBuf {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    .join("tests/fixtures/monorepo")
+    .join(path)
+}
+
+// npm and GitHub serving the fixture tree. Every URL asked for is kept.
+#[derive(Default)]
+struct Monorepo(Mutex<Vec<String>>);
+
+impl Monorepo {
+  fn probed(&self) -> Vec<String> {
+    let asked = self.0.lock().unwrap();
+    let probes = asked
+      .iter()
+      .filter_map(|url| url.strip_prefix("https://github.com/o/mono/blob/main/"));
+    probes.map(str::to_owned).collect()
+  }
+}
+
+impl MetadataBackend for Monorepo {
+  fn fetch<'a>(&'a self, request: MetadataRequest<'a>) -> BackendFuture<'a> {
+    self.0.lock().unwrap().push(request.url.to_owned());
+    let file = |path: &str| fs::read_to_string(fixture(path)).ok();
+    let url = request.url;
+    let (body, content_type) = match url {
+      "https://api.github.com/repos/o/mono" => (Some(r#"{"default_branch": "main"}"#.to_owned()), "application/json"),
+      "https://github.com/o/mono" => (Some(String::new()), "text/html"),
+      _ => match url.strip_prefix("https://github.com/o/mono/blob/main/") {
+        Some(path) => (file(path), "text/plain"),
+        None => match url.strip_prefix("https://registry.npmjs.org/@mono/") {
+          Some(package) => (
+            file(&format!("packages/{}/package.json", package.split('/').next().unwrap())),
+            "application/json",
+          ),
+          None => (None, "text/html"),
+        },
+      },
+    };
+    let mut response = match body {
+      Some(body) => HttpResponse::new(StatusCode::OK, url, body),
+      None => HttpResponse::new(StatusCode::NOT_FOUND, url, ""),
+    };
+    response.content_type = Some(content_type.to_owned());
+    Box::pin(async move { Ok(response) })
+  }
+}
+
+fn resolve_package(backend: &Monorepo, name: &str) -> ResolvedDependency {
+  let entry = DepsEntry {
+    name: name.to_owned(),
+    version: "2.0.0".to_owned(),
+    scope: DepScope::Runtime,
+    sources: BTreeSet::new(),
+  };
+  let runtime = tokio::runtime::Runtime::new().unwrap();
+  runtime
+    .block_on(resolve([entry], backend, ResolveOptions::new(Ecosystem::Npm)))
+    .remove(0)
+}
+
+// packages/core has a LICENSE of its own, so the root one, under another license, is never asked for
+#[test]
+fn license_in_the_package_directory_wins_over_the_root() {
+  let backend = Monorepo::default();
+  let resolved = resolve_package(&backend, "@mono/core");
+
+  let row = resolved.row.as_ref().unwrap();
+  assert_eq!(row.license.as_deref(), Some("Apache-2.0"));
+  assert_eq!(
+    row.license_url.as_deref(),
+    Some("https://github.com/o/mono/blob/main/packages/core/LICENSE")
+  );
+  assert!(
+    row
+      .provenance
+      .contains(&"license file: package directory packages/core".to_owned()),
+    "{:?}",
+    row.provenance
+  );
+  assert_eq!(backend.probed(), ["packages/core/LICENSE.txt", "packages/core/LICENSE"]);
+}
+
+// packages/cli has none: every candidate there is tried, then the root supplies it
+#[test]
+fn package_directory_without_a_license_falls_back_to_the_root() {
+  let backend = Monorepo::default();
+  let resolved = resolve_package(&backend, "@mono/cli");
+
+  let row = resolved.row.as_ref().unwrap();
+  assert_eq!(
+    row.license_url.as_deref(),
+    Some("https://github.com/o/mono/blob/main/LICENSE")
+  );
+  assert!(
+    row.provenance.contains(&"license file: repository root".to_owned()),
+    "{:?}",
+    row.provenance
+  );
+  assert_eq!(
+    backend.probed(),
+    [
+      "packages/cli/LICENSE.txt",
+      "packages/cli/LICENSE",
+      "packages/cli/LICENSE.md",
+      "packages/cli/license",
+      "LICENSE.txt",
+      "LICENSE",
+    ]
+  );
+}
@@ -0,0 +1,495 @@
+mod common;
+
+use common::{depsfetch, scratch_dir, write, MockServer, Request, Response};
+use depsfetch::{
+  deps::npmrc::Npmrc,
+  diagnostics::{Category, Diagnostics},
+  http::HttpClient,
+  report::NpmRegistry,
+  resolve::{resolve, ResolveOptions},
+  types::{DepScope, DepsEntry, Ecosystem},
+};
+use serde_json::{json, Value};
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  env, fs,
+};
+
+fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+  tokio::runtime::Runtime::new().unwrap().block_on(future)
+}
+
+// What the server was asked for, in order
+fn paths(server: &MockServer) -> Vec<String> {
+  server.requests().into_iter().map(|request| request.path).collect()
+}
+
+fn registry(base: &str, auth_style: Option<&str>, token: &str) -> NpmRegistry {
+  let stored = BTreeMap::from([("127.0.0.1".to_owned(), token.to_owned())]);
+  NpmRegistry::new(Some(base), auth_style, &stored).unwrap()
+}
+
+#[test]
+fn auth_styles_send_their_header() {
+  let server = MockServer::start(|_| Response::json(r#"{"name":"left-pad","version":"1.3.0","license":"WTFPL"}"#));
+  let client = HttpClient::new(&None).unwrap();
+  let fetch = |registry: NpmRegistry| {
+    block_on(registry.fetch(&client, "left-pad", "1.3.0")).unwrap();
+    server.requests().last().unwrap().headers.get("authorization").cloned()
+  };
+
+  assert_eq!(fetch(registry(&server.base, None, "ignored")), None);
+  assert_eq!(
+    fetch(registry(&server.base, Some("bearer"), "npm-token")),
+    Some("Bearer npm-token".to_owned())
+  );
+  // `user:password` is encoded, anything else is taken as already encoded
+  assert_eq!(
+    fetch(registry(&server.base, Some("basic"), "user:secret")),
+    Some("Basic dXNlcjpzZWNyZXQ=".to_owned())
+  );
+  assert_eq!(
+    fetch(registry(&server.base, Some("basic"), "dXNlcjpzZWNyZXQ=")),
+    Some("Basic dXNlcjpzZWNyZXQ=".to_owned())
+  );
+
+  let err = NpmRegistry::new(Some(&server.base), Some("digest"), &BTreeMap::new())
+    .err()
+    .unwrap();
+  assert_eq!(
+    err.to_string(),
+    "Unknown npm auth style `digest`, expected one of: bearer, basic"
+  );
+}
+
+// Verdaccio behind some proxies only knows the version route with a trailing slash; one retry, not more
+#[test]
+fn version_route_is_retried_with_a_trailing_slash() {
+  let server = MockServer::start(|request| match request.path.as_str() {
+    "/left-pad/1.3.0/" => Response::json(r#"{"license":"WTFPL","repository":"https://github.com/stevemao/left-pad"}"#),
+    _ => Response::status(404),
+  });
+  let client = HttpClient::new(&None).unwrap();
+  let fetched = block_on(registry(&server.base, None, "").fetch(&client, "left-pad", "1.3.0")).unwrap();
+
+  assert_eq!(fetched.dialect.to_string(), "version route with trailing slash");
+  // Trimmed documents get the name and version they were asked by, a string repository is its URL
+  assert_eq!(
+    (
+      fetched.package_info.name.as_str(),
+      fetched.package_info.version.as_str()
+    ),
+    ("left-pad", "1.3.0")
+  );
+  assert_eq!(
+    fetched.package_info.repository.url,
+    "https://github.com/stevemao/left-pad"
+  );
+  assert_eq!(fetched.package_info.license, "WTFPL");
+  assert_eq!(paths(&server), ["/left-pad/1.3.0", "/left-pad/1.3.0/"]);
+}
+
+// Registries without a version route at all: the document comes out of the packument's `versions`, a missing
+// license is an empty one and not a parse failure
+#[test]
+fn packument_only_registry() {
+  let server = MockServer::start(|request| match request.path.as_str() {
+    "/is-odd" => Response::json(
+      r#"{
+        "name": "is-odd",
+        "dist-tags": {"latest": "3.0.1"},
+        "time": {"3.0.1": "2018-04-01T00:00:00.000Z"},
+        "versions": {
+          "3.0.0": {"license": "MIT"},
+          "3.0.1": {"name": "is-odd", "version": "3.0.1", "repository": "git+https://github.com/o/is-odd.git"}
+        }
+      }"#,
+    ),
+    _ => Response::status(404),
+  });
+  let client = HttpClient::new(&None).unwrap();
+  let fetched = block_on(registry(&server.base, None, "").fetch(&client, "is-odd", "3.0.1")).unwrap();
+
+  assert_eq!(fetched.dialect.to_string(), "packument");
+  assert_eq!(fetched.package_info.version, "3.0.1");
+  assert_eq!(fetched.package_info.license, "");
+  assert_eq!(
+    fetched.package_info.repository.url,
+    "git+https://github.com/o/is-odd.git"
+  );
+  assert_eq!(fetched.unpublished, None);
+  assert_eq!(paths(&server), ["/is-odd/3.0.1", "/is-odd/3.0.1/", "/is-odd"]);
+}
+
+// A registry turning the token away is an error naming the registry, not a missing package
+#[test]
+fn refused_credentials_fail_the_fetch() {
+  let server = MockServer::start(|_| Response::status(401));
+  let client = HttpClient::new(&None).unwrap();
+  let err = block_on(registry(&server.base, Some("bearer"), "expired").fetch(&client, "left-pad", "1.3.0"))
+    .err()
+    .unwrap();
+
+  let err = format!("{:#}", err);
+  assert!(
+    err.contains(&format!("Registry {} (bearer auth) refused", server.base)),
+    "{}",
+    err
+  );
+  assert!(!err.contains("expired"), "{}", err);
+  assert_eq!(server.requests().len(), 1);
+}
+
+// tests/fixtures/npm_registry: chalk's packument as registry.npmjs.org serves it (with a few malformed enrichment
+// fields) and in the abbreviated form Artifactory remotes serve
+const FULL_PACKUMENT: &str = include_str!("fixtures/npm_registry/chalk.full.json");
+const ABBREVIATED_PACKUMENT: &str = include_str!("fixtures/npm_registry/chalk.abbreviated.json");
+
+// A registry without a version route, serving the full packument, the abbreviated one unless the full one is asked
+// for, or only ever the abbreviated one
+fn packument_registry(served: &'static str) -> MockServer {
+  MockServer::start(move |request| {
+    let asked_full = request
+      .headers
+      .get("accept")
+      .is_some_and(|accept| accept == "application/json");
+    match (request.path.as_str(), served) {
+      ("/chalk", "full") => Response::json(FULL_PACKUMENT),
+      ("/chalk", "fallback") if asked_full => Response::json(FULL_PACKUMENT),
+      ("/chalk", _) => Response::json(ABBREVIATED_PACKUMENT),
+      _ => Response::status(404),
+    }
+  })
+}
+
+#[test]
+fn abbreviated_packuments_give_the_same_core_fields() {
+  let client = HttpClient::new(&None).unwrap();
+  for version in ["5.3.0", "5.2.0", "latest"] {
+    let mut core = Vec::new();
+    for served in ["full", "fallback", "abbreviated"] {
+      let server = packument_registry(served);
+      let fetched = block_on(registry(&server.base, None, "").fetch(&client, "chalk", version)).unwrap();
+      let info = &fetched.package_info;
+      core.push((info.name.clone(), info.version.clone(), info.license.clone()));
+
+      let packuments = paths(&server).into_iter().filter(|path| path == "/chalk").count();
+      match served {
+        // Malformed enrichment fields read as empty, they don't fail the package
+        "full" => {
+          assert_eq!(fetched.dialect.to_string(), "packument");
+          assert_eq!(packuments, 1);
+          if version != "5.2.0" {
+            assert_eq!(info.repository.url, "");
+            assert!(info.funding.is_none());
+          }
+        },
+        // The full packument is asked for once the abbreviated one has no license
+        "fallback" => {
+          assert_eq!(fetched.dialect.to_string(), "packument");
+          assert_eq!(packuments, 2);
+        },
+        _ => {
+          assert_eq!(fetched.dialect.to_string(), "abbreviated packument");
+          assert_eq!(packuments, 2);
+        },
+      }
+    }
+
+    let expected = match version {
+      "5.2.0" => "5.2.0",
+      _ => "5.3.0",
+    };
+    let full = ("chalk".to_owned(), expected.to_owned(), "MIT".to_owned());
+    assert_eq!(core[..2], [full.clone(), full], "{}", version);
+    // Nothing to fall back to: the license column degrades, name and version don't
+    assert_eq!(
+      core[2],
+      ("chalk".to_owned(), expected.to_owned(), String::new()),
+      "{}",
+      version
+    );
+  }
+}
+
+fn entry(name: &str, version: &str) -> DepsEntry {
+  DepsEntry {
+    name: name.to_owned(),
+    version: version.to_owned(),
+    scope: DepScope::Runtime,
+    sources: BTreeSet::new(),
+  }
+}
+
+// left-pad style: 1.2.3 is gone from a package that is still there, 0.9.0 predates every surviving version, and
+// gone-pkg has nothing published anymore
+fn unpublished_registry() -> MockServer {
+  MockServer::start(|request| match request.path.as_str() {
+    "/left-pad" => Response::json(
+      r#"{"versions": {
+        "1.2.2": {"license": "MIT"},
+        "1.2.4": {"license": "MIT"},
+        "1.3.0": {"license": "WTFPL"}
+      }}"#,
+    ),
+    "/gone-pkg" => Response::json(r#"{"versions": {}, "time": {"unpublished": {"time": "2016-03-23T00:00:00.000Z"}}}"#),
+    _ => Response::status(404),
+  })
+}
+
+#[test]
+fn unpublished_version_stands_in_the_nearest_one() {
+  let server = unpublished_registry();
+  let client = HttpClient::new(&None).unwrap();
+  let options = ResolveOptions::new(Ecosystem::Npm).with_npm_registry(registry(&server.base, None, ""));
+  let entries = [
+    entry("left-pad", "1.2.3"),
+    entry("left-pad", "0.9.0"),
+    entry("gone-pkg", "1.0.0"),
+  ];
+  let resolved = block_on(resolve(entries, &client, options));
+
+  // The closest lower version, else the closest higher one, marked in the version cell and the provenance
+  let lower = resolved[0].row.as_ref().unwrap();
+  assert_eq!(lower.version, "1.2.2 (pinned 1.2.3 unpublished; showing 1.2.2)");
+  assert_eq!(lower.license.as_deref(), Some("MIT"));
+  assert!(lower
+    .provenance
+    .contains(&"version: pinned 1.2.3 unpublished; showing 1.2.2".to_owned()));
+  assert!(resolved[0]
+    .warnings
+    .iter()
+    .any(|(_, warning)| warning == "left-pad@1.2.3 was unpublished, resolving 1.2.2 instead"));
+  let higher = resolved[1].row.as_ref().unwrap();
+  assert_eq!(higher.version, "1.2.2 (pinned 0.9.0 unpublished; showing 1.2.2)");
+
+  // Nothing left to stand in: no row, and a warning saying why
+  assert!(resolved[2].row.is_none());
+  assert!(
+    resolved[2]
+      .warnings
+      .iter()
+      .any(|(_, warning)| warning.contains("Packument for gone-pkg has no published versions")),
+    "{:?}",
+    resolved[2].warnings
+  );
+}
+
+#[test]
+fn strict_versions_fail_the_unpublished_pin() {
+  let server = unpublished_registry();
+  let client = HttpClient::new(&None).unwrap();
+  let registry = registry(&server.base, None, "").with_strict_versions(true);
+  let options = ResolveOptions::new(Ecosystem::Npm).with_npm_registry(registry);
+  let resolved = block_on(resolve([entry("left-pad", "1.2.3")], &client, options));
+
+  let row = resolved[0].row.as_ref().unwrap();
+  assert_eq!((row.version.as_str(), row.license.as_deref()), ("1.2.3", None));
+  assert_eq!(row.provenance, ["failed: pinned 1.2.3 unpublished"]);
+  assert_eq!(resolved[0].warnings[0].0, Category::FetchFailure);
+}
+
+// tests/fixtures/npm_registry: a package published from a pnpm monorepo with a `workspace:` peer left in
+#[test]
+fn leaked_workspace_peer_resolves_as_latest() {
+  const UI_KIT: &str = include_str!("fixtures/npm_registry/ui-kit.json");
+  let server = MockServer::start(|request| match request.path.as_str() {
+    "/@ourorg/ui-kit" => Response::json(UI_KIT),
+    "/@ourorg/tokens" => Response::json(include_str!("fixtures/npm_registry/tokens.json")),
+    _ => Response::status(404),
+  });
+  let ui_kit = serde_json::from_str::<serde_json::Value>(UI_KIT).unwrap();
+  let peers = ui_kit["versions"]["2.1.0"]["peerDependencies"].as_object().unwrap();
+  let specifier = peers["@ourorg/tokens"].as_str().unwrap();
+  assert_eq!(specifier, "workspace:^");
+
+  let client = HttpClient::new(&None).unwrap();
+  let options = ResolveOptions::new(Ecosystem::Npm).with_npm_registry(registry(&server.base, None, ""));
+  let entries = [entry("@ourorg/ui-kit", "2.1.0"), entry("@ourorg/tokens", specifier)];
+  let resolved = block_on(resolve(entries, &client, options));
+
+  // The latest dist-tag stands in, not the newest version nor a registry error over the specifier
+  let row = resolved[1].row.as_ref().unwrap();
+  assert_eq!(
+    row.version,
+    "1.4.0 (malformed upstream specifier workspace:^; showing latest)"
+  );
+  assert_eq!(row.license.as_deref(), Some("Apache-2.0"));
+  assert!(row
+    .provenance
+    .contains(&"version: malformed upstream specifier workspace:^; showing latest".to_owned()));
+
+  // Counted once per leaked specifier, the well-formed package not at all
+  let malformed = |idx: usize| {
+    resolved[idx]
+      .warnings
+      .iter()
+      .filter(|(category, _)| *category == Category::MalformedSpecifier)
+      .map(|(_, warning)| warning.clone())
+      .collect::<Vec<_>>()
+  };
+  assert_eq!(malformed(0), Vec::<String>::new());
+  assert_eq!(
+    malformed(1),
+    ["@ourorg/tokens@workspace:^ leaked from a pnpm workspace, resolving latest 1.4.0"]
+  );
+  assert!(!paths(&server).iter().any(|path| path.contains("workspace")));
+}
+
+// Two scopes on two registries in one run, GitHub Packages' bare metadata (no homepage, no repository) included,
+// and a scope nobody maps left on the default registry
+#[test]
+fn scopes_are_routed_to_their_registries() {
+  let document = |license: &'static str| move |_: &Request| Response::json(format!(r#"{{"license":"{}"}}"#, license));
+  let (github, acme, default) = (
+    MockServer::start(document("Apache-2.0")),
+    MockServer::start(document("BSD-3-Clause")),
+    MockServer::start(document("MIT")),
+  );
+  env::set_var("SCOPE_TEST_GH_TOKEN", "gh-token");
+  let npmrc = Npmrc::parse(
+    "app/.npmrc".to_owned(),
+    &format!(
+      "@ourorg:registry={0}\n//{1}/:_authToken=${{SCOPE_TEST_GH_TOKEN}}\n@acme:registry={2}\n",
+      github.base,
+      github.base.trim_start_matches("http://"),
+      default.base
+    ),
+    &Diagnostics::quiet(),
+  );
+  let registry = registry(&default.base, None, "")
+    .with_scope_registries(&Some(vec![format!("@acme={}/", acme.base)]), &[npmrc])
+    .unwrap();
+  // The flag wins over the .npmrc for @acme
+  assert_eq!(
+    registry.describe_scopes(),
+    [
+      format!("@acme = {} (scope_registry)", acme.base),
+      format!("@ourorg = {} (bearer auth) (app/.npmrc)", github.base),
+    ]
+  );
+
+  let client = HttpClient::new(&None).unwrap();
+  let entries = [
+    entry("@ourorg/ui", "1.0.0"),
+    entry("@acme/core", "1.0.0"),
+    entry("@other/util", "1.0.0"),
+  ];
+  let options = ResolveOptions::new(Ecosystem::Npm).with_npm_registry(registry);
+  let resolved = block_on(resolve(entries, &client, options));
+  let row = |idx: usize| resolved[idx].row.as_ref().unwrap();
+
+  assert_eq!(row(0).license.as_deref(), Some("Apache-2.0"));
+  assert_eq!((row(0).homepage.as_str(), row(0).license_url.as_deref()), ("", None));
+  assert!(row(0)
+    .provenance
+    .contains(&format!("metadata: npm registry {}", github.base)));
+  assert!(row(0)
+    .provenance
+    .contains(&"license file: no repository declared".to_owned()));
+  assert_eq!(row(1).license.as_deref(), Some("BSD-3-Clause"));
+  assert!(row(1)
+    .provenance
+    .contains(&format!("metadata: npm registry {}", acme.base)));
+  assert_eq!(row(2).license.as_deref(), Some("MIT"));
+  assert!(!row(2)
+    .provenance
+    .iter()
+    .any(|line| line.starts_with("metadata: npm registry")));
+
+  // Each registry was asked for its own packages only, the token went to GitHub Packages alone
+  let asked = |server: &MockServer| {
+    server
+      .requests()
+      .into_iter()
+      .map(|request| (request.path, request.headers.get("authorization").cloned()))
+      .collect::<Vec<_>>()
+  };
+  assert_eq!(
+    asked(&github),
+    [("/@ourorg/ui/1.0.0".to_owned(), Some("Bearer gh-token".to_owned()))]
+  );
+  assert_eq!(asked(&acme), [("/@acme/core/1.0.0".to_owned(), None)]);
+  assert_eq!(asked(&default), [("/@other/util/1.0.0".to_owned(), None)]);
+}
+
+// tests/fixtures/npm_registry/attestations: what the registry answers for a version published with provenance from
+// CI and for one published by hand
+const ATTESTED: &str = include_str!("fixtures/npm_registry/attestations/sigstore.json");
+const NOT_ATTESTED: &str = include_str!("fixtures/npm_registry/attestations/left-pad.json");
+
+fn attestation_registry() -> MockServer {
+  MockServer::start(|request| match request.path.as_str() {
+    "/-/npm/v1/attestations/sigstore@2.3.1" => Response::json(ATTESTED),
+    "/-/npm/v1/attestations/left-pad@1.3.0" => Response::json(NOT_ATTESTED),
+    "/-/npm/v1/attestations/garbled@1.0.0" => Response::json("<html>Service Unavailable</html>"),
+    "/-/npm/v1/attestations/down@1.0.0" => Response::status(503),
+    path if path.starts_with("/-/npm/v1/attestations/") => Response::status(404),
+    path => {
+      let name = path.trim_start_matches('/').split('/').next().unwrap_or_default();
+      Response::json(format!(r#"{{"name": "{}", "license": "MIT"}}"#, name))
+    },
+  })
+}
+
+#[test]
+fn attestation_responses_map_to_a_status() {
+  let server = attestation_registry();
+  let client = HttpClient::new(&None).unwrap();
+  let registry = registry(&server.base, None, "");
+  let status = |name: &str, version: &str| block_on(registry.attestation(&client, name, version)).to_string();
+
+  assert_eq!(status("sigstore", "2.3.1"), "attested");
+  assert_eq!(status("left-pad", "1.3.0"), "not attested");
+  assert_eq!(status("never-published", "1.0.0"), "not attested");
+  assert_eq!(status("garbled", "1.0.0"), "unknown");
+  assert_eq!(status("down", "1.0.0"), "unknown");
+}
+
+// --provenance-check: the status is a column of its own, a failing endpoint leaves the license alone
+#[test]
+fn failed_attestation_check_keeps_the_license() {
+  let server = attestation_registry();
+  let base = scratch_dir("attestation-check");
+  write(
+    &base.join("app/package.json"),
+    r#"{"dependencies": {"sigstore": "2.3.1", "left-pad": "1.3.0", "down": "1.0.0"}}"#,
+  );
+  let args = [
+    "app",
+    "--provenance-check",
+    "--format",
+    "json",
+    "--npm-registry",
+    &server.base,
+    "--final-retry-window",
+    "0",
+  ];
+  let output = depsfetch(&base, &args, &[]);
+  let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+  assert!(output.status.success(), "{}", stdout);
+
+  let mut rows = fs::read_to_string(base.join("deps_report.jsonl"))
+    .unwrap()
+    .lines()
+    .map(|line| {
+      let row = serde_json::from_str::<Value>(line).unwrap();
+      (row["name"].clone(), row["license"].clone(), row["attestation"].clone())
+    })
+    .collect::<Vec<_>>();
+  rows.sort_by_key(|(name, _, _)| name.to_string());
+  assert_eq!(
+    rows,
+    [
+      (json!("down"), json!("MIT"), json!("unknown")),
+      (json!("left-pad"), json!("MIT"), json!("not_attested")),
+      (json!("sigstore"), json!("MIT"), json!("attested")),
+    ]
+  );
+  assert!(
+    stdout.contains("Provenance attestations: attested: 1, not attested: 1, unknown: 1"),
+    "{}",
+    stdout
+  );
+  let _ = fs::remove_dir_all(&base);
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use common::{depsfetch, scratch_dir, write, MockServer, Response};
+use serde_json::Value;
+use std::fs;
+
+fn rows(path: &std::path::Path) -> Vec<Value> {
+  fs::read_to_string(path)
+    .unwrap()
+    .lines()
+    .map(|line| serde_json::from_str(line).unwrap())
+    .collect()
+}
+
+// A baseline taken online serves left-pad again; is-odd, added since, and the Go module fail to fetch without a
+// request and without stopping the run, and there is no final retry of them
+#[test]
+fn offline_run_sends_no_request() {
+  let registry = MockServer::start(|request| match request.path.as_str() {
+    "/left-pad/1.3.0" => Response::json(r#"{"name":"left-pad","version":"1.3.0","license":"WTFPL"}"#),
+    _ => Response::status(404),
+  });
+  let base = scratch_dir("offline");
+  write(
+    &base.join("repo/app/package.json"),
+    r#"{"dependencies": {"left-pad": "1.3.0"}}"#,
+  );
+  let online = [
+    "repo",
+    "--jsonl",
+    "baseline.jsonl",
+    "--npm-registry",
+    &registry.base,
+    "--final-retry-window",
+    "0",
+  ];
+  assert!(depsfetch(&base, &online, &[]).status.success());
+  assert_eq!(registry.requests().len(), 1);
+
+  write(
+    &base.join("repo/app/package.json"),
+    r#"{"dependencies": {"left-pad": "1.3.0", "is-odd": "3.0.1"}}"#,
+  );
+  write(
+    &base.join("repo/svc/go.mod"),
+    "module example.com/svc\n\ngo 1.22\n\nrequire golang.org/x/text v0.14.0\n",
+  );
+  let offline = [
+    "repo",
+    "--jsonl",
+    "rows.jsonl",
+    "--npm-registry",
+    &registry.base,
+    "--trust-baseline",
+    "baseline.jsonl",
+    "--offline",
+  ];
+  let output = depsfetch(&base, &offline, &[]);
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  assert!(
+    output.status.success(),
+    "{}{}",
+    stdout,
+    String::from_utf8_lossy(&output.stderr)
+  );
+  assert_eq!(registry.requests().len(), 1, "{:?}", registry.requests());
+  assert!(!stdout.contains("Final retry"), "{}", stdout);
+
+  let rows = rows(&base.join("rows.jsonl"));
+  assert_eq!(rows.len(), 1, "{:?}", rows);
+  assert_eq!(
+    (&rows[0]["name"], &rows[0]["license"]),
+    (&"left-pad".into(), &"WTFPL".into())
+  );
+  let log = fs::read_to_string(base.join("warnings.log")).unwrap();
+  for failed in [
+    format!("Request to {}/is-odd/3.0.1 not sent, --offline is set", registry.base),
+    "Can't fetch Go dependency golang.org/x/text@v0.14.0: Request to https://pkg.go.dev/golang.org/x/text?tab=licenses \
+     not sent, --offline is set"
+      .to_owned(),
+  ] {
+    assert!(log.contains(&failed), "{} not in\n{}", failed, log);
+  }
+  let _ = fs::remove_dir_all(&base);
+}
+
+// Link checks can only report every link as dead without the network
+#[test]
+fn offline_refuses_link_checks() {
+  let base = scratch_dir("offline-links");
+  for flag in ["--verify-links", "--fail-on-dead-links"] {
+    let output = depsfetch(&base, &[".", "--offline", flag], &[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+      stderr.contains("--verify-links checks every link over the network, it doesn't go with --offline"),
+      "{}",
+      stderr
+    );
+  }
+  let _ = fs::remove_dir_all(&base);
+}
@@ -0,0 +1,170 @@
+mod common;
+
+use common::{scratch_dir, write};
+use depsfetch::{
+  deps::patterns::{validate_patterns, NamespaceSet, PatternSet},
+  report::CanonicalMap,
+};
+use std::fs;
+
+fn patterns(values: &[&str]) -> Option<Vec<String>> {
+  Some(values.iter().map(|value| value.to_string()).collect())
+}
+
+#[test]
+fn every_malformed_pattern_is_reported_at_once() {
+  let exclude = patterns(&["node_modules", "(unclosed", "fine/.*"]);
+  let skip = patterns(&["^@internal/", "[z-a]"]);
+  let err = validate_patterns(&[("--exclude", &exclude), ("--skip", &skip)])
+    .unwrap_err()
+    .to_string();
+
+  assert!(err.starts_with("Failed to compile 2 regex pattern(s):"), "{}", err);
+  assert!(err.contains("--exclude `(unclosed`:"), "{}", err);
+  assert!(err.contains("--skip `[z-a]`:"), "{}", err);
+  // The regex error keeps its caret under the offending position, indented under its pattern
+  assert!(err.contains("\n    ") && err.contains('^'), "{}", err);
+  assert!(!err.contains("node_modules") && !err.contains("fine/"), "{}", err);
+}
+
+#[test]
+fn valid_or_missing_patterns_pass() {
+  let exclude = patterns(&["node_modules", "^vendor/"]);
+  assert!(validate_patterns(&[("--exclude", &exclude), ("--skip", &None)]).is_ok());
+  assert!(PatternSet::compile("--skip", &None).unwrap().is_empty());
+}
+
+#[test]
+fn pattern_set_reports_all_errors_of_its_option() {
+  let err = PatternSet::compile("--skip", &patterns(&["(", "ok", "*"]))
+    .err()
+    .unwrap()
+    .to_string();
+  assert!(err.starts_with("Failed to compile 2 regex pattern(s):"), "{}", err);
+  assert!(err.contains("--skip `(`") && err.contains("--skip `*`"), "{}", err);
+}
+
+#[test]
+fn namespace_errors_cover_both_options() {
+  let err = NamespaceSet::new(
+    &patterns(&["@ourorg", "ourorg", "@a/b"]),
+    &patterns(&["github.com/ourorg", "/abs", "a//b"]),
+  )
+  .err()
+  .unwrap()
+  .to_string();
+  assert!(err.starts_with("Invalid 4 namespace(s):"), "{}", err);
+  for bad in ["`ourorg`", "`@a/b`", "`/abs`", "`a//b`"] {
+    assert!(err.contains(bad), "{} missing from {}", bad, err);
+  }
+}
+
+// (name, skipped) against `--skip-scope @ourorg --skip-prefix github.com/ourorg`
+const NAMESPACE_CASES: &[(&str, bool)] = &[
+  ("@ourorg/ui", true),
+  ("@ourorg/ui/nested", true),
+  // Whole scope segments only
+  ("@ourorg-community/ui", false),
+  ("@ourorgx/ui", false),
+  ("@our/ui", false),
+  ("ourorg/ui", false),
+  ("@other/ourorg", false),
+  ("@ourorg", true),
+  // Case is ignored
+  ("@OurOrg/ui", true),
+  ("github.com/ourorg/api", true),
+  ("github.com/ourorg/api/v2", true),
+  ("github.com/ourorg", true),
+  ("github.com/OurOrg/api", true),
+  // Whole path segments only, from the start
+  ("github.com/ourorg-tools/api", false),
+  ("github.com/ourorgx", false),
+  ("github.com/our", false),
+  ("gitlab.com/github.com/ourorg/api", false),
+  ("golang.org/x/text", false),
+];
+
+#[test]
+fn namespaces_match_on_segment_boundaries() {
+  let given = [
+    (patterns(&["@ourorg"]), patterns(&["github.com/ourorg"])),
+    // A trailing `/*` or `/` names the same namespace
+    (patterns(&["@ourorg/*"]), patterns(&["github.com/ourorg/*"])),
+    (patterns(&[" @OURORG "]), patterns(&["github.com/ourorg/"])),
+  ];
+  for (scopes, prefixes) in given {
+    let namespaces = NamespaceSet::new(&scopes, &prefixes).unwrap();
+    for (name, skipped) in NAMESPACE_CASES {
+      assert_eq!(
+        namespaces.is_match(name),
+        *skipped,
+        "{} with {:?} {:?}",
+        name,
+        scopes,
+        prefixes
+      );
+    }
+  }
+}
+
+#[test]
+fn skipped_names_are_credited_to_their_option() {
+  let namespaces = NamespaceSet::new(
+    &patterns(&["@ourorg", "@tools"]),
+    &patterns(&["github.com/ourorg", "github.com/ourorg/api"]),
+  )
+  .unwrap();
+  for name in [
+    "@ourorg/ui",
+    "@ourorg/ui",
+    "@OurOrg/cli",
+    "github.com/ourorg/api",
+    "github.com/ourorg/web",
+  ] {
+    assert!(namespaces.is_match(name));
+  }
+  assert!(!namespaces.is_match("@ourorg-community/ui"));
+  // Distinct names, the first namespace that matches gets them
+  assert_eq!(namespaces.filtered_counts("--skip-scope"), [2, 0]);
+  assert_eq!(namespaces.filtered_counts("--skip-prefix"), [2, 0]);
+}
+
+#[test]
+fn wildcards_other_than_a_trailing_segment_are_refused() {
+  let err = NamespaceSet::new(
+    &patterns(&["@ourorg*", "@our*org/*"]),
+    &patterns(&["github.com/ourorg*", "github.com/*/api"]),
+  )
+  .err()
+  .unwrap()
+  .to_string();
+  assert!(err.starts_with("Invalid 4 namespace(s):"), "{}", err);
+  for bad in [
+    "`@ourorg*`",
+    "`@our*org/*`",
+    "`github.com/ourorg*`",
+    "`github.com/*/api`",
+  ] {
+    assert!(err.contains(bad), "{} missing from {}", bad, err);
+  }
+}
+
+#[test]
+fn canonical_map_reports_every_bad_glob_with_file_and_key() {
+  let dir = scratch_dir("patterns-canonical-map");
+  let path = dir.join("canonical.toml");
+  write(
+    &path,
+    "\"react\" = \"React\"\n\"@babel/*\" = \"Babel\"\n\"[abc\" = \"Broken\"\n\"x[\" = \"Also broken\"\n",
+  );
+  let path = path.display().to_string();
+  let err = CanonicalMap::load(&path).err().unwrap().to_string();
+  assert!(
+    err.starts_with("Failed to compile 2 glob pattern(s) in canonical map:"),
+    "{}",
+    err
+  );
+  assert!(err.contains(&format!("{}: key `[abc`", path)), "{}", err);
+  assert!(err.contains(&format!("{}: key `x[`", path)), "{}", err);
+  let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use common::{depsfetch, scratch_dir, write, MockServer, Response};
+use serde_json::{json, Value};
+use std::{fs, path::PathBuf};
+
+// tests/fixtures/platform: an app whose lockfile has left-pad, mac-helper (darwin only) and the optional fsevents
+// (darwin only too). The registry says the same about them.
+fn fixture(file: &str) -> String {
+  let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    .join("tests/fixtures/platform/app")
+    .join(file);
+  fs::read_to_string(path).unwrap()
+}
+
+// The app scanned for the target, returning what the run printed and the JSON lines rows as (name, platform)
+fn scan(name: &str, extra: &[&str]) -> (String, Vec<(String, Option<String>)>) {
+  let server = MockServer::start(|request| {
+    let name = request
+      .path
+      .trim_start_matches('/')
+      .split('/')
+      .next()
+      .unwrap_or_default();
+    let mut document = json!({"name": name, "license": "MIT", "homepage": format!("https://example.com/{}", name)});
+    if name == "fsevents" || name == "mac-helper" {
+      document["os"] = json!(["darwin"]);
+    }
+    Response::json(document.to_string())
+  });
+  let base = scratch_dir(name);
+  for file in ["package.json", "package-lock.json"] {
+    write(&base.join("app").join(file), &fixture(file));
+  }
+  let mut args = vec![
+    "app",
+    "--format",
+    "json",
+    "--npm-registry",
+    &server.base,
+    "--final-retry-window",
+    "0",
+  ];
+  args.extend(extra);
+  let output = depsfetch(&base, &args, &[]);
+  let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+  assert!(
+    output.status.success(),
+    "{}{}",
+    stdout,
+    String::from_utf8_lossy(&output.stderr)
+  );
+
+  let mut rows = fs::read_to_string(base.join("deps_report.jsonl"))
+    .unwrap()
+    .lines()
+    .map(|line| {
+      let row = serde_json::from_str::<Value>(line).unwrap();
+      let platform = row["platform"].as_str().map(str::to_owned);
+      (row["name"].as_str().unwrap().to_owned(), platform)
+    })
+    .collect::<Vec<_>>();
+  rows.sort();
+  let _ = fs::remove_dir_all(&base);
+  (stdout, rows)
+}
+
+fn kept(name: &str, platform: Option<&str>) -> (String, Option<String>) {
+  (name.to_owned(), platform.map(str::to_owned))
+}
+
+// On Linux the optional fsevents is left out and listed as such; mac-helper is a regular dependency, so it stays
+// with its restriction shown
+#[test]
+fn optional_package_for_another_os_is_excluded() {
+  let (stdout, rows) = scan("platform-linux", &["--target-platform", "linux-x64"]);
+
+  assert_eq!(rows, [kept("left-pad", None), kept("mac-helper", Some("os: darwin"))]);
+  assert!(
+    stdout.contains("Excluded 1 package(s) not installable on linux-x64:\n  fsevents@2.3.3 (optional): os: darwin"),
+    "{}",
+    stdout
+  );
+}
+
+// --strict-platform holds every dependency to the target, not only optional ones
+#[test]
+fn strict_platform_excludes_regular_dependencies_too() {
+  let (stdout, rows) = scan(
+    "platform-strict",
+    &["--target-platform", "linux-x64", "--strict-platform"],
+  );
+
+  assert_eq!(rows, [kept("left-pad", None)]);
+  assert!(
+    stdout.contains("Excluded 2 package(s) not installable on linux-x64:"),
+    "{}",
+    stdout
+  );
+  assert!(stdout.contains("  fsevents@2.3.3 (optional): os: darwin"), "{}", stdout);
+  assert!(
+    stdout.contains("  mac-helper@1.0.0 (runtime): os: darwin"),
+    "{}",
+    stdout
+  );
+}
+
+// On the platform the packages are for nothing is excluded, the restrictions are still shown
+#[test]
+fn matching_target_keeps_every_package() {
+  let (stdout, rows) = scan(
+    "platform-darwin",
+    &["--target-platform", "darwin-arm64", "--strict-platform"],
+  );
+
+  assert_eq!(
+    rows,
+    [
+      kept("fsevents", Some("os: darwin")),
+      kept("left-pad", None),
+      kept("mac-helper", Some("os: darwin")),
+    ]
+  );
+  assert!(!stdout.contains("Excluded"), "{}", stdout);
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use common::{depsfetch, scratch_dir, write, MockServer, Response};
+use serde_json::{json, Value};
+use std::{fs, path::PathBuf, process::Output};
+
+fn fixture() -> String {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    .join("tests/fixtures/post_process/legal.sh")
+    .display()
+    .to_string()
+}
+
+// Three npm packages piped through the fixture script in `mode`. Returns the run and the JSON rows it wrote.
+fn scan(name: &str, mode: &str, extra: &[&str]) -> (Output, Vec<Value>) {
+  let server = MockServer::start(|request| {
+    let name = request
+      .path
+      .trim_start_matches('/')
+      .split('/')
+      .next()
+      .unwrap_or_default();
+    let license = match name {
+      "internal-a" => "LicenseRef-Internal-A",
+      _ => "ISC",
+    };
+    Response::json(json!({"name": name, "version": "1.0.0", "license": license}).to_string())
+  });
+  let base = scratch_dir(name);
+  write(
+    &base.join("app/package.json"),
+    &json!({"dependencies": {"internal-a": "1.0.0", "drop-me": "1.0.0", "kept": "1.0.0"}}).to_string(),
+  );
+  let script = fixture();
+  let mut args = vec![
+    "app",
+    "--npm-registry",
+    &server.base,
+    "--format",
+    "json",
+    "--jsonl",
+    "rows.jsonl",
+    "--post-process",
+    &script,
+    "--final-retry-window",
+    "0",
+  ];
+  args.extend(extra);
+  let output = depsfetch(&base, &args, &[("FIXTURE_MODE", mode)]);
+  let rows = fs::read_to_string(base.join("rows.jsonl"))
+    .unwrap_or_default()
+    .lines()
+    .map(|line| serde_json::from_str(line).unwrap())
+    .collect();
+  let _ = fs::remove_dir_all(&base);
+  (output, rows)
+}
+
+fn log(output: &Output) -> String {
+  format!(
+    "{}{}",
+    String::from_utf8_lossy(&output.stdout),
+    String::from_utf8_lossy(&output.stderr)
+  )
+}
+
+#[test]
+fn script_rewrites_drops_and_adds_rows() {
+  let (output, rows) = scan("post-process", "rewrite", &[]);
+  assert!(output.status.success(), "{}", log(&output));
+  assert!(
+    log(&output).contains("Post-process kept or added 3 row(s) out of 3"),
+    "{}",
+    log(&output)
+  );
+
+  let rows = rows
+    .iter()
+    .map(|row| (row["name"].as_str().unwrap(), row["license"].as_str().unwrap(), row))
+    .collect::<Vec<_>>();
+  let summary = rows
+    .iter()
+    .map(|(name, license, _)| (*name, *license))
+    .collect::<Vec<_>>();
+  assert_eq!(
+    summary,
+    [("internal-a", "MIT"), ("kept", "ISC"), ("vendored-sdk", "Apache-2.0")]
+  );
+  // Only the row the script made up is marked as its own
+  for (name, _, row) in &rows {
+    let marked = row["provenance"]
+      .as_array()
+      .unwrap()
+      .contains(&json!("source: post-process"));
+    assert_eq!(marked, *name == "vendored-sdk", "{}", row);
+  }
+}
+
+#[test]
+fn script_failures_stop_the_run() {
+  let cases = [
+    ("wrong-schema", "speaks schema version 99, expected 1"),
+    ("fail", "failed: exit status: 3"),
+    ("garbage", "row 1 is not a valid report row"),
+  ];
+  for (mode, expected) in cases {
+    let (output, rows) = scan(&format!("post-process-{}", mode), mode, &[]);
+    assert!(!output.status.success(), "{}: {}", mode, log(&output));
+    assert!(log(&output).contains(expected), "{}: {}", mode, log(&output));
+    assert!(rows.is_empty(), "{}", mode);
+  }
+
+  let (output, _) = scan("post-process-hang", "hang", &["--post-process-timeout", "1"]);
+  assert!(!output.status.success());
+  assert!(log(&output).contains("did not finish within 1s"), "{}", log(&output));
+}
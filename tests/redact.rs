@@ -0,0 +1,126 @@
+mod common;
+
+use common::{contains, contents, depsfetch, files, scratch_dir, write, MockServer, Response};
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, fs, path::Path};
+
+const KEY: &str = "auditor-share-key";
+// Everything that would give the internal packages away
+const INTERNAL: &[&str] = &["@ourorg", "ourorg", "ui-kit", "git.internal.example"];
+
+fn registry() -> MockServer {
+  MockServer::start(|request| {
+    let path = request.path.replace("%2f", "/").replace("%2F", "/");
+    match path.trim_start_matches('/') {
+      path if path.starts_with("@ourorg/ui-kit") => Response::json(
+        json!({"name": "@ourorg/ui-kit", "version": "1.0.0", "license": "MIT",
+          "homepage": "https://git.internal.example/ourorg/ui-kit"})
+        .to_string(),
+      ),
+      path if path.starts_with("left-pad") => {
+        Response::json(json!({"name": "left-pad", "version": "1.3.0", "license": "WTFPL"}).to_string())
+      },
+      _ => Response::status(404),
+    }
+  })
+}
+
+// A redacted scan of src/app written to `out`, the redaction map to `private`. Returns the JSON rows by name.
+fn scan(base: &Path, out: &str, server: &MockServer, extra: &[&str]) -> BTreeMap<String, Value> {
+  let out = base.join(out);
+  fs::create_dir_all(&out).unwrap();
+  fs::create_dir_all(base.join("private")).unwrap();
+  let map = base
+    .join("private")
+    .join(format!("{}-map.json", out.file_name().unwrap().to_string_lossy()));
+  let map = map.display().to_string();
+  let app = base.join("src/app").display().to_string();
+  let mut args = vec![
+    app.as_str(),
+    "--npm-registry",
+    &server.base,
+    "--redact-internal",
+    "--redact-key",
+    KEY,
+    "--redaction-map",
+    &map,
+    "--self-audit",
+    "--jsonl",
+    "rows.jsonl",
+    "--csv",
+    "rows.csv",
+    "--graph",
+    "graph.dot",
+    "--bundle",
+    "share.zip",
+    "--final-retry-window",
+    "0",
+  ];
+  args.extend(extra);
+  let output = depsfetch(&out, &args, &[]);
+  assert!(
+    output.status.success(),
+    "{}{}",
+    String::from_utf8_lossy(&output.stdout),
+    String::from_utf8_lossy(&output.stderr)
+  );
+
+  // Every file the run wrote: the report, the streams, the graph, the bundle and what's in it, the checksums
+  let written = files(&out);
+  assert!(written.len() >= 5, "{:?}", written);
+  for path in &written {
+    let content = contents(path);
+    for name in INTERNAL {
+      assert!(!contains(&content, name), "{} names {}", path.display(), name);
+    }
+  }
+  fs::read_to_string(out.join("rows.jsonl"))
+    .unwrap()
+    .lines()
+    .map(|line| serde_json::from_str::<Value>(line).unwrap())
+    .map(|row| (row["name"].as_str().unwrap().to_owned(), row))
+    .collect()
+}
+
+#[test]
+fn internal_names_stay_out_of_every_output() {
+  let server = registry();
+  let base = scratch_dir("redact");
+  // No license field, so the self audit has something to say about the app
+  write(
+    &base.join("src/app/package.json"),
+    &json!({"name": "@ourorg/web", "version": "1.0.0",
+      "dependencies": {"@ourorg/ui-kit": "1.0.0", "left-pad": "1.3.0"}})
+    .to_string(),
+  );
+
+  let first = scan(&base, "first", &server, &["--format", "json"]);
+  // Same key, same pseudonyms, whatever the format
+  let second_format = if cfg!(feature = "xlsx") { "xlsx" } else { "csv" };
+  let second = scan(&base, "second", &server, &["--format", second_format]);
+  assert_eq!(first.keys().collect::<Vec<_>>(), second.keys().collect::<Vec<_>>());
+
+  // The external package as it is, the internal one under its pseudonym with its license and version
+  assert_eq!(first["left-pad"]["license"], "WTFPL");
+  let pseudonyms = first
+    .keys()
+    .filter(|name| name.starts_with("internal-"))
+    .collect::<Vec<_>>();
+  assert_eq!(pseudonyms.len(), 1, "{:?}", first.keys());
+  let internal = &first[pseudonyms[0]];
+  assert_eq!(
+    (internal["version"].as_str(), internal["license"].as_str()),
+    (Some("1.0.0"), Some("MIT"))
+  );
+  assert_eq!(internal["homepage"], "");
+
+  // Only the local map says what it stands for
+  let map: BTreeMap<String, String> =
+    serde_json::from_str(&fs::read_to_string(base.join("private/first-map.json")).unwrap()).unwrap();
+  assert_eq!(map[pseudonyms[0]], "@ourorg/ui-kit");
+  assert_eq!(
+    fs::read_to_string(base.join("private/first-map.json")).unwrap(),
+    fs::read_to_string(base.join("private/second-map.json")).unwrap()
+  );
+  let _ = fs::remove_dir_all(&base);
+}
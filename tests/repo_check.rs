@@ -0,0 +1,75 @@
+use depsfetch::report::repo_mismatch;
+
+// (package, declared repository, repository missing, flag)
+const CASES: &[(&str, &str, bool, Option<&str>)] = &[
+  // Owner and scope the same up to case and punctuation, or one within the other
+  ("@acme/utils", "https://github.com/acme/utils", false, None),
+  ("@Acme/utils", "https://github.com/ACME-inc/utils", false, None),
+  (
+    "@sentry/browser",
+    "git+https://github.com/getsentry/sentry-javascript.git",
+    false,
+    None,
+  ),
+  ("@vue/compiler-sfc", "https://github.com/vuejs/core", false, None),
+  ("@fluentui/react", "git@github.com:microsoft/fluentui.git", false, None),
+  // The allowlist
+  (
+    "@types/node",
+    "https://github.com/DefinitelyTyped/DefinitelyTyped",
+    false,
+    None,
+  ),
+  (
+    "@google-cloud/storage",
+    "https://github.com/googleapis/nodejs-storage",
+    false,
+    None,
+  ),
+  (
+    "@vscode/test-electron",
+    "https://github.com/microsoft/vscode-test",
+    false,
+    None,
+  ),
+  // Allowed for its own scope only
+  (
+    "@types/node",
+    "https://github.com/microsoft/TypeScript",
+    false,
+    Some("scope @types published from github.com/microsoft"),
+  ),
+  (
+    "@acme/utils",
+    "https://github.com/evil-org/utils",
+    false,
+    Some("scope @acme published from github.com/evil-org"),
+  ),
+  // Nothing to compare: unscoped, elsewhere than GitHub, no owner in the URL
+  ("left-pad", "https://github.com/someone-else/left-pad", false, None),
+  ("@acme/utils", "https://gitlab.com/other/utils", false, None),
+  ("@acme/utils", "https://github.com/", false, None),
+  ("@acme/utils", "", false, None),
+  // A missing repository is flagged whatever the names
+  (
+    "@acme/utils",
+    "https://github.com/acme/utils",
+    true,
+    Some("declared repository not found"),
+  ),
+  ("left-pad", "", true, Some("declared repository not found")),
+];
+
+#[test]
+fn repo_mismatch_flags() {
+  for (package, repository, missing, expected) in CASES {
+    assert_eq!(
+      repo_mismatch(package, repository, *missing).as_deref(),
+      *expected,
+      "{} from {:?} (missing: {})",
+      package,
+      repository,
+      missing
+    );
+  }
+}
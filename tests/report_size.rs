@@ -0,0 +1,78 @@
+#![cfg(feature = "xlsx")]
+
+mod common;
+
+use common::{report_row, scratch_dir};
+use depsfetch::{
+  report::{ReportWriter, ResolvedRow, Section, WriterMessage},
+  types::{Ecosystem, ReportRow},
+};
+use std::fs;
+
+const ROWS: usize = 50_000;
+// Monorepos publishing many packages, all with the repository's homepage and license link
+const PACKAGES_PER_REPO: usize = 100;
+// With --constant-memory and --no-hyperlink-format, the measured size plus some slack for libxlsxwriter versions
+const BYTE_BUDGET: u64 = 1_300_000;
+
+// A synthetic transitive report of ROWS npm packages
+fn rows() -> Vec<ReportRow> {
+  (0..ROWS)
+    .map(|idx| {
+      let repo = idx / PACKAGES_PER_REPO;
+      ReportRow {
+        homepage: format!("https://github.com/example-org/monorepo-{}#readme", repo),
+        license_url: Some(format!(
+          "https://github.com/example-org/monorepo-{}/blob/HEAD/LICENSE",
+          repo
+        )),
+        ..report_row(
+          Ecosystem::Npm,
+          &format!("@scope-{}/package-{}", repo, idx),
+          &format!("1.{}.{}", idx % 100, idx % 7),
+          Some(["MIT", "Apache-2.0", "GPL-3.0-only", "BSD-3-Clause"][repo % 4]),
+        )
+      }
+    })
+    .collect()
+}
+
+// Writes the rows on a Web sheet with a Summary, like a scan would; returns the workbook's size in bytes
+async fn write(path: &str, configure: impl FnOnce(ReportWriter) -> ReportWriter) -> u64 {
+  let (sender, handle) = configure(ReportWriter::new(path).unwrap()).spawn();
+  sender
+    .send(WriterMessage::Section(Section::Dependencies("Web")))
+    .unwrap();
+  for row in rows() {
+    sender
+      .send(WriterMessage::Row(Box::new(ResolvedRow { sheet: "Web", row })))
+      .unwrap();
+  }
+  sender
+    .send(WriterMessage::Section(Section::Summary("Summary")))
+    .unwrap();
+  sender.send(WriterMessage::Finish).unwrap();
+  handle.await.unwrap().unwrap();
+  fs::metadata(path).unwrap().len()
+}
+
+// The size options on the fixture: the default workbook measured 1,784,576 bytes, with --constant-memory and
+// --no-hyperlink-format 1,195,370 (33% smaller)
+#[tokio::test]
+async fn size_options_keep_the_workbook_under_budget() {
+  let dir = scratch_dir("report-size");
+  fs::create_dir_all(&dir).unwrap();
+  let default = write(&dir.join("default.xlsx").display().to_string(), |writer| writer).await;
+  let optimized = write(&dir.join("optimized.xlsx").display().to_string(), |writer| {
+    writer.with_constant_memory().without_hyperlink_format()
+  })
+  .await;
+  assert!(optimized < BYTE_BUDGET, "{} bytes", optimized);
+  assert!(
+    optimized * 100 < default * 70,
+    "{} bytes against {} without the options",
+    optimized,
+    default
+  );
+  let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,166 @@
+use depsfetch::{
+  cache::BranchCache,
+  diagnostics::Category,
+  http::{BackendFuture, HttpResponse, MetadataBackend, MetadataRequest},
+  resolve::{resolve, ResolveOptions},
+  types::{DepScope, DepsEntry, Ecosystem},
+};
+use reqwest::StatusCode;
+use std::{
+  collections::BTreeSet,
+  sync::{
+    atomic::{AtomicUsize, Ordering::SeqCst},
+    Arc, Mutex,
+  },
+  time::Duration,
+};
+use tokio::sync::Semaphore;
+
+const REPO: &str = "https://github.com/o/r";
+
+// crates.io, npm and GitHub as a fixture: serde is MIT, left-pad's repository has its license on `develop`, the
+// default branch; nothing else exists. Every URL asked for is kept.
+#[derive(Default)]
+struct Fixture(Mutex<Vec<String>>);
+
+impl Fixture {
+  fn asked(&self, prefix: &str) -> usize {
+    self
+      .0
+      .lock()
+      .unwrap()
+      .iter()
+      .filter(|url| url.starts_with(prefix))
+      .count()
+  }
+}
+
+impl MetadataBackend for Fixture {
+  fn fetch<'a>(&'a self, request: MetadataRequest<'a>) -> BackendFuture<'a> {
+    self.0.lock().unwrap().push(request.url.to_owned());
+    let ok = |body: &str| HttpResponse::new(StatusCode::OK, request.url, body.to_owned());
+    let mut response = match request.url {
+      url if url.ends_with("/serde/1.0.0") => ok(r#"{"version": {"num": "1.0.0", "license": "MIT"}}"#),
+      url if url.ends_with("/left-pad/1.3.0") => {
+        ok(r#"{"license": "MIT", "repository": "git+https://github.com/o/r.git"}"#)
+      },
+      "https://api.github.com/repos/o/r" => ok(r#"{"default_branch": "develop"}"#),
+      REPO => ok(""),
+      "https://github.com/o/r/blob/develop/LICENSE" => {
+        ok("MIT License\n\nPermission is hereby granted, free of charge")
+      },
+      _ => HttpResponse::new(StatusCode::NOT_FOUND, request.url, ""),
+    };
+    response.content_type = Some("text/plain".to_owned());
+    Box::pin(async move { Ok(response) })
+  }
+}
+
+// Every crate is MIT, answered after a while; the most requests ever in flight at once are kept
+#[derive(Default)]
+struct Slow {
+  in_flight: AtomicUsize,
+  most: AtomicUsize,
+}
+
+impl MetadataBackend for Slow {
+  fn fetch<'a>(&'a self, request: MetadataRequest<'a>) -> BackendFuture<'a> {
+    Box::pin(async move {
+      let now = self.in_flight.fetch_add(1, SeqCst) + 1;
+      self.most.fetch_max(now, SeqCst);
+      tokio::time::sleep(Duration::from_millis(20)).await;
+      self.in_flight.fetch_sub(1, SeqCst);
+      let body = r#"{"version": {"num": "1.0.0", "license": "MIT"}}"#;
+      Ok(HttpResponse::new(StatusCode::OK, request.url, body.to_owned()))
+    })
+  }
+}
+
+fn entry(name: &str, version: &str) -> DepsEntry {
+  DepsEntry {
+    name: name.to_owned(),
+    version: version.to_owned(),
+    scope: DepScope::Runtime,
+    sources: BTreeSet::new(),
+  }
+}
+
+fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+  tokio::runtime::Runtime::new().unwrap().block_on(future)
+}
+
+// What went wrong with an entry is in its warnings, the call itself doesn't fail
+#[test]
+fn failures_are_warnings() {
+  let options = ResolveOptions::new(Ecosystem::Cargo).with_permits(Arc::new(Semaphore::new(4)));
+  let resolved = block_on(resolve(
+    [entry("serde", "1.0.0"), entry("missing", "1.0.0")],
+    &Fixture::default(),
+    options,
+  ));
+
+  let serde = resolved[0].row.as_ref().unwrap();
+  assert_eq!(serde.license.as_deref(), Some("MIT"));
+  assert!(resolved[0].warnings.is_empty());
+  assert_eq!(resolved[1].entry.name, "missing");
+  assert_eq!(resolved[1].row.as_ref().unwrap().license, None);
+  assert_eq!(resolved[1].warnings[0].0, Category::FetchFailure);
+}
+
+// Without a cache of the caller's every call looks the default branch up again; with one, calls share it and the
+// caller has what was found
+#[test]
+fn branch_cache_comes_from_the_caller() {
+  let backend = Fixture::default();
+  let npm = || ResolveOptions::new(Ecosystem::Npm);
+  for _ in 0..2 {
+    let resolved = block_on(resolve([entry("left-pad", "1.3.0")], &backend, npm()));
+    let row = resolved[0].row.as_ref().unwrap();
+    assert_eq!(
+      row.license_url.as_deref(),
+      Some("https://github.com/o/r/blob/develop/LICENSE")
+    );
+  }
+  assert_eq!(backend.asked("https://api.github.com/repos/o/r"), 2);
+
+  let backend = Fixture::default();
+  let branches = Arc::new(BranchCache::default());
+  for _ in 0..2 {
+    let options = npm().with_branch_cache(Arc::clone(&branches));
+    block_on(resolve([entry("left-pad", "1.3.0")], &backend, options));
+  }
+  assert_eq!(backend.asked("https://api.github.com/repos/o/r"), 1);
+  assert_eq!(branches.get(REPO).as_deref(), Some("develop"));
+  // An in-memory cache has nowhere to be saved to
+  branches.save().unwrap();
+}
+
+// As many entries at once as the caller's permits allow, one at a time without them; results in the entries' order
+#[test]
+fn entries_resolve_side_by_side_up_to_the_permits() {
+  let names = (0..9).map(|idx| format!("crate-{}", idx)).collect::<Vec<_>>();
+  for (permits, most) in [(None, 1), (Some(3), 3)] {
+    let backend = Slow::default();
+    let options = match permits {
+      Some(permits) => ResolveOptions::new(Ecosystem::Cargo).with_permits(Arc::new(Semaphore::new(permits))),
+      None => ResolveOptions::new(Ecosystem::Cargo),
+    };
+    let resolved = block_on(resolve(
+      names.iter().map(|name| entry(name, "1.0.0")),
+      &backend,
+      options,
+    ));
+
+    assert_eq!(backend.most.load(SeqCst), most);
+    assert_eq!(
+      resolved
+        .iter()
+        .map(|resolved| resolved.entry.name.clone())
+        .collect::<Vec<_>>(),
+      names
+    );
+    assert!(resolved
+      .iter()
+      .all(|resolved| resolved.row.as_ref().unwrap().license.as_deref() == Some("MIT")));
+  }
+}
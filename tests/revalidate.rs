@@ -0,0 +1,99 @@
+use depsfetch::{
+  report::{Revalidated, Revalidation},
+  types::{Ecosystem, ReportRow},
+};
+
+fn row(name: &str, version: &str, license: Option<&str>) -> ReportRow {
+  serde_json::from_value(serde_json::json!({
+    "ecosystem": "npm",
+    "name": name,
+    "version": version,
+    "homepage": "",
+    "license": license,
+  }))
+  .unwrap()
+}
+
+fn sample(percent: f64, seed: u64) -> Vec<usize> {
+  let revalidation = Revalidation::new(percent, Some(seed)).unwrap();
+  (0..400)
+    .filter(|idx| revalidation.is_sampled(Ecosystem::Npm, &format!("pkg-{}", idx), "1.0.0"))
+    .collect()
+}
+
+#[test]
+fn draws_depend_on_the_seed_alone() {
+  // The same seed draws the same packages, another seed others, about a quarter either way
+  assert_eq!(sample(25.0, 7), sample(25.0, 7));
+  assert_ne!(sample(25.0, 7), sample(25.0, 8));
+  assert!((70..130).contains(&sample(25.0, 7).len()));
+  // A larger share draws what a smaller one did, and more
+  assert!(sample(25.0, 7).iter().all(|idx| sample(50.0, 7).contains(idx)));
+  assert!(sample(0.0, 7).is_empty());
+  assert_eq!(sample(100.0, 7).len(), 400);
+
+  // The version and ecosystem are part of the draw
+  let revalidation = Revalidation::new(50.0, Some(7)).unwrap();
+  let drawn = |ecosystem, version| {
+    (0..64)
+      .map(|idx| revalidation.is_sampled(ecosystem, &format!("pkg-{}", idx), version))
+      .collect::<Vec<_>>()
+  };
+  assert_ne!(drawn(Ecosystem::Npm, "1.0.0"), drawn(Ecosystem::Npm, "1.0.1"));
+  assert_ne!(drawn(Ecosystem::Npm, "1.0.0"), drawn(Ecosystem::Cargo, "1.0.0"));
+
+  assert_eq!(
+    Revalidation::new(12.5, Some(42)).unwrap().describe(),
+    "12.5% of baseline rows, seed 42"
+  );
+  for percent in [-1.0, 120.0, f64::NAN] {
+    assert!(Revalidation::new(percent, None).is_err(), "{}", percent);
+  }
+}
+
+#[test]
+fn fresh_rows_are_compared_with_the_held_ones() {
+  let revalidation = Revalidation::new(100.0, Some(7)).unwrap();
+  revalidation.hold(&["Web"], row("left-pad", "1.3.0", Some("MIT")));
+  revalidation.hold(&["Web"], row("is-odd", "3.0.1", Some("MIT")));
+  revalidation.hold(&["Web"], row("gone", "1.0.0", Some("ISC")));
+  revalidation.hold(&["Web", "Api"], row("lost", "2.0.0", Some("MIT")));
+  revalidation.hold(&["Web"], row("chalk", "5.0.0", Some("MIT")));
+
+  // The publisher fixed the license: the fresh row goes out with what the baseline had
+  match revalidation.compare(row("left-pad", "1.3.0", Some("WTFPL"))) {
+    Revalidated::Drifted(fresh, before) => {
+      assert_eq!((fresh.license.as_deref(), before.as_str()), (Some("WTFPL"), "MIT"))
+    },
+    _ => panic!("left-pad didn't drift"),
+  }
+  // A version note doesn't hide the held row
+  assert!(matches!(
+    revalidation.compare(row(
+      "is-odd",
+      "3.0.1 (pinned 3.0.0 unpublished; showing 3.0.1)",
+      Some("MIT")
+    )),
+    Revalidated::Fresh(_)
+  ));
+  // No license found this time, the baseline's row stands
+  match revalidation.compare(row("gone", "1.0.0", None)) {
+    Revalidated::Kept(kept) => assert_eq!(kept.license.as_deref(), Some("ISC")),
+    _ => panic!("gone wasn't kept"),
+  }
+  // Never held back
+  assert!(matches!(
+    revalidation.compare(row("chalk", "4.0.0", Some("MIT"))),
+    Revalidated::Fresh(_)
+  ));
+
+  // What no fresh row came for goes out as the baseline had it, with its sheets
+  let unanswered = revalidation.unanswered();
+  let names = unanswered
+    .iter()
+    .map(|(sheets, row)| (row.name.as_str(), sheets.clone()))
+    .collect::<Vec<_>>();
+  assert_eq!(names, [("chalk", vec!["Web"]), ("lost", vec!["Web", "Api"])]);
+  assert!(revalidation.unanswered().is_empty());
+  assert_eq!(revalidation.counts(), (5, 1));
+}
@@ -0,0 +1,204 @@
+mod common;
+
+use common::{depsfetch, scratch_dir, write, MockServer, Response};
+use depsfetch::{
+  report::{CollapsedRows, ReportLine, Schema, BUNDLE, PROJECTS, REPORT},
+  types::{AttestationStatus, DepScope, Ecosystem, LicenseConfidence, LicenseFlag, LinkStatus, ReportRow},
+};
+use serde_json::{json, Value};
+use std::{fs, io::Read};
+
+// A scan of three npm packages, two of them from one repository, against a registry that answers for all
+fn scan(name: &str, extra: &[&str]) -> (Vec<Value>, Value) {
+  let server = MockServer::start(|request| {
+    let name = request
+      .path
+      .trim_start_matches('/')
+      .split('/')
+      .next()
+      .unwrap_or_default()
+      .to_owned();
+    // A homepage and no repository, so nothing goes to GitHub
+    let homepage = match name.as_str() {
+      "is-even" | "is-odd" => "https://github.com/o/numbers#readme",
+      _ => "https://github.com/o/left-pad#readme",
+    };
+    Response::json(json!({"name": name, "version": "1.0.0", "license": "MIT", "homepage": homepage}).to_string())
+  });
+  let base = scratch_dir(name);
+  write(
+    &base.join("app/package.json"),
+    r#"{"dependencies": {"left-pad": "1.0.0", "is-odd": "1.0.0", "is-even": "1.0.0"}}"#,
+  );
+  let mut args = vec![
+    "app",
+    "--format",
+    "json",
+    "--jsonl",
+    "rows.jsonl",
+    "--bundle",
+    "bundle.zip",
+    "--npm-registry",
+    &server.base,
+    "--final-retry-window",
+    "0",
+  ];
+  args.extend(extra);
+  let output = depsfetch(&base, &args, &[]);
+  assert!(
+    output.status.success(),
+    "{}{}",
+    String::from_utf8_lossy(&output.stdout),
+    String::from_utf8_lossy(&output.stderr)
+  );
+
+  let lines = fs::read_to_string(base.join("rows.jsonl"))
+    .unwrap()
+    .lines()
+    .map(|line| serde_json::from_str(line).unwrap())
+    .collect();
+  let mut manifest = String::new();
+  zip::ZipArchive::new(fs::File::open(base.join("bundle.zip")).unwrap())
+    .unwrap()
+    .by_name("MANIFEST.json")
+    .unwrap()
+    .read_to_string(&mut manifest)
+    .unwrap();
+  let _ = fs::remove_dir_all(&base);
+  (lines, serde_json::from_str(&manifest).unwrap())
+}
+
+fn error(schema: &Schema, value: &Value) -> String {
+  format!("{:#}", schema.check(value).unwrap_err())
+}
+
+#[test]
+fn emitted_outputs_match_their_schemas() {
+  let (lines, manifest) = scan("schema-plain", &["--filter", "npm:is-*"]);
+  assert_eq!(lines.len(), 2, "{:?}", lines);
+  for line in &lines {
+    REPORT.check(line).unwrap();
+  }
+  BUNDLE.check(&manifest).unwrap();
+  // The index nests the same rows
+  let rows = lines
+    .iter()
+    .map(|line| {
+      let mut row = line.clone();
+      let object = row.as_object_mut().unwrap();
+      object.remove("schema_version");
+      object.remove("filters");
+      row
+    })
+    .collect::<Vec<_>>();
+  let index = json!({"schema_version": 1, "projects": [{"name": "app", "file": "app.xlsx", "rows": rows}]});
+  PROJECTS.check(&index).unwrap();
+
+  let (lines, _) = scan("schema-collapsed", &["--collapse-by-repo"]);
+  assert!(
+    lines.iter().any(|line| line["collapsed_by_repo"]["package_count"] == 2),
+    "{:?}",
+    lines
+  );
+  for line in &lines {
+    REPORT.check(line).unwrap();
+  }
+}
+
+#[test]
+fn unknown_fields_are_rejected() {
+  let (lines, manifest) = scan("schema-unknown", &["--collapse-by-repo"]);
+  let collapsed = lines
+    .iter()
+    .find(|line| line.get("collapsed_by_repo").is_some())
+    .unwrap();
+
+  let mut line = lines[0].clone();
+  line["licence"] = json!("MIT");
+  assert!(
+    error(&REPORT, &line).ends_with("/: unexpected field licence"),
+    "{}",
+    error(&REPORT, &line)
+  );
+  let mut line = collapsed.clone();
+  line["collapsed_by_repo"]["packages"][1]["extra"] = json!(1);
+  assert!(error(&REPORT, &line).ends_with("/collapsed_by_repo/packages/1: unexpected field extra"));
+  let mut line = collapsed.clone();
+  line["collapsed_by_repo"]["count"] = json!(2);
+  assert!(error(&REPORT, &line).ends_with("/collapsed_by_repo: unexpected field count"));
+  let mut line = lines[0].clone();
+  line["link_status"] = json!({"dead": [], "moved": []});
+  assert!(error(&REPORT, &line).ends_with("/link_status: unexpected field moved"));
+
+  // Rows in the index are bare, not report lines
+  let mut row = collapsed["collapsed_by_repo"]["packages"][0].clone();
+  let mut index = json!({"schema_version": 1, "projects": [{"name": "app", "file": "app.xlsx", "rows": [row]}]});
+  PROJECTS.check(&index).unwrap();
+  row["schema_version"] = json!(1);
+  index["projects"][0]["rows"] = json!([row]);
+  assert!(error(&PROJECTS, &index).ends_with("/projects/0/rows/0: unexpected field schema_version"));
+  let mut changed = manifest.clone();
+  changed["files"][0]["mtime"] = json!(0);
+  assert!(error(&BUNDLE, &changed).ends_with("/files/0: unexpected field mtime"));
+  let mut changed = manifest;
+  changed["signature"] = json!("");
+  assert!(error(&BUNDLE, &changed).ends_with("/: unexpected field signature"));
+}
+
+// A row with every field set, so a field added to ReportRow without its schema property fails here
+#[test]
+fn every_row_field_is_in_the_schema() {
+  let row = ReportRow {
+    ecosystem: Ecosystem::Npm,
+    name: "left-pad".to_owned(),
+    version: "1.3.0".to_owned(),
+    scope: DepScope::Dev,
+    homepage: "https://github.com/stevemao/left-pad#readme".to_owned(),
+    license: Some("WTFPL".to_owned()),
+    license_confidence: Some(LicenseConfidence::Declared),
+    license_url: Some("https://github.com/stevemao/left-pad/blob/master/LICENSE".to_owned()),
+    funding: Some("https://github.com/sponsors/stevemao".to_owned()),
+    platform: Some("linux-x64".to_owned()),
+    repo_mismatch: Some("github.com/other/left-pad".to_owned()),
+    installed_mismatch: Some("1.2.0".to_owned()),
+    third_party_notices: Some("https://github.com/stevemao/left-pad/blob/master/NOTICE".to_owned()),
+    link_status: Some(LinkStatus {
+      dead: vec!["https://left-pad.io (404)".to_owned()],
+      replaced: vec!["https://a -> https://b".to_owned()],
+    }),
+    license_text_key: Some("3f2a9c0d1e4b5a6c".to_owned()),
+    attestation: Some(AttestationStatus::Attested),
+    osi_approved: Some(LicenseFlag::No),
+    fsf_libre: Some(LicenseFlag::Mixed),
+    size: Some(12_345),
+    provenance: vec!["metadata: npm registry".to_owned()],
+  };
+  let filters = ["npm:left-*".to_owned()];
+  let line = ReportLine {
+    schema_version: 1,
+    filters: &filters,
+    row: &row,
+    collapsed_by_repo: Some(CollapsedRows {
+      repository: "https://github.com/stevemao/left-pad",
+      package_count: 1,
+      packages: vec![&row],
+    }),
+  };
+  REPORT.check(&line).unwrap();
+
+  let serialized = serde_json::to_value(&row).unwrap();
+  let document: Value = serde_json::from_str(&capture_schema("report")).unwrap();
+  let properties = document["$defs"]["row"]["properties"].as_object().unwrap();
+  let mut fields = serialized.as_object().unwrap().keys().collect::<Vec<_>>();
+  fields.retain(|field| !properties.contains_key(*field));
+  assert!(fields.is_empty(), "not in the schema: {:?}", fields);
+}
+
+// The document as `depsfetch schema` prints it
+fn capture_schema(name: &str) -> String {
+  let base = scratch_dir(&format!("schema-print-{}", name));
+  let output = depsfetch(&base, &["schema", name], &[]);
+  assert!(output.status.success());
+  let _ = fs::remove_dir_all(&base);
+  String::from_utf8(output.stdout).unwrap()
+}
@@ -0,0 +1,163 @@
+use depsfetch::{
+  deps::sheets::{shorten_sheet_names, SheetNames, SheetRouter},
+  types::{DepScope, DepsEntry, Ecosystem},
+};
+use std::collections::{BTreeSet, HashMap};
+
+fn router(mappings: &[&str]) -> SheetRouter {
+  let mappings = Some(mappings.iter().map(|mapping| mapping.to_string()).collect());
+  SheetRouter::new(&mappings, &SheetNames::default()).unwrap()
+}
+
+fn declared_by(name: &str, sources: &[&str]) -> (String, DepsEntry) {
+  let entry = DepsEntry {
+    name: name.to_owned(),
+    version: "1.0.0".to_owned(),
+    scope: DepScope::Runtime,
+    sources: sources.iter().map(|source| source.to_string()).collect::<BTreeSet<_>>(),
+  };
+  (name.to_owned(), entry)
+}
+
+// Each dependency with the sheets it was routed to
+fn routed(router: &SheetRouter, deps: &[(&str, &[&str])]) -> Vec<(String, Vec<&'static str>)> {
+  let deps = deps
+    .iter()
+    .map(|(name, sources)| declared_by(name, sources))
+    .collect::<HashMap<_, _>>();
+  let mut routed = router
+    .route(Ecosystem::Npm, deps)
+    .into_iter()
+    .flat_map(|(sheets, group)| group.into_keys().map(move |name| (name, sheets.clone())))
+    .collect::<Vec<_>>();
+  routed.sort();
+  routed
+}
+
+// Overlapping globs: the first one given that matches a manifest decides its sheet
+#[test]
+fn first_matching_glob_wins() {
+  let deps: &[(&str, &[&str])] = &[
+    ("admin-only", &["apps/admin/package.json"]),
+    ("app-only", &["apps/shop/package.json"]),
+    ("root-only", &["package.json"]),
+  ];
+  let narrow_first = router(&["Admin=apps/admin/*", "Apps=apps/**"]);
+  assert_eq!(
+    routed(&narrow_first, deps),
+    [
+      ("admin-only".to_owned(), vec!["Admin"]),
+      ("app-only".to_owned(), vec!["Apps"]),
+      ("root-only".to_owned(), vec!["Web"]),
+    ]
+  );
+  // The wider glob given first takes the narrow one's manifests too, its sheet stays empty
+  let wide_first = router(&["Apps=apps/**", "Admin=apps/admin/*"]);
+  assert_eq!(
+    routed(&wide_first, deps),
+    [
+      ("admin-only".to_owned(), vec!["Apps"]),
+      ("app-only".to_owned(), vec!["Apps"]),
+      ("root-only".to_owned(), vec!["Web"]),
+    ]
+  );
+  assert_eq!(wide_first.sheets()[..3], ["Apps", "Admin", "Web"]);
+}
+
+// A dependency declared by manifests on different sheets is listed on each of them, in sheet order
+#[test]
+fn dependency_of_several_manifests_lands_on_each_sheet() {
+  let router = router(&["Admin=apps/admin/*", "Apps=apps/**", "Apps=libs/**"]);
+  let deps: &[(&str, &[&str])] = &[
+    (
+      "react",
+      &["package.json", "apps/shop/package.json", "apps/admin/package.json"],
+    ),
+    ("lodash", &["apps/shop/package.json", "libs/ui/package.json"]),
+    // Merged from an SBOM, no manifest of its own
+    ("from-sbom", &[]),
+  ];
+  assert_eq!(
+    routed(&router, deps),
+    [
+      ("from-sbom".to_owned(), vec!["Web"]),
+      ("lodash".to_owned(), vec!["Apps"]),
+      ("react".to_owned(), vec!["Admin", "Apps", "Web"]),
+    ]
+  );
+}
+
+#[test]
+fn long_names_keep_their_label() {
+  let shortened = shorten_sheet_names(&["checkout-frontend-web-app-v2 (npm)", "Web"]).unwrap();
+  assert_eq!(shortened, ["checkout-frontend-web-app (npm)", "Web"]);
+  // No label to keep, or one too long to count as a label
+  let shortened = shorten_sheet_names(&["platform-shared-infrastructure-services"]).unwrap();
+  assert_eq!(shortened, ["platform-shared-infrastructure"]);
+}
+
+// Two cuts that would collide both get a hash of their whole name, a cut colliding with a reserved sheet too
+#[test]
+fn colliding_cuts_get_a_hash() {
+  let names = [
+    "payments-service-backend-api (Go)",
+    "payments-service-backend-worker (Go)",
+  ];
+  let shortened = shorten_sheet_names(&names).unwrap();
+  for short in &shortened {
+    assert!(
+      short.starts_with("payments-service-ba") && short.ends_with(" (Go)"),
+      "{}",
+      short
+    );
+    assert_eq!(short.chars().count(), 31);
+  }
+  assert_ne!(shortened[0], shortened[1]);
+
+  let reserved = format!("Imported{}", "-".repeat(30));
+  let shortened = shorten_sheet_names(&[&reserved]).unwrap();
+  assert!(shortened[0].starts_with("Imported~"), "{}", shortened[0]);
+}
+
+// Random long project names, many sharing a prefix, with and without an ecosystem label
+#[test]
+fn random_names_stay_distinct_and_stable() {
+  let mut seed = 0x2545_f491_4f6c_dd1du64;
+  let mut next = move |bound: u64| {
+    seed ^= seed << 13;
+    seed ^= seed >> 7;
+    seed ^= seed << 17;
+    seed % bound
+  };
+  for _ in 0..200 {
+    let mut names = Vec::new();
+    for _ in 0..1 + next(30) {
+      let prefix = ["payments-service", "platform-shared-infrastructure", "x"][next(3) as usize];
+      let mut name = format!("{}-{}", prefix, "abcdefghijklmnopqrstuvwxyz-".repeat(3));
+      name.truncate(prefix.len() + 1 + next(60) as usize);
+      match next(3) {
+        0 => name.push_str(" (Go)"),
+        1 => name.push_str(" (Python)"),
+        _ => {},
+      }
+      if !names.contains(&name) {
+        names.push(name);
+      }
+    }
+    let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+    let shortened = shorten_sheet_names(&names).unwrap();
+    assert_eq!(shortened.len(), names.len());
+    for (name, short) in names.iter().zip(&shortened) {
+      assert!(short.chars().count() <= 31, "{} is too long", short);
+      if name.chars().count() <= 31 {
+        assert_eq!(name, short);
+      }
+    }
+    let mut distinct = shortened.iter().map(|short| short.to_lowercase()).collect::<Vec<_>>();
+    distinct.sort();
+    distinct.dedup();
+    assert_eq!(distinct.len(), shortened.len());
+    // Stable for the same names
+    assert_eq!(shorten_sheet_names(&names).unwrap(), shortened);
+  }
+}
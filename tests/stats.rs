@@ -0,0 +1,114 @@
+mod common;
+
+use common::scratch_dir;
+use depsfetch::{
+  report::{ReportWriter, ResolvedRow, Section, StreamFormat, StreamWriter, WriterMessage, WriterOutput, STATS},
+  types::ReportRow,
+};
+use serde_json::Value;
+use std::{fs, path::Path};
+
+// tests/fixtures/stats: eight distinct entries, one of them listed on both sheets. By license half permissive, a
+// quarter each copyleft and unknown; by scope five runtime, one optional, two dev; by ecosystem six npm, two Go.
+const ROWS: &str = include_str!("fixtures/stats/rows.jsonl");
+const EXPECTED: &str = include_str!("fixtures/stats/deps_report.stats.json");
+
+fn fixture_rows() -> Vec<ResolvedRow> {
+  ROWS
+    .lines()
+    .map(|line| {
+      let line = serde_json::from_str::<Value>(line).unwrap();
+      let sheet = match line["sheet"].as_str() {
+        Some("Web") => "Web",
+        _ => "Backend",
+      };
+      let row = serde_json::from_value::<ReportRow>(line["row"].clone()).unwrap();
+      ResolvedRow { sheet, row }
+    })
+    .collect()
+}
+
+// The fixture rows through the writer thread with a JSON lines report at `dir`/deps_report.jsonl
+async fn write_fixture(dir: &Path, writer: ReportWriter) -> WriterOutput {
+  let stream = dir.join("deps_report.jsonl").display().to_string();
+  let writer = writer.with_stream(StreamWriter::create(&stream, StreamFormat::JsonLines, true).unwrap());
+  let (sender, handle) = writer.spawn();
+  for sheet in ["Web", "Backend"] {
+    sender
+      .send(WriterMessage::Section(Section::Dependencies(sheet)))
+      .unwrap();
+  }
+  for row in fixture_rows() {
+    sender.send(WriterMessage::Row(Box::new(row))).unwrap();
+  }
+  sender
+    .send(WriterMessage::Section(Section::Summary("Summary")))
+    .unwrap();
+  sender.send(WriterMessage::Finish).unwrap();
+  handle.await.unwrap().unwrap().unwrap()
+}
+
+// The stats file next to the JSON lines report, against the snapshot and the schema
+#[tokio::test]
+async fn stats_file_matches_the_snapshot() {
+  let dir = scratch_dir("stats-json");
+  let path = dir.join("deps_report.xlsx").display().to_string();
+  let output = write_fixture(&dir, ReportWriter::new(&path).unwrap().without_workbook()).await;
+
+  let stats_file = dir.join("deps_report.stats.json");
+  assert_eq!(
+    output.streams,
+    [
+      dir.join("deps_report.jsonl").display().to_string(),
+      stats_file.display().to_string()
+    ]
+  );
+  let written = serde_json::from_str::<Value>(&fs::read_to_string(&stats_file).unwrap()).unwrap();
+  STATS.check(&written).unwrap();
+  assert_eq!(written, serde_json::from_str::<Value>(EXPECTED).unwrap());
+  let _ = fs::remove_dir_all(&dir);
+}
+
+// The Summary sheet's blocks below the totals, read back: counts, and shares as fractions in percentage cells
+#[cfg(feature = "xlsx")]
+#[tokio::test]
+async fn summary_breakdown_matches_the_snapshot() {
+  use common::workbook::read_workbook;
+
+  let dir = scratch_dir("stats-workbook");
+  let path = dir.join("deps_report.xlsx");
+  write_fixture(&dir, ReportWriter::new(&path.display().to_string()).unwrap()).await;
+
+  let sheets = read_workbook(&path);
+  let summary = &sheets.iter().find(|sheet| sheet.name == "Summary").unwrap().rows;
+  let distinct = "Уникальных пакетов (включая транзитивные)";
+  let start = summary
+    .iter()
+    .position(|row| row.first().map(String::as_str) == Some(distinct))
+    .unwrap();
+  let blocks = summary[start..]
+    .iter()
+    .filter(|row| !row.is_empty())
+    .map(|row| row.join(" | "))
+    .collect::<Vec<_>>();
+  assert_eq!(
+    blocks,
+    [
+      "Уникальных пакетов (включая транзитивные) | 8",
+      "Категория лицензии | Количество | Доля",
+      "permissive | 4 | 0.5",
+      "copyleft | 2 | 0.25",
+      "unknown | 2 | 0.25",
+      "Тип зависимости | Количество | Доля",
+      "runtime | 5 | 0.625",
+      "optional | 1 | 0.125",
+      "dev | 2 | 0.25",
+      "Экосистема | Количество | Доля",
+      "npm | 6 | 0.75",
+      "go | 2 | 0.25",
+    ]
+  );
+  // Totals above the blocks count every row, the entry on both sheets twice
+  assert_eq!(summary[0], ["Всего пакетов", "9"]);
+  let _ = fs::remove_dir_all(&dir);
+}
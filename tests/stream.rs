@@ -0,0 +1,86 @@
+mod common;
+
+use common::scratch_dir;
+use depsfetch::{
+  license_data::LicenseData,
+  report::{StreamFormat, StreamWriter},
+  types::ReportRow,
+};
+use std::fs;
+
+const ROWS: usize = 50_000;
+
+fn row(idx: usize) -> ReportRow {
+  serde_json::from_value(serde_json::json!({
+    "ecosystem": "npm",
+    "name": format!("pkg-{}", ROWS - idx),
+    "version": "1.0.0",
+    "homepage": format!("https://github.com/o/pkg-{}#readme", idx % 100),
+    "license": "MIT",
+  }))
+  .unwrap()
+}
+
+// Pushes every row, returning the most the writer held back at once and how much was on disk before `finish`
+fn stream(path: &str, format: StreamFormat, sort: bool, grouped: bool) -> (usize, u64) {
+  let mut writer = StreamWriter::create(path, format, sort).unwrap();
+  if grouped {
+    writer.hold_for_repo_groups();
+  }
+  let mut most = 0;
+  for idx in 0..ROWS {
+    writer.push(&row(idx)).unwrap();
+    most = most.max(writer.buffered());
+  }
+  let written = fs::metadata(path).unwrap().len();
+  writer.finish(&LicenseData::load(None).unwrap()).unwrap();
+  (most, written)
+}
+
+// --no-sort writes rows as they come, a report of any size never sits in memory
+#[test]
+fn unsorted_rows_are_not_buffered() {
+  let dir = scratch_dir("stream-unsorted");
+  for (format, file, header) in [
+    (StreamFormat::JsonLines, "rows.jsonl", 0),
+    (StreamFormat::Csv, "rows.csv", 1),
+  ] {
+    let path = dir.join(file).display().to_string();
+    let (most, written) = stream(&path, format, false, false);
+    assert_eq!(most, 0, "{}", file);
+    // Most of the report reached the file before `finish`
+    let total = fs::metadata(&path).unwrap().len();
+    assert!(
+      written > total * 9 / 10,
+      "{} of {} bytes written before finish",
+      written,
+      total
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), ROWS + header);
+  }
+
+  // CSV has no groups, so it still streams with --collapse-by-repo
+  let path = dir.join("grouped.csv").display().to_string();
+  assert_eq!(stream(&path, StreamFormat::Csv, false, true).0, 0);
+  let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn sorted_and_grouped_rows_wait_for_finish() {
+  let dir = scratch_dir("stream-sorted");
+  let path = dir.join("sorted.jsonl").display().to_string();
+  assert_eq!(stream(&path, StreamFormat::JsonLines, true, false), (ROWS, 0));
+  let names = fs::read_to_string(&path)
+    .unwrap()
+    .lines()
+    .take(2)
+    .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["name"].clone())
+    .collect::<Vec<_>>();
+  assert_eq!(names, ["pkg-1", "pkg-10"]);
+
+  let path = dir.join("grouped.jsonl").display().to_string();
+  assert_eq!(stream(&path, StreamFormat::JsonLines, false, true), (ROWS, 0));
+  // One line per repository
+  assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 100);
+  let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,148 @@
+use depsfetch::http::{HttpClient, TlsPolicy};
+use rustls::{crypto::ring::default_provider, ServerConfig, ServerConnection, StreamOwned};
+use std::{
+  fs::File,
+  io::{BufRead, BufReader, Write},
+  net::TcpListener,
+  path::PathBuf,
+  sync::Arc,
+  thread,
+};
+
+// tests/fixtures/tls: a test CA and a localhost/127.0.0.1 server certificate it signed, plus an unrelated CA. Made
+// with `openssl req -x509 -newkey ec` (P-256, a hundred years); PIN is the server key's SPKI SHA-256.
+const PIN: &str = "pKmRrffyJehukQdKbh7rXdxxJSPTwD5XV23MCfeFul4=";
+const OTHER_PIN: &str = "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=";
+
+fn fixture(name: &str) -> String {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    .join("tests/fixtures/tls")
+    .join(name)
+    .display()
+    .to_string()
+}
+
+// An HTTPS server on a free local port presenting the fixture certificate, answering every request with `ok`
+fn start_server() -> u16 {
+  let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(fixture("server.pem")).unwrap()))
+    .collect::<Result<Vec<_>, _>>()
+    .unwrap();
+  let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(fixture("server.key")).unwrap()))
+    .unwrap()
+    .unwrap();
+  let config = ServerConfig::builder_with_provider(Arc::new(default_provider()))
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_no_client_auth()
+    .with_single_cert(certs, key)
+    .unwrap();
+  let config = Arc::new(config);
+
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let port = listener.local_addr().unwrap().port();
+  thread::spawn(move || {
+    for stream in listener.incoming().flatten() {
+      let config = Arc::clone(&config);
+      thread::spawn(move || {
+        let mut tls = StreamOwned::new(ServerConnection::new(config).unwrap(), stream);
+        // A failed handshake ends here, the client has its error already
+        let mut reader = BufReader::new(&mut tls);
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|read| read > 0) && line != "\r\n" {
+          line.clear();
+        }
+        let _ = tls.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok");
+        let _ = tls.flush();
+      });
+    }
+  });
+  port
+}
+
+fn get(policy: TlsPolicy, url: &str) -> Result<String, String> {
+  let client = HttpClient::new(&None).unwrap().with_tls(policy).unwrap();
+  tokio::runtime::Runtime::new()
+    .unwrap()
+    .block_on(client.get(url))
+    .map(|response| response.text())
+    .map_err(|err| format!("{:#}", err))
+}
+
+fn pins(pins: &[&str]) -> Option<Vec<String>> {
+  Some(pins.iter().map(|pin| pin.to_string()).collect())
+}
+
+#[test]
+fn ca_bundle_replaces_the_trusted_roots() {
+  let port = start_server();
+  let url = format!("https://localhost:{}/left-pad", port);
+
+  let trusted = TlsPolicy::load(&fixture("ca.pem"), &None).unwrap();
+  assert_eq!(trusted.describe(), "1 root(s)");
+  assert_eq!(get(trusted, &url).unwrap(), "ok");
+  // Only the bundle is trusted, a certificate from anyone else is refused
+  let err = get(TlsPolicy::load(&fixture("other-ca.pem"), &None).unwrap(), &url).unwrap_err();
+  assert!(err.contains("UnknownIssuer"), "{}", err);
+}
+
+#[test]
+fn pin_mismatch_names_the_fingerprint() {
+  let port = start_server();
+  let url = format!("https://localhost:{}/left-pad", port);
+
+  // Any one of a host's pins will do, and pins of other hosts don't apply
+  let pinned = TlsPolicy::load(
+    &fixture("ca.pem"),
+    &pins(&[
+      &format!("localhost=sha256//{}", PIN),
+      &format!("localhost={}", OTHER_PIN),
+    ]),
+  )
+  .unwrap();
+  assert_eq!(pinned.describe(), "1 root(s), pinned: localhost");
+  assert_eq!(get(pinned, &url).unwrap(), "ok");
+  let elsewhere = TlsPolicy::load(
+    &fixture("ca.pem"),
+    &pins(&[&format!("registry.npmjs.org={}", OTHER_PIN)]),
+  )
+  .unwrap();
+  assert_eq!(get(elsewhere, &url).unwrap(), "ok");
+
+  // The chain is fine, the key is not the pinned one
+  let mismatched = TlsPolicy::load(&fixture("ca.pem"), &pins(&[&format!("LOCALHOST={}", OTHER_PIN)])).unwrap();
+  let err = get(mismatched, &url).unwrap_err();
+  assert!(err.contains(&format!("Request to {} failed", url)), "{}", err);
+  assert!(
+    err.contains(&format!(
+      "certificate pin mismatch for localhost: its key is sha256//{}, pinned {}",
+      PIN, OTHER_PIN
+    )),
+    "{}",
+    err
+  );
+}
+
+#[test]
+fn bad_bundles_and_pins_are_rejected() {
+  let error = |ca_bundle: &str, pin: &[&str]| {
+    TlsPolicy::load(ca_bundle, &pins(pin))
+      .err()
+      .map(|err| format!("{:#}", err))
+      .unwrap()
+  };
+
+  // A key is not a certificate
+  assert_eq!(
+    error(&fixture("server.key"), &[]),
+    format!("CA bundle {} has no certificates", fixture("server.key"))
+  );
+  assert!(error(&fixture("missing.pem"), &[]).starts_with("Failed to open CA bundle"));
+  assert_eq!(
+    error(&fixture("ca.pem"), &["localhost"]),
+    "--pin `localhost` is not HOST=SPKI-SHA256"
+  );
+  assert_eq!(
+    error(&fixture("ca.pem"), &["localhost=sha256//c2hvcnQ="]),
+    "--pin `localhost=sha256//c2hvcnQ=`: expected the base64 SHA-256 of the certificate's public key"
+  );
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use common::{depsfetch, scratch_dir};
+use std::{fs, path::PathBuf};
+
+// tests/fixtures/history: three archived reports, the first from before rows carried a schema version, and one
+// from a future schema that is skipped
+#[test]
+fn trend_over_archived_reports() {
+  let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/history");
+  let base = scratch_dir("trend");
+  fs::create_dir_all(&base).unwrap();
+  let output = depsfetch(
+    &base,
+    &[
+      "history",
+      &fixtures.display().to_string(),
+      "--csv",
+      "trend.csv",
+      "--xlsx",
+      "trend.xlsx",
+    ],
+    &[],
+  );
+  let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+  assert!(output.status.success(), "{}", stdout);
+  assert!(
+    stdout.contains("deps-2024-10-15.jsonl: line 1 has schema version 99, this build reads versions up to 1"),
+    "{}",
+    stdout
+  );
+  assert!(stdout.contains("Trend for 3 report(s)"), "{}", stdout);
+
+  // January to April: three packages collapsed under @babel/core and an LGPL one join, a custom license takes the
+  // place of the one without any. April to July: the GPL one goes, the LGPL one offers MIT too and still counts as
+  // copyleft, two packages without a license come in.
+  let csv = fs::read_to_string(base.join("trend.csv")).unwrap();
+  assert_eq!(
+    csv,
+    "report,total,unknown_license,copyleft\n\
+     2024-01-15,4,1,1\n\
+     2024-04-15,8,1,2\n\
+     2024-07-15,6,2,1\n"
+  );
+  let rows = csv
+    .lines()
+    .skip(1)
+    .map(|line| {
+      line
+        .split(',')
+        .skip(1)
+        .map(|count| count.parse::<i64>().unwrap())
+        .collect::<Vec<_>>()
+    })
+    .collect::<Vec<_>>();
+  let deltas = rows
+    .windows(2)
+    .map(|pair| {
+      pair[1]
+        .iter()
+        .zip(&pair[0])
+        .map(|(now, then)| now - then)
+        .collect::<Vec<_>>()
+    })
+    .collect::<Vec<_>>();
+  assert_eq!(deltas, [[4, 0, 1], [-2, 1, -1]]);
+
+  #[cfg(feature = "xlsx")]
+  {
+    let sheets = common::workbook::read_workbook(&base.join("trend.xlsx"));
+    assert_eq!(sheets.len(), 1);
+    assert_eq!(sheets[0].name, "Trend");
+    let cells = sheets[0].body().to_vec();
+    assert_eq!(
+      cells,
+      [
+        ["2024-01-15", "4", "1", "1"],
+        ["2024-04-15", "8", "1", "2"],
+        ["2024-07-15", "6", "2", "1"]
+      ]
+    );
+  }
+  let _ = fs::remove_dir_all(&base);
+}
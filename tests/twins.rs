@@ -0,0 +1,96 @@
+use depsfetch::{license_data::LicenseData, report::TwinDetector, types::ReportRow};
+
+fn row(ecosystem: &str, name: &str, license: Option<&str>, repository: &str) -> ReportRow {
+  serde_json::from_value(serde_json::json!({
+    "ecosystem": ecosystem,
+    "name": name,
+    "version": "1.0.0",
+    "homepage": format!("https://github.com/{}#readme", repository),
+    "license": license,
+  }))
+  .unwrap()
+}
+
+// (primary, variant, rows) of what `rows` link up to
+fn twins(pairs: Option<&[&str]>, rows: &[ReportRow]) -> Vec<(String, String, usize)> {
+  let data = LicenseData::load(None).unwrap();
+  let pairs = pairs.map(|pairs| pairs.iter().map(|pair| pair.to_string()).collect());
+  let mut detector = TwinDetector::new(&pairs).unwrap();
+  for row in rows {
+    detector.observe(row, &data);
+  }
+  detector
+    .twins()
+    .into_iter()
+    .map(|twin| (twin.primary, twin.variant, twin.rows))
+    .collect()
+}
+
+#[test]
+fn suffixed_names_need_the_repository_and_the_license() {
+  let npm = |name, license, repository| row("npm", name, Some(license), repository);
+  let found = twins(
+    None,
+    &[
+      // One project, two builds, the variant in two versions
+      npm("date-kit", "MIT", "acme/date-kit"),
+      npm("date-kit-esm", "MIT", "acme/date-kit"),
+      npm("date-kit-esm", "MIT", "acme/date-kit"),
+      // Same suffix, someone else's package
+      npm("router", "MIT", "acme/router"),
+      npm("router-compat", "MIT", "other/router-compat"),
+      // Same repository, relicensed build
+      npm("parser", "MIT", "acme/parser"),
+      npm("parser-cjs", "Apache-2.0", "acme/parser"),
+      // The suffix alone is no package
+      npm("-es", "MIT", "acme/es"),
+    ],
+  );
+  assert_eq!(found, [("date-kit".to_owned(), "date-kit-esm".to_owned(), 2)]);
+}
+
+#[test]
+fn unknown_or_mixed_licenses_stay_apart() {
+  let found = twins(
+    None,
+    &[
+      row("npm", "lodash", Some("MIT"), "lodash/lodash"),
+      row("npm", "lodash-es", None, "lodash/lodash"),
+      row("npm", "rxjs", Some("Apache-2.0"), "reactivex/rxjs"),
+      row("npm", "rxjs", Some("MIT"), "reactivex/rxjs"),
+      row("npm", "rxjs-compat", Some("MIT"), "reactivex/rxjs"),
+    ],
+  );
+  assert!(found.is_empty(), "{:?}", found);
+}
+
+// Curated pairs, built in or from the config, need no repository in common; the ecosystem still has to match
+#[test]
+fn curated_pairs_skip_the_repository_check() {
+  let found = twins(
+    Some(&[" vendor-lib = vendor-lib-legacy "]),
+    &[
+      row("npm", "lodash", Some("MIT"), "lodash/lodash"),
+      row("npm", "lodash-es", Some("MIT"), "lodash/lodash-es"),
+      row("npm", "vendor-lib", Some("BSD-3-Clause"), "acme/vendor-lib"),
+      row("npm", "vendor-lib-legacy", Some("BSD-3-Clause"), "acme/vendor-legacy"),
+      row("pypi", "rxjs", Some("MIT"), "reactivex/rxjs"),
+      row("npm", "rxjs-compat", Some("MIT"), "reactivex/rxjs"),
+    ],
+  );
+  assert_eq!(
+    found,
+    [
+      ("lodash".to_owned(), "lodash-es".to_owned(), 1),
+      ("vendor-lib".to_owned(), "vendor-lib-legacy".to_owned(), 1),
+    ]
+  );
+
+  for pair in ["vendor-lib", "vendor-lib=", "=vendor-lib", "same=same"] {
+    let err = TwinDetector::new(&Some(vec![pair.to_owned()])).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      format!("twin_pairs entry `{}` is not PRIMARY=VARIANT", pair)
+    );
+  }
+}
@@ -0,0 +1,371 @@
+use depsfetch::{
+  deps::py_deps::highest_matching,
+  types::Ecosystem,
+  version::{highest_match, PrereleasePolicy, Version, VersionReq},
+};
+
+// npm's own default
+const NPM: PrereleasePolicy = PrereleasePolicy::OnlyWhenRequested;
+
+// Whether each version is in its range read the npm way, under `policy`
+fn check(policy: PrereleasePolicy, cases: &[(&str, &str)], expected: bool) {
+  check_as(Ecosystem::Npm, policy, cases, expected)
+}
+
+fn check_as(ecosystem: Ecosystem, policy: PrereleasePolicy, cases: &[(&str, &str)], expected: bool) {
+  for (range, version) in cases {
+    let matched = VersionReq::parse(range, ecosystem)
+      .unwrap()
+      .matches(&Version::parse(version).unwrap(), policy);
+    assert_eq!(
+      matched, expected,
+      "{} in `{}` under {} as {}",
+      version, range, policy, ecosystem
+    );
+  }
+}
+
+// node-semver's range fixtures for the syntax supported
+#[test]
+fn node_semver_ranges() {
+  check(
+    NPM,
+    &[
+      ("1.0.0 - 2.0.0", "1.2.3"),
+      ("^1.2.3+build", "1.2.3"),
+      ("^1.2.3+build", "1.3.0"),
+      ("1.2.3-pre+asdf - 2.4.3-pre+asdf", "1.2.3"),
+      ("1.2.3-pre+asdf - 2.4.3-pre+asdf", "1.2.3-pre.2"),
+      ("1.2.3-pre+asdf - 2.4.3-pre+asdf", "2.4.3-alpha"),
+      ("1.2.3+asdf - 2.4.3+asdf", "1.2.3"),
+      ("1.0.0", "1.0.0"),
+      (">=*", "0.2.4"),
+      ("*", "1.2.3"),
+      (">=1.0.0", "1.0.0"),
+      (">=1.0.0", "1.0.1"),
+      (">=1.0.0", "1.1.0"),
+      (">1.0.0", "1.0.1"),
+      (">1.0.0", "1.1.0"),
+      ("<=2.0.0", "2.0.0"),
+      ("<=2.0.0", "1.9999.9999"),
+      ("<=2.0.0", "0.2.9"),
+      ("<2.0.0", "1.9999.9999"),
+      ("<2.0.0", "0.2.9"),
+      (">= 1.0.0", "1.0.0"),
+      (">=  1.0.0", "1.0.1"),
+      ("> 1.0.0", "1.0.1"),
+      ("<=   2.0.0", "2.0.0"),
+      ("< 2.0.0", "0.2.9"),
+      (">=0.1.97", "0.1.97"),
+      ("0.1.20 || 1.2.4", "1.2.4"),
+      (">=0.2.3 || <0.0.1", "0.0.0"),
+      (">=0.2.3 || <0.0.1", "0.2.3"),
+      (">=0.2.3 || <0.0.1", "0.2.4"),
+      ("||", "1.3.4"),
+      ("2.x.x", "2.1.3"),
+      ("1.2.x", "1.2.3"),
+      ("1.2.x || 2.x", "2.1.3"),
+      ("1.2.x || 2.x", "1.2.3"),
+      ("x", "1.2.3"),
+      ("2.*.*", "2.1.3"),
+      ("1.2.*", "1.2.3"),
+      ("2", "2.1.2"),
+      ("2.3", "2.3.1"),
+      ("~0.0.1", "0.0.1"),
+      ("~0.0.1", "0.0.2"),
+      ("~x", "0.0.9"),
+      ("~2", "2.0.9"),
+      ("~2.4", "2.4.0"),
+      ("~2.4", "2.4.5"),
+      ("~>3.2.1", "3.2.2"),
+      ("~1", "1.2.3"),
+      ("~>1", "1.2.3"),
+      ("~> 1", "1.2.3"),
+      ("~1.0", "1.0.2"),
+      ("~ 1.0", "1.0.2"),
+      ("~ 1.0.3", "1.0.12"),
+      (">=1", "1.0.0"),
+      (">= 1", "1.0.0"),
+      ("<1.2", "1.1.1"),
+      ("< 1.2", "1.1.1"),
+      ("~v0.5.4-pre", "0.5.5"),
+      ("~v0.5.4-pre", "0.5.4"),
+      ("=0.7.x", "0.7.2"),
+      ("<=0.7.x", "0.7.2"),
+      (">=0.7.x", "0.7.2"),
+      ("<=0.7.x", "0.6.2"),
+      ("~1.2.1 >=1.2.3", "1.2.3"),
+      ("~1.2.1 =1.2.3", "1.2.3"),
+      ("~1.2.1 1.2.3", "1.2.3"),
+      ("~1.2.1 >=1.2.3 1.2.3", "1.2.3"),
+      ("~1.2.1 1.2.3 >=1.2.3", "1.2.3"),
+      (">=1.2.1 1.2.3", "1.2.3"),
+      ("1.2.3 >=1.2.1", "1.2.3"),
+      (">=1.2.3 >=1.2.1", "1.2.3"),
+      (">=1.2.1 >=1.2.3", "1.2.3"),
+      (">=1.2", "1.2.8"),
+      ("^1.2.3", "1.8.1"),
+      ("^0.1.2", "0.1.2"),
+      ("^0.1", "0.1.2"),
+      ("^0.0.1", "0.0.1"),
+      ("^1.2", "1.4.2"),
+      ("^1.2 ^1", "1.4.2"),
+      ("^1.2.3-alpha", "1.2.3-pre"),
+      ("^1.2.0-alpha", "1.2.0-pre"),
+      ("^0.0.1-alpha", "0.0.1-beta"),
+      ("^0.0.1-alpha", "0.0.1"),
+      ("^0.1.1-alpha", "0.1.1-beta"),
+      ("^x", "1.2.3"),
+      ("x - 1.0.0", "0.9.7"),
+      ("x - 1.x", "0.9.7"),
+      ("1.0.0 - x", "1.9.7"),
+      ("1.x - x", "1.9.7"),
+      ("<=7.x", "7.9.9"),
+      ("^1.0.0-0", "1.0.0-rc1"),
+      (">=1.0.0-beta, <2", "1.0.0-rc.1"),
+    ],
+    true,
+  );
+  check(
+    NPM,
+    &[
+      ("1.0.0 - 2.0.0", "2.2.3"),
+      ("1.2.3+asdf - 2.4.3+asdf", "1.2.3-pre.2"),
+      ("1.2.3+asdf - 2.4.3+asdf", "2.4.3-alpha"),
+      ("^1.2.3+build", "2.0.0"),
+      ("^1.2.3+build", "1.2.0"),
+      ("^1.2.3", "1.2.3-pre"),
+      ("^1.2", "1.2.0-pre"),
+      (">1.2", "1.3.0-beta"),
+      ("<=1.2.3", "1.2.3-beta"),
+      ("^1.2.3", "1.2.3-beta"),
+      ("=0.7.x", "0.7.0-asdf"),
+      (">=0.7.x", "0.7.0-asdf"),
+      ("<=0.7.x", "0.7.0-asdf"),
+      (">=1.0.0", "0.0.0"),
+      (">=1.0.0", "0.0.1"),
+      (">=1.0.0", "0.1.0"),
+      (">1.0.0", "0.0.1"),
+      (">1.0.0", "0.1.0"),
+      ("<=2.0.0", "3.0.0"),
+      ("<=2.0.0", "2.9999.9999"),
+      ("<=2.0.0", "2.2.9"),
+      ("<2.0.0", "2.9999.9999"),
+      ("<2.0.0", "2.2.9"),
+      (">=0.1.97", "0.1.93"),
+      ("0.1.20 || 1.2.4", "1.2.3"),
+      (">=0.2.3 || <0.0.1", "0.0.3"),
+      (">=0.2.3 || <0.0.1", "0.2.2"),
+      ("2.x.x", "1.1.3"),
+      ("2.x.x", "3.1.3"),
+      ("1.2.x", "1.3.3"),
+      ("1.2.x || 2.x", "3.1.3"),
+      ("1.2.x || 2.x", "1.1.3"),
+      ("2.*.*", "1.1.3"),
+      ("2.*.*", "3.1.3"),
+      ("1.2.*", "1.3.3"),
+      ("1.2.* || 2.*", "3.1.3"),
+      ("2", "1.1.2"),
+      ("~0.0.1", "0.1.0-alpha"),
+      ("~0.0.1", "0.1.0"),
+      ("~2.4", "2.5.0"),
+      ("~2.4", "2.3.9"),
+      ("~>3.2.1", "3.3.2"),
+      ("~>3.2.1", "3.2.0"),
+      ("~1", "0.2.3"),
+      ("~>1", "2.2.3"),
+      ("~1.0", "1.1.0"),
+      ("<1", "1.0.0"),
+      (">=1.2", "1.1.1"),
+      ("=0.7.x", "0.8.2"),
+      (">=0.7.x", "0.6.2"),
+      ("<0.7.x", "0.7.2"),
+      ("<1.2.3", "1.2.3-beta"),
+      ("=1.2.3", "1.2.3-beta"),
+      (">1.2", "1.2.8"),
+      ("^0.0.1", "0.0.2-alpha"),
+      ("^0.0.1", "0.0.2"),
+      ("^1.2.3", "2.0.0-alpha"),
+      ("^1.2.3", "1.2.2"),
+      ("^1.2", "1.1.9"),
+      ("*", "1.0.0-rc1"),
+      ("^1.0.0-0", "1.0.1-rc1"),
+      ("^1.0.0-rc2", "1.0.1-rc1"),
+      ("^1.0.0", "1.0.1-rc1"),
+      ("^1.0.0", "1.1.0-rc1"),
+    ],
+    false,
+  );
+}
+
+// node-semver's includePrerelease
+#[test]
+fn allowed_prereleases() {
+  check(
+    PrereleasePolicy::Allow,
+    &[
+      ("2.x", "2.0.0-pre.0"),
+      ("2.x", "2.1.0-pre.0"),
+      ("1.1.x", "1.1.0-a"),
+      ("*", "1.0.0-rc1"),
+      ("^1.0.0-0", "1.0.1-rc1"),
+      ("^1.0.0-rc2", "1.0.1-rc1"),
+      ("^1.0.0", "1.0.1-rc1"),
+      ("^1.0.0", "1.1.0-rc1"),
+      ("1 - 2", "2.0.0-pre"),
+      ("<=0.7.x", "0.7.0-asdf"),
+      (">=1.0.0 <=1.1.0", "1.1.0-pre"),
+    ],
+    true,
+  );
+  check(
+    PrereleasePolicy::Allow,
+    &[
+      ("^1.2.3", "2.0.0-alpha"),
+      ("^0.0.1", "0.0.2-alpha"),
+      ("^1.2.3", "1.2.3-pre"),
+      ("<1.2", "1.2.0-rc.1"),
+    ],
+    false,
+  );
+}
+
+// Pinned versions aren't ranges, callers look them up as written before asking
+#[test]
+fn denied_prereleases() {
+  check(
+    PrereleasePolicy::Deny,
+    &[
+      ("^1.0.0-0", "1.0.0-rc1"),
+      ("=1.2.3-beta", "1.2.3-beta"),
+      ("*", "1.0.0-rc1"),
+    ],
+    false,
+  );
+  check(PrereleasePolicy::Deny, &[("^1.0.0-0", "1.0.1"), ("*", "1.0.0")], true);
+}
+
+// A version without an operator is exact in npm, an x-range when partial; cargo reads it as a caret
+#[test]
+fn bare_versions_depend_on_the_ecosystem() {
+  check(
+    NPM,
+    &[
+      ("1.2.3", "1.2.3"),
+      ("1.2", "1.2.0"),
+      ("1.2", "1.2.9"),
+      ("1", "1.9.0"),
+      ("0.2", "0.2.9"),
+      ("~1.2.1 1.2.3", "1.2.3"),
+    ],
+    true,
+  );
+  check(
+    NPM,
+    &[
+      ("1.2.3", "1.2.4"),
+      ("1.2.3", "1.3.0"),
+      ("1.2", "1.3.0"),
+      ("1.2", "1.1.9"),
+      ("1", "2.0.0"),
+      ("~1.2.1 1.2.3", "1.2.5"),
+    ],
+    false,
+  );
+  // Helm charts and JSR packages follow npm
+  for ecosystem in [Ecosystem::Helm, Ecosystem::Deno] {
+    check_as(ecosystem, NPM, &[("1.2", "1.2.5"), ("1.2.3", "1.2.3")], true);
+    check_as(ecosystem, NPM, &[("1.2", "1.3.0"), ("1.2.3", "1.2.4")], false);
+  }
+
+  let cargo = |cases: &[(&str, &str)], expected| check_as(Ecosystem::Cargo, NPM, cases, expected);
+  cargo(
+    &[
+      ("1.2.3", "1.2.3"),
+      ("1.2.3", "1.9.0"),
+      ("1.2", "1.9.0"),
+      ("1", "1.9.0"),
+      ("0.2", "0.2.9"),
+      ("0.0.3", "0.0.3"),
+      ("~1.2.1 1.2.3", "1.2.5"),
+    ],
+    true,
+  );
+  cargo(
+    &[
+      ("1.2.3", "1.2.2"),
+      ("1.2.3", "2.0.0"),
+      ("0.2", "0.3.0"),
+      ("0.0.3", "0.0.4"),
+      ("1.2.3", "1.3.0-rc.1"),
+    ],
+    false,
+  );
+  // Wildcards and hyphen ranges read the same everywhere
+  for ecosystem in [Ecosystem::Npm, Ecosystem::Cargo] {
+    check_as(ecosystem, NPM, &[("1.2.x", "1.2.7"), ("1.2 - 1.4", "1.4.9")], true);
+    check_as(ecosystem, NPM, &[("1.2.x", "1.3.0"), ("1.2 - 1.4", "1.5.0")], false);
+  }
+}
+
+#[test]
+fn highest_match_follows_the_policy() {
+  let published = ["1.0.0", "1.0.1", "1.1.0-rc.1", "2.0.0-beta", "nightly"];
+  let caret = VersionReq::parse("^1.0.0", Ecosystem::Npm).unwrap();
+  assert_eq!(highest_match(published, &caret, NPM), Some("1.0.1"));
+  assert_eq!(
+    highest_match(published, &caret, PrereleasePolicy::Allow),
+    Some("1.1.0-rc.1")
+  );
+  let beta = VersionReq::parse(">=2.0.0-alpha", Ecosystem::Npm).unwrap();
+  assert_eq!(highest_match(published, &beta, NPM), Some("2.0.0-beta"));
+  assert_eq!(highest_match(published, &beta, PrereleasePolicy::Deny), None);
+  // A bare partial version
+  let bare = |ecosystem| VersionReq::parse("1.0", ecosystem).unwrap();
+  assert_eq!(highest_match(published, &bare(Ecosystem::Npm), NPM), Some("1.0.1"));
+  assert_eq!(highest_match(published, &bare(Ecosystem::Cargo), NPM), Some("1.0.1"));
+  assert_eq!(
+    highest_match(published, &bare(Ecosystem::Cargo), PrereleasePolicy::Allow),
+    Some("1.1.0-rc.1")
+  );
+}
+
+// The PEP 440/Poetry engine PyPI, Composer and CocoaPods ranges go through
+#[test]
+fn pep440_highest_matching() {
+  let published = [
+    "0.9.0",
+    "1.0",
+    "1.2.0",
+    "1.2.5",
+    "1.3.0",
+    "1.4.2",
+    "1.9.0",
+    "2.0.0",
+    "2.1.0rc1",
+    "2.1.0.dev3",
+    "2.0.0.post1",
+  ];
+  let highest = |constraint: &str| highest_matching(constraint, published.iter().copied());
+
+  assert_eq!(highest("*").as_deref(), Some("2.0.0"));
+  // Bare, `==` and `===` are exact; trailing zeros don't count
+  assert_eq!(highest("1.2").as_deref(), Some("1.2.0"));
+  assert_eq!(highest("==1.0.0").as_deref(), Some("1.0"));
+  assert_eq!(highest("1.4.*").as_deref(), Some("1.4.2"));
+  assert_eq!(highest("!=2.*").as_deref(), Some("1.9.0"));
+  assert_eq!(highest(">=1,<2").as_deref(), Some("1.9.0"));
+  assert_eq!(highest(">1.2.5, <=1.4.2").as_deref(), Some("1.4.2"));
+  assert_eq!(highest("^1.2").as_deref(), Some("1.9.0"));
+  assert_eq!(highest("^0.9").as_deref(), Some("0.9.0"));
+  assert_eq!(highest("~1.2").as_deref(), Some("1.2.5"));
+  assert_eq!(highest("~=1.2").as_deref(), Some("1.9.0"));
+  assert_eq!(highest("~=1.2.0").as_deref(), Some("1.2.5"));
+  assert_eq!(highest("~1.2 | ~1.4").as_deref(), Some("1.4.2"));
+  // Pre-, post- and dev releases are never picked
+  assert_eq!(highest(">=2.0.0").as_deref(), Some("2.0.0"));
+  assert_eq!(highest(">2.0.0"), None);
+  // Not understood: nothing, rather than a guess
+  assert_eq!(highest("~=1"), None);
+  assert_eq!(highest(">=1 garbage"), None);
+}
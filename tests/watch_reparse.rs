@@ -0,0 +1,120 @@
+use depsfetch::{
+  deps::manifests::{ManifestFilter, ReparseScope},
+  types::{DepScope, DepsEntry, Ecosystem, ReportRow},
+  watch::ParsedManifests,
+};
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap},
+  path::{Path, PathBuf},
+};
+
+fn deps(source: &str, packages: &[(&str, &str)]) -> HashMap<String, DepsEntry> {
+  let mut deps = HashMap::new();
+  for (name, version) in packages {
+    DepsEntry {
+      name: name.to_string(),
+      version: version.to_string(),
+      scope: DepScope::Runtime,
+      sources: BTreeSet::from([source.to_owned()]),
+    }
+    .insert_into(&mut deps);
+  }
+  deps
+}
+
+fn settled(source: &str, name: &str) -> (String, ReportRow) {
+  let row = serde_json::from_value(serde_json::json!({
+    "ecosystem": "pypi",
+    "name": name,
+    "version": "0.1.0",
+    "homepage": "",
+    "license": "MIT",
+  }))
+  .unwrap();
+  (source.to_owned(), row)
+}
+
+fn changed(paths: &[&str]) -> ReparseScope {
+  ReparseScope::new(&paths.iter().map(PathBuf::from).collect())
+}
+
+fn versions(deps: &HashMap<String, DepsEntry>) -> BTreeMap<&str, (&str, Vec<&str>)> {
+  deps
+    .values()
+    .map(|dep| {
+      let sources = dep.sources.iter().map(String::as_str).collect();
+      (dep.name.as_str(), (dep.version.as_str(), sources))
+    })
+    .collect()
+}
+
+// The packages of the manifests a cycle didn't read are the earlier cycle's, the ones it read replace theirs
+#[test]
+fn unchanged_manifests_keep_their_packages() {
+  let root = Path::new("/repo");
+  let mut parsed = ParsedManifests::default();
+  let mut first = deps("web/package.json", &[("left-pad", "1.3.0")]);
+  for (_, dep) in deps("api/package.json", &[("left-pad", "1.3.0"), ("is-odd", "3.0.1")]) {
+    dep.insert_into(&mut first);
+  }
+  let (all, _) = parsed.merge(Ecosystem::Npm, root, None, first, Vec::new());
+  assert_eq!(all.len(), 2);
+
+  // web bumped left-pad, api wasn't read again
+  let scope = changed(&["/repo/web/package.json"]);
+  let read = deps("web/package.json", &[("left-pad", "1.3.1")]);
+  let (all, _) = parsed.merge(Ecosystem::Npm, root, Some(&scope), read, Vec::new());
+  assert_eq!(
+    versions(&all),
+    BTreeMap::from([
+      ("is-odd", ("3.0.1", vec!["api/package.json"])),
+      ("left-pad", ("1.3.1", vec!["api/package.json", "web/package.json"])),
+    ])
+  );
+
+  // api dropped both, web's left-pad stays
+  let scope = changed(&["/repo/api/package.json"]);
+  let (all, _) = parsed.merge(Ecosystem::Npm, root, Some(&scope), HashMap::new(), Vec::new());
+  assert_eq!(
+    versions(&all),
+    BTreeMap::from([("left-pad", ("1.3.1", vec!["web/package.json"]))])
+  );
+}
+
+#[test]
+fn settled_rows_follow_their_manifest() {
+  let root = Path::new("/repo");
+  let mut parsed = ParsedManifests::default();
+  let first = vec![
+    settled("svc/requirements.txt", "internal-lib"),
+    settled("tools/requirements.txt", "internal-lib"),
+    settled("tools/requirements.txt", "helper"),
+  ];
+  let (_, rows) = parsed.merge(Ecosystem::Pypi, root, None, HashMap::new(), first);
+  let names = rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>();
+  assert_eq!(names, ["helper", "internal-lib"]);
+
+  // tools no longer references either, svc still settles internal-lib
+  let scope = changed(&["/repo/tools/requirements.txt"]);
+  let (_, rows) = parsed.merge(Ecosystem::Pypi, root, Some(&scope), HashMap::new(), Vec::new());
+  let names = rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>();
+  assert_eq!(names, ["internal-lib"]);
+}
+
+// Next to, below and above a changed file are read again, siblings aren't, and lifting the scope reads everything
+#[test]
+fn reparse_scope_limits_the_manifests_read() {
+  let manifests = ManifestFilter::new(&BTreeMap::new(), &None).unwrap();
+  manifests.set_reparse_scope(Some(changed(&["/repo/web/package-lock.json"])));
+  for (path, allowed) in [
+    ("/repo/web/package.json", true),
+    ("/repo/web/nested/package.json", true),
+    ("/repo/package.json", true),
+    ("/repo/api/package.json", false),
+  ] {
+    assert_eq!(manifests.allows("package.json", Path::new(path)), allowed, "{}", path);
+  }
+
+  manifests.set_reparse_scope(None);
+  assert!(manifests.allows("package.json", Path::new("/repo/api/package.json")));
+}
@@ -0,0 +1,408 @@
+#![cfg(feature = "xlsx")]
+
+mod common;
+
+use common::{
+  depsfetch, report_row, scratch_dir,
+  workbook::{read_workbook, Sheet},
+  write, MockServer, Response,
+};
+use depsfetch::{
+  report::{ReportWriter, ResolvedRow, Section, WriterMessage},
+  types::{Ecosystem, ReportRow},
+};
+use serde_json::json;
+use std::{fs, path::Path};
+
+// A scan of `count` npm packages, each with a homepage of its own and no repository, so nothing goes to GitHub.
+// Returns the workbook's sheets and what the run printed.
+fn scan(name: &str, count: usize, extra: &[&str]) -> (Vec<Sheet>, String) {
+  let server = MockServer::start(|request| {
+    let name = request
+      .path
+      .trim_start_matches('/')
+      .split('/')
+      .next()
+      .unwrap_or_default()
+      .to_owned();
+    let homepage = format!("https://example.com/{}", name);
+    Response::json(json!({"name": name, "version": "1.0.0", "license": "MIT", "homepage": homepage}).to_string())
+  });
+  let base = scratch_dir(name);
+  let dependencies = (0..count)
+    .map(|idx| (format!("pkg-{:02}", idx), json!("1.0.0")))
+    .collect::<serde_json::Map<_, _>>();
+  write(
+    &base.join("app/package.json"),
+    &json!({ "dependencies": dependencies }).to_string(),
+  );
+  let mut args = vec!["app", "--npm-registry", &server.base, "--final-retry-window", "0"];
+  args.extend(extra);
+  let output = depsfetch(&base, &args, &[]);
+  let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+  assert!(
+    output.status.success(),
+    "{}{}",
+    stdout,
+    String::from_utf8_lossy(&output.stderr)
+  );
+
+  let sheets = read_workbook(&base.join("deps_report.xlsx"));
+  let _ = fs::remove_dir_all(&base);
+  (sheets, stdout)
+}
+
+fn sheet<'a>(sheets: &'a [Sheet], name: &str) -> &'a Sheet {
+  sheets.iter().find(|sheet| sheet.name == name).unwrap_or_else(|| {
+    panic!(
+      "No sheet {} in {:?}",
+      name,
+      sheets.iter().map(|sheet| &sheet.name).collect::<Vec<_>>()
+    )
+  })
+}
+
+fn homepages(sheet: &Sheet) -> Vec<&str> {
+  sheet.body().iter().map(|row| row[2].as_str()).collect()
+}
+
+#[test]
+fn homepages_are_hyperlinks() {
+  let (sheets, _) = scan("workbook-links", 3, &[]);
+  let npm = sheet(&sheets, "Web");
+  assert_eq!(npm.hyperlinks, ["C2", "C3", "C4"]);
+  assert_eq!(
+    homepages(npm),
+    [
+      "https://example.com/pkg-00",
+      "https://example.com/pkg-01",
+      "https://example.com/pkg-02"
+    ]
+  );
+
+  let (sheets, _) = scan("workbook-no-links", 3, &["--no-hyperlink-format"]);
+  let npm = sheet(&sheets, "Web");
+  assert!(npm.hyperlinks.is_empty());
+  assert_eq!(homepages(npm).len(), 3);
+}
+
+// Past the limit, URLs are still on the sheet as text, and the switch is logged once
+#[test]
+fn urls_past_the_link_limit_are_plain_text() {
+  let (sheets, stdout) = scan("workbook-link-limit", 5, &["--link-limit", "3"]);
+  let npm = sheet(&sheets, "Web");
+  assert_eq!(npm.hyperlinks, ["C2", "C3", "C4"]);
+  assert_eq!(homepages(npm).len(), 5);
+  assert_eq!(npm.cell(5, 2), "https://example.com/pkg-04");
+  assert_eq!(
+    stdout
+      .matches("Sheet Web reached 3 hyperlinks, the rest of its URLs are written as plain text")
+      .count(),
+    1,
+    "{}",
+    stdout
+  );
+  assert!(!stdout.contains("Write errors"), "{}", stdout);
+}
+
+#[test]
+fn split_on_link_limit_continues_the_sheet() {
+  let (sheets, stdout) = scan(
+    "workbook-link-split",
+    7,
+    &["--link-limit", "3", "--split-on-link-limit"],
+  );
+  let parts = [
+    sheet(&sheets, "Web"),
+    sheet(&sheets, "Web (2)"),
+    sheet(&sheets, "Web (3)"),
+  ];
+  assert_eq!(parts.map(|part| homepages(part).len()), [3, 3, 1]);
+  for part in parts {
+    assert_eq!(part.hyperlinks.len(), homepages(part).len());
+    assert_eq!(part.rows[0], sheet(&sheets, "Web").rows[0]);
+  }
+  assert_eq!(parts[2].cell(1, 0), "pkg-06");
+  assert!(
+    stdout.contains("Sheet Web reached 3 hyperlinks, continued on Web (2)"),
+    "{}",
+    stdout
+  );
+  assert!(!stdout.contains("plain text"), "{}", stdout);
+  assert!(!stdout.contains("Write errors"), "{}", stdout);
+}
+
+// Control characters are dropped from what reaches the sheet, tab and line breaks aside, and a URL too long for a
+// hyperlink is kept whole as text; neither costs a cell
+#[test]
+fn control_characters_and_long_urls_are_written() {
+  let controls = (0u8..0x20).map(char::from).collect::<String>();
+  let long_url = format!("https://example.com/{}", "a".repeat(10_000 - 20));
+  let homepages = [
+    ("ctl", format!("https://example.com/{}ctl", controls)),
+    ("long", long_url.clone()),
+    ("plain", "https://example.com/plain".to_owned()),
+  ];
+  let served = homepages.clone();
+  let server = MockServer::start(move |request| {
+    let name = request
+      .path
+      .trim_start_matches('/')
+      .split('/')
+      .next()
+      .unwrap_or_default();
+    match served.iter().find(|(known, _)| *known == name) {
+      Some((_, homepage)) => {
+        Response::json(json!({"name": name, "version": "1.0.0", "license": "MIT", "homepage": homepage}).to_string())
+      },
+      None => Response::status(404),
+    }
+  });
+  let base = scratch_dir("workbook-write-errors");
+  write(
+    &base.join("app/package.json"),
+    &json!({"dependencies": {"ctl": "1.0.0", "long": "1.0.0", "plain": "1.0.0"}}).to_string(),
+  );
+  let output = depsfetch(
+    &base,
+    &["app", "--npm-registry", &server.base, "--final-retry-window", "0"],
+    &[],
+  );
+  let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+  assert!(
+    output.status.success(),
+    "{}{}",
+    stdout,
+    String::from_utf8_lossy(&output.stderr)
+  );
+  assert!(!stdout.contains("Write errors"), "{}", stdout);
+
+  let sheets = read_workbook(&base.join("deps_report.xlsx"));
+  let npm = sheet(&sheets, "Web");
+  let names = npm.body().iter().map(|row| row[0].as_str()).collect::<Vec<_>>();
+  assert_eq!(names, ["ctl", "long", "plain"]);
+  assert_eq!(npm.cell(1, 2), "https://example.com/\t\n\rctl");
+  assert_eq!(npm.cell(2, 2), long_url);
+  assert_eq!(npm.cell(2, 2).len(), 10_000);
+  assert_eq!(npm.cell(3, 2), "https://example.com/plain");
+  // Only the URL a link can hold is one
+  assert_eq!(npm.hyperlinks, ["C4"]);
+  assert!(!sheets
+    .iter()
+    .flat_map(|sheet| &sheet.rows)
+    .flatten()
+    .any(|cell| cell.contains("<write error>")));
+  let _ = fs::remove_dir_all(&base);
+}
+
+// Web and Backend rows of a small tree, in the order a scan would resolve them
+fn fixture_rows() -> Vec<(&'static str, ReportRow)> {
+  vec![
+    ("Web", report_row(Ecosystem::Npm, "react", "18.2.0", Some("MIT"))),
+    ("Web", report_row(Ecosystem::Npm, "left-pad", "1.3.0", Some("WTFPL"))),
+    (
+      "Backend",
+      report_row(Ecosystem::Go, "golang.org/x/text", "v0.14.0", Some("BSD-3-Clause")),
+    ),
+    ("Web", report_row(Ecosystem::Npm, "chalk", "5.3.0", Some("MIT"))),
+    ("Web", report_row(Ecosystem::Npm, "internal-utils", "0.1.0", None)),
+    (
+      "Backend",
+      report_row(Ecosystem::Go, "github.com/pkg/errors", "v0.9.1", Some("BSD-2-Clause")),
+    ),
+    (
+      "Web",
+      report_row(Ecosystem::Npm, "readline-sync", "1.4.10", Some("GPL-3.0-only")),
+    ),
+    (
+      "Backend",
+      report_row(
+        Ecosystem::Go,
+        "github.com/hashicorp/vault/api",
+        "v1.12.0",
+        Some("MPL-2.0"),
+      ),
+    ),
+    ("Web", report_row(Ecosystem::Npm, "react-dom", "18.2.0", Some("MIT"))),
+  ]
+}
+
+// `rows` straight through the writer thread in the order given, then `sections`; returns the sheets read back
+async fn write_rows(
+  dir: &Path,
+  name: &str,
+  rows: Vec<(&'static str, ReportRow)>,
+  sections: Vec<Section>,
+) -> Vec<Sheet> {
+  let path = dir.join(name);
+  let (sender, handle) = ReportWriter::new(&path.display().to_string()).unwrap().spawn();
+  for sheet in ["Web", "Backend"] {
+    sender
+      .send(WriterMessage::Section(Section::Dependencies(sheet)))
+      .unwrap();
+  }
+  for (sheet, row) in rows {
+    sender
+      .send(WriterMessage::Row(Box::new(ResolvedRow { sheet, row })))
+      .unwrap();
+  }
+  for section in sections {
+    sender.send(WriterMessage::Section(section)).unwrap();
+  }
+  sender.send(WriterMessage::Finish).unwrap();
+  handle.await.unwrap().unwrap();
+  read_workbook(&path)
+}
+
+// Rows land in arrival order and are sorted once at the end, so however resolution interleaves them the workbook
+// comes out the same
+#[tokio::test]
+async fn arrival_order_does_not_change_the_workbook() {
+  let dir = scratch_dir("workbook-arrival-order");
+  let rows = fixture_rows();
+  let mut reversed = rows.clone();
+  reversed.reverse();
+  let mut rotated = rows.clone();
+  rotated.rotate_left(4);
+
+  let sheets = write_rows(&dir, "in-order.xlsx", rows, vec![Section::Summary("Summary")]).await;
+  for (name, rows) in [("reversed.xlsx", reversed), ("rotated.xlsx", rotated)] {
+    let other = write_rows(&dir, name, rows, vec![Section::Summary("Summary")]).await;
+    assert_eq!(other.len(), sheets.len());
+    for (sheet, other) in sheets.iter().zip(&other) {
+      assert_eq!(
+        (&sheet.name, &sheet.rows, &sheet.hyperlinks),
+        (&other.name, &other.rows, &other.hyperlinks)
+      );
+    }
+  }
+
+  let columns = |sheet: &Sheet| {
+    sheet
+      .body()
+      .iter()
+      .map(|row| [0, 1, 3, 4].map(|col| row.get(col).cloned().unwrap_or_default()))
+      .collect::<Vec<_>>()
+  };
+  // What was sent, by name and version
+  for name in ["Web", "Backend"] {
+    let mut sent = fixture_rows()
+      .into_iter()
+      .filter(|(sheet, _)| *sheet == name)
+      .map(|(_, row)| row)
+      .collect::<Vec<_>>();
+    sent.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    let expected = sent
+      .iter()
+      .map(|row| {
+        [
+          row.name.clone(),
+          row.version.clone(),
+          row.license.clone().unwrap_or_default(),
+          row.license_url.clone().unwrap_or_default(),
+        ]
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(columns(sheet(&sheets, name)), expected);
+  }
+  let _ = fs::remove_dir_all(&dir);
+}
+
+// The By license sheet read back as (section title, its package rows)
+fn license_sections(sheet: &Sheet) -> Vec<(String, Vec<Vec<String>>)> {
+  let mut sections: Vec<(String, Vec<Vec<String>>)> = Vec::new();
+  for row in sheet.body() {
+    match (row.len(), sections.last_mut()) {
+      (1, _) => sections.push((row[0].clone(), Vec::new())),
+      (_, Some((_, packages))) => packages.push(row.clone()),
+      (_, None) => panic!("Package row {:?} before any section", row),
+    }
+  }
+  sections
+}
+
+// Each package of the main sheets once, under a section whose count matches its rows
+#[tokio::test]
+async fn by_license_sections_match_the_main_sheets() {
+  let dir = scratch_dir("workbook-by-license");
+  let mut rows = fixture_rows();
+  // Listed on both sheets, counted once
+  rows.push(("Backend", report_row(Ecosystem::Npm, "react", "18.2.0", Some("MIT"))));
+
+  let expected_titles: [&[&str]; 2] = [
+    &[
+      "GPL-3.0-only (copyleft, 1 пакетов)",
+      "MPL-2.0 (copyleft, 1 пакетов)",
+      "Без лицензии (unknown, 1 пакетов)",
+      "BSD-2-Clause (permissive, 1 пакетов)",
+      "BSD-3-Clause (permissive, 1 пакетов)",
+      "MIT (permissive, 3 пакетов)",
+      "WTFPL (permissive, 1 пакетов)",
+    ],
+    &[
+      "MIT (permissive, 3 пакетов)",
+      "Прочие лицензии, пакетов на лицензию не больше 1 (6 пакетов)",
+    ],
+  ];
+  for (collapse, expected_titles) in [0, 1].into_iter().zip(expected_titles) {
+    let sheets = write_rows(
+      &dir,
+      &format!("by-license-{}.xlsx", collapse),
+      rows.clone(),
+      vec![Section::ByLicense("By license", collapse)],
+    )
+    .await;
+    let sections = license_sections(sheet(&sheets, "By license"));
+    let titles = sections.iter().map(|(title, _)| title.as_str()).collect::<Vec<_>>();
+    assert_eq!(titles, expected_titles);
+
+    for (title, packages) in &sections {
+      let count = title.trim_end_matches(" пакетов)").rsplit([' ', '(']).next().unwrap();
+      assert_eq!(
+        count.parse::<usize>().unwrap(),
+        packages.len(),
+        "{}: {:?}",
+        title,
+        packages
+      );
+    }
+    let mut listed = sections
+      .iter()
+      .flat_map(|(_, packages)| packages.iter().map(|package| (package[2].clone(), package[3].clone())))
+      .collect::<Vec<_>>();
+    listed.sort();
+    let mut on_sheets = ["Web", "Backend"]
+      .iter()
+      .flat_map(|name| {
+        sheet(&sheets, name)
+          .body()
+          .iter()
+          .map(|row| (row[0].clone(), row[1].clone()))
+      })
+      .collect::<Vec<_>>();
+    on_sheets.sort();
+    on_sheets.dedup();
+    assert_eq!(listed, on_sheets);
+
+    // Packages of the collapsed tail show their own license
+    if collapse == 1 {
+      let licenses = sections[1]
+        .1
+        .iter()
+        .map(|package| package[0].as_str())
+        .collect::<Vec<_>>();
+      assert_eq!(
+        licenses,
+        [
+          "Без лицензии",
+          "BSD-2-Clause",
+          "BSD-3-Clause",
+          "GPL-3.0-only",
+          "MPL-2.0",
+          "WTFPL"
+        ]
+      );
+    }
+  }
+  let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use common::{depsfetch, files, scratch_dir, MockServer, Response};
+use depsfetch::{deps::yarn_berry::YarnCache, diagnostics::Diagnostics};
+use serde_json::{json, Value};
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+// tests/fixtures/yarn_berry: a PnP project with left-pad and @acme/tiny in its cache, and a cache zip for broken
+// that was cut short
+fn fixture() -> PathBuf {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/yarn_berry")
+}
+
+fn copy_tree(from: &Path, to: &Path) {
+  for file in files(from) {
+    let target = to.join(file.strip_prefix(from).unwrap());
+    fs::create_dir_all(target.parent().unwrap()).unwrap();
+    fs::copy(&file, &target).unwrap();
+  }
+}
+
+#[test]
+fn cache_zips_are_read_in_place() {
+  let cache = YarnCache::load(&fixture(), &Diagnostics::quiet()).unwrap();
+
+  let left_pad = cache.lookup("left-pad", "1.3.0").unwrap();
+  assert_eq!(left_pad.zip, "left-pad-npm-1.3.0-3d7e3c7a12-8.zip");
+  assert_eq!(left_pad.license_entry.as_deref(), Some("node_modules/left-pad/LICENSE"));
+  assert_eq!(left_pad.notices_file, None);
+  let info = left_pad.package_info();
+  assert_eq!((info.name.as_str(), info.version.as_str()), ("left-pad", "1.3.0"));
+  assert_eq!(info.license, "WTFPL");
+  assert_eq!(info.repository.url, "git+https://github.com/stevemao/left-pad.git");
+
+  // Scoped, with the license in its object form
+  let tiny = cache.lookup("@acme/tiny", "2.0.0").unwrap();
+  assert_eq!(
+    tiny.license_entry.as_deref(),
+    Some("node_modules/@acme/tiny/LICENSE.md")
+  );
+  assert_eq!(tiny.notices_file.as_deref(), Some("NOTICE"));
+  assert_eq!(tiny.package_info().license, "MIT");
+  assert_eq!(tiny.package_info().homepage, "https://github.com/acme/tiny");
+
+  // Locked, but its zip doesn't open
+  assert!(cache.lookup("broken", "1.0.0").is_none());
+  // Nothing was unpacked next to the zips
+  assert_eq!(files(&fixture().join(".yarn/cache")).len(), 3);
+}
+
+// A scan of the project goes to the registry only for the package its cache couldn't supply
+#[test]
+fn cached_packages_resolve_without_the_registry() {
+  let server = MockServer::start(|request| match request.path.as_str() {
+    "/broken/1.0.0" => Response::json(json!({"name": "broken", "version": "1.0.0", "license": "ISC"}).to_string()),
+    _ => Response::status(404),
+  });
+  let base = scratch_dir("yarn-berry-offline");
+  copy_tree(&fixture(), &base.join("app"));
+  let args = [
+    "app",
+    "--format",
+    "json",
+    "--npm-registry",
+    &server.base,
+    "--final-retry-window",
+    "0",
+  ];
+  let output = depsfetch(&base, &args, &[]);
+  let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+  assert!(
+    output.status.success(),
+    "{}{}",
+    stdout,
+    String::from_utf8_lossy(&output.stderr)
+  );
+
+  let rows = fs::read_to_string(base.join("deps_report.jsonl"))
+    .unwrap()
+    .lines()
+    .map(|line| serde_json::from_str::<Value>(line).unwrap())
+    .map(|row| (row["name"].as_str().unwrap().to_owned(), row))
+    .collect::<Vec<_>>();
+  let row = |name: &str| &rows.iter().find(|(row_name, _)| row_name == name).unwrap().1;
+  assert_eq!(rows.len(), 3, "{:?}", rows);
+
+  assert_eq!(row("left-pad")["license"], "WTFPL");
+  assert_eq!(
+    row("left-pad")["provenance"],
+    json!([
+      "metadata: yarn cache left-pad-npm-1.3.0-3d7e3c7a12-8.zip",
+      "license file: yarn cache left-pad-npm-1.3.0-3d7e3c7a12-8.zip:node_modules/left-pad/LICENSE",
+    ])
+  );
+  assert_eq!(row("@acme/tiny")["license"], "MIT");
+  assert_eq!(row("broken")["license"], "ISC");
+  let requested = server
+    .requests()
+    .into_iter()
+    .map(|request| request.path)
+    .collect::<Vec<_>>();
+  assert_eq!(requested, ["/broken/1.0.0"]);
+  assert!(
+    stdout.contains("Can't read yarn cache entry") && stdout.contains("broken-npm-1.0.0-0a0b0c0d0e-8.zip"),
+    "{}",
+    stdout
+  );
+  let _ = fs::remove_dir_all(&base);
+}